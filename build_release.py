@@ -40,6 +40,11 @@ INCLUDE_DIRS = [
     "docs",
 ]
 
+# Необязательные директории (setup/download_fonts.py)
+OPTIONAL_DIRS = [
+    "fonts",
+]
+
 # Отдельные файлы из корня
 INCLUDE_FILES = [
     "start_superset.bat",
@@ -57,6 +62,7 @@ INCLUDE_EXTRA = [
     "setup/generate_demo_data.py",
     "setup/install_superset.bat",
     "setup/download_python.ps1",
+    "setup/download_fonts.py",
     "target/release/create_dashboard.exe",
     "target/release/superset-launcher.exe",
     "docs/HOWTO_UPDATE_DATA.md",
@@ -163,7 +169,8 @@ def main():
     files_to_pack = []  # (source_path, archive_path)
 
     # Директории
-    for dir_name in INCLUDE_DIRS:
+    optional = [d for d in OPTIONAL_DIRS if (ROOT_DIR / d).exists()]
+    for dir_name in INCLUDE_DIRS + optional:
         dir_path = ROOT_DIR / dir_name
         for root, dirs, files in os.walk(dir_path):
             # Filter out excluded directories in-place
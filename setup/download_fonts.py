@@ -0,0 +1,58 @@
+#!/usr/bin/env python
+# -*- coding: utf-8 -*-
+"""
+Скачать шрифт PT Sans (SIL Open Font License) для базы знаний LightDocs.
+
+Запускать на машине с интернетом перед сборкой релиза:
+    python setup/download_fonts.py
+
+Файлы кладутся в fonts/pt-sans/ по блокам Unicode (latin, cyrillic, ...);
+при сборке сайта LightDocs копирует только нужные блоки.
+"""
+
+import sys
+import urllib.request
+from pathlib import Path
+
+ROOT_DIR = Path(__file__).resolve().parent.parent
+FONT_DIR = ROOT_DIR / "fonts" / "pt-sans"
+
+BASE_URL = "https://cdn.jsdelivr.net/fontsource/fonts/pt-sans@latest"
+LICENSE_URL = "https://raw.githubusercontent.com/google/fonts/main/ofl/ptsans/OFL.txt"
+
+# Должны совпадать с BLOCKS и FACES в src/lightdocs/fonts.rs
+BLOCKS = ["latin", "latin-ext", "cyrillic", "cyrillic-ext"]
+FACES = [("400", "normal"), ("700", "normal"), ("400", "italic"), ("700", "italic")]
+
+
+def download(url, target):
+    print(f"  {target.name} <- {url}")
+    with urllib.request.urlopen(url, timeout=60) as response:
+        target.write_bytes(response.read())
+
+
+def main():
+    FONT_DIR.mkdir(parents=True, exist_ok=True)
+    failed = []
+    for block in BLOCKS:
+        for weight, style in FACES:
+            name = f"{block}-{weight}-{style}.woff2"
+            try:
+                download(f"{BASE_URL}/{name}", FONT_DIR / name)
+            except Exception as e:
+                failed.append(f"{name}: {e}")
+    try:
+        download(LICENSE_URL, FONT_DIR / "OFL.txt")
+    except Exception as e:
+        failed.append(f"OFL.txt: {e}")
+
+    if failed:
+        print("\n[ERROR] Не удалось скачать:")
+        for f in failed:
+            print(f"    {f}")
+        sys.exit(1)
+    print(f"\n[OK] Шрифты в {FONT_DIR}")
+
+
+if __name__ == "__main__":
+    main()
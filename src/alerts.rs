@@ -0,0 +1,359 @@
+//! Lightweight alerts: SQL threshold checks with desktop notifications
+//!
+//! Superset's own Alerts & Reports need Celery and Redis, which the portable
+//! kit doesn't ship (`ALERT_REPORTS` is off). Instead, `alerts.yaml` in the
+//! kit root lists SQL queries returning one number and a threshold:
+//!
+//! ```yaml
+//! alerts:
+//!   - name: Пунктуальность ниже цели
+//!     sql: SELECT on_time_pct FROM rzd_monthly_stats ORDER BY year DESC, month DESC LIMIT 1
+//!     op: "<"
+//!     threshold: 95
+//!     every_minutes: 60
+//! ```
+//!
+//! The launcher runs each check on its schedule against examples.db (or the
+//! `database` named in the rule), appends the result to `logs/alerts.log`,
+//! keeps the latest state in `logs/alerts.json` for the launcher UI and
+//! raises a notification when a check starts failing.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local};
+use rusqlite::{types::ValueRef, Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{error, info};
+
+use crate::databases::{self, DatabaseConfig};
+use crate::notifications::{self, Level};
+
+/// Alert rules file (relative to root)
+pub const ALERTS_FILE: &str = "alerts.yaml";
+
+/// Latest result of every rule, read by the launcher UI (relative to root)
+const STATE_FILE: &str = "logs/alerts.json";
+
+/// Append-only history of checks (relative to root)
+const LOG_FILE: &str = "logs/alerts.log";
+
+/// How often the scheduler looks for due checks
+const TICK: Duration = Duration::from_secs(60);
+
+const EXAMPLE_ALERTS: &str = r#"# Проверки выполняются лаунчером по расписанию.
+# sql должен вернуть одно число; при выполнении условия — уведомление.
+alerts:
+  - name: Пунктуальность ниже цели
+    sql: SELECT on_time_pct FROM rzd_monthly_stats ORDER BY year DESC, month DESC LIMIT 1
+    op: "<"
+    threshold: 95
+    every_minutes: 60
+    # database: finance   # логическое имя из config.json (по умолчанию examples)
+    # severity: error     # warning (по умолчанию) или error
+"#;
+
+/// Comparison between the query result and the threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Op {
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = ">=")]
+    Ge,
+    #[serde(rename = "<")]
+    Lt,
+    #[serde(rename = "<=")]
+    Le,
+    #[serde(rename = "==")]
+    Eq,
+    #[serde(rename = "!=")]
+    Ne,
+}
+
+impl Op {
+    fn holds(self, value: f64, threshold: f64) -> bool {
+        match self {
+            Op::Gt => value > threshold,
+            Op::Ge => value >= threshold,
+            Op::Lt => value < threshold,
+            Op::Le => value <= threshold,
+            Op::Eq => value == threshold,
+            Op::Ne => value != threshold,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Eq => "==",
+            Op::Ne => "!=",
+        }
+    }
+}
+
+/// How loudly a triggered alert is raised
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Warning,
+    Error,
+}
+
+/// One threshold check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub name: String,
+    /// Query returning a single number (first column of the first row)
+    pub sql: String,
+    /// Logical database name from config.json (default: examples)
+    #[serde(default)]
+    pub database: Option<String>,
+    pub op: Op,
+    pub threshold: f64,
+    #[serde(default = "default_every_minutes")]
+    pub every_minutes: u64,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+fn default_every_minutes() -> u64 {
+    15
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AlertsFile {
+    #[serde(default)]
+    alerts: Vec<AlertRule>,
+}
+
+/// Outcome of one check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertResult {
+    pub name: String,
+    pub checked_at: DateTime<Local>,
+    pub value: Option<f64>,
+    /// Condition: e.g. `> 15`
+    pub condition: String,
+    pub triggered: bool,
+    pub error: Option<String>,
+}
+
+/// Rules from alerts.yaml (none if the file doesn't exist)
+pub fn load_rules(root: &Path) -> Result<Vec<AlertRule>> {
+    let path = root.join(ALERTS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let file: AlertsFile = serde_yaml::from_str(&std::fs::read_to_string(&path)?)
+        .with_context(|| format!("Invalid {}", path.display()))?;
+    Ok(file.alerts)
+}
+
+/// Write an example alerts.yaml; fails if one exists
+pub fn init(root: &Path) -> Result<PathBuf> {
+    let path = root.join(ALERTS_FILE);
+    if path.exists() {
+        bail!("{} already exists", path.display());
+    }
+    std::fs::write(&path, EXAMPLE_ALERTS)?;
+    Ok(path)
+}
+
+fn query_value(db_path: &Path, sql: &str) -> Result<Option<f64>> {
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open {}", db_path.display()))?;
+    let value = conn.query_row(sql, [], |row| {
+        Ok(match row.get_ref(0)? {
+            ValueRef::Integer(i) => Some(i as f64),
+            ValueRef::Real(f) => Some(f),
+            ValueRef::Text(t) => std::str::from_utf8(t).ok().and_then(|s| s.trim().parse().ok()),
+            _ => None,
+        })
+    })?;
+    Ok(value)
+}
+
+/// Run one rule now
+pub fn check(root: &Path, databases: &[DatabaseConfig], rule: &AlertRule) -> AlertResult {
+    let name = rule.database.as_deref().unwrap_or(databases::EXAMPLES_NAME);
+    let value = databases::resolve(root, databases, name).and_then(|path| query_value(&path, &rule.sql));
+    let (value, error) = match value {
+        Ok(value) => (value, None),
+        Err(e) => (None, Some(format!("{:#}", e))),
+    };
+    AlertResult {
+        name: rule.name.clone(),
+        checked_at: Local::now(),
+        value,
+        condition: format!("{} {}", rule.op.symbol(), rule.threshold),
+        triggered: value.map_or(false, |v| rule.op.holds(v, rule.threshold)),
+        error,
+    }
+}
+
+/// Run every rule now, record the results and return them
+pub fn check_all(root: &Path, databases: &[DatabaseConfig]) -> Result<Vec<AlertResult>> {
+    let results: Vec<AlertResult> = load_rules(root)?.iter().map(|rule| check(root, databases, rule)).collect();
+    for result in &results {
+        record(root, result)?;
+    }
+    Ok(results)
+}
+
+/// Latest results for the launcher UI
+pub fn latest(root: &Path) -> Vec<AlertResult> {
+    std::fs::read(root.join(STATE_FILE))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<HashMap<String, AlertResult>>(&bytes).ok())
+        .map(|state| {
+            let mut results: Vec<AlertResult> = state.into_values().collect();
+            results.sort_by(|a, b| b.triggered.cmp(&a.triggered).then_with(|| a.name.cmp(&b.name)));
+            results
+        })
+        .unwrap_or_default()
+}
+
+/// Append to the history log and update the latest-state file
+fn record(root: &Path, result: &AlertResult) -> Result<()> {
+    std::fs::create_dir_all(root.join("logs"))?;
+
+    let status = match (&result.error, result.triggered) {
+        (Some(_), _) => "ERROR",
+        (None, true) => "ALERT",
+        (None, false) => "OK",
+    };
+    let detail = match (&result.error, result.value) {
+        (Some(e), _) => e.clone(),
+        (None, Some(v)) => format!("value={} ({})", v, result.condition),
+        (None, None) => format!("no value ({})", result.condition),
+    };
+    let mut log = std::fs::OpenOptions::new().create(true).append(true).open(root.join(LOG_FILE))?;
+    writeln!(log, "{}\t{}\t{}\t{}", result.checked_at.format("%Y-%m-%d %H:%M:%S"), status, result.name, detail)?;
+
+    let state_path = root.join(STATE_FILE);
+    let mut state: HashMap<String, AlertResult> = std::fs::read(&state_path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default();
+    state.insert(result.name.clone(), result.clone());
+    // Rules removed from alerts.yaml drop out of the UI
+    let names: Vec<String> = load_rules(root)?.into_iter().map(|r| r.name).collect();
+    state.retain(|name, _| names.contains(name));
+    std::fs::write(&state_path, serde_json::to_vec_pretty(&state)?)?;
+    Ok(())
+}
+
+/// Check due rules every minute for as long as the launcher runs.
+///
+/// A notification is raised when a rule starts failing, not on every check.
+/// A rule with an entry in `schedules.json` runs at the times of its cron
+/// expression instead of every `every_minutes`.
+pub fn spawn_scheduler(root: PathBuf, databases: Vec<DatabaseConfig>) {
+    tokio::spawn(async move {
+        let mut last_run: HashMap<String, std::time::Instant> = HashMap::new();
+        let mut last_tick = Local::now().naive_local();
+        let mut firing: HashMap<String, bool> = HashMap::new();
+        let mut announced = false;
+        loop {
+            if crate::drive_watchdog::is_paused() {
+                tokio::time::sleep(TICK).await;
+                continue;
+            }
+            let rules = match load_rules(&root) {
+                Ok(rules) => rules,
+                Err(e) => {
+                    error!("Alerts not checked: {}", e);
+                    Vec::new()
+                }
+            };
+            if !rules.is_empty() && !announced {
+                info!("🔔 {} alert rule(s) from {}", rules.len(), ALERTS_FILE);
+                announced = true;
+            }
+
+            let schedules = crate::schedules::load(&root);
+            let now = Local::now().naive_local();
+            let since = std::mem::replace(&mut last_tick, now);
+            for rule in rules {
+                let due = match schedules.get(&crate::schedules::alert_job(&rule.name)) {
+                    Some(schedule) => schedule.is_due(since, now),
+                    None => last_run
+                        .get(&rule.name)
+                        .map_or(true, |at| at.elapsed() >= Duration::from_secs(rule.every_minutes.max(1) * 60)),
+                };
+                if !due {
+                    continue;
+                }
+                last_run.insert(rule.name.clone(), std::time::Instant::now());
+
+                let (check_root, check_dbs, check_rule) = (root.clone(), databases.clone(), rule.clone());
+                let result = tokio::task::spawn_blocking(move || {
+                    let result = check(&check_root, &check_dbs, &check_rule);
+                    record(&check_root, &result).map(|_| result)
+                })
+                .await;
+                let result = match result {
+                    Ok(Ok(result)) => result,
+                    Ok(Err(e)) => {
+                        error!("Alert '{}' not recorded: {}", rule.name, e);
+                        continue;
+                    }
+                    Err(e) => {
+                        error!("Alert '{}' task panicked: {}", rule.name, e);
+                        continue;
+                    }
+                };
+
+                let was_firing = firing.insert(rule.name.clone(), result.triggered).unwrap_or(false);
+                if result.triggered && !was_firing {
+                    let level = match rule.severity {
+                        Severity::Warning => Level::Warning,
+                        Severity::Error => Level::Error,
+                    };
+                    let value = result.value.map(|v| format!("{:.2}", v)).unwrap_or_default();
+                    notifications::notify(level, &rule.name, &format!("{} ({})", value, result.condition));
+                }
+            }
+
+            tokio::time::sleep(TICK).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_check_and_record() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        Connection::open(root.join(databases::EXAMPLES_PATH))
+            .unwrap()
+            .execute_batch("CREATE TABLE t (delay REAL); INSERT INTO t VALUES (10), (30);")
+            .unwrap();
+        std::fs::write(
+            root.join(ALERTS_FILE),
+            "alerts:\n  - name: delay\n    sql: SELECT AVG(delay) FROM t\n    op: \">\"\n    threshold: 15\n  - name: broken\n    sql: SELECT nope FROM t\n    op: \"<\"\n    threshold: 1\n",
+        )
+        .unwrap();
+
+        let results = check_all(root, &[]).unwrap();
+        assert_eq!(results[0].value, Some(20.0));
+        assert!(results[0].triggered);
+        assert!(results[1].error.is_some() && !results[1].triggered);
+
+        let latest = latest(root);
+        assert_eq!(latest.len(), 2);
+        assert_eq!(latest[0].name, "delay");
+        let log = std::fs::read_to_string(root.join(LOG_FILE)).unwrap();
+        assert!(log.contains("ALERT\tdelay"));
+    }
+}
@@ -0,0 +1,145 @@
+//! Browser caching of the gateway's static files
+//!
+//! Superset's webpack build puts a content hash in every bundle name
+//! (`spa.3f9c0a1b2d4e5f60.entry.js`), so such a file never changes under its
+//! name and the browser may keep it for a year without asking again
+//! (`immutable`). Files without a hash (`manifest.json`, docs pages) get
+//! `no-cache`: the browser keeps them but revalidates with `If-Modified-Since`
+//! and gets a short 304. Thin clients then reload a dashboard without
+//! refetching megabytes of JS. Rules are matched by path prefix
+//! (`gateway.cache_policy` in config.json), the longest prefix wins.
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// One year, the longest max-age browsers honour
+pub const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// Keep, but check with the server before every use
+pub const REVALIDATE: &str = "no-cache";
+
+/// Shortest run of hex digits taken for a content hash
+const MIN_HASH_LEN: usize = 8;
+
+/// Cache-Control for the paths under `prefix`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheRule {
+    /// Request path prefix, e.g. `/static/assets/`
+    pub prefix: String,
+    /// Header for file names with a content hash (empty: leave the header out)
+    pub hashed: String,
+    /// Header for all other files under the prefix
+    pub other: String,
+}
+
+impl Default for CacheRule {
+    fn default() -> Self {
+        Self {
+            prefix: "/".to_string(),
+            hashed: IMMUTABLE.to_string(),
+            other: REVALIDATE.to_string(),
+        }
+    }
+}
+
+/// Static file caching settings (`gateway.cache_policy` in config.json)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CachePolicyConfig {
+    pub enabled: bool,
+    pub rules: Vec<CacheRule>,
+}
+
+impl Default for CachePolicyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rules: vec![
+                CacheRule { prefix: "/static/assets/".to_string(), ..CacheRule::default() },
+                CacheRule { prefix: "/docs/".to_string(), ..CacheRule::default() },
+            ],
+        }
+    }
+}
+
+impl CachePolicyConfig {
+    /// Cache-Control for `path`, if a rule covers it
+    pub fn header_for(&self, path: &str) -> Option<&str> {
+        if !self.enabled {
+            return None;
+        }
+        let rule = self
+            .rules
+            .iter()
+            .filter(|rule| path.starts_with(&rule.prefix))
+            .max_by_key(|rule| rule.prefix.len())?;
+        let value = if is_hashed(path) { &rule.hashed } else { &rule.other };
+        Some(value.as_str()).filter(|v| !v.is_empty())
+    }
+}
+
+/// Whether the file name carries a content hash (a dot- or dash-separated
+/// run of at least 8 hex digits with a letter in it, so dates don't count)
+pub fn is_hashed(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    stem.split(['.', '-', '_']).any(|part| {
+        part.len() >= MIN_HASH_LEN
+            && part.chars().all(|c| c.is_ascii_hexdigit())
+            && part.chars().any(|c| c.is_ascii_alphabetic())
+    })
+}
+
+/// Set Cache-Control on successful static responses
+pub async fn middleware(State(policy): State<Arc<CachePolicyConfig>>, req: Request, next: Next) -> Response {
+    let value = policy.header_for(req.uri().path()).and_then(|v| HeaderValue::from_str(v).ok());
+    let mut response = next.run(req).await;
+    let cacheable = response.status() == StatusCode::OK || response.status() == StatusCode::NOT_MODIFIED;
+    if let (Some(value), true) = (value, cacheable) {
+        response.headers_mut().insert(header::CACHE_CONTROL, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashed_names() {
+        assert!(is_hashed("/static/assets/spa.3f9c0a1b2d4e5f60.entry.js"));
+        assert!(is_hashed("/static/assets/8721.a1b2c3d4e5f60718.chunk.js"));
+        assert!(is_hashed("vendors-deadbeef01.css"));
+        assert!(!is_hashed("/static/assets/manifest.json"));
+        assert!(!is_hashed("/static/assets/images/superset-logo-horiz.png"));
+        assert!(!is_hashed("/docs/report-20240115.html"));
+        assert!(!is_hashed("/static/assets/3f9c0a1b2d4e5f60/index.html"));
+    }
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        let mut policy = CachePolicyConfig::default();
+        policy.rules.push(CacheRule {
+            prefix: "/static/assets/images/".to_string(),
+            hashed: String::new(),
+            other: "public, max-age=86400".to_string(),
+        });
+
+        assert_eq!(policy.header_for("/static/assets/spa.3f9c0a1b2d4e5f60.entry.js"), Some(IMMUTABLE));
+        assert_eq!(policy.header_for("/static/assets/manifest.json"), Some(REVALIDATE));
+        assert_eq!(policy.header_for("/static/assets/images/logo.png"), Some("public, max-age=86400"));
+        assert_eq!(policy.header_for("/static/assets/images/logo.0123abcdef.png"), None);
+        assert_eq!(policy.header_for("/docs/index.html"), Some(REVALIDATE));
+        assert_eq!(policy.header_for("/superset/welcome/"), None);
+
+        policy.enabled = false;
+        assert_eq!(policy.header_for("/static/assets/manifest.json"), None);
+    }
+}
@@ -0,0 +1,408 @@
+//! Audit log of launcher actions and its signed export
+//!
+//! Every action taken through the launcher — token-protected calls to the
+//! panel API (refused ones included) and `ctl` commands — is appended to
+//! `logs/audit.jsonl` with its time, who asked (client address or `ctl`) and
+//! how it ended. Each line also carries the SHA-256 of its content with the
+//! previous line's hash, so an edited or dropped line in the log itself is
+//! found when it is read for an export. Lines written before the log was
+//! chained have neither field and are accepted only ahead of the chain.
+//!
+//! `audit export --from --to` writes a date range of it for hand-off as a
+//! hash chain: every line carries the SHA-256 of its content together with
+//! the previous line's hash, the first line describes the export and the
+//! last one counts the events and is signed with the kit's Ed25519 key
+//! (created on first export, kept with the other credentials). Editing,
+//! dropping or reordering a line breaks the chain, cutting the file loses the
+//! signed last line. `audit verify` checks a file; whoever receives it should
+//! compare the public key in its first line with the one the site registered
+//! (`--public-key`), since anyone can sign a chain with a fresh key.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, NaiveDate};
+use ring::digest::{digest, SHA256};
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::warn;
+
+use crate::credentials::{self, CredentialStore};
+
+pub const AUDIT_LOG: &str = "logs/audit.jsonl";
+
+/// `prev` of the first line
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Tail of `logs/audit.jsonl` searched for the last hash before an append
+const TAIL_BYTES: u64 = 64 * 1024;
+
+/// Appends from the API and the control channel don't interleave
+static APPEND: Mutex<()> = Mutex::new(());
+
+/// One line of `logs/audit.jsonl`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuditEvent {
+    /// RFC 3339, local time
+    pub at: String,
+    /// Client IP of an API call, or `ctl`
+    pub actor: String,
+    /// `POST /api/superset/start`, `ctl stop`, ...
+    pub action: String,
+    /// `ok`, or the HTTP status or error it ended with
+    pub outcome: String,
+}
+
+impl AuditEvent {
+    /// An action that ends now
+    pub fn now(actor: &str, action: &str, outcome: &str) -> Self {
+        Self {
+            at: Local::now().to_rfc3339(),
+            actor: actor.to_string(),
+            action: action.to_string(),
+            outcome: outcome.to_string(),
+        }
+    }
+}
+
+/// A line of `logs/audit.jsonl`
+#[derive(Debug, Serialize, Deserialize)]
+struct LogLine {
+    #[serde(flatten)]
+    event: AuditEvent,
+    /// Hash of the previous line, `GENESIS` for the first one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prev: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hash: Option<String>,
+}
+
+/// What a log line's hash covers
+#[derive(Serialize)]
+struct ChainedEvent<'a> {
+    #[serde(flatten)]
+    event: &'a AuditEvent,
+    prev: &'a str,
+}
+
+fn event_hash(event: &AuditEvent, prev: &str) -> Result<String> {
+    let body = serde_json::to_vec(&ChainedEvent { event, prev })?;
+    Ok(hex::encode(digest(&SHA256, &body)))
+}
+
+/// Hash of the last readable line of the log, `GENESIS` for an empty or
+/// unchained one
+fn last_hash(path: &Path) -> Result<String> {
+    let Ok(mut file) = std::fs::File::open(path) else {
+        return Ok(GENESIS.to_string());
+    };
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(TAIL_BYTES)))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    let last = String::from_utf8_lossy(&tail).lines().rev().find_map(|line| serde_json::from_str::<LogLine>(line).ok());
+    match last {
+        Some(line) => Ok(line.hash.unwrap_or_else(|| GENESIS.to_string())),
+        None if len > TAIL_BYTES => bail!("No readable line in the last {} KB of {}", TAIL_BYTES / 1024, AUDIT_LOG),
+        None => Ok(GENESIS.to_string()),
+    }
+}
+
+/// Append an action to the audit log; a failure is logged, never raised
+pub fn record(root: &Path, actor: &str, action: &str, outcome: &str) {
+    append(root, &AuditEvent::now(actor, action, outcome));
+}
+
+/// Append an event stamped earlier (writes held while the drive was away)
+pub fn append(root: &Path, event: &AuditEvent) {
+    let _guard = APPEND.lock().unwrap_or_else(|e| e.into_inner());
+    let result = (|| -> Result<()> {
+        let path = root.join(AUDIT_LOG);
+        std::fs::create_dir_all(path.parent().unwrap_or(root))?;
+        let prev = last_hash(&path)?;
+        let hash = event_hash(event, &prev)?;
+        let line = LogLine { event: event.clone(), prev: Some(prev), hash: Some(hash) };
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+        writeln!(file, "{}", serde_json::to_string(&line)?)?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        warn!("Audit record '{}' not written: {:#}", event.action, e);
+    }
+}
+
+/// First line of an export
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExportHeader {
+    pub computer: String,
+    pub kit: String,
+    /// Inclusive dates, `None` for open ends
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub exported_at: String,
+    pub launcher_version: String,
+    /// Hex Ed25519 key the last line is signed with
+    pub public_key: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Record {
+    Header(ExportHeader),
+    Event { event: AuditEvent },
+    Trailer { events: u64 },
+}
+
+/// What a line's hash covers, in this field order
+#[derive(Serialize)]
+struct Chained<'a> {
+    seq: u64,
+    #[serde(flatten)]
+    record: &'a Record,
+    prev: &'a str,
+}
+
+/// A line of an export
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportLine {
+    seq: u64,
+    #[serde(flatten)]
+    record: Record,
+    prev: String,
+    hash: String,
+    /// Of the trailer's hash, hex
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+}
+
+impl ExportLine {
+    fn chained(seq: u64, record: Record, prev: String) -> Result<Self> {
+        let body = serde_json::to_vec(&Chained { seq, record: &record, prev: &prev })?;
+        let hash = hex::encode(digest(&SHA256, &body));
+        Ok(Self { seq, record, prev, hash, signature: None })
+    }
+}
+
+/// The kit's signing key, created on first use
+fn signing_key(store: &CredentialStore) -> Result<Ed25519KeyPair> {
+    let pkcs8 = match store.get(credentials::AUDIT_SIGNING_KEY)? {
+        Some(stored) => hex::decode(stored.trim()).context("Stored audit signing key is not hex")?,
+        None => {
+            let generated = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+                .map_err(|_| anyhow::anyhow!("Failed to generate the audit signing key"))?;
+            store.set(credentials::AUDIT_SIGNING_KEY, &hex::encode(generated.as_ref()))?;
+            tracing::info!("🔏 Created the audit signing key ({})", store.describe());
+            generated.as_ref().to_vec()
+        }
+    };
+    Ed25519KeyPair::from_pkcs8(&pkcs8).map_err(|_| anyhow::anyhow!("Stored audit signing key is invalid"))
+}
+
+/// Events of the audit log dated within `from..=to`; unreadable lines are
+/// skipped with a warning, a broken chain fails the whole read
+pub fn events(root: &Path, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<Vec<AuditEvent>> {
+    let path = root.join(AUDIT_LOG);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut events = Vec::new();
+    let mut prev = GENESIS.to_string();
+    for (number, line) in BufReader::new(std::fs::File::open(&path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line: LogLine = match serde_json::from_str(&line) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("{}:{} skipped: {}", AUDIT_LOG, number + 1, e);
+                continue;
+            }
+        };
+        match (&line.prev, &line.hash) {
+            (Some(line_prev), Some(hash)) => {
+                if *line_prev != prev {
+                    bail!("{}:{}: chain broken (a line was removed, inserted or reordered)", AUDIT_LOG, number + 1);
+                }
+                if event_hash(&line.event, &prev)? != *hash {
+                    bail!("{}:{}: content doesn't match its hash (edited)", AUDIT_LOG, number + 1);
+                }
+                prev = hash.clone();
+            }
+            _ if prev == GENESIS => {}
+            _ => bail!("{}:{}: unchained line after the chain started", AUDIT_LOG, number + 1),
+        }
+        let event = line.event;
+        let Ok(at) = DateTime::parse_from_rfc3339(&event.at) else {
+            warn!("{}:{} skipped: bad time '{}'", AUDIT_LOG, number + 1, event.at);
+            continue;
+        };
+        let date = at.date_naive();
+        if from.is_some_and(|from| date < from) || to.is_some_and(|to| date > to) {
+            continue;
+        }
+        events.push(event);
+    }
+    Ok(events)
+}
+
+/// Write the events of `from..=to` to `output` as a signed chain; returns the
+/// number of events and the public key
+pub fn export(
+    root: &Path,
+    store: &CredentialStore,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    output: &Path,
+) -> Result<(u64, String)> {
+    let key = signing_key(store)?;
+    let public_key = hex::encode(key.public_key().as_ref());
+    let header = ExportHeader {
+        computer: crate::notifications::hostname(),
+        kit: root.canonicalize().unwrap_or_else(|_| root.to_path_buf()).display().to_string(),
+        from,
+        to,
+        exported_at: Local::now().to_rfc3339(),
+        launcher_version: env!("CARGO_PKG_VERSION").to_string(),
+        public_key: public_key.clone(),
+    };
+
+    let mut out = BufWriter::new(std::fs::File::create(output).with_context(|| format!("Cannot create {}", output.display()))?);
+    let mut write = |line: &ExportLine| -> Result<()> {
+        writeln!(out, "{}", serde_json::to_string(line)?)?;
+        Ok(())
+    };
+    let mut line = ExportLine::chained(0, Record::Header(header), GENESIS.to_string())?;
+    write(&line)?;
+    let mut count = 0;
+    for event in events(root, from, to)? {
+        count += 1;
+        line = ExportLine::chained(line.seq + 1, Record::Event { event }, line.hash)?;
+        write(&line)?;
+    }
+    let mut trailer = ExportLine::chained(line.seq + 1, Record::Trailer { events: count }, line.hash)?;
+    trailer.signature = Some(hex::encode(key.sign(trailer.hash.as_bytes()).as_ref()));
+    write(&trailer)?;
+    out.flush()?;
+    Ok((count, public_key))
+}
+
+/// A verified export
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verified {
+    pub header: ExportHeader,
+    pub events: u64,
+}
+
+/// Check the chain, the event count and the signature of an export; with
+/// `public_key`, also that it was signed with that key
+pub fn verify(path: &Path, public_key: Option<&str>) -> Result<Verified> {
+    let file = std::fs::File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+    let mut header = None;
+    let mut prev = GENESIS.to_string();
+    let mut events = 0;
+    let mut signed = None;
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let number = index + 1;
+        let line = line?;
+        if signed.is_some() {
+            bail!("Line {}: data after the signed last line", number);
+        }
+        let parsed: ExportLine = serde_json::from_str(&line).with_context(|| format!("Line {} is not an export line", number))?;
+        if parsed.seq != index as u64 || parsed.prev != prev {
+            bail!("Line {}: chain broken (a line was removed, inserted or reordered)", number);
+        }
+        let expected = ExportLine::chained(parsed.seq, parsed.record.clone(), prev)?;
+        if expected.hash != parsed.hash {
+            bail!("Line {}: content doesn't match its hash (edited)", number);
+        }
+        match (&parsed.record, index) {
+            (Record::Header(h), 0) => header = Some(h.clone()),
+            (Record::Event { .. }, i) if i > 0 => events += 1,
+            (Record::Trailer { events: counted }, i) if i > 0 => {
+                if *counted != events {
+                    bail!("Line {}: {} events counted, {} present", number, counted, events);
+                }
+                signed = Some((parsed.hash.clone(), parsed.signature.clone().context("Last line is not signed")?));
+            }
+            _ => bail!("Line {}: unexpected {} line", number, if index == 0 { "non-header" } else { "header" }),
+        }
+        prev = parsed.hash;
+    }
+
+    let header = header.context("Empty export")?;
+    let (hash, signature) = signed.context("No signed last line: the export was cut short")?;
+    if let Some(expected) = public_key {
+        if !expected.trim().eq_ignore_ascii_case(&header.public_key) {
+            bail!("Signed with {}, not with the registered key", header.public_key);
+        }
+    }
+    let key = hex::decode(&header.public_key).context("Bad public key in the header")?;
+    let signature = hex::decode(signature).context("Bad signature")?;
+    UnparsedPublicKey::new(&ED25519, key)
+        .verify(hash.as_bytes(), &signature)
+        .map_err(|_| anyhow::anyhow!("Signature doesn't match the public key in the header"))?;
+    Ok(Verified { header, events })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_export_verifies_and_detects_tampering() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let store = CredentialStore::file(root);
+        record(root, "127.0.0.1", "POST /api/superset/start", "ok");
+        record(root, "ctl", "ctl stop", "ok");
+        record(root, "192.168.1.7", "POST /api/shutdown", "401 Unauthorized");
+
+        let output = root.join("audit-export.jsonl");
+        let (count, public_key) = export(root, &store, None, None, &output).unwrap();
+        assert_eq!(count, 3);
+        let verified = verify(&output, Some(&public_key)).unwrap();
+        assert_eq!(verified.events, 3);
+        // The same key next time
+        assert_eq!(export(root, &store, None, None, &output).unwrap().1, public_key);
+
+        let tomorrow = Local::now().date_naive().succ_opt().unwrap();
+        assert_eq!(export(root, &store, Some(tomorrow), None, &output).unwrap().0, 0);
+        assert_eq!(verify(&output, None).unwrap().events, 0);
+
+        export(root, &store, None, None, &output).unwrap();
+        let text = std::fs::read_to_string(&output).unwrap();
+        let edited = text.replace("401 Unauthorized", "ok");
+        std::fs::write(&output, &edited).unwrap();
+        assert!(verify(&output, None).unwrap_err().to_string().contains("Line 4"));
+
+        let mut lines: Vec<&str> = text.lines().collect();
+        lines.remove(2);
+        std::fs::write(&output, lines.join("\n")).unwrap();
+        assert!(verify(&output, None).unwrap_err().to_string().contains("chain broken"));
+
+        let cut: Vec<&str> = text.lines().take(4).collect();
+        std::fs::write(&output, cut.join("\n")).unwrap();
+        assert!(verify(&output, None).unwrap_err().to_string().contains("cut short"));
+
+        std::fs::write(&output, &text).unwrap();
+        assert!(verify(&output, Some(&"ab".repeat(32))).is_err());
+
+        // The log itself is chained: an edit there refuses the export
+        let log = root.join(AUDIT_LOG);
+        let source = std::fs::read_to_string(&log).unwrap();
+        std::fs::write(&log, source.replace("401 Unauthorized", "ok")).unwrap();
+        assert!(export(root, &store, None, None, &output).unwrap_err().to_string().contains("audit.jsonl:3"));
+        let dropped: Vec<&str> = source.lines().skip(1).collect();
+        std::fs::write(&log, dropped.join("\n")).unwrap();
+        assert!(export(root, &store, None, None, &output).unwrap_err().to_string().contains("chain broken"));
+        // Appends continue the chain after a line written before it existed
+        std::fs::write(&log, "{\"at\":\"2026-01-05T10:00:00+03:00\",\"actor\":\"ctl\",\"action\":\"ctl stop\",\"outcome\":\"ok\"}\n").unwrap();
+        record(root, "ctl", "ctl start", "ok");
+        record(root, "ctl", "ctl stop", "ok");
+        assert_eq!(events(root, None, None).unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,467 @@
+//! Starter dashboard generated from a table
+//!
+//! `dashboard auto --table <name>` looks at the table's columns and writes a
+//! first-look dashboard into superset.db: a big number with the total of each
+//! measure, a time series of each measure over the first date column, and a
+//! pie for each category with few values. The dataset is registered if
+//! Superset doesn't know the table yet. Charts and the dashboard get UUIDs
+//! derived from the database and table, so running it again after new data
+//! arrives updates them in place instead of adding copies.
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Transaction};
+use serde_json::{json, Value};
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::data_loader;
+use crate::dates::{self, DateFormat};
+
+/// Namespace for UUIDs of generated charts and dashboards
+const UUID_NAMESPACE: Uuid = Uuid::from_u128(0x8c41d2e7_3b6f_4a05_b9d8_6e1f0a7c2d54);
+
+const MAX_BIG_NUMBERS: usize = 4;
+const MAX_TIME_SERIES: usize = 4;
+const MAX_PIES: usize = 3;
+/// More values than this make an unreadable pie (and a category must repeat)
+const PIE_MAX_VALUES: u64 = 12;
+
+/// How a column is used on the dashboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnKind {
+    Temporal,
+    /// Summed into big numbers and time series
+    Measure,
+    /// Low-cardinality text, shown as pie slices
+    Category,
+    Other,
+}
+
+/// Column of the source table
+#[derive(Debug, Clone)]
+struct SourceColumn {
+    name: String,
+    kind: ColumnKind,
+    /// Type written to the Superset dataset
+    superset_type: &'static str,
+}
+
+/// One chart to generate
+#[derive(Debug, Clone)]
+struct ChartPlan {
+    key: String,
+    name: String,
+    viz_type: &'static str,
+    params: Value,
+}
+
+/// Dashboard written by [`create`]
+#[derive(Debug, Clone)]
+pub struct AutoDashboard {
+    pub id: i64,
+    pub slug: String,
+    pub title: String,
+    /// Chart names, in layout order
+    pub charts: Vec<String>,
+}
+
+/// Identifiers are counts and keys, not measures
+fn is_identifier(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name == "id" || name.ends_with("_id") || name.ends_with("_code") || name == "year" || name == "month"
+}
+
+/// Classify the columns of `table` in the SQLite file at `db_path`
+fn inspect(db_path: &Path, table: &str) -> Result<Vec<SourceColumn>> {
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open {}", db_path.display()))?;
+    let declared: Vec<(String, String)> = conn
+        .prepare(&format!("PRAGMA table_info({})", data_loader::quote_ident(table)))?
+        .query_map([], |r| Ok((r.get(1)?, r.get(2)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    if declared.is_empty() {
+        bail!("Table '{}' not found in {}", table, db_path.display());
+    }
+    let rows: u64 = conn.query_row(&format!("SELECT COUNT(*) FROM {}", data_loader::quote_ident(table)), [], |r| r.get(0))?;
+    let declared_dates = data_loader::date_columns(db_path, table)?;
+    let profiles = data_loader::profile_columns(db_path, table)?;
+
+    Ok(declared
+        .into_iter()
+        .map(|(name, declared_type)| {
+            let declared_type = declared_type.to_uppercase();
+            let numeric = ["INT", "REAL", "FLOA", "DOUB", "NUM", "DEC"].iter().any(|t| declared_type.contains(t));
+            let profile = profiles.iter().find(|p| p.column == name);
+            let distinct = profile.map(|p| p.distinct).unwrap_or_default();
+            // ISO text is the only date format SQLite lets Superset put on a time axis
+            let iso_dates = !numeric
+                && profile
+                    .and_then(|p| Some(vec![p.min.clone()?, p.max.clone()?]))
+                    .is_some_and(|range| dates::detect(&name, &range) == Some(DateFormat::Pattern("%Y-%m-%d")));
+            let (kind, superset_type) = if declared_dates.contains(&name) || iso_dates {
+                (ColumnKind::Temporal, "DATETIME")
+            } else if numeric && !is_identifier(&name) {
+                (ColumnKind::Measure, if declared_type.contains("INT") { "INTEGER" } else { "FLOAT" })
+            } else if numeric {
+                (ColumnKind::Other, "INTEGER")
+            } else if (2..=PIE_MAX_VALUES).contains(&distinct) && distinct < rows {
+                (ColumnKind::Category, "STRING")
+            } else {
+                (ColumnKind::Other, "STRING")
+            };
+            SourceColumn { name, kind, superset_type }
+        })
+        .collect())
+}
+
+fn sum_metric(column: &SourceColumn) -> Value {
+    json!({
+        "aggregate": "SUM",
+        "column": {"column_name": column.name, "type": column.superset_type},
+        "expressionType": "SIMPLE",
+        "label": format!("SUM({})", column.name),
+    })
+}
+
+/// Charts for the classified columns, in layout order
+fn plan(columns: &[SourceColumn]) -> Vec<ChartPlan> {
+    let of_kind = |kind: ColumnKind| columns.iter().filter(move |c| c.kind == kind);
+    let mut charts = Vec::new();
+
+    for measure in of_kind(ColumnKind::Measure).take(MAX_BIG_NUMBERS) {
+        charts.push(ChartPlan {
+            key: format!("total_{}", measure.name),
+            name: format!("Итого: {}", measure.name),
+            viz_type: "big_number_total",
+            params: json!({
+                "viz_type": "big_number_total", "time_range": "No filter",
+                "metric": sum_metric(measure), "y_axis_format": "SMART_NUMBER",
+            }),
+        });
+    }
+
+    if let Some(time) = of_kind(ColumnKind::Temporal).next() {
+        for measure in of_kind(ColumnKind::Measure).take(MAX_TIME_SERIES) {
+            charts.push(ChartPlan {
+                key: format!("series_{}_{}", measure.name, time.name),
+                name: format!("{} по {}", measure.name, time.name),
+                viz_type: "echarts_timeseries_line",
+                params: json!({
+                    "viz_type": "echarts_timeseries_line", "time_range": "No filter",
+                    "x_axis": time.name, "time_grain_sqla": "P1D", "x_axis_sort_asc": true,
+                    "metrics": [sum_metric(measure)], "groupby": [], "show_legend": false,
+                    "y_axis_format": "SMART_NUMBER",
+                }),
+            });
+        }
+    }
+
+    let pie_metric = of_kind(ColumnKind::Measure).next().map(sum_metric).unwrap_or_else(|| {
+        json!({"expressionType": "SQL", "sqlExpression": "COUNT(*)", "label": "Количество"})
+    });
+    for category in of_kind(ColumnKind::Category).take(MAX_PIES) {
+        charts.push(ChartPlan {
+            key: format!("pie_{}", category.name),
+            name: format!("Доли: {}", category.name),
+            viz_type: "pie",
+            params: json!({
+                "viz_type": "pie", "time_range": "No filter", "groupby": [category.name],
+                "metric": pie_metric, "show_labels": true, "show_legend": true,
+                "label_type": "key_percent", "number_format": "SMART_NUMBER",
+            }),
+        });
+    }
+    charts
+}
+
+/// Dashboard slug for a table: `auto_` and the name in lowercase ASCII,
+/// Cyrillic transliterated so `Продажи` and `Склад` don't both become `auto_______`
+fn slug_for(table: &str) -> String {
+    let mut name = String::new();
+    for c in table.chars().flat_map(char::to_lowercase) {
+        match translit(c) {
+            Some(latin) => name.push_str(latin),
+            None if c.is_ascii_alphanumeric() => name.push(c),
+            None => name.push('_'),
+        }
+    }
+    format!("auto_{}", name)
+}
+
+/// Latin spelling of a lowercase Cyrillic letter
+fn translit(c: char) -> Option<&'static str> {
+    const LETTERS: [&str; 32] = [
+        "a", "b", "v", "g", "d", "e", "zh", "z", "i", "y", "k", "l", "m", "n", "o", "p", "r", "s", "t", "u", "f",
+        "kh", "ts", "ch", "sh", "shch", "", "y", "", "e", "yu", "ya",
+    ];
+    match c {
+        'а'..='я' => Some(LETTERS[c as usize - 'а' as usize]),
+        'ё' => Some("e"),
+        _ => None,
+    }
+}
+
+/// Generate (or refresh) the starter dashboard for `table` of the Superset
+/// database connection `database_name`, whose SQLite file is `db_path`
+pub fn create(root: &Path, database_name: &str, db_path: &Path, table: &str) -> Result<AutoDashboard> {
+    if crate::validations::is_blocked(db_path, table)? {
+        bail!("Table '{}' failed its rules in {}; fix the data and reload it", table, crate::validations::RULES_FILE);
+    }
+    let columns = inspect(db_path, table)?;
+    let charts = plan(&columns);
+    if charts.is_empty() {
+        bail!("Table '{}' has no numeric, date or category columns to chart", table);
+    }
+
+    let meta_path = root.join("superset_home").join("superset.db");
+    if !meta_path.exists() {
+        bail!("superset.db not found at {}", meta_path.display());
+    }
+    let mut conn = Connection::open(&meta_path).with_context(|| format!("Failed to open {}", meta_path.display()))?;
+    let tx = conn.transaction()?;
+    let now = Utc::now().format("%Y-%m-%d %H:%M:%S%.6f").to_string();
+
+    let database_id: i64 = tx
+        .query_row("SELECT id FROM dbs WHERE database_name = ?1", [database_name], |r| r.get(0))
+        .optional()?
+        .with_context(|| format!("Database '{}' is not registered in Superset (run: superset-launcher databases register)", database_name))?;
+    let dataset_id = ensure_dataset(&tx, database_id, database_name, table, &columns, &now)?;
+
+    let mut placed = Vec::new();
+    for chart in &charts {
+        let uuid = Uuid::new_v5(&UUID_NAMESPACE, format!("{}/{}/{}", database_name, table, chart.key).as_bytes());
+        let mut params = chart.params.clone();
+        params["datasource"] = json!(format!("{}__table", dataset_id));
+        let existing: Option<i64> = tx.query_row("SELECT id FROM slices WHERE uuid = ?1", [uuid], |r| r.get(0)).optional()?;
+        let chart_id = match existing {
+            Some(id) => {
+                tx.execute(
+                    "UPDATE slices SET slice_name = ?1, viz_type = ?2, datasource_type = 'table', datasource_id = ?3,
+                     datasource_name = ?4, params = ?5, changed_on = ?6 WHERE id = ?7",
+                    params![chart.name, chart.viz_type, dataset_id, table, params.to_string(), now, id],
+                )?;
+                id
+            }
+            None => {
+                tx.execute(
+                    "INSERT INTO slices (slice_name, viz_type, datasource_type, datasource_id, datasource_name, params,
+                     uuid, created_on, changed_on, created_by_fk, changed_by_fk)
+                     VALUES (?1, ?2, 'table', ?3, ?4, ?5, ?6, ?7, ?7, 1, 1)",
+                    params![chart.name, chart.viz_type, dataset_id, table, params.to_string(), uuid, now],
+                )?;
+                tx.last_insert_rowid()
+            }
+        };
+        placed.push((chart, chart_id, uuid));
+    }
+
+    let dashboard_uuid = Uuid::new_v5(&UUID_NAMESPACE, format!("{}/{}", database_name, table).as_bytes());
+    let mut slug = slug_for(table);
+    // Slugs are unique: a table of another database, or a user's dashboard, may hold it
+    let taken: bool = tx.query_row(
+        "SELECT EXISTS (SELECT 1 FROM dashboards WHERE slug = ?1 AND (uuid IS NULL OR uuid != ?2))",
+        params![slug, dashboard_uuid],
+        |r| r.get(0),
+    )?;
+    if taken {
+        slug = format!("{}_{}", slug, &dashboard_uuid.simple().to_string()[..8]);
+    }
+    let title = format!("Обзор: {}", table);
+    let position = layout(&title, &placed);
+    let metadata = json!({"color_scheme": "supersetColors", "refresh_frequency": 0, "expanded_slices": {}, "label_colors": {}});
+    let existing: Option<i64> =
+        tx.query_row("SELECT id FROM dashboards WHERE uuid = ?1", [dashboard_uuid], |r| r.get(0)).optional()?;
+    let dashboard_id = match existing {
+        Some(id) => {
+            tx.execute(
+                "UPDATE dashboards SET dashboard_title = ?1, slug = ?2, position_json = ?3, json_metadata = ?4,
+                 uuid = ?5, changed_on = ?6 WHERE id = ?7",
+                params![title, slug, position.to_string(), metadata.to_string(), dashboard_uuid, now, id],
+            )?;
+            id
+        }
+        None => {
+            tx.execute(
+                "INSERT INTO dashboards (dashboard_title, slug, position_json, json_metadata, uuid, published,
+                 created_on, changed_on, created_by_fk, changed_by_fk)
+                 VALUES (?1, ?2, ?3, ?4, ?5, 0, ?6, ?6, 1, 1)",
+                params![title, slug, position.to_string(), metadata.to_string(), dashboard_uuid, now],
+            )?;
+            tx.last_insert_rowid()
+        }
+    };
+    tx.execute("DELETE FROM dashboard_slices WHERE dashboard_id = ?1", [dashboard_id])?;
+    for (_, chart_id, _) in &placed {
+        tx.execute("INSERT INTO dashboard_slices (dashboard_id, slice_id) VALUES (?1, ?2)", params![dashboard_id, chart_id])?;
+    }
+    tx.commit()?;
+
+    Ok(AutoDashboard {
+        id: dashboard_id,
+        slug,
+        title,
+        charts: charts.into_iter().map(|c| c.name).collect(),
+    })
+}
+
+/// Id of the dataset for `table`, registering it (with its columns) if missing
+fn ensure_dataset(
+    tx: &Transaction,
+    database_id: i64,
+    database_name: &str,
+    table: &str,
+    columns: &[SourceColumn],
+    now: &str,
+) -> Result<i64> {
+    let existing: Option<i64> = tx
+        .query_row(
+            "SELECT id FROM tables WHERE table_name = ?1 AND database_id = ?2",
+            params![table, database_id],
+            |r| r.get(0),
+        )
+        .optional()?;
+    if let Some(id) = existing {
+        return Ok(id);
+    }
+
+    let main_dttm_col = columns.iter().find(|c| c.kind == ColumnKind::Temporal).map(|c| c.name.clone());
+    tx.execute(
+        "INSERT INTO tables (table_name, database_id, schema, uuid, main_dttm_col, created_on, changed_on,
+         created_by_fk, changed_by_fk, is_sqllab_view, filter_select_enabled)
+         VALUES (?1, ?2, '', ?3, ?4, ?5, ?5, 1, 1, 0, 1)",
+        params![table, database_id, Uuid::new_v4(), main_dttm_col, now],
+    )?;
+    let dataset_id = tx.last_insert_rowid();
+    tx.execute(
+        "UPDATE tables SET perm = ?1 WHERE id = ?2",
+        params![format!("[{}].[{}](id:{})", database_name, table, dataset_id), dataset_id],
+    )?;
+    for column in columns {
+        tx.execute(
+            "INSERT INTO table_columns (table_id, column_name, type, is_dttm, is_active, groupby, filterable, uuid,
+             created_on, changed_on, created_by_fk, changed_by_fk)
+             VALUES (?1, ?2, ?3, ?4, 1, ?5, 1, ?6, ?7, ?7, 1, 1)",
+            params![
+                dataset_id,
+                column.name,
+                column.superset_type,
+                column.kind == ColumnKind::Temporal,
+                column.kind != ColumnKind::Measure,
+                Uuid::new_v4(),
+                now
+            ],
+        )?;
+    }
+    Ok(dataset_id)
+}
+
+/// Dashboard layout: big numbers share the first row, time series go two
+/// to a row, pies three to a row
+fn layout(title: &str, placed: &[(&ChartPlan, i64, Uuid)]) -> Value {
+    let mut position = json!({
+        "DASHBOARD_VERSION_KEY": "v2",
+        "ROOT_ID": {"id": "ROOT_ID", "type": "ROOT", "children": ["GRID_ID"]},
+        "HEADER_ID": {"id": "HEADER_ID", "type": "HEADER", "meta": {"text": title}},
+    });
+    let mut rows = Vec::new();
+    for (viz_type, per_row) in [("big_number_total", MAX_BIG_NUMBERS), ("echarts_timeseries_line", 2), ("pie", 3)] {
+        let group: Vec<_> = placed.iter().filter(|(chart, _, _)| chart.viz_type == viz_type).collect();
+        for row in group.chunks(per_row) {
+            let row_id = format!("ROW-{}", rows.len() + 1);
+            let width = 12 / row.len();
+            let mut children = Vec::new();
+            for (chart, chart_id, uuid) in row {
+                let chart_key = format!("CHART-{}", chart_id);
+                position[&chart_key] = json!({
+                    "id": chart_key, "type": "CHART", "children": [], "parents": ["ROOT_ID", "GRID_ID", row_id],
+                    "meta": {"chartId": chart_id, "width": width, "height": 50, "sliceName": chart.name, "uuid": uuid.to_string()},
+                });
+                children.push(chart_key);
+            }
+            position[&row_id] = json!({
+                "id": row_id, "type": "ROW", "children": children, "parents": ["ROOT_ID", "GRID_ID"],
+                "meta": {"background": "BACKGROUND_TRANSPARENT"},
+            });
+            rows.push(row_id);
+        }
+    }
+    position["GRID_ID"] = json!({"id": "GRID_ID", "type": "GRID", "children": rows, "parents": ["ROOT_ID"]});
+    position
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_create_is_idempotent() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let data_path = root.join("examples.db");
+        let data = Connection::open(&data_path).unwrap();
+        data.execute_batch(
+            "CREATE TABLE ops (date TEXT, region TEXT, station_id INTEGER, passengers INTEGER, revenue REAL, note TEXT);
+             INSERT INTO ops VALUES ('2024-01-01', 'Север', 1, 10, 1.5, 'a'), ('2024-01-02', 'Юг', 2, 20, 2.5, 'b'),
+                                    ('2024-01-03', 'Север', 3, 30, 3.5, 'c');",
+        )
+        .unwrap();
+
+        std::fs::create_dir_all(root.join("superset_home")).unwrap();
+        let meta = Connection::open(root.join("superset_home").join("superset.db")).unwrap();
+        meta.execute_batch(
+            "CREATE TABLE dbs (id INTEGER PRIMARY KEY, database_name TEXT);
+             CREATE TABLE tables (id INTEGER PRIMARY KEY, table_name TEXT, database_id INTEGER, schema TEXT, uuid BLOB,
+                main_dttm_col TEXT, perm TEXT, created_on DATETIME, changed_on DATETIME, created_by_fk INTEGER,
+                changed_by_fk INTEGER, is_sqllab_view BOOLEAN, filter_select_enabled BOOLEAN);
+             CREATE TABLE table_columns (id INTEGER PRIMARY KEY, table_id INTEGER, column_name TEXT, type TEXT,
+                is_dttm BOOLEAN, is_active BOOLEAN, groupby BOOLEAN, filterable BOOLEAN, uuid BLOB,
+                created_on DATETIME, changed_on DATETIME, created_by_fk INTEGER, changed_by_fk INTEGER);
+             CREATE TABLE slices (id INTEGER PRIMARY KEY, slice_name TEXT, viz_type TEXT, datasource_type TEXT,
+                datasource_id INTEGER, datasource_name TEXT, params TEXT, uuid BLOB, created_on DATETIME,
+                changed_on DATETIME, created_by_fk INTEGER, changed_by_fk INTEGER);
+             CREATE TABLE dashboards (id INTEGER PRIMARY KEY, dashboard_title TEXT, slug TEXT, position_json TEXT,
+                json_metadata TEXT, uuid BLOB, published BOOLEAN, created_on DATETIME, changed_on DATETIME,
+                created_by_fk INTEGER, changed_by_fk INTEGER);
+             CREATE TABLE dashboard_slices (id INTEGER PRIMARY KEY, dashboard_id INTEGER, slice_id INTEGER);
+             INSERT INTO dbs VALUES (1, 'examples');",
+        )
+        .unwrap();
+
+        let first = create(root, "examples", &data_path, "ops").unwrap();
+        assert_eq!(first.slug, "auto_ops");
+        // 2 totals + 2 series over `date` + 1 pie of `region` (`station_id` and `note` are skipped)
+        assert_eq!(first.charts.len(), 5, "{:?}", first.charts);
+        assert!(first.charts.contains(&"Доли: region".to_string()));
+
+        let second = create(root, "examples", &data_path, "ops").unwrap();
+        assert_eq!(second.id, first.id);
+        let count = |sql: &str| -> i64 { meta.query_row(sql, [], |r| r.get(0)).unwrap() };
+        assert_eq!(count("SELECT COUNT(*) FROM slices"), 5);
+        assert_eq!(count("SELECT COUNT(*) FROM tables"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM dashboard_slices"), 5);
+        assert_eq!(count("SELECT COUNT(*) FROM table_columns WHERE is_dttm = 1"), 1);
+
+        let position: String = meta.query_row("SELECT position_json FROM dashboards", [], |r| r.get(0)).unwrap();
+        let position: Value = serde_json::from_str(&position).unwrap();
+        assert_eq!(position["GRID_ID"]["children"].as_array().unwrap().len(), 3);
+
+        assert!(create(root, "examples", &data_path, "missing").is_err());
+
+        // A user's dashboard holding the slug is left alone
+        data.execute_batch("CREATE TABLE ops2 AS SELECT * FROM ops").unwrap();
+        meta.execute("INSERT INTO dashboards (dashboard_title, slug) VALUES ('Мой', 'auto_ops2')", []).unwrap();
+        let other = create(root, "examples", &data_path, "ops2").unwrap();
+        assert!(other.slug.starts_with("auto_ops2_"), "{}", other.slug);
+        assert_eq!(count("SELECT COUNT(*) FROM dashboards WHERE dashboard_title = 'Мой' AND slug = 'auto_ops2'"), 1);
+    }
+
+    #[test]
+    fn test_slug_for() {
+        assert_eq!(slug_for("Продажи 2024"), "auto_prodazhi_2024");
+        assert_eq!(slug_for("Склад"), "auto_sklad");
+        assert_eq!(slug_for("Объём"), "auto_obem");
+        assert_eq!(slug_for("Sales-EU"), "auto_sales_eu");
+    }
+}
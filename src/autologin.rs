@@ -0,0 +1,192 @@
+//! One-time login links for the local browser
+//!
+//! `open --as admin` signs a short-lived token with a key kept in
+//! `superset_config.py` (`LAUNCHER_AUTOLOGIN_KEY`) and opens
+//! `/launcher/autologin?...` on Superset. The route comes from a small helper
+//! module installed next to the config (`launcher_autologin.py`, hooked in
+//! through `FLASK_APP_MUTATOR`): it checks the HMAC, the expiry and that the
+//! nonce was not used before, accepts only loopback clients, logs the user in
+//! and redirects to the requested page. Kiosk and demo machines thus come up
+//! signed in without anyone typing admin/admin.
+
+use anyhow::{bail, Context, Result};
+use ring::hmac;
+use std::path::Path;
+use tracing::info;
+
+use crate::secret_key;
+
+/// Assignment in superset_config.py holding the signing key
+const KEY_NAME: &str = "LAUNCHER_AUTOLOGIN_KEY";
+
+/// Helper module written next to superset_config.py
+const HELPER_FILE: &str = "launcher_autologin.py";
+
+/// Superset route served by the helper
+const ROUTE: &str = "/launcher/autologin";
+
+/// How long a link stays valid
+pub const LINK_TTL_SECS: i64 = 60;
+
+/// Line that marks the hook in superset_config.py
+const HOOK_MARKER: &str = "from launcher_autologin import FLASK_APP_MUTATOR";
+
+const HELPER_SOURCE: &str = r#""""Auto-login links of `superset-launcher open --as` (written by the launcher, do not edit)."""
+import hashlib
+import hmac
+import time
+
+from flask import abort, redirect, request
+
+_used_nonces = {}
+
+
+def _accept(key, user, expires, nonce, next_url, signature):
+    message = "|".join([user, expires, nonce, next_url]).encode("utf-8")
+    expected = hmac.new(key.encode("utf-8"), message, hashlib.sha256).hexdigest()
+    if not hmac.compare_digest(expected, signature):
+        return False
+    now = time.time()
+    for used, until in list(_used_nonces.items()):
+        if until < now:
+            del _used_nonces[used]
+    if int(expires) < now or nonce in _used_nonces:
+        return False
+    _used_nonces[nonce] = int(expires)
+    return True
+
+
+def FLASK_APP_MUTATOR(app):
+    @app.route("/launcher/autologin")
+    def launcher_autologin():
+        from flask_login import login_user
+        from superset import security_manager
+
+        key = app.config.get("LAUNCHER_AUTOLOGIN_KEY")
+        if not key or request.remote_addr not in ("127.0.0.1", "::1"):
+            abort(404)
+        args = request.args
+        user_name = args.get("u", "")
+        expires = args.get("exp", "")
+        next_url = args.get("next", "/")
+        if not expires.isdigit() or not _accept(
+            key, user_name, expires, args.get("nonce", ""), next_url, args.get("sig", "")
+        ):
+            abort(403)
+        user = security_manager.find_user(username=user_name)
+        if user is None or not user.is_active:
+            abort(403)
+        login_user(user, remember=False)
+        if not next_url.startswith("/") or next_url.startswith("//"):
+            next_url = "/"
+        return redirect(next_url)
+"#;
+
+/// Write the helper module and hook it into superset_config.py.
+/// Returns `true` when the config changed (Superset must be restarted).
+pub fn install(root: &Path) -> Result<bool> {
+    let config_path = secret_key::config_path(root);
+    let config = std::fs::read_to_string(&config_path)
+        .with_context(|| format!("{} not found — run: superset-launcher init", config_path.display()))?;
+    let home = config_path.parent().context("Bad config path")?;
+    std::fs::write(home.join(HELPER_FILE), HELPER_SOURCE)?;
+
+    let hooked = config.contains(HOOK_MARKER);
+    if !hooked && defines_mutator(&config) {
+        bail!(
+            "superset_config.py already defines FLASK_APP_MUTATOR; call launcher_autologin.FLASK_APP_MUTATOR(app) from it"
+        );
+    }
+    if hooked && secret_key::read_assignment(&config, KEY_NAME).is_some() {
+        return Ok(false);
+    }
+    let mut updated = config.clone();
+    if !hooked {
+        if !updated.ends_with('\n') {
+            updated.push('\n');
+        }
+        updated.push_str(&format!(
+            "\n# One-time login links (superset-launcher open --as <user>)\n\
+             import os as _os, sys as _sys\n\
+             _sys.path.insert(0, _os.path.dirname(__file__))\n\
+             {}\n",
+            HOOK_MARKER
+        ));
+    }
+    if secret_key::read_assignment(&updated, KEY_NAME).is_none() {
+        let key = hex::encode(rand::random::<[u8; 32]>());
+        updated = secret_key::set_assignment(&updated, KEY_NAME, Some(&key));
+    }
+    std::fs::write(&config_path, updated)?;
+    info!("🔑 Auto-login helper added to superset_config.py");
+    Ok(true)
+}
+
+fn defines_mutator(config: &str) -> bool {
+    config.lines().any(|line| {
+        let line = line.trim_start();
+        line.starts_with("def FLASK_APP_MUTATOR") || line.split('=').next().is_some_and(|l| l.trim() == "FLASK_APP_MUTATOR")
+    })
+}
+
+/// Signed one-time link logging `user` in and landing on `next` (a path)
+pub fn login_url(root: &Path, port: u16, user: &str, next: &str) -> Result<String> {
+    let config = std::fs::read_to_string(secret_key::config_path(root))?;
+    let key = secret_key::read_assignment(&config, KEY_NAME)
+        .context("No auto-login key in superset_config.py — run install first")?;
+    let expires = chrono::Utc::now().timestamp() + LINK_TTL_SECS;
+    Ok(signed_url(&key, port, user, next, expires, &hex::encode(rand::random::<[u8; 16]>())))
+}
+
+fn signed_url(key: &str, port: u16, user: &str, next: &str, expires: i64, nonce: &str) -> String {
+    let expires = expires.to_string();
+    let message = [user, expires.as_str(), nonce, next].join("|");
+    let tag = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key.as_bytes()), message.as_bytes());
+    let base = format!("http://localhost:{}{}", port, ROUTE);
+    reqwest::Url::parse_with_params(
+        &base,
+        [
+            ("u", user),
+            ("exp", expires.as_str()),
+            ("nonce", nonce),
+            ("next", next),
+            ("sig", hex::encode(tag.as_ref()).as_str()),
+        ],
+    )
+    .map(String::from)
+    .unwrap_or(base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_install_is_idempotent() {
+        let dir = tempdir().unwrap();
+        let config_path = secret_key::config_path(dir.path());
+        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+        std::fs::write(&config_path, "import os\nSECRET_KEY = \"abc\"").unwrap();
+
+        assert!(install(dir.path()).unwrap());
+        assert!(!install(dir.path()).unwrap());
+        let config = std::fs::read_to_string(&config_path).unwrap();
+        assert_eq!(config.matches(HOOK_MARKER).count(), 1);
+        assert_eq!(secret_key::read_assignment(&config, KEY_NAME).unwrap().len(), 64);
+        assert!(dir.path().join("superset_home").join(HELPER_FILE).exists());
+        assert!(login_url(dir.path(), 8088, "admin", "/").unwrap().starts_with("http://localhost:8088/launcher/autologin?u=admin"));
+
+        std::fs::write(&config_path, "def FLASK_APP_MUTATOR(app):\n    pass\n").unwrap();
+        assert!(install(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_signature_matches_helper() {
+        // Same digest as hmac.new(b"key", b"admin|1700000000|n1|/superset/welcome/", hashlib.sha256)
+        let url = signed_url("key", 8088, "admin", "/superset/welcome/", 1_700_000_000, "n1");
+        let expected = "63cafa742824b82391966afcce29552a5c2cb488a8b8eda9b8931c50f76580b0";
+        assert!(url.contains("next=%2Fsuperset%2Fwelcome%2F"), "{}", url);
+        assert!(url.ends_with(&format!("sig={}", expected)), "{}", url);
+    }
+}
@@ -0,0 +1,251 @@
+//! Antivirus warm-up of a freshly unpacked kit
+//!
+//! Real-time antivirus scans every file the first time it is opened. With
+//! ~20k files in the Python tree, Superset's first start spends minutes
+//! waiting on those scans one import at a time. `warmup` (or `unpack
+//! --warm-up`) reads every file once, in parallel, so the scans happen up
+//! front; then it re-reads a sample, whose scan results are already cached,
+//! to estimate what the antivirus costs per file. The report lists the
+//! slowest folders and the exact paths an administrator should exclude.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::info;
+use walkdir::WalkDir;
+
+use crate::doctor::SUPPORT_DIR;
+use crate::python::PythonEnv;
+
+/// Folders read during warm-up (relative to root)
+const WARM_DIRS: &[&str] = &["python"];
+
+/// Folders worth excluding from real-time scanning, if present
+const EXCLUDE_DIRS: &[&str] = &["python", "superset_home", "cache", "data"];
+
+/// Files re-read to measure the cost of the first (scanned) read
+const SAMPLE_FILES: usize = 500;
+
+/// Progress is logged every this many files
+const PROGRESS_EVERY: usize = 2000;
+
+/// Folder depth used to group timings (`python/Lib/site-packages/<package>`)
+const GROUP_DEPTH: usize = 4;
+
+/// Folders shown in the "slowest" list
+const SLOWEST_SHOWN: usize = 10;
+
+/// Outcome of a warm-up run
+#[derive(Debug, Clone)]
+pub struct WarmupReport {
+    pub root: PathBuf,
+    pub files: usize,
+    pub bytes: u64,
+    pub threads: usize,
+    /// Wall time of the parallel first read
+    pub elapsed: Duration,
+    /// Average time of a first read
+    pub first_read: Duration,
+    /// Average time of a repeat read (scan result cached)
+    pub repeat_read: Duration,
+    /// Folders by total first-read time, slowest first
+    pub slowest: Vec<(PathBuf, Duration)>,
+    /// Folders to add to the antivirus exclusions
+    pub exclude_dirs: Vec<PathBuf>,
+    /// Executables to add to the process exclusions
+    pub exclude_processes: Vec<PathBuf>,
+}
+
+impl WarmupReport {
+    /// Estimated antivirus cost of opening one file for the first time
+    pub fn av_cost_per_file(&self) -> Duration {
+        self.first_read.saturating_sub(self.repeat_read)
+    }
+
+    /// Write the report to `support/av-warmup-<time>.txt`
+    pub fn write(&self, root: &Path) -> Result<PathBuf> {
+        let dir = root.join(SUPPORT_DIR);
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("av-warmup-{}.txt", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+        std::fs::write(&path, self.to_string())?;
+        Ok(path)
+    }
+}
+
+impl fmt::Display for WarmupReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        writeln!(f, "Прогрев комплекта: {}", self.root.display())?;
+        writeln!(f, "Файлов: {} ({:.1} МБ), потоков: {}", self.files, self.bytes as f64 / 1_048_576.0, self.threads)?;
+        writeln!(f, "Первое чтение: {:.1} с, в среднем {:.2} мс на файл", self.elapsed.as_secs_f64(), ms(self.first_read))?;
+        writeln!(f, "Повторное чтение: {:.2} мс на файл", ms(self.repeat_read))?;
+        if self.first_read > Duration::ZERO {
+            let share = self.av_cost_per_file().as_secs_f64() / self.first_read.as_secs_f64() * 100.0;
+            writeln!(
+                f,
+                "Антивирус и холодный диск добавляют ≈ {:.2} мс на файл ({:.0}% времени первого чтения)",
+                ms(self.av_cost_per_file()),
+                share
+            )?;
+        }
+
+        if !self.slowest.is_empty() {
+            writeln!(f, "\nДольше всего проверялись:")?;
+            for (dir, time) in &self.slowest {
+                writeln!(f, "  {:<50} {:.1} с", dir.display(), time.as_secs_f64())?;
+            }
+        }
+
+        writeln!(f, "\nДобавьте в исключения антивируса папки:")?;
+        for dir in &self.exclude_dirs {
+            writeln!(f, "  {}", dir.display())?;
+        }
+        writeln!(f, "и процессы:")?;
+        for exe in &self.exclude_processes {
+            writeln!(f, "  {}", exe.display())?;
+        }
+
+        writeln!(f, "\nДля Microsoft Defender (PowerShell от имени администратора):")?;
+        for dir in &self.exclude_dirs {
+            writeln!(f, "  Add-MpPreference -ExclusionPath \"{}\"", dir.display())?;
+        }
+        for exe in &self.exclude_processes {
+            writeln!(f, "  Add-MpPreference -ExclusionProcess \"{}\"", exe.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Read a whole file, returning its size
+fn touch(path: &Path) -> io::Result<u64> {
+    io::copy(&mut File::open(path)?, &mut io::sink())
+}
+
+/// Timing group of a file: its first [`GROUP_DEPTH`] folders under root
+fn group_of(rel: &Path) -> PathBuf {
+    let dirs: Vec<Component> = rel.parent().map(|p| p.components().collect()).unwrap_or_default();
+    dirs.into_iter().take(GROUP_DEPTH).collect()
+}
+
+/// Read every file of the kit once (in `threads` workers) and measure the
+/// antivirus cost. `threads = 0` uses one worker per CPU.
+pub fn warm_up(root: &Path, threads: usize) -> Result<WarmupReport> {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let files: Vec<PathBuf> = WARM_DIRS
+        .iter()
+        .flat_map(|dir| WalkDir::new(root.join(dir)).into_iter().filter_map(|e| e.ok()))
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect();
+    let threads = if threads == 0 {
+        std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4)
+    } else {
+        threads
+    };
+    info!("🛡️ Warming up {} files in {} thread(s)...", files.len(), threads);
+
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let results: Mutex<Vec<(usize, Duration, u64)>> = Mutex::new(Vec::with_capacity(files.len()));
+    let started = Instant::now();
+    std::thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| {
+                let mut local = Vec::new();
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(path) = files.get(i) else { break };
+                    let t = Instant::now();
+                    let bytes = touch(path).unwrap_or_default();
+                    local.push((i, t.elapsed(), bytes));
+                    let finished = done.fetch_add(1, Ordering::Relaxed) + 1;
+                    if finished % PROGRESS_EVERY == 0 {
+                        info!("   ...{}/{} files ({}s)", finished, files.len(), started.elapsed().as_secs());
+                    }
+                }
+                results.lock().unwrap_or_else(|e| e.into_inner()).extend(local);
+            });
+        }
+    });
+    let elapsed = started.elapsed();
+    let results = results.into_inner().unwrap_or_else(|e| e.into_inner());
+
+    let mut groups: HashMap<PathBuf, Duration> = HashMap::new();
+    let mut first_total = Duration::ZERO;
+    let mut bytes = 0;
+    for (i, time, size) in &results {
+        let rel = files[*i].strip_prefix(&root).unwrap_or(&files[*i]);
+        *groups.entry(group_of(rel)).or_default() += *time;
+        first_total += *time;
+        bytes += size;
+    }
+    let mut slowest: Vec<(PathBuf, Duration)> = groups.into_iter().collect();
+    slowest.sort_by(|a, b| b.1.cmp(&a.1));
+    slowest.truncate(SLOWEST_SHOWN);
+
+    // Repeat reads one at a time: the scan verdicts are cached now
+    let step = (files.len() / SAMPLE_FILES).max(1);
+    let sample: Vec<&PathBuf> = files.iter().step_by(step).take(SAMPLE_FILES).collect();
+    let t = Instant::now();
+    for path in &sample {
+        let _ = touch(path);
+    }
+    let repeat_read = if sample.is_empty() { Duration::ZERO } else { t.elapsed() / sample.len() as u32 };
+    let first_read = if files.is_empty() { Duration::ZERO } else { first_total / files.len() as u32 };
+
+    let exclude_dirs = EXCLUDE_DIRS.iter().map(|d| root.join(d)).filter(|d| d.is_dir()).collect();
+    let mut exclude_processes: Vec<PathBuf> = std::env::current_exe().into_iter().collect();
+    let python = PythonEnv::new(&root)?;
+    if python.is_valid() {
+        exclude_processes.push(python.python_path().to_path_buf());
+    }
+
+    Ok(WarmupReport {
+        root,
+        files: files.len(),
+        bytes,
+        threads,
+        elapsed,
+        first_read,
+        repeat_read,
+        slowest,
+        exclude_dirs,
+        exclude_processes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_warm_up_reads_everything_and_lists_exclusions() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let pkg = root.join("python/Lib/site-packages/pandas/core");
+        std::fs::create_dir_all(&pkg).unwrap();
+        std::fs::create_dir_all(root.join("superset_home")).unwrap();
+        for i in 0..20 {
+            std::fs::write(pkg.join(format!("m{}.py", i)), "x = 1\n").unwrap();
+        }
+        std::fs::write(root.join("python/python.exe"), "MZ").unwrap();
+
+        let report = warm_up(root, 3).unwrap();
+        assert_eq!(report.files, 21);
+        assert_eq!(report.bytes, 20 * 6 + 2);
+        assert!(report.slowest.iter().any(|(d, _)| d == Path::new("python/Lib/site-packages/pandas")));
+        assert_eq!(report.exclude_dirs.len(), 2, "python and superset_home");
+        assert!(report.exclude_processes.iter().any(|p| p.ends_with("python/python.exe")));
+
+        let text = report.to_string();
+        assert!(text.contains("Add-MpPreference -ExclusionPath"));
+        assert!(report.write(root).unwrap().exists());
+    }
+}
@@ -0,0 +1,268 @@
+//! Backups of Superset metadata and demo data
+//!
+//! Creates `backups/backup-YYYYMMDD-HHMMSS.zip` archives with consistent
+//! SQLite snapshots (`VACUUM INTO`, safe while Superset is running) plus the
+//! launcher/Superset config files, and keeps only the newest N archives.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDateTime, Timelike};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{error, info, warn};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::notifications::{self, Level};
+
+const BACKUP_DIR: &str = "backups";
+const BACKUP_PREFIX: &str = "backup-";
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// SQLite databases snapshotted into every backup (relative to root)
+const DATABASES: &[&str] = &["superset_home/superset.db", "examples.db"];
+
+/// Plain files copied into every backup (relative to root)
+const FILES: &[&str] = &["superset_home/superset_config.py", "config.json", "lightdocs.json"];
+
+/// Automatic backup settings (`backup` section of config.json)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// Run the nightly backup from the launcher UI process
+    pub enabled: bool,
+    /// Local hour (0-23) when the nightly backup window opens
+    pub hour: u32,
+    /// Number of archives to keep in `backups/`
+    pub keep: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            hour: 3,
+            keep: 7,
+        }
+    }
+}
+
+/// Backup archive found on disk
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupInfo {
+    pub path: PathBuf,
+    pub created: NaiveDateTime,
+    pub size_bytes: u64,
+}
+
+/// Create a new backup archive and return its path
+pub fn create_backup(root: &Path) -> Result<PathBuf> {
+    let backup_dir = root.join(BACKUP_DIR);
+    fs::create_dir_all(&backup_dir)?;
+
+    let stamp = Local::now().format(TIMESTAMP_FORMAT).to_string();
+    let archive_path = backup_dir.join(format!("{}{}.zip", BACKUP_PREFIX, stamp));
+    let tmp_dir = backup_dir.join(format!(".tmp-{}", stamp));
+    fs::create_dir_all(&tmp_dir)?;
+
+    let result = write_archive(root, &tmp_dir, &archive_path);
+    let _ = fs::remove_dir_all(&tmp_dir);
+
+    if result.is_err() {
+        let _ = fs::remove_file(&archive_path);
+    }
+    result?;
+
+    info!("💾 Backup created: {}", archive_path.display());
+    Ok(archive_path)
+}
+
+fn write_archive(root: &Path, tmp_dir: &Path, archive_path: &Path) -> Result<()> {
+    let file = File::create(archive_path)?;
+    let mut zip = ZipWriter::new(BufWriter::new(file));
+    let options = FileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .compression_level(Some(6));
+
+    for rel in DATABASES {
+        let db_path = root.join(rel);
+        if !db_path.exists() {
+            warn!("  Skipping missing database: {}", rel);
+            continue;
+        }
+
+        // Consistent snapshot even if Superset is writing right now
+        let snapshot = tmp_dir.join(rel.replace('/', "_"));
+        let conn = rusqlite::Connection::open(&db_path)
+            .with_context(|| format!("Failed to open {}", db_path.display()))?;
+        conn.execute("VACUUM INTO ?1", [snapshot.to_string_lossy().as_ref()])
+            .with_context(|| format!("Failed to snapshot {}", rel))?;
+
+        zip.start_file(*rel, options)?;
+        io::copy(&mut File::open(&snapshot)?, &mut zip)?;
+    }
+
+    for rel in FILES {
+        let path = root.join(rel);
+        if path.exists() {
+            zip.start_file(*rel, options)?;
+            io::copy(&mut File::open(&path)?, &mut zip)?;
+        }
+    }
+
+    zip.finish()?;
+    Ok(())
+}
+
+/// List backups, newest first
+pub fn list_backups(root: &Path) -> Result<Vec<BackupInfo>> {
+    let backup_dir = root.join(BACKUP_DIR);
+    if !backup_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups: Vec<BackupInfo> = fs::read_dir(&backup_dir)?
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let path = e.path();
+            let created = parse_backup_name(path.file_name()?.to_str()?)?;
+            let size_bytes = e.metadata().map(|m| m.len()).unwrap_or(0);
+            Some(BackupInfo { path, created, size_bytes })
+        })
+        .collect();
+
+    backups.sort_by(|a, b| b.created.cmp(&a.created));
+    Ok(backups)
+}
+
+/// Timestamp encoded in `backup-YYYYMMDD-HHMMSS.zip`
+fn parse_backup_name(name: &str) -> Option<NaiveDateTime> {
+    let stamp = name.strip_prefix(BACKUP_PREFIX)?.strip_suffix(".zip")?;
+    NaiveDateTime::parse_from_str(stamp, TIMESTAMP_FORMAT).ok()
+}
+
+/// Time of the most recent backup
+pub fn last_backup(root: &Path) -> Option<NaiveDateTime> {
+    list_backups(root).ok()?.first().map(|b| b.created)
+}
+
+/// Delete all but the newest `keep` backups, returns number removed
+pub fn rotate_backups(root: &Path, keep: usize) -> Result<usize> {
+    let backups = list_backups(root)?;
+    let mut removed = 0;
+    for old in backups.iter().skip(keep.max(1)) {
+        fs::remove_file(&old.path)?;
+        info!("🗑️ Removed old backup: {}", old.path.display());
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// Start the nightly backup loop in the background.
+///
+/// Once the configured hour is reached, the backup waits for Superset to be
+/// idle (not listening) until the window closes, then runs regardless since
+/// the snapshot is consistent either way.
+///
+/// A `backup` entry in `schedules.json` replaces the nightly window: the
+/// backup then runs at the times of its cron expression, or not at all when
+/// the entry is switched off.
+pub fn spawn_scheduler(root: PathBuf, config: BackupConfig, superset_port: u16) {
+    match crate::schedules::load(&root).get(crate::schedules::BACKUP_JOB) {
+        Some(schedule) if schedule.enabled => {
+            info!("💾 Automatic backup scheduled by '{}' (keep {})", schedule.cron, config.keep)
+        }
+        Some(_) => info!("Automatic backups disabled in {}", crate::schedules::SCHEDULES_FILE),
+        None if config.enabled => {
+            info!("💾 Automatic backup scheduled daily at {:02}:00 (keep {})", config.hour, config.keep)
+        }
+        None => info!("Automatic backups disabled"),
+    }
+
+    // Keeps running while disabled: the panel can switch the schedule on
+    tokio::spawn(async move {
+        let mut last_tick = Local::now().naive_local();
+        loop {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+
+            if crate::drive_watchdog::is_paused() {
+                continue;
+            }
+            let now = Local::now();
+            let since = std::mem::replace(&mut last_tick, now.naive_local());
+            let due = match crate::schedules::load(&root).remove(crate::schedules::BACKUP_JOB) {
+                Some(schedule) => schedule.is_due(since, now.naive_local()),
+                None => config.enabled && nightly_window_open(&root, &config, superset_port, now).await,
+            };
+            if !due {
+                continue;
+            }
+
+            let backup_root = root.clone();
+            let keep = config.keep;
+            let result = tokio::task::spawn_blocking(move || {
+                create_backup(&backup_root)?;
+                rotate_backups(&backup_root, keep)
+            })
+            .await;
+
+            match result {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => {
+                    error!("Automatic backup failed: {}", e);
+                    notifications::notify(Level::Error, "Резервная копия не создана", &e.to_string());
+                }
+                Err(e) => error!("Automatic backup task panicked: {}", e),
+            }
+        }
+    });
+}
+
+/// Within the configured hour, no backup yet today, and Superset idle or the window closing
+async fn nightly_window_open(root: &Path, config: &BackupConfig, superset_port: u16, now: DateTime<Local>) -> bool {
+    let done_today = last_backup(root)
+        .map_or(false, |last| last.date() == now.date_naive());
+    if done_today || now.hour() != config.hour {
+        return false;
+    }
+
+    let window_closing = now.minute() >= 55;
+    let superset_busy = tokio::net::TcpStream::connect(("127.0.0.1", superset_port)).await.is_ok();
+    !superset_busy || window_closing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_backup_and_rotation() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let conn = rusqlite::Connection::open(root.join("examples.db")).unwrap();
+        conn.execute("CREATE TABLE t (x INTEGER)", []).unwrap();
+        fs::write(root.join("config.json"), "{}").unwrap();
+
+        let archive = create_backup(root).unwrap();
+        assert!(archive.exists());
+
+        // Fake older archives
+        for stamp in ["20250101-030000", "20250102-030000", "20250103-030000"] {
+            fs::write(root.join(BACKUP_DIR).join(format!("backup-{}.zip", stamp)), b"").unwrap();
+        }
+        assert_eq!(list_backups(root).unwrap().len(), 4);
+
+        assert_eq!(rotate_backups(root, 2).unwrap(), 2);
+        let left = list_backups(root).unwrap();
+        assert_eq!(left.len(), 2);
+        assert_eq!(left[0].path, archive);
+    }
+
+    #[test]
+    fn test_parse_backup_name() {
+        assert!(parse_backup_name("backup-20260101-031500.zip").is_some());
+        assert!(parse_backup_name("notes.txt").is_none());
+    }
+}
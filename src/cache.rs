@@ -1,13 +1,17 @@
 //! Embedded cache module using sled for persistent caching
 //! 
 //! Designed for offline/air-gapped environments on low-power computers.
-//! Caches query results to speed up dashboard loading.
+//! Caches query results to speed up dashboard loading. Values are sealed
+//! when `cache_encryption` covers the `cache` namespace (see `cache_crypto`).
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use crate::cache_crypto::{self, CacheCipher, CacheEncryptionConfig};
+use crate::sled_journal::{CompactReport, Journal, CHECKPOINT_MAX_AGE, CHECKPOINT_MAX_VALUE_BYTES, COMPACT_INTERVAL, DEFAULT_TREE};
+
 /// Cache entry with TTL support
 #[derive(Serialize, Deserialize)]
 struct CacheEntry {
@@ -38,25 +42,54 @@ impl CacheEntry {
     }
 }
 
+/// Compaction filter: drop expired and unreadable entries
+fn is_live(raw: &[u8]) -> bool {
+    serde_json::from_slice::<CacheEntry>(raw).map_or(false, |entry| !entry.is_expired())
+}
+
 /// Persistent cache using sled embedded database
 pub struct Cache {
     db: sled::Db,
     default_ttl: Duration,
+    cipher: Option<CacheCipher>,
 }
 
 impl Cache {
     /// Open or create a cache at the specified path
     pub fn open(root: &Path) -> Result<Self> {
-        let cache_path = root.join("cache");
-        let db = sled::open(&cache_path)
+        let journal = Self::journal(root);
+        if let Err(e) = journal.compact_if_due(COMPACT_INTERVAL, is_live) {
+            tracing::warn!("Cache compaction skipped: {}", e);
+        }
+        let db = journal.open()
             .context("Failed to open sled cache database")?;
+        if let Err(e) = journal.checkpoint_if_stale(&db, CHECKPOINT_MAX_AGE) {
+            tracing::warn!("Cache checkpoint failed: {}", e);
+        }
+        
+        let encryption = CacheEncryptionConfig::load(root);
+        let cipher = cache_crypto::cipher_for(root, cache_crypto::LAUNCHER_CACHE, &encryption)?;
+        if cache_crypto::key_changed(root, cache_crypto::LAUNCHER_CACHE, cipher.as_ref())? {
+            tracing::info!("Cache encryption key changed, cache emptied");
+            db.clear()?;
+        }
         
         Ok(Self {
             db,
             default_ttl: Duration::from_secs(300), // 5 minutes default
+            cipher,
         })
     }
     
+    fn journal(root: &Path) -> Journal {
+        Journal::new(&root.join("cache"), &[DEFAULT_TREE]).max_value_bytes(CHECKPOINT_MAX_VALUE_BYTES)
+    }
+    
+    /// Rewrite the (closed) cache without expired entries
+    pub fn compact(root: &Path) -> Result<CompactReport> {
+        Self::journal(root).compact_with(is_live)
+    }
+    
     /// Set default TTL for cache entries
     pub fn set_default_ttl(&mut self, ttl: Duration) {
         self.default_ttl = ttl;
@@ -73,7 +106,10 @@ impl Cache {
             return None;
         }
         
-        Some(entry.data)
+        match &self.cipher {
+            Some(cipher) => cipher.open(key.as_bytes(), &entry.data),
+            None => Some(entry.data),
+        }
     }
     
     /// Get a string value from cache
@@ -88,7 +124,11 @@ impl Cache {
     
     /// Set a value with custom TTL
     pub fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<()> {
-        let entry = CacheEntry::new(value.to_vec(), ttl);
+        let data = match &self.cipher {
+            Some(cipher) => cipher.seal(key.as_bytes(), value),
+            None => value.to_vec(),
+        };
+        let entry = CacheEntry::new(data, ttl);
         let serialized = serde_json::to_vec(&entry)?;
         self.db.insert(key.as_bytes(), serialized)?;
         self.db.flush()?;
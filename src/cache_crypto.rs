@@ -0,0 +1,384 @@
+//! Encryption at rest of cached data (`cache_encryption` in config.json)
+//!
+//! Some sites don't allow unencrypted query results on a flash drive, even
+//! derived ones. Each cache (namespace) listed here keeps its values sealed
+//! with AES-256-GCM:
+//!
+//! ```json
+//! "cache_encryption": { "namespaces": ["gateway", "cache"], "key": "machine" }
+//! ```
+//!
+//! - `gateway` — chart data cached by the gateway (sled entries and big files)
+//! - `cache` — the launcher's own sled cache
+//!
+//! The key never lies next to the cache. `machine` derives it from this
+//! computer's id (MachineGuid on Windows, machine-id on Linux), so the drive
+//! read elsewhere shows only noise; `passphrase` derives it from
+//! `SUPERSET_CACHE_PASSPHRASE`, so the cache works on any machine that knows
+//! it. A random salt in `cache/encryption.salt` makes keys differ per kit.
+//! When the key changes (another computer, passphrase or setting) the cache
+//! is simply emptied — it's only a cache. Without a usable key an encrypted
+//! namespace caches nothing rather than write plain data.
+
+use anyhow::{bail, Context, Result};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{hkdf, pbkdf2};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Chart data cached by the gateway
+pub const GATEWAY: &str = "gateway";
+
+/// The launcher's sled cache (`cache::Cache`)
+pub const LAUNCHER_CACHE: &str = "cache";
+
+/// Passphrase for `"key": "passphrase"`
+pub const PASSPHRASE_ENV: &str = "SUPERSET_CACHE_PASSPHRASE";
+
+/// Per-kit salt, inside `cache/`
+const SALT_FILE: &str = "encryption.salt";
+
+const PBKDF2_ITERATIONS: u32 = 100_000;
+
+const TAG_LEN: usize = 16;
+
+/// Bytes a sealed value is longer than the plain one
+const OVERHEAD: usize = NONCE_LEN + TAG_LEN;
+
+/// Plain bytes per sealed block of a cached file
+pub const BLOCK_SIZE: usize = 256 * 1024;
+
+/// Known text sealed into `cache/<namespace>.keycheck`
+const KEY_CHECK: &[u8] = b"superset-portable cache key";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeySource {
+    /// Tied to this computer
+    #[default]
+    Machine,
+    /// From `SUPERSET_CACHE_PASSPHRASE`
+    Passphrase,
+}
+
+/// Cache encryption settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheEncryptionConfig {
+    /// Caches to encrypt: `gateway`, `cache`
+    pub namespaces: Vec<String>,
+    pub key: KeySource,
+}
+
+impl CacheEncryptionConfig {
+    /// Section of the portable root's config.json (defaults if missing or
+    /// unreadable); reading never creates the file
+    pub fn load(root: &Path) -> Self {
+        crate::config::Config::load(root)
+            .map(|c| c.cache_encryption)
+            .unwrap_or_default()
+    }
+
+    pub fn covers(&self, namespace: &str) -> bool {
+        self.namespaces.iter().any(|n| n == namespace)
+    }
+}
+
+/// AES-256-GCM key of one namespace
+#[derive(Clone)]
+pub struct CacheCipher {
+    key: Arc<LessSafeKey>,
+}
+
+impl CacheCipher {
+    fn derive(master: &[u8], salt: &[u8], namespace: &str) -> Result<Self> {
+        let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, salt).extract(master);
+        let info = [namespace.as_bytes()];
+        let okm = prk.expand(&info, &AES_256_GCM).map_err(|_| anyhow::anyhow!("Cache key derivation failed"))?;
+        Ok(Self { key: Arc::new(LessSafeKey::new(UnboundKey::from(okm))) })
+    }
+
+    /// Key from a passphrase (slow on purpose, like any password hash)
+    pub fn from_passphrase(passphrase: &str, salt: &[u8], namespace: &str) -> Result<Self> {
+        let mut master = [0u8; 32];
+        let iterations = NonZeroU32::new(PBKDF2_ITERATIONS).expect("non-zero");
+        pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, iterations, salt, passphrase.as_bytes(), &mut master);
+        Self::derive(&master, salt, namespace)
+    }
+
+    /// `plain` sealed with a random nonce, bound to `context` (the cache key)
+    pub fn seal(&self, context: &[u8], plain: &[u8]) -> Vec<u8> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new().fill(&mut nonce).expect("system random source");
+        let mut sealed = Vec::with_capacity(plain.len() + OVERHEAD);
+        sealed.extend_from_slice(&nonce);
+        let mut in_out = plain.to_vec();
+        self.key
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(context), &mut in_out)
+            .expect("AES-GCM seal");
+        sealed.extend_from_slice(&in_out);
+        sealed
+    }
+
+    /// Plain bytes of a sealed value; `None` if the key or context differ or it was altered
+    pub fn open(&self, context: &[u8], sealed: &[u8]) -> Option<Vec<u8>> {
+        if sealed.len() < OVERHEAD {
+            return None;
+        }
+        let (nonce, body) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+        let mut in_out = body.to_vec();
+        let plain_len = self.key.open_in_place(nonce, Aad::from(context), &mut in_out).ok()?.len();
+        in_out.truncate(plain_len);
+        Some(in_out)
+    }
+}
+
+/// Key of `namespace`: `None` when the namespace isn't encrypted, an error
+/// when it should be but no key can be made
+pub fn cipher_for(root: &Path, namespace: &str, config: &CacheEncryptionConfig) -> Result<Option<CacheCipher>> {
+    if !config.covers(namespace) {
+        return Ok(None);
+    }
+    let salt = salt(root)?;
+    let cipher = match config.key {
+        KeySource::Machine => CacheCipher::derive(machine_secret()?.as_bytes(), &salt, namespace)?,
+        KeySource::Passphrase => {
+            let passphrase = std::env::var(PASSPHRASE_ENV).unwrap_or_default();
+            if passphrase.is_empty() {
+                bail!("Cache '{}' is encrypted with a passphrase, but {} is not set", namespace, PASSPHRASE_ENV);
+            }
+            CacheCipher::from_passphrase(&passphrase, &salt, namespace)?
+        }
+    };
+    Ok(Some(cipher))
+}
+
+/// Whether the cache of `namespace` was written with another key (or
+/// unencrypted, or encrypted and now isn't) and must be emptied; records
+/// the current key for next time
+pub fn key_changed(root: &Path, namespace: &str, cipher: Option<&CacheCipher>) -> Result<bool> {
+    let path = root.join("cache").join(format!("{}.keycheck", namespace));
+    let stored = std::fs::read(&path).ok();
+    let Some(cipher) = cipher else {
+        if stored.is_some() {
+            std::fs::remove_file(&path)?;
+        }
+        return Ok(stored.is_some());
+    };
+    let matches = stored.as_deref().and_then(|s| cipher.open(namespace.as_bytes(), s)).is_some_and(|p| p == KEY_CHECK);
+    if !matches {
+        std::fs::create_dir_all(path.parent().unwrap_or(root))?;
+        std::fs::write(&path, cipher.seal(namespace.as_bytes(), KEY_CHECK))?;
+    }
+    Ok(!matches)
+}
+
+fn salt(root: &Path) -> Result<Vec<u8>> {
+    let path = root.join("cache").join(SALT_FILE);
+    if let Ok(salt) = std::fs::read(&path) {
+        if salt.len() == 16 {
+            return Ok(salt);
+        }
+    }
+    let mut salt = vec![0u8; 16];
+    SystemRandom::new().fill(&mut salt).map_err(|_| anyhow::anyhow!("No system random source"))?;
+    std::fs::create_dir_all(path.parent().unwrap_or(root))?;
+    std::fs::write(&path, &salt).with_context(|| format!("Cannot write {}", path.display()))?;
+    Ok(salt)
+}
+
+/// Identifier of this computer that doesn't travel with the kit
+#[cfg(windows)]
+fn machine_secret() -> Result<String> {
+    let output = std::process::Command::new("reg")
+        .args(["query", r"HKLM\SOFTWARE\Microsoft\Cryptography", "/v", "MachineGuid"])
+        .output()
+        .context("Failed to run reg")?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains("MachineGuid"))
+        .and_then(|line| line.split_whitespace().last())
+        .map(str::to_string)
+        .context("MachineGuid not found in the registry")
+}
+
+#[cfg(not(windows))]
+fn machine_secret() -> Result<String> {
+    for path in ["/etc/machine-id", "/var/lib/dbus/machine-id"] {
+        if let Ok(id) = std::fs::read_to_string(path) {
+            if !id.trim().is_empty() {
+                return Ok(id.trim().to_string());
+            }
+        }
+    }
+    let output = std::process::Command::new("ioreg").args(["-rd1", "-c", "IOPlatformExpertDevice"]).output();
+    output
+        .ok()
+        .and_then(|out| {
+            let text = String::from_utf8_lossy(&out.stdout).to_string();
+            let line = text.lines().find(|l| l.contains("IOPlatformUUID"))?.to_string();
+            Some(line.rsplit('"').nth(1)?.to_string())
+        })
+        .context("No machine id on this system; use \"key\": \"passphrase\"")
+}
+
+/// Context of block `index` of the file cached under `key`; the final block
+/// is marked so a cut-off file doesn't pass for a whole one
+fn block_context(key: &str, index: u64, last: bool) -> Vec<u8> {
+    let mut context = key.as_bytes().to_vec();
+    context.extend_from_slice(&index.to_le_bytes());
+    context.push(last as u8);
+    context
+}
+
+/// Plain length of a file of sealed blocks; `None` if its last block is
+/// too short to be one (cut off or not sealed)
+pub fn plain_len(sealed_len: u64) -> Option<u64> {
+    let block = (BLOCK_SIZE + OVERHEAD) as u64;
+    let last = sealed_len % block;
+    if last != 0 && last < OVERHEAD as u64 {
+        return None;
+    }
+    Some(sealed_len - sealed_len.div_ceil(block) * OVERHEAD as u64)
+}
+
+/// Writer of a cached file: passes bytes through, or seals them in blocks
+/// of [`BLOCK_SIZE`] when the namespace is encrypted
+pub struct SealedWriter<W: Write> {
+    inner: W,
+    cipher: Option<CacheCipher>,
+    key: String,
+    pending: Vec<u8>,
+    index: u64,
+}
+
+impl<W: Write> SealedWriter<W> {
+    pub fn new(inner: W, cipher: Option<CacheCipher>, key: &str) -> Self {
+        Self { inner, cipher, key: key.to_string(), pending: Vec::new(), index: 0 }
+    }
+
+    /// Seal what's left as the final block and hand back the inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(cipher) = &self.cipher {
+            let sealed = cipher.seal(&block_context(&self.key, self.index, true), &self.pending);
+            self.inner.write_all(&sealed)?;
+        }
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for SealedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(cipher) = &self.cipher else {
+            return self.inner.write(buf);
+        };
+        self.pending.extend_from_slice(buf);
+        // A full block is sealed only once more data follows it: the last one is marked
+        while self.pending.len() > BLOCK_SIZE {
+            let rest = self.pending.split_off(BLOCK_SIZE);
+            let sealed = cipher.seal(&block_context(&self.key, self.index, false), &self.pending);
+            self.inner.write_all(&sealed)?;
+            self.pending = rest;
+            self.index += 1;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Blocks of a sealed cached file, opened one by one
+pub struct SealedReader<R: Read> {
+    inner: R,
+    cipher: CacheCipher,
+    key: String,
+    index: u64,
+    done: bool,
+}
+
+impl<R: Read> SealedReader<R> {
+    pub fn new(inner: R, cipher: CacheCipher, key: &str) -> Self {
+        Self { inner, cipher, key: key.to_string(), index: 0, done: false }
+    }
+}
+
+impl<R: Read> Iterator for SealedReader<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut block = Vec::with_capacity(BLOCK_SIZE + OVERHEAD);
+        if let Err(e) = (&mut self.inner).take((BLOCK_SIZE + OVERHEAD) as u64).read_to_end(&mut block) {
+            self.done = true;
+            return Some(Err(e));
+        }
+        let opened = self
+            .cipher
+            .open(&block_context(&self.key, self.index, false), &block)
+            .or_else(|| {
+                self.done = true;
+                self.cipher.open(&block_context(&self.key, self.index, true), &block)
+            });
+        self.index += 1;
+        match opened {
+            Some(plain) => Some(Ok(plain)),
+            None => {
+                self.done = true;
+                Some(Err(io::Error::new(io::ErrorKind::InvalidData, "cached file doesn't decrypt (altered or cut off)")))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_seal_values_and_files() {
+        let salt = [7u8; 16];
+        let cipher = CacheCipher::from_passphrase("correct horse", &salt, GATEWAY).unwrap();
+        let sealed = cipher.seal(b"req_1", b"{\"rows\": 42}");
+        assert!(!sealed.windows(4).any(|w| w == b"rows"));
+        assert_eq!(cipher.open(b"req_1", &sealed).unwrap(), b"{\"rows\": 42}");
+        assert!(cipher.open(b"req_2", &sealed).is_none(), "bound to its cache key");
+        let other = CacheCipher::from_passphrase("wrong", &salt, GATEWAY).unwrap();
+        assert!(other.open(b"req_1", &sealed).is_none());
+
+        let data: Vec<u8> = (0..BLOCK_SIZE * 2 + 100).map(|i| (i % 251) as u8).collect();
+        let mut writer = SealedWriter::new(Vec::new(), Some(cipher.clone()), "big");
+        for chunk in data.chunks(10_000) {
+            writer.write_all(chunk).unwrap();
+        }
+        let file = writer.finish().unwrap();
+        assert_eq!(plain_len(file.len() as u64), Some(data.len() as u64));
+        assert_eq!(plain_len(OVERHEAD as u64 - 1), None);
+        assert_eq!(plain_len((BLOCK_SIZE + OVERHEAD + 5) as u64), None);
+        let read: Vec<u8> = SealedReader::new(&file[..], cipher.clone(), "big").flat_map(Result::unwrap).collect();
+        assert_eq!(read, data);
+        // Dropping the last block is noticed
+        let cut = &file[..file.len() - 100 - OVERHEAD];
+        assert!(SealedReader::new(cut, cipher.clone(), "big").any(|block| block.is_err()));
+
+        let dir = tempdir().unwrap();
+        assert!(key_changed(dir.path(), GATEWAY, Some(&cipher)).unwrap());
+        assert!(!key_changed(dir.path(), GATEWAY, Some(&cipher)).unwrap());
+        assert!(key_changed(dir.path(), GATEWAY, Some(&other)).unwrap());
+        assert!(key_changed(dir.path(), GATEWAY, None).unwrap());
+        assert!(!key_changed(dir.path(), GATEWAY, None).unwrap());
+
+        let config = CacheEncryptionConfig { namespaces: vec![GATEWAY.to_string()], key: KeySource::Passphrase };
+        assert!(cipher_for(dir.path(), LAUNCHER_CACHE, &config).unwrap().is_none());
+    }
+}
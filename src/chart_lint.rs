@@ -0,0 +1,343 @@
+//! Chart params checked against their datasets
+//!
+//! When a CSV is reloaded with renamed or dropped columns, Superset keeps the
+//! charts built on them and shows them blank or with a vague query error.
+//! `dashboard lint` reads each chart's `params`, collects the columns and
+//! saved metrics it refers to (group-bys, series, time column, metric
+//! columns, simple filters) and reports those its dataset's `table_columns`
+//! and `sql_metrics` no longer have. SQL expressions are not parsed.
+
+use anyhow::{bail, Context, Result};
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+/// Params keys holding column names (a name, or a list of them)
+const COLUMN_FIELDS: &[&str] = &[
+    "groupby",
+    "columns",
+    "all_columns",
+    "all_columns_x",
+    "all_columns_y",
+    "granularity_sqla",
+    "x_axis",
+    "series",
+    "entity",
+    "groupbyRows",
+    "groupbyColumns",
+];
+
+/// Params keys holding metrics (saved metric names or ad-hoc metrics)
+const METRIC_FIELDS: &[&str] = &[
+    "metrics",
+    "metric",
+    "metric_2",
+    "percent_metrics",
+    "timeseries_limit_metric",
+    "secondary_metric",
+    "x",
+    "y",
+    "size",
+];
+
+/// What a chart refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefKind {
+    Column,
+    /// Saved metric of the dataset
+    Metric,
+}
+
+/// Name used by a chart in one of its params fields
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Reference {
+    field: String,
+    name: String,
+    kind: RefKind,
+}
+
+/// What is wrong with a chart
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "problem", rename_all = "snake_case")]
+pub enum Problem {
+    /// `field` names something the dataset doesn't have
+    Missing { field: String, name: String, kind: RefKind },
+    /// The dataset itself is gone
+    NoDataset,
+    /// `params` is not valid JSON
+    BadParams,
+}
+
+/// One finding
+#[derive(Debug, Clone, Serialize)]
+pub struct LintIssue {
+    pub chart_id: i64,
+    pub chart: String,
+    pub dataset: String,
+    #[serde(flatten)]
+    pub problem: Problem,
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.problem {
+            Problem::Missing { field, name, kind } => {
+                let what = match kind {
+                    RefKind::Column => "колонки",
+                    RefKind::Metric => "метрики",
+                };
+                write!(f, "{}: нет {} «{}» в датасете {} (поле {})", self.chart, what, name, self.dataset, field)
+            }
+            Problem::NoDataset => write!(f, "{}: датасет {} удалён", self.chart, self.dataset),
+            Problem::BadParams => write!(f, "{}: параметры графика не читаются как JSON", self.chart),
+        }
+    }
+}
+
+/// Columns and saved metrics of a dataset
+#[derive(Debug, Default)]
+struct Dataset {
+    name: String,
+    columns: HashSet<String>,
+    metrics: HashSet<String>,
+}
+
+fn has_table(conn: &Connection, table: &str) -> Result<bool> {
+    Ok(conn
+        .query_row("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1", [table], |_| Ok(()))
+        .optional()?
+        .is_some())
+}
+
+fn load_dataset(conn: &Connection, id: i64) -> Result<Option<Dataset>> {
+    let Some(name) = conn
+        .query_row("SELECT table_name FROM tables WHERE id = ?1", [id], |r| r.get::<_, String>(0))
+        .optional()?
+    else {
+        return Ok(None);
+    };
+    let names = |sql: &str| -> Result<HashSet<String>> {
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map([id], |r| r.get::<_, String>(0))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    };
+    let columns = names("SELECT column_name FROM table_columns WHERE table_id = ?1")?;
+    let metrics = if has_table(conn, "sql_metrics")? {
+        names("SELECT metric_name FROM sql_metrics WHERE table_id = ?1")?
+    } else {
+        HashSet::new()
+    };
+    Ok(Some(Dataset { name, columns, metrics }))
+}
+
+/// Entries of a params field that may hold one value or a list
+fn entries(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Array(items) => items.iter().collect(),
+        Value::Null => Vec::new(),
+        other => vec![other],
+    }
+}
+
+/// Column of a simple ad-hoc metric or column (`None` for SQL expressions)
+fn adhoc_column(value: &Value) -> Option<&str> {
+    if value.get("expressionType").and_then(Value::as_str) == Some("SQL") {
+        return None;
+    }
+    value
+        .get("column")
+        .and_then(|c| c.get("column_name"))
+        .or_else(|| value.get("column_name"))
+        .and_then(Value::as_str)
+}
+
+/// Columns and metrics a chart's params refer to
+fn references(params: &Value) -> Vec<Reference> {
+    let mut refs = Vec::new();
+    let mut add = |field: &str, name: &str, kind: RefKind| {
+        // `__timestamp` and friends are Superset's own pseudo-columns
+        if !name.is_empty() && !name.starts_with("__") {
+            refs.push(Reference { field: field.to_string(), name: name.to_string(), kind });
+        }
+    };
+    for field in COLUMN_FIELDS {
+        for entry in params.get(*field).map(entries).unwrap_or_default() {
+            match entry {
+                Value::String(name) => add(field, name, RefKind::Column),
+                other => {
+                    if let Some(name) = adhoc_column(other) {
+                        add(field, name, RefKind::Column);
+                    }
+                }
+            }
+        }
+    }
+    for field in METRIC_FIELDS {
+        for entry in params.get(*field).map(entries).unwrap_or_default() {
+            match entry {
+                Value::String(name) => add(field, name, RefKind::Metric),
+                other => {
+                    if let Some(name) = adhoc_column(other) {
+                        add(field, name, RefKind::Column);
+                    }
+                }
+            }
+        }
+    }
+    for filter in params.get("adhoc_filters").map(entries).unwrap_or_default() {
+        if filter.get("expressionType").and_then(Value::as_str) == Some("SIMPLE") {
+            if let Some(subject) = filter.get("subject").and_then(Value::as_str) {
+                add("adhoc_filters", subject, RefKind::Column);
+            }
+        }
+    }
+    refs
+}
+
+/// Check every chart in superset.db, or only those on `dashboard` (slug or id)
+pub fn lint(superset_db: &Path, dashboard: Option<&str>) -> Result<Vec<LintIssue>> {
+    if !superset_db.exists() {
+        bail!("superset.db not found at {}", superset_db.display());
+    }
+    let conn = Connection::open_with_flags(superset_db, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open {}", superset_db.display()))?;
+
+    let mut sql = "SELECT s.id, s.slice_name, s.datasource_id, s.params FROM slices s".to_string();
+    if let Some(dashboard) = dashboard {
+        let found: Option<i64> = conn
+            .query_row(
+                "SELECT id FROM dashboards WHERE slug = ?1 OR CAST(id AS TEXT) = ?1",
+                [dashboard],
+                |r| r.get(0),
+            )
+            .optional()?;
+        let Some(id) = found else {
+            bail!("Dashboard '{}' not found", dashboard);
+        };
+        sql.push_str(&format!(
+            " JOIN dashboard_slices ds ON ds.slice_id = s.id WHERE ds.dashboard_id = {} AND",
+            id
+        ));
+    } else {
+        sql.push_str(" WHERE");
+    }
+    sql.push_str(" COALESCE(s.datasource_type, 'table') = 'table' ORDER BY s.id");
+
+    let mut stmt = conn.prepare(&sql)?;
+    let charts: Vec<(i64, Option<String>, Option<i64>, Option<String>)> = stmt
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut datasets: HashMap<i64, Option<Dataset>> = HashMap::new();
+    let mut issues = Vec::new();
+    for (chart_id, name, dataset_id, params) in charts {
+        let chart = name.unwrap_or_else(|| format!("#{}", chart_id));
+        let dataset_id = dataset_id.unwrap_or_default();
+        if !datasets.contains_key(&dataset_id) {
+            datasets.insert(dataset_id, load_dataset(&conn, dataset_id)?);
+        }
+        let issue = |dataset: &str, problem| LintIssue { chart_id, chart: chart.clone(), dataset: dataset.to_string(), problem };
+        let Some(dataset) = &datasets[&dataset_id] else {
+            issues.push(issue(&format!("#{}", dataset_id), Problem::NoDataset));
+            continue;
+        };
+        let params: Value = match serde_json::from_str(params.as_deref().unwrap_or("{}")) {
+            Ok(params) => params,
+            Err(_) => {
+                issues.push(issue(&dataset.name, Problem::BadParams));
+                continue;
+            }
+        };
+        let mut seen = HashSet::new();
+        for Reference { field, name, kind } in references(&params) {
+            let known = match kind {
+                RefKind::Column => dataset.columns.contains(&name),
+                RefKind::Metric => dataset.metrics.contains(&name),
+            };
+            if !known && seen.insert((kind, name.clone())) {
+                issues.push(issue(&dataset.name, Problem::Missing { field, name, kind }));
+            }
+        }
+    }
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_references() {
+        let params = json!({
+            "groupby": ["region", {"expressionType": "SQL", "sqlExpression": "upper(x)", "label": "X"}],
+            "granularity_sqla": "date",
+            "x_axis": "__timestamp",
+            "metrics": [
+                "count",
+                {"expressionType": "SIMPLE", "aggregate": "SUM", "column": {"column_name": "revenue"}},
+                {"expressionType": "SQL", "sqlExpression": "SUM(a)/SUM(b)"}
+            ],
+            "metric": "avg_delay",
+            "adhoc_filters": [{"expressionType": "SIMPLE", "subject": "station", "operator": "==", "comparator": "A"}],
+        });
+        let refs: Vec<(String, RefKind)> = references(&params).into_iter().map(|r| (r.name, r.kind)).collect();
+        assert_eq!(
+            refs,
+            vec![
+                ("region".to_string(), RefKind::Column),
+                ("date".to_string(), RefKind::Column),
+                ("count".to_string(), RefKind::Metric),
+                ("revenue".to_string(), RefKind::Column),
+                ("avg_delay".to_string(), RefKind::Metric),
+                ("station".to_string(), RefKind::Column),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_finds_renamed_columns() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("superset.db");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE tables (id INTEGER PRIMARY KEY, table_name TEXT);
+             CREATE TABLE table_columns (id INTEGER PRIMARY KEY, table_id INTEGER, column_name TEXT);
+             CREATE TABLE sql_metrics (id INTEGER PRIMARY KEY, table_id INTEGER, metric_name TEXT);
+             CREATE TABLE slices (id INTEGER PRIMARY KEY, slice_name TEXT, datasource_type TEXT,
+                datasource_id INTEGER, params TEXT);
+             CREATE TABLE dashboards (id INTEGER PRIMARY KEY, slug TEXT);
+             CREATE TABLE dashboard_slices (id INTEGER PRIMARY KEY, dashboard_id INTEGER, slice_id INTEGER);
+             INSERT INTO tables VALUES (1, 'ops');
+             INSERT INTO table_columns (table_id, column_name) VALUES (1, 'date'), (1, 'region_name'), (1, 'revenue');
+             INSERT INTO sql_metrics (table_id, metric_name) VALUES (1, 'count');
+             INSERT INTO slices VALUES (1, 'Доли', 'table', 1, '{\"groupby\": [\"region\"], \"metrics\": [\"count\"]}');
+             INSERT INTO slices VALUES (2, 'Выручка', 'table', 1,
+                '{\"granularity_sqla\": \"date\", \"metrics\": [{\"column\": {\"column_name\": \"revenue\"}}]}');
+             INSERT INTO slices VALUES (3, 'Сирота', 'table', 9, '{}');
+             INSERT INTO slices VALUES (4, 'Битый', 'table', 1, '{not json');
+             INSERT INTO dashboards VALUES (1, 'ops');
+             INSERT INTO dashboard_slices (dashboard_id, slice_id) VALUES (1, 1), (1, 2);",
+        )
+        .unwrap();
+
+        let issues = lint(&path, None).unwrap();
+        let problems: Vec<(i64, &Problem)> = issues.iter().map(|i| (i.chart_id, &i.problem)).collect();
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert_eq!(
+            problems[0],
+            (1, &Problem::Missing { field: "groupby".into(), name: "region".into(), kind: RefKind::Column })
+        );
+        assert_eq!(problems[1], (3, &Problem::NoDataset));
+        assert_eq!(problems[2], (4, &Problem::BadParams));
+        assert!(issues[0].to_string().contains("нет колонки «region» в датасете ops"));
+
+        assert_eq!(lint(&path, Some("ops")).unwrap().len(), 1, "only the dashboard's charts");
+        assert!(lint(&path, Some("missing")).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+//! Shell completions and man pages for the launcher CLI
+//!
+//! `completions <shell>` prints a completion script for the whole command
+//! tree; with `--install` the script is put where the shell finds it on this
+//! computer (bash-completion's user folder, or a script dot-sourced from the
+//! PowerShell profile), so the stick itself is never referenced by path and
+//! a changed drive letter doesn't break it. `--generate-docs` writes one man
+//! page per (sub)command, `superset-launcher-data-rollback.1` and so on.
+
+use anyhow::{bail, Context, Result};
+use clap::Command;
+use clap_complete::Shell;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const BIN_NAME: &str = "superset-launcher";
+
+/// Line that marks our block in a PowerShell profile
+const PROFILE_MARKER: &str = "# superset-launcher completions";
+
+/// Print the completion script for `shell` to stdout
+pub fn print(mut cli: Command, shell: Shell) {
+    clap_complete::generate(shell, &mut cli, BIN_NAME, &mut std::io::stdout());
+}
+
+fn script(mut cli: Command, shell: Shell) -> Vec<u8> {
+    let mut out = Vec::new();
+    clap_complete::generate(shell, &mut cli, BIN_NAME, &mut out);
+    out
+}
+
+fn home_dir() -> Result<PathBuf> {
+    let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    std::env::var_os(var)
+        .map(PathBuf::from)
+        .with_context(|| format!("{} is not set", var))
+}
+
+/// Install the completion script for `shell` for the current user; returns
+/// the files written
+pub fn install(cli: Command, shell: Shell) -> Result<Vec<PathBuf>> {
+    install_into(&home_dir()?, cli, shell)
+}
+
+fn install_into(home: &Path, cli: Command, shell: Shell) -> Result<Vec<PathBuf>> {
+    match shell {
+        Shell::Bash => {
+            let dir = home.join(".local/share/bash-completion/completions");
+            fs::create_dir_all(&dir)?;
+            let path = dir.join(BIN_NAME);
+            fs::write(&path, script(cli, shell))?;
+            Ok(vec![path])
+        }
+        Shell::PowerShell => {
+            let script = script(cli, shell);
+            let mut written = Vec::new();
+            // Windows PowerShell 5 and PowerShell 7 keep separate profiles
+            for edition in ["WindowsPowerShell", "PowerShell"] {
+                let dir = home.join("Documents").join(edition);
+                fs::create_dir_all(&dir)?;
+                let path = dir.join(format!("{}-completion.ps1", BIN_NAME));
+                fs::write(&path, &script)?;
+                written.push(path.clone());
+
+                let profile = dir.join("Microsoft.PowerShell_profile.ps1");
+                let mut content = fs::read_to_string(&profile).unwrap_or_default();
+                if !content.contains(PROFILE_MARKER) {
+                    if !content.is_empty() && !content.ends_with('\n') {
+                        content.push('\n');
+                    }
+                    content.push_str(&format!("{}\n. '{}'\n", PROFILE_MARKER, path.display()));
+                    fs::write(&profile, content)?;
+                    written.push(profile);
+                }
+            }
+            Ok(written)
+        }
+        other => bail!(
+            "--install supports bash and powershell; for {} save the script yourself: {} completions {} > <file>",
+            other,
+            BIN_NAME,
+            other
+        ),
+    }
+}
+
+/// Write man pages for the command and every subcommand into `dir`;
+/// returns the pages written
+pub fn generate_man_pages(cli: Command, dir: &Path) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+    let mut cli = cli.name(BIN_NAME);
+    cli.build();
+    let mut written = Vec::new();
+    write_man_page(&cli, BIN_NAME, dir, &mut written)?;
+    Ok(written)
+}
+
+fn write_man_page(cmd: &Command, name: &str, dir: &Path, written: &mut Vec<PathBuf>) -> Result<()> {
+    let path = dir.join(format!("{}.1", name));
+    let mut page = Vec::new();
+    clap_mangen::Man::new(cmd.clone().name(name.to_string())).render(&mut page)?;
+    fs::write(&path, page)?;
+    written.push(path);
+    for sub in cmd.get_subcommands().filter(|sub| sub.get_name() != "help") {
+        write_man_page(sub, &format!("{}-{}", name, sub.get_name()), dir, written)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn cli() -> Command {
+        Command::new(BIN_NAME)
+            .subcommand(Command::new("data").subcommand(Command::new("rollback").arg(clap::Arg::new("snapshot"))))
+            .subcommand(Command::new("status"))
+    }
+
+    #[test]
+    fn test_install_is_idempotent() {
+        let home = tempdir().unwrap();
+        let first = install_into(home.path(), cli(), Shell::PowerShell).unwrap();
+        assert_eq!(first.len(), 4);
+        let second = install_into(home.path(), cli(), Shell::PowerShell).unwrap();
+        assert_eq!(second.len(), 2, "profiles are edited once");
+
+        let profile = home.path().join("Documents/WindowsPowerShell/Microsoft.PowerShell_profile.ps1");
+        assert_eq!(fs::read_to_string(profile).unwrap().matches(PROFILE_MARKER).count(), 1);
+
+        let bash = install_into(home.path(), cli(), Shell::Bash).unwrap();
+        assert!(fs::read_to_string(&bash[0]).unwrap().contains("rollback"));
+        assert!(install_into(home.path(), cli(), Shell::Zsh).is_err());
+    }
+
+    #[test]
+    fn test_man_pages_per_subcommand() {
+        let dir = tempdir().unwrap();
+        let pages = generate_man_pages(cli(), dir.path()).unwrap();
+        let names: Vec<_> = pages.iter().map(|p| p.file_name().unwrap().to_string_lossy().to_string()).collect();
+        assert!(names.contains(&"superset-launcher.1".to_string()));
+        assert!(names.contains(&"superset-launcher-data-rollback.1".to_string()));
+        assert!(names.contains(&"superset-launcher-status.1".to_string()));
+    }
+}
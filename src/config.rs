@@ -4,6 +4,24 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 
+use crate::backup::BackupConfig;
+use crate::cache_crypto::CacheEncryptionConfig;
+use crate::credentials::CredentialsConfig;
+use crate::databases::DatabaseConfig;
+use crate::drive_watchdog::DriveWatchdogConfig;
+use crate::freshness::FreshnessConfig;
+use crate::gateway::GatewayConfig;
+use crate::hardware::HardwareConfig;
+use crate::log_shipping::LogShippingConfig;
+use crate::notifications::SinkConfig;
+use crate::packer::PackConfig;
+use crate::python::EnvConfig;
+use crate::query_export::QueryExportConfig;
+use crate::query_limits::QueryLimitsConfig;
+use crate::replica::ReplicaConfig;
+use crate::security_headers::SecurityConfig;
+use crate::snapshots::SnapshotConfig;
+
 const CONFIG_FILE: &str = "config.json";
 
 /// Launcher configuration
@@ -19,6 +37,82 @@ pub struct Config {
     pub python_path: String,
     /// Superset home directory (relative to root)
     pub superset_home: String,
+    /// How long to wait for Superset's /health after start (slow flash drives need 60-120 s)
+    #[serde(default = "default_startup_timeout")]
+    pub startup_timeout_secs: u64,
+    /// Automatic backup settings
+    #[serde(default)]
+    pub backup: BackupConfig,
+    /// Gateway settings (landing dashboard)
+    #[serde(default)]
+    pub gateway: GatewayConfig,
+    /// Extra analytics SQLite files registered as Superset databases
+    #[serde(default)]
+    pub databases: Vec<DatabaseConfig>,
+    /// Where the admin password and API tokens are kept
+    #[serde(default)]
+    pub credentials: CredentialsConfig,
+    /// Read-only snapshots Superset queries instead of the live files
+    #[serde(default)]
+    pub replica: ReplicaConfig,
+    /// Desktop notifications when services start, crash or refresh data
+    #[serde(default = "default_notifications")]
+    pub notifications: bool,
+    /// Where notifications go besides the desktop (SMTP relay, webhook, drop folder)
+    #[serde(default)]
+    pub notification_sinks: Vec<SinkConfig>,
+    /// CORS and security headers on every server (iframe embedding)
+    #[serde(default)]
+    pub security: SecurityConfig,
+    /// When loaded tables count as stale in the launcher UI
+    #[serde(default)]
+    pub freshness: FreshnessConfig,
+    /// Extra environment variables for Superset (pool settings, proxies)
+    #[serde(default)]
+    pub env: EnvConfig,
+    /// Pause services when the flash drive disappears
+    #[serde(default)]
+    pub drive_watchdog: DriveWatchdogConfig,
+    /// Memory a data load may use; bigger CSV files are read in batches.
+    /// Unset means the hardware profile's budget
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub load_memory_mb: Option<u64>,
+    /// Tuning profile; `auto` picks one from the CPU, RAM and drive
+    #[serde(default)]
+    pub hardware: HardwareConfig,
+    /// Copies of data files taken before loads replace their tables
+    #[serde(default)]
+    pub snapshots: SnapshotConfig,
+    /// CSV/XLSX export of query results from the launcher UI API
+    #[serde(default)]
+    pub query_export: QueryExportConfig,
+    /// What `pack` puts into a release
+    #[serde(default)]
+    pub pack: PackConfig,
+    /// Copy logs to a network drop folder for a central collector
+    #[serde(default)]
+    pub log_shipping: LogShippingConfig,
+    /// Caches whose values are encrypted at rest
+    #[serde(default)]
+    pub cache_encryption: CacheEncryptionConfig,
+    /// Row limits and query timeouts written into superset_config.py
+    #[serde(default)]
+    pub query_limits: QueryLimitsConfig,
+    /// Compress launcher UI responses (gzip or zstd, as the browser asks)
+    #[serde(default = "default_ui_compression")]
+    pub ui_compression: bool,
+}
+
+fn default_startup_timeout() -> u64 {
+    180
+}
+
+fn default_notifications() -> bool {
+    true
+}
+
+fn default_ui_compression() -> bool {
+    true
 }
 
 impl Default for Config {
@@ -27,21 +121,50 @@ impl Default for Config {
             port: 8088,
             open_browser: true,
             host: "127.0.0.1".to_string(),
-            python_path: "python/python.exe".to_string(),
+            python_path: if cfg!(windows) { "python/python.exe" } else { "python/bin/python3" }.to_string(),
             superset_home: "superset_home".to_string(),
+            startup_timeout_secs: default_startup_timeout(),
+            backup: BackupConfig::default(),
+            gateway: GatewayConfig::default(),
+            databases: Vec::new(),
+            credentials: CredentialsConfig::default(),
+            replica: ReplicaConfig::default(),
+            notifications: default_notifications(),
+            notification_sinks: Vec::new(),
+            security: SecurityConfig::default(),
+            freshness: FreshnessConfig::default(),
+            env: EnvConfig::default(),
+            drive_watchdog: DriveWatchdogConfig::default(),
+            load_memory_mb: None,
+            hardware: HardwareConfig::default(),
+            snapshots: SnapshotConfig::default(),
+            query_export: QueryExportConfig::default(),
+            pack: PackConfig::default(),
+            log_shipping: LogShippingConfig::default(),
+            cache_encryption: CacheEncryptionConfig::default(),
+            query_limits: QueryLimitsConfig::default(),
+            ui_compression: default_ui_compression(),
         }
     }
 }
 
 impl Config {
+    /// Load config from file, or the defaults without writing them
+    pub fn load(root: &Path) -> Result<Self> {
+        let config_path = root.join(CONFIG_FILE);
+        if !config_path.exists() {
+            return Ok(Config::default());
+        }
+        let content = std::fs::read_to_string(&config_path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
     /// Load config from file or create default
     pub fn load_or_create(root: &Path) -> Result<Self> {
         let config_path = root.join(CONFIG_FILE);
         
         if config_path.exists() {
-            let content = std::fs::read_to_string(&config_path)?;
-            let config: Config = serde_json::from_str(&content)?;
-            Ok(config)
+            Self::load(root)
         } else {
             let config = Config::default();
             config.save(root)?;
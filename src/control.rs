@@ -0,0 +1,267 @@
+//! Local control channel of a running launcher
+//!
+//! The tray, the launcher UI and scripts drive the long-running launcher
+//! through line-delimited JSON-RPC 2.0 on a named pipe (Windows) or a Unix
+//! socket in the kit root; only local processes can connect. Every request
+//! is handed as a [`ControlRequest`] to the task that owns the services,
+//! which answers on its own runtime. Tray menu callbacks run outside tokio
+//! and use the same channel, so they reach the services too. The endpoint
+//! name is written to `control.endpoint` for third-party scripts.
+//!
+//! ```text
+//! -> {"jsonrpc":"2.0","id":1,"method":"status"}
+//! <- {"jsonrpc":"2.0","id":1,"result":{...}}
+//! ```
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+use std::path::Path;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{info, warn};
+
+/// File in the kit root holding the endpoint name
+pub const ENDPOINT_FILE: &str = "control.endpoint";
+
+/// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// What a client asks the launcher to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlCommand {
+    /// Answered by the channel itself: launcher pid and version
+    Ping,
+    Status,
+    /// Start Superset
+    Start,
+    /// Stop Superset
+    Stop,
+    /// Stop everything and exit
+    Shutdown,
+}
+
+impl ControlCommand {
+    pub const ALL: &'static [ControlCommand] = &[
+        ControlCommand::Ping,
+        ControlCommand::Status,
+        ControlCommand::Start,
+        ControlCommand::Stop,
+        ControlCommand::Shutdown,
+    ];
+
+    /// JSON-RPC method name
+    pub fn method(&self) -> &'static str {
+        match self {
+            ControlCommand::Ping => "ping",
+            ControlCommand::Status => "status",
+            ControlCommand::Start => "start",
+            ControlCommand::Stop => "stop",
+            ControlCommand::Shutdown => "shutdown",
+        }
+    }
+
+    pub fn from_method(method: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|c| c.method() == method)
+    }
+}
+
+/// Request handed to the owner of the services
+#[derive(Debug)]
+pub struct ControlRequest {
+    pub command: ControlCommand,
+    /// Result, or an error message for the client
+    pub reply: oneshot::Sender<Result<Value, String>>,
+}
+
+impl ControlRequest {
+    /// Request nobody waits on (tray menu items)
+    pub fn detached(command: ControlCommand) -> Self {
+        let (reply, _) = oneshot::channel();
+        Self { command, reply }
+    }
+}
+
+/// Pipe or socket path for the kit at `root`
+pub fn endpoint(root: &Path) -> String {
+    #[cfg(windows)]
+    {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        let id = uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_URL, root.to_string_lossy().to_lowercase().as_bytes());
+        format!(r"\\.\pipe\superset-portable-{}", &id.simple().to_string()[..16])
+    }
+    #[cfg(not(windows))]
+    {
+        root.join("launcher.sock").to_string_lossy().to_string()
+    }
+}
+
+/// Listen on the kit's endpoint and forward requests to `requests`
+pub fn spawn(root: &Path, requests: mpsc::Sender<ControlRequest>) -> Result<()> {
+    let endpoint = endpoint(root);
+    listen(&endpoint, requests).with_context(|| format!("Cannot open control channel {}", endpoint))?;
+    if let Err(e) = std::fs::write(root.join(ENDPOINT_FILE), &endpoint) {
+        warn!("Failed to write {}: {}", ENDPOINT_FILE, e);
+    }
+    info!("🎛️ Control channel: {}", endpoint);
+    Ok(())
+}
+
+#[cfg(unix)]
+fn listen(endpoint: &str, requests: mpsc::Sender<ControlRequest>) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // A socket left by a launcher that crashed blocks bind
+    let _ = std::fs::remove_file(endpoint);
+    let listener = tokio::net::UnixListener::bind(endpoint)?;
+    std::fs::set_permissions(endpoint, std::fs::Permissions::from_mode(0o600))?;
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(serve(stream, requests.clone()));
+                }
+                Err(e) => {
+                    warn!("Control channel stopped: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(windows)]
+fn listen(endpoint: &str, requests: mpsc::Sender<ControlRequest>) -> Result<()> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let endpoint = endpoint.to_string();
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .reject_remote_clients(true)
+        .create(&endpoint)?;
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = server.connect().await {
+                warn!("Control channel stopped: {}", e);
+                break;
+            }
+            // The next client needs a fresh pipe instance
+            let connected = server;
+            server = match ServerOptions::new().reject_remote_clients(true).create(&endpoint) {
+                Ok(next) => next,
+                Err(e) => {
+                    warn!("Control channel stopped: {}", e);
+                    break;
+                }
+            };
+            tokio::spawn(serve(connected, requests.clone()));
+        }
+    });
+    Ok(())
+}
+
+/// Answer requests on one connection until the client hangs up
+async fn serve<S: AsyncRead + AsyncWrite + Unpin>(stream: S, requests: mpsc::Sender<ControlRequest>) {
+    let (read, mut write) = tokio::io::split(stream);
+    let mut lines = BufReader::new(read).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = dispatch(&line, &requests).await.to_string() + "\n";
+        if write.write_all(response.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// JSON-RPC response to one request line
+async fn dispatch(line: &str, requests: &mpsc::Sender<ControlRequest>) -> Value {
+    let error = |id: &Value, code: i64, message: String| {
+        json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+    };
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => return error(&Value::Null, PARSE_ERROR, e.to_string()),
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let method = request.get("method").and_then(Value::as_str).unwrap_or_default();
+    let Some(command) = ControlCommand::from_method(method) else {
+        return error(&id, METHOD_NOT_FOUND, format!("Unknown method '{}'", method));
+    };
+
+    let result = if command == ControlCommand::Ping {
+        Ok(json!({"pid": std::process::id(), "version": env!("CARGO_PKG_VERSION")}))
+    } else {
+        let (reply, answer) = oneshot::channel();
+        if requests.send(ControlRequest { command, reply }).await.is_err() {
+            Err("Launcher is shutting down".to_string())
+        } else {
+            answer.await.unwrap_or_else(|_| Err("No answer from launcher".to_string()))
+        }
+    };
+    match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err(message) => error(&id, INTERNAL_ERROR, message),
+    }
+}
+
+/// Call `method` on the launcher running from `root`
+pub async fn call(root: &Path, method: &str) -> Result<Value> {
+    let endpoint = endpoint(root);
+    #[cfg(windows)]
+    let stream = tokio::net::windows::named_pipe::ClientOptions::new().open(&endpoint);
+    #[cfg(not(windows))]
+    let stream = tokio::net::UnixStream::connect(&endpoint).await;
+    let stream = stream.with_context(|| format!("No running launcher at {}", endpoint))?;
+    exchange(stream, method).await
+}
+
+/// Send one request and read its response
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(stream: S, method: &str) -> Result<Value> {
+    let (read, mut write) = tokio::io::split(stream);
+    let request = json!({"jsonrpc": "2.0", "id": 1, "method": method}).to_string() + "\n";
+    write.write_all(request.as_bytes()).await?;
+    let line = BufReader::new(read).lines().next_line().await?.context("Launcher closed the connection")?;
+    let mut response: Value = serde_json::from_str(&line)?;
+    if let Some(error) = response.get("error") {
+        bail!("{}", error.get("message").and_then(Value::as_str).unwrap_or("error"));
+    }
+    Ok(response["result"].take())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(async {
+            let (tx, mut rx) = mpsc::channel::<ControlRequest>(4);
+            tokio::spawn(async move {
+                while let Some(request) = rx.recv().await {
+                    let answer = match request.command {
+                        ControlCommand::Status => Ok(json!({"superset": "running"})),
+                        _ => Err("not here".to_string()),
+                    };
+                    let _ = request.reply.send(answer);
+                }
+            });
+
+            let (client, server) = tokio::io::duplex(1024);
+            tokio::spawn(serve(server, tx.clone()));
+            assert_eq!(exchange(client, "status").await.unwrap()["superset"], "running");
+
+            let (client, server) = tokio::io::duplex(1024);
+            tokio::spawn(serve(server, tx.clone()));
+            assert!(exchange(client, "stop").await.unwrap_err().to_string().contains("not here"));
+
+            assert_eq!(dispatch("{\"id\":7,\"method\":\"ping\"}", &tx).await["result"]["pid"], std::process::id());
+            assert_eq!(dispatch("{\"id\":7,\"method\":\"reboot\"}", &tx).await["error"]["code"], METHOD_NOT_FOUND);
+            assert_eq!(dispatch("not json", &tx).await["error"]["code"], PARSE_ERROR);
+        });
+    }
+}
@@ -0,0 +1,216 @@
+//! Crash reports instead of a launcher that silently disappears
+//!
+//! [`install`] sets a panic hook that writes `logs/crash-<time>.json` with
+//! the panic message and place, a backtrace, the launcher version, the
+//! command line with password values masked, the last lines the launcher
+//! logged and a summary of config.json (no passwords or paths outside the
+//! kit), then raises a notification — the tray shows it as
+//! a balloon. The launcher UI shows a banner for a report of the last day,
+//! so a panel reopened after a crash says what happened. The previous hook
+//! still runs afterwards, so the panic is printed to the console as before.
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Local};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::notifications::{self, Level};
+
+/// Folder of the reports, relative to root
+const CRASH_DIR: &str = "logs";
+
+const CRASH_PREFIX: &str = "crash-";
+
+/// Reports younger than this are shown in the launcher UI
+const NOTICE_HOURS: i64 = 24;
+
+/// Written instead of a password given on the command line
+const REDACTED: &str = "***";
+
+/// What `logs/crash-<time>.json` holds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub at: String,
+    pub version: String,
+    /// `windows x86_64`
+    pub os: String,
+    pub args: Vec<String>,
+    pub thread: String,
+    pub message: String,
+    /// `src/file.rs:12:5`
+    pub location: Option<String>,
+    pub backtrace: Vec<String>,
+    pub last_log_lines: Vec<String>,
+    pub config: serde_json::Value,
+}
+
+/// The newest recent report, for the launcher UI
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct CrashNotice {
+    /// Report file name in `logs/`
+    pub file: String,
+    pub at: String,
+    pub message: String,
+}
+
+/// Write a report (and notify) on every panic from now on
+pub fn install(root: &Path, config: &Config) {
+    let root = root.to_path_buf();
+    let summary = config_summary(config);
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "(no message)".to_string());
+        let location = info.location().map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+        let report = CrashReport {
+            at: Local::now().to_rfc3339(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            os: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+            args: redact_args(std::env::args()),
+            thread: std::thread::current().name().unwrap_or("unnamed").to_string(),
+            message,
+            location,
+            backtrace: std::backtrace::Backtrace::force_capture().to_string().lines().map(str::to_string).collect(),
+            last_log_lines: crate::log_shipping::recent_lines(),
+            config: summary.clone(),
+        };
+        // Nothing here may panic again: a failed write only loses the report
+        match write(&root, &report) {
+            Ok(path) => notifications::notify(
+                Level::Error,
+                "Сбой лаунчера",
+                &format!("{}. Отчёт: {}", report.message, path.strip_prefix(&root).unwrap_or(&path).display()),
+            ),
+            Err(e) => eprintln!("Crash report not written: {:#}", e),
+        }
+        previous(info);
+    }));
+}
+
+/// Save a report as `logs/crash-<time>.json`
+pub fn write(root: &Path, report: &CrashReport) -> Result<PathBuf> {
+    let dir = root.join(CRASH_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}{}.json", CRASH_PREFIX, Local::now().format("%Y%m%d-%H%M%S-%3f")));
+    std::fs::write(&path, serde_json::to_string_pretty(report)?)?;
+    Ok(path)
+}
+
+/// The newest report written in the last `NOTICE_HOURS` hours
+pub fn latest(root: &Path) -> Option<CrashNotice> {
+    let newest = std::fs::read_dir(root.join(CRASH_DIR))
+        .ok()?
+        .flatten()
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with(CRASH_PREFIX) && name.ends_with(".json"))
+        .max()?;
+    let report: CrashReport = serde_json::from_slice(&std::fs::read(root.join(CRASH_DIR).join(&newest)).ok()?).ok()?;
+    let at = DateTime::parse_from_rfc3339(&report.at).ok()?;
+    if at < Local::now() - Duration::hours(NOTICE_HOURS) {
+        return None;
+    }
+    Some(CrashNotice { file: newest, at: report.at, message: report.message })
+}
+
+/// Command line with the values of `--password`/`-p` masked
+/// (`init --password X` is accepted, if discouraged)
+fn redact_args(args: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut redacted = Vec::new();
+    let mut secret_next = false;
+    for arg in args {
+        if std::mem::take(&mut secret_next) {
+            redacted.push(REDACTED.to_string());
+        } else if arg == "--password" || arg == "-p" {
+            secret_next = true;
+            redacted.push(arg);
+        } else if arg.starts_with("--password=") {
+            redacted.push(format!("--password={}", REDACTED));
+        } else if arg.starts_with("-p") && !arg.starts_with("--") && arg.len() > 2 {
+            redacted.push(format!("-p{}", REDACTED));
+        } else {
+            redacted.push(arg);
+        }
+    }
+    redacted
+}
+
+/// Settings that explain most crashes; credentials and sinks are left out
+fn config_summary(config: &Config) -> serde_json::Value {
+    serde_json::json!({
+        "port": config.port,
+        "host": config.host,
+        "python_path": config.python_path,
+        "superset_home": config.superset_home,
+        "startup_timeout_secs": config.startup_timeout_secs,
+        "hardware_profile": crate::hardware::current().profile,
+        "load_memory_mb": config.load_memory_mb,
+        "databases": config.databases.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(),
+        "replica": config.replica.enabled,
+        "notifications": config.notifications,
+        "drive_watchdog": config.drive_watchdog.enabled,
+        "query_export": config.query_export.enabled,
+        "log_shipping": config.log_shipping.enabled,
+        "ui_compression": config.ui_compression,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn report(at: DateTime<Local>, message: &str) -> CrashReport {
+        CrashReport {
+            at: at.to_rfc3339(),
+            version: "1.0.0".to_string(),
+            os: "windows x86_64".to_string(),
+            args: vec!["superset-launcher.exe".to_string(), "ui".to_string()],
+            thread: "main".to_string(),
+            message: message.to_string(),
+            location: Some("src/main.rs:1:1".to_string()),
+            backtrace: Vec::new(),
+            last_log_lines: vec!["INFO Portable Superset Launcher".to_string()],
+            config: config_summary(&Config::default()),
+        }
+    }
+
+    #[test]
+    fn test_latest_recent_report() {
+        let dir = tempdir().unwrap();
+        assert_eq!(latest(dir.path()), None);
+
+        let path = write(dir.path(), &report(Local::now() - Duration::hours(30), "old")).unwrap();
+        assert!(path.file_name().unwrap().to_string_lossy().starts_with("crash-"));
+        assert_eq!(latest(dir.path()), None);
+
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        write(dir.path(), &report(Local::now(), "index out of bounds")).unwrap();
+        let notice = latest(dir.path()).unwrap();
+        assert_eq!(notice.message, "index out of bounds");
+        let saved: CrashReport =
+            serde_json::from_slice(&std::fs::read(dir.path().join("logs").join(&notice.file)).unwrap()).unwrap();
+        assert_eq!(saved.config["port"], 8088);
+        assert!(saved.config.get("credentials").is_none());
+    }
+
+    #[test]
+    fn test_redact_args() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            redact_args(args(&["launcher", "init", "--password", "hunter2", "--username", "admin"])),
+            args(&["launcher", "init", "--password", "***", "--username", "admin"])
+        );
+        assert_eq!(redact_args(args(&["launcher", "init", "-p", "hunter2"])), args(&["launcher", "init", "-p", "***"]));
+        assert_eq!(redact_args(args(&["launcher", "init", "-phunter2"])), args(&["launcher", "init", "-p***"]));
+        assert_eq!(
+            redact_args(args(&["launcher", "init", "--password=hunter2"])),
+            args(&["launcher", "init", "--password=***"])
+        );
+        assert_eq!(redact_args(args(&["launcher", "ui", "--port", "3000"])), args(&["launcher", "ui", "--port", "3000"]));
+    }
+}
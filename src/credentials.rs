@@ -0,0 +1,279 @@
+//! Admin credentials and launcher API tokens
+//!
+//! Secrets are kept in the Windows Credential Manager (generic credentials
+//! named `superset-portable:<name>`) so they never show up in shell history,
+//! process arguments typed by hand or `config.json`. The vault belongs to the
+//! Windows profile and doesn't travel with the flash drive, so a kit set to
+//! `"credentials": { "portable": true }` — and every non-Windows system —
+//! keeps them in `.credentials.json` in the kit root instead.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+/// Superset admin password
+pub const ADMIN_PASSWORD: &str = "admin_password";
+
+/// Token for launcher UI API calls from other machines (`--lan`)
+pub const LAUNCHER_TOKEN: &str = "launcher_api_token";
+
+/// Copy of Superset's SECRET_KEY (see `secret_key`)
+pub const SUPERSET_SECRET_KEY: &str = "superset_secret_key";
+
+/// SECRET_KEY replaced by the last `rotate-secret`
+pub const SUPERSET_SECRET_KEY_PREVIOUS: &str = "superset_secret_key_previous";
+
+/// Ed25519 key (hex PKCS#8) signing `audit export` files
+pub const AUDIT_SIGNING_KEY: &str = "audit_signing_key";
+
+/// Credential Manager target prefix
+const TARGET_PREFIX: &str = "superset-portable:";
+
+/// Fallback store (relative to root)
+const CREDENTIALS_FILE: &str = ".credentials.json";
+
+/// Length of generated passwords
+const GENERATED_LENGTH: usize = 20;
+
+/// Credential storage settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CredentialsConfig {
+    /// Keep secrets in the kit folder instead of the Windows vault
+    pub portable: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Backend {
+    #[cfg(windows)]
+    WindowsVault,
+    File(PathBuf),
+}
+
+/// Where the launcher keeps its secrets
+#[derive(Debug, Clone)]
+pub struct CredentialStore {
+    backend: Backend,
+}
+
+impl CredentialStore {
+    /// Windows Credential Manager, or the kit file in portable mode / elsewhere
+    pub fn open(root: &Path, config: &CredentialsConfig) -> Self {
+        #[cfg(windows)]
+        if !config.portable {
+            return Self { backend: Backend::WindowsVault };
+        }
+        #[cfg(not(windows))]
+        let _ = config;
+        Self::file(root)
+    }
+
+    /// File-backed store in the kit root
+    pub fn file(root: &Path) -> Self {
+        Self { backend: Backend::File(root.join(CREDENTIALS_FILE)) }
+    }
+
+    /// Human-readable backend name
+    pub fn describe(&self) -> String {
+        match &self.backend {
+            #[cfg(windows)]
+            Backend::WindowsVault => "Windows Credential Manager".to_string(),
+            Backend::File(path) => path.display().to_string(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<Option<String>> {
+        match &self.backend {
+            #[cfg(windows)]
+            Backend::WindowsVault => vault::read(&format!("{}{}", TARGET_PREFIX, name)),
+            Backend::File(path) => Ok(read_file(path)?.remove(name)),
+        }
+    }
+
+    pub fn set(&self, name: &str, secret: &str) -> Result<()> {
+        match &self.backend {
+            #[cfg(windows)]
+            Backend::WindowsVault => vault::write(&format!("{}{}", TARGET_PREFIX, name), secret),
+            Backend::File(path) => {
+                let mut secrets = read_file(path)?;
+                secrets.insert(name.to_string(), secret.to_string());
+                write_file(path, &secrets)
+            }
+        }
+    }
+
+    pub fn delete(&self, name: &str) -> Result<()> {
+        match &self.backend {
+            #[cfg(windows)]
+            Backend::WindowsVault => vault::delete(&format!("{}{}", TARGET_PREFIX, name)),
+            Backend::File(path) => {
+                let mut secrets = read_file(path)?;
+                if secrets.remove(name).is_some() {
+                    write_file(path, &secrets)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Stored secret, or a freshly generated and stored one (`true` when new)
+    pub fn get_or_generate(&self, name: &str) -> Result<(String, bool)> {
+        if let Some(secret) = self.get(name)? {
+            return Ok((secret, false));
+        }
+        let secret = generate_secret();
+        self.set(name, &secret)?;
+        Ok((secret, true))
+    }
+}
+
+/// Random alphanumeric secret, easy to type when it has to be
+pub fn generate_secret() -> String {
+    use rand::{distributions::Alphanumeric, Rng};
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(GENERATED_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// Read a secret from the first line of stdin (`--password-stdin`)
+pub fn read_stdin_secret() -> Result<String> {
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line).context("Не удалось прочитать пароль из stdin")?;
+    let secret = line.trim_end_matches(['\r', '\n']).to_string();
+    if secret.is_empty() {
+        bail!("Пустой пароль в stdin");
+    }
+    Ok(secret)
+}
+
+/// Compare secrets without leaking the mismatch position through timing
+pub fn secrets_match(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn read_file(path: &Path) -> Result<BTreeMap<String, String>> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let content = std::fs::read_to_string(path)?;
+    serde_json::from_str(&content).with_context(|| format!("Повреждён файл {}", path.display()))
+}
+
+fn write_file(path: &Path, secrets: &BTreeMap<String, String>) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string_pretty(secrets)?)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600))?;
+    }
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(windows)]
+mod vault {
+    use anyhow::{bail, Result};
+    use std::ptr;
+    use winapi::shared::minwindef::DWORD;
+    use winapi::shared::winerror::ERROR_NOT_FOUND;
+    use winapi::um::errhandlingapi::GetLastError;
+    use winapi::um::wincred::{
+        CredDeleteW, CredFree, CredReadW, CredWriteW, CREDENTIALW, CRED_PERSIST_LOCAL_MACHINE,
+        CRED_TYPE_GENERIC, PCREDENTIALW,
+    };
+
+    const USER_NAME: &str = "superset-portable";
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+
+    pub fn read(target: &str) -> Result<Option<String>> {
+        let target = wide(target);
+        let mut cred: PCREDENTIALW = ptr::null_mut();
+        // SAFETY: CredReadW fills `cred` on success; it is freed with CredFree below
+        unsafe {
+            if CredReadW(target.as_ptr(), CRED_TYPE_GENERIC, 0, &mut cred) == 0 {
+                let err = GetLastError();
+                if err == ERROR_NOT_FOUND {
+                    return Ok(None);
+                }
+                bail!("CredReadW failed (error {})", err);
+            }
+            let blob = std::slice::from_raw_parts((*cred).CredentialBlob, (*cred).CredentialBlobSize as usize);
+            let secret = String::from_utf8(blob.to_vec());
+            CredFree(cred as *mut _);
+            Ok(Some(secret?))
+        }
+    }
+
+    pub fn write(target: &str, secret: &str) -> Result<()> {
+        let mut target = wide(target);
+        let mut user = wide(USER_NAME);
+        let mut blob = secret.as_bytes().to_vec();
+        // SAFETY: all pointers outlive the call; zeroed CREDENTIALW is a valid empty credential
+        unsafe {
+            let mut cred: CREDENTIALW = std::mem::zeroed();
+            cred.Type = CRED_TYPE_GENERIC;
+            cred.TargetName = target.as_mut_ptr();
+            cred.UserName = user.as_mut_ptr();
+            cred.CredentialBlobSize = blob.len() as DWORD;
+            cred.CredentialBlob = blob.as_mut_ptr();
+            cred.Persist = CRED_PERSIST_LOCAL_MACHINE;
+            if CredWriteW(&mut cred, 0) == 0 {
+                bail!("CredWriteW failed (error {})", GetLastError());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn delete(target: &str) -> Result<()> {
+        let target = wide(target);
+        // SAFETY: `target` is a valid NUL-terminated wide string
+        unsafe {
+            if CredDeleteW(target.as_ptr(), CRED_TYPE_GENERIC, 0) == 0 {
+                let err = GetLastError();
+                if err != ERROR_NOT_FOUND {
+                    bail!("CredDeleteW failed (error {})", err);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_file_store() {
+        let dir = tempdir().unwrap();
+        let store = CredentialStore::file(dir.path());
+        assert_eq!(store.get(ADMIN_PASSWORD).unwrap(), None);
+
+        store.set(ADMIN_PASSWORD, "s3cret").unwrap();
+        assert_eq!(store.get(ADMIN_PASSWORD).unwrap().as_deref(), Some("s3cret"));
+
+        let (token, created) = store.get_or_generate(LAUNCHER_TOKEN).unwrap();
+        assert!(created);
+        assert_eq!(token.len(), GENERATED_LENGTH);
+        assert_eq!(store.get_or_generate(LAUNCHER_TOKEN).unwrap(), (token, false));
+
+        store.delete(ADMIN_PASSWORD).unwrap();
+        assert_eq!(store.get(ADMIN_PASSWORD).unwrap(), None);
+    }
+
+    #[test]
+    fn test_secrets_match() {
+        assert!(secrets_match("abc", "abc"));
+        assert!(!secrets_match("abc", "abd"));
+        assert!(!secrets_match("abc", "abcd"));
+    }
+}
@@ -3,15 +3,168 @@
 //! Uses `polars` for fast reading and schema inference.
 //! Writes to SQLite using batch transactions.
 
-use anyhow::{Context, Result, anyhow};
+use anyhow::{bail, Context, Result, anyhow};
 use polars::prelude::*;
+use rusqlite::types::Value;
 use rusqlite::Connection;
+use serde::Serialize;
 use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::info;
 use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use crate::dates::{self, DateFormat, DateStorage, DATETIME_TYPE, SAMPLE_VALUES};
+use crate::freshness;
+use crate::partitions::{self, Partitioning};
+
+/// Rows read for schema inference in dry-run mode
+const PREVIEW_SCAN_ROWS: usize = 1000;
+
+/// Tables at least this big get indexes on likely filter columns
+pub const AUTO_INDEX_MIN_ROWS: usize = 50_000;
+
+/// Rows sampled when profiling columns for indexing
+const PROFILE_SAMPLE_ROWS: usize = 10_000;
+
+/// A string column with at most this many distinct values is a filter candidate
+const LOW_CARDINALITY_MAX: usize = 500;
+
+/// Longest min/max text kept in a column profile
+const PROFILE_VALUE_CHARS: usize = 60;
+
+/// Start of generated column descriptions, so reloads replace only their own
+pub const PROFILE_DESCRIPTION_PREFIX: &str = "📊 ";
+
+/// CSV reader on the hardware profile's thread count
+fn csv_reader(file_path: &Path) -> PolarsResult<CsvReader<'static, File>> {
+    Ok(CsvReader::from_path(file_path)?.with_n_threads(Some(crate::hardware::current().polars_threads)))
+}
+
+/// Memory a load may use unless config.json says otherwise (`load_memory_mb`)
+pub const DEFAULT_MEMORY_BUDGET_MB: u64 = 1024;
+
+/// Peak memory of a one-shot polars CSV read, per byte of file
+const CSV_MEMORY_FACTOR: u64 = 3;
+
+/// calamine unzips the workbook and keeps every cell: memory per byte of .xlsx
+const EXCEL_MEMORY_FACTOR: u64 = 20;
+
+/// Rows parsed to fix the column types of a CSV loaded in batches
+const STREAM_SCHEMA_ROWS: usize = 10_000;
+
+/// Bounds of the batch size when a CSV is loaded in batches
+const STREAM_MIN_ROWS: u64 = 1_000;
+const STREAM_MAX_ROWS: u64 = 500_000;
+
+/// Most frequent values listed per column by `load-data --preview`
+const PREVIEW_TOP_VALUES: usize = 3;
+
+/// `--preview` suggests down-sampling above this estimated load time…
+const DOWNSAMPLE_LOAD_SECS: u64 = 15 * 60;
+
+/// …or this estimated table size
+const DOWNSAMPLE_DB_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Options for `load_file_with`
+#[derive(Debug, Clone)]
+pub struct LoadOptions {
+    /// Index likely filter columns of large tables
+    pub auto_index: bool,
+    /// Detect date columns and rewrite them in one format
+    pub detect_dates: bool,
+    /// How detected dates are stored
+    pub date_storage: DateStorage,
+    /// Split the table into year/month shards behind a view
+    pub partition: Option<Partitioning>,
+    /// Without `partition`, split a partitioned table again the same way
+    pub keep_partitioning: bool,
+    /// Files that would need more memory are read in batches (CSV) or refused (Excel)
+    pub memory_budget_mb: u64,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self {
+            auto_index: true,
+            detect_dates: true,
+            date_storage: DateStorage::Iso,
+            partition: None,
+            keep_partitioning: true,
+            memory_budget_mb: DEFAULT_MEMORY_BUDGET_MB,
+        }
+    }
+}
+
+/// Result of `load-data --dry-run`: what would be written, without writing
+#[derive(Debug)]
+pub struct LoadPreview {
+    pub create_sql: String,
+    pub row_count: usize,
+    /// `row_count` comes from a line count rather than a full parse
+    pub estimated: bool,
+    pub sample: String,
+    /// `--preview`: the first rows loaded into a scratch database
+    pub scan: Option<LoadEstimate>,
+}
+
+impl std::fmt::Display for LoadPreview {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "-- Proposed schema")?;
+        writeln!(f, "{};", self.create_sql)?;
+        writeln!(f)?;
+        let prefix = if self.estimated { "≈" } else { "" };
+        write!(f, "Rows: {}{}", prefix, self.row_count)?;
+        if let Some(scan) = &self.scan {
+            write!(
+                f,
+                " ({} read in {} ms, file {})",
+                scan.rows_read,
+                scan.read_time.as_millis(),
+                megabytes(scan.file_bytes)
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(f)?;
+        if let Some(scan) = &self.scan {
+            writeln!(f, "Columns (first {} rows):", scan.rows_read)?;
+            for column in &scan.columns {
+                let profile = &column.profile;
+                let approx = if profile.sampled { "≈" } else { "" };
+                write!(f, "  {}: distinct {}{}, empty {}", profile.column, approx, profile.distinct, profile.nulls)?;
+                if let (Some(min), Some(max)) = (&profile.min, &profile.max) {
+                    write!(f, ", {} … {}", min, max)?;
+                }
+                if !column.top.is_empty() {
+                    let top: Vec<String> = column.top.iter().map(|(value, count)| format!("{} ×{}", value, count)).collect();
+                    write!(f, "; top: {}", top.join(", "))?;
+                }
+                writeln!(f)?;
+            }
+            writeln!(f)?;
+            writeln!(f, "Estimated load: {}{} s", prefix, scan.estimated_load.as_secs().max(1))?;
+            writeln!(f, "Estimated table size: {}{}", prefix, megabytes(scan.estimated_db_bytes))?;
+            if scan.batched {
+                writeln!(f, "Over the memory budget: the file would be loaded in batches")?;
+            }
+            if scan.suggests_downsampling() {
+                writeln!(f, "⚠️ Consider down-sampling first: load only the period or columns the dashboards need, or aggregate the file")?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "Sample:")?;
+        write!(f, "{}", self.sample)
+    }
+}
 
 /// Load a file (Excel or CSV) into the SQLite database
 pub fn load_file(file_path: &Path, table_name: &str, db_path: &Path) -> Result<String> {
+    load_file_with(file_path, table_name, db_path, &LoadOptions::default())
+}
+
+/// `load_file` with explicit options
+pub fn load_file_with(file_path: &Path, table_name: &str, db_path: &Path, options: &LoadOptions) -> Result<String> {
     info!("🚀 Loading data from: {}", file_path.display());
     
     // Detect extension
@@ -22,11 +175,58 @@ pub fn load_file(file_path: &Path, table_name: &str, db_path: &Path) -> Result<S
         
     let conn = Connection::open(db_path)
         .context("Failed to open database")?;
+    // A partitioned table is a view: the file is parsed into a table of its
+    // own first, so one that fails to load leaves the old shards in place
+    let previous = partitions::partitioning(&conn, table_name)?;
+    let target = match previous {
+        Some(_) => partitions::loading_table(table_name),
+        None => table_name.to_string(),
+    };
+    let options = &LoadOptions {
+        partition: options.partition.clone().or(previous.clone().filter(|_| options.keep_partitioning)),
+        ..options.clone()
+    };
+    
+    let loaded = load_rows(&conn, file_path, &ext, &target, options);
+    if previous.is_some() && loaded.is_err() {
+        let _ = conn.execute(&format!("DROP TABLE IF EXISTS {}", quote_ident(&target)), []);
+    }
+    let (rows_count, mode) = loaded?;
+    if previous.is_some() {
+        partitions::replace_partitioned(&conn, table_name, &target)?;
+    }
+    
+    info!("✅ Loaded {} rows into table '{}'", rows_count, table_name);
+    let message = format!("Successfully loaded {} rows into {}{}", rows_count, table_name, mode);
+    finish_load(&conn, file_path, table_name, message, options)
+}
+
+/// Parse the file into `table_name`, replacing it. Returns the row count and
+/// a note on how it was read.
+fn load_rows(
+    conn: &Connection,
+    file_path: &Path,
+    ext: &str,
+    table_name: &str,
+    options: &LoadOptions,
+) -> Result<(usize, &'static str)> {
+    let file_size = std::fs::metadata(file_path)
+        .with_context(|| format!("Cannot read {}", file_path.display()))?
+        .len();
+    let budget = options.memory_budget_mb * 1024 * 1024;
         
     // Use Polars to read file into DataFrame
-    let df = match ext.as_str() {
+    let df = match ext {
+        "csv" if file_size * CSV_MEMORY_FACTOR > budget => {
+            info!(
+                "🐘 {} MB file is over the {} MB memory budget: loading in batches",
+                file_size / (1024 * 1024),
+                options.memory_budget_mb
+            );
+            return Ok((stream_csv(file_path, table_name, conn, options, budget)?, ""));
+        }
         "csv" => {
-             CsvReader::from_path(file_path)?
+             csv_reader(file_path)?
                 .has_header(true)
                 .finish()?
         },
@@ -46,23 +246,485 @@ pub fn load_file(file_path: &Path, table_name: &str, db_path: &Path) -> Result<S
             // Let's just stick to the manual implementation for Excel for now,
             // as Polars Excel support requires `connector-arrow` or specific features we might not have enabled fully.
             // ACTUALLY: Let's use our manual loader for Excel but optimized.
-            return legacy_load_excel(file_path, table_name, &conn);
+            if file_size * EXCEL_MEMORY_FACTOR > budget {
+                bail!(
+                    "{} needs about {} MB of memory, over the {} MB budget.\n\
+                     Save the sheet as CSV (large CSV files are loaded in batches), split the workbook, \
+                     or raise load_memory_mb in config.json (--memory-mb for one load) if the computer has the RAM.",
+                    file_path.display(),
+                    file_size * EXCEL_MEMORY_FACTOR / (1024 * 1024),
+                    options.memory_budget_mb
+                );
+            }
+            return Ok((legacy_load_excel(file_path, table_name, conn, options)?, " (Legacy Excel Mode)"));
         }
         _ => return Err(anyhow!("Unsupported file extension: {}", ext)),
     };
 
     info!("📊 Schema detected: {:?}", df.schema());
-    let rows_count = df.height();
     
     // Write DF to SQLite
-    write_df_to_sqlite(&df, table_name, &conn)?;
+    let date_formats = if options.detect_dates { detect_df_dates(&df) } else { vec![None; df.width()] };
+    write_df_to_sqlite(&df, table_name, conn, &date_formats, options.date_storage)?;
+    Ok((df.height(), ""))
+}
+
+/// Post-load steps shared by the CSV and Excel paths
+fn finish_load(
+    conn: &Connection,
+    file_path: &Path,
+    table_name: &str,
+    mut message: String,
+    options: &LoadOptions,
+) -> Result<String> {
+    let source = file_path.file_name().unwrap_or_default().to_string_lossy();
+    freshness::record(conn, table_name, &source)?;
+    let date_columns = temporal_columns(conn, table_name)?;
+    if !date_columns.is_empty() {
+        info!("📅 Date columns normalized: {}", date_columns.join(", "));
+        message = format!("{} (dates: {})", message, date_columns.join(", "));
+    }
+    if options.auto_index {
+        let indexed = create_filter_indexes(conn, table_name, AUTO_INDEX_MIN_ROWS)?;
+        if !indexed.is_empty() {
+            info!("⚡ Indexed filter columns: {}", indexed.join(", "));
+            message = format!("{} (indexed: {})", message, indexed.join(", "));
+        }
+    }
+    if let Some(partitioning) = &options.partition {
+        let shards = partitions::partition(conn, table_name, partitioning)?;
+        message = format!("{} (partitioned: {} shards by {})", message, shards.len(), partitioning.column);
+    }
+    Ok(message)
+}
+
+/// Index likely dashboard filter columns — dates and low-cardinality strings —
+/// when the table has at least `min_rows` rows. Returns the indexed columns.
+pub fn create_filter_indexes(conn: &Connection, table_name: &str, min_rows: usize) -> Result<Vec<String>> {
+    let rows: usize = conn.query_row(&format!("SELECT COUNT(*) FROM {}", quote_ident(table_name)), [], |r| r.get(0))?;
+    if rows < min_rows {
+        return Ok(Vec::new());
+    }
     
-    info!("✅ Loaded {} rows into table '{}'", rows_count, table_name);
-    Ok(format!("Successfully loaded {} rows into {}", rows_count, table_name))
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", quote_ident(table_name)))?;
+    let columns: Vec<(String, String)> = stmt
+        .query_map([], |r| Ok((r.get::<_, String>(1)?, r.get::<_, String>(2)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    
+    let mut indexed = Vec::new();
+    for (column, sql_type) in columns {
+        let textual = sql_type.eq_ignore_ascii_case("TEXT") || sql_type.eq_ignore_ascii_case(DATETIME_TYPE);
+        if column.eq_ignore_ascii_case("id") || (!textual && !dates::is_date_name(&column)) {
+            continue;
+        }
+        if !is_date_column(conn, table_name, &column)? && !is_low_cardinality(conn, table_name, &column)? {
+            continue;
+        }
+        conn.execute(
+            &format!(
+                "CREATE INDEX IF NOT EXISTS {} ON {} ({})",
+                quote_ident(&format!("idx_{}_{}", table_name, column)),
+                quote_ident(table_name),
+                quote_ident(&column)
+            ),
+            [],
+        )?;
+        indexed.push(column);
+    }
+    Ok(indexed)
 }
 
-fn write_df_to_sqlite(df: &DataFrame, table_name: &str, conn: &Connection) -> Result<()> {
-    // 1. Create table based on DataFrame columns
+/// Columns declared DATETIME, i.e. normalized by the date detection
+fn temporal_columns(conn: &Connection, table_name: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", quote_ident(table_name)))?;
+    let columns = stmt
+        .query_map([], |r| Ok((r.get::<_, String>(1)?, r.get::<_, String>(2)?)))?
+        .filter_map(|c| c.ok())
+        .filter(|(_, sql_type)| sql_type.eq_ignore_ascii_case(DATETIME_TYPE))
+        .map(|(name, _)| name)
+        .collect();
+    Ok(columns)
+}
+
+/// Date columns of `table_name` in the SQLite file at `db_path`
+pub fn date_columns(db_path: &Path, table_name: &str) -> Result<Vec<String>> {
+    temporal_columns(&Connection::open(db_path)?, table_name)
+}
+
+/// Named like a date, or sampled values look like ISO dates
+fn is_date_column(conn: &Connection, table_name: &str, column: &str) -> Result<bool> {
+    if dates::is_date_name(column) {
+        return Ok(true);
+    }
+    let sql = format!(
+        "SELECT {0} FROM {1} WHERE {0} IS NOT NULL AND {0} != '' LIMIT 100",
+        quote_ident(column),
+        quote_ident(table_name)
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let values: Vec<String> = stmt
+        .query_map([], |r| r.get::<_, String>(0))?
+        .filter_map(|v| v.ok())
+        .collect();
+    Ok(!values.is_empty() && values.iter().all(|v| looks_like_iso_date(v)))
+}
+
+fn looks_like_iso_date(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    bytes.len() >= 10
+        && bytes[..4].iter().all(u8::is_ascii_digit)
+        && bytes[4] == b'-'
+        && bytes[5..7].iter().all(u8::is_ascii_digit)
+        && bytes[7] == b'-'
+        && bytes[8..10].iter().all(u8::is_ascii_digit)
+}
+
+/// Few distinct values in a sample: a category a dashboard filter would use
+fn is_low_cardinality(conn: &Connection, table_name: &str, column: &str) -> Result<bool> {
+    let sql = format!(
+        "SELECT COUNT(DISTINCT {0}), COUNT(*) FROM (SELECT {0} FROM {1} LIMIT {2})",
+        quote_ident(column),
+        quote_ident(table_name),
+        PROFILE_SAMPLE_ROWS
+    );
+    let (distinct, sampled): (usize, usize) = conn.query_row(&sql, [], |r| Ok((r.get(0)?, r.get(1)?)))?;
+    // Values must repeat (at least 10 rows each on average) to be worth an index
+    Ok(distinct > 1 && distinct <= LOW_CARDINALITY_MAX && distinct * 10 <= sampled)
+}
+
+/// Value range and cardinality of one column, shown in Superset's dataset
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnProfile {
+    pub column: String,
+    pub min: Option<String>,
+    pub max: Option<String>,
+    pub distinct: u64,
+    /// `distinct` counted on the first rows of a large table
+    pub sampled: bool,
+    pub nulls: u64,
+}
+
+impl ColumnProfile {
+    /// One-line summary for `table_columns.description`
+    pub fn description(&self) -> String {
+        let mut parts = Vec::new();
+        match (&self.min, &self.max) {
+            (Some(min), Some(max)) if min == max => parts.push(format!("всегда {}", min)),
+            (Some(min), Some(max)) => parts.push(format!("от {} до {}", min, max)),
+            _ => {}
+        }
+        let approx = if self.sampled { "≈" } else { "" };
+        parts.push(format!("различных значений: {}{}", approx, self.distinct));
+        if self.nulls > 0 {
+            parts.push(format!("пустых: {}", self.nulls));
+        }
+        format!("{}{}", PROFILE_DESCRIPTION_PREFIX, parts.join(" · "))
+    }
+
+    /// Entry of `column_stats` in the dataset's `extra`
+    pub fn stats(&self) -> serde_json::Value {
+        serde_json::json!({
+            "min": self.min, "max": self.max, "distinct": self.distinct, "sampled": self.sampled, "nulls": self.nulls,
+        })
+    }
+}
+
+/// Min/max, distinct and empty counts of every column of `table_name`
+/// (except the generated `id`). Distinct values of tables with
+/// [`AUTO_INDEX_MIN_ROWS`] or more rows are counted on a sample.
+pub fn profile_columns(db_path: &Path, table_name: &str) -> Result<Vec<ColumnProfile>> {
+    profile_table(&Connection::open(db_path)?, table_name)
+}
+
+/// [`profile_columns`] on an open connection
+pub fn profile_table(conn: &Connection, table_name: &str) -> Result<Vec<ColumnProfile>> {
+    let rows: usize = conn.query_row(&format!("SELECT COUNT(*) FROM {}", quote_ident(table_name)), [], |r| r.get(0))?;
+    let sampled = rows >= AUTO_INDEX_MIN_ROWS;
+
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", quote_ident(table_name)))?;
+    let columns: Vec<String> = stmt
+        .query_map([], |r| r.get::<_, String>(1))?
+        .filter_map(|c| c.ok())
+        .filter(|c| c != "id")
+        .collect();
+
+    let mut profiles = Vec::new();
+    for column in columns {
+        let (min, max, nulls): (Value, Value, u64) = conn.query_row(
+            &format!("SELECT MIN({0}), MAX({0}), COUNT(*) - COUNT({0}) FROM {1}", quote_ident(column), quote_ident(table_name)),
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )?;
+        let source = if sampled {
+            format!("(SELECT {} FROM {} LIMIT {})", quote_ident(column), quote_ident(table_name), PROFILE_SAMPLE_ROWS)
+        } else {
+            quote_ident(table_name)
+        };
+        let distinct: u64 =
+            conn.query_row(&format!("SELECT COUNT(DISTINCT {}) FROM {}", quote_ident(column), source), [], |r| r.get(0))?;
+        profiles.push(ColumnProfile {
+            column,
+            min: profile_value(min),
+            max: profile_value(max),
+            distinct,
+            sampled,
+            nulls,
+        });
+    }
+    Ok(profiles)
+}
+
+fn profile_value(value: Value) -> Option<String> {
+    let text = match value {
+        Value::Integer(v) => v.to_string(),
+        Value::Real(v) => format!("{}", (v * 100.0).round() / 100.0),
+        Value::Text(v) if !v.trim().is_empty() => v,
+        _ => return None,
+    };
+    Some(if text.chars().count() > PROFILE_VALUE_CHARS {
+        format!("{}…", text.chars().take(PROFILE_VALUE_CHARS).collect::<String>())
+    } else {
+        text
+    })
+}
+
+/// `--preview`: how far [`preview_file`] goes beyond the schema
+#[derive(Debug)]
+pub struct PreviewScan<'a> {
+    /// Rows parsed and written to the scratch database
+    pub rows: usize,
+    /// Where the scratch database goes (same drive as the target, so the timing holds)
+    pub scratch_dir: &'a Path,
+    pub options: &'a LoadOptions,
+}
+
+/// Read and infer the schema of a file without touching the database. With
+/// `scan` (CSV only) the first rows are also written to a scratch database
+/// to extrapolate the time and size of loading the whole file.
+pub fn preview_file(file_path: &Path, table_name: &str, sample_rows: usize, scan: Option<&PreviewScan>) -> Result<LoadPreview> {
+    info!("🔍 Dry run: {}", file_path.display());
+    
+    let ext = file_path.extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    
+    match ext.as_str() {
+        "csv" => preview_csv(file_path, table_name, sample_rows, scan),
+        "xlsx" | "xls" | "xlsb" if scan.is_some() => {
+            bail!("--preview streams CSV files only; Excel workbooks are read whole, use --dry-run for them")
+        }
+        "xlsx" | "xls" | "xlsb" => preview_excel(file_path, table_name, sample_rows),
+        _ => Err(anyhow!("Unsupported file extension: {}", ext)),
+    }
+}
+
+fn preview_csv(file_path: &Path, table_name: &str, sample_rows: usize, scan: Option<&PreviewScan>) -> Result<LoadPreview> {
+    let rows = scan.map_or(PREVIEW_SCAN_ROWS, |scan| scan.rows);
+    let started = Instant::now();
+    let mut reader = csv_reader(file_path)?.has_header(true).with_n_rows(Some(rows));
+    if scan.is_some() {
+        // Same inference window as a batched load of a big file
+        reader = reader.infer_schema(Some(rows.min(STREAM_SCHEMA_ROWS)));
+    }
+    let df = reader.finish()?;
+    let read_time = started.elapsed();
+    let date_formats = match scan {
+        Some(scan) if !scan.options.detect_dates => vec![None; df.width()],
+        _ => detect_df_dates(&df),
+    };
+    
+    // Only a prefix was parsed; estimate the rest from the line count, or
+    // for a scan (big files) from the bytes the prefix took
+    let (row_count, estimated) = if df.height() < rows {
+        (df.height(), false)
+    } else if scan.is_some() {
+        (extrapolate_rows(file_path, df.height())?, true)
+    } else {
+        (count_lines(file_path)?.saturating_sub(1), true)
+    };
+    let scan = match scan {
+        Some(scan) => Some(estimate_load(file_path, &df, table_name, scan, &date_formats, row_count, read_time)?),
+        None => None,
+    };
+    
+    Ok(LoadPreview {
+        create_sql: create_table_sql(&df, table_name, &date_formats),
+        row_count,
+        estimated,
+        sample: df.head(Some(sample_rows)).to_string(),
+        scan,
+    })
+}
+
+fn count_lines(file_path: &Path) -> Result<usize> {
+    let reader = BufReader::new(File::open(file_path)?);
+    Ok(reader.split(b'\n').count())
+}
+
+/// Data rows of the whole file, from the bytes its first `rows_read` took
+fn extrapolate_rows(file_path: &Path, rows_read: usize) -> Result<usize> {
+    let file_bytes = std::fs::metadata(file_path)?.len();
+    let reader = BufReader::new(File::open(file_path)?);
+    let prefix_bytes = reader
+        .split(b'\n')
+        .take(rows_read + 1)
+        .map(|line| line.map(|l| l.len() as u64 + 1))
+        .sum::<std::io::Result<u64>>()?;
+    let row_bytes = prefix_bytes as f64 / (rows_read + 1) as f64;
+    Ok(((file_bytes as f64 / row_bytes) as usize).saturating_sub(1).max(rows_read))
+}
+
+/// Value distribution of one column in the previewed rows
+#[derive(Debug, Clone)]
+pub struct ColumnDistribution {
+    pub profile: ColumnProfile,
+    /// Most frequent values with their counts
+    pub top: Vec<(String, u64)>,
+}
+
+/// What `--preview` adds to a dry run: the parsed prefix loaded into a
+/// scratch database, and the full load extrapolated from it
+#[derive(Debug)]
+pub struct LoadEstimate {
+    pub rows_read: usize,
+    pub file_bytes: u64,
+    pub columns: Vec<ColumnDistribution>,
+    pub read_time: Duration,
+    pub write_time: Duration,
+    pub estimated_load: Duration,
+    pub estimated_db_bytes: u64,
+    /// The file is over the memory budget and would be loaded in batches
+    pub batched: bool,
+}
+
+impl LoadEstimate {
+    /// The full load looks big enough to reduce the data first
+    pub fn suggests_downsampling(&self) -> bool {
+        self.estimated_load.as_secs() > DOWNSAMPLE_LOAD_SECS || self.estimated_db_bytes > DOWNSAMPLE_DB_BYTES
+    }
+}
+
+fn megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// Write the parsed prefix to a scratch database in `scan.scratch_dir`,
+/// profile it and scale its timing and size up to `total_rows`
+fn estimate_load(
+    file_path: &Path,
+    df: &DataFrame,
+    table_name: &str,
+    scan: &PreviewScan,
+    date_formats: &[Option<DateFormat>],
+    total_rows: usize,
+    read_time: Duration,
+) -> Result<LoadEstimate> {
+    let file_bytes = std::fs::metadata(file_path)
+        .with_context(|| format!("Cannot read {}", file_path.display()))?
+        .len();
+    let scratch = scan.scratch_dir.join(format!(".preview-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&scratch);
+    let measured = (|| -> Result<_> {
+        let conn = Connection::open(&scratch)?;
+        let started = Instant::now();
+        write_df_to_sqlite(df, table_name, &conn, date_formats, scan.options.date_storage)?;
+        let write_time = started.elapsed();
+        let db_bytes: u64 = conn.query_row(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+            [],
+            |r| r.get(0),
+        )?;
+        let mut columns = Vec::new();
+        for profile in profile_table(&conn, table_name)? {
+            let top = top_values(&conn, table_name, &profile.column)?;
+            columns.push(ColumnDistribution { profile, top });
+        }
+        Ok((write_time, db_bytes, columns))
+    })();
+    let _ = std::fs::remove_file(&scratch);
+    let (write_time, db_bytes, columns) = measured?;
+
+    let scale = total_rows as f64 / df.height().max(1) as f64;
+    Ok(LoadEstimate {
+        rows_read: df.height(),
+        file_bytes,
+        columns,
+        read_time,
+        write_time,
+        estimated_load: (read_time + write_time).mul_f64(scale),
+        estimated_db_bytes: (db_bytes as f64 * scale) as u64,
+        batched: file_bytes * CSV_MEMORY_FACTOR > scan.options.memory_budget_mb * 1024 * 1024,
+    })
+}
+
+/// Most frequent values of a column that repeats (unique columns have no top)
+fn top_values(conn: &Connection, table_name: &str, column: &str) -> Result<Vec<(String, u64)>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {0}, COUNT(*) AS n FROM {1} WHERE {0} IS NOT NULL GROUP BY 1 HAVING n > 1 ORDER BY n DESC LIMIT {2}",
+        quote_ident(column),
+        quote_ident(table_name),
+        PREVIEW_TOP_VALUES
+    ))?;
+    let rows = stmt.query_map([], |r| Ok((r.get::<_, Value>(0)?, r.get::<_, u64>(1)?)))?;
+    let mut top = Vec::new();
+    for row in rows {
+        let (value, count) = row?;
+        if let Some(value) = profile_value(value) {
+            top.push((value, count));
+        }
+    }
+    Ok(top)
+}
+
+/// Date format of each column (`None` for non-date columns)
+fn detect_df_dates(df: &DataFrame) -> Vec<Option<DateFormat>> {
+    df.get_columns()
+        .iter()
+        .map(|col| {
+            let numeric = col.dtype().is_numeric() && dates::is_date_name(col.name());
+            if !matches!(col.dtype(), DataType::String) && !numeric {
+                return None;
+            }
+            let samples: Vec<String> = (0..col.len())
+                .filter_map(|i| col.get(i).ok().and_then(|v| any_value_text(&v)))
+                .filter(|s| !s.trim().is_empty())
+                .take(SAMPLE_VALUES)
+                .collect();
+            dates::detect(col.name(), &samples)
+        })
+        .collect()
+}
+
+/// Cell value as plain text (polars quotes strings in `Display`)
+fn any_value_text(val: &AnyValue) -> Option<String> {
+    match val {
+        AnyValue::Null => None,
+        AnyValue::String(v) => Some(v.to_string()),
+        AnyValue::StringOwned(v) => Some(v.to_string()),
+        other => Some(other.to_string()),
+    }
+}
+
+fn sql_type(dtype: &DataType) -> &'static str {
+    match dtype {
+        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64 | DataType::UInt8 | DataType::UInt16 | DataType::UInt32 | DataType::UInt64 => "INTEGER",
+        DataType::Float32 | DataType::Float64 => "REAL",
+        DataType::String => "TEXT",
+        DataType::Boolean => "INTEGER",
+        _ => "TEXT", // Fallback
+    }
+}
+
+/// A table or column name as an SQL identifier: file stems and headers come
+/// from users (`report (1).csv`, quotes, semicolons), so every statement
+/// quotes them here
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// CREATE TABLE statement for a DataFrame; date columns are declared DATETIME
+fn create_table_sql(df: &DataFrame, table_name: &str, date_formats: &[Option<DateFormat>]) -> String {
     let columns = df.get_columns();
     let has_id = columns.iter().any(|c| c.name() == "id");
     
@@ -72,59 +734,124 @@ fn write_df_to_sqlite(df: &DataFrame, table_name: &str, conn: &Connection) -> Re
         field_defs.push("id INTEGER PRIMARY KEY AUTOINCREMENT".to_string());
     }
     
-    for c in columns.iter() {
-        let name = c.name();
-        let dtype = c.dtype();
-        let sql_type = match dtype {
-            DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64 | DataType::UInt8 | DataType::UInt16 | DataType::UInt32 | DataType::UInt64 => "INTEGER",
-            DataType::Float32 | DataType::Float64 => "REAL",
-            DataType::String => "TEXT",
-            DataType::Boolean => "INTEGER",
-            _ => "TEXT", // Fallback
-        };
-        // If it's the ID column, make it Primary Key if it's integer?
-        // relying on user data for PK is risky if not unique. 
-        // But for "id" collision, let's just let it be a normal column if it exists.
-        // Or if the user provided "id", maybe they want it to be the ID.
-        // For simplicity: If "id" exists, we don't add our own. We just treat "id" as a normal column (SQLite auto-rowid handles internal storage).
-        // If they want it to be PK, they'd need schema inference to be smarter.
-        field_defs.push(format!("\"{}\" {}", name, sql_type));
+    for (c, format) in columns.iter().zip(date_formats) {
+        // If "id" exists, we don't add our own. We just treat "id" as a normal column
+        // (SQLite auto-rowid handles internal storage); relying on user data for PK is risky.
+        let column_type = if format.is_some() { DATETIME_TYPE } else { sql_type(c.dtype()) };
+        field_defs.push(format!("{} {}", quote_ident(c.name()), column_type));
     }
     
-    let fields_sql = field_defs.join(", ");
+    format!("CREATE TABLE {} ({})", quote_ident(table_name), field_defs.join(", "))
+}
+
+/// Read a CSV too big for memory in batches of rows, writing each one to
+/// SQLite before the next is parsed. Column types come from the first rows.
+/// Returns the row count.
+fn stream_csv(file_path: &Path, table_name: &str, conn: &Connection, options: &LoadOptions, budget: u64) -> Result<usize> {
+    let sample = csv_reader(file_path)?
+        .has_header(true)
+        .infer_schema(Some(STREAM_SCHEMA_ROWS))
+        .with_n_rows(Some(STREAM_SCHEMA_ROWS))
+        .finish()?;
+    info!("📊 Schema detected: {:?}", sample.schema());
+    let row_bytes = (sample.estimated_size() / sample.height().max(1)).max(1) as u64;
+    let batch_rows = (budget / 2 / (row_bytes * CSV_MEMORY_FACTOR)).clamp(STREAM_MIN_ROWS, STREAM_MAX_ROWS) as usize;
+    let date_formats = if options.detect_dates { detect_df_dates(&sample) } else { vec![None; sample.width()] };
+    
+    let mut reader = csv_reader(file_path)?
+        .has_header(true)
+        .low_memory(true)
+        .with_chunk_size(batch_rows)
+        .batched_read(Some(Arc::new(sample.schema())))?;
     
-    conn.execute(&format!("DROP TABLE IF EXISTS {}", table_name), [])?;
-    let create_sql = format!("CREATE TABLE {} ({})", table_name, fields_sql);
-    conn.execute(&create_sql, [])?;
+    let create_sql = create_table_sql(&sample, table_name, &date_formats);
+    drop(sample);
+    
+    // The old table goes in the same transaction, so a failed load keeps it
+    conn.execute("BEGIN TRANSACTION", [])?;
+    let mut rows_count = 0;
+    let result = (|| -> Result<()> {
+        conn.execute(&format!("DROP TABLE IF EXISTS {}", quote_ident(table_name)), [])?;
+        conn.execute(&create_sql, [])?;
+        while let Some(batches) = reader.next_batches(1)? {
+            for df in batches {
+                insert_rows(&df, table_name, conn, &date_formats, options.date_storage)?;
+                rows_count += df.height();
+            }
+        }
+        Ok(())
+    })();
+    if let Err(e) = result {
+        let _ = conn.execute("ROLLBACK", []);
+        return Err(e.context(format!(
+            "Batched load stopped after {} rows, the table is left as it was. Column types are taken from the first {} rows; \
+             if a later value doesn't fit, fix that column in the file, or raise load_memory_mb \
+             in config.json (--memory-mb for one load) to read the whole file at once",
+            rows_count, STREAM_SCHEMA_ROWS
+        )));
+    }
+    conn.execute("COMMIT", [])?;
+    
+    info!("📦 Read in batches of {} rows", batch_rows);
+    Ok(rows_count)
+}
+
+fn write_df_to_sqlite(
+    df: &DataFrame,
+    table_name: &str,
+    conn: &Connection,
+    date_formats: &[Option<DateFormat>],
+    date_storage: DateStorage,
+) -> Result<()> {
+    // 1. Create table based on DataFrame columns
+    conn.execute(&format!("DROP TABLE IF EXISTS {}", quote_ident(table_name)), [])?;
+    conn.execute(&create_table_sql(df, table_name, date_formats), [])?;
     
     // 2. Insert data
     conn.execute("BEGIN TRANSACTION", [])?;
+    insert_rows(df, table_name, conn, date_formats, date_storage)?;
+    conn.execute("COMMIT", [])?;
     
+    Ok(())
+}
+
+/// Insert every row of `df` (inside the caller's transaction)
+fn insert_rows(
+    df: &DataFrame,
+    table_name: &str,
+    conn: &Connection,
+    date_formats: &[Option<DateFormat>],
+    date_storage: DateStorage,
+) -> Result<()> {
+    let columns = df.get_columns();
     let n_rows = df.height();
     let n_cols = columns.len();
     
     // Prepare statement
     let placeholders = (0..n_cols).map(|_| "?").collect::<Vec<_>>().join(", ");
-    let col_names = columns.iter().map(|c| format!("\"{}\"", c.name())).collect::<Vec<_>>().join(", ");
-    let insert_sql = format!("INSERT INTO {} ({}) VALUES ({})", table_name, col_names, placeholders);
+    let col_names = columns.iter().map(|c| quote_ident(c.name())).collect::<Vec<_>>().join(", ");
+    let insert_sql = format!("INSERT INTO {} ({}) VALUES ({})", quote_ident(table_name), col_names, placeholders);
     
     let mut stmt = conn.prepare(&insert_sql)?;
     
     // Iterate rows
     for i in 0..n_rows {
         let mut params = Vec::with_capacity(n_cols);
-        for col in columns {
+        for (col, format) in columns.iter().zip(date_formats) {
              // col.get(i) returns AnyValue, not Result
              let val = col.get(i).unwrap(); 
-             params.push(val_to_sql_param(val));
+             // Values that don't parse as dates are kept as they are
+             let date = format.and_then(|f| f.normalize(&any_value_text(&val)?, date_storage));
+             match date {
+                 Some(date) => params.push(Box::new(date) as Box<dyn rusqlite::ToSql>),
+                 None => params.push(val_to_sql_param(val)),
+             }
         }
         
         let params_ref: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
         stmt.execute(&*params_ref)?;
     }
     
-    conn.execute("COMMIT", [])?;
-    
     Ok(())
 }
 
@@ -148,9 +875,10 @@ fn val_to_sql_param(val: AnyValue) -> Box<dyn rusqlite::ToSql> {
     }
 }
 
-/// Fallback for Excel using Calamine (Polars Excel reader is optional/heavy)
-fn legacy_load_excel(file_path: &Path, table_name: &str, conn: &Connection) -> Result<String> {
-    use calamine::{Reader, open_workbook, Data, Xlsx};
+/// Fallback for Excel using Calamine (Polars Excel reader is optional/heavy).
+/// Returns the row count.
+fn legacy_load_excel(file_path: &Path, table_name: &str, conn: &Connection, options: &LoadOptions) -> Result<usize> {
+    use calamine::{Reader, open_workbook, Xlsx};
     
     let mut workbook: Xlsx<std::io::BufReader<std::fs::File>> = open_workbook(file_path)
         .context("Cannot open Excel file")?;
@@ -170,27 +898,287 @@ fn legacy_load_excel(file_path: &Path, table_name: &str, conn: &Connection) -> R
         .map(|c| c.to_string())
         .collect();
         
+    let date_formats = if options.detect_dates {
+        detect_excel_dates(&range, &headers)
+    } else {
+        vec![None; headers.len()]
+    };
+    
     // Create table (legacy string-based)
-    conn.execute(&format!("DROP TABLE IF EXISTS {}", table_name), [])?;
-    let columns = headers.iter().map(|h| format!("\"{}\" TEXT", h)).collect::<Vec<_>>().join(", ");
-    conn.execute(&format!("CREATE TABLE {} (id INTEGER PRIMARY KEY AUTOINCREMENT, {})", table_name, columns), [])?;
+    conn.execute(&format!("DROP TABLE IF EXISTS {}", quote_ident(table_name)), [])?;
+    conn.execute(&excel_create_sql(&headers, table_name, &date_formats), [])?;
     
     conn.execute("BEGIN TRANSACTION", [])?;
     
     let placeholders = headers.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
-    let columns_sql = headers.iter().map(|h| format!("\"{}\"", h)).collect::<Vec<_>>().join(", ");
-    let sql = format!("INSERT INTO {} ({}) VALUES ({})", table_name, columns_sql, placeholders);
+    let columns_sql = headers.iter().map(|h| quote_ident(h)).collect::<Vec<_>>().join(", ");
+    let sql = format!("INSERT INTO {} ({}) VALUES ({})", quote_ident(table_name), columns_sql, placeholders);
     let mut stmt = conn.prepare(&sql)?;
     
     let mut count = 0;
     for row in rows {
-        let params: Vec<String> = row.iter().map(|c| c.to_string()).collect();
-        let params_ref: Vec<&dyn rusqlite::ToSql> = params.iter().map(|s| s as &dyn rusqlite::ToSql).collect();
+        let params: Vec<Value> = row
+            .iter()
+            .zip(&date_formats)
+            .map(|(c, format)| {
+                let text = excel_cell_text(c);
+                format
+                    .and_then(|f| f.normalize(&text, options.date_storage))
+                    .unwrap_or(Value::Text(text))
+            })
+            .collect();
+        let params_ref: Vec<&dyn rusqlite::ToSql> = params.iter().map(|v| v as &dyn rusqlite::ToSql).collect();
         stmt.execute(&*params_ref)?;
         count += 1;
     }
     
     conn.execute("COMMIT", [])?;
     
-    Ok(format!("Successfully loaded {} rows into {} (Legacy Excel Mode)", count, table_name))
+    Ok(count)
+}
+
+/// Cell text; real Excel date cells become ISO instead of a serial number
+fn excel_cell_text(cell: &calamine::Data) -> String {
+    match cell {
+        calamine::Data::DateTime(dt) if dt.is_datetime() => {
+            match DateFormat::ExcelSerial.normalize(&dt.as_f64().to_string(), DateStorage::Iso) {
+                Some(Value::Text(iso)) => iso,
+                _ => cell.to_string(),
+            }
+        }
+        _ => cell.to_string(),
+    }
+}
+
+/// Date format of each sheet column, sampled below the header row
+fn detect_excel_dates(range: &calamine::Range<calamine::Data>, headers: &[String]) -> Vec<Option<DateFormat>> {
+    headers
+        .iter()
+        .enumerate()
+        .map(|(j, header)| {
+            let samples: Vec<String> = range
+                .rows()
+                .skip(1)
+                .filter_map(|row| row.get(j))
+                .map(excel_cell_text)
+                .filter(|s| !s.trim().is_empty())
+                .take(SAMPLE_VALUES)
+                .collect();
+            dates::detect(header, &samples)
+        })
+        .collect()
+}
+
+/// CREATE TABLE statement for the legacy Excel loader (TEXT, DATETIME for dates)
+fn excel_create_sql(headers: &[String], table_name: &str, date_formats: &[Option<DateFormat>]) -> String {
+    let columns = headers
+        .iter()
+        .zip(date_formats)
+        .map(|(h, format)| format!("{} {}", quote_ident(h), if format.is_some() { DATETIME_TYPE } else { "TEXT" }))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("CREATE TABLE {} (id INTEGER PRIMARY KEY AUTOINCREMENT, {})", quote_ident(table_name), columns)
+}
+
+/// Dry-run counterpart of `legacy_load_excel`
+fn preview_excel(file_path: &Path, table_name: &str, sample_rows: usize) -> Result<LoadPreview> {
+    use calamine::{Reader, open_workbook, Xlsx};
+    
+    let mut workbook: Xlsx<std::io::BufReader<std::fs::File>> = open_workbook(file_path)
+        .context("Cannot open Excel file")?;
+    
+    let sheet_name = workbook.sheet_names().first()
+        .ok_or_else(|| anyhow!("No sheets in workbook"))?
+        .to_owned();
+    
+    let range = workbook.worksheet_range(&sheet_name)
+        .context("Cannot read sheet")?;
+    
+    let mut rows = range.rows();
+    let headers: Vec<String> = rows.next()
+        .ok_or_else(|| anyhow!("Empty file"))?
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+    
+    let mut sample = headers.join(" | ");
+    sample.push('\n');
+    for row in rows.take(sample_rows) {
+        let cells: Vec<String> = row.iter().map(excel_cell_text).collect();
+        sample.push_str(&cells.join(" | "));
+        sample.push('\n');
+    }
+    
+    Ok(LoadPreview {
+        create_sql: excel_create_sql(&headers, table_name, &detect_excel_dates(&range, &headers)),
+        row_count: range.height().saturating_sub(1),
+        estimated: false,
+        sample,
+        scan: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    
+    #[test]
+    fn test_preview_csv_does_not_touch_db() {
+        let dir = tempdir().unwrap();
+        let csv = dir.path().join("stations.csv");
+        std::fs::write(&csv, "name,passengers,share\nМосква,120,0.5\nКазань,40,0.2\n").unwrap();
+        
+        let preview = preview_file(&csv, "stations", 5, None).unwrap();
+        assert_eq!(
+            preview.create_sql,
+            "CREATE TABLE \"stations\" (id INTEGER PRIMARY KEY AUTOINCREMENT, \"name\" TEXT, \"passengers\" INTEGER, \"share\" REAL)"
+        );
+        assert_eq!(preview.row_count, 2);
+        assert!(!preview.estimated);
+        assert!(preview.sample.contains("Казань"));
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+    
+    #[test]
+    fn test_awkward_table_names_are_quoted() {
+        let dir = tempdir().unwrap();
+        let csv = dir.path().join("report (1).csv");
+        std::fs::write(&csv, "region,\"say \"\"hi\"\"\"\nСевер,1\n").unwrap();
+        let db = dir.path().join("test.db");
+        Connection::open(&db).unwrap().execute("CREATE TABLE keep (x INTEGER)", []).unwrap();
+        
+        load_file(&csv, "report (1)", &db).unwrap();
+        load_file(&csv, "x\"; DROP TABLE keep--", &db).unwrap();
+        let conn = Connection::open(&db).unwrap();
+        let count = |sql: &str| -> i64 { conn.query_row(sql, [], |r| r.get(0)).unwrap() };
+        assert_eq!(count("SELECT COUNT(*) FROM \"report (1)\""), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM \"x\"\"; DROP TABLE keep--\""), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM keep"), 0);
+    }
+    
+    #[test]
+    fn test_preview_scan_extrapolates() {
+        let dir = tempdir().unwrap();
+        let csv = dir.path().join("trips.csv");
+        let mut content = String::from("trip_date,region,passengers\n");
+        for i in 0..3_000 {
+            content.push_str(&format!("2024-01-{:02},Регион {},{}\n", i % 28 + 1, i % 3, 1000 + i));
+        }
+        std::fs::write(&csv, &content).unwrap();
+        
+        let options = LoadOptions::default();
+        let scan = |rows| PreviewScan { rows, scratch_dir: dir.path(), options: &options };
+        let preview = preview_file(&csv, "trips", 5, Some(&scan(1_000))).unwrap();
+        let estimate = preview.scan.as_ref().unwrap();
+        assert_eq!(estimate.rows_read, 1_000);
+        assert!(preview.estimated);
+        assert!((2_900..=3_100).contains(&preview.row_count), "{}", preview.row_count);
+        assert!(estimate.estimated_db_bytes > 0);
+        let region = estimate.columns.iter().find(|c| c.profile.column == "region").unwrap();
+        assert_eq!(region.profile.distinct, 3);
+        assert_eq!(region.top.len(), 3);
+        // Every passenger count is unique: nothing to list
+        assert!(estimate.columns.iter().find(|c| c.profile.column == "passengers").unwrap().top.is_empty());
+        assert!(preview.to_string().contains("Rows: ≈"));
+        // Only the CSV is left behind
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+        
+        let whole = preview_file(&csv, "trips", 5, Some(&scan(10_000))).unwrap();
+        assert_eq!((whole.row_count, whole.estimated), (3_000, false));
+        assert!(preview_file(&dir.path().join("trips.xlsx"), "trips", 5, Some(&scan(10))).is_err());
+    }
+    
+    #[test]
+    fn test_over_budget_loads_in_batches() {
+        let dir = tempdir().unwrap();
+        let csv = dir.path().join("trips.csv");
+        let mut content = String::from("trip_date,region,passengers\n");
+        for i in 0..2_500 {
+            content.push_str(&format!("2024-01-{:02},Север,{}\n", i % 28 + 1, i));
+        }
+        std::fs::write(&csv, &content).unwrap();
+        let db = dir.path().join("test.db");
+        let options = LoadOptions { memory_budget_mb: 0, ..LoadOptions::default() };
+        
+        let message = load_file_with(&csv, "trips", &db, &options).unwrap();
+        assert!(message.contains("loaded 2500 rows"), "{}", message);
+        let conn = Connection::open(&db).unwrap();
+        let (rows, total): (i64, i64) = conn
+            .query_row("SELECT COUNT(*), SUM(passengers) FROM trips", [], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap();
+        assert_eq!((rows, total), (2_500, 2_499 * 2_500 / 2));
+        assert_eq!(date_columns(&db, "trips").unwrap(), vec!["trip_date"]);
+        
+        // A value past the schema rows that doesn't fit stops the load; the old table stays
+        let mut broken = String::from("trip_date,region,passengers\n");
+        for i in 0..STREAM_SCHEMA_ROWS {
+            broken.push_str(&format!("2024-02-01,Юг,{}\n", i));
+        }
+        broken.push_str("2024-02-02,Юг,много\n");
+        std::fs::write(&csv, &broken).unwrap();
+        assert!(load_file_with(&csv, "trips", &db, &options).is_err());
+        let rows: i64 = conn.query_row("SELECT COUNT(*) FROM trips", [], |r| r.get(0)).unwrap();
+        assert_eq!(rows, 2_500);
+        
+        let xlsx = dir.path().join("big.xlsx");
+        std::fs::write(&xlsx, b"not really a workbook").unwrap();
+        let error = load_file_with(&xlsx, "big", &db, &options).unwrap_err().to_string();
+        assert!(error.contains("Save the sheet as CSV"), "{}", error);
+    }
+    
+    #[test]
+    fn test_profile_columns() {
+        let dir = tempdir().unwrap();
+        let csv = dir.path().join("stations.csv");
+        std::fs::write(&csv, "name,passengers,share\nМосква,120,0.5\nКазань,40,\nМосква,7,0.25\n").unwrap();
+        let db = dir.path().join("test.db");
+        load_file(&csv, "stations", &db).unwrap();
+        
+        let profiles = profile_columns(&db, "stations").unwrap();
+        assert_eq!(profiles.iter().map(|p| p.column.as_str()).collect::<Vec<_>>(), vec!["name", "passengers", "share"]);
+        assert_eq!(profiles[0].distinct, 2);
+        assert_eq!((profiles[1].min.as_deref(), profiles[1].max.as_deref()), (Some("7"), Some("120")));
+        assert_eq!(profiles[2].nulls, 1);
+        assert_eq!(profiles[1].description(), "📊 от 7 до 120 · различных значений: 3");
+        assert!(profiles[2].description().ends_with("пустых: 1"));
+    }
+    
+    #[test]
+    fn test_filter_indexes() {
+        let dir = tempdir().unwrap();
+        let csv = dir.path().join("trips.csv");
+        let mut content = String::from("trip_date,region,ticket,passengers\n");
+        for i in 0..200 {
+            content.push_str(&format!("2024-01-{:02},Регион {},T{},{}\n", i % 28 + 1, i % 5, i, i));
+        }
+        std::fs::write(&csv, content).unwrap();
+        let db = dir.path().join("test.db");
+        
+        // Small tables are left alone
+        load_file(&csv, "trips", &db).unwrap();
+        let conn = Connection::open(&db).unwrap();
+        assert!(create_filter_indexes(&conn, "trips", 1000).unwrap().is_empty());
+        
+        assert_eq!(create_filter_indexes(&conn, "trips", 100).unwrap(), vec!["trip_date", "region"]);
+        let count: usize = conn
+            .query_row("SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND tbl_name = 'trips'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+    
+    #[test]
+    fn test_dates_normalized_on_load() {
+        let dir = tempdir().unwrap();
+        let csv = dir.path().join("shipments.csv");
+        std::fs::write(&csv, "shipped,amount\n31.12.2024,10\n01.02.2025,20\n").unwrap();
+        let db = dir.path().join("test.db");
+        
+        let message = load_file(&csv, "shipments", &db).unwrap();
+        assert!(message.contains("dates: shipped"));
+        assert_eq!(date_columns(&db, "shipments").unwrap(), vec!["shipped"]);
+        let conn = Connection::open(&db).unwrap();
+        let first: String = conn.query_row("SELECT shipped FROM shipments ORDER BY id LIMIT 1", [], |r| r.get(0)).unwrap();
+        assert_eq!(first, "2024-12-31");
+    }
 }
@@ -0,0 +1,344 @@
+//! Analytics database registry
+//!
+//! Besides the bundled `examples.db`, extra SQLite files (e.g. `data/finance.db`,
+//! `data/ops.db`) can be declared in the `databases` section of config.json.
+//! Each one is registered as a Superset database connection with a stable UUID
+//! (derived from its logical name unless pinned in config), and `load-data
+//! --db-name <name>` targets it by that name.
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::data_loader::{ColumnProfile, PROFILE_DESCRIPTION_PREFIX};
+use crate::dates::DateStorage;
+
+/// Logical name of the bundled demo database
+pub const EXAMPLES_NAME: &str = "examples";
+
+/// Path of the bundled demo database (relative to root)
+pub const EXAMPLES_PATH: &str = "examples.db";
+
+/// Namespace for name-derived database UUIDs
+const UUID_NAMESPACE: Uuid = Uuid::from_u128(0x5f0e2b1c_7d3a_4c59_9a61_2b8d4e7f1a30);
+
+/// UUID of the `examples` connection (the demo metadata pins it)
+pub const EXAMPLES_UUID: Uuid = Uuid::from_u128(0xa2dc77af_e654_49bb_b321_40f6b559a1ee);
+
+/// One analytics SQLite file (`databases` section of config.json)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseConfig {
+    /// Logical name, shown in Superset as the connection name
+    pub name: String,
+    /// SQLite file, relative to root (or absolute)
+    pub path: PathBuf,
+    /// Pin the connection UUID (by default derived from `name`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<Uuid>,
+}
+
+impl DatabaseConfig {
+    /// Absolute path of the SQLite file
+    pub fn path_abs(&self, root: &Path) -> PathBuf {
+        root.join(&self.path)
+    }
+
+    /// Connection UUID, stable across kits and re-registrations
+    pub fn uuid(&self) -> Uuid {
+        self.uuid
+            .unwrap_or_else(|| Uuid::new_v5(&UUID_NAMESPACE, self.name.as_bytes()))
+    }
+
+    /// SQLAlchemy URI Superset uses to connect
+    pub fn sqlalchemy_uri(&self, root: &Path) -> String {
+        format!("sqlite:///{}", self.path_abs(root).to_string_lossy().replace('\\', "/"))
+    }
+}
+
+/// Superset connection UUID of a logical database name
+pub fn connection_uuid(databases: &[DatabaseConfig], name: &str) -> Uuid {
+    match databases.iter().find(|db| db.name == name) {
+        Some(db) => db.uuid(),
+        None if name == EXAMPLES_NAME => EXAMPLES_UUID,
+        None => Uuid::new_v5(&UUID_NAMESPACE, name.as_bytes()),
+    }
+}
+
+/// Resolve a logical database name to its SQLite file
+pub fn resolve(root: &Path, databases: &[DatabaseConfig], name: &str) -> Result<PathBuf> {
+    if let Some(db) = databases.iter().find(|db| db.name == name) {
+        return Ok(db.path_abs(root));
+    }
+    if name == EXAMPLES_NAME {
+        return Ok(root.join(EXAMPLES_PATH));
+    }
+
+    let known: Vec<&str> = std::iter::once(EXAMPLES_NAME)
+        .chain(databases.iter().map(|db| db.name.as_str()))
+        .collect();
+    bail!("Unknown database '{}'. Known: {}", name, known.join(", "))
+}
+
+/// Register (or update) every configured database as a Superset connection.
+/// Returns the number of connections written.
+pub fn register_all(root: &Path, databases: &[DatabaseConfig]) -> Result<usize> {
+    if databases.is_empty() {
+        return Ok(0);
+    }
+
+    let db_path = root.join("superset_home").join("superset.db");
+    if !db_path.exists() {
+        bail!("superset.db not found at {}", db_path.display());
+    }
+    let mut conn = Connection::open(&db_path)
+        .with_context(|| format!("Failed to open {}", db_path.display()))?;
+    let tx = conn.transaction()?;
+    let now = Utc::now().format("%Y-%m-%d %H:%M:%S%.6f").to_string();
+
+    for db in databases {
+        let uuid = db.uuid();
+        let uri = db.sqlalchemy_uri(root);
+
+        // Match by UUID first, then adopt a connection created by hand with the same name
+        let existing: Option<i64> = tx
+            .query_row("SELECT id FROM dbs WHERE uuid = ?1", [uuid], |r| r.get(0))
+            .optional()?;
+        let existing = match existing {
+            Some(id) => Some(id),
+            None => tx
+                .query_row("SELECT id FROM dbs WHERE database_name = ?1", [&db.name], |r| r.get(0))
+                .optional()?,
+        };
+
+        match existing {
+            Some(id) => {
+                tx.execute(
+                    "UPDATE dbs SET database_name = ?1, sqlalchemy_uri = ?2, uuid = ?3, changed_on = ?4 WHERE id = ?5",
+                    params![db.name, uri, uuid, now, id],
+                )?;
+                info!("  🗄️ {} updated (id={})", db.name, id);
+            }
+            None => {
+                let extra = serde_json::json!({
+                    "metadata_params": {}, "engine_params": {}, "metadata_cache_timeout": {},
+                    "schemas_allowed_for_file_upload": []
+                })
+                .to_string();
+                tx.execute(
+                    "INSERT INTO dbs (database_name, sqlalchemy_uri, uuid, extra, expose_in_sqllab, allow_dml,
+                     allow_file_upload, created_on, changed_on, created_by_fk, changed_by_fk)
+                     VALUES (?1, ?2, ?3, ?4, 1, 1, 1, ?5, ?5, 1, 1)",
+                    params![db.name, uri, uuid, extra, now],
+                )?;
+                info!("  🗄️ {} registered -> {}", db.name, db.path.display());
+            }
+        }
+    }
+
+    tx.commit()?;
+    Ok(databases.len())
+}
+
+/// Flag `columns` of the existing Superset dataset for `table_name` as temporal
+/// (`is_dttm`), so charts can use them as the time axis without editing the
+/// dataset. Datasets created later pick this up from the DATETIME column type.
+/// Epoch seconds also get `python_date_format = 'epoch_s'`, without which
+/// Superset reads them as plain numbers. Returns the number of dataset columns
+/// updated.
+pub fn mark_temporal_columns(
+    root: &Path,
+    database_name: &str,
+    table_name: &str,
+    columns: &[String],
+    storage: DateStorage,
+) -> Result<usize> {
+    let db_path = root.join("superset_home").join("superset.db");
+    if columns.is_empty() || !db_path.exists() {
+        return Ok(0);
+    }
+    let conn = Connection::open(&db_path)
+        .with_context(|| format!("Failed to open {}", db_path.display()))?;
+    let now = Utc::now().format("%Y-%m-%d %H:%M:%S%.6f").to_string();
+    let python_date_format = match storage {
+        DateStorage::Epoch => Some("epoch_s"),
+        DateStorage::Iso => None,
+    };
+
+    let mut updated = 0;
+    for column in columns {
+        updated += conn.execute(
+            "UPDATE table_columns SET is_dttm = 1, type = 'DATETIME', python_date_format = ?5, changed_on = ?1
+             WHERE column_name = ?2 AND table_id IN (
+                 SELECT t.id FROM tables t JOIN dbs d ON d.id = t.database_id
+                 WHERE t.table_name = ?3 AND d.database_name = ?4)",
+            params![now, column, table_name, database_name, python_date_format],
+        )?;
+    }
+    Ok(updated)
+}
+
+/// Write column profiles into the existing Superset dataset for `table_name`:
+/// a generated `table_columns.description` (never over one written by hand)
+/// and `column_stats` in the dataset's `extra` JSON. Returns the number of
+/// column descriptions written.
+pub fn push_column_stats(root: &Path, database_name: &str, table_name: &str, profiles: &[ColumnProfile]) -> Result<usize> {
+    let db_path = root.join("superset_home").join("superset.db");
+    if profiles.is_empty() || !db_path.exists() {
+        return Ok(0);
+    }
+    let mut conn = Connection::open(&db_path)
+        .with_context(|| format!("Failed to open {}", db_path.display()))?;
+    let tx = conn.transaction()?;
+    let now = Utc::now().format("%Y-%m-%d %H:%M:%S%.6f").to_string();
+
+    let datasets: Vec<(i64, Option<String>)> = tx
+        .prepare(
+            "SELECT t.id, t.extra FROM tables t JOIN dbs d ON d.id = t.database_id
+             WHERE t.table_name = ?1 AND d.database_name = ?2",
+        )?
+        .query_map(params![table_name, database_name], |r| Ok((r.get(0)?, r.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let stats: serde_json::Map<String, serde_json::Value> = profiles
+        .iter()
+        .map(|p| (p.column.clone(), p.stats()))
+        .collect();
+
+    let mut updated = 0;
+    for (table_id, extra) in datasets {
+        let mut extra: serde_json::Value = extra
+            .as_deref()
+            .and_then(|e| serde_json::from_str(e).ok())
+            .filter(serde_json::Value::is_object)
+            .unwrap_or_else(|| serde_json::json!({}));
+        extra["column_stats"] = serde_json::Value::Object(stats.clone());
+        extra["column_stats_at"] = serde_json::json!(now);
+        tx.execute(
+            "UPDATE tables SET extra = ?1, changed_on = ?2 WHERE id = ?3",
+            params![extra.to_string(), now, table_id],
+        )?;
+
+        for profile in profiles {
+            updated += tx.execute(
+                "UPDATE table_columns SET description = ?1, changed_on = ?2
+                 WHERE table_id = ?3 AND column_name = ?4
+                   AND (description IS NULL OR description = '' OR substr(description, 1, length(?5)) = ?5)",
+                params![profile.description(), now, table_id, profile.column, PROFILE_DESCRIPTION_PREFIX],
+            )?;
+        }
+    }
+    tx.commit()?;
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_register_is_idempotent_and_resolves() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("superset_home")).unwrap();
+        let conn = Connection::open(root.join("superset_home").join("superset.db")).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE dbs (id INTEGER PRIMARY KEY, database_name TEXT, sqlalchemy_uri TEXT, uuid BLOB,
+                extra TEXT, expose_in_sqllab BOOLEAN, allow_dml BOOLEAN, allow_file_upload BOOLEAN,
+                created_on DATETIME, changed_on DATETIME, created_by_fk INTEGER, changed_by_fk INTEGER);
+             INSERT INTO dbs (database_name, sqlalchemy_uri) VALUES ('ops', 'sqlite:///old.db');",
+        )
+        .unwrap();
+
+        let databases = vec![
+            DatabaseConfig { name: "finance".into(), path: "data/finance.db".into(), uuid: None },
+            DatabaseConfig { name: "ops".into(), path: "data/ops.db".into(), uuid: None },
+        ];
+        assert_eq!(register_all(root, &databases).unwrap(), 2);
+        assert_eq!(register_all(root, &databases).unwrap(), 2);
+
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM dbs", [], |r| r.get(0)).unwrap();
+        assert_eq!(count, 2);
+        let uuid: Uuid = conn
+            .query_row("SELECT uuid FROM dbs WHERE database_name = 'finance'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(uuid, databases[0].uuid());
+
+        assert_eq!(resolve(root, &databases, "ops").unwrap(), root.join("data/ops.db"));
+        assert_eq!(resolve(root, &databases, "examples").unwrap(), root.join("examples.db"));
+        assert!(resolve(root, &databases, "hr").is_err());
+    }
+
+    #[test]
+    fn test_push_column_stats_keeps_manual_descriptions() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("superset_home")).unwrap();
+        let conn = Connection::open(root.join("superset_home").join("superset.db")).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE dbs (id INTEGER PRIMARY KEY, database_name TEXT);
+             CREATE TABLE tables (id INTEGER PRIMARY KEY, table_name TEXT, database_id INTEGER, extra TEXT, changed_on DATETIME);
+             CREATE TABLE table_columns (id INTEGER PRIMARY KEY, table_id INTEGER, column_name TEXT, description TEXT, changed_on DATETIME);
+             INSERT INTO dbs VALUES (1, 'examples');
+             INSERT INTO tables VALUES (7, 'sales', 1, '{\"certified_by\": \"ops\"}', NULL);
+             INSERT INTO table_columns VALUES (1, 7, 'region', NULL, NULL), (2, 7, 'amount', 'Сумма без НДС', NULL);",
+        )
+        .unwrap();
+
+        let profile = |column: &str| ColumnProfile {
+            column: column.into(),
+            min: Some("1".into()),
+            max: Some("9".into()),
+            distinct: 5,
+            sampled: false,
+            nulls: 0,
+        };
+        let profiles = vec![profile("region"), profile("amount")];
+        assert_eq!(push_column_stats(root, "examples", "sales", &profiles).unwrap(), 1);
+        assert_eq!(push_column_stats(root, "examples", "sales", &profiles).unwrap(), 1, "own description is refreshed");
+
+        let description: String = conn
+            .query_row("SELECT description FROM table_columns WHERE column_name = 'region'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(description, "📊 от 1 до 9 · различных значений: 5");
+        let manual: String = conn
+            .query_row("SELECT description FROM table_columns WHERE column_name = 'amount'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(manual, "Сумма без НДС");
+        let extra: String = conn.query_row("SELECT extra FROM tables WHERE id = 7", [], |r| r.get(0)).unwrap();
+        let extra: serde_json::Value = serde_json::from_str(&extra).unwrap();
+        assert_eq!(extra["certified_by"], "ops");
+        assert_eq!(extra["column_stats"]["region"]["distinct"], 5);
+    }
+
+    #[test]
+    fn test_mark_temporal_columns_sets_epoch_format() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("superset_home")).unwrap();
+        let conn = Connection::open(root.join("superset_home").join("superset.db")).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE dbs (id INTEGER PRIMARY KEY, database_name TEXT);
+             CREATE TABLE tables (id INTEGER PRIMARY KEY, table_name TEXT, database_id INTEGER);
+             CREATE TABLE table_columns (id INTEGER PRIMARY KEY, table_id INTEGER, column_name TEXT, type TEXT,
+                is_dttm BOOLEAN, python_date_format TEXT, changed_on DATETIME);
+             INSERT INTO dbs VALUES (1, 'examples');
+             INSERT INTO tables VALUES (7, 'trips', 1);
+             INSERT INTO table_columns VALUES (1, 7, 'trip_date', 'INTEGER', 0, NULL, NULL);",
+        )
+        .unwrap();
+        let columns = vec!["trip_date".to_string()];
+        let format = || -> Option<String> {
+            conn.query_row("SELECT python_date_format FROM table_columns WHERE id = 1", [], |r| r.get(0)).unwrap()
+        };
+
+        assert_eq!(mark_temporal_columns(root, "examples", "trips", &columns, DateStorage::Epoch).unwrap(), 1);
+        assert_eq!(format().as_deref(), Some("epoch_s"));
+        mark_temporal_columns(root, "examples", "trips", &columns, DateStorage::Iso).unwrap();
+        assert_eq!(format(), None);
+    }
+}
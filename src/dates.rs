@@ -0,0 +1,174 @@
+//! Date column detection and normalization for `load-data`
+//!
+//! Spreadsheets exported from 1C and Excel carry dates as `31.12.2024`,
+//! `2024/12/31` or bare Excel serial numbers, which land in SQLite as TEXT or
+//! numbers Superset can't put on a time axis. A column whose sampled values
+//! all parse with one format is declared `DATETIME` and its values are
+//! rewritten as ISO-8601 text (or unix epoch seconds).
+
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use rusqlite::types::Value;
+
+/// Non-empty values sampled per column for detection
+pub const SAMPLE_VALUES: usize = 100;
+
+/// Declared SQLite type of detected date columns (Superset treats it as temporal)
+pub const DATETIME_TYPE: &str = "DATETIME";
+
+/// Date part formats, most specific first. Day-first wins over month-first
+/// unless a value proves otherwise (`12/31/2024`).
+const DATE_PATTERNS: &[&str] = &[
+    "%Y-%m-%d", "%d.%m.%Y", "%Y/%m/%d", "%Y.%m.%d", "%d/%m/%Y", "%m/%d/%Y", "%d-%m-%Y",
+];
+
+/// Time part formats accepted after a space or `T`
+const TIME_PATTERNS: &[&str] = &["%H:%M:%S%.f", "%H:%M:%S", "%H:%M"];
+
+/// Excel serial day numbers cover 1900-01-01 .. 9999-12-31
+const EXCEL_SERIAL_RANGE: std::ops::RangeInclusive<f64> = 1.0..=2_958_465.0;
+
+/// Serials a number column must stay within to be taken for dates
+/// (1954-10-03 .. 2119-01-10): counts and tonnages rarely all land here
+const EXCEL_SERIAL_DETECT: std::ops::RangeInclusive<f64> = 20_000.0..=80_000.0;
+
+/// How normalized dates are stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DateStorage {
+    /// `YYYY-MM-DD` or `YYYY-MM-DD HH:MM:SS` text
+    #[default]
+    Iso,
+    /// Unix epoch seconds (UTC)
+    Epoch,
+}
+
+/// Format shared by every value of a date column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateFormat {
+    /// chrono pattern of the date part, optionally followed by a time
+    Pattern(&'static str),
+    /// Days since 1899-12-30, fraction = time of day
+    ExcelSerial,
+}
+
+impl DateFormat {
+    /// Parse one value; `bool` is whether it carried a time of day
+    pub fn parse(&self, value: &str) -> Option<(NaiveDateTime, bool)> {
+        let value = value.trim();
+        match self {
+            DateFormat::Pattern(pattern) => {
+                let (date, time) = match value.find([' ', 'T']) {
+                    Some(pos) => (&value[..pos], Some(value[pos + 1..].trim())),
+                    None => (value, None),
+                };
+                let date = NaiveDate::parse_from_str(date, pattern).ok()?;
+                match time {
+                    Some(time) => {
+                        let time = TIME_PATTERNS
+                            .iter()
+                            .find_map(|p| NaiveTime::parse_from_str(time, p).ok())?;
+                        Some((date.and_time(time), true))
+                    }
+                    None => Some((date.and_hms_opt(0, 0, 0)?, false)),
+                }
+            }
+            DateFormat::ExcelSerial => {
+                let serial: f64 = value.replace(',', ".").parse().ok()?;
+                if !EXCEL_SERIAL_RANGE.contains(&serial) {
+                    return None;
+                }
+                let epoch = NaiveDate::from_ymd_opt(1899, 12, 30)?.and_hms_opt(0, 0, 0)?;
+                let seconds = (serial * 86_400.0).round() as i64;
+                Some((epoch + Duration::seconds(seconds), serial.fract() != 0.0))
+            }
+        }
+    }
+
+    /// Normalized SQL value, or `None` if `value` doesn't parse
+    pub fn normalize(&self, value: &str, storage: DateStorage) -> Option<Value> {
+        let (datetime, has_time) = self.parse(value)?;
+        Some(match storage {
+            DateStorage::Epoch => Value::Integer(datetime.and_utc().timestamp()),
+            DateStorage::Iso if has_time => Value::Text(datetime.format("%Y-%m-%d %H:%M:%S").to_string()),
+            DateStorage::Iso => Value::Text(datetime.format("%Y-%m-%d").to_string()),
+        })
+    }
+}
+
+/// Column names that mean a date/time column on their own
+pub const DATE_NAMES: &[&str] = &["date", "datetime", "timestamp", "time", "day", "period", "дата", "день", "период"];
+
+/// Words that make a compound name a date (`order_date`, `Дата отгрузки`);
+/// `year`/`month` aren't here: `cargo_tons_year` holds tons, not dates
+const DATE_NAME_WORDS: &[&str] = &["date", "datetime", "timestamp", "дата"];
+
+/// Whether a column is named like a date
+pub fn is_date_name(column: &str) -> bool {
+    let lower = column.trim().to_lowercase();
+    DATE_NAMES.contains(&lower.as_str())
+        || lower.split(|c: char| !c.is_alphanumeric()).any(|word| DATE_NAME_WORDS.contains(&word))
+}
+
+/// The single format all (non-empty) sampled values parse with, if any
+pub fn detect(column: &str, samples: &[String]) -> Option<DateFormat> {
+    let samples: Vec<&str> = samples
+        .iter()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .take(SAMPLE_VALUES)
+        .collect();
+    if samples.is_empty() {
+        return None;
+    }
+
+    // Plain numbers are Excel serials only under a date-like header: ids and amounts aren't dates
+    if samples.iter().all(|s| s.replace(',', ".").parse::<f64>().is_ok()) {
+        let serials = samples.iter().all(|s| {
+            s.replace(',', ".").parse::<f64>().is_ok_and(|n| EXCEL_SERIAL_DETECT.contains(&n))
+        });
+        return (serials && is_date_name(column)).then_some(DateFormat::ExcelSerial);
+    }
+
+    DATE_PATTERNS
+        .iter()
+        .map(|p| DateFormat::Pattern(*p))
+        .find(|format| samples.iter().all(|s| format.parse(s).is_some()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_detect_formats() {
+        assert_eq!(detect("x", &strings(&["31.12.2024", "01.02.2023"])), Some(DateFormat::Pattern("%d.%m.%Y")));
+        assert_eq!(detect("x", &strings(&["2024/12/31", ""])), Some(DateFormat::Pattern("%Y/%m/%d")));
+        assert_eq!(detect("x", &strings(&["05/06/2024", "12/31/2024"])), Some(DateFormat::Pattern("%m/%d/%Y")));
+        assert_eq!(detect("Дата", &strings(&["45657", "45292.5"])), Some(DateFormat::ExcelSerial));
+        assert_eq!(detect("order_date", &strings(&["45657"])), Some(DateFormat::ExcelSerial));
+        assert_eq!(detect("amount", &strings(&["45657", "45292"])), None);
+        assert_eq!(detect("cargo_tons_year", &strings(&["45657", "45292"])), None);
+        assert_eq!(detect("month", &strings(&["1", "12"])), None);
+        assert_eq!(detect("date", &strings(&["1", "12"])), None);
+        assert_eq!(detect("name", &strings(&["Москва", "31.12.2024"])), None);
+    }
+
+    #[test]
+    fn test_normalize() {
+        let dmy = DateFormat::Pattern("%d.%m.%Y");
+        assert_eq!(dmy.normalize("31.12.2024", DateStorage::Iso), Some(Value::Text("2024-12-31".into())));
+        assert_eq!(
+            dmy.normalize("31.12.2024 14:30", DateStorage::Iso),
+            Some(Value::Text("2024-12-31 14:30:00".into()))
+        );
+        assert_eq!(dmy.normalize("01.01.1970", DateStorage::Epoch), Some(Value::Integer(0)));
+        assert_eq!(
+            DateFormat::ExcelSerial.normalize("45657", DateStorage::Iso),
+            Some(Value::Text("2024-12-31".into()))
+        );
+        assert!(dmy.normalize("not a date", DateStorage::Iso).is_none());
+    }
+}
@@ -2,11 +2,122 @@
 //! 
 //! Imports CSV data into SQLite database for offline Superset dashboards.
 //! Designed for air-gapped networks without internet access.
+//!
+//! Every CSV is checked against the expected schema before anything is
+//! written: columns are matched by header name (order doesn't matter),
+//! values are type-checked with row numbers in the report, and the whole
+//! import runs in one transaction, so a bad file never leaves half-loaded
+//! or shifted data behind.
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use rusqlite::types::Value;
 use rusqlite::Connection;
 use std::path::Path;
 
+/// Stop collecting errors after this many (the report stays readable)
+const MAX_REPORTED_ERRORS: usize = 20;
+
+/// Expected column type in a demo CSV
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnType {
+    Integer,
+    Real,
+    Text,
+}
+
+/// Expected layout of one demo CSV
+struct CsvSchema {
+    file: &'static str,
+    table: &'static str,
+    label: &'static str,
+    columns: &'static [(&'static str, ColumnType)],
+}
+
+use ColumnType::{Integer, Real, Text};
+
+const SCHEMAS: &[CsvSchema] = &[
+    CsvSchema {
+        file: "rzd_stations_full.csv",
+        table: "rzd_stations",
+        label: "🚉 станции",
+        columns: STATION_COLUMNS,
+    },
+    // Fallback if the full dataset is not shipped
+    CsvSchema {
+        file: "rzd_stations.csv",
+        table: "rzd_stations",
+        label: "🚉 станции",
+        columns: STATION_COLUMNS,
+    },
+    CsvSchema {
+        file: "rzd_routes.csv",
+        table: "rzd_routes",
+        label: "🛤️ маршруты",
+        columns: &[
+            ("id", Integer), ("origin_id", Integer), ("origin_name", Text), ("dest_id", Integer),
+            ("dest_name", Text), ("distance_km", Real), ("trains_per_day", Integer), ("geometry", Text),
+        ],
+    },
+    CsvSchema {
+        file: "rzd_monthly_stats.csv",
+        table: "rzd_monthly_stats",
+        label: "📊 месячная статистика",
+        columns: &[
+            ("month", Integer), ("year", Integer), ("passengers_mln", Real), ("cargo_mln_tons", Real),
+            ("revenue_bln_rub", Real), ("on_time_pct", Real),
+        ],
+    },
+    CsvSchema {
+        file: "rzd_cargo_types.csv",
+        table: "rzd_cargo_types",
+        label: "📦 типы грузов",
+        columns: &[
+            ("cargo_type", Text), ("volume_mln_tons", Real), ("share_pct", Real), ("revenue_bln_rub", Real),
+        ],
+    },
+    CsvSchema {
+        file: "rzd_daily_operations.csv",
+        table: "rzd_daily_operations",
+        label: "📈 ежедневные операции",
+        columns: &[
+            ("date", Text), ("region", Text), ("route_type", Text), ("passengers_thousands", Real),
+            ("cargo_tons_thousands", Real), ("revenue_mln_rub", Real), ("avg_speed_kmh", Real),
+            ("delay_minutes", Integer), ("trains_count", Integer), ("occupancy_pct", Real),
+        ],
+    },
+    CsvSchema {
+        file: "rzd_incidents.csv",
+        table: "rzd_incidents",
+        label: "⚠️ инциденты",
+        columns: &[
+            ("incident_id", Text), ("date", Text), ("time", Text), ("region", Text), ("railway_branch", Text),
+            ("incident_type", Text), ("severity", Text), ("duration_minutes", Integer),
+            ("affected_trains", Integer), ("resolved", Text), ("cause", Text), ("description", Text),
+        ],
+    },
+    CsvSchema {
+        file: "rzd_kpi_metrics.csv",
+        table: "rzd_kpi_metrics",
+        label: "📊 KPI метрики",
+        columns: &[
+            ("year", Integer), ("quarter", Text), ("metric_name", Text), ("metric_value", Real), ("unit", Text),
+            ("yoy_change_pct", Real), ("target_value", Real), ("target_met", Text),
+        ],
+    },
+];
+
+const STATION_COLUMNS: &[(&str, ColumnType)] = &[
+    ("id", Integer), ("name", Text), ("city", Text), ("region", Text), ("latitude", Real),
+    ("longitude", Real), ("passengers_day", Integer), ("cargo_tons_year", Integer),
+    ("railway_branch", Text), ("station_class", Integer),
+];
+
+/// Validated rows of one CSV, ready to insert
+struct ParsedCsv {
+    schema: &'static CsvSchema,
+    rows: Vec<Vec<Value>>,
+}
+
 /// Import all RZD demo data into the examples database
 pub fn import_demo_data(root: &Path) -> Result<()> {
     let examples_db = root.join("examples.db");
@@ -16,38 +127,155 @@ pub fn import_demo_data(root: &Path) -> Result<()> {
     println!("   База: {}", examples_db.display());
     println!("   Данные: {}", demo_data_dir.display());
     
+    // Validate everything first: a broken file must not leave a half-updated database
+    let mut parsed = Vec::new();
+    let mut loaded_tables = Vec::new();
+    for schema in SCHEMAS {
+        if loaded_tables.contains(&schema.table) {
+            continue;
+        }
+        let csv_path = demo_data_dir.join(schema.file);
+        if !csv_path.exists() {
+            println!("   ⚠️ Файл не найден: {}", csv_path.display());
+            continue;
+        }
+        parsed.push(parse_csv(&csv_path, schema)?);
+        loaded_tables.push(schema.table);
+    }
+    
+    let snapshot_config = crate::config::Config::load_or_create(root)?.snapshots;
+    crate::snapshots::take(root, &snapshot_config, &examples_db, "import-demo")?;
+
     // Open or create the database
-    let conn = Connection::open(&examples_db)
+    let mut conn = Connection::open(&examples_db)
         .context("Не удалось открыть базу данных examples.db")?;
     
     // Create tables
     create_tables(&conn)?;
     
-    // Import data from CSV files
-    let files = [
-        ("rzd_stations_full.csv", import_stations as fn(&Connection, &Path) -> Result<()>),
-        ("rzd_stations.csv", import_stations), // Fallback if full not found
-        ("rzd_routes.csv", import_routes),
-        ("rzd_monthly_stats.csv", import_monthly_stats),
-        ("rzd_cargo_types.csv", import_cargo_types),
-        ("rzd_daily_operations.csv", import_daily_operations),
-        ("rzd_incidents.csv", import_incidents),
-        ("rzd_kpi_metrics.csv", import_kpi_metrics),
-    ];
-    
-    for (filename, import_fn) in files {
-        let csv_path = demo_data_dir.join(filename);
-        if csv_path.exists() {
-            import_fn(&conn, &csv_path)?;
-        } else {
-            println!("   ⚠️ Файл не найден: {}", csv_path.display());
-        }
+    let tx = conn.transaction()?;
+    for csv in &parsed {
+        let count = insert_rows(&tx, csv)?;
+        println!("   {}: импортировано {} ({})", csv.schema.label, count, csv.schema.file);
     }
+    tx.commit()?;
     
     println!("✅ Импорт завершён!");
     Ok(())
 }
 
+/// Read a CSV and check it against `schema`, mapping columns by header name
+fn parse_csv(csv_path: &Path, schema: &'static CsvSchema) -> Result<ParsedCsv> {
+    let mut rdr = csv::Reader::from_path(csv_path)
+        .with_context(|| format!("Ошибка чтения CSV файла {}", schema.file))?;
+    
+    let headers: Vec<String> = rdr
+        .headers()?
+        .iter()
+        .map(|h| h.trim_start_matches('\u{feff}').trim().to_lowercase())
+        .collect();
+    
+    let mut positions = Vec::with_capacity(schema.columns.len());
+    let mut missing = Vec::new();
+    for (name, _) in schema.columns {
+        match headers.iter().position(|h| h == name) {
+            Some(pos) => positions.push(pos),
+            None => missing.push(*name),
+        }
+    }
+    if !missing.is_empty() {
+        bail!(
+            "{}: нет обязательных колонок: {}\n   Найдены колонки: {}",
+            schema.file,
+            missing.join(", "),
+            headers.join(", ")
+        );
+    }
+    for extra in headers.iter().filter(|h| !schema.columns.iter().any(|(name, _)| *name == h.as_str())) {
+        println!("   ⚠️ {}: колонка '{}' не используется", schema.file, extra);
+    }
+    
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    for result in rdr.records() {
+        let record = result.with_context(|| format!("{}: повреждённая строка", schema.file))?;
+        let line = record.position().map_or(0, |p| p.line());
+        
+        // Skip empty rows
+        if record.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        
+        let mut row = Vec::with_capacity(positions.len());
+        for (&pos, (name, column_type)) in positions.iter().zip(schema.columns) {
+            let raw = record.get(pos).unwrap_or("").trim();
+            match parse_value(raw, *column_type) {
+                Some(value) => row.push(value),
+                None => {
+                    if errors.len() < MAX_REPORTED_ERRORS {
+                        errors.push(format!(
+                            "строка {}, колонка {}: '{}' — ожидалось {}",
+                            line,
+                            name,
+                            raw,
+                            type_name(*column_type)
+                        ));
+                    }
+                    row.push(Value::Null);
+                }
+            }
+        }
+        rows.push(row);
+    }
+    
+    if !errors.is_empty() {
+        bail!("{}: ошибки типов данных, импорт отменён:\n   {}", schema.file, errors.join("\n   "));
+    }
+    Ok(ParsedCsv { schema, rows })
+}
+
+/// Convert a CSV field; `None` if it doesn't match the type (empty -> NULL)
+fn parse_value(raw: &str, column_type: ColumnType) -> Option<Value> {
+    if raw.is_empty() {
+        return Some(Value::Null);
+    }
+    match column_type {
+        Integer => raw.parse::<i64>().ok().map(Value::Integer),
+        Real => raw.replace(',', ".").parse::<f64>().ok().map(Value::Real),
+        Text => Some(Value::Text(raw.to_string())),
+    }
+}
+
+fn type_name(column_type: ColumnType) -> &'static str {
+    match column_type {
+        Integer => "целое число",
+        Real => "число",
+        Text => "текст",
+    }
+}
+
+/// Replace the table contents with the parsed rows
+fn insert_rows(conn: &Connection, csv: &ParsedCsv) -> Result<usize> {
+    let schema = csv.schema;
+    conn.execute(&format!("DELETE FROM {}", schema.table), [])?;
+    
+    let names: Vec<&str> = schema.columns.iter().map(|(name, _)| *name).collect();
+    let placeholders: Vec<String> = (1..=names.len()).map(|i| format!("?{}", i)).collect();
+    let sql = format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        schema.table,
+        names.join(", "),
+        placeholders.join(", ")
+    );
+    
+    let mut stmt = conn.prepare(&sql)?;
+    for row in &csv.rows {
+        stmt.execute(rusqlite::params_from_iter(row.iter()))
+            .with_context(|| format!("Ошибка вставки в {}", schema.table))?;
+    }
+    Ok(csv.rows.len())
+}
+
 /// Create RZD tables if they don't exist
 fn create_tables(conn: &Connection) -> Result<()> {
     println!("   📋 Создание таблиц...");
@@ -144,20 +372,6 @@ fn create_tables(conn: &Connection) -> Result<()> {
         [],
     ).context("Ошибка создания таблицы rzd_kpi_metrics")?;
     
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS rzd_kpi_metrics (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            year INTEGER,
-            quarter TEXT,
-            metric_name TEXT,
-            metric_value REAL,
-            unit TEXT,
-            yoy_change_pct REAL,
-            target_value REAL,
-            target_met TEXT
-        )",
-        [],
-    ).context("Ошибка создания таблицы rzd_kpi_metrics")?;
 
     conn.execute(
         "CREATE TABLE IF NOT EXISTS rzd_routes (
@@ -167,7 +381,6 @@ fn create_tables(conn: &Connection) -> Result<()> {
             dest_id INTEGER,
             dest_name TEXT,
             distance_km REAL,
-            distance_km REAL,
             trains_per_day INTEGER,
             geometry TEXT
         )",
@@ -177,284 +390,39 @@ fn create_tables(conn: &Connection) -> Result<()> {
     Ok(())
 }
 
-fn import_stations(conn: &Connection, csv_path: &Path) -> Result<()> {
-    println!("   🚉 Импорт станций ({})", csv_path.file_name().unwrap_or_default().to_string_lossy());
-    
-    // Clear existing data only if importing full dataset or if table is empty
-    let count: i32 = conn.query_row("SELECT count(*) FROM rzd_stations", [], |row| row.get(0)).unwrap_or(0);
-    if count > 0 && csv_path.file_name().unwrap().to_string_lossy() == "rzd_stations.csv" {
-         // If we already have data (likely from full dataset), skip the small one
-         println!("      Пропуск rzd_stations.csv так как данные уже есть");
-         return Ok(());
-    }
-    
-    conn.execute("DELETE FROM rzd_stations", [])?;
-    
-    let mut rdr = csv::Reader::from_path(csv_path)
-        .context("Ошибка чтения CSV файла станций")?;
-    
-    let mut count = 0;
-    for result in rdr.records() {
-        let record = result?;
-        
-        conn.execute(
-            "INSERT INTO rzd_stations 
-             (id, name, city, region, latitude, longitude, passengers_day, cargo_tons_year, railway_branch, station_class)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-            rusqlite::params![
-                record.get(0).unwrap_or("0").parse::<i32>().unwrap_or(0),
-                record.get(1).unwrap_or(""),
-                record.get(2).unwrap_or(""),
-                record.get(3).unwrap_or(""),
-                record.get(4).unwrap_or("0").parse::<f64>().unwrap_or(0.0),
-                record.get(5).unwrap_or("0").parse::<f64>().unwrap_or(0.0),
-                record.get(6).unwrap_or("0").parse::<i32>().unwrap_or(0),
-                record.get(7).unwrap_or("0").parse::<i32>().unwrap_or(0),
-                record.get(8).unwrap_or(""),
-                record.get(9).unwrap_or("0").parse::<i32>().unwrap_or(0),
-            ],
-        )?;
-        count += 1;
-    }
-    
-    println!("      Импортировано станций: {}", count);
-    Ok(())
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
 
-/// Import monthly statistics from CSV
-fn import_monthly_stats(conn: &Connection, csv_path: &Path) -> Result<()> {
-    println!("   📊 Импорт месячной статистики...");
-    
-    conn.execute("DELETE FROM rzd_monthly_stats", [])?;
-    
-    let mut rdr = csv::Reader::from_path(csv_path)
-        .context("Ошибка чтения CSV файла статистики")?;
-    
-    let mut count = 0;
-    for result in rdr.records() {
-        let record = result?;
-        
-        // Skip empty rows
-        if record.len() < 6 || record.get(0).map(|s| s.is_empty()).unwrap_or(true) {
-            continue;
-        }
-        
-        conn.execute(
-            "INSERT INTO rzd_monthly_stats 
-             (month, year, passengers_mln, cargo_mln_tons, revenue_bln_rub, on_time_pct)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            rusqlite::params![
-                record.get(0).unwrap_or("0").parse::<i32>().unwrap_or(0),
-                record.get(1).unwrap_or("0").parse::<i32>().unwrap_or(0),
-                record.get(2).unwrap_or("0").parse::<f64>().unwrap_or(0.0),
-                record.get(3).unwrap_or("0").parse::<f64>().unwrap_or(0.0),
-                record.get(4).unwrap_or("0").parse::<f64>().unwrap_or(0.0),
-                record.get(5).unwrap_or("0").parse::<f64>().unwrap_or(0.0),
-            ],
-        )?;
-        count += 1;
+    fn schema(file: &str) -> &'static CsvSchema {
+        SCHEMAS.iter().find(|s| s.file == file).unwrap()
     }
-    
-    println!("      Импортировано записей: {}", count);
-    Ok(())
-}
 
-/// Import cargo types from CSV
-fn import_cargo_types(conn: &Connection, csv_path: &Path) -> Result<()> {
-    println!("   📦 Импорт типов грузов...");
-    
-    conn.execute("DELETE FROM rzd_cargo_types", [])?;
-    
-    let mut rdr = csv::Reader::from_path(csv_path)
-        .context("Ошибка чтения CSV файла грузов")?;
-    
-    let mut count = 0;
-    for result in rdr.records() {
-        let record = result?;
-        
-        // Skip empty rows
-        if record.len() < 4 || record.get(0).map(|s| s.is_empty()).unwrap_or(true) {
-            continue;
-        }
-        
-        conn.execute(
-            "INSERT INTO rzd_cargo_types 
-             (cargo_type, volume_mln_tons, share_pct, revenue_bln_rub)
-             VALUES (?1, ?2, ?3, ?4)",
-            rusqlite::params![
-                record.get(0).unwrap_or(""),
-                record.get(1).unwrap_or("0").parse::<f64>().unwrap_or(0.0),
-                record.get(2).unwrap_or("0").parse::<f64>().unwrap_or(0.0),
-                record.get(3).unwrap_or("0").parse::<f64>().unwrap_or(0.0),
-            ],
-        )?;
-        count += 1;
-    }
-    
-    println!("      Импортировано типов: {}", count);
-    Ok(())
-}
+    #[test]
+    fn test_columns_mapped_by_name() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("rzd_cargo_types.csv");
+        std::fs::write(&path, "share_pct,cargo_type,revenue_bln_rub,volume_mln_tons,note\n28.5,Уголь,412.5,358.2,x\n").unwrap();
 
-/// Import daily operations from CSV
-fn import_daily_operations(conn: &Connection, csv_path: &Path) -> Result<()> {
-    println!("   📈 Импорт ежедневных операций...");
-    
-    conn.execute("DELETE FROM rzd_daily_operations", [])?;
-    
-    let mut rdr = csv::Reader::from_path(csv_path)
-        .context("Ошибка чтения CSV файла операций")?;
-    
-    let mut count = 0;
-    for result in rdr.records() {
-        let record = result?;
-        
-        if record.len() < 10 || record.get(0).map(|s| s.is_empty()).unwrap_or(true) {
-            continue;
-        }
-        
-        conn.execute(
-            "INSERT INTO rzd_daily_operations 
-             (date, region, route_type, passengers_thousands, cargo_tons_thousands, 
-              revenue_mln_rub, avg_speed_kmh, delay_minutes, trains_count, occupancy_pct)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-            rusqlite::params![
-                record.get(0).unwrap_or(""),
-                record.get(1).unwrap_or(""),
-                record.get(2).unwrap_or(""),
-                record.get(3).unwrap_or("0").parse::<f64>().unwrap_or(0.0),
-                record.get(4).unwrap_or("0").parse::<f64>().unwrap_or(0.0),
-                record.get(5).unwrap_or("0").parse::<f64>().unwrap_or(0.0),
-                record.get(6).unwrap_or("0").parse::<f64>().unwrap_or(0.0),
-                record.get(7).unwrap_or("0").parse::<i32>().unwrap_or(0),
-                record.get(8).unwrap_or("0").parse::<i32>().unwrap_or(0),
-                record.get(9).unwrap_or("0").parse::<f64>().unwrap_or(0.0),
-            ],
-        )?;
-        count += 1;
+        let parsed = parse_csv(&path, schema("rzd_cargo_types.csv")).unwrap();
+        assert_eq!(parsed.rows.len(), 1);
+        assert_eq!(parsed.rows[0][0], Value::Text("Уголь".into()));
+        assert_eq!(parsed.rows[0][1], Value::Real(358.2));
+        assert_eq!(parsed.rows[0][2], Value::Real(28.5));
     }
-    
-    println!("      Импортировано операций: {}", count);
-    Ok(())
-}
 
-/// Import incidents from CSV
-fn import_incidents(conn: &Connection, csv_path: &Path) -> Result<()> {
-    println!("   ⚠️ Импорт инцидентов...");
-    
-    conn.execute("DELETE FROM rzd_incidents", [])?;
-    
-    let mut rdr = csv::Reader::from_path(csv_path)
-        .context("Ошибка чтения CSV файла инцидентов")?;
-    
-    let mut count = 0;
-    for result in rdr.records() {
-        let record = result?;
-        
-        if record.len() < 12 || record.get(0).map(|s| s.is_empty()).unwrap_or(true) {
-            continue;
-        }
-        
-        conn.execute(
-            "INSERT INTO rzd_incidents 
-             (incident_id, date, time, region, railway_branch, incident_type, 
-              severity, duration_minutes, affected_trains, resolved, cause, description)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
-            rusqlite::params![
-                record.get(0).unwrap_or(""),
-                record.get(1).unwrap_or(""),
-                record.get(2).unwrap_or(""),
-                record.get(3).unwrap_or(""),
-                record.get(4).unwrap_or(""),
-                record.get(5).unwrap_or(""),
-                record.get(6).unwrap_or(""),
-                record.get(7).unwrap_or("0").parse::<i32>().unwrap_or(0),
-                record.get(8).unwrap_or("0").parse::<i32>().unwrap_or(0),
-                record.get(9).unwrap_or(""),
-                record.get(10).unwrap_or(""),
-                record.get(11).unwrap_or(""),
-            ],
-        )?;
-        count += 1;
-    }
-    
-    println!("      Импортировано инцидентов: {}", count);
-    Ok(())
-}
+    #[test]
+    fn test_bad_files_are_rejected() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("rzd_cargo_types.csv");
 
-/// Import KPI metrics from CSV
-fn import_kpi_metrics(conn: &Connection, csv_path: &Path) -> Result<()> {
-    println!("   📊 Импорт KPI метрик...");
-    
-    conn.execute("DELETE FROM rzd_kpi_metrics", [])?;
-    
-    let mut rdr = csv::Reader::from_path(csv_path)
-        .context("Ошибка чтения CSV файла KPI")?;
-    
-    let mut count = 0;
-    for result in rdr.records() {
-        let record = result?;
-        
-        if record.len() < 8 || record.get(0).map(|s| s.is_empty()).unwrap_or(true) {
-            continue;
-        }
-        
-        conn.execute(
-            "INSERT INTO rzd_kpi_metrics 
-             (year, quarter, metric_name, metric_value, unit, yoy_change_pct, target_value, target_met)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            rusqlite::params![
-                record.get(0).unwrap_or("0").parse::<i32>().unwrap_or(0),
-                record.get(1).unwrap_or(""),
-                record.get(2).unwrap_or(""),
-                record.get(3).unwrap_or("0").parse::<f64>().unwrap_or(0.0),
-                record.get(4).unwrap_or(""),
-                record.get(5).unwrap_or("0").parse::<f64>().unwrap_or(0.0),
-                record.get(6).unwrap_or("0").parse::<f64>().unwrap_or(0.0),
-                record.get(7).unwrap_or(""),
-            ],
-        )?;
-        count += 1;
-    }
-    
-    println!("      Импортировано KPI: {}", count);
-    Ok(())
-}
+        std::fs::write(&path, "cargo_type,volume_mln_tons,share_pct\nУголь,1,2\n").unwrap();
+        let err = parse_csv(&path, schema("rzd_cargo_types.csv")).unwrap_err().to_string();
+        assert!(err.contains("revenue_bln_rub"));
 
-/// Import routes from CSV
-fn import_routes(conn: &Connection, csv_path: &Path) -> Result<()> {
-    println!("   🛤️ Импорт маршрутов...");
-    
-    conn.execute("DELETE FROM rzd_routes", [])?;
-    
-    let mut rdr = csv::Reader::from_path(csv_path)
-        .context("Ошибка чтения CSV файла маршрутов")?;
-    
-    let mut count = 0;
-    for result in rdr.records() {
-        let record = result?;
-        
-        if record.len() < 8 || record.get(0).map(|s| s.is_empty()).unwrap_or(true) {
-            continue;
-        }
-        
-        conn.execute(
-            "INSERT INTO rzd_routes 
-             (id, origin_id, origin_name, dest_id, dest_name, distance_km, trains_per_day, geometry)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            rusqlite::params![
-                record.get(0).unwrap_or("0").parse::<i32>().unwrap_or(0),
-                record.get(1).unwrap_or("0").parse::<i32>().unwrap_or(0),
-                record.get(2).unwrap_or(""),
-                record.get(3).unwrap_or("0").parse::<i32>().unwrap_or(0),
-                record.get(4).unwrap_or(""),
-                record.get(5).unwrap_or("0").parse::<f64>().unwrap_or(0.0),
-                record.get(6).unwrap_or("0").parse::<i32>().unwrap_or(0),
-                record.get(7).unwrap_or(""),
-            ],
-        )?;
-        count += 1;
+        std::fs::write(&path, "cargo_type,volume_mln_tons,share_pct,revenue_bln_rub\nУголь,1,2,3\nНефть,много,2,3\n").unwrap();
+        let err = parse_csv(&path, schema("rzd_cargo_types.csv")).unwrap_err().to_string();
+        assert!(err.contains("строка 3, колонка volume_mln_tons: 'много'"));
     }
-    
-    println!("      Импортировано маршрутов: {}", count);
-    Ok(())
 }
@@ -0,0 +1,352 @@
+//! Post-processing of the static `docs/` folder served by DocsServer
+//!
+//! Pages in `docs/` are edited by hand, often in Notepad, and the browser
+//! opens `.md` files as bare text in whatever encoding it guesses. One pass
+//! of [`build`] fixes that: Windows-1251 `.md` files are re-saved as UTF-8, every
+//! `.md` gets an `.html` page next to it with the rendered text and an explicit
+//! `charset=utf-8`, and `toc.html` lists all of them. The docs server runs a
+//! pass at start and again whenever something in the folder changes, so an
+//! edit shows up on the next reload; `docs build [--watch]` does the same
+//! without the server.
+//!
+//! Generated pages carry [`MARKER`] and only such pages are overwritten or
+//! removed — a hand-written `.html` with the same name is left alone.
+
+use anyhow::{Context, Result};
+use notify::{RecursiveMode, Watcher};
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use crate::encoding;
+use crate::html::html_escape;
+
+/// First line of every generated page
+pub const MARKER: &str = "<!-- generated by superset-launcher from docs sources, edits will be lost -->";
+
+pub const TOC_FILE: &str = "toc.html";
+
+/// Folders with data rather than pages
+const SKIPPED_DIRS: &[&str] = &["demo_data", "demo_dashboard"];
+
+/// Editors save in several steps; rebuild once they are done
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// What a pass changed
+#[derive(Debug, Default)]
+pub struct BuildSummary {
+    /// Files re-saved as UTF-8
+    pub converted: Vec<PathBuf>,
+    /// Pages written (new or changed), `toc.html` included
+    pub written: Vec<PathBuf>,
+    /// Pages whose `.md` is gone
+    pub removed: Vec<PathBuf>,
+}
+
+impl BuildSummary {
+    pub fn is_empty(&self) -> bool {
+        self.converted.is_empty() && self.written.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// A `.md` page of the folder
+struct Page {
+    /// Relative to the docs folder, `/`-separated
+    source: String,
+    title: String,
+}
+
+/// Text of the first `# ` heading, else the file name
+fn page_title(markdown: &str, fallback: &str) -> String {
+    markdown
+        .lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| fallback.to_string())
+}
+
+/// `x.md` → `x.html`, also with an anchor; other links unchanged
+fn html_link(dest: &str) -> Option<String> {
+    if dest.contains("://") || dest.starts_with("mailto:") {
+        return None;
+    }
+    let (path, anchor) = dest.split_once('#').map_or((dest, None), |(path, anchor)| (path, Some(anchor)));
+    let stem = path.strip_suffix(".md")?;
+    Some(match anchor {
+        Some(anchor) => format!("{}.html#{}", stem, anchor),
+        None => format!("{}.html", stem),
+    })
+}
+
+fn layout(title: &str, up: &str, body: &str) -> String {
+    format!(
+        r#"{marker}
+<!DOCTYPE html>
+<html lang="ru">
+<head>
+    <meta charset="utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>{title}</title>
+    <style>
+        body {{ font-family: 'Segoe UI', Tahoma, sans-serif; background: #1a1a2e; color: #eaeaea; max-width: 900px; margin: 0 auto; padding: 30px 20px; line-height: 1.6; }}
+        a {{ color: #00d4ff; }}
+        nav {{ font-size: 0.9rem; margin-bottom: 20px; }}
+        code, pre {{ background: #16213e; border-radius: 4px; }}
+        code {{ padding: 2px 6px; }}
+        pre {{ padding: 12px; overflow-x: auto; }}
+        pre code {{ padding: 0; }}
+        table {{ border-collapse: collapse; }}
+        th, td {{ border: 1px solid #0f3460; padding: 6px 10px; }}
+    </style>
+</head>
+<body>
+    <nav><a href="{up}index.html">Главная</a> • <a href="{up}{toc}">Содержание</a></nav>
+{body}
+</body>
+</html>
+"#,
+        marker = MARKER,
+        title = html_escape(title),
+        up = up,
+        toc = TOC_FILE,
+        body = body,
+    )
+}
+
+/// The page generated from `markdown` found at `source` (relative path)
+pub fn render_page(source: &str, markdown: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    let events = Parser::new_ext(markdown, options).map(|event| match event {
+        Event::Start(Tag::Link { link_type, dest_url, title, id }) => {
+            let dest_url = html_link(&dest_url).map(CowStr::from).unwrap_or(dest_url);
+            Event::Start(Tag::Link { link_type, dest_url, title, id })
+        }
+        event => event,
+    });
+    let mut body = String::new();
+    html::push_html(&mut body, events);
+
+    let stem = Path::new(source).file_stem().and_then(|s| s.to_str()).unwrap_or(source);
+    let up = "../".repeat(source.matches('/').count());
+    layout(&page_title(markdown, stem), &up, &body)
+}
+
+fn render_toc(pages: &[Page]) -> String {
+    let mut body = String::from("<h1>Содержание</h1>\n");
+    let mut folder = None;
+    for page in pages {
+        let page_folder = page.source.rsplit_once('/').map(|(folder, _)| folder);
+        if folder != Some(page_folder) {
+            if folder.is_some() {
+                body.push_str("</ul>\n");
+            }
+            if let Some(name) = page_folder {
+                body.push_str(&format!("<h2>{}</h2>\n", html_escape(name)));
+            }
+            body.push_str("<ul>\n");
+            folder = Some(page_folder);
+        }
+        let href = html_link(&page.source).unwrap_or_else(|| page.source.clone());
+        body.push_str(&format!("<li><a href=\"{}\">{}</a></li>\n", html_escape(&href), html_escape(&page.title)));
+    }
+    if folder.is_some() {
+        body.push_str("</ul>\n");
+    }
+    layout("Содержание", "", &body)
+}
+
+/// A page of ours (or no file yet), as opposed to a hand-written one
+fn is_generated(path: &Path) -> bool {
+    match std::fs::read_to_string(path) {
+        Ok(text) => text.starts_with(MARKER),
+        Err(_) => !path.exists(),
+    }
+}
+
+/// Write `content` unless the file already has it; true when written.
+/// Unchanged files stay untouched so the watcher doesn't wake itself up.
+fn write_if_changed(path: &Path, content: &str) -> Result<bool> {
+    if std::fs::read_to_string(path).is_ok_and(|old| old == content) {
+        return Ok(false);
+    }
+    std::fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(true)
+}
+
+fn is_skipped(docs: &Path, path: &Path) -> bool {
+    path.strip_prefix(docs)
+        .ok()
+        .and_then(|relative| relative.components().next())
+        .is_some_and(|first| SKIPPED_DIRS.iter().any(|dir| first.as_os_str() == *dir))
+}
+
+/// One pass over `docs`: encodings, pages, table of contents
+pub fn build(docs: &Path) -> Result<BuildSummary> {
+    let mut summary = BuildSummary::default();
+    let mut pages = Vec::new();
+    let mut generated = Vec::new();
+    for entry in walkdir::WalkDir::new(docs)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| !is_skipped(docs, e.path()))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let path = entry.path();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("md") => {}
+            Some("html") => {
+                generated.push(path.to_path_buf());
+                continue;
+            }
+            _ => continue,
+        }
+        // Only the pages: data files and hand-written HTML keep their bytes
+        summary.converted.extend(encoding::convert_path(path, false)?.converted);
+        let source = path.strip_prefix(docs)?.to_string_lossy().replace('\\', "/");
+        let target = path.with_extension("html");
+        if !is_generated(&target) {
+            warn!("{} is hand-written, not replaced by the page of {}", target.display(), source);
+            continue;
+        }
+        let markdown = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        if write_if_changed(&target, &render_page(&source, &markdown))? {
+            summary.written.push(target);
+        }
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+        pages.push(Page { title: page_title(&markdown, stem), source });
+    }
+
+    // Root pages first, then folder by folder
+    pages.sort_by(|a, b| (a.source.contains('/'), &a.source).cmp(&(b.source.contains('/'), &b.source)));
+    let toc = docs.join(TOC_FILE);
+    if is_generated(&toc) && write_if_changed(&toc, &render_toc(&pages))? {
+        summary.written.push(toc.clone());
+    }
+
+    for page in generated {
+        let orphan = page != toc && !page.with_extension("md").exists();
+        if orphan && std::fs::read_to_string(&page).is_ok_and(|text| text.starts_with(MARKER)) {
+            std::fs::remove_file(&page)?;
+            summary.removed.push(page);
+        }
+    }
+    Ok(summary)
+}
+
+fn log_summary(summary: &BuildSummary) {
+    if summary.is_empty() {
+        return;
+    }
+    info!(
+        "📝 Docs rebuilt: {} re-saved as UTF-8, {} page(s) written, {} removed",
+        summary.converted.len(),
+        summary.written.len(),
+        summary.removed.len()
+    );
+}
+
+/// Build `docs` now and after every change for as long as `running` is set
+pub fn spawn_watcher(docs: PathBuf, running: Arc<AtomicBool>) {
+    tokio::spawn(async move {
+        let (tx, mut rx) = mpsc::channel(16);
+        let mut watcher = match notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            if let Ok(event) = res {
+                if event.kind.is_modify() || event.kind.is_create() || event.kind.is_remove() {
+                    let _ = tx.try_send(());
+                }
+            }
+        }) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                error!("Docs watcher not started: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = watcher.watch(&docs, RecursiveMode::Recursive) {
+            error!("Cannot watch {}: {}", docs.display(), e);
+            return;
+        }
+        info!("👀 Watching {} for edits", docs.display());
+
+        loop {
+            let build_docs = docs.clone();
+            match tokio::task::spawn_blocking(move || build(&build_docs)).await {
+                Ok(Ok(summary)) => log_summary(&summary),
+                Ok(Err(e)) => error!("Docs build failed: {:#}", e),
+                Err(e) => error!("Docs build task panicked: {}", e),
+            }
+
+            // Our own writes come back as events too; the next pass finds nothing to do
+            if rx.recv().await.is_none() {
+                break;
+            }
+            tokio::time::sleep(DEBOUNCE).await;
+            while rx.try_recv().is_ok() {}
+            if !running.load(Ordering::SeqCst) {
+                break;
+            }
+        }
+        info!("Docs watcher stopped");
+    });
+}
+
+/// `docs build --watch`: rebuild until Ctrl+C
+pub async fn watch(docs: &Path) -> Result<()> {
+    let running = Arc::new(AtomicBool::new(true));
+    spawn_watcher(docs.to_path_buf(), running.clone());
+    tokio::signal::ctrl_c().await?;
+    running.store(false, Ordering::SeqCst);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_build_pages_and_toc() {
+        let dir = tempdir().unwrap();
+        let docs = dir.path();
+        std::fs::create_dir_all(docs.join("ru")).unwrap();
+        std::fs::create_dir_all(docs.join("demo_data")).unwrap();
+        std::fs::write(docs.join("RELEASES.md"), "# Выпуски\n\nСм. [руководство](ru/guide.md#start)").unwrap();
+        // "# Гид" in Windows-1251
+        std::fs::write(docs.join("ru").join("guide.md"), [b'#', b' ', 0xC3, 0xE8, 0xE4]).unwrap();
+        std::fs::write(docs.join("ru").join("notes.html"), "<p>hand-written</p>").unwrap();
+        std::fs::write(docs.join("ru").join("notes.md"), "# Notes").unwrap();
+        std::fs::write(docs.join("demo_data").join("cp1251.csv"), [0xCC, 0xEE, 0xF1]).unwrap();
+        std::fs::write(docs.join("notes.txt"), [0xCC, 0xEE, 0xF1]).unwrap();
+
+        let summary = build(docs).unwrap();
+        assert_eq!(summary.converted, vec![docs.join("ru").join("guide.md")]);
+        assert_eq!(summary.written.len(), 3);
+        assert_eq!(std::fs::read(docs.join("demo_data").join("cp1251.csv")).unwrap(), [0xCC, 0xEE, 0xF1]);
+        assert_eq!(std::fs::read(docs.join("notes.txt")).unwrap(), [0xCC, 0xEE, 0xF1]);
+        assert_eq!(std::fs::read_to_string(docs.join("ru").join("notes.html")).unwrap(), "<p>hand-written</p>");
+
+        let page = std::fs::read_to_string(docs.join("RELEASES.html")).unwrap();
+        assert!(page.starts_with(MARKER));
+        assert!(page.contains("<title>Выпуски</title>"));
+        assert!(page.contains(r#"href="ru/guide.html#start""#));
+        let guide = std::fs::read_to_string(docs.join("ru").join("guide.html")).unwrap();
+        assert!(guide.contains(r#"href="../toc.html""#));
+        let toc = std::fs::read_to_string(docs.join(TOC_FILE)).unwrap();
+        assert!(toc.find("Выпуски").unwrap() < toc.find("<h2>ru</h2>").unwrap());
+        assert!(toc.contains(r#"<a href="ru/guide.html">Гид</a>"#));
+
+        // Nothing left to do, so the watcher settles
+        assert!(build(docs).unwrap().is_empty());
+        std::fs::remove_file(docs.join("RELEASES.md")).unwrap();
+        assert_eq!(build(docs).unwrap().removed, vec![docs.join("RELEASES.html")]);
+    }
+}
@@ -0,0 +1,277 @@
+//! File operations on the knowledge folder for the launcher UI
+//!
+//! Kiosk accounts often have no Explorer, so the panel lists the knowledge
+//! folder (LightDocs `docs_root`) and creates folders, renames, moves and
+//! deletes in it. Every path comes from the browser and is checked to stay
+//! inside the folder. Deleting moves the item to `trash/knowledge/` in the
+//! kit root — outside the knowledge folder, so it leaves the site, and not
+//! shipped by `pack` — where it can be restored for `TRASH_DAYS` days.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, Local};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::lightdocs::LightDocsConfig;
+use crate::safe_path;
+
+/// Deleted items, relative to root
+pub const TRASH_DIR: &str = "trash/knowledge";
+
+/// Items older than this are purged from the trash
+const TRASH_DAYS: i64 = 30;
+
+/// Entries listed at most (the panel is not a bulk tool)
+const MAX_ENTRIES: usize = 5000;
+
+/// A file or folder of the knowledge folder
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Entry {
+    /// Relative, `/`-separated
+    pub path: String,
+    pub dir: bool,
+    pub size: u64,
+    pub modified: Option<String>,
+}
+
+/// A deleted item
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TrashItem {
+    /// Name of its folder in the trash
+    #[serde(default)]
+    pub id: String,
+    /// Where it was, relative to the knowledge folder
+    pub path: String,
+    pub deleted_at: String,
+}
+
+/// The knowledge folder and its trash
+pub struct KnowledgeFs {
+    docs: PathBuf,
+    trash: PathBuf,
+}
+
+impl KnowledgeFs {
+    pub fn open(root: &Path) -> Result<Self> {
+        let docs = LightDocsConfig::load(root)?.docs_root_abs(root);
+        Ok(Self { docs, trash: root.join(TRASH_DIR) })
+    }
+
+    #[cfg(test)]
+    fn at(docs: &Path, trash: &Path) -> Self {
+        Self { docs: docs.to_path_buf(), trash: trash.to_path_buf() }
+    }
+
+    /// Absolute path of a relative one from the browser
+    fn resolve(&self, path: &str) -> Result<PathBuf> {
+        safe_path::join(&self.docs, path)
+    }
+
+    /// Existing item
+    fn existing(&self, path: &str) -> Result<PathBuf> {
+        let full = self.resolve(path)?;
+        if !full.exists() {
+            bail!("{} not found", path);
+        }
+        Ok(full)
+    }
+
+    fn relative(&self, full: &Path) -> String {
+        full.strip_prefix(&self.docs).unwrap_or(full).to_string_lossy().replace('\\', "/")
+    }
+
+    /// Everything in the folder, folders before files, hidden ones left out
+    pub fn tree(&self) -> Result<Vec<Entry>> {
+        if !self.docs.is_dir() {
+            return Ok(Vec::new());
+        }
+        let walker = walkdir::WalkDir::new(&self.docs)
+            .min_depth(1)
+            .sort_by(|a, b| {
+                b.file_type().is_dir().cmp(&a.file_type().is_dir()).then_with(|| a.file_name().cmp(b.file_name()))
+            })
+            .into_iter()
+            .filter_entry(|e| !e.file_name().to_string_lossy().starts_with('.'));
+        let mut entries = Vec::new();
+        for entry in walker.take(MAX_ENTRIES) {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            entries.push(Entry {
+                path: self.relative(entry.path()),
+                dir: metadata.is_dir(),
+                size: if metadata.is_dir() { 0 } else { metadata.len() },
+                modified: metadata.modified().ok().map(|t| DateTime::<Local>::from(t).to_rfc3339()),
+            });
+        }
+        Ok(entries)
+    }
+
+    pub fn create_dir(&self, path: &str) -> Result<String> {
+        let full = self.resolve(path)?;
+        if full.exists() {
+            bail!("{} already exists", path);
+        }
+        for part in path.split(['/', '\\']).filter(|p| !p.is_empty()) {
+            check_name(part)?;
+        }
+        std::fs::create_dir_all(&full).with_context(|| format!("Cannot create {}", path))?;
+        info!("📁 Knowledge folder created: {}", path);
+        Ok(self.relative(&full))
+    }
+
+    /// Give an item a new name in the same folder
+    pub fn rename(&self, path: &str, name: &str) -> Result<String> {
+        let from = self.existing(path)?;
+        check_name(name)?;
+        let to = from.with_file_name(name);
+        self.move_item(&from, &to)
+    }
+
+    /// Move an item into `folder` (empty for the top of the knowledge folder)
+    pub fn move_to(&self, path: &str, folder: &str) -> Result<String> {
+        let from = self.existing(path)?;
+        let dir = if folder.trim_matches(['/', '\\']).is_empty() { self.docs.clone() } else { self.existing(folder)? };
+        if !dir.is_dir() {
+            bail!("{} is not a folder", folder);
+        }
+        if dir.starts_with(&from) {
+            bail!("Cannot move a folder into itself");
+        }
+        let to = dir.join(from.file_name().context("Nothing to move")?);
+        self.move_item(&from, &to)
+    }
+
+    fn move_item(&self, from: &Path, to: &Path) -> Result<String> {
+        if to.exists() {
+            bail!("{} already exists", self.relative(to));
+        }
+        std::fs::rename(from, to).with_context(|| format!("Cannot move {}", self.relative(from)))?;
+        info!("📁 Knowledge item moved: {} -> {}", self.relative(from), self.relative(to));
+        Ok(self.relative(to))
+    }
+
+    /// Move an item to the trash
+    pub fn delete(&self, path: &str) -> Result<TrashItem> {
+        let full = self.existing(path)?;
+        self.purge_trash();
+        let now = Local::now();
+        let stamp = format!("{}-{:03}", now.format("%Y%m%d-%H%M%S"), now.timestamp_subsec_millis());
+        let mut id = stamp.clone();
+        for n in 1.. {
+            if !self.trash.join(&id).exists() {
+                break;
+            }
+            id = format!("{}-{}", stamp, n);
+        }
+        let folder = self.trash.join(&id);
+        std::fs::create_dir_all(&folder)?;
+        let item = TrashItem { id: id.clone(), path: self.relative(&full), deleted_at: now.to_rfc3339() };
+        let name = full.file_name().context("Nothing to delete")?;
+        // The trash sits next to the knowledge folder; one on another drive can't take it
+        std::fs::rename(&full, folder.join(name))
+            .with_context(|| format!("Cannot move {} to {}", item.path, self.trash.display()))?;
+        std::fs::write(self.trash.join(format!("{}.json", id)), serde_json::to_string_pretty(&item)?)?;
+        info!("🗑️ Knowledge item moved to the trash: {}", item.path);
+        Ok(item)
+    }
+
+    /// Deleted items, newest first
+    pub fn trash(&self) -> Result<Vec<TrashItem>> {
+        let Ok(entries) = std::fs::read_dir(&self.trash) else {
+            return Ok(Vec::new());
+        };
+        let mut items = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            match std::fs::read_to_string(&path).map_err(anyhow::Error::from).and_then(|t| Ok(serde_json::from_str::<TrashItem>(&t)?)) {
+                Ok(mut item) => {
+                    item.id = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+                    items.push(item);
+                }
+                Err(e) => warn!("Skipping {}: {:#}", path.display(), e),
+            }
+        }
+        items.sort_by(|a, b| b.id.cmp(&a.id));
+        Ok(items)
+    }
+
+    /// Put a deleted item back where it was
+    pub fn restore(&self, id: &str) -> Result<String> {
+        let item = self.trash()?.into_iter().find(|i| i.id == id).with_context(|| format!("{} is not in the trash", id))?;
+        let to = self.resolve(&item.path)?;
+        if to.exists() {
+            bail!("{} exists again; rename it first", item.path);
+        }
+        if let Some(parent) = to.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let from = self.trash.join(id).join(to.file_name().context("Bad trash item")?);
+        std::fs::rename(&from, &to).with_context(|| format!("Cannot restore {}", item.path))?;
+        let _ = std::fs::remove_dir(self.trash.join(id));
+        let _ = std::fs::remove_file(self.trash.join(format!("{}.json", id)));
+        info!("♻️ Knowledge item restored: {}", item.path);
+        Ok(item.path)
+    }
+
+    /// Drop items deleted more than `TRASH_DAYS` ago
+    fn purge_trash(&self) {
+        let cutoff = Local::now() - Duration::days(TRASH_DAYS);
+        for item in self.trash().unwrap_or_default() {
+            let old = DateTime::parse_from_rfc3339(&item.deleted_at).is_ok_and(|at| at < cutoff);
+            if old {
+                let _ = std::fs::remove_dir_all(self.trash.join(&item.id));
+                let _ = std::fs::remove_file(self.trash.join(format!("{}.json", item.id)));
+            }
+        }
+    }
+}
+
+/// A file or folder name typed in the panel: no separators, nothing Windows rejects
+fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.contains(['/', '\\']) || safe_path::file_name(name) != name {
+        bail!("Invalid name: {:?}", name);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_operations_stay_inside_and_trash_restores() {
+        let dir = tempdir().unwrap();
+        let fs = KnowledgeFs::at(&dir.path().join("knowledge"), &dir.path().join(TRASH_DIR));
+        std::fs::create_dir_all(dir.path().join("knowledge").join(".obsidian")).unwrap();
+        std::fs::write(dir.path().join("knowledge").join("faq.md"), "# FAQ").unwrap();
+
+        assert_eq!(fs.create_dir("guides/hr").unwrap(), "guides/hr");
+        assert!(fs.create_dir("../outside").is_err());
+        assert!(fs.create_dir("guides").is_err());
+        assert_eq!(fs.rename("faq.md", "вопросы.md").unwrap(), "вопросы.md");
+        assert!(fs.rename("вопросы.md", "a/b.md").is_err());
+        assert!(fs.rename("вопросы.md", "con.md").is_err());
+        assert_eq!(fs.move_to("вопросы.md", "guides/hr").unwrap(), "guides/hr/вопросы.md");
+        assert!(fs.move_to("guides", "guides/hr").is_err());
+
+        let paths: Vec<(String, bool)> = fs.tree().unwrap().into_iter().map(|e| (e.path, e.dir)).collect();
+        assert_eq!(
+            paths,
+            vec![("guides".into(), true), ("guides/hr".into(), true), ("guides/hr/вопросы.md".into(), false)]
+        );
+
+        let item = fs.delete("guides/hr/вопросы.md").unwrap();
+        assert!(fs.tree().unwrap().iter().all(|e| !e.path.ends_with(".md")));
+        assert_eq!(fs.trash().unwrap(), vec![item.clone()]);
+        fs.delete("guides").unwrap();
+        assert!(fs.restore(&item.id).unwrap().ends_with("вопросы.md"));
+        assert!(dir.path().join("knowledge").join("guides").join("hr").join("вопросы.md").exists());
+        assert_eq!(fs.trash().unwrap().len(), 1);
+        assert!(fs.restore(&item.id).is_err());
+    }
+}
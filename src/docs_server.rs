@@ -20,12 +20,15 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::oneshot;
 use tracing::{info, error};
 
+use crate::security_headers::SecurityConfig;
+
 /// Documentation server that serves static files
 pub struct DocsServer {
     docs_path: PathBuf,
     port: u16,
     running: Arc<AtomicBool>,
     shutdown_tx: Option<oneshot::Sender<()>>,
+    security: SecurityConfig,
 }
 
 impl DocsServer {
@@ -36,6 +39,7 @@ impl DocsServer {
             port,
             running: Arc::new(AtomicBool::new(false)),
             shutdown_tx: None,
+            security: SecurityConfig::load(root),
         }
     }
     
@@ -65,6 +69,13 @@ impl DocsServer {
         let app = Router::new()
             .route("/health", get(health_handler))
             .nest_service("/", serve_dir)
+            // Stream big demo assets (GeoJSON, video) with Range support
+            .layer(axum::middleware::from_fn_with_state(
+                docs_path.clone(),
+                crate::large_files::large_file_middleware,
+            ))
+            // Fix Windows-1251 docs and CSVs on the fly
+            .layer(axum::middleware::from_fn(crate::encoding::transcode_middleware))
             .layer(SetResponseHeaderLayer::overriding(
                 header::CONTENT_TYPE,
                 |response: &Response<Body>| {
@@ -87,18 +98,16 @@ impl DocsServer {
                     }
                     None
                 },
-            ))
-            .layer(
-                tower_http::cors::CorsLayer::new()
-                    .allow_origin(tower_http::cors::Any)
-                    .allow_methods(tower_http::cors::Any)
-                    .allow_headers(tower_http::cors::Any),
-            );
+            ));
+        // Dashboards on other ports fetch GeoJSON from here: open CORS unless configured
+        let app = crate::security_headers::apply(app, &self.security, true);
         
         let addr = SocketAddr::from(([127, 0, 0, 1], port));
         
         running.store(true, Ordering::SeqCst);
         info!("📚 Docs server starting on http://127.0.0.1:{}", port);
+        // Hand edits get their encoding fixed and pages regenerated while we serve
+        crate::docs_build::spawn_watcher(docs_path.clone(), running.clone());
         
         // Spawn the server in a background task
         tokio::spawn(async move {
@@ -0,0 +1,354 @@
+//! `doctor`: every diagnostic in one run
+//!
+//! Combines the environment validator, health probes, stale PID files,
+//! recent log errors, free disk space and port conflicts into one list of
+//! problems, most serious first. A support bundle (logs, config, versions and
+//! the report) is written to `support/` so it can be emailed as one file.
+
+use anyhow::Result;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::config::Config;
+use crate::validator::Validator;
+
+/// Support bundles folder (relative to root)
+pub const SUPPORT_DIR: &str = "support";
+
+/// Only the end of each log is scanned and bundled
+const LOG_TAIL_BYTES: u64 = 1024 * 1024;
+
+/// Free space thresholds
+const DISK_CRITICAL_BYTES: u64 = 500 * 1024 * 1024;
+const DISK_WARNING_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Ports of the optional services (launcher UI, LightDocs, gateway)
+const SERVICE_PORTS: &[(u16, &str)] = &[(3000, "Launcher UI"), (3030, "LightDocs"), (8000, "Gateway")];
+
+/// Known log messages: (pattern, severity, explanation and fix)
+const LOG_PATTERNS: &[(&str, Severity, &str)] = &[
+    ("database is locked", Severity::Warning, "SQLite заблокирована во время загрузки — включите \"replica\" в config.json"),
+    ("MemoryError", Severity::Critical, "Не хватает памяти — закройте другие программы или уменьшите число воркеров"),
+    ("ModuleNotFoundError", Severity::Critical, "Повреждена установка Python — распакуйте папку python заново"),
+    ("Address already in use", Severity::Warning, "Порт занят — остановите старый процесс или смените порт"),
+    ("WinError 10048", Severity::Warning, "Порт занят — остановите старый процесс или смените порт"),
+    ("No space left on device", Severity::Critical, "Диск заполнен"),
+    ("Traceback (most recent call last)", Severity::Info, "В логе есть необработанные исключения Python"),
+];
+
+/// How urgent a finding is (most urgent first when sorted)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Critical,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    fn icon(self) -> &'static str {
+        match self {
+            Severity::Critical => "❌",
+            Severity::Warning => "⚠️",
+            Severity::Info => "ℹ️",
+        }
+    }
+}
+
+/// One detected problem
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    pub area: String,
+    pub message: String,
+}
+
+impl Finding {
+    fn new(severity: Severity, area: &str, message: impl Into<String>) -> Self {
+        Self { severity, area: area.to_string(), message: message.into() }
+    }
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} [{}] {}", self.severity.icon(), self.area, self.message)
+    }
+}
+
+/// Run all checks; findings sorted by severity
+pub async fn diagnose(root: &Path, config: &Config) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    for check in Validator::new(root).validate_all() {
+        if !check.passed {
+            let severity = if check.critical { Severity::Critical } else { Severity::Warning };
+            findings.push(Finding::new(severity, &check.name, check.message));
+        }
+    }
+
+    let pids = pid_files(root);
+    let superset_up = crate::health_check::check_superset(config.port).await.unwrap_or(false);
+    let superset_pid = pids.iter().any(|(name, _, _)| name == "superset.pid");
+    if superset_pid && !superset_up {
+        findings.push(Finding::new(
+            Severity::Warning,
+            "Superset",
+            format!("Процесс записан в superset.pid, но http://127.0.0.1:{}/health не отвечает", config.port),
+        ));
+    }
+
+    for (name, pid, alive) in &pids {
+        if !alive {
+            findings.push(Finding::new(
+                Severity::Warning,
+                "PID",
+                format!("{} указывает на завершённый процесс {} — удалите файл", name, pid),
+            ));
+        }
+    }
+
+    findings.extend(scan_logs(root));
+
+    let store = crate::credentials::CredentialStore::open(root, &config.credentials);
+    if let Ok(crate::secret_key::KeyStatus::Mismatch) = crate::secret_key::status(root, &store) {
+        findings.push(Finding::new(
+            Severity::Critical,
+            "SECRET_KEY",
+            "Ключ в superset_config.py не совпадает с сохранённой копией — пароли подключений к базам не расшифруются. \
+             Верните ключ из `user show-secret-key`",
+        ));
+    }
+
+    match free_disk_space(root) {
+        Some(free) if free < DISK_CRITICAL_BYTES => findings.push(Finding::new(
+            Severity::Critical,
+            "Диск",
+            format!("Свободно {:.0} MB — Superset может не запуститься", free as f64 / 1_048_576.0),
+        )),
+        Some(free) if free < DISK_WARNING_BYTES => findings.push(Finding::new(
+            Severity::Warning,
+            "Диск",
+            format!("Свободно {:.1} GB", free as f64 / 1_073_741_824.0),
+        )),
+        Some(_) => {}
+        None => findings.push(Finding::new(Severity::Info, "Диск", "Не удалось определить свободное место")),
+    }
+
+    for (port, service) in SERVICE_PORTS {
+        if TcpListener::bind(("127.0.0.1", *port)).is_err() && pids.is_empty() {
+            findings.push(Finding::new(
+                Severity::Info,
+                &format!("Порт {}", port),
+                format!("Занят, хотя лаунчер не запущен — {} не сможет стартовать", service),
+            ));
+        }
+    }
+
+    findings.sort_by_key(|f| f.severity);
+    findings
+}
+
+/// `*.pid` files in root: (file name, pid, process alive)
+fn pid_files(root: &Path) -> Vec<(String, u32, bool)> {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "pid"))
+        .filter_map(|e| {
+            let pid = std::fs::read_to_string(e.path()).ok()?.trim().parse().ok()?;
+            Some((e.file_name().to_string_lossy().to_string(), pid, process_alive(pid)))
+        })
+        .collect()
+}
+
+#[cfg(windows)]
+fn process_alive(pid: u32) -> bool {
+    std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+        .output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).contains(&pid.to_string()))
+        .unwrap_or(true)
+}
+
+#[cfg(not(windows))]
+fn process_alive(pid: u32) -> bool {
+    Path::new(&format!("/proc/{}", pid)).exists()
+}
+
+/// Known error patterns in the tail of every log in `logs/`
+fn scan_logs(root: &Path) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for path in log_files(root) {
+        let Ok(text) = read_tail(&path) else {
+            continue;
+        };
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        for (pattern, severity, hint) in LOG_PATTERNS {
+            let count = text.matches(pattern).count();
+            if count > 0 {
+                findings.push(Finding::new(*severity, &name, format!("«{}» ×{}: {}", pattern, count, hint)));
+            }
+        }
+    }
+    findings
+}
+
+fn log_files(root: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(root.join("logs")) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "log"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Last `LOG_TAIL_BYTES` of a file as text
+fn read_tail(path: &Path) -> Result<String> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(LOG_TAIL_BYTES)))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+#[cfg(windows)]
+fn free_disk_space(root: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use winapi::um::fileapi::GetDiskFreeSpaceExW;
+
+    let path: Vec<u16> = root.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    let mut free: u64 = 0;
+    // SAFETY: `path` is NUL-terminated; the out pointer is a valid ULARGE_INTEGER-sized u64
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(path.as_ptr(), &mut free as *mut u64 as *mut _, std::ptr::null_mut(), std::ptr::null_mut())
+    };
+    (ok != 0).then_some(free)
+}
+
+#[cfg(not(windows))]
+fn free_disk_space(root: &Path) -> Option<u64> {
+    let out = std::process::Command::new("df").arg("-Pk").arg(root).output().ok()?;
+    let text = String::from_utf8_lossy(&out.stdout);
+    let kb: u64 = text.lines().nth(1)?.split_whitespace().nth(3)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// Zip logs, config, versions and the report into `support/doctor-<time>.zip`
+pub fn write_support_bundle(root: &Path, findings: &[Finding]) -> Result<PathBuf> {
+    let dir = root.join(SUPPORT_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("doctor-{}.zip", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(&path)?));
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file("report.txt", options)?;
+    for finding in findings {
+        writeln!(zip, "{}", finding)?;
+    }
+    if findings.is_empty() {
+        writeln!(zip, "Проблем не найдено")?;
+    }
+
+    zip.start_file("versions.txt", options)?;
+    write!(zip, "{}", versions(root))?;
+
+    if let Ok(config) = std::fs::read_to_string(root.join("config.json")) {
+        zip.start_file("config.json", options)?;
+        zip.write_all(config.as_bytes())?;
+    }
+    if let Ok(superset_config) = std::fs::read_to_string(root.join("superset_home").join("superset_config.py")) {
+        zip.start_file("superset_config.py", options)?;
+        zip.write_all(redact_secrets(&superset_config).as_bytes())?;
+    }
+
+    for log in log_files(root) {
+        let name = format!("logs/{}", log.file_name().unwrap_or_default().to_string_lossy());
+        if let Ok(text) = read_tail(&log) {
+            zip.start_file(name, options)?;
+            zip.write_all(text.as_bytes())?;
+        }
+    }
+
+    zip.finish()?;
+    Ok(path)
+}
+
+/// Launcher, OS, Python and Superset versions
+fn versions(root: &Path) -> String {
+    let mut out = format!(
+        "superset-launcher {}\nOS: {} {}\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    let Ok(python) = crate::python::PythonEnv::new(root) else {
+        return out;
+    };
+    if let Ok(output) = python.run_python(&["--version"]) {
+        out.push_str(String::from_utf8_lossy(&output.stdout).trim());
+        out.push('\n');
+    }
+    if let Ok(entries) = std::fs::read_dir(python.site_packages()) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with("apache_superset-") && name.ends_with(".dist-info") {
+                out.push_str(&format!("Superset: {}\n", name.trim_end_matches(".dist-info")));
+            }
+        }
+    }
+    out
+}
+
+/// Blank out `SECRET_KEY` and similar lines before sharing a config
+fn redact_secrets(config: &str) -> String {
+    config
+        .lines()
+        .map(|line| {
+            let upper = line.to_uppercase();
+            let is_secret = ["SECRET", "PASSWORD", "TOKEN", "AUTOLOGIN"].iter().any(|word| upper.contains(word));
+            match line.split_once('=') {
+                Some((name, _)) if is_secret && !line.trim_start().starts_with('#') => format!("{}= \"***\"", name),
+                _ => line.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_scan_logs() {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("logs")).unwrap();
+        std::fs::write(
+            dir.path().join("logs").join("superset.stderr.log"),
+            "sqlite3.OperationalError: database is locked\n...database is locked\n",
+        )
+        .unwrap();
+
+        let findings = scan_logs(dir.path());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert!(findings[0].message.contains("×2"));
+    }
+
+    #[test]
+    fn test_redact_secrets() {
+        let config = "SECRET_KEY = \"abc\"\n# SECRET_KEY explained\nROW_LIMIT = 5000";
+        assert_eq!(redact_secrets(config), "SECRET_KEY = \"***\"\n# SECRET_KEY explained\nROW_LIMIT = 5000");
+    }
+}
@@ -0,0 +1,245 @@
+//! Watchdog for the drive the kit runs from
+//!
+//! A flash drive pulled out (or put to sleep by the power plan) under a
+//! running Superset leaves half-written SQLite pages and sled segments
+//! behind. The launcher probes the portable root every few seconds; when it
+//! stops answering, the launcher stops Superset and the data watcher,
+//! schedulers skip their runs, and small writes (usage counters) wait in a
+//! queue. When the drive is back, the queue is flushed and the services
+//! that were running are started again. If it stays gone for
+//! `drive_watchdog.shutdown_after_secs`, the launcher shuts down cleanly.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::notifications::{self, Level};
+
+/// Writes kept while paused; older ones are dropped beyond this
+const MAX_QUEUED_WRITES: usize = 1000;
+
+/// Consecutive good probes before the drive counts as back (USB re-mounts flap)
+const PROBES_TO_RESUME: u32 = 2;
+
+/// The process's pause state; tests use their own [`WriteGate`]
+static GATE: WriteGate = WriteGate::new();
+
+type QueuedWrite = Box<dyn FnOnce() + Send>;
+
+/// Whether writes are held, and the writes waiting for the drive
+struct WriteGate {
+    paused: AtomicBool,
+    /// Unix time the drive was lost (0 while available)
+    lost_at: AtomicU64,
+    queue: Mutex<Vec<QueuedWrite>>,
+}
+
+impl WriteGate {
+    const fn new() -> Self {
+        Self { paused: AtomicBool::new(false), lost_at: AtomicU64::new(0), queue: Mutex::new(Vec::new()) }
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    fn paused_for(&self) -> Option<u64> {
+        let lost_at = self.lost_at.load(Ordering::SeqCst);
+        if !self.is_paused() || lost_at == 0 {
+            return None;
+        }
+        Some(unix_now().saturating_sub(lost_at))
+    }
+
+    fn defer_write(&self, write: impl FnOnce() + Send + 'static) {
+        if !self.is_paused() {
+            write();
+            return;
+        }
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        if queue.len() >= MAX_QUEUED_WRITES {
+            queue.remove(0);
+        }
+        queue.push(Box::new(write));
+    }
+
+    fn pause(&self) {
+        self.lost_at.store(unix_now(), Ordering::SeqCst);
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    fn resume(&self) -> usize {
+        self.paused.store(false, Ordering::SeqCst);
+        self.lost_at.store(0, Ordering::SeqCst);
+        let queued = std::mem::take(&mut *self.queue.lock().unwrap_or_else(|e| e.into_inner()));
+        let count = queued.len();
+        for write in queued {
+            write();
+        }
+        count
+    }
+}
+
+/// Watchdog settings (`drive_watchdog` in config.json)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DriveWatchdogConfig {
+    pub enabled: bool,
+    /// Seconds between probes of the portable root
+    pub poll_secs: u64,
+    /// Shut the launcher down if the drive is gone this long (0 = wait forever)
+    pub shutdown_after_secs: u64,
+}
+
+impl Default for DriveWatchdogConfig {
+    fn default() -> Self {
+        Self { enabled: true, poll_secs: 5, shutdown_after_secs: 600 }
+    }
+}
+
+/// State change reported to the launcher
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriveEvent {
+    /// Root unavailable: stop services and hold writes
+    Lost,
+    /// Root answers again: queued writes are done, restart services
+    Restored,
+    /// Gone for longer than `shutdown_after_secs`
+    GiveUp,
+}
+
+/// True while the portable root is unavailable
+pub fn is_paused() -> bool {
+    GATE.is_paused()
+}
+
+/// Seconds since the drive was lost, while paused
+pub fn paused_for() -> Option<u64> {
+    GATE.paused_for()
+}
+
+/// Run `write` now, or hold it until the drive is back
+pub fn defer_write(write: impl FnOnce() + Send + 'static) {
+    GATE.defer_write(write)
+}
+
+/// Whether the portable root can be read: the folder lists and config.json
+/// is still there (a removed drive fails both, even with cached metadata)
+pub fn root_available(root: &Path) -> bool {
+    std::fs::read_dir(root).is_ok() && std::fs::File::open(root.join("config.json")).is_ok()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+fn pause() {
+    GATE.pause()
+}
+
+/// Clear the pause and run the queued writes in order; returns how many ran
+fn resume() -> usize {
+    GATE.resume()
+}
+
+/// Probe `root` in the background and report changes on `events`
+pub fn spawn(root: PathBuf, config: DriveWatchdogConfig, events: mpsc::Sender<DriveEvent>) {
+    if !config.enabled {
+        info!("Drive watchdog disabled");
+        return;
+    }
+    let poll = Duration::from_secs(config.poll_secs.max(1));
+
+    tokio::spawn(async move {
+        let mut lost_since: Option<Instant> = None;
+        let mut good_probes = 0;
+        loop {
+            tokio::time::sleep(poll).await;
+            let probe_root = root.clone();
+            let available = tokio::task::spawn_blocking(move || root_available(&probe_root)).await.unwrap_or(false);
+
+            match (available, lost_since) {
+                (false, None) => {
+                    warn!("💾 Portable root unavailable: {}", root.display());
+                    pause();
+                    lost_since = Some(Instant::now());
+                    good_probes = 0;
+                    notifications::notify(
+                        Level::Error,
+                        "Флешка недоступна",
+                        "Сервисы приостановлены. Вставьте флешку обратно — работа продолжится.",
+                    );
+                    let _ = events.send(DriveEvent::Lost).await;
+                }
+                (false, Some(since)) => {
+                    good_probes = 0;
+                    if config.shutdown_after_secs > 0 && since.elapsed().as_secs() >= config.shutdown_after_secs {
+                        notifications::notify(
+                            Level::Error,
+                            "Флешка так и не вернулась",
+                            "Лаунчер закрывается. Запустите его снова, когда флешка будет вставлена.",
+                        );
+                        let _ = events.send(DriveEvent::GiveUp).await;
+                        return;
+                    }
+                }
+                (true, Some(since)) => {
+                    good_probes += 1;
+                    if good_probes < PROBES_TO_RESUME {
+                        continue;
+                    }
+                    let flushed = tokio::task::spawn_blocking(resume).await.unwrap_or_default();
+                    info!("💾 Portable root is back after {}s ({} queued writes done)", since.elapsed().as_secs(), flushed);
+                    lost_since = None;
+                    notifications::notify(Level::Info, "Флешка снова доступна", "Сервисы запускаются заново");
+                    let _ = events.send(DriveEvent::Restored).await;
+                }
+                (true, None) => {}
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_probe_and_queue() {
+        let dir = tempdir().unwrap();
+        assert!(!root_available(dir.path()), "no config.json");
+        std::fs::write(dir.path().join("config.json"), "{}").unwrap();
+        assert!(root_available(dir.path()));
+        assert!(!root_available(&dir.path().join("gone")));
+
+        // A gate of its own: pausing the process's one would hold other tests' writes
+        let gate = WriteGate::new();
+        let done = Arc::new(AtomicUsize::new(0));
+        gate.pause();
+        assert!(gate.paused_for().is_some());
+        assert!(!is_paused());
+        for _ in 0..3 {
+            let done = done.clone();
+            gate.defer_write(move || {
+                done.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        assert_eq!(done.load(Ordering::SeqCst), 0, "held while paused");
+        assert_eq!(gate.resume(), 3);
+        assert_eq!(done.load(Ordering::SeqCst), 3);
+        assert!(!gate.is_paused() && gate.paused_for().is_none());
+
+        let ran = done.clone();
+        gate.defer_write(move || {
+            ran.fetch_add(1, Ordering::SeqCst);
+        });
+        assert_eq!(done.load(Ordering::SeqCst), 4, "runs at once when the drive is there");
+    }
+}
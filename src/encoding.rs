@@ -0,0 +1,181 @@
+//! Windows-1251 → UTF-8 transcoding
+//!
+//! Some shipped docs and customer CSVs are saved in Windows-1251. Serving
+//! them with `charset=utf-8` only turns the mojibake into a different one,
+//! so text responses are sniffed and transcoded on the fly, and the
+//! `convert-encoding` command rewrites such files in place.
+
+use anyhow::{Context, Result};
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use std::path::Path;
+use tracing::{info, warn};
+
+use crate::large_files::LARGE_FILE_THRESHOLD;
+
+/// File extensions treated as text by `convert-encoding`
+pub const TEXT_EXTENSIONS: &[&str] = &[
+    "md", "txt", "csv", "tsv", "html", "htm", "json", "geojson", "sql", "yaml", "yml", "css", "js",
+];
+
+/// Detected text encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    Utf8,
+    Windows1251,
+    /// Not text (contains NUL bytes) — left untouched
+    Binary,
+}
+
+/// Sniff the encoding of a text buffer.
+///
+/// Valid UTF-8 wins; anything else without NUL bytes is assumed to be
+/// Windows-1251, the only legacy encoding we see in practice.
+pub fn detect(bytes: &[u8]) -> TextEncoding {
+    if std::str::from_utf8(bytes).is_ok() {
+        TextEncoding::Utf8
+    } else if bytes.contains(&0) {
+        TextEncoding::Binary
+    } else {
+        TextEncoding::Windows1251
+    }
+}
+
+/// Whether a Content-Type is worth sniffing
+fn is_text_type(content_type: &str) -> bool {
+    content_type.starts_with("text/")
+        || content_type.contains("json")
+        || content_type.contains("csv")
+        || content_type.contains("markdown")
+        || content_type.contains("javascript")
+}
+
+/// Middleware: transcode Windows-1251 text responses to UTF-8
+pub async fn transcode_middleware(req: Request, next: Next) -> Response {
+    let response = next.run(req).await;
+
+    // Ranged responses must keep byte offsets of the original file
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    if !is_text_type(&content_type) {
+        return response;
+    }
+
+    let too_large = response
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .map_or(true, |len| len >= LARGE_FILE_THRESHOLD);
+    if too_large {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, LARGE_FILE_THRESHOLD as usize).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("Failed to buffer response for transcoding: {}", e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+
+    if detect(&bytes) != TextEncoding::Windows1251 {
+        return Response::from_parts(parts, Body::from(bytes));
+    }
+
+    let (text, _, _) = encoding_rs::WINDOWS_1251.decode(&bytes);
+    let utf8 = text.into_owned().into_bytes();
+
+    let mime = content_type.split(';').next().unwrap_or("text/plain").trim();
+    if let Ok(ct) = HeaderValue::from_str(&format!("{}; charset=utf-8", mime)) {
+        parts.headers.insert(header::CONTENT_TYPE, ct);
+    }
+    parts.headers.insert(header::CONTENT_LENGTH, HeaderValue::from(utf8.len()));
+    Response::from_parts(parts, Body::from(utf8))
+}
+
+/// Result of a bulk conversion
+#[derive(Debug, Default)]
+pub struct ConvertSummary {
+    pub scanned: usize,
+    pub converted: Vec<std::path::PathBuf>,
+}
+
+/// Convert every Windows-1251 text file under `path` (file or folder) to UTF-8
+pub fn convert_path(path: &Path, dry_run: bool) -> Result<ConvertSummary> {
+    let mut summary = ConvertSummary::default();
+
+    for entry in walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| {
+            e.path()
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map_or(false, |ext| TEXT_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        })
+    {
+        summary.scanned += 1;
+        let file = entry.path();
+        let bytes = std::fs::read(file).with_context(|| format!("Failed to read {}", file.display()))?;
+        if detect(&bytes) != TextEncoding::Windows1251 {
+            continue;
+        }
+
+        if !dry_run {
+            let (text, _, _) = encoding_rs::WINDOWS_1251.decode(&bytes);
+            std::fs::write(file, text.as_bytes())
+                .with_context(|| format!("Failed to write {}", file.display()))?;
+        }
+        info!("  🔤 {}", file.display());
+        summary.converted.push(file.to_path_buf());
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect("Привет".as_bytes()), TextEncoding::Utf8);
+        // "Привет" in Windows-1251
+        assert_eq!(detect(&[0xCF, 0xF0, 0xE8, 0xE2, 0xE5, 0xF2]), TextEncoding::Windows1251);
+        assert_eq!(detect(&[0x89, b'P', b'N', b'G', 0x00]), TextEncoding::Binary);
+    }
+
+    #[test]
+    fn test_convert_path() {
+        let dir = tempdir().unwrap();
+        let csv = dir.path().join("stations.csv");
+        std::fs::write(&csv, [b'i', b'd', b';', 0xCC, 0xEE, 0xF1, 0xEA, 0xE2, 0xE0]).unwrap();
+        std::fs::write(dir.path().join("ok.md"), "# Уже UTF-8").unwrap();
+
+        let dry = convert_path(dir.path(), true).unwrap();
+        assert_eq!(dry.converted.len(), 1);
+        assert!(std::str::from_utf8(&std::fs::read(&csv).unwrap()).is_err());
+
+        let summary = convert_path(dir.path(), false).unwrap();
+        assert_eq!(summary.scanned, 2);
+        assert_eq!(summary.converted, vec![csv.clone()]);
+        assert_eq!(std::fs::read_to_string(&csv).unwrap(), "id;Москва");
+    }
+}
@@ -0,0 +1,438 @@
+//! Superset feature flag management
+//!
+//! Edits the `FEATURE_FLAGS = {...}` block of `superset_config.py` in place.
+//! The block is located with a small Python-literal scanner (strings, comments
+//! and nested brackets are respected), so unrelated parts of the config and
+//! comments inside the block are preserved byte-for-byte.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Known feature flag with the Superset versions it is valid for
+pub struct KnownFlag {
+    pub name: &'static str,
+    /// First Superset version that understands the flag
+    pub since: (u32, u32),
+    /// Version where the flag was removed (behaviour became default)
+    pub removed_in: Option<(u32, u32)>,
+}
+
+/// Feature flags that are safe to toggle on the portable build
+pub const KNOWN_FLAGS: &[KnownFlag] = &[
+    KnownFlag { name: "ALERT_REPORTS", since: (1, 0), removed_in: None },
+    KnownFlag { name: "ALLOW_ADHOC_SUBQUERY", since: (2, 0), removed_in: None },
+    KnownFlag { name: "ALLOW_FULL_CSV_EXPORT", since: (2, 0), removed_in: None },
+    KnownFlag { name: "CACHE_IMPERSONATION", since: (1, 0), removed_in: None },
+    KnownFlag { name: "CONFIRM_DASHBOARD_DIFF", since: (3, 0), removed_in: None },
+    KnownFlag { name: "DASHBOARD_CROSS_FILTERS", since: (1, 0), removed_in: Some((4, 0)) },
+    KnownFlag { name: "DASHBOARD_NATIVE_FILTERS", since: (1, 0), removed_in: Some((4, 0)) },
+    KnownFlag { name: "DASHBOARD_NATIVE_FILTERS_SET", since: (1, 0), removed_in: Some((4, 0)) },
+    KnownFlag { name: "DASHBOARD_RBAC", since: (1, 0), removed_in: None },
+    KnownFlag { name: "DRILL_BY", since: (2, 1), removed_in: None },
+    KnownFlag { name: "DRILL_TO_DETAIL", since: (2, 1), removed_in: None },
+    KnownFlag { name: "DYNAMIC_PLUGINS", since: (1, 0), removed_in: None },
+    KnownFlag { name: "EMBEDDABLE_CHARTS", since: (1, 0), removed_in: None },
+    KnownFlag { name: "EMBEDDED_SUPERSET", since: (2, 0), removed_in: None },
+    KnownFlag { name: "ENABLE_EXPLORE_DRAG_AND_DROP", since: (1, 3), removed_in: Some((4, 0)) },
+    KnownFlag { name: "ENABLE_JAVASCRIPT_CONTROLS", since: (0, 36), removed_in: None },
+    KnownFlag { name: "ENABLE_TEMPLATE_PROCESSING", since: (0, 36), removed_in: None },
+    KnownFlag { name: "ESCAPE_MARKDOWN_HTML", since: (3, 0), removed_in: None },
+    KnownFlag { name: "ESTIMATE_QUERY_COST", since: (0, 36), removed_in: None },
+    KnownFlag { name: "GLOBAL_ASYNC_QUERIES", since: (1, 0), removed_in: None },
+    KnownFlag { name: "HORIZONTAL_FILTER_BAR", since: (2, 1), removed_in: None },
+    KnownFlag { name: "LISTVIEWS_DEFAULT_CARD_VIEW", since: (1, 0), removed_in: None },
+    KnownFlag { name: "SCHEDULED_QUERIES", since: (0, 36), removed_in: None },
+    KnownFlag { name: "SIP_38_VIZ_REARCHITECTURE", since: (0, 37), removed_in: Some((1, 0)) },
+    KnownFlag { name: "SQLLAB_BACKEND_PERSISTENCE", since: (1, 0), removed_in: None },
+    KnownFlag { name: "SSH_TUNNELING", since: (2, 1), removed_in: None },
+    KnownFlag { name: "TAGGING_SYSTEM", since: (1, 0), removed_in: None },
+    KnownFlag { name: "THUMBNAILS", since: (0, 36), removed_in: None },
+    KnownFlag { name: "VERSIONED_EXPORT", since: (1, 0), removed_in: Some((4, 0)) },
+];
+
+/// Look up a known flag by name
+pub fn find_known_flag(name: &str) -> Option<&'static KnownFlag> {
+    KNOWN_FLAGS.iter().find(|f| f.name == name)
+}
+
+/// Parse "4.0.2" / "3.1.0rc1" into (major, minor)
+pub fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.split('.');
+    let major = parts.next()?.trim().parse().ok()?;
+    let minor_raw = parts.next().unwrap_or("0");
+    let minor_digits: String = minor_raw.chars().take_while(|c| c.is_ascii_digit()).collect();
+    let minor = minor_digits.parse().unwrap_or(0);
+    Some((major, minor))
+}
+
+/// Check a flag name against the bundled Superset version
+pub fn validate_flag(name: &str, superset_version: Option<&str>) -> Result<()> {
+    let known = find_known_flag(name)
+        .ok_or_else(|| anyhow!("Unknown feature flag: {} (use --force to set it anyway)", name))?;
+
+    let Some(version) = superset_version.and_then(parse_version) else {
+        warn!("Superset version unknown, skipping compatibility check for {}", name);
+        return Ok(());
+    };
+
+    if version < known.since {
+        bail!(
+            "{} requires Superset {}.{}+, bundled version is {}.{}",
+            name, known.since.0, known.since.1, version.0, version.1
+        );
+    }
+    if let Some(removed) = known.removed_in {
+        if version >= removed {
+            bail!(
+                "{} was removed in Superset {}.{} (bundled version is {}.{}), it has no effect",
+                name, removed.0, removed.1, version.0, version.1
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Single `'NAME': value` entry of the FEATURE_FLAGS dict
+#[derive(Debug, Clone)]
+pub struct FlagEntry {
+    pub name: String,
+    /// Raw Python expression of the value
+    pub value: String,
+    /// Byte range of the value inside the config source
+    value_span: Range<usize>,
+}
+
+impl FlagEntry {
+    /// Boolean value, if the entry is a plain `True`/`False` literal
+    pub fn enabled(&self) -> Option<bool> {
+        match self.value.as_str() {
+            "True" => Some(true),
+            "False" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// Location and contents of the FEATURE_FLAGS block
+#[derive(Debug)]
+struct FlagsBlock {
+    /// Byte offset of the closing `}`
+    close: usize,
+    entries: Vec<FlagEntry>,
+    indent: String,
+    quote: char,
+}
+
+/// Editable view of `superset_config.py`
+pub struct SupersetConfigFile {
+    path: PathBuf,
+    source: String,
+}
+
+impl SupersetConfigFile {
+    /// Load config from `superset_home/superset_config.py`
+    pub fn open(root: &Path) -> Result<Self> {
+        let path = root.join("superset_home").join("superset_config.py");
+        let source = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read: {}", path.display()))?;
+        Ok(Self { path, source })
+    }
+
+    /// Create from an in-memory source
+    #[cfg(test)]
+    pub fn from_source(path: &Path, source: &str) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            source: source.to_string(),
+        }
+    }
+
+    /// Current config source
+    #[cfg(test)]
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// All entries of the FEATURE_FLAGS block (empty if there is no block)
+    pub fn flags(&self) -> Result<Vec<FlagEntry>> {
+        Ok(find_flags_block(&self.source)?
+            .map(|b| b.entries)
+            .unwrap_or_default())
+    }
+
+    /// Set a flag to True/False, adding it to the block if missing
+    pub fn set_flag(&mut self, name: &str, enabled: bool) -> Result<()> {
+        let literal = if enabled { "True" } else { "False" };
+
+        match find_flags_block(&self.source)? {
+            Some(block) => {
+                if let Some(entry) = block.entries.iter().find(|e| e.name == name) {
+                    self.source.replace_range(entry.value_span.clone(), literal);
+                } else {
+                    // The previous entry may lack a trailing comma
+                    let comma_at = block.entries.last()
+                        .filter(|last| !self.source[last.value_span.end..block.close].contains(','))
+                        .map(|last| last.value_span.end);
+
+                    // Insert a new line right before the closing brace
+                    let line_start = self.source[..block.close].rfind('\n').map_or(0, |p| p + 1);
+                    let before_close = &self.source[line_start..block.close];
+                    let line = format!("{}{q}{}{q}: {},\n", block.indent, name, literal, q = block.quote);
+
+                    if before_close.trim().is_empty() {
+                        // `}` is on its own line
+                        self.source.insert_str(line_start, &line);
+                    } else {
+                        // `{...}` on a single line: put the entry on its own line
+                        self.source.insert_str(block.close, &format!("\n{}", line));
+                    }
+
+                    if let Some(pos) = comma_at {
+                        self.source.insert(pos, ',');
+                    }
+                }
+            }
+            None => {
+                if !self.source.ends_with('\n') && !self.source.is_empty() {
+                    self.source.push('\n');
+                }
+                self.source.push_str(&format!("\nFEATURE_FLAGS = {{\n    '{}': {},\n}}\n", name, literal));
+            }
+        }
+        Ok(())
+    }
+
+    /// Write changes back to disk
+    pub fn save(&self) -> Result<()> {
+        std::fs::write(&self.path, &self.source)
+            .with_context(|| format!("Failed to write: {}", self.path.display()))?;
+        info!("Updated {}", self.path.display());
+        Ok(())
+    }
+}
+
+/// Locate the top-level `FEATURE_FLAGS = {` assignment and parse its entries
+fn find_flags_block(source: &str) -> Result<Option<FlagsBlock>> {
+    let mut offset = 0;
+    let mut open = None;
+
+    for line in source.split_inclusive('\n') {
+        if let Some(rest) = line.strip_prefix("FEATURE_FLAGS") {
+            let rest = rest.trim_start();
+            if let Some(rest) = rest.strip_prefix('=') {
+                let rest_trimmed = rest.trim_start();
+                if rest_trimmed.starts_with('{') {
+                    open = Some(offset + (line.len() - rest_trimmed.len()));
+                    break;
+                }
+            }
+        }
+        offset += line.len();
+    }
+
+    let Some(open) = open else {
+        return Ok(None);
+    };
+
+    let close = find_matching_brace(source, open)?;
+    let (entries, quote) = parse_entries(source, open + 1, close)?;
+    let indent = detect_indent(&source[open + 1..close]);
+
+    Ok(Some(FlagsBlock { close, entries, indent, quote }))
+}
+
+/// Find the `}` matching the `{` at `open`
+fn find_matching_brace(source: &str, open: usize) -> Result<usize> {
+    let bytes = source.as_bytes();
+    let mut depth = 0usize;
+    let mut i = open;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\'' | b'"' => i = skip_string(bytes, i)?,
+            b'#' => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'{' | b'[' | b'(' => depth += 1,
+            b'}' | b']' | b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    bail!("FEATURE_FLAGS block is not closed")
+}
+
+/// Return index of the closing quote of the string starting at `start`
+fn skip_string(bytes: &[u8], start: usize) -> Result<usize> {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'\n' => bail!("Unterminated string in FEATURE_FLAGS block"),
+            c if c == quote => return Ok(i),
+            _ => i += 1,
+        }
+    }
+    bail!("Unterminated string in FEATURE_FLAGS block")
+}
+
+/// Skip whitespace and comments
+fn skip_trivia(bytes: &[u8], mut i: usize, end: usize) -> usize {
+    while i < end {
+        match bytes[i] {
+            b' ' | b'\t' | b'\r' | b'\n' => i += 1,
+            b'#' => {
+                while i < end && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            _ => break,
+        }
+    }
+    i
+}
+
+/// Parse `'KEY': value,` pairs between `start` and `end`
+fn parse_entries(source: &str, start: usize, end: usize) -> Result<(Vec<FlagEntry>, char)> {
+    let bytes = source.as_bytes();
+    let mut entries = Vec::new();
+    let mut quote = '\'';
+    let mut i = skip_trivia(bytes, start, end);
+
+    while i < end {
+        // Key
+        if bytes[i] != b'\'' && bytes[i] != b'"' {
+            bail!("Unsupported key in FEATURE_FLAGS at byte {}: only string keys are allowed", i);
+        }
+        if entries.is_empty() {
+            quote = bytes[i] as char;
+        }
+        let key_end = skip_string(bytes, i)?;
+        let name = source[i + 1..key_end].to_string();
+
+        // Colon
+        i = skip_trivia(bytes, key_end + 1, end);
+        if i >= end || bytes[i] != b':' {
+            bail!("Expected ':' after '{}' in FEATURE_FLAGS", name);
+        }
+        i = skip_trivia(bytes, i + 1, end);
+
+        // Value: up to the next top-level comma
+        let value_start = i;
+        let mut depth = 0usize;
+        let mut value_end = i;
+        while i < end {
+            match bytes[i] {
+                b'\'' | b'"' => i = skip_string(bytes, i)?,
+                b'#' if depth == 0 => break,
+                b'{' | b'[' | b'(' => depth += 1,
+                b'}' | b']' | b')' => depth = depth.saturating_sub(1),
+                b',' if depth == 0 => break,
+                _ => {}
+            }
+            i += 1;
+            if !bytes[i - 1].is_ascii_whitespace() {
+                value_end = i;
+            }
+        }
+
+        entries.push(FlagEntry {
+            name,
+            value: source[value_start..value_end].to_string(),
+            value_span: value_start..value_end,
+        });
+
+        i = skip_trivia(bytes, i, end);
+        if i < end && bytes[i] == b',' {
+            i += 1;
+        }
+        i = skip_trivia(bytes, i, end);
+    }
+
+    Ok((entries, quote))
+}
+
+/// Indentation used by existing entries (4 spaces by default)
+fn detect_indent(body: &str) -> String {
+    body.lines()
+        .find(|l| !l.trim().is_empty())
+        .map(|l| l.chars().take_while(|c| c.is_whitespace()).collect::<String>())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "    ".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"import os
+
+FEATURE_FLAGS = {
+    'ALERT_REPORTS': False,
+    # Filters
+    'DRILL_TO_DETAIL': True,  # nice
+    'THUMBNAILS': os.environ.get("THUMBS", "x,y") == "1",
+}
+
+CACHE_CONFIG = {'CACHE_TYPE': 'SimpleCache'}
+"#;
+
+    #[test]
+    fn test_parse_flags() {
+        let cfg = SupersetConfigFile::from_source(Path::new("x.py"), SAMPLE);
+        let flags = cfg.flags().unwrap();
+        assert_eq!(flags.len(), 3);
+        assert_eq!(flags[0].name, "ALERT_REPORTS");
+        assert_eq!(flags[0].enabled(), Some(false));
+        assert_eq!(flags[1].enabled(), Some(true));
+        assert_eq!(flags[2].value, r#"os.environ.get("THUMBS", "x,y") == "1""#);
+        assert_eq!(flags[2].enabled(), None);
+    }
+
+    #[test]
+    fn test_set_existing_flag_preserves_comments() {
+        let mut cfg = SupersetConfigFile::from_source(Path::new("x.py"), SAMPLE);
+        cfg.set_flag("DRILL_TO_DETAIL", false).unwrap();
+        assert!(cfg.source().contains("'DRILL_TO_DETAIL': False,  # nice"));
+        assert!(cfg.source().contains("# Filters"));
+        assert!(cfg.source().contains("CACHE_CONFIG = {'CACHE_TYPE': 'SimpleCache'}"));
+    }
+
+    #[test]
+    fn test_add_new_flag() {
+        let mut cfg = SupersetConfigFile::from_source(Path::new("x.py"), SAMPLE);
+        cfg.set_flag("DRILL_BY", true).unwrap();
+        let flags = cfg.flags().unwrap();
+        assert_eq!(flags.len(), 4);
+        assert!(cfg.source().contains("    'DRILL_BY': True,\n}"));
+    }
+
+    #[test]
+    fn test_missing_block_is_appended() {
+        let mut cfg = SupersetConfigFile::from_source(Path::new("x.py"), "DEBUG = False");
+        cfg.set_flag("ALERT_REPORTS", true).unwrap();
+        let flags = cfg.flags().unwrap();
+        assert_eq!(flags.len(), 1);
+        assert_eq!(flags[0].enabled(), Some(true));
+    }
+
+    #[test]
+    fn test_validate_flag_versions() {
+        assert!(validate_flag("DRILL_BY", Some("3.1.0")).is_ok());
+        assert!(validate_flag("DRILL_BY", Some("2.0.1")).is_err());
+        assert!(validate_flag("VERSIONED_EXPORT", Some("4.0.2")).is_err());
+        assert!(validate_flag("NOT_A_FLAG", Some("4.0.2")).is_err());
+        assert!(validate_flag("DRILL_BY", None).is_ok());
+    }
+}
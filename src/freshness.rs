@@ -0,0 +1,161 @@
+//! Row counts and load times of data tables
+//!
+//! Every load (`load-data`, a launcher upload, the data watcher's rebuild)
+//! records the table's row count and time in `_table_freshness` inside the
+//! same SQLite file, so the record travels with the database. The launcher
+//! UI reads it from `/api/data/freshness` and flags tables that haven't been
+//! reloaded for longer than `freshness.stale_after_hours`.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Bookkeeping table inside each data database
+pub const FRESHNESS_TABLE: &str = "_table_freshness";
+
+/// Freshness settings (`freshness` in config.json)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FreshnessConfig {
+    /// Tables not reloaded for this long are shown as stale
+    pub stale_after_hours: u64,
+}
+
+impl Default for FreshnessConfig {
+    fn default() -> Self {
+        Self { stale_after_hours: 7 * 24 }
+    }
+}
+
+/// One table of the freshness report
+#[derive(Debug, Clone, Serialize)]
+pub struct TableFreshness {
+    pub table: String,
+    pub rows: i64,
+    /// Last recorded load; `None` for tables created outside the launcher
+    pub updated_at: Option<DateTime<Local>>,
+    /// File (or process) the table was last loaded from
+    pub source: Option<String>,
+    pub stale: bool,
+}
+
+fn ensure_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(&format!(
+        "CREATE TABLE IF NOT EXISTS \"{}\" (
+            table_name TEXT PRIMARY KEY,
+            row_count INTEGER NOT NULL,
+            updated_at TEXT NOT NULL,
+            source TEXT
+        )",
+        FRESHNESS_TABLE
+    ))?;
+    Ok(())
+}
+
+/// Record that `table` was just loaded from `source`; returns its row count
+pub fn record(conn: &Connection, table: &str, source: &str) -> Result<i64> {
+    ensure_table(conn)?;
+    let rows: i64 = conn
+        .query_row(&format!("SELECT COUNT(*) FROM {}", crate::data_loader::quote_ident(table)), [], |r| r.get(0))
+        .with_context(|| format!("Failed to count rows of {}", table))?;
+    conn.execute(
+        &format!(
+            "INSERT INTO \"{}\" (table_name, row_count, updated_at, source) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(table_name) DO UPDATE SET row_count = ?2, updated_at = ?3, source = ?4",
+            FRESHNESS_TABLE
+        ),
+        params![table, rows, Local::now().to_rfc3339(), source],
+    )?;
+    Ok(rows)
+}
+
+/// Every data table of the database at `db_path`, oldest load first
+pub fn report(db_path: &Path, config: &FreshnessConfig) -> Result<Vec<TableFreshness>> {
+    if !db_path.exists() {
+        return Ok(Vec::new());
+    }
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open {}", db_path.display()))?;
+
+    let tables: Vec<String> = conn
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' AND name NOT IN (?1, ?2)")?
+        .query_map([FRESHNESS_TABLE, crate::validations::RESULTS_TABLE], |r| r.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    let has_records = conn
+        .query_row("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1", [FRESHNESS_TABLE], |_| Ok(()))
+        .optional()?
+        .is_some();
+
+    let stale_after = chrono::Duration::hours(config.stale_after_hours as i64);
+    let now = Local::now();
+    let mut report = Vec::new();
+    for table in tables {
+        let recorded: Option<(i64, String, Option<String>)> = if has_records {
+            conn.query_row(
+                &format!("SELECT row_count, updated_at, source FROM \"{}\" WHERE table_name = ?1", FRESHNESS_TABLE),
+                [&table],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )
+            .optional()?
+        } else {
+            None
+        };
+
+        let entry = match recorded {
+            Some((rows, updated_at, source)) => {
+                let updated_at = DateTime::parse_from_rfc3339(&updated_at).ok().map(|t| t.with_timezone(&Local));
+                TableFreshness {
+                    stale: updated_at.is_some_and(|t| now - t > stale_after),
+                    table,
+                    rows,
+                    updated_at,
+                    source,
+                }
+            }
+            None => {
+                let rows = conn.query_row(&format!("SELECT COUNT(*) FROM {}", crate::data_loader::quote_ident(table)), [], |r| r.get(0))?;
+                TableFreshness { table, rows, updated_at: None, source: None, stale: false }
+            }
+        };
+        report.push(entry);
+    }
+    report.sort_by(|a, b| a.updated_at.cmp(&b.updated_at).then_with(|| a.table.cmp(&b.table)));
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_record_and_report() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("examples.db");
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE sales (id INTEGER); INSERT INTO sales VALUES (1), (2), (3);
+             CREATE TABLE manual (id INTEGER); INSERT INTO manual VALUES (1);",
+        )
+        .unwrap();
+        assert_eq!(record(&conn, "sales", "sales.csv").unwrap(), 3);
+
+        // Backdate the load past the threshold
+        conn.execute(
+            "UPDATE _table_freshness SET updated_at = ?1",
+            [(Local::now() - chrono::Duration::hours(30)).to_rfc3339()],
+        )
+        .unwrap();
+        drop(conn);
+
+        let report = report(&db_path, &FreshnessConfig { stale_after_hours: 24 }).unwrap();
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].table, "manual");
+        assert!(report[0].updated_at.is_none() && !report[0].stale);
+        assert_eq!(report[1].rows, 3);
+        assert_eq!(report[1].source.as_deref(), Some("sales.csv"));
+        assert!(report[1].stale);
+    }
+}
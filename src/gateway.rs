@@ -1,44 +1,295 @@
 //! Gateway module for reverse proxying requests to Superset
 //! 
 //! Handles routing between:
-//! - /docs/* -> Documentation server
-//! - /static/assets/* -> Direct static file serving (Fast!)
-//! - /api/v1/chart/data -> Cached API requests (Smart!)
-//! - /* -> Superset backend
+//! - /docs/* -> Documentation server (large assets streamed with Range support)
+//! - /static/assets/* -> Direct static file serving, hot assets from memory (Fast!)
+//! - /metrics -> Hot asset list and memory usage
+//! - /gateway/routes -> Route mappings, cache policies and upstream status
+//!   (served on the public port, so file targets are named relative to the kit)
+//! - /swagger/v1 -> Superset's Swagger UI (a hint page when it's switched off)
+//! - /api/v1/chart/data -> Cached API requests, streamed while cached (see `gateway_spool`)
+//! - / -> Landing dashboard redirect (optional, for kiosks)
+//! - /_canary/on, /_canary/off -> Switch this browser to the trial Superset (optional)
+//! - /* -> Superset backend (or the trial instance when the canary cookie is set)
+//!
+//! Every request is tagged with an `X-Request-Id` (see `request_id`).
+//! Requests that reach Superset take a slot per instance first and wait
+//! their turn in a queue (see `upstream_limit`).
 
 use axum::{
     body::Body,
     extract::{Request, State},
-    response::{IntoResponse, Response},
+    response::{Html, IntoResponse, Json, Redirect, Response},
+    routing::get,
     Router,
-    http::{Method, Uri},
+    http::{header, HeaderMap, HeaderValue, Method, Uri},
 };
 use hyper::StatusCode;
 use hyper_util::{client::legacy::Client, rt::TokioExecutor};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tower_http::services::ServeDir;
-use tracing::{info, error};
+use tracing::{info, error, warn};
 use std::hash::{Hash, Hasher};
 use std::collections::hash_map::DefaultHasher;
 
+use crate::gateway_spool::ResponseCache;
+use crate::asset_cache::CachePolicyConfig;
+use crate::hot_assets::{HotAssets, HotAssetsConfig};
+use crate::security_headers::SecurityConfig;
+use crate::self_docs::ApiRoute;
+use crate::slo;
+use crate::upstream_limit::{self, ConcurrencyConfig, Limiter};
+use crate::sled_journal::{CompactReport, Journal, CHECKPOINT_INTERVAL, CHECKPOINT_MAX_VALUE_BYTES, COMPACT_INTERVAL, DEFAULT_TREE};
+
+/// Every route of the gateway, for the generated reference
+pub const ROUTES: &[ApiRoute] = &[
+    ApiRoute { method: "GET", path: "/", token: false, summary: "Стартовый дашборд (`gateway.landing_dashboard`), иначе Superset" },
+    ApiRoute { method: "GET", path: "/docs/*", token: false, summary: "Документация и база знаний" },
+    ApiRoute { method: "GET", path: "/static/assets/*", token: false, summary: "Статика Superset прямо с диска, частые файлы из памяти" },
+    ApiRoute { method: "GET", path: "/metrics", token: false, summary: "Горячие файлы статики, занятая ими память и очередь запросов к Superset" },
+    ApiRoute { method: "GET", path: "/gateway/routes", token: false, summary: "Куда шлюз направляет каждый адрес, политика кэша и состояние Superset" },
+    ApiRoute { method: "GET", path: "/swagger/v1", token: false, summary: "Swagger UI Superset (описание API — `/api/v1/_openapi`)" },
+    ApiRoute { method: "POST", path: "/api/v1/chart/data", token: false, summary: "Данные графиков Superset через кэш (`x-superset-cache: HIT/MISS`)" },
+    ApiRoute { method: "GET", path: "/_canary/on", token: false, summary: "Переключить браузер на пробный Superset (`gateway.canary`)" },
+    ApiRoute { method: "GET", path: "/_canary/off", token: false, summary: "Вернуть браузер на основной Superset" },
+    ApiRoute { method: "*", path: "/*", token: false, summary: "Всё остальное — Superset" },
+];
+
+/// Gateway settings (`gateway` section of config.json)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GatewayConfig {
+    /// Dashboard slug (or id) that `/` redirects to, e.g. `rzd_analytics`
+    pub landing_dashboard: Option<String>,
+    /// Open the landing dashboard without Superset navigation (kiosk mode)
+    pub standalone: bool,
+    /// Second Superset build trialled alongside production
+    pub canary: Option<CanaryConfig>,
+    /// In-memory cache of the most requested static assets; unset means
+    /// the hardware profile's size
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hot_assets: Option<HotAssetsConfig>,
+    /// Browser caching of static files (immutable hashed bundles)
+    pub cache_policy: CachePolicyConfig,
+    /// Empty the chart-data cache whenever Superset restarts (fresh data
+    /// after every restart instead of warm dashboards)
+    pub flush_cache_on_restart: bool,
+    /// Requests let through to Superset at once, and the queue behind them
+    pub concurrency: ConcurrencyConfig,
+}
+
+/// Trial Superset instance (`gateway.canary` in config.json).
+///
+/// Browsers that visit `/_canary/on` get a cookie and are routed to this
+/// instance; everyone else keeps using production. `/_canary/off` switches back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CanaryConfig {
+    /// Internal port of the trial instance
+    pub port: u16,
+    /// Python tree with the new Superset build (relative to root)
+    pub python_dir: String,
+    /// Superset home of the trial instance (relative to root)
+    pub superset_home: String,
+    /// Cookie that selects the trial instance
+    pub cookie: String,
+}
+
+impl Default for CanaryConfig {
+    fn default() -> Self {
+        Self {
+            port: 8098,
+            python_dir: "python_canary".to_string(),
+            superset_home: "superset_home_canary".to_string(),
+            cookie: "superset_upstream".to_string(),
+        }
+    }
+}
+
+/// Cookie value that routes to the trial instance
+const CANARY_COOKIE_VALUE: &str = "canary";
+
+/// How long `/gateway/routes` waits for an upstream's `/health`
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Shown at `/swagger/v1` when Superset answers 404 there
+const SWAGGER_OFF_PAGE: &str = "<!doctype html><meta charset=\"utf-8\"><title>Swagger UI</title>\
+<h1>Swagger UI в Superset выключен</h1>\
+<p>Добавьте <code>FAB_API_SWAGGER_UI = True</code> в <code>superset_home/superset_config.py</code> и перезапустите Superset.</p>\
+<p>Описание API в формате OpenAPI: <a href=\"/api/v1/_openapi\">/api/v1/_openapi</a>. Маршруты шлюза: <a href=\"/gateway/routes\">/gateway/routes</a>.</p>";
+
+impl GatewayConfig {
+    /// Redirect target for `/`, if a landing dashboard is configured
+    fn landing_url(&self) -> Option<String> {
+        let slug = self.landing_dashboard.as_deref()?.trim();
+        if slug.is_empty() {
+            return None;
+        }
+        let query = if self.standalone { "?standalone=true" } else { "" };
+        Some(format!("/superset/dashboard/{}/{}", slug, query))
+    }
+}
+
 /// Gateway configuration state
 #[derive(Clone)]
 struct GatewayState {
     superset_port: u16,
     client: Client<hyper_util::client::legacy::connect::HttpConnector, Body>,
-    cache: sled::Db,
+    cache: ResponseCache,
+    landing_url: Option<String>,
+    canary: Option<CanaryConfig>,
+    /// Kit root (for local usage counters)
+    root: PathBuf,
+    hot_assets: Arc<HotAssets>,
+    /// Slots per upstream port (empty when the limit is off)
+    limiters: Arc<HashMap<u16, Limiter>>,
+    /// What `/gateway/routes` lists
+    routes: Arc<Vec<RouteMapping>>,
+}
+
+/// Where one gateway route sends its requests
+#[derive(Debug, Clone, Serialize)]
+struct RouteMapping {
+    method: &'static str,
+    path: &'static str,
+    target: String,
+    /// Caching on the route, if any
+    cache: Option<String>,
+    summary: &'static str,
+}
+
+/// One Superset instance behind the gateway, as `/gateway/routes` found it
+#[derive(Debug, Serialize)]
+struct UpstreamStatus {
+    name: &'static str,
+    port: u16,
+    up: bool,
+    /// HTTP status of `/health` (none: no answer)
+    status: Option<u16>,
+    latency_ms: Option<u64>,
+    queue: Option<upstream_limit::LimiterStats>,
+}
+
+impl GatewayState {
+    /// Port of the Superset instance this request should go to
+    fn upstream_port(&self, headers: &HeaderMap) -> u16 {
+        match &self.canary {
+            Some(canary) if has_cookie(headers, &canary.cookie, CANARY_COOKIE_VALUE) => canary.port,
+            _ => self.superset_port,
+        }
+    }
+
+    /// Wait for a slot on `port`; `Err` is the 429 to send instead
+    async fn acquire(&self, port: u16, headers: &HeaderMap) -> Result<Option<tokio::sync::OwnedSemaphorePermit>, Response> {
+        let Some(limiter) = self.limiters.get(&port) else {
+            return Ok(None);
+        };
+        match limiter.acquire().await {
+            Ok(permit) => Ok(Some(permit)),
+            Err(rejection) => {
+                warn!("⏳ Superset on port {} busy ({:?}), request turned away", port, rejection);
+                Err(upstream_limit::busy_response(headers, rejection))
+            }
+        }
+    }
+}
+
+/// Whether the request carries `name=value` in its Cookie header(s)
+fn has_cookie(headers: &HeaderMap, name: &str, value: &str) -> bool {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .any(|(k, v)| k == name && v == value)
+}
+
+/// Browser caching of the paths under `prefix` (`gateway.cache_policy`)
+fn browser_cache(policy: &CachePolicyConfig, prefix: &str) -> Option<String> {
+    if !policy.enabled {
+        return None;
+    }
+    let rule = policy.rules.iter().filter(|rule| prefix.starts_with(&rule.prefix)).max_by_key(|rule| rule.prefix.len())?;
+    Some(format!("Cache-Control: \"{}\" (hashed names), \"{}\" (others)", rule.hashed, rule.other))
+}
+
+/// A served directory as `/gateway/routes` shows it: never an absolute path
+fn kit_files(root: &Path, dir: &Path) -> String {
+    match dir.strip_prefix(root) {
+        Ok(relative) => format!("files: {}", relative.display().to_string().replace('\\', "/")),
+        Err(_) => "files outside the kit".to_string(),
+    }
+}
+
+/// Every route of `ROUTES` with where it leads under this configuration
+fn route_table(config: &GatewayConfig, superset_port: u16, root: &Path, docs_root: &Path, static_assets: &Path) -> Vec<RouteMapping> {
+    let superset = format!("Superset, 127.0.0.1:{}", superset_port);
+    ROUTES
+        .iter()
+        .map(|route| {
+            let (target, cache) = match route.path {
+                "/" => (config.landing_url().unwrap_or_else(|| superset.clone()), None),
+                "/docs/*" => (kit_files(root, docs_root), browser_cache(&config.cache_policy, "/docs/")),
+                "/static/assets/*" => {
+                    let hot_assets = crate::hardware::current().hot_assets(config.hot_assets.as_ref());
+                    let hot = format!("hot assets: {} MB, {} files", hot_assets.memory_mb, hot_assets.max_entries);
+                    let cache = match browser_cache(&config.cache_policy, "/static/assets/") {
+                        Some(browser) => format!("{}; {}", browser, hot),
+                        None => hot,
+                    };
+                    (kit_files(root, static_assets), Some(cache))
+                }
+                "/metrics" | "/gateway/routes" => ("gateway".to_string(), None),
+                "/api/v1/chart/data" => {
+                    let flush = if config.flush_cache_on_restart { ", emptied when Superset restarts" } else { "" };
+                    (superset.clone(), Some(format!("response cache (cache/gateway_sled){}", flush)))
+                }
+                "/_canary/on" | "/_canary/off" => match &config.canary {
+                    Some(canary) => (format!("trial Superset, 127.0.0.1:{} (cookie {})", canary.port, canary.cookie), None),
+                    None => ("off (no gateway.canary)".to_string(), None),
+                },
+                _ => (superset.clone(), None),
+            };
+            RouteMapping { method: route.method, path: route.path, target, cache, summary: route.summary }
+        })
+        .collect()
+}
+
+fn cache_journal(root: &std::path::Path) -> Journal {
+    Journal::new(&root.join("cache").join("gateway_sled"), &[DEFAULT_TREE]).max_value_bytes(CHECKPOINT_MAX_VALUE_BYTES)
+}
+
+/// Rewrite the gateway's response cache (the gateway must be stopped)
+pub fn compact_cache(root: &std::path::Path) -> anyhow::Result<CompactReport> {
+    cache_journal(root).compact()
 }
 
 /// Start the gateway server
 pub async fn start_gateway(
     public_port: u16, 
     superset_port: u16, 
-    root_path: &std::path::Path
+    root_path: &std::path::Path,
+    config: &GatewayConfig,
 ) -> anyhow::Result<()> {
+    let landing_url = config.landing_url();
+
     info!("🚀 Starting Gateway on port {}", public_port);
+    if let Some(url) = &landing_url {
+        info!("   - /     -> {}", url);
+    }
     info!("   - /docs -> Documentation");
     info!("   - /static/assets -> Direct file serving");
+    info!("   - /gateway/routes -> Route listing, /swagger/v1 -> Superset API docs");
     info!("   - /*    -> Superset (internal port {})", superset_port);
+    if let Some(canary) = &config.canary {
+        info!("   - /_canary/on -> trial Superset (internal port {})", canary.port);
+    }
 
     // Create HTTP client for proxying
     let client: Client<hyper_util::client::legacy::connect::HttpConnector, Body> = 
@@ -46,14 +297,53 @@ pub async fn start_gateway(
 
     // Open/Create Cache
     let cache_path = root_path.join("cache").join("gateway_sled");
-    let cache = sled::open(&cache_path)?;
+    let journal = cache_journal(root_path);
+    if let Err(e) = journal.compact_if_due(COMPACT_INTERVAL, |_| true) {
+        warn!("Gateway cache compaction skipped: {}", e);
+    }
+    let cache = journal.open()?;
+    journal.spawn_periodic(cache.clone(), CHECKPOINT_INTERVAL);
     info!("   - Smart Cache enabled at: {}", cache_path.display());
 
-    let state = GatewayState {
-        superset_port,
-        client,
-        cache,
+    // Static Assets Service (Direct from Python env)
+    // Path: <site-packages>/superset/static/assets
+    let static_assets_path = crate::python::PythonEnv::new(root_path)?.site_packages().join("superset/static/assets");
+    let hot_assets_config = crate::hardware::current().hot_assets(config.hot_assets.as_ref());
+    let hot_assets = Arc::new(HotAssets::new(&static_assets_path, &hot_assets_config));
+
+    let encryption = crate::cache_crypto::CacheEncryptionConfig::load(root_path);
+    let response_cache = match crate::cache_crypto::cipher_for(root_path, crate::cache_crypto::GATEWAY, &encryption) {
+        Ok(cipher) => {
+            let key_changed = crate::cache_crypto::key_changed(root_path, crate::cache_crypto::GATEWAY, cipher.as_ref())?;
+            if cipher.is_some() {
+                info!("   - Cached chart data is encrypted");
+            }
+            let response_cache = ResponseCache::new(root_path, cache).encrypted(cipher);
+            if key_changed {
+                let removed = response_cache.clear()?;
+                info!("   - Cache encryption key changed: {} cached response(s) dropped", removed);
+            }
+            response_cache
+        }
+        Err(e) => {
+            warn!("Chart data is not cached: {:#}", e);
+            ResponseCache::new(root_path, cache).disabled()
+        }
     };
+    if config.flush_cache_on_restart {
+        crate::upstream_boot::spawn_watch(root_path, superset_port, response_cache.clone());
+        info!("   - Cache flushed on Superset restart");
+    }
+
+    let mut limiters = HashMap::new();
+    if config.concurrency.enabled {
+        let limit = crate::hardware::current().upstream_requests(config.concurrency.max_requests);
+        limiters.insert(superset_port, Limiter::new(&config.concurrency, limit));
+        if let Some(canary) = &config.canary {
+            limiters.insert(canary.port, Limiter::new(&config.concurrency, limit));
+        }
+        info!("   - Up to {} Superset request(s) at once, {} queued", limit, config.concurrency.queue);
+    }
 
     // Docs service
     // Served as static for now, or use docs server logic? 
@@ -63,19 +353,46 @@ pub async fn start_gateway(
     // Let's point /docs to `_site` if it exists, else `knowledge`.
     let site_path = root_path.join("_site");
     let docs_root = if site_path.exists() { site_path } else { root_path.join("knowledge") };
-    let docs_service = ServeDir::new(docs_root).append_index_html_on_directories(true);
 
-    // Static Assets Service (Direct from Python env)
-    // Path: python/Lib/site-packages/superset/static/assets
-    let static_assets_path = root_path.join("python/Lib/site-packages/superset/static/assets");
-    let static_service = ServeDir::new(static_assets_path);
+    let state = GatewayState {
+        superset_port,
+        client,
+        cache: response_cache,
+        landing_url,
+        canary: config.canary.clone(),
+        root: root_path.to_path_buf(),
+        hot_assets: hot_assets.clone(),
+        limiters: Arc::new(limiters),
+        routes: Arc::new(route_table(config, superset_port, root_path, &docs_root, &static_assets_path)),
+    };
+
+    let docs_service = Router::new()
+        .fallback_service(ServeDir::new(&docs_root).append_index_html_on_directories(true))
+        .layer(axum::middleware::from_fn_with_state(
+            docs_root.clone(),
+            crate::large_files::large_file_middleware,
+        ))
+        .layer(axum::middleware::from_fn(crate::encoding::transcode_middleware));
+
+    let static_service = Router::new()
+        .fallback_service(ServeDir::new(&static_assets_path))
+        .layer(axum::middleware::from_fn_with_state(hot_assets, crate::hot_assets::middleware));
 
     // Build router
     let app = Router::new()
         .nest_service("/docs", docs_service)
         .nest_service("/static/assets", static_service) // Intercept static assets
+        .route("/metrics", get(metrics_handler))
+        .route("/gateway/routes", get(routes_handler))
+        .route("/swagger/v1", get(swagger_handler))
         .fallback(proxy_handler) // Smart proxy for everything else
-        .with_state(state);
+        .with_state(state)
+        .layer(axum::middleware::from_fn_with_state(
+            Arc::new(config.cache_policy.clone()),
+            crate::asset_cache::middleware,
+        ))
+        .layer(axum::middleware::from_fn(crate::request_id::middleware));
+    let app = crate::security_headers::apply(app, &SecurityConfig::load(root_path), false);
 
     let addr = SocketAddr::from(([0, 0, 0, 0], public_port));
     let listener = tokio::net::TcpListener::bind(addr).await?;
@@ -86,14 +403,67 @@ pub async fn start_gateway(
     Ok(())
 }
 
-/// Handler that proxies requests to Superset with Smart Caching
+/// Handler that proxies requests to Superset, counting them for the SLO report
 async fn proxy_handler(
     State(state): State<GatewayState>,
+    req: Request,
+) -> Result<Response, StatusCode> {
+    let started = Instant::now();
+    let chart_data = req.method() == Method::POST && req.uri().path() == "/api/v1/chart/data";
+    let result = route_request(state, req).await;
+    let status = result.as_ref().map_or_else(|status| *status, |response| response.status());
+    slo::add(slo::Metric::Requests, 1);
+    if status.is_server_error() {
+        slo::add(slo::Metric::Errors, 1);
+    }
+    if chart_data && status.is_success() {
+        slo::add(slo::Metric::Renders, 1);
+        slo::add(slo::Metric::RenderMillis, started.elapsed().as_millis() as u64);
+    }
+    result
+}
+
+/// Route a request to Superset with Smart Caching
+async fn route_request(
+    state: GatewayState,
     mut req: Request,
 ) -> Result<Response, StatusCode> {
     let path = req.uri().path().to_string();
     let method = req.method().clone();
     
+    // Kiosk landing page: boot straight into the configured dashboard
+    if method == Method::GET && path == "/" {
+        if let Some(url) = &state.landing_url {
+            return Ok(Redirect::temporary(url).into_response());
+        }
+    }
+    
+    if method == Method::GET {
+        if let Some(dashboard) = crate::usage_stats::dashboard_from_path(&path) {
+            let root = state.root.clone();
+            let event = dashboard.to_string();
+            tokio::task::spawn_blocking(move || {
+                crate::usage_stats::record(&root, crate::usage_stats::UsageEvent::DashboardOpen(&event))
+            });
+        }
+    }
+    
+    // A/B switch between production and the trial Superset
+    if let Some(canary) = &state.canary {
+        if method == Method::GET && (path == "/_canary/on" || path == "/_canary/off") {
+            let cookie = if path == "/_canary/on" {
+                format!("{}={}; Path=/; SameSite=Lax", canary.cookie, CANARY_COOKIE_VALUE)
+            } else {
+                format!("{}=; Path=/; Max-Age=0", canary.cookie)
+            };
+            let mut response = Redirect::temporary("/").into_response();
+            if let Ok(value) = HeaderValue::from_str(&cookie) {
+                response.headers_mut().insert(header::SET_COOKIE, value);
+            }
+            return Ok(response);
+        }
+    }
+    
     // Check if cacheable (API chart data)
     // /api/v1/chart/data is POST
     if method == Method::POST && path == "/api/v1/chart/data" {
@@ -104,6 +474,64 @@ async fn proxy_handler(
     forward_request(state, req).await
 }
 
+/// Gateway internals for tuning: which assets sit in memory and how much it costs
+async fn metrics_handler(State(state): State<GatewayState>) -> impl IntoResponse {
+    let upstreams: HashMap<String, _> = state
+        .limiters
+        .iter()
+        .map(|(port, limiter)| (port.to_string(), limiter.stats()))
+        .collect();
+    Json(serde_json::json!({
+        "hot_assets": state.hot_assets.stats(),
+        "upstreams": upstreams,
+    }))
+}
+
+/// Health of one upstream, with its request queue
+async fn probe(state: &GatewayState, name: &'static str, port: u16) -> UpstreamStatus {
+    let started = Instant::now();
+    let request = Request::get(format!("http://127.0.0.1:{}/health", port)).body(Body::empty());
+    let answer = match request {
+        Ok(request) => tokio::time::timeout(PROBE_TIMEOUT, state.client.request(request)).await.ok().and_then(Result::ok),
+        Err(_) => None,
+    };
+    UpstreamStatus {
+        name,
+        port,
+        up: answer.as_ref().is_some_and(|res| res.status().is_success()),
+        status: answer.as_ref().map(|res| res.status().as_u16()),
+        latency_ms: answer.is_some().then(|| started.elapsed().as_millis() as u64),
+        queue: state.limiters.get(&port).map(Limiter::stats),
+    }
+}
+
+/// Route mappings, cache policies and upstream status in one place for
+/// integrators, with pointers to Superset's own API description
+async fn routes_handler(State(state): State<GatewayState>) -> impl IntoResponse {
+    let mut upstreams = vec![probe(&state, "superset", state.superset_port).await];
+    if let Some(canary) = &state.canary {
+        upstreams.push(probe(&state, "canary", canary.port).await);
+    }
+    Json(serde_json::json!({
+        "routes": state.routes.as_slice(),
+        "upstreams": upstreams,
+        "superset_api": {
+            "swagger_ui": "/swagger/v1",
+            "openapi": "/api/v1/_openapi",
+        },
+    }))
+}
+
+/// Superset's Swagger UI; FAB serves it only with `FAB_API_SWAGGER_UI`, so a
+/// 404 gets a page saying how to switch it on
+async fn swagger_handler(State(state): State<GatewayState>, req: Request) -> Result<Response, StatusCode> {
+    let response = forward_request(state, req).await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok((StatusCode::NOT_FOUND, Html(SWAGGER_OFF_PAGE)).into_response());
+    }
+    Ok(response)
+}
+
 async fn handle_cached_request(
     state: GatewayState,
     req: Request,
@@ -112,39 +540,42 @@ async fn handle_cached_request(
     let (parts, body) = req.into_parts();
     let bytes = axum::body::to_bytes(body, usize::MAX).await
         .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let upstream_port = state.upstream_port(&parts.headers);
     
-    // 2. Compute Hash
+    // 2. Compute Hash (per upstream: two Superset versions may answer differently)
     let mut hasher = DefaultHasher::new();
+    upstream_port.hash(&mut hasher);
     parts.uri.path().hash(&mut hasher);
     bytes.hash(&mut hasher); // Hash the JSON body
     let hash = hasher.finish();
     let key = format!("req_{}", hash);
 
     // 3. Check Cache
-    if let Ok(Some(cached)) = state.cache.get(&key) {
+    if let Some((body, len)) = state.cache.get(&key).await {
         // Return cached response
-        // Note: We need to store headers + status + body.
         // For simplicity v1, assuming 200 OK and application/json.
-        // Better: use serde to store struct { status, headers, body }
-        // Here we just return body as JSON.
         info!("⚡ CACHE HIT: {}", parts.uri.path());
-        
-        let body = Body::from(cached.to_vec());
+
         let mut response = Response::new(body);
         *response.status_mut() = StatusCode::OK;
         response.headers_mut().insert("content-type", "application/json".parse().unwrap());
+        response.headers_mut().insert(header::CONTENT_LENGTH, len.into());
         response.headers_mut().insert("x-superset-cache", "HIT".parse().unwrap());
         return Ok(response);
     }
 
-    // 4. Cache Miss - Forward Request
+    // 4. Cache Miss - Forward Request once Superset has a free slot
+    let permit = match state.acquire(upstream_port, &parts.headers).await {
+        Ok(permit) => permit,
+        Err(busy) => return Ok(busy),
+    };
     // Reconstruct request
     let body = Body::from(bytes.clone());
     let mut new_req = Request::from_parts(parts, body);
     
     // Helper to modify URI for forwarding
     let path_query = new_req.uri().path_and_query().map(|v| v.as_str()).unwrap_or("/").to_string();
-    let uri_string = format!("http://127.0.0.1:{}{}", state.superset_port, path_query);
+    let uri_string = format!("http://127.0.0.1:{}{}", upstream_port, path_query);
     let uri = uri_string.parse::<Uri>().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     *new_req.uri_mut() = uri;
     new_req.headers_mut().remove("host");
@@ -154,23 +585,15 @@ async fn handle_cached_request(
         Ok(res) => {
             let status = res.status();
             if status.is_success() {
-                // Cache the response body
-                // We need to read response body to cache it
+                // Stream to the client, caching a copy on the side
                 let (resp_parts, resp_body) = res.into_parts();
-                let resp_bytes = axum::body::to_bytes(Body::new(resp_body), usize::MAX).await
-                    .map_err(|_| StatusCode::BAD_GATEWAY)?;
-                
-                // Save to sled (TTL could be added here)
-                let _ = state.cache.insert(&key, resp_bytes.to_vec());
-                let _ = state.cache.flush();
-                info!("🐢 CACHE MISS: {} (Cached {} bytes)", path_query, resp_bytes.len());
-
-                // Return response
-                let mut response = Response::from_parts(resp_parts, Body::from(resp_bytes));
+                info!("🐢 CACHE MISS: {}", path_query);
+                let body = Body::new(state.cache.tee(key, resp_body));
+                let mut response = Response::from_parts(resp_parts, body);
                 response.headers_mut().insert("x-superset-cache", "MISS".parse().unwrap());
-                Ok(response)
+                Ok(upstream_limit::hold(response, permit))
             } else {
-                Ok(res.into_response())
+                Ok(upstream_limit::hold(res.into_response(), permit))
             }
         }
         Err(e) => {
@@ -181,15 +604,20 @@ async fn handle_cached_request(
 }
 
 async fn forward_request(state: GatewayState, mut req: Request) -> Result<Response, StatusCode> {
+    let port = state.upstream_port(req.headers());
     let path_query = req.uri().path_and_query().map(|v| v.as_str()).unwrap_or("/");
-    let uri_string = format!("http://127.0.0.1:{}{}", state.superset_port, path_query);
+    let uri_string = format!("http://127.0.0.1:{}{}", port, path_query);
     
     if let Ok(uri) = uri_string.parse::<Uri>() {
+        let permit = match state.acquire(port, req.headers()).await {
+            Ok(permit) => permit,
+            Err(busy) => return Ok(busy),
+        };
         *req.uri_mut() = uri;
         req.headers_mut().remove("host");
         
         match state.client.request(req).await {
-            Ok(res) => Ok(res.into_response()),
+            Ok(res) => Ok(upstream_limit::hold(res.into_response(), permit)),
             Err(e) => {
                 error!("Proxy error: {}", e);
                 Err(StatusCode::BAD_GATEWAY)
@@ -199,3 +627,40 @@ async fn forward_request(state: GatewayState, mut req: Request) -> Result<Respon
         Err(StatusCode::INTERNAL_SERVER_ERROR)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_cookie() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, "session=abc; superset_upstream=canary".parse().unwrap());
+        assert!(has_cookie(&headers, "superset_upstream", "canary"));
+        assert!(!has_cookie(&headers, "session", "canary"));
+        assert!(!has_cookie(&HeaderMap::new(), "superset_upstream", "canary"));
+    }
+
+    #[test]
+    fn test_route_table() {
+        let config = GatewayConfig {
+            landing_dashboard: Some("rzd_analytics".to_string()),
+            flush_cache_on_restart: true,
+            ..GatewayConfig::default()
+        };
+        let table = route_table(&config, 8088, Path::new("/kit"), Path::new("/kit/_site"), Path::new("/kit/static"));
+        assert_eq!(table.len(), ROUTES.len());
+        let route = |path: &str| table.iter().find(|r| r.path == path).unwrap();
+
+        assert_eq!(route("/").target, "/superset/dashboard/rzd_analytics/");
+        assert_eq!(route("/*").target, "Superset, 127.0.0.1:8088");
+        assert!(route("/docs/*").cache.as_deref().unwrap().contains("immutable"));
+        assert_eq!(route("/docs/*").target, "files: _site");
+        assert_eq!(route("/static/assets/*").target, "files: static");
+        assert_eq!(kit_files(Path::new("/kit"), Path::new("/opt/assets")), "files outside the kit");
+        assert!(route("/static/assets/*").cache.as_deref().unwrap().ends_with("hot assets: 32 MB, 64 files"));
+        assert!(route("/api/v1/chart/data").cache.as_deref().unwrap().contains("Superset restarts"));
+        assert_eq!(route("/_canary/on").target, "off (no gateway.canary)");
+        assert!(route("/swagger/v1").cache.is_none());
+    }
+}
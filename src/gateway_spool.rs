@@ -0,0 +1,390 @@
+//! Streaming fill of the gateway's response cache
+//!
+//! A cache miss on `/api/v1/chart/data` is sent to the client frame by frame
+//! as it arrives from Superset, while a blocking writer copies the same
+//! frames aside. A response that ends within [`SLED_MAX_BYTES`] goes into
+//! the sled cache as before; a bigger one is kept as a file in
+//! `cache/gateway_files/` and streamed from disk on a hit, so a tens-of-MB
+//! export is never held in memory whole. Responses over [`MAX_CACHED_BYTES`],
+//! transfers cut short and a writer that falls behind the client leave
+//! nothing cached: the cache never slows the response down. With
+//! `cache_encryption` covering `gateway`, both kinds of entry are sealed
+//! (see `cache_crypto`) and decrypted on the way out.
+
+use axum::body::{Body, Bytes, HttpBody};
+use hyper::body::{Frame, SizeHint};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+use tokio_util::io::ReaderStream;
+use tracing::{info, warn};
+
+use crate::cache_crypto::{self, CacheCipher, SealedReader, SealedWriter};
+
+/// Largest response stored in sled; bigger ones become files
+pub const SLED_MAX_BYTES: usize = 1024 * 1024;
+
+/// Largest response cached at all
+pub const MAX_CACHED_BYTES: usize = 64 * 1024 * 1024;
+
+/// Total size of the file-backed entries; the oldest go first
+const MAX_FILES_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Frames the writer may lag behind before caching is given up
+const SPOOL_QUEUE: usize = 64;
+
+/// Read chunk size when streaming a cached file
+const CHUNK_SIZE: usize = 256 * 1024;
+
+/// Folder of file-backed entries (inside `cache/`)
+const FILES_DIR: &str = "gateway_files";
+
+/// Decrypted blocks a reader may run ahead of the client
+const DECRYPT_QUEUE: usize = 4;
+
+/// Numbers the spool files of this process, so concurrent writers never share one
+static SPOOL_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// The gateway's response cache: small entries in sled, big ones on disk
+#[derive(Clone)]
+pub struct ResponseCache {
+    db: sled::Db,
+    files: PathBuf,
+    cipher: Option<CacheCipher>,
+    /// `false`: encryption is required but has no key, nothing is cached
+    enabled: bool,
+}
+
+impl ResponseCache {
+    pub fn new(root: &Path, db: sled::Db) -> Self {
+        Self { db, files: root.join("cache").join(FILES_DIR), cipher: None, enabled: true }
+    }
+
+    /// Seal entries with `cipher`
+    pub fn encrypted(mut self, cipher: Option<CacheCipher>) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
+    /// Pass everything through without caching
+    pub fn disabled(mut self) -> Self {
+        self.enabled = false;
+        self
+    }
+
+    fn file(&self, key: &str) -> PathBuf {
+        self.files.join(key)
+    }
+
+    /// Cached body and its length
+    pub async fn get(&self, key: &str) -> Option<(Body, u64)> {
+        if !self.enabled {
+            return None;
+        }
+        if let Ok(Some(cached)) = self.db.get(key) {
+            let cached = match &self.cipher {
+                Some(cipher) => match cipher.open(key.as_bytes(), &cached) {
+                    Some(plain) => plain,
+                    None => {
+                        let _ = self.db.remove(key);
+                        return None;
+                    }
+                },
+                None => cached.to_vec(),
+            };
+            let len = cached.len() as u64;
+            return Some((Body::from(cached), len));
+        }
+        let path = self.file(key);
+        let file = tokio::fs::File::open(&path).await.ok()?;
+        let len = file.metadata().await.ok()?.len();
+        let Some(cipher) = self.cipher.clone() else {
+            return Some((Body::from_stream(ReaderStream::with_capacity(file, CHUNK_SIZE)), len));
+        };
+        let Some(plain_len) = cache_crypto::plain_len(len) else {
+            warn!("Cached response {} is cut off, dropped", key);
+            drop(file);
+            let _ = tokio::fs::remove_file(&path).await;
+            return None;
+        };
+        let file = io::BufReader::new(file.into_std().await);
+        let key = key.to_string();
+        let (tx, rx) = mpsc::channel(DECRYPT_QUEUE);
+        tokio::task::spawn_blocking(move || {
+            for block in SealedReader::new(file, cipher, &key) {
+                let failed = block.is_err();
+                if tx.blocking_send(block.map(Bytes::from)).is_err() {
+                    break;
+                }
+                if failed {
+                    warn!("Cached response {} doesn't decrypt, dropped", key);
+                    let _ = fs::remove_file(&path);
+                    break;
+                }
+            }
+        });
+        Some((Body::new(ChannelBody { rx }), plain_len))
+    }
+
+    /// Pass `body` through unchanged, caching a copy under `key` once it ends
+    pub fn tee<B>(&self, key: String, body: B) -> TeeBody<B> {
+        if !self.enabled {
+            return TeeBody { inner: body, writer: None, bytes: 0 };
+        }
+        let (tx, rx) = mpsc::channel(SPOOL_QUEUE);
+        let cache = self.clone();
+        tokio::task::spawn_blocking(move || cache.spool(key, rx));
+        TeeBody { inner: body, writer: Some(tx), bytes: 0 }
+    }
+
+    /// Writer side of [`TeeBody`]: `None` marks a complete body
+    fn spool(&self, key: String, mut rx: mpsc::Receiver<Option<Bytes>>) {
+        // Two requests for the same key may spool at once; each gets its own
+        // file, and the last rename wins with a complete copy
+        let seq = SPOOL_SEQ.fetch_add(1, Ordering::Relaxed);
+        let part = self.files.join(format!("{}.{}-{}.part", key, std::process::id(), seq));
+        let mut buffer = Vec::new();
+        let mut file: Option<SealedWriter<io::BufWriter<File>>> = None;
+        let mut complete = false;
+        let mut size = 0;
+        while let Some(chunk) = rx.blocking_recv() {
+            let Some(chunk) = chunk else {
+                complete = true;
+                break;
+            };
+            size += chunk.len();
+            let written = match &mut file {
+                Some(file) => file.write_all(&chunk),
+                None if size <= SLED_MAX_BYTES => {
+                    buffer.extend_from_slice(&chunk);
+                    Ok(())
+                }
+                // Too big for sled: continue on disk
+                None => fs::create_dir_all(&self.files)
+                    .and_then(|_| File::create_new(&part))
+                    .map(|out| SealedWriter::new(io::BufWriter::new(out), self.cipher.clone(), &key))
+                    .and_then(|mut out| {
+                        out.write_all(&buffer)?;
+                        out.write_all(&chunk)?;
+                        buffer = Vec::new();
+                        file = Some(out);
+                        Ok(())
+                    }),
+            };
+            if let Err(e) = written {
+                warn!("Gateway cache spool failed: {}", e);
+                break;
+            }
+        }
+
+        match (complete, file) {
+            (false, file) => {
+                if file.is_some() {
+                    let _ = fs::remove_file(&part);
+                }
+            }
+            (true, None) => {
+                if let Some(cipher) = &self.cipher {
+                    buffer = cipher.seal(key.as_bytes(), &buffer);
+                }
+                let _ = self.db.insert(&key, buffer);
+                let _ = self.db.flush();
+                info!("💾 Cached {} bytes", size);
+            }
+            (true, Some(out)) => {
+                // The file is closed before the rename (Windows won't move an open file)
+                let stored = out.finish().map(drop).and_then(|_| fs::rename(&part, self.file(&key)));
+                match stored {
+                    Ok(()) => {
+                        info!("💾 Cached {:.1} MB on disk", size as f64 / 1_048_576.0);
+                        self.evict();
+                    }
+                    Err(e) => {
+                        warn!("Gateway cache spool failed: {}", e);
+                        let _ = fs::remove_file(&part);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Drop every cached response. Returns how many were removed.
+    pub fn clear(&self) -> anyhow::Result<usize> {
+        let mut removed = self.db.len();
+        self.db.clear()?;
+        self.db.flush()?;
+        if let Ok(entries) = fs::read_dir(&self.files) {
+            for entry in entries.filter_map(|e| e.ok()) {
+                if fs::remove_file(entry.path()).is_ok() {
+                    removed += 1;
+                }
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Drop the oldest file-backed entries over [`MAX_FILES_BYTES`]
+    fn evict(&self) {
+        let Ok(entries) = fs::read_dir(&self.files) else { return };
+        let mut files: Vec<(std::time::SystemTime, u64, PathBuf)> = entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().extension().map_or(true, |ext| ext != "part"))
+            .filter_map(|e| {
+                let meta = e.metadata().ok()?;
+                Some((meta.modified().ok()?, meta.len(), e.path()))
+            })
+            .collect();
+        files.sort();
+        let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+        for (_, len, path) in files {
+            if total <= MAX_FILES_BYTES {
+                break;
+            }
+            if fs::remove_file(&path).is_ok() {
+                total -= len;
+            }
+        }
+    }
+}
+
+/// Cached file decrypted block by block on a blocking thread
+struct ChannelBody {
+    rx: mpsc::Receiver<io::Result<Bytes>>,
+}
+
+impl HttpBody for ChannelBody {
+    type Data = Bytes;
+    type Error = io::Error;
+
+    fn poll_frame(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, io::Error>>> {
+        self.rx.poll_recv(cx).map(|block| block.map(|block| block.map(Frame::data)))
+    }
+}
+
+/// Upstream body that hands each data frame to the cache writer on its way
+/// to the client
+pub struct TeeBody<B> {
+    inner: B,
+    /// `None` once caching is done or given up
+    writer: Option<mpsc::Sender<Option<Bytes>>>,
+    bytes: usize,
+}
+
+impl<B> TeeBody<B> {
+    /// Queue a frame (`None`: the body ended) without ever waiting
+    fn spool(&mut self, chunk: Option<Bytes>) {
+        let Some(writer) = &self.writer else { return };
+        let finished = chunk.is_none();
+        if let Some(chunk) = &chunk {
+            self.bytes += chunk.len();
+            if self.bytes > MAX_CACHED_BYTES {
+                self.writer = None;
+                return;
+            }
+        }
+        // A full queue closes the channel, which the writer treats as an abort
+        if writer.try_send(chunk).is_err() || finished {
+            self.writer = None;
+        }
+    }
+}
+
+impl<B> HttpBody for TeeBody<B>
+where
+    B: HttpBody<Data = Bytes> + Unpin,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, B::Error>>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_frame(cx);
+        match &poll {
+            Poll::Ready(Some(Ok(frame))) => {
+                if let Some(data) = frame.data_ref() {
+                    this.spool(Some(data.clone()));
+                }
+                // The server may stop polling once the body reports its end
+                if this.inner.is_end_stream() {
+                    this.spool(None);
+                }
+            }
+            Poll::Ready(None) => this.spool(None),
+            Poll::Ready(Some(Err(_))) => this.writer = None,
+            Poll::Pending => {}
+        }
+        poll
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_tee_caches_small_in_sled_and_big_on_disk() {
+        let dir = tempdir().unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(async {
+            let db = sled::open(dir.path().join("sled")).unwrap();
+            let cache = ResponseCache::new(dir.path(), db.clone());
+            let cached = |cache: &ResponseCache, key: &'static str| {
+                let cache = cache.clone();
+                async move {
+                    for _ in 0..100 {
+                        if let Some((body, len)) = cache.get(key).await {
+                            return Some((axum::body::to_bytes(body, usize::MAX).await.unwrap(), len));
+                        }
+                        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                    }
+                    None
+                }
+            };
+
+            let small = Body::new(cache.tee("small".into(), Body::from("{\"data\": 1}")));
+            assert_eq!(axum::body::to_bytes(small, usize::MAX).await.unwrap(), "{\"data\": 1}");
+            let (body, len) = cached(&cache, "small").await.unwrap();
+            assert_eq!((body.as_ref(), len), (&b"{\"data\": 1}"[..], 11));
+            assert!(db.get("small").unwrap().is_some());
+
+            let big = vec![b'x'; SLED_MAX_BYTES + 10];
+            let body = Body::new(cache.tee("big".into(), Body::from(big.clone())));
+            assert_eq!(axum::body::to_bytes(body, usize::MAX).await.unwrap().len(), big.len());
+            let (body, len) = cached(&cache, "big").await.unwrap();
+            assert_eq!((body.len(), len), (big.len(), big.len() as u64));
+            assert!(db.get("big").unwrap().is_none(), "big entries stay out of sled");
+            assert!(dir.path().join("cache").join(FILES_DIR).join("big").exists());
+
+            assert_eq!(cache.clear().unwrap(), 2);
+            assert!(cache.get("small").await.is_none());
+            assert!(cache.get("big").await.is_none());
+
+            let cipher = CacheCipher::from_passphrase("secret", &[1u8; 16], cache_crypto::GATEWAY).unwrap();
+            let sealed = ResponseCache::new(dir.path(), db.clone()).encrypted(Some(cipher));
+            let body = Body::new(sealed.tee("small".into(), Body::from("{\"data\": 1}")));
+            axum::body::to_bytes(body, usize::MAX).await.unwrap();
+            let body = Body::new(sealed.tee("big".into(), Body::from(big.clone())));
+            axum::body::to_bytes(body, usize::MAX).await.unwrap();
+            let (body, len) = cached(&sealed, "small").await.unwrap();
+            assert_eq!((body.as_ref(), len), (&b"{\"data\": 1}"[..], 11));
+            assert!(!db.get("small").unwrap().unwrap().windows(4).any(|w| w == b"data"));
+            let (body, len) = cached(&sealed, "big").await.unwrap();
+            assert_eq!((body, len), (Bytes::from(big.clone()), big.len() as u64));
+            // Read without the key, it isn't the response
+            assert!(ResponseCache::new(dir.path(), db.clone()).get("small").await.unwrap().1 > 11);
+        });
+    }
+}
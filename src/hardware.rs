@@ -0,0 +1,339 @@
+//! Startup tuning for the machine the kit runs on
+//!
+//! The same stick goes into 4 GB office laptops and 32-core workstations, and
+//! one set of defaults is wrong on both. At startup the launcher counts CPU
+//! cores, reads the installed RAM and checks whether the kit sits on a
+//! removable drive, picks a profile (`low`, `standard`, `high`) and derives
+//! thread pools, cache sizes and compression levels from it. `hardware.profile`
+//! in config.json pins a profile; settings written explicitly in config.json
+//! (`gateway.hot_assets`, `load_memory_mb`) always win over the profile.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::hot_assets::HotAssetsConfig;
+
+/// Below this much RAM (or with 2 cores or fewer) the machine is `low`
+const LOW_RAM_MB: u64 = 6 * 1024;
+
+/// From this much RAM and `HIGH_CORES` cores the machine is `high`
+const HIGH_RAM_MB: u64 = 16 * 1024;
+const HIGH_CORES: usize = 8;
+
+/// Bytes written by [`write_speed_mb_s`]
+const SPEED_PROBE_BYTES: usize = 32 * 1024 * 1024;
+
+/// Tuning chosen at startup, see [`init`]
+static TUNING: OnceLock<Tuning> = OnceLock::new();
+
+/// Tuning profile (`hardware.profile` in config.json)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    /// Picked from the detected hardware
+    #[default]
+    Auto,
+    Low,
+    Standard,
+    High,
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Profile::Auto => "auto",
+            Profile::Low => "low",
+            Profile::Standard => "standard",
+            Profile::High => "high",
+        })
+    }
+}
+
+/// Hardware settings (`hardware` in config.json)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HardwareConfig {
+    pub profile: Profile,
+}
+
+/// Where the kit is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiskKind {
+    /// USB stick or card reader: slow random reads
+    Removable,
+    Fixed,
+    Unknown,
+}
+
+/// What was detected at startup
+#[derive(Debug, Clone, Serialize)]
+pub struct Hardware {
+    pub cores: usize,
+    /// Installed RAM, if it could be read
+    pub ram_mb: Option<u64>,
+    pub disk: DiskKind,
+}
+
+impl Hardware {
+    /// Inspect this machine and the drive holding `root`
+    pub fn detect(root: &Path) -> Self {
+        Self {
+            cores: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(2),
+            ram_mb: total_memory_mb(),
+            disk: disk_kind(root),
+        }
+    }
+
+    /// Profile that fits this machine
+    pub fn profile(&self) -> Profile {
+        let ram_mb = self.ram_mb.unwrap_or(LOW_RAM_MB);
+        if ram_mb < LOW_RAM_MB || self.cores <= 2 {
+            Profile::Low
+        } else if ram_mb >= HIGH_RAM_MB && self.cores >= HIGH_CORES {
+            Profile::High
+        } else {
+            Profile::Standard
+        }
+    }
+}
+
+/// Settings derived from the profile
+#[derive(Debug, Clone, Serialize)]
+pub struct Tuning {
+    pub profile: Profile,
+    pub hardware: Hardware,
+    /// Polars thread pool of the launcher (data loads)
+    pub polars_threads: usize,
+    /// numpy/pandas threads inside Superset
+    pub superset_threads: usize,
+    /// Whether Flask serves requests in threads (off on single-core machines)
+    pub flask_threaded: bool,
+    /// Requests the gateway lets through to Superset at once
+    pub upstream_requests: usize,
+    pub hot_assets: HotAssetsConfig,
+    pub load_memory_mb: u64,
+    /// zstd level for tar.zst release archives
+    pub zstd_level: i32,
+}
+
+impl Tuning {
+    /// Tuning for `hardware`, with `profile` forcing a profile unless `Auto`
+    pub fn new(hardware: Hardware, profile: Profile) -> Self {
+        let profile = if profile == Profile::Auto { hardware.profile() } else { profile };
+        let cores = hardware.cores.max(1);
+        let (hot_assets, load_memory_mb, zstd_level) = match profile {
+            Profile::Low => (HotAssetsConfig { memory_mb: 8, max_entries: 32 }, 512, 3),
+            Profile::High => (HotAssetsConfig { memory_mb: 128, max_entries: 256 }, 4096, 9),
+            Profile::Standard | Profile::Auto => (HotAssetsConfig::default(), 1024, 3),
+        };
+        // Every asset read off a stick is slow; keep more of them in memory
+        let hot_assets = if hardware.disk == DiskKind::Removable && profile != Profile::Low {
+            HotAssetsConfig { memory_mb: hot_assets.memory_mb * 2, max_entries: hot_assets.max_entries * 2 }
+        } else {
+            hot_assets
+        };
+        Self {
+            profile,
+            polars_threads: if profile == Profile::Low { cores.min(2) } else { cores },
+            superset_threads: match profile {
+                Profile::Low => 1,
+                Profile::High => (cores / 2).min(8),
+                _ => (cores / 2).clamp(1, 4),
+            },
+            flask_threaded: cores > 1,
+            upstream_requests: match profile {
+                _ if cores == 1 => 1,
+                Profile::Low => 2,
+                Profile::High => (cores * 2).min(16),
+                _ => cores.clamp(2, 8),
+            },
+            hot_assets,
+            load_memory_mb,
+            zstd_level,
+            hardware,
+        }
+    }
+
+    /// Hot asset cache: `configured` if set in config.json, else the profile's
+    pub fn hot_assets(&self, configured: Option<&HotAssetsConfig>) -> HotAssetsConfig {
+        configured.unwrap_or(&self.hot_assets).clone()
+    }
+
+    /// Data load budget: `configured` if set in config.json, else the profile's
+    pub fn load_memory_mb(&self, configured: Option<u64>) -> u64 {
+        configured.unwrap_or(self.load_memory_mb)
+    }
+
+    /// Upstream request limit: `configured` if set in config.json, else the profile's
+    pub fn upstream_requests(&self, configured: usize) -> usize {
+        if configured == 0 {
+            self.upstream_requests
+        } else {
+            configured
+        }
+    }
+
+    /// Thread limits for Superset's numeric libraries
+    pub fn superset_env(&self) -> Vec<(String, String)> {
+        let threads = self.superset_threads.to_string();
+        ["OMP_NUM_THREADS", "OPENBLAS_NUM_THREADS", "MKL_NUM_THREADS", "NUMEXPR_MAX_THREADS"]
+            .into_iter()
+            .map(|key| (key.to_string(), threads.clone()))
+            .collect()
+    }
+}
+
+impl fmt::Display for Tuning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ram = self.hardware.ram_mb.map_or("?".to_string(), |mb| format!("{:.1}", mb as f64 / 1024.0));
+        let disk = match self.hardware.disk {
+            DiskKind::Removable => "съёмный",
+            DiskKind::Fixed => "встроенный",
+            DiskKind::Unknown => "неизвестно",
+        };
+        writeln!(f, "🖥️ Ядер: {}, памяти: {} ГБ, диск: {}", self.hardware.cores, ram, disk)?;
+        writeln!(f, "⚙️ Профиль: {}", self.profile)?;
+        writeln!(f, "   Потоков загрузки данных (polars): {}", self.polars_threads)?;
+        writeln!(f, "   Потоков numpy/pandas в Superset: {}", self.superset_threads)?;
+        writeln!(f, "   Многопоточный Flask: {}", if self.flask_threaded { "да" } else { "нет" })?;
+        writeln!(f, "   Одновременных запросов к Superset: {}", self.upstream_requests)?;
+        writeln!(
+            f,
+            "   Кэш статики шлюза: {} МБ, {} файлов",
+            self.hot_assets.memory_mb, self.hot_assets.max_entries
+        )?;
+        writeln!(f, "   Память на загрузку данных: {} МБ", self.load_memory_mb)?;
+        write!(f, "   Уровень сжатия zstd: {}", self.zstd_level)
+    }
+}
+
+/// Detect the hardware and fix the tuning for this run. Call once, before
+/// anything reads [`current`].
+pub fn init(root: &Path, config: &HardwareConfig) -> &'static Tuning {
+    TUNING.get_or_init(|| Tuning::new(Hardware::detect(root), config.profile))
+}
+
+/// Tuning of this run (the `standard` profile if [`init`] was not called)
+pub fn current() -> &'static Tuning {
+    TUNING.get_or_init(|| {
+        let hardware = Hardware { cores: 4, ram_mb: None, disk: DiskKind::Unknown };
+        Tuning::new(hardware, Profile::Standard)
+    })
+}
+
+/// Sequential write speed of the drive holding `dir`, in MB/s. Writes and
+/// removes a 32 MB file, so call it only when the answer matters.
+pub fn write_speed_mb_s(dir: &Path) -> Option<f64> {
+    use std::io::Write;
+
+    let path = dir.join(".write-speed-probe.tmp");
+    let chunk = vec![0x5a_u8; 1024 * 1024];
+    let start = std::time::Instant::now();
+    let written = (|| -> std::io::Result<()> {
+        let mut file = std::fs::File::create(&path)?;
+        for _ in 0..SPEED_PROBE_BYTES / chunk.len() {
+            file.write_all(&chunk)?;
+        }
+        // Past the OS cache, or a stick looks as fast as RAM
+        file.sync_all()
+    })();
+    let elapsed = start.elapsed().as_secs_f64();
+    let _ = std::fs::remove_file(&path);
+    written.ok()?;
+    Some(SPEED_PROBE_BYTES as f64 / 1_048_576.0 / elapsed.max(0.001))
+}
+
+#[cfg(windows)]
+fn total_memory_mb() -> Option<u64> {
+    use winapi::um::sysinfoapi::{GlobalMemoryStatusEx, MEMORYSTATUSEX};
+
+    // SAFETY: MEMORYSTATUSEX is plain data; dwLength is set as the API requires
+    let mut status: MEMORYSTATUSEX = unsafe { std::mem::zeroed() };
+    status.dwLength = std::mem::size_of::<MEMORYSTATUSEX>() as u32;
+    let ok = unsafe { GlobalMemoryStatusEx(&mut status) };
+    (ok != 0).then_some(status.ullTotalPhys / 1_048_576)
+}
+
+#[cfg(not(windows))]
+fn total_memory_mb() -> Option<u64> {
+    if let Ok(meminfo) = std::fs::read_to_string("/proc/meminfo") {
+        let line = meminfo.lines().find(|l| l.starts_with("MemTotal:"))?;
+        let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+        return Some(kb / 1024);
+    }
+    let out = std::process::Command::new("sysctl").args(["-n", "hw.memsize"]).output().ok()?;
+    let bytes: u64 = String::from_utf8_lossy(&out.stdout).trim().parse().ok()?;
+    Some(bytes / 1_048_576)
+}
+
+#[cfg(windows)]
+fn disk_kind(root: &Path) -> DiskKind {
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Component;
+    use winapi::um::fileapi::GetDriveTypeW;
+    use winapi::um::winbase::{DRIVE_FIXED, DRIVE_REMOVABLE};
+
+    let Some(Component::Prefix(prefix)) = root.components().next() else {
+        return DiskKind::Unknown;
+    };
+    let drive = Path::new(prefix.as_os_str()).join("\\");
+    let wide: Vec<u16> = drive.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    // SAFETY: `wide` is a NUL-terminated root path
+    match unsafe { GetDriveTypeW(wide.as_ptr()) } {
+        DRIVE_REMOVABLE => DiskKind::Removable,
+        DRIVE_FIXED => DiskKind::Fixed,
+        _ => DiskKind::Unknown,
+    }
+}
+
+#[cfg(not(windows))]
+fn disk_kind(_root: &Path) -> DiskKind {
+    DiskKind::Unknown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn machine(cores: usize, ram_gb: u64, disk: DiskKind) -> Hardware {
+        Hardware { cores, ram_mb: Some(ram_gb * 1024), disk }
+    }
+
+    #[test]
+    fn test_profile_from_hardware() {
+        assert_eq!(machine(4, 4, DiskKind::Fixed).profile(), Profile::Low);
+        assert_eq!(machine(2, 16, DiskKind::Fixed).profile(), Profile::Low);
+        assert_eq!(machine(4, 8, DiskKind::Fixed).profile(), Profile::Standard);
+        assert_eq!(machine(16, 32, DiskKind::Fixed).profile(), Profile::High);
+        assert_eq!(Hardware { cores: 8, ram_mb: None, disk: DiskKind::Unknown }.profile(), Profile::Standard);
+
+        let low = Tuning::new(machine(2, 4, DiskKind::Removable), Profile::Auto);
+        assert_eq!(low.profile, Profile::Low);
+        assert_eq!(low.hot_assets, HotAssetsConfig { memory_mb: 8, max_entries: 32 });
+        assert_eq!(low.superset_threads, 1);
+        assert_eq!(low.upstream_requests, 2);
+        assert_eq!(low.upstream_requests(6), 6);
+        assert!(low.superset_env().contains(&("OMP_NUM_THREADS".to_string(), "1".to_string())));
+
+        // A pinned profile wins; a stick doubles the asset cache
+        let pinned = Tuning::new(machine(2, 4, DiskKind::Removable), Profile::Standard);
+        assert_eq!(pinned.hot_assets.memory_mb, 64);
+        assert!(!Tuning::new(machine(1, 64, DiskKind::Fixed), Profile::High).flask_threaded);
+    }
+
+    #[test]
+    fn test_explicit_settings_win() {
+        let high = Tuning::new(machine(16, 32, DiskKind::Fixed), Profile::Auto);
+        assert_eq!(high.hot_assets(None).memory_mb, 128);
+        let configured = HotAssetsConfig { memory_mb: 16, max_entries: 10 };
+        assert_eq!(high.hot_assets(Some(&configured)), configured);
+        // A setting equal to the default is still the user's choice
+        assert_eq!(high.hot_assets(Some(&HotAssetsConfig::default())), HotAssetsConfig::default());
+        assert_eq!(high.load_memory_mb(None), 4096);
+        assert_eq!(high.load_memory_mb(Some(crate::data_loader::DEFAULT_MEMORY_BUDGET_MB)), 1024);
+        assert_eq!(high.load_memory_mb(Some(300)), 300);
+    }
+}
@@ -0,0 +1,313 @@
+//! Headless launcher (`superset-launcher launcher --headless`)
+//!
+//! For servers and shared PCs where nobody watches a console or a tray icon:
+//! the command starts a copy of itself detached from the console and exits.
+//! The copy runs the same launcher as `launcher` (API, control pipe/socket,
+//! schedulers) without opening a browser, writes its output to
+//! `logs/headless.log` and keeps `headless.json` in the kit root — next to
+//! `control.endpoint` and the pid files — up to date with its pid, address
+//! and the status the control channel reports. Everything is driven through
+//! `ctl` or the launcher API.
+//!
+//! A user logging off doesn't stop it. Windows still ends every process of the
+//! session on logoff, so to outlive it the launcher must be started outside
+//! the session: `headless install` registers a Task Scheduler task that starts
+//! it at boot (needs an elevated prompt). The task runs as the installing user,
+//! or a dedicated account given with `--user`, with limited rights and no
+//! stored password — never as SYSTEM: the exe lives on a stick anyone can
+//! write to, and whoever replaces it would get the task's account.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tracing::{info, warn};
+
+pub const STATUS_FILE: &str = "headless.json";
+
+/// Output of the detached launcher, under `logs/`
+const LOG_FILE: &str = "headless.log";
+
+/// Set for the detached copy so it doesn't detach again
+const CHILD_ENV: &str = "SUPERSET_LAUNCHER_HEADLESS";
+
+/// How often `headless.json` is refreshed
+const STATUS_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Contents of `headless.json`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HeadlessStatus {
+    pub pid: u32,
+    /// `running` or `stopped`
+    pub state: String,
+    pub started_at: String,
+    pub updated_at: String,
+    pub launcher_url: String,
+    pub control_endpoint: String,
+    /// Last answer to `status` on the control channel
+    #[serde(default)]
+    pub services: Value,
+}
+
+impl HeadlessStatus {
+    pub fn load(root: &Path) -> Option<Self> {
+        serde_json::from_str(&std::fs::read_to_string(root.join(STATUS_FILE)).ok()?).ok()
+    }
+
+    fn save(&self, root: &Path) -> Result<()> {
+        // Written whole and renamed, so a reader never sees half a file
+        let tmp = root.join(format!("{}.tmp", STATUS_FILE));
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&tmp, root.join(STATUS_FILE))?;
+        Ok(())
+    }
+}
+
+/// True in the detached copy
+pub fn is_detached() -> bool {
+    std::env::var_os(CHILD_ENV).is_some()
+}
+
+/// Start this executable again with the same arguments, detached from the
+/// console; returns the pid of the copy
+pub async fn detach(root: &Path) -> Result<u32> {
+    // Its control channel would be taken anyway
+    if crate::control::call(root, "ping").await.is_ok() {
+        bail!("A launcher is already running for this kit; stop it with `ctl shutdown` first");
+    }
+    let logs = root.join("logs");
+    std::fs::create_dir_all(&logs)?;
+    let log_path = logs.join(LOG_FILE);
+    let log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .with_context(|| format!("Cannot open {}", log_path.display()))?;
+
+    let mut command = Command::new(std::env::current_exe()?);
+    command
+        .args(std::env::args_os().skip(1))
+        .current_dir(root)
+        .env(CHILD_ENV, "1")
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const DETACHED_PROCESS: u32 = 0x0000_0008;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+        // No console to close, and Ctrl+C in the parent's window doesn't reach it
+        command.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // Out of the terminal's foreground group: Ctrl+C and hangup stay with the shell
+        command.process_group(0);
+    }
+    let child = command.spawn().context("Failed to start the headless launcher")?;
+    Ok(child.id())
+}
+
+/// Keep running through logoff and terminal hangup; a shutdown of the machine
+/// stops the launcher the normal way, through `shutdown` on the control channel
+pub fn survive_session_end(root: PathBuf) {
+    #[cfg(windows)]
+    {
+        // Having a handler at all keeps the default one from exiting the process
+        match tokio::signal::windows::ctrl_logoff() {
+            Ok(mut logoff) => {
+                tokio::spawn(async move {
+                    while logoff.recv().await.is_some() {
+                        info!("👤 User logged off — headless launcher keeps running");
+                    }
+                });
+            }
+            Err(e) => warn!("Cannot watch for logoff: {}", e),
+        }
+        match tokio::signal::windows::ctrl_shutdown() {
+            Ok(mut system_shutdown) => {
+                tokio::spawn(async move {
+                    if system_shutdown.recv().await.is_some() {
+                        info!("Windows is shutting down, stopping the headless launcher");
+                        let _ = crate::control::call(&root, "shutdown").await;
+                    }
+                });
+            }
+            Err(e) => warn!("Cannot watch for system shutdown: {}", e),
+        }
+    }
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::hangup()) {
+            Ok(mut hangup) => {
+                tokio::spawn(async move {
+                    while hangup.recv().await.is_some() {
+                        info!("Terminal closed — headless launcher keeps running");
+                    }
+                });
+            }
+            Err(e) => warn!("Cannot watch for hangup: {}", e),
+        }
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::spawn(async move {
+                    if terminate.recv().await.is_some() {
+                        info!("SIGTERM received, stopping the headless launcher");
+                        let _ = crate::control::call(&root, "shutdown").await;
+                    }
+                });
+            }
+            Err(e) => warn!("Cannot watch for SIGTERM: {}", e),
+        }
+    }
+}
+
+/// Keep `headless.json` current while the launcher runs; the status comes
+/// from the launcher's own control channel, as any other client would see it
+pub fn spawn_status_writer(root: PathBuf, port: u16) {
+    let mut status = HeadlessStatus {
+        pid: std::process::id(),
+        state: "running".to_string(),
+        started_at: chrono::Local::now().to_rfc3339(),
+        updated_at: String::new(),
+        launcher_url: format!("http://127.0.0.1:{}", port),
+        control_endpoint: crate::control::endpoint(&root),
+        services: Value::Null,
+    };
+    tokio::spawn(async move {
+        // The first write comes before the control channel is up
+        let mut first = true;
+        let mut interval = tokio::time::interval(STATUS_INTERVAL);
+        loop {
+            interval.tick().await;
+            if !std::mem::take(&mut first) {
+                match crate::control::call(&root, "status").await {
+                    Ok(services) => status.services = services,
+                    Err(e) => warn!("Headless status: {:#}", e),
+                }
+            }
+            status.updated_at = chrono::Local::now().to_rfc3339();
+            if let Err(e) = status.save(&root) {
+                warn!("Failed to write {}: {:#}", STATUS_FILE, e);
+            }
+        }
+    });
+}
+
+/// Mark the launcher stopped in `headless.json`
+pub fn write_stopped(root: &Path) {
+    let Some(mut status) = HeadlessStatus::load(root) else {
+        return;
+    };
+    status.state = "stopped".to_string();
+    status.updated_at = chrono::Local::now().to_rfc3339();
+    status.services = Value::Null;
+    if let Err(e) = status.save(root) {
+        warn!("Failed to write {}: {:#}", STATUS_FILE, e);
+    }
+}
+
+/// Task Scheduler name for the kit at `root` (one task per unpacked kit)
+fn task_name(root: &Path) -> String {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let path: String = root
+        .to_string_lossy()
+        .trim_start_matches(r"\\?\")
+        .chars()
+        .map(|c| if r#"\/:*?"<>|"#.contains(c) { '-' } else { c })
+        .collect();
+    format!("Superset Portable ({})", path.trim_matches('-'))
+}
+
+/// Register a task starting the headless launcher at boot, outside any user
+/// session, as `user` (`DOMAIN\name`; the installing user by default)
+pub fn install_task(root: &Path, user: Option<&str>) -> Result<String> {
+    let name = task_name(root);
+    if !cfg!(windows) {
+        bail!(
+            "Scheduled tasks are Windows-only; use a systemd unit or launchd agent running `{} launcher --headless` instead",
+            std::env::current_exe()?.display()
+        );
+    }
+    let account = match user {
+        Some(user) => user.to_string(),
+        None => current_user().context("Cannot tell the current user; pass --user")?,
+    };
+    if account.eq_ignore_ascii_case("SYSTEM") || account.to_uppercase().starts_with("NT AUTHORITY\\") {
+        bail!("The task must not run as {}: anyone who can write to the kit would get its rights", account);
+    }
+    let action = format!("\"{}\" launcher --headless", std::env::current_exe()?.display());
+    // `/NP`: runs whether or not the user is logged on, without storing a password
+    schtasks(&[
+        "/Create", "/F", "/TN", &name, "/TR", &action, "/SC", "ONSTART", "/RU", &account, "/NP", "/RL", "LIMITED",
+    ])?;
+    info!("Task runs as {} with limited rights", account);
+    Ok(name)
+}
+
+/// `DOMAIN\name` of the user running the launcher
+fn current_user() -> Option<String> {
+    let name = std::env::var("USERNAME").ok().filter(|n| !n.is_empty())?;
+    Some(match std::env::var("USERDOMAIN") {
+        Ok(domain) if !domain.is_empty() => format!("{}\\{}", domain, name),
+        _ => name,
+    })
+}
+
+pub fn uninstall_task(root: &Path) -> Result<String> {
+    let name = task_name(root);
+    if !cfg!(windows) {
+        bail!("Scheduled tasks are Windows-only");
+    }
+    schtasks(&["/Delete", "/F", "/TN", &name])?;
+    Ok(name)
+}
+
+fn schtasks(args: &[&str]) -> Result<()> {
+    let output = Command::new("schtasks").args(args).output().context("Failed to run schtasks")?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        if message.to_lowercase().contains("access") || message.contains("Отказано") {
+            bail!("schtasks: {} (run the command from an elevated prompt)", message);
+        }
+        bail!("schtasks: {}", message);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_status_file_roundtrip() {
+        let dir = tempdir().unwrap();
+        assert!(HeadlessStatus::load(dir.path()).is_none());
+        write_stopped(dir.path());
+        assert!(!dir.path().join(STATUS_FILE).exists());
+
+        let status = HeadlessStatus {
+            pid: 42,
+            state: "running".to_string(),
+            started_at: "2026-01-01T00:00:00+03:00".to_string(),
+            updated_at: String::new(),
+            launcher_url: "http://127.0.0.1:3000".to_string(),
+            control_endpoint: crate::control::endpoint(dir.path()),
+            services: serde_json::json!({"superset": "running"}),
+        };
+        status.save(dir.path()).unwrap();
+        assert_eq!(HeadlessStatus::load(dir.path()), Some(status));
+
+        write_stopped(dir.path());
+        let stopped = HeadlessStatus::load(dir.path()).unwrap();
+        assert_eq!(stopped.state, "stopped");
+        assert_eq!(stopped.services, Value::Null);
+        assert!(!task_name(dir.path()).contains(['\\', '/', ':']));
+    }
+}
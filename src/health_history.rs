@@ -0,0 +1,161 @@
+//! History of the launcher's service health checks
+//!
+//! The SLO monitor ([`crate::slo::spawn_monitor`]) checks Superset, and
+//! LightDocs where the launcher runs it, every minute and keeps each answer (up or not, and how long it took) in a sled
+//! database inside the kit. The UI draws the last 24 hours per service as a
+//! sparkline of 15-minute buckets, so a slow patch every night at 02:00 (an
+//! antivirus scan, a backup job) shows up as a pattern instead of being
+//! missed between two glances at the status badge. Samples older than
+//! [`RETENTION_HOURS`] are dropped as new ones come in.
+//!
+//! Like the usage counters, the database is used through the process's one
+//! handle ([`crate::sled_journal::shared`]).
+
+use anyhow::Result;
+use chrono::{DateTime, Local, TimeZone};
+use serde::Serialize;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::sled_journal;
+
+const HISTORY_DB: &str = ".health_history";
+
+/// Samples older than this are removed
+pub const RETENTION_HOURS: i64 = 48;
+
+/// Width of one sparkline bar
+pub const BUCKET_MINUTES: i64 = 15;
+
+/// Marks a failed check in the stored latency
+const DOWN: u32 = u32::MAX;
+
+/// Services the launcher watches
+pub const SERVICES: [&str; 2] = ["superset", "lightdocs"];
+
+/// One bucket of the sparkline
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Bucket {
+    /// Start of the bucket (RFC 3339, local time)
+    pub start: String,
+    /// Checks made in the bucket (0: the launcher wasn't running)
+    pub checks: u32,
+    /// Of those, answered
+    pub up: u32,
+    /// Mean answer time of the successful checks
+    pub avg_ms: Option<u32>,
+    pub max_ms: Option<u32>,
+}
+
+fn key(service: &str, at: i64) -> Vec<u8> {
+    let mut key = format!("{}:", service).into_bytes();
+    key.extend_from_slice(&at.to_be_bytes());
+    key
+}
+
+/// Store one check of `service` made at `at` (unix seconds); `latency_ms` is
+/// `None` when the service didn't answer
+pub fn record(root: &Path, service: &str, at: i64, latency_ms: Option<u32>) -> Result<()> {
+    let result = record_in(&sled_journal::shared(&root.join(HISTORY_DB))?, service, at, latency_ms);
+    if result.is_err() {
+        sled_journal::release(&root.join(HISTORY_DB));
+    }
+    result
+}
+
+fn record_in(db: &sled::Db, service: &str, at: i64, latency_ms: Option<u32>) -> Result<()> {
+    let value = latency_ms.map_or(DOWN, |ms| ms.min(DOWN - 1));
+    db.insert(key(service, at), &value.to_be_bytes())?;
+
+    let cutoff = at - RETENTION_HOURS * 3600;
+    for item in db.range(key(service, 0)..key(service, cutoff)) {
+        let (old, _) = item?;
+        db.remove(old)?;
+    }
+    db.flush()?;
+    Ok(())
+}
+
+/// Buckets of `service` for the `hours` before `now`, oldest first
+pub fn series(root: &Path, service: &str, now: DateTime<Local>, hours: i64) -> Result<Vec<Bucket>> {
+    let bucket_secs = BUCKET_MINUTES * 60;
+    let count = (hours * 3600 / bucket_secs).max(1);
+    // Align to bucket boundaries so bars don't shift between refreshes
+    let end = (now.timestamp() / bucket_secs + 1) * bucket_secs;
+    let start = end - count * bucket_secs;
+
+    let mut buckets: Vec<(Bucket, u64)> = (0..count)
+        .map(|i| {
+            let at = Local.timestamp_opt(start + i * bucket_secs, 0).single().unwrap_or(now);
+            (Bucket { start: at.to_rfc3339(), ..Bucket::default() }, 0)
+        })
+        .collect();
+
+    let db = sled_journal::shared(&root.join(HISTORY_DB))?;
+    for item in db.range(key(service, start)..key(service, end)) {
+        let (k, v) = item?;
+        let (Some(at), Ok(latency)) = (
+            k.get(k.len().saturating_sub(8)..).and_then(|b| <[u8; 8]>::try_from(b).ok()).map(i64::from_be_bytes),
+            <[u8; 4]>::try_from(v.as_ref()).map(u32::from_be_bytes),
+        ) else {
+            continue;
+        };
+        let (bucket, total_ms) = &mut buckets[((at - start) / bucket_secs) as usize];
+        bucket.checks += 1;
+        if latency != DOWN {
+            bucket.up += 1;
+            *total_ms += latency as u64;
+            bucket.max_ms = Some(bucket.max_ms.unwrap_or(0).max(latency));
+        }
+    }
+    Ok(buckets
+        .into_iter()
+        .map(|(mut bucket, total_ms)| {
+            if bucket.up > 0 {
+                bucket.avg_ms = Some((total_ms / bucket.up as u64) as u32);
+            }
+            bucket
+        })
+        .collect())
+}
+
+/// Time a GET of `url`; `None` when it fails or answers with an error
+pub async fn timed_get(client: &reqwest::Client, url: &str) -> Option<u32> {
+    let started = Instant::now();
+    let response = client.get(url).send().await.ok()?;
+    response.status().is_success().then(|| started.elapsed().as_millis().min(u32::MAX as u128) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_buckets_and_retention() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let now = Local.with_ymd_and_hms(2024, 3, 1, 12, 7, 0).unwrap();
+        let t = now.timestamp();
+
+        record(root, "superset", t - 3 * 86400, Some(50)).unwrap();
+        record(root, "superset", t - 60, Some(100)).unwrap();
+        record(root, "superset", t - 30, Some(300)).unwrap();
+        record(root, "superset", t, None).unwrap();
+        record(root, "lightdocs", t, Some(5)).unwrap();
+
+        let series = series(root, "superset", now, 24).unwrap();
+        assert_eq!(series.len(), 96);
+        let last = series.last().unwrap();
+        assert_eq!((last.checks, last.up), (3, 2));
+        assert_eq!(last.avg_ms, Some(200));
+        assert_eq!(last.max_ms, Some(300));
+        assert!(last.start.starts_with("2024-03-01T12:00:00"));
+        assert_eq!(series.iter().map(|b| b.checks).sum::<u32>(), 3);
+
+        // The three-day-old sample went when the newer ones were recorded
+        let db = sled_journal::shared(&root.join(HISTORY_DB)).unwrap();
+        assert!(db.get(key("superset", t - 3 * 86400)).unwrap().is_none());
+        assert_eq!(db.len(), 4);
+    }
+}
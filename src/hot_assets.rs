@@ -0,0 +1,365 @@
+//! In-memory cache of the gateway's most requested static assets
+//!
+//! Every dashboard load pulls the same few megabytes of Superset JS/CSS from
+//! `/static/assets`, and on a USB stick each of those reads is slow. Assets
+//! requested more than once are kept in RAM, gzip-compressed once up front,
+//! and served without touching the disk. Memory is capped (`gateway.hot_assets`
+//! in config.json) and the least recently used asset is evicted first, so the
+//! budget can be shrunk on 4 GB machines; `/metrics` shows what is held.
+//! Hot assets carry an `ETag`, so a browser revalidating one gets a bodiless
+//! 304. Request counts of paths that are not cached age out, so a crawl of
+//! thousands of distinct URLs doesn't grow the table forever.
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use flate2::{write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::debug;
+
+/// Requests before an asset is worth a slot in memory
+const ADMIT_AFTER: u64 = 2;
+
+/// Assets larger than this are left to the disk
+const MAX_ASSET_BYTES: usize = 8 * 1024 * 1024;
+
+/// Paths whose request counts are tracked; past this the rarer half is dropped
+const MAX_TRACKED_PATHS: usize = 1024;
+
+/// Hot asset cache settings (`gateway.hot_assets` in config.json)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HotAssetsConfig {
+    /// Memory budget in MB (0 disables the cache)
+    pub memory_mb: usize,
+    /// Most assets kept at once
+    pub max_entries: usize,
+}
+
+impl Default for HotAssetsConfig {
+    fn default() -> Self {
+        Self {
+            memory_mb: 32,
+            max_entries: 64,
+        }
+    }
+}
+
+struct HotEntry {
+    body: Bytes,
+    /// Gzip variant, when it came out smaller
+    gzip: Option<Bytes>,
+    content_type: HeaderValue,
+    /// Strong validator: start of the body's SHA-256
+    etag: HeaderValue,
+    last_used: u64,
+}
+
+impl HotEntry {
+    fn memory(&self) -> usize {
+        self.body.len() + self.gzip.as_ref().map_or(0, |g| g.len())
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<String, HotEntry>,
+    /// Requests per asset path, cached or not
+    requests: HashMap<String, u64>,
+    used_bytes: usize,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+/// One asset in the `/metrics` hot list
+#[derive(Debug, Serialize)]
+pub struct HotAssetStat {
+    pub path: String,
+    pub requests: u64,
+    pub bytes: usize,
+    pub gzip_bytes: Option<usize>,
+}
+
+/// Snapshot for `/metrics`
+#[derive(Debug, Serialize)]
+pub struct HotAssetsStats {
+    pub entries: usize,
+    pub memory_bytes: usize,
+    pub budget_bytes: usize,
+    pub hits: u64,
+    pub misses: u64,
+    /// Cached assets, most requested first
+    pub hot: Vec<HotAssetStat>,
+}
+
+/// Static asset cache in front of a directory
+pub struct HotAssets {
+    dir: PathBuf,
+    budget_bytes: usize,
+    max_entries: usize,
+    inner: Mutex<Inner>,
+}
+
+impl HotAssets {
+    pub fn new(dir: &Path, config: &HotAssetsConfig) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            budget_bytes: config.memory_mb * 1024 * 1024,
+            max_entries: config.max_entries,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.budget_bytes > 0 && self.max_entries > 0
+    }
+
+    /// Serve `path` from memory if it's cached; 304 when `if_none_match`
+    /// names its ETag
+    fn get(&self, path: &str, gzip_ok: bool, if_none_match: Option<&HeaderValue>) -> Option<Response> {
+        let mut guard = self.inner.lock().ok()?;
+        let inner = &mut *guard;
+        inner.clock += 1;
+        let clock = inner.clock;
+        *inner.requests.entry(path.to_string()).or_default() += 1;
+        if inner.requests.len() > MAX_TRACKED_PATHS {
+            age_requests(inner);
+        }
+
+        let Some(entry) = inner.entries.get_mut(path) else {
+            inner.misses += 1;
+            return None;
+        };
+        entry.last_used = clock;
+        inner.hits += 1;
+        if if_none_match.is_some_and(|tags| etag_matches(tags, &entry.etag)) {
+            let mut response = Response::new(Body::empty());
+            *response.status_mut() = StatusCode::NOT_MODIFIED;
+            response.headers_mut().insert(header::ETAG, entry.etag.clone());
+            response.headers_mut().insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+            return Some(response);
+        }
+        let gzip = entry.gzip.clone().filter(|_| gzip_ok);
+        let mut response = Response::new(Body::from(gzip.clone().unwrap_or_else(|| entry.body.clone())));
+        let headers = response.headers_mut();
+        headers.insert(header::CONTENT_TYPE, entry.content_type.clone());
+        headers.insert(header::ETAG, entry.etag.clone());
+        headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+        headers.insert("x-gateway-cache", HeaderValue::from_static("HOT"));
+        if gzip.is_some() {
+            headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        }
+        Some(response)
+    }
+
+    /// Whether a just-served asset has been asked for often enough to load it
+    fn should_admit(&self, path: &str) -> bool {
+        let Ok(inner) = self.inner.lock() else {
+            return false;
+        };
+        !inner.entries.contains_key(path) && inner.requests.get(path).copied().unwrap_or(0) >= ADMIT_AFTER
+    }
+
+    /// Read and compress an asset, then make room for it (blocking)
+    fn load(&self, path: &str) {
+        // Only plain relative paths inside the assets folder
+        let rel = Path::new(path);
+        if rel.components().any(|c| !matches!(c, Component::Normal(_))) {
+            return;
+        }
+        let file = self.dir.join(rel);
+        let Ok(body) = std::fs::read(&file) else {
+            return;
+        };
+        if body.len() > MAX_ASSET_BYTES || body.len() > self.budget_bytes {
+            return;
+        }
+        let mime = mime_guess::from_path(&file).first_or_octet_stream();
+        let gzip = compressible(mime.essence_str()).then(|| gzip(&body)).flatten().filter(|g| g.len() < body.len());
+        let Ok(content_type) = HeaderValue::from_str(mime.as_ref()) else {
+            return;
+        };
+        let digest = ring::digest::digest(&ring::digest::SHA256, &body);
+        let Ok(etag) = HeaderValue::from_str(&format!("\"{}\"", hex::encode(&digest.as_ref()[..16]))) else {
+            return;
+        };
+
+        let Ok(mut guard) = self.inner.lock() else {
+            return;
+        };
+        let inner = &mut *guard;
+        let entry = HotEntry {
+            body: Bytes::from(body),
+            gzip: gzip.map(Bytes::from),
+            content_type,
+            etag,
+            last_used: inner.clock,
+        };
+        let size = entry.memory();
+        while !inner.entries.is_empty()
+            && (inner.used_bytes + size > self.budget_bytes || inner.entries.len() >= self.max_entries)
+        {
+            let Some(victim) = inner.entries.iter().min_by_key(|(_, e)| e.last_used).map(|(k, _)| k.clone()) else {
+                break;
+            };
+            if let Some(evicted) = inner.entries.remove(&victim) {
+                inner.used_bytes -= evicted.memory();
+                debug!("Hot asset evicted: {}", victim);
+            }
+        }
+        if inner.used_bytes + size <= self.budget_bytes {
+            inner.used_bytes += size;
+            inner.entries.insert(path.to_string(), entry);
+            debug!("Hot asset cached: {} ({} bytes)", path, size);
+        }
+    }
+
+    /// Hot list and memory usage
+    pub fn stats(&self) -> HotAssetsStats {
+        let Ok(inner) = self.inner.lock() else {
+            return HotAssetsStats { entries: 0, memory_bytes: 0, budget_bytes: self.budget_bytes, hits: 0, misses: 0, hot: Vec::new() };
+        };
+        let mut hot: Vec<HotAssetStat> = inner
+            .entries
+            .iter()
+            .map(|(path, e)| HotAssetStat {
+                path: path.clone(),
+                requests: inner.requests.get(path).copied().unwrap_or(0),
+                bytes: e.body.len(),
+                gzip_bytes: e.gzip.as_ref().map(|g| g.len()),
+            })
+            .collect();
+        hot.sort_by(|a, b| b.requests.cmp(&a.requests).then_with(|| a.path.cmp(&b.path)));
+        HotAssetsStats {
+            entries: inner.entries.len(),
+            memory_bytes: inner.used_bytes,
+            budget_bytes: self.budget_bytes,
+            hits: inner.hits,
+            misses: inner.misses,
+            hot,
+        }
+    }
+}
+
+/// Keep the more requested half of the tracked paths (and every cached one)
+/// at half their counts, so old popularity fades
+fn age_requests(inner: &mut Inner) {
+    let mut counts: Vec<u64> = inner.requests.values().copied().collect();
+    counts.sort_unstable_by(|a, b| b.cmp(a));
+    let floor = counts[MAX_TRACKED_PATHS / 2];
+    let entries = &inner.entries;
+    inner.requests.retain(|path, count| {
+        let keep = entries.contains_key(path) || *count > floor;
+        *count = count.div_ceil(2);
+        keep
+    });
+}
+
+/// Whether an `If-None-Match` list names `etag` (weak comparison, as for GET)
+fn etag_matches(if_none_match: &HeaderValue, etag: &HeaderValue) -> bool {
+    let Ok(tags) = if_none_match.to_str() else {
+        return false;
+    };
+    let etag = etag.to_str().unwrap_or_default();
+    tags.split(',').map(str::trim).any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+fn compressible(mime: &str) -> bool {
+    mime.starts_with("text/")
+        || matches!(mime, "application/javascript" | "application/json" | "image/svg+xml" | "application/wasm")
+}
+
+fn gzip(data: &[u8]) -> Option<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(data).ok()?;
+    encoder.finish().ok()
+}
+
+fn accepts_gzip(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|enc| enc.trim().split(';').next() == Some("gzip"))
+}
+
+/// Serve hot assets from memory; everything else goes on to the directory service
+pub async fn middleware(State(hot): State<Arc<HotAssets>>, req: Request, next: Next) -> Response {
+    // Range and conditional requests stay with the file service
+    let plain = req.method() == Method::GET
+        && !req.headers().contains_key(header::RANGE)
+        && !req.headers().contains_key(header::IF_MODIFIED_SINCE);
+    if !hot.enabled() || !plain {
+        return next.run(req).await;
+    }
+
+    let path = req.uri().path().trim_start_matches('/').to_string();
+    let if_none_match = req.headers().get(header::IF_NONE_MATCH);
+    if let Some(response) = hot.get(&path, accepts_gzip(req.headers()), if_none_match) {
+        return response;
+    }
+
+    let response = next.run(req).await;
+    if response.status() == StatusCode::OK && hot.should_admit(&path) {
+        tokio::task::spawn_blocking(move || hot.load(&path));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_admit_and_evict() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("a.js"), "console.log('a');".repeat(1000)).unwrap();
+        std::fs::write(dir.path().join("b.js"), "console.log('b');".repeat(1000)).unwrap();
+        let hot = HotAssets::new(dir.path(), &HotAssetsConfig { memory_mb: 1, max_entries: 1 });
+
+        assert!(hot.get("a.js", true, None).is_none());
+        assert!(!hot.should_admit("a.js"));
+        assert!(hot.get("a.js", true, None).is_none());
+        assert!(hot.should_admit("a.js"));
+        hot.load("a.js");
+
+        let response = hot.get("a.js", true, None).unwrap();
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        let etag = response.headers()[header::ETAG].clone();
+        let revalidated = hot.get("a.js", true, Some(&etag)).unwrap();
+        assert_eq!(revalidated.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(revalidated.headers()[header::ETAG], etag);
+        let stale = HeaderValue::from_static("\"0000\"");
+        assert_eq!(hot.get("a.js", true, Some(&stale)).unwrap().status(), StatusCode::OK);
+        assert!(hot.get("a.js", false, None).unwrap().headers().get(header::CONTENT_ENCODING).is_none());
+
+        // A second asset pushes the least recently used one out
+        hot.load("b.js");
+        let stats = hot.stats();
+        assert_eq!(stats.entries, 1);
+        assert_eq!(stats.hot[0].path, "b.js");
+        assert_eq!(stats.hits, 4);
+
+        hot.load("../secret.txt");
+        assert_eq!(hot.stats().entries, 1);
+
+        // Request counts of uncached paths don't pile up
+        for i in 0..3 * MAX_TRACKED_PATHS {
+            hot.get(&format!("chunk-{}.js", i), true, None);
+        }
+        let inner = hot.inner.lock().unwrap();
+        assert!(inner.requests.len() <= MAX_TRACKED_PATHS);
+        assert!(inner.requests.contains_key("a.js"), "a path asked for more often survives");
+    }
+}
@@ -0,0 +1,32 @@
+//! HTML escaping for the pages the launcher and LightDocs write by hand
+//!
+//! Text from documents, uploads and config ends up both in element content
+//! and in quoted attributes (`title="..."`, `href='...'`), so both quote
+//! characters are escaped along with `&`, `<` and `>`.
+
+/// Escape `text` for element content or a quoted attribute value
+pub fn html_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_escape() {
+        assert_eq!(html_escape("a < b && c > \"d\" 'e'"), "a &lt; b &amp;&amp; c &gt; &quot;d&quot; &#39;e&#39;");
+        assert_eq!(html_escape("Москва"), "Москва");
+    }
+}
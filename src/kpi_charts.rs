@@ -0,0 +1,289 @@
+//! Static SVG charts of the demo KPIs for the knowledge base
+//!
+//! On a machine where Superset can't run at all (no AVX, Python blocked by
+//! policy, a thin client without a browser that handles the SPA) the
+//! knowledge base still opens. Whenever the demo data is refreshed
+//! (`import-demo`, the data watcher), a few key figures from examples.db are
+//! drawn as plain SVG files into `knowledge/kpi/` together with a public page
+//! that shows them inline, so the site has at least a picture of the numbers.
+//! No renderer, GPU or fonts are needed: the SVG is written by hand.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OpenFlags};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::html::html_escape;
+use crate::lightdocs::LightDocsConfig;
+
+/// Folder inside the knowledge base
+const KPI_FOLDER: &str = "kpi";
+
+/// Generated page, overwritten on every refresh
+const PAGE_FILE: &str = "index.md";
+
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 260.0;
+const MARGIN_LEFT: f64 = 56.0;
+const MARGIN_RIGHT: f64 = 16.0;
+const MARGIN_TOP: f64 = 40.0;
+const MARGIN_BOTTOM: f64 = 36.0;
+const COLOR: &str = "#2563eb";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Line,
+    Bar,
+}
+
+/// One chart: a query returning `(label, value)` rows
+struct ChartDef {
+    file: &'static str,
+    title: &'static str,
+    unit: &'static str,
+    kind: Kind,
+    sql: &'static str,
+}
+
+const CHARTS: &[ChartDef] = &[
+    ChartDef {
+        file: "passengers",
+        title: "Перевезено пассажиров по месяцам",
+        unit: "млн",
+        kind: Kind::Line,
+        sql: "SELECT printf('%02d.%d', month, year), passengers_mln FROM rzd_monthly_stats ORDER BY year, month",
+    },
+    ChartDef {
+        file: "on_time",
+        title: "Пунктуальность по месяцам",
+        unit: "%",
+        kind: Kind::Line,
+        sql: "SELECT printf('%02d.%d', month, year), on_time_pct FROM rzd_monthly_stats ORDER BY year, month",
+    },
+    ChartDef {
+        file: "revenue",
+        title: "Выручка по месяцам",
+        unit: "млрд ₽",
+        kind: Kind::Bar,
+        sql: "SELECT printf('%02d.%d', month, year), revenue_bln_rub FROM rzd_monthly_stats ORDER BY year, month",
+    },
+    ChartDef {
+        file: "revenue_by_region",
+        title: "Выручка по регионам",
+        unit: "млн ₽",
+        kind: Kind::Bar,
+        sql: "SELECT region, SUM(revenue_mln_rub) AS total FROM rzd_daily_operations GROUP BY region ORDER BY total DESC LIMIT 12",
+    },
+];
+
+fn format_value(value: f64) -> String {
+    if value.abs() >= 100.0 {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.1}", value)
+    }
+}
+
+/// Draw `points` as an SVG document
+fn svg(title: &str, unit: &str, kind: Kind, points: &[(String, f64)]) -> String {
+    let plot_w = WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+    let plot_h = HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+    let max = points.iter().map(|(_, v)| *v).fold(f64::MIN, f64::max);
+    let min = points.iter().map(|(_, v)| *v).fold(f64::MAX, f64::min);
+    // Bars start at zero; a line zooms in on its range so small changes show
+    let (low, high) = match kind {
+        Kind::Bar => (min.min(0.0), max.max(0.0)),
+        Kind::Line => {
+            let pad = ((max - min) * 0.1).max(max.abs() * 0.01).max(0.5);
+            (min - pad, max + pad)
+        }
+    };
+    let span = if high > low { high - low } else { 1.0 };
+    let y = |v: f64| MARGIN_TOP + plot_h * (1.0 - (v - low) / span);
+    let slot = plot_w / points.len().max(1) as f64;
+    let x = |i: usize| MARGIN_LEFT + slot * (i as f64 + 0.5);
+
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {w} {h}" width="{w}" height="{h}" font-family="sans-serif" font-size="11" role="img" aria-label="{title}">"#,
+        w = WIDTH,
+        h = HEIGHT,
+        title = html_escape(title)
+    );
+    let _ = write!(out, "\n<title>{}</title>", html_escape(title));
+    let _ = write!(out, "\n<rect width=\"{}\" height=\"{}\" fill=\"#ffffff\"/>", WIDTH, HEIGHT);
+    let _ = write!(
+        out,
+        "\n<text x=\"{}\" y=\"22\" font-size=\"14\" font-weight=\"bold\" fill=\"#111827\">{}, {}</text>",
+        MARGIN_LEFT,
+        html_escape(title),
+        html_escape(unit)
+    );
+
+    // Grid with value labels
+    for step in 0..=4 {
+        let value = low + span * step as f64 / 4.0;
+        let gy = y(value);
+        let _ = write!(
+            out,
+            "\n<line x1=\"{}\" y1=\"{gy:.1}\" x2=\"{}\" y2=\"{gy:.1}\" stroke=\"#e5e7eb\"/><text x=\"{}\" y=\"{:.1}\" text-anchor=\"end\" fill=\"#6b7280\">{}</text>",
+            MARGIN_LEFT,
+            WIDTH - MARGIN_RIGHT,
+            MARGIN_LEFT - 6.0,
+            gy + 4.0,
+            format_value(value),
+            gy = gy
+        );
+    }
+
+    // At most ~12 axis labels so they don't overlap
+    let every = points.len().div_ceil(12).max(1);
+    for (i, (label, value)) in points.iter().enumerate() {
+        if kind == Kind::Bar {
+            let (top, bottom) = (y(value.max(0.0)), y(value.min(0.0)));
+            let _ = write!(
+                out,
+                "\n<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"><title>{}: {}</title></rect>",
+                x(i) - slot * 0.35,
+                top,
+                slot * 0.7,
+                (bottom - top).max(0.5),
+                COLOR,
+                html_escape(label),
+                format_value(*value)
+            );
+        }
+        if i % every == 0 {
+            let _ = write!(
+                out,
+                "\n<text x=\"{:.1}\" y=\"{}\" text-anchor=\"middle\" fill=\"#374151\">{}</text>",
+                x(i),
+                HEIGHT - MARGIN_BOTTOM + 16.0,
+                html_escape(label)
+            );
+        }
+    }
+    if kind == Kind::Line {
+        let path: Vec<String> = points.iter().enumerate().map(|(i, (_, v))| format!("{:.1},{:.1}", x(i), y(*v))).collect();
+        let _ = write!(
+            out,
+            "\n<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>",
+            path.join(" "),
+            COLOR
+        );
+        for (i, (label, value)) in points.iter().enumerate() {
+            let _ = write!(
+                out,
+                "\n<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"{}\"><title>{}: {}</title></circle>",
+                x(i),
+                y(*value),
+                COLOR,
+                html_escape(label),
+                format_value(*value)
+            );
+        }
+    }
+    out.push_str("\n</svg>\n");
+    out
+}
+
+fn query(conn: &Connection, sql: &str) -> Result<Vec<(String, f64)>> {
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<f64>>(1)?)))?;
+    let mut points = Vec::new();
+    for row in rows {
+        if let (label, Some(value)) = row? {
+            points.push((label, value));
+        }
+    }
+    Ok(points)
+}
+
+/// Draw the charts from `examples_db` into the knowledge base and write
+/// their page; returns the files written. Charts whose table is missing or
+/// empty are left out; no database at all writes nothing.
+pub fn render(root: &Path, examples_db: &Path) -> Result<Vec<PathBuf>> {
+    if !examples_db.exists() {
+        return Ok(Vec::new());
+    }
+    let conn = Connection::open_with_flags(examples_db, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open {}", examples_db.display()))?;
+    let dir = LightDocsConfig::load(root)?.docs_root_abs(root).join(KPI_FOLDER);
+
+    let mut drawn = Vec::new();
+    for chart in CHARTS {
+        match query(&conn, chart.sql) {
+            Ok(points) if !points.is_empty() => drawn.push((chart, svg(chart.title, chart.unit, chart.kind, &points))),
+            Ok(_) => {}
+            Err(e) => warn!("KPI chart '{}' skipped: {}", chart.file, e),
+        }
+    }
+    if drawn.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    fs::create_dir_all(&dir)?;
+    let mut written = Vec::new();
+    let mut page = format!(
+        "---\ntitle: Ключевые показатели (статичные графики)\nstatus: public\ntags: [kpi, demo]\nupdated: {}\n---\n\n\
+         > Страница создаётся автоматически при обновлении демо-данных — правки в ней будут перезаписаны.\n\
+         > Графики нарисованы без Superset и открываются на любом компьютере.\n",
+        chrono::Local::now().format("%Y-%m-%d")
+    );
+    for (chart, content) in &drawn {
+        let path = dir.join(format!("{}.svg", chart.file));
+        fs::write(&path, content)?;
+        written.push(path);
+        // Inline, so the built site needs no image files; the file link is for download
+        let _ = write!(
+            page,
+            "\n## {}\n\n<div class=\"kpi-chart\">\n{}</div>\n\n[[file:{}.svg|Скачать SVG]]\n",
+            chart.title,
+            content.trim_end(),
+            chart.file
+        );
+    }
+    let page_path = dir.join(PAGE_FILE);
+    fs::write(&page_path, page)?;
+    written.push(page_path);
+    info!("📊 {} KPI charts drawn into {}", drawn.len(), dir.display());
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_render_demo_kpis() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let db = root.join("examples.db");
+        assert!(render(root, &db).unwrap().is_empty());
+
+        Connection::open(&db)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE rzd_monthly_stats (month INTEGER, year INTEGER, passengers_mln REAL, cargo_mln_tons REAL, revenue_bln_rub REAL, on_time_pct REAL);
+                 INSERT INTO rzd_monthly_stats VALUES (1, 2024, 85.2, 102.3, 245.8, 94.2), (2, 2024, 78.5, 98.7, 232.1, 93.8);",
+            )
+            .unwrap();
+        let written = render(root, &db).unwrap();
+        // Three monthly charts and the page; no daily table, no region chart
+        assert_eq!(written.len(), 4);
+
+        let passengers = fs::read_to_string(root.join("knowledge/kpi/passengers.svg")).unwrap();
+        assert!(passengers.starts_with("<svg"));
+        assert!(passengers.contains("01.2024: 85.2"));
+        assert!(!passengers.contains("\n\n"), "a blank line would end the inline HTML block");
+
+        let page = fs::read_to_string(root.join("knowledge/kpi/index.md")).unwrap();
+        assert!(page.contains("status: public"));
+        assert!(page.contains("[[file:on_time.svg|Скачать SVG]]"));
+        assert!(!root.join("knowledge/kpi/revenue_by_region.svg").exists());
+    }
+}
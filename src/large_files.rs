@@ -0,0 +1,215 @@
+//! Streaming file serving with HTTP range support
+//!
+//! Demo assets (multi-hundred-MB GeoJSON, videos) are streamed from disk in
+//! fixed-size chunks instead of being read whole, and `Range` requests are
+//! answered with `206 Partial Content` so browsers can seek and resume.
+//! Small files keep going through `ServeDir`.
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::ops::RangeInclusive;
+use std::path::{Component, Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+use tracing::{error, info};
+
+/// Files at or above this size are streamed by this module
+pub const LARGE_FILE_THRESHOLD: u64 = 8 * 1024 * 1024;
+
+/// Read chunk size for streamed responses
+const CHUNK_SIZE: usize = 256 * 1024;
+
+/// Middleware: serve large files (or any ranged request) from `base_dir`,
+/// pass everything else to the inner service
+pub async fn large_file_middleware(
+    State(base_dir): State<PathBuf>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        return next.run(req).await;
+    }
+
+    let Some(path) = resolve_path(&base_dir, req.uri().path()) else {
+        return next.run(req).await;
+    };
+
+    let size = match tokio::fs::metadata(&path).await {
+        Ok(meta) if meta.is_file() => meta.len(),
+        _ => return next.run(req).await,
+    };
+
+    if size < LARGE_FILE_THRESHOLD && !req.headers().contains_key(header::RANGE) {
+        return next.run(req).await;
+    }
+
+    serve_file(&path, size, req.method() == Method::HEAD, req.headers()).await
+}
+
+/// Map a request path onto `base_dir`, rejecting traversal outside it
+fn resolve_path(base_dir: &Path, uri_path: &str) -> Option<PathBuf> {
+    let decoded = percent_decode(uri_path.trim_start_matches('/'))?;
+    let mut path = base_dir.to_path_buf();
+    for component in Path::new(&decoded).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(path)
+}
+
+/// Minimal percent-decoding for request paths (UTF-8 file names)
+fn percent_decode(input: &str) -> Option<String> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+/// Parse a single `Range: bytes=...` header against a file of `len` bytes.
+///
+/// Returns `Ok(None)` when the header is absent or not a byte range we handle
+/// (the whole file is sent), `Err(())` when the range cannot be satisfied.
+fn parse_range(value: Option<&HeaderValue>, len: u64) -> Result<Option<RangeInclusive<u64>>, ()> {
+    let Some(value) = value.and_then(|v| v.to_str().ok()) else {
+        return Ok(None);
+    };
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return Ok(None);
+    };
+    // Multipart ranges are rare for media; serve the full body instead
+    if spec.contains(',') {
+        return Ok(None);
+    }
+
+    let (start, end) = spec.split_once('-').ok_or(())?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().map_err(|_| ())?;
+            if suffix == 0 || len == 0 {
+                return Err(());
+            }
+            (len.saturating_sub(suffix), len - 1)
+        }
+        (start, "") => (start.parse().map_err(|_| ())?, len.saturating_sub(1)),
+        (start, end) => {
+            let start: u64 = start.parse().map_err(|_| ())?;
+            let end: u64 = end.parse().map_err(|_| ())?;
+            (start, end.min(len.saturating_sub(1)))
+        }
+    };
+
+    if start >= len || start > end {
+        return Err(());
+    }
+    Ok(Some(start..=end))
+}
+
+/// Stream `path` (or the requested byte range of it)
+async fn serve_file(path: &Path, size: u64, head_only: bool, headers: &HeaderMap) -> Response {
+    let mime = mime_guess::from_path(path).first_or_octet_stream();
+    let content_type = if path.extension().map_or(false, |e| e == "geojson") {
+        "application/geo+json".to_string()
+    } else {
+        mime.essence_str().to_string()
+    };
+
+    let range = match parse_range(headers.get(header::RANGE), size) {
+        Ok(range) => range,
+        Err(()) => {
+            return (
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_RANGE, format!("bytes */{}", size))],
+            )
+                .into_response();
+        }
+    };
+
+    let (status, start, len) = match &range {
+        Some(r) => (StatusCode::PARTIAL_CONTENT, *r.start(), r.end() - r.start() + 1),
+        None => (StatusCode::OK, 0, size),
+    };
+
+    let body = if head_only {
+        Body::empty()
+    } else {
+        let mut file = match tokio::fs::File::open(path).await {
+            Ok(f) => f,
+            Err(e) => {
+                error!("Failed to open {}: {}", path.display(), e);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        };
+        if start > 0 {
+            if let Err(e) = file.seek(std::io::SeekFrom::Start(start)).await {
+                error!("Failed to seek {}: {}", path.display(), e);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        }
+        Body::from_stream(ReaderStream::with_capacity(file.take(len), CHUNK_SIZE))
+    };
+
+    if range.is_some() {
+        info!("📼 Range {}-{} of {} ({})", start, start + len - 1, size, path.display());
+    }
+
+    let mut response = Response::new(body);
+    *response.status_mut() = status;
+    let h = response.headers_mut();
+    h.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    h.insert(header::CONTENT_LENGTH, HeaderValue::from(len));
+    if let Ok(ct) = HeaderValue::from_str(&content_type) {
+        h.insert(header::CONTENT_TYPE, ct);
+    }
+    if range.is_some() {
+        if let Ok(cr) = HeaderValue::from_str(&format!("bytes {}-{}/{}", start, start + len - 1, size)) {
+            h.insert(header::CONTENT_RANGE, cr);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(v: &str, len: u64) -> Result<Option<RangeInclusive<u64>>, ()> {
+        parse_range(Some(&HeaderValue::from_str(v).unwrap()), len)
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(range("bytes=0-99", 1000), Ok(Some(0..=99)));
+        assert_eq!(range("bytes=500-", 1000), Ok(Some(500..=999)));
+        assert_eq!(range("bytes=-100", 1000), Ok(Some(900..=999)));
+        assert_eq!(range("bytes=900-5000", 1000), Ok(Some(900..=999)));
+        assert_eq!(range("bytes=1000-", 1000), Err(()));
+        assert_eq!(range("bytes=0-1,5-9", 1000), Ok(None));
+        assert_eq!(parse_range(None, 1000), Ok(None));
+    }
+
+    #[test]
+    fn test_resolve_path_rejects_traversal() {
+        let base = Path::new("/srv/docs");
+        assert_eq!(resolve_path(base, "/maps/world.geojson"), Some(base.join("maps").join("world.geojson")));
+        assert_eq!(resolve_path(base, "/../secret.txt"), None);
+        assert_eq!(resolve_path(base, "/%D0%BA%D0%B0%D1%80%D1%82%D0%B0.mp4"), Some(base.join("карта.mp4")));
+    }
+}
@@ -1,29 +1,197 @@
 //! Unified Web Launcher UI
 //!
 //! Web interface for managing Superset and LightDocs services.
-//! Runs on port 3000 by default.
+//! Runs on port 3000 by default. The page is responsive and installable
+//! (web manifest + service worker) so it can be used from a phone on the
+//! same closed network when started with `--lan`.
 
 use anyhow::Result;
 use axum::{
-    extract::State,
-    response::{Html, IntoResponse, Json},
+    extract::{ConnectInfo, DefaultBodyLimit, Multipart, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Json, Response},
     routing::{get, post},
     Router,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::{RwLock, mpsc};
 use tracing::{info, error};
 
+use crate::control::{ControlCommand, ControlRequest};
+use crate::credentials::{self, CredentialStore};
+use crate::drive_watchdog::DriveEvent;
+use crate::notifications::{self, Level};
+use crate::self_docs::ApiRoute;
+use crate::ui_strings::Lang;
+use crate::usage_stats::UsageEvent;
+
 #[derive(Deserialize)]
 struct SearchQuery {
     q: String,
+    limit: Option<usize>,
+    #[serde(default)]
+    offset: usize,
+    #[serde(default)]
+    min_score: f32,
+    #[serde(default)]
+    explain: bool,
+    /// Search inside one document (slug)
+    doc: Option<String>,
+}
+
+impl SearchQuery {
+    fn options(&self) -> crate::lightdocs::search::SearchOptions {
+        crate::lightdocs::search::SearchOptions {
+            limit: self.limit,
+            offset: self.offset,
+            min_score: self.min_score,
+            explain: self.explain,
+            doc: self.doc.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct FreshnessQuery {
+    /// Logical database name from config.json (default: examples)
+    db: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    /// Hours to show (default: 24)
+    hours: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct LangQuery {
+    /// `ru` or `en`; the browser's Accept-Language when missing
+    lang: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SchedulePreviewQuery {
+    cron: String,
+    lang: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ScheduleUpdate {
+    /// `backup` or `alert:<name>`
+    job: String,
+    /// Cron expression; missing brings back the built-in timing
+    cron: Option<String>,
+    enabled: bool,
+}
+
+#[derive(Deserialize)]
+struct UploadQuery {
+    /// Target table (default: file name)
+    table: Option<String>,
+    /// Logical database name from config.json (default: examples)
+    db: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LogQuery {
+    /// Byte offset to continue from (`X-Log-End` of the previous answer)
+    from: Option<u64>,
+    /// Last bytes to send when `from` is missing (default 256 KiB, 0 for the whole file)
+    tail: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct DocsFsRequest {
+    /// Item, relative to the knowledge folder (trash id for restore)
+    path: String,
+    /// New name (rename)
+    name: Option<String>,
+    /// Target folder (move), empty for the top of the knowledge folder
+    to: Option<String>,
+}
+
+/// What a `/api/docs/fs/*` call does
+#[derive(Clone, Copy)]
+enum DocsFsOp {
+    Mkdir,
+    Rename,
+    Move,
+    Delete,
+    Restore,
+}
+
+#[derive(Deserialize)]
+struct LoginRequest {
+    password: String,
 }
 
 /// Default port for launcher UI
 pub const LAUNCHER_PORT: u16 = 3000;
 
+/// Cookie carrying the launcher API token after a UI login
+const TOKEN_COOKIE: &str = "launcher_token";
+
+/// Wrong passwords allowed from one address before it has to wait
+const LOGIN_MAX_FAILURES: u32 = 5;
+
+/// How long an address that ran out of login attempts waits
+const LOGIN_LOCKOUT: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// Log files served by `/api/logs`, relative to root
+const LOGS_DIR: &str = "logs";
+
+/// Log bytes sent without `tail` or `from`
+const DEFAULT_LOG_TAIL: u64 = 256 * 1024;
+
+/// Read size when streaming a log
+const LOG_CHUNK: usize = 64 * 1024;
+
+/// Every route of the UI, for the generated reference (keep in step with `start`)
+pub const ROUTES: &[ApiRoute] = &[
+    ApiRoute { method: "GET", path: "/", token: false, summary: "Страница панели" },
+    ApiRoute { method: "GET", path: "/manifest.webmanifest", token: false, summary: "Манифест для установки панели на телефон" },
+    ApiRoute { method: "GET", path: "/sw.js", token: false, summary: "Service worker панели" },
+    ApiRoute { method: "GET", path: "/icon.svg", token: false, summary: "Значок панели" },
+    ApiRoute { method: "GET", path: "/help", token: false, summary: "Краткая справка" },
+    ApiRoute { method: "GET", path: "/api/status", token: false, summary: "Состояние Superset, LightDocs и наблюдателя" },
+    ApiRoute { method: "POST", path: "/api/login", token: false, summary: "Вход в панель паролем администратора Superset" },
+    ApiRoute { method: "GET", path: "/api/lightdocs/search", token: false, summary: "Поиск по базе знаний (`q`, `limit`, `offset`, `doc`)" },
+    ApiRoute { method: "GET", path: "/api/lightdocs/stats", token: false, summary: "Статистика базы знаний" },
+    ApiRoute { method: "GET", path: "/api/search", token: false, summary: "Общий поиск по базе знаний и объектам Superset" },
+    ApiRoute { method: "GET", path: "/api/usage", token: false, summary: "Счётчики использования" },
+    ApiRoute { method: "GET", path: "/api/alerts", token: false, summary: "Последние срабатывания оповещений" },
+    ApiRoute { method: "GET", path: "/api/schedules", token: false, summary: "Расписания резервного копирования и оповещений с ближайшими запусками (`lang`)" },
+    ApiRoute { method: "GET", path: "/api/schedules/preview", token: false, summary: "Cron-выражение словами и пять ближайших запусков (`cron`, `lang`)" },
+    ApiRoute { method: "GET", path: "/api/health/history", token: false, summary: "Доступность и время ответа сервисов по 15 минут (`hours`)" },
+    ApiRoute { method: "GET", path: "/api/data/freshness", token: false, summary: "Свежесть загруженных таблиц" },
+    ApiRoute { method: "GET", path: "/api/data/validations", token: false, summary: "Результаты проверок validations.yaml" },
+    ApiRoute { method: "GET", path: "/api/actions", token: false, summary: "Список действий панели" },
+    ApiRoute { method: "GET", path: "/api/i18n", token: false, summary: "Строки интерфейса (`lang`)" },
+    ApiRoute { method: "POST", path: "/api/superset/start", token: true, summary: "Запустить Superset" },
+    ApiRoute { method: "POST", path: "/api/superset/stop", token: true, summary: "Остановить Superset" },
+    ApiRoute { method: "POST", path: "/api/lightdocs/start", token: true, summary: "Запустить LightDocs" },
+    ApiRoute { method: "POST", path: "/api/lightdocs/stop", token: true, summary: "Остановить LightDocs" },
+    ApiRoute { method: "POST", path: "/api/watcher/start", token: true, summary: "Включить наблюдение за папкой данных" },
+    ApiRoute { method: "POST", path: "/api/watcher/stop", token: true, summary: "Выключить наблюдение за папкой данных" },
+    ApiRoute { method: "POST", path: "/api/upload", token: true, summary: "Загрузить файл Excel/CSV в таблицу" },
+    ApiRoute { method: "POST", path: "/api/db/query/export", token: true, summary: "Выгрузить результат SELECT в CSV/XLSX (`sql`, `params`, `format`, `db`)" },
+    ApiRoute { method: "POST", path: "/api/schedules", token: true, summary: "Изменить расписание задачи или включить/выключить её (`job`, `cron`, `enabled`)" },
+    ApiRoute { method: "GET", path: "/api/logs", token: true, summary: "Файлы журналов в logs/ с размером и временем изменения" },
+    ApiRoute { method: "GET", path: "/api/logs/:name", token: true, summary: "Содержимое журнала потоком (`tail` — последние байты, `from` — продолжить с `X-Log-End`)" },
+    ApiRoute { method: "GET", path: "/api/docs/fs/tree", token: true, summary: "Файлы и папки базы знаний и корзина" },
+    ApiRoute { method: "POST", path: "/api/docs/fs/mkdir", token: true, summary: "Создать папку в базе знаний (`path`)" },
+    ApiRoute { method: "POST", path: "/api/docs/fs/rename", token: true, summary: "Переименовать файл или папку (`path`, `name`)" },
+    ApiRoute { method: "POST", path: "/api/docs/fs/move", token: true, summary: "Переместить файл или папку в другую папку (`path`, `to`)" },
+    ApiRoute { method: "POST", path: "/api/docs/fs/delete", token: true, summary: "Удалить в корзину (`path`), хранится 30 дней" },
+    ApiRoute { method: "POST", path: "/api/docs/fs/restore", token: true, summary: "Вернуть из корзины на прежнее место (`path` — id в корзине)" },
+    ApiRoute { method: "POST", path: "/api/shutdown", token: true, summary: "Остановить всё и закрыть лаунчер" },
+];
+
 /// Service status
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -42,6 +210,12 @@ pub struct SystemStatus {
     pub lightdocs: ServiceInfo,
     pub watcher: ServiceInfo,
     pub uptime_seconds: u64,
+    /// Time of the newest archive in `backups/`
+    pub last_backup: Option<String>,
+    /// Seconds since the flash drive disappeared (services paused)
+    pub drive_lost_seconds: Option<u64>,
+    /// Crash report of the last day (`logs/crash-*.json`)
+    pub last_crash: Option<crate::crash_report::CrashNotice>,
 }
 
 /// Individual service info
@@ -50,6 +224,9 @@ pub struct ServiceInfo {
     pub status: ServiceStatus,
     pub port: u16,
     pub url: String,
+    /// Seconds since start while the service is still warming up
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warmup_seconds: Option<u64>,
 }
 
 /// Shared application state
@@ -63,13 +240,25 @@ pub struct AppState {
     pub lightdocs_port: u16,
     pub shutdown_tx: mpsc::Sender<()>,
     pub watcher: Arc<crate::watcher::DataWatcher>,
+    /// When the current Superset start began (None when not starting)
+    pub superset_started_at: RwLock<Option<std::time::Instant>>,
+    /// How long to wait for Superset's /health before reporting an error
+    pub startup_timeout: std::time::Duration,
+    /// Admin password store (UI login)
+    pub credentials: CredentialStore,
+    /// Required for actions from other machines
+    pub api_token: String,
+    /// Failed logins per address: count and time of the last one
+    login_failures: std::sync::Mutex<HashMap<IpAddr, (u32, std::time::Instant)>>,
 }
 
 impl AppState {
-    pub fn new(root: &PathBuf, superset_port: u16, lightdocs_port: u16, shutdown_tx: mpsc::Sender<()>, watcher: Arc<crate::watcher::DataWatcher>) -> Self {
+    pub fn new(root: &PathBuf, superset_port: u16, lightdocs_port: u16, shutdown_tx: mpsc::Sender<()>, watcher: Arc<crate::watcher::DataWatcher>, startup_timeout: std::time::Duration, credentials: CredentialStore, api_token: String) -> Self {
         Self {
             root: root.clone(),
             start_time: std::time::Instant::now(),
+            superset_started_at: RwLock::new(None),
+            startup_timeout,
             superset_status: RwLock::new(ServiceStatus::Stopped),
             lightdocs_status: RwLock::new(ServiceStatus::Stopped),
             watcher_status: RwLock::new(if watcher.is_running() { ServiceStatus::Running } else { ServiceStatus::Stopped }),
@@ -77,6 +266,9 @@ impl AppState {
             lightdocs_port,
             shutdown_tx,
             watcher,
+            credentials,
+            api_token,
+            login_failures: std::sync::Mutex::new(HashMap::new()),
         }
     }
 }
@@ -84,6 +276,8 @@ impl AppState {
 /// Launcher UI server
 pub struct LauncherUI {
     root: PathBuf,
+    /// Bind address: loopback by default, all interfaces with `--lan`
+    host: String,
     port: u16,
     superset_port: u16,
     lightdocs_port: u16,
@@ -94,6 +288,7 @@ impl LauncherUI {
     pub fn new(root: &PathBuf, port: u16, superset_port: u16, lightdocs_port: u16, watcher: Arc<crate::watcher::DataWatcher>) -> Self {
         Self {
             root: root.clone(),
+            host: "127.0.0.1".to_string(),
             port,
             superset_port,
             lightdocs_port,
@@ -101,30 +296,108 @@ impl LauncherUI {
         }
     }
 
+    /// Listen on all interfaces so phones on the same network can connect
+    pub fn lan(mut self, enabled: bool) -> Self {
+        if enabled {
+            self.host = "0.0.0.0".to_string();
+        }
+        self
+    }
+
     /// Start the launcher UI server
     pub async fn start(&self) -> Result<()> {
         let (tx, mut rx) = mpsc::channel(1);
-        let state = Arc::new(AppState::new(&self.root, self.superset_port, self.lightdocs_port, tx, self.watcher.clone()));
+        let config = crate::config::Config::load_or_create(&self.root)?;
+        let startup_timeout = std::time::Duration::from_secs(config.startup_timeout_secs);
+        let credentials = CredentialStore::open(&self.root, &config.credentials);
+        let (api_token, _) = credentials.get_or_generate(credentials::LAUNCHER_TOKEN)?;
+        // Other devices get the token only by logging in with the admin password
+        if self.host != "127.0.0.1" && credentials.get(credentials::ADMIN_PASSWORD)?.is_none() {
+            anyhow::bail!("--lan needs the admin password for the UI login; set it with: superset-launcher user reset-password");
+        }
+        let state = Arc::new(AppState::new(&self.root, self.superset_port, self.lightdocs_port, tx, self.watcher.clone(), startup_timeout, credentials, api_token));
         
-        let app = Router::new()
-            .route("/", get(index_handler))
-            .route("/api/status", get(status_handler))
+        // Nightly backups run for as long as the launcher is open
+        crate::backup::spawn_scheduler(self.root.clone(), config.backup, self.superset_port);
+        crate::alerts::spawn_scheduler(self.root.clone(), config.databases.clone());
+        crate::slo::spawn_monitor(&self.root, self.superset_port, Some(self.lightdocs_port));
+        crate::log_shipping::spawn_shipper(self.root.clone(), config.log_shipping.clone());
+        
+        // Pause services while the flash drive is away, resume when it's back
+        let (drive_tx, drive_rx) = mpsc::channel(4);
+        crate::drive_watchdog::spawn(self.root.clone(), config.drive_watchdog.clone(), drive_tx);
+        tokio::spawn(handle_drive_events(state.clone(), drive_rx));
+        
+        // Tray, TUI and scripts reach this process through the control pipe/socket
+        let (control_tx, control_rx) = mpsc::channel(16);
+        match crate::control::spawn(&self.root, control_tx) {
+            Ok(()) => {
+                tokio::spawn(handle_control_requests(state.clone(), control_rx));
+            }
+            Err(e) => error!("{:#}", e),
+        }
+        
+        // Actions: open on this machine, token (UI login) from others
+        let actions = Router::new()
             .route("/api/superset/start", post(superset_start_handler))
             .route("/api/superset/stop", post(superset_stop_handler))
             .route("/api/lightdocs/start", post(lightdocs_start_handler))
             .route("/api/lightdocs/stop", post(lightdocs_stop_handler))
             .route("/api/watcher/start", post(watcher_start_handler))
             .route("/api/watcher/stop", post(watcher_stop_handler))
-            .route("/api/lightdocs/search", get(search_handler))
+            .route(
+                "/api/upload",
+                post(upload_handler).layer(DefaultBodyLimit::max(crate::upload::MAX_UPLOAD_BYTES)),
+            )
+            .route("/api/db/query/export", post(query_export_handler))
+            .route("/api/schedules", post(schedule_update_handler))
+            .route("/api/logs", get(logs_handler))
+            .route("/api/logs/:name", get(log_handler))
+            .route("/api/docs/fs/tree", get(docs_fs_tree_handler))
+            .route("/api/docs/fs/mkdir", post(|state, request| docs_fs_handler(state, request, DocsFsOp::Mkdir)))
+            .route("/api/docs/fs/rename", post(|state, request| docs_fs_handler(state, request, DocsFsOp::Rename)))
+            .route("/api/docs/fs/move", post(|state, request| docs_fs_handler(state, request, DocsFsOp::Move)))
+            .route("/api/docs/fs/delete", post(|state, request| docs_fs_handler(state, request, DocsFsOp::Delete)))
+            .route("/api/docs/fs/restore", post(|state, request| docs_fs_handler(state, request, DocsFsOp::Restore)))
             .route("/api/shutdown", post(shutdown_handler))
+            .route_layer(middleware::from_fn_with_state(state.clone(), require_token))
+            // Outermost, so refused calls are recorded too
+            .route_layer(middleware::from_fn_with_state(state.clone(), audit_action));
+        
+        let app = Router::new()
+            .route("/", get(index_handler))
+            .route("/manifest.webmanifest", get(manifest_handler))
+            .route("/sw.js", get(service_worker_handler))
+            .route("/icon.svg", get(icon_handler))
+            .route("/api/status", get(status_handler))
+            .route("/api/login", post(login_handler))
+            .route("/api/lightdocs/search", get(search_handler))
+            .route("/api/lightdocs/stats", get(stats_handler))
+            .route("/api/search", get(unified_search_handler))
+            .route("/api/usage", get(usage_handler))
+            .route("/api/alerts", get(alerts_handler))
+            .route("/api/schedules", get(schedules_handler))
+            .route("/api/schedules/preview", get(schedule_preview_handler))
+            .route("/api/health/history", get(health_history_handler))
+            .route("/api/data/freshness", get(freshness_handler))
+            .route("/api/data/validations", get(validations_handler))
+            .route("/api/actions", get(actions_handler))
+            .route("/api/i18n", get(strings_handler))
+            .route("/help", get(help_handler))
+            .merge(actions)
             .with_state(state);
+        let app = crate::security_headers::apply(app, &config.security, false);
+        let app = if config.ui_compression { with_compression(app) } else { app };
 
-        let addr = format!("127.0.0.1:{}", self.port);
+        let addr = format!("{}:{}", self.host, self.port);
         info!("🚀 Launcher UI starting at http://{}", addr);
+        if self.host != "127.0.0.1" {
+            info!("⚠️ Launcher UI is reachable from the local network (--lan)");
+        }
         
         let listener = tokio::net::TcpListener::bind(&addr).await?;
         
-        axum::serve(listener, app)
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
             .with_graceful_shutdown(async move {
                 let _ = rx.recv().await;
                 info!("Shutdown signal received");
@@ -140,11 +413,192 @@ impl LauncherUI {
     }
 }
 
+/// gzip or zstd by Accept-Encoding, streamed bodies included; XLSX exports are
+/// already deflated and images are left alone by the default predicate
+fn with_compression(app: Router) -> Router {
+    use tower_http::compression::predicate::{NotForContentType, Predicate};
+    use tower_http::compression::{CompressionLayer, DefaultPredicate};
+    let predicate = DefaultPredicate::new().and(NotForContentType::const_new("application/vnd.openxmlformats"));
+    app.layer(CompressionLayer::new().compress_when(predicate))
+}
+
+/// Stop services when the drive is lost and bring back the ones that ran
+async fn handle_drive_events(state: Arc<AppState>, mut events: mpsc::Receiver<DriveEvent>) {
+    let mut restart_superset = false;
+    let mut restart_watcher = false;
+    while let Some(event) = events.recv().await {
+        match event {
+            DriveEvent::Lost => {
+                restart_superset = check_port(state.superset_port).await
+                    || *state.superset_status.read().await == ServiceStatus::Starting;
+                restart_watcher = state.watcher.is_running();
+                if restart_superset {
+                    let _ = superset_stop_handler(State(state.clone())).await;
+                }
+                if restart_watcher {
+                    let _ = watcher_stop_handler(State(state.clone())).await;
+                }
+            }
+            DriveEvent::Restored => {
+                if restart_watcher {
+                    let _ = watcher_start_handler(State(state.clone())).await;
+                }
+                if restart_superset {
+                    let _ = superset_start_handler(State(state.clone())).await;
+                }
+            }
+            DriveEvent::GiveUp => {
+                let _ = state.shutdown_tx.send(()).await;
+            }
+        }
+    }
+}
+
+/// Answer control channel requests with the handlers the page uses
+async fn handle_control_requests(state: Arc<AppState>, mut requests: mpsc::Receiver<ControlRequest>) {
+    while let Some(request) = requests.recv().await {
+        let (answer, action) = match request.command {
+            ControlCommand::Ping | ControlCommand::Status => (response_json(status_handler(State(state.clone())).await).await, None),
+            ControlCommand::Start => (response_json(superset_start_handler(State(state.clone())).await).await, Some("ctl start")),
+            ControlCommand::Stop => (response_json(superset_stop_handler(State(state.clone())).await).await, Some("ctl stop")),
+            ControlCommand::Shutdown => (response_json(shutdown_handler(State(state.clone())).await).await, Some("ctl shutdown")),
+        };
+        if let Some(action) = action {
+            let outcome = answer.as_ref().err().map_or("ok", String::as_str);
+            record_audit(&state, crate::audit::AuditEvent::now("ctl", action, outcome));
+        }
+        let _ = request.reply.send(answer);
+    }
+}
+
+/// JSON body of a handler's response; `{"error": ...}` becomes the error message
+async fn response_json(response: impl IntoResponse) -> Result<serde_json::Value, String> {
+    let response = response.into_response();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.map_err(|e| e.to_string())?;
+    let value: serde_json::Value = serde_json::from_slice(&body).unwrap_or_default();
+    match value.get("error") {
+        Some(error) => Err(error.as_str().map(String::from).unwrap_or_else(|| error.to_string())),
+        None if !status.is_success() => Err(status.to_string()),
+        None => Ok(value),
+    }
+}
+
 // Handler: Main HTML page
 async fn index_handler() -> Html<&'static str> {
     Html(LAUNCHER_HTML)
 }
 
+// Handler: Latest alert check results
+async fn alerts_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(crate::alerts::latest(&state.root))
+}
+
+// Handler: Backup and alert schedules with their next runs
+async fn schedules_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    axum::extract::Query(params): axum::extract::Query<LangQuery>,
+) -> impl IntoResponse {
+    let root = state.root.clone();
+    let lang = request_lang(&headers, params.lang.as_deref());
+    let jobs = tokio::task::spawn_blocking(move || {
+        let config = crate::config::Config::load_or_create(&root)?;
+        crate::schedules::jobs(&root, &config.backup, lang, chrono::Local::now().naive_local())
+    })
+    .await;
+    match jobs {
+        Ok(Ok(jobs)) => Json(serde_json::to_value(jobs).unwrap()),
+        Ok(Err(e)) => Json(serde_json::json!({"error": e.to_string()})),
+        Err(e) => Json(serde_json::json!({"error": e.to_string()})),
+    }
+}
+
+// Handler: A cron expression in words, while it is being typed
+async fn schedule_preview_handler(
+    headers: HeaderMap,
+    axum::extract::Query(params): axum::extract::Query<SchedulePreviewQuery>,
+) -> Response {
+    let lang = request_lang(&headers, params.lang.as_deref());
+    match crate::schedules::preview(&params.cron, lang, chrono::Local::now().naive_local()) {
+        Ok(preview) => Json(serde_json::to_value(preview).unwrap()).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": format!("{:#}", e)}))).into_response(),
+    }
+}
+
+// Handler: New schedule of a job, or the job switched on/off
+async fn schedule_update_handler(
+    State(state): State<Arc<AppState>>,
+    Json(update): Json<ScheduleUpdate>,
+) -> Response {
+    let root = state.root.clone();
+    let result = tokio::task::spawn_blocking(move || -> Result<()> {
+        let known = update.job == crate::schedules::BACKUP_JOB
+            || crate::alerts::load_rules(&root)?.iter().any(|rule| crate::schedules::alert_job(&rule.name) == update.job);
+        if !known {
+            anyhow::bail!("Unknown job '{}'", update.job);
+        }
+        let schedule = update.cron.map(|cron| crate::schedules::Schedule { cron: cron.trim().to_string(), enabled: update.enabled });
+        crate::schedules::set(&root, &update.job, schedule)?;
+        info!("🗓️ Schedule of '{}' changed from the panel", update.job);
+        Ok(())
+    })
+    .await;
+    match result {
+        Ok(Ok(())) => Json(serde_json::json!({"ok": true})).into_response(),
+        Ok(Err(e)) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": format!("{:#}", e)}))).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": e.to_string()}))).into_response(),
+    }
+}
+
+// Handler: Action registry (shortcuts, command palette, help)
+async fn actions_handler() -> impl IntoResponse {
+    Json(crate::ui_actions::registry_json())
+}
+
+// Handler: UI strings in the requested or browser language
+async fn strings_handler(
+    headers: HeaderMap,
+    axum::extract::Query(params): axum::extract::Query<LangQuery>,
+) -> impl IntoResponse {
+    let lang = request_lang(&headers, params.lang.as_deref());
+    Json(serde_json::json!({ "lang": lang, "strings": crate::ui_strings::bundle(lang) }))
+}
+
+/// `?lang=`, else the browser's Accept-Language
+fn request_lang(headers: &HeaderMap, lang: Option<&str>) -> Lang {
+    lang.and_then(Lang::from_code).unwrap_or_else(|| {
+        let accept = headers.get(header::ACCEPT_LANGUAGE).and_then(|v| v.to_str().ok()).unwrap_or_default();
+        Lang::from_accept_language(accept)
+    })
+}
+
+// Handler: Help page
+async fn help_handler() -> Html<String> {
+    Html(crate::ui_actions::help_html())
+}
+
+// Handler: PWA manifest
+async fn manifest_handler() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "application/manifest+json")], MANIFEST_JSON)
+}
+
+// Handler: Service worker (caches the page shell for offline use)
+async fn service_worker_handler() -> impl IntoResponse {
+    (
+        [
+            (header::CONTENT_TYPE, "application/javascript"),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        SERVICE_WORKER_JS,
+    )
+}
+
+// Handler: App icon
+async fn icon_handler() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "image/svg+xml")], ICON_SVG)
+}
+
 // Handler: Get system status
 async fn status_handler(
     State(state): State<Arc<AppState>>,
@@ -152,8 +606,13 @@ async fn status_handler(
     let superset_status = state.superset_status.read().await.clone();
     let lightdocs_status = state.lightdocs_status.read().await.clone();
     
-    // Check actual port availability
-    let superset_running = check_port(state.superset_port).await;
+    // Superset counts as running only once /health answers (port opens earlier)
+    let superset_running = crate::health_check::check_superset(state.superset_port).await.unwrap_or(false);
+    let superset_warmup = if superset_running {
+        None
+    } else {
+        state.superset_started_at.read().await.map(|t| t.elapsed().as_secs())
+    };
     let lightdocs_running = check_port(state.lightdocs_port).await;
     
     // Watcher status
@@ -169,18 +628,25 @@ async fn status_handler(
             status: if superset_running { ServiceStatus::Running } else { superset_status },
             port: state.superset_port,
             url: format!("http://localhost:{}", state.superset_port),
+            warmup_seconds: superset_warmup,
         },
         lightdocs: ServiceInfo {
             status: if lightdocs_running { ServiceStatus::Running } else { lightdocs_status },
             port: state.lightdocs_port,
             url: format!("http://localhost:{}", state.lightdocs_port),
+            warmup_seconds: None,
         },
         watcher: ServiceInfo {
             status: watcher_status,
             port: 0, // No port for internal service
             url: "internal".to_string(),
+            warmup_seconds: None,
         },
         uptime_seconds: state.start_time.elapsed().as_secs(),
+        last_backup: crate::backup::last_backup(&state.root)
+            .map(|t| t.format("%d.%m.%Y %H:%M").to_string()),
+        drive_lost_seconds: crate::drive_watchdog::paused_for(),
+        last_crash: crate::crash_report::latest(&state.root),
     })
 }
 
@@ -194,10 +660,13 @@ async fn superset_start_handler(
         let mut status = state.superset_status.write().await;
         *status = ServiceStatus::Starting;
     }
+    *state.superset_started_at.write().await = Some(std::time::Instant::now());
+    record_usage(&state, UsageEvent::ServiceStart("superset"));
     
     // Spawn Superset process
     let root = state.root.clone();
     let port = state.superset_port;
+    let readiness_state = state.clone();
     
     tokio::spawn(async move {
         // Prepare paths
@@ -210,7 +679,8 @@ async fn superset_start_handler(
             std::fs::File::create("superset.stderr.log").unwrap() // Fallback
         );
         
-        let python_env = crate::python::PythonEnv::new(&root).unwrap();
+        let env_overrides = crate::config::Config::load_or_create(&root).map(|c| c.env.superset).unwrap_or_default();
+        let python_env = crate::python::PythonEnv::new(&root).unwrap().with_env(&env_overrides);
         let python_path = python_env.python_path();
         
         // Build command with correct environment from PythonEnv
@@ -223,6 +693,9 @@ async fn superset_start_handler(
             "--host", "127.0.0.1",
             "--port", &port.to_string(),
         ]);
+        if !crate::hardware::current().flask_threaded {
+            cmd.arg("--without-threads");
+        }
         
         cmd.current_dir(&root);
         
@@ -238,8 +711,26 @@ async fn superset_start_handler(
             
         match cmd.spawn() {
             Ok(_) => info!("Superset process started via UI"),
-            Err(e) => error!("Failed to start Superset: {}", e),
+            Err(e) => {
+                error!("Failed to start Superset: {}", e);
+                *readiness_state.superset_status.write().await = ServiceStatus::Error;
+                *readiness_state.superset_started_at.write().await = None;
+                return;
+            }
+        }
+        
+        // Poll /health until ready (slow flash drives need 60-120 s)
+        let ready = wait_superset_ready(port, readiness_state.startup_timeout).await;
+        let mut status = readiness_state.superset_status.write().await;
+        if *status == ServiceStatus::Starting {
+            *status = if ready { ServiceStatus::Running } else { ServiceStatus::Error };
+            if ready {
+                notifications::notify(Level::Info, "Superset запущен", &format!("http://localhost:{}", port));
+            } else {
+                notifications::notify(Level::Error, "Superset не запустился", "Подробности: logs\\superset.stderr.log");
+            }
         }
+        *readiness_state.superset_started_at.write().await = None;
     });
     
     Json(serde_json::json!({"status": "starting", "port": state.superset_port}))
@@ -264,6 +755,7 @@ async fn superset_stop_handler(
         let mut status = state.superset_status.write().await;
         *status = ServiceStatus::Stopped;
     }
+    *state.superset_started_at.write().await = None;
     
     Json(serde_json::json!({"status": "stopped"}))
 }
@@ -273,6 +765,7 @@ async fn lightdocs_start_handler(
     State(state): State<Arc<AppState>>,
 ) -> impl IntoResponse {
     info!("Starting LightDocs...");
+    record_usage(&state, UsageEvent::ServiceStart("lightdocs"));
     
     {
         let mut status = state.lightdocs_status.write().await;
@@ -325,6 +818,7 @@ async fn watcher_start_handler(
     State(state): State<Arc<AppState>>,
 ) -> impl IntoResponse {
     info!("Starting Data Watcher...");
+    record_usage(&state, UsageEvent::ServiceStart("watcher"));
     state.watcher.start().await;
     {
         let mut status = state.watcher_status.write().await;
@@ -363,18 +857,513 @@ async fn search_handler(
     State(state): State<Arc<AppState>>,
     axum::extract::Query(params): axum::extract::Query<SearchQuery>,
 ) -> impl IntoResponse {
+    record_usage(&state, UsageEvent::Search);
     let index_res = crate::lightdocs::search::SearchIndex::open(&state.root);
     match index_res {
         Ok(index) => {
-            match index.search(&params.q) {
-                Ok(results) => Json(serde_json::to_value(results).unwrap()),
-                Err(e) => Json(serde_json::json!({"error": e.to_string()})),
+            match index.search_with(&params.q, &params.options()) {
+                Ok(page) => search_response(page),
+                Err(e) => Json(serde_json::json!({"error": e.to_string()})).into_response(),
             }
         },
+        Err(e) => Json(serde_json::json!({"error": e.to_string()})).into_response(),
+    }
+}
+
+/// Results as a JSON array, total match count in `X-Total-Count`
+fn search_response(page: crate::lightdocs::search::SearchPage) -> Response {
+    (
+        [("x-total-count", page.total.to_string())],
+        Json(serde_json::to_value(page.results).unwrap()),
+    )
+        .into_response()
+}
+
+// Handler: Search knowledge base and Superset objects together
+async fn unified_search_handler(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(params): axum::extract::Query<SearchQuery>,
+) -> impl IntoResponse {
+    record_usage(&state, UsageEvent::Search);
+    let results = crate::lightdocs::search::SearchIndex::open(&state.root).and_then(|index| {
+        crate::superset_search::refresh(&state.root, &index)?;
+        index.search_with(&params.q, &params.options())
+    });
+    match results {
+        Ok(page) => search_response(page),
+        Err(e) => Json(serde_json::json!({"error": e.to_string()})).into_response(),
+    }
+}
+
+// Handler: Knowledge base statistics
+async fn stats_handler(
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let stats = crate::lightdocs::LightDocs::new(&state.root)
+        .and_then(|lightdocs| lightdocs.stats());
+    match stats {
+        Ok(stats) => Json(serde_json::to_value(stats).unwrap()),
+        Err(e) => Json(serde_json::json!({"error": e.to_string()})),
+    }
+}
+
+// Handler: Upload a CSV/Excel file (drag and drop) into an analytics database
+async fn upload_handler(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(params): axum::extract::Query<UploadQuery>,
+    mut multipart: Multipart,
+) -> impl IntoResponse {
+    if crate::drive_watchdog::is_paused() {
+        return Json(serde_json::json!({"error": "Флешка недоступна — загрузка приостановлена"}));
+    }
+    let field = match multipart.next_field().await {
+        Ok(Some(field)) => field,
+        Ok(None) => return Json(serde_json::json!({"error": "Файл не передан"})),
+        Err(e) => return Json(serde_json::json!({"error": e.to_string()})),
+    };
+    let file_name = field.file_name().unwrap_or("upload.csv").to_string();
+    let bytes = match field.bytes().await {
+        Ok(bytes) => bytes,
+        Err(e) => return Json(serde_json::json!({"error": e.to_string()})),
+    };
+    
+    let root = state.root.clone();
+    let result = tokio::task::spawn_blocking(move || -> Result<serde_json::Value> {
+        let upload = crate::upload::prepare(&root, &file_name, &bytes)?;
+        let table = params.table.filter(|t| !t.trim().is_empty()).unwrap_or_else(|| {
+            upload.path.file_stem().unwrap_or_default().to_string_lossy().to_string()
+        });
+        let config = crate::config::Config::load_or_create(&root)?;
+        let db_name = params.db.as_deref().unwrap_or(crate::databases::EXAMPLES_NAME);
+        let db_path = crate::databases::resolve(&root, &config.databases, db_name)?;
+        
+        let memory_budget_mb = crate::hardware::current().load_memory_mb(config.load_memory_mb);
+        let options = crate::data_loader::LoadOptions { memory_budget_mb, ..Default::default() };
+        crate::snapshots::take(&root, &config.snapshots, &db_path, &format!("upload {}", table))?;
+        let message = crate::data_loader::load_file_with(&upload.path, &table, &db_path, &options)?;
+        let validation = crate::validations::after_load(&root, &db_path, &table)?;
+        if let Some(validation) = validation.as_ref().filter(|v| v.blocks()) {
+            anyhow::bail!("{}", validation.summary());
+        }
+        let date_columns = crate::data_loader::date_columns(&db_path, &table)?;
+        crate::databases::mark_temporal_columns(&root, db_name, &table, &date_columns, options.date_storage)?;
+        let profiles = crate::data_loader::profile_columns(&db_path, &table)?;
+        crate::databases::push_column_stats(&root, db_name, &table, &profiles)?;
+        if config.replica.enabled {
+            let uuid = crate::databases::connection_uuid(&config.databases, db_name);
+            crate::replica::refresh(&root, db_name, uuid, &db_path)?;
+        }
+        Ok(serde_json::json!({
+            "table": table,
+            "database": db_name,
+            "detected": upload.path.extension().map(|e| e.to_string_lossy().to_string()),
+            "renamed": upload.renamed,
+            "message": message,
+            "validation": validation,
+        }))
+    })
+    .await;
+    
+    match result {
+        Ok(Ok(value)) => Json(value),
+        Ok(Err(e)) => Json(serde_json::json!({"error": e.to_string()})),
+        Err(e) => Json(serde_json::json!({"error": e.to_string()})),
+    }
+}
+
+// Handler: Local usage counters
+async fn usage_handler(
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let root = state.root.clone();
+    let report = tokio::task::spawn_blocking(move || {
+        crate::usage_stats::UsageStats::open(&root).and_then(|stats| stats.report())
+    })
+    .await;
+    match report {
+        Ok(Ok(report)) => Json(serde_json::to_value(report).unwrap()),
+        Ok(Err(e)) => Json(serde_json::json!({"error": e.to_string()})),
+        Err(e) => Json(serde_json::json!({"error": e.to_string()})),
+    }
+}
+
+// Handler: Run a read-only query and stream the rows back as CSV or XLSX
+async fn query_export_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<crate::query_export::ExportRequest>,
+) -> Response {
+    if crate::drive_watchdog::is_paused() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({"error": "Флешка недоступна — выгрузка приостановлена"})),
+        )
+            .into_response();
+    }
+    let root = state.root.clone();
+    let content_type = request.format.content_type();
+    let download_name = request.download_name();
+    let (tx, mut rx) = mpsc::channel(4);
+    tokio::task::spawn_blocking(move || {
+        let mut writer = crate::query_export::ChunkWriter::new(tx);
+        let result = (|| -> Result<u64> {
+            let config = crate::config::Config::load_or_create(&root)?;
+            if !config.query_export.enabled {
+                anyhow::bail!("Query export is disabled (query_export.enabled in config.json)");
+            }
+            let db_name = request.db.as_deref().unwrap_or(crate::databases::EXAMPLES_NAME);
+            let db_path = crate::databases::resolve(&root, &config.databases, db_name)?;
+            let rows = crate::query_export::export(&db_path, &config.query_export, &request, &mut writer)?;
+            info!("📤 Exported {} rows from {} as {}", rows, db_name, request.download_name());
+            Ok(rows)
+        })();
+        writer.finish(result);
+    });
+
+    match rx.recv().await {
+        Some(Ok(first)) => Response::builder()
+            .header(header::CONTENT_TYPE, content_type)
+            .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", download_name))
+            .body(axum::body::Body::new(crate::query_export::ChunkBody::new(Some(first), rx)))
+            .unwrap(),
+        Some(Err(e)) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": e}))).into_response(),
+        None => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": "Export failed"}))).into_response(),
+    }
+}
+
+// Handler: Log files with their size and last change
+async fn logs_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let dir = state.root.join(LOGS_DIR);
+    let logs = tokio::task::spawn_blocking(move || -> Result<Vec<serde_json::Value>> {
+        let mut logs = Vec::new();
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            return Ok(logs);
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() || path.extension().and_then(|e| e.to_str()) != Some("log") {
+                continue;
+            }
+            let modified = metadata.modified().ok().map(|t| chrono::DateTime::<chrono::Local>::from(t).to_rfc3339());
+            logs.push(serde_json::json!({
+                "name": entry.file_name().to_string_lossy(),
+                "size": metadata.len(),
+                "modified": modified,
+            }));
+        }
+        logs.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+        Ok(logs)
+    })
+    .await;
+    match logs {
+        Ok(Ok(logs)) => Json(serde_json::json!({ "logs": logs })),
+        Ok(Err(e)) => Json(serde_json::json!({"error": e.to_string()})),
         Err(e) => Json(serde_json::json!({"error": e.to_string()})),
     }
 }
 
+// Handler: Stream a log (its tail, or what was appended since `from`) in chunks
+async fn log_handler(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+    axum::extract::Query(params): axum::extract::Query<LogQuery>,
+) -> Response {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+    let not_found = || (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Журнал не найден"}))).into_response();
+    if !name.ends_with(".log") {
+        return not_found();
+    }
+    let Ok(path) = crate::safe_path::join(&state.root.join(LOGS_DIR), &name) else {
+        return not_found();
+    };
+    let Ok(mut file) = tokio::fs::File::open(&path).await else {
+        return not_found();
+    };
+    let size = match file.metadata().await {
+        Ok(metadata) if metadata.is_file() => metadata.len(),
+        _ => return not_found(),
+    };
+    let start = match (params.from, params.tail.unwrap_or(DEFAULT_LOG_TAIL)) {
+        // A log that shrank was rotated or recreated: start over
+        (Some(from), _) if from <= size => from,
+        (Some(_), _) | (None, 0) => 0,
+        (None, tail) => size.saturating_sub(tail),
+    };
+    if let Err(e) = file.seek(std::io::SeekFrom::Start(start)).await {
+        return Json(serde_json::json!({"error": e.to_string()})).into_response();
+    }
+    // No Content-Length: the body goes out chunked (and compressed on the fly)
+    Response::builder()
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .header(header::CACHE_CONTROL, "no-store")
+        .header("x-log-start", start)
+        .header("x-log-end", size)
+        .body(axum::body::Body::from_stream(tokio_util::io::ReaderStream::with_capacity(file.take(size - start), LOG_CHUNK)))
+        .unwrap()
+}
+
+// Handler: Files and folders of the knowledge folder, and its trash
+async fn docs_fs_tree_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let root = state.root.clone();
+    let tree = tokio::task::spawn_blocking(move || -> Result<serde_json::Value> {
+        let fs = crate::docs_fs::KnowledgeFs::open(&root)?;
+        Ok(serde_json::json!({ "entries": fs.tree()?, "trash": fs.trash()? }))
+    })
+    .await;
+    match tree {
+        Ok(Ok(tree)) => Json(tree),
+        Ok(Err(e)) => Json(serde_json::json!({"error": format!("{:#}", e)})),
+        Err(e) => Json(serde_json::json!({"error": e.to_string()})),
+    }
+}
+
+// Handler: Create, rename, move, delete to the trash or restore in the knowledge folder
+async fn docs_fs_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<DocsFsRequest>,
+    op: DocsFsOp,
+) -> impl IntoResponse {
+    if crate::drive_watchdog::is_paused() {
+        return Json(serde_json::json!({"error": "Флешка недоступна — изменения приостановлены"}));
+    }
+    let root = state.root.clone();
+    let result = tokio::task::spawn_blocking(move || -> Result<String> {
+        let fs = crate::docs_fs::KnowledgeFs::open(&root)?;
+        let name = request.name.as_deref().unwrap_or_default();
+        let to = request.to.as_deref().unwrap_or_default();
+        Ok(match op {
+            DocsFsOp::Mkdir => fs.create_dir(&request.path)?,
+            DocsFsOp::Rename => fs.rename(&request.path, name)?,
+            DocsFsOp::Move => fs.move_to(&request.path, to)?,
+            DocsFsOp::Delete => fs.delete(&request.path)?.path,
+            DocsFsOp::Restore => fs.restore(&request.path)?,
+        })
+    })
+    .await;
+    match result {
+        Ok(Ok(path)) => Json(serde_json::json!({ "path": path })),
+        Ok(Err(e)) => Json(serde_json::json!({"error": format!("{:#}", e)})),
+        Err(e) => Json(serde_json::json!({"error": e.to_string()})),
+    }
+}
+
+// Handler: Uptime and latency of each service in 15-minute buckets
+async fn health_history_handler(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(params): axum::extract::Query<HistoryQuery>,
+) -> impl IntoResponse {
+    let root = state.root.clone();
+    let hours = params.hours.unwrap_or(24).clamp(1, crate::health_history::RETENTION_HOURS);
+    let history = tokio::task::spawn_blocking(move || -> Result<serde_json::Value> {
+        let now = chrono::Local::now();
+        let mut services = serde_json::Map::new();
+        for service in crate::health_history::SERVICES {
+            let series = crate::health_history::series(&root, service, now, hours)?;
+            services.insert(service.to_string(), serde_json::to_value(series)?);
+        }
+        Ok(serde_json::json!({
+            "bucket_minutes": crate::health_history::BUCKET_MINUTES,
+            "services": services,
+        }))
+    })
+    .await;
+    match history {
+        Ok(Ok(history)) => Json(history),
+        Ok(Err(e)) => Json(serde_json::json!({"error": e.to_string()})),
+        Err(e) => Json(serde_json::json!({"error": e.to_string()})),
+    }
+}
+
+// Handler: Row counts and last load time of every table
+async fn freshness_handler(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(params): axum::extract::Query<FreshnessQuery>,
+) -> impl IntoResponse {
+    let root = state.root.clone();
+    let report = tokio::task::spawn_blocking(move || {
+        let config = crate::config::Config::load_or_create(&root)?;
+        let db_name = params.db.as_deref().unwrap_or(crate::databases::EXAMPLES_NAME);
+        let db_path = crate::databases::resolve(&root, &config.databases, db_name)?;
+        crate::freshness::report(&db_path, &config.freshness)
+    })
+    .await;
+    match report {
+        Ok(Ok(report)) => Json(serde_json::to_value(report).unwrap()),
+        Ok(Err(e)) => Json(serde_json::json!({"error": e.to_string()})),
+        Err(e) => Json(serde_json::json!({"error": e.to_string()})),
+    }
+}
+
+// Handler: Latest validation result of every table with rules
+async fn validations_handler(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(params): axum::extract::Query<FreshnessQuery>,
+) -> impl IntoResponse {
+    let root = state.root.clone();
+    let results = tokio::task::spawn_blocking(move || {
+        let config = crate::config::Config::load_or_create(&root)?;
+        let db_name = params.db.as_deref().unwrap_or(crate::databases::EXAMPLES_NAME);
+        let db_path = crate::databases::resolve(&root, &config.databases, db_name)?;
+        crate::validations::results(&db_path)
+    })
+    .await;
+    match results {
+        Ok(Ok(results)) => Json(serde_json::to_value(results).unwrap()),
+        Ok(Err(e)) => Json(serde_json::json!({"error": e.to_string()})),
+        Err(e) => Json(serde_json::json!({"error": e.to_string()})),
+    }
+}
+
+// Middleware: actions from other machines (`--lan`) need the API token
+async fn require_token(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // Any page open in the kiosk browser is a loopback peer too
+    if !same_origin(request.headers()) {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({"error": "Запрос со сторонней страницы отклонён"})),
+        )
+            .into_response();
+    }
+    let authorized = (peer.ip().is_loopback() && loopback_host(request.headers()))
+        || request_token(request.headers()).is_some_and(|token| credentials::secrets_match(token, &state.api_token));
+    if authorized {
+        return next.run(request).await;
+    }
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(serde_json::json!({"error": "Требуется вход", "login": true})),
+    )
+        .into_response()
+}
+
+// Middleware: every action goes to the audit log with who asked and how it ended.
+// The outcome comes from the status alone: bodies stream through untouched.
+async fn audit_action(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let action = format!("{} {}", request.method(), request.uri().path());
+    let response = next.run(request).await;
+    let status = response.status();
+    let outcome = if status.is_success() { "ok".to_string() } else { status.to_string() };
+    record_audit(&state, crate::audit::AuditEvent::now(&peer.ip().to_string(), &action, &outcome));
+    response
+}
+
+/// Whether a browser request comes from a launcher page: browsers send `Origin`
+/// with every POST and it must name the host the request went to. Scripts and
+/// the tray send neither header.
+fn same_origin(headers: &HeaderMap) -> bool {
+    let header_text = |name: header::HeaderName| headers.get(name).and_then(|v| v.to_str().ok());
+    match header_text(header::ORIGIN) {
+        Some(origin) => header_text(header::HOST).is_some_and(|host| origin == format!("http://{}", host)),
+        None => !matches!(header_text(header::HeaderName::from_static("sec-fetch-site")), Some("cross-site" | "same-site")),
+    }
+}
+
+/// Whether the request was addressed to this machine by a loopback name, not
+/// through a DNS name that an outside page rebound to 127.0.0.1
+fn loopback_host(headers: &HeaderMap) -> bool {
+    let Some(host) = headers.get(header::HOST).and_then(|v| v.to_str().ok()) else {
+        return true;
+    };
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    matches!(name, "127.0.0.1" | "localhost" | "[::1]")
+}
+
+/// Token from the `X-Launcher-Token` header or the login cookie
+fn request_token(headers: &HeaderMap) -> Option<&str> {
+    if let Some(token) = headers.get("x-launcher-token").and_then(|v| v.to_str().ok()) {
+        return Some(token);
+    }
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == TOKEN_COOKIE)
+        .map(|(_, value)| value)
+}
+
+// Handler: UI login with the stored Superset admin password
+async fn login_handler(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Json(body): Json<LoginRequest>,
+) -> Response {
+    if let Some(wait) = login_locked(&state, peer.ip()) {
+        let error = format!("Слишком много попыток входа, повторите через {} мин", wait.as_secs() / 60 + 1);
+        return (StatusCode::TOO_MANY_REQUESTS, Json(serde_json::json!({"error": error}))).into_response();
+    }
+    let store = state.credentials.clone();
+    let stored = tokio::task::spawn_blocking(move || store.get(credentials::ADMIN_PASSWORD)).await;
+    let error = match stored {
+        Ok(Ok(Some(password))) if credentials::secrets_match(&body.password, &password) => {
+            state.login_failures.lock().unwrap_or_else(|e| e.into_inner()).remove(&peer.ip());
+            let cookie = format!("{}={}; Path=/; HttpOnly; SameSite=Strict", TOKEN_COOKIE, state.api_token);
+            return ([(header::SET_COOKIE, cookie)], Json(serde_json::json!({"status": "ok"}))).into_response();
+        }
+        Ok(Ok(Some(_))) => {
+            let mut failures = state.login_failures.lock().unwrap_or_else(|e| e.into_inner());
+            let entry = failures.entry(peer.ip()).or_insert((0, std::time::Instant::now()));
+            *entry = (entry.0 + 1, std::time::Instant::now());
+            "Неверный пароль".to_string()
+        }
+        Ok(Ok(None)) => "Пароль не задан: superset-launcher user reset-password".to_string(),
+        Ok(Err(e)) => e.to_string(),
+        Err(e) => e.to_string(),
+    };
+    (StatusCode::UNAUTHORIZED, Json(serde_json::json!({"error": error}))).into_response()
+}
+
+/// Time left before `ip` may try to log in again, if it ran out of attempts
+fn login_locked(state: &AppState, ip: IpAddr) -> Option<std::time::Duration> {
+    let mut failures = state.login_failures.lock().unwrap_or_else(|e| e.into_inner());
+    failures.retain(|_, (_, last)| last.elapsed() < LOGIN_LOCKOUT);
+    let (count, last) = failures.get(&ip)?;
+    (*count >= LOGIN_MAX_FAILURES).then(|| LOGIN_LOCKOUT - last.elapsed())
+}
+
+/// Count a usage event in the background
+fn record_usage(state: &AppState, event: UsageEvent<'static>) {
+    let root = state.root.clone();
+    tokio::task::spawn_blocking(move || crate::usage_stats::record(&root, event));
+}
+
+fn record_audit(state: &AppState, event: crate::audit::AuditEvent) {
+    let root = state.root.clone();
+    tokio::task::spawn_blocking(move || crate::drive_watchdog::defer_write(move || crate::audit::append(&root, &event)));
+}
+
+/// Wait for Superset's /health, logging warm-up progress
+async fn wait_superset_ready(port: u16, timeout: std::time::Duration) -> bool {
+    let start = std::time::Instant::now();
+    while start.elapsed() < timeout {
+        if crate::health_check::check_superset(port).await.unwrap_or(false) {
+            info!("✅ Superset ready in {}s", start.elapsed().as_secs());
+            return true;
+        }
+        let elapsed = start.elapsed().as_secs();
+        if elapsed > 0 && elapsed % 10 == 0 {
+            info!("   ...Superset warming up ({}s)", elapsed);
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+    error!("Superset did not become ready within {}s", timeout.as_secs());
+    false
+}
+
 /// Check if a port is in use
 async fn check_port(port: u16) -> bool {
     tokio::net::TcpStream::connect(format!("127.0.0.1:{}", port))
@@ -407,11 +1396,16 @@ async fn kill_process_on_port(port: u16) -> Result<()> {
 }
 
 /// Embedded HTML for launcher UI
-const LAUNCHER_HTML: &str = r#"<!DOCTYPE html>
+const LAUNCHER_HTML: &str = r##"<!DOCTYPE html>
 <html lang="ru">
 <head>
     <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0, viewport-fit=cover">
+    <meta name="theme-color" content="#1a1a2e">
+    <meta name="apple-mobile-web-app-capable" content="yes">
+    <link rel="manifest" href="/manifest.webmanifest">
+    <link rel="icon" href="/icon.svg" type="image/svg+xml">
+    <link rel="apple-touch-icon" href="/icon.svg">
     <title>Apache Superset Portable</title>
     <style>
         * { margin: 0; padding: 0; box-sizing: border-box; }
@@ -450,7 +1444,7 @@ const LAUNCHER_HTML: &str = r#"<!DOCTYPE html>
         
         .services {
             display: grid;
-            grid-template-columns: repeat(auto-fit, minmax(300px, 1fr));
+            grid-template-columns: repeat(auto-fit, minmax(min(100%, 300px), 1fr));
             gap: 24px;
             margin-bottom: 32px;
         }
@@ -542,6 +1536,16 @@ const LAUNCHER_HTML: &str = r#"<!DOCTYPE html>
             cursor: not-allowed;
         }
         
+        .drive-banner {
+            display: none;
+            background: rgba(239, 68, 68, 0.15);
+            border: 1px solid #ef4444;
+            border-radius: 12px;
+            padding: 12px 16px;
+            margin-bottom: 24px;
+            color: #fca5a5;
+        }
+        
         .footer {
             text-align: center;
             color: #666;
@@ -572,15 +1576,58 @@ const LAUNCHER_HTML: &str = r#"<!DOCTYPE html>
             margin-top: 8px;
         }
         .btn-text:hover { color: #888; }
+
+        .search-row { display: flex; gap: 10px; }
+        .drop-zone.dragover { border-color: #3b82f6; background: rgba(59,130,246,0.1); }
+
+        /* Phones: top-aligned layout, bigger touch targets */
+        @media (max-width: 600px) {
+            body { align-items: flex-start; }
+            .container { padding: 12px; padding-top: max(12px, env(safe-area-inset-top)); }
+            .header { margin-bottom: 20px; }
+            .header h1 { font-size: 1.4rem; }
+            .services { gap: 12px; margin-bottom: 16px; }
+            .service-card { padding: 16px; }
+            .service-card:hover { transform: none; }
+            .btn { padding: 14px 12px; font-size: 1rem; }
+            .search-row { flex-direction: column; }
+            .search-row .btn { width: 100% !important; }
+        }
+        
+        .overlay {
+            position: fixed; inset: 0; background: rgba(0,0,0,0.6);
+            display: none; align-items: flex-start; justify-content: center;
+            padding-top: 12vh; z-index: 100;
+        }
+        .overlay.open { display: flex; }
+        .overlay-box {
+            background: #1f1f38; border: 1px solid #333; border-radius: 12px;
+            padding: 20px; width: min(520px, 92vw); max-height: 70vh; overflow-y: auto;
+        }
+        .overlay-box h3 { margin-bottom: 12px; }
+        .shortcut-row { display: flex; gap: 12px; padding: 6px 0; align-items: center; }
+        kbd {
+            background: #2a2a40; border: 1px solid #444; border-radius: 4px;
+            padding: 1px 7px; font-family: monospace; min-width: 24px; text-align: center;
+        }
+        #palette-input {
+            width: 100%; padding: 10px; border-radius: 8px; border: 1px solid #444;
+            background: rgba(0,0,0,0.2); color: white; margin-bottom: 10px;
+        }
+        .palette-item { padding: 8px 10px; border-radius: 6px; cursor: pointer; display: flex; justify-content: space-between; }
+        .palette-item.active, .palette-item:hover { background: rgba(59,130,246,0.25); }
     </style>
 </head>
 <body>
     <div class="container">
         <div class="header">
             <h1>🚀 Apache Superset Portable</h1>
-            <p class="subtitle">Панель управления сервисами</p>
+            <p class="subtitle" data-i18n="subtitle">Панель управления сервисами</p>
         </div>
         
+        <div class="drive-banner" id="drive-banner"></div>
+        <div class="drive-banner" id="crash-banner"><span id="crash-text"></span> <button class="btn-text" onclick="dismissCrash()" data-i18n="crash.dismiss">Скрыть</button></div>
+        
         <div class="services">
             <div class="service-card" id="superset-card">
                 <div class="service-header">
@@ -589,31 +1636,31 @@ const LAUNCHER_HTML: &str = r#"<!DOCTYPE html>
                 </div>
                 <div class="service-port" id="superset-port">Порт: 8088</div>
                 <div class="btn-group">
-                    <button class="btn btn-primary" id="superset-open" onclick="openSuperset()" disabled>Открыть</button>
+                    <button class="btn btn-primary" id="superset-open" onclick="openSuperset()" disabled data-i18n="btn.open">Открыть</button>
                     <button class="btn btn-secondary" id="superset-toggle" onclick="toggleSuperset()">Запустить</button>
                 </div>
             </div>
             
             <div class="service-card" id="lightdocs-card">
                 <div class="service-header">
-                    <span class="service-name">📚 База знаний</span>
+                    <span class="service-name" data-i18n="panel.lightdocs">📚 База знаний</span>
                     <span class="status-badge status-stopped" id="lightdocs-status">Остановлен</span>
                 </div>
                 <div class="service-port" id="lightdocs-port">Порт: 3030</div>
                 <div class="btn-group">
-                    <button class="btn btn-primary" id="lightdocs-open" onclick="openLightdocs()" disabled>Открыть</button>
+                    <button class="btn btn-primary" id="lightdocs-open" onclick="openLightdocs()" disabled data-i18n="btn.open">Открыть</button>
                     <button class="btn btn-secondary" id="lightdocs-toggle" onclick="toggleLightdocs()">Запустить</button>
                 </div>
             </div>
 
             <div class="service-card" id="watcher-card">
                 <div class="service-header">
-                    <span class="service-name">🔄 Авто-обновление</span>
+                    <span class="service-name" data-i18n="panel.watcher">🔄 Авто-обновление</span>
                     <span class="status-badge status-stopped" id="watcher-status">Остановлен</span>
                 </div>
-                <div class="service-port" id="watcher-port">Мониторинг CSV</div>
+                <div class="service-port" id="watcher-port" data-i18n="watcher.hint">Мониторинг CSV</div>
                 <div class="btn-group">
-                    <button class="btn btn-primary" disabled style="opacity: 0.3">Фон</button>
+                    <button class="btn btn-primary" disabled style="opacity: 0.3" data-i18n="btn.background">Фон</button>
                     <button class="btn btn-secondary" id="watcher-toggle" onclick="toggleWatcher()">Запустить</button>
                 </div>
             </div>
@@ -621,18 +1668,91 @@ const LAUNCHER_HTML: &str = r#"<!DOCTYPE html>
         
         <div class="service-card" style="grid-column: 1 / -1;">
             <div class="service-header">
-                <span class="service-name">🧠 База знаний (Поиск)</span>
+                <span class="service-name" data-i18n="panel.search">🧠 Поиск (статьи, дашборды, графики, запросы)</span>
             </div>
-            <div style="display: flex; gap: 10px;">
-                <input type="text" id="search-input" placeholder="Как исправить ошибку..." style="width: 100%; padding: 10px; border-radius: 8px; border: 1px solid #444; background: rgba(0,0,0,0.2); color: white;">
-                <button class="btn btn-primary" onclick="searchDocs()" style="width: auto;">Найти</button>
+            <div class="search-row">
+                <input type="text" id="search-input" data-i18n-placeholder="search.placeholder" placeholder="Выручка, инциденты, как исправить ошибку..." style="width: 100%; padding: 10px; border-radius: 8px; border: 1px solid #444; background: rgba(0,0,0,0.2); color: white;">
+                <button class="btn btn-primary" onclick="searchDocs()" style="width: auto;" data-i18n="search.button">Найти</button>
             </div>
             <div id="search-results" style="margin-top: 15px; max-height: 200px; overflow-y: auto;"></div>
         </div>
         
+        <div class="service-card drop-zone" id="drop-zone" style="margin-bottom: 24px;">
+            <div class="service-header">
+                <span class="service-name" data-i18n="panel.upload">📥 Загрузка данных</span>
+            </div>
+            <div style="color: #888; font-size: 0.9rem;" data-i18n="upload.hint">Перетащите сюда CSV или Excel — таблица появится в базе examples</div>
+            <input type="file" id="upload-input" accept=".csv,.txt,.xlsx,.xls" style="margin-top: 10px; color: #888;" onchange="uploadFile(this.files[0])">
+            <div id="upload-result" style="margin-top: 10px; font-size: 0.9rem;"></div>
+        </div>
+        
+        <details class="service-card" id="health-history-card" style="margin-bottom: 24px;" ontoggle="if (this.open) fetchHealthHistory()">
+            <summary class="service-name" style="cursor: pointer;" data-i18n="panel.health_history">📉 Доступность за 24 часа</summary>
+            <div id="health-history-body" style="margin-top: 12px; font-size: 0.9rem;"></div>
+            <div style="color: #666; font-size: 0.75rem; margin-top: 8px;" data-i18n="health_history.legend">Столбик — 15 минут: зелёный — всё отвечало, жёлтый — были сбои, красный — не отвечал, серый — лаунчер был закрыт. Линия — время ответа.</div>
+        </details>
+        
+        <details class="service-card" id="freshness-card" style="margin-bottom: 24px;" ontoggle="if (this.open) fetchFreshness()">
+            <summary class="service-name" style="cursor: pointer;"><span data-i18n="panel.freshness">🗄️ Свежесть данных</span> <span id="freshness-stale" class="status-badge status-starting" style="display: none;"></span></summary>
+            <div id="freshness-body" style="margin-top: 12px; font-size: 0.9rem;"></div>
+        </details>
+        
+        <div class="service-card" id="reviews-card" style="margin-bottom: 24px; display: none;">
+            <div class="service-header">
+                <span class="service-name" data-i18n="panel.reviews">⏰ Статьи на проверку</span>
+            </div>
+            <div style="color: #888; font-size: 0.9rem;" data-i18n="reviews.hint">Срок проверки (review_due) истёк — обновите статью и дату</div>
+            <div id="reviews-body" style="margin-top: 10px; font-size: 0.9rem;"></div>
+        </div>
+        
+        <div class="service-card" id="alerts-card" style="margin-bottom: 24px; display: none;">
+            <div class="service-header">
+                <span class="service-name" data-i18n="panel.alerts">🔔 Оповещения</span>
+            </div>
+            <div id="alerts-body" style="font-size: 0.9rem;"></div>
+        </div>
+        
+        <details class="service-card" id="schedules-card" style="margin-bottom: 24px;" ontoggle="if (this.open) fetchSchedules()">
+            <summary class="service-name" style="cursor: pointer;" data-i18n="panel.schedules">🗓️ Расписания</summary>
+            <div style="color: #888; font-size: 0.85rem; margin-top: 8px;" data-i18n="schedules.hint">Cron: минута час день месяц день_недели, например 30 1 * * 1-5 — в 01:30 по будням</div>
+            <div id="schedules-body" style="margin-top: 8px; font-size: 0.9rem;"></div>
+        </details>
+        
+        <details class="service-card" id="docs-fs-card" style="margin-bottom: 24px;" ontoggle="if (this.open) fetchDocsFs()">
+            <summary class="service-name" style="cursor: pointer;" data-i18n="panel.docs_fs">🗂️ Файлы базы знаний</summary>
+            <div style="margin-top: 8px;"><button class="btn-text" onclick="docsFsCall('mkdir', { path: prompt(t('docs_fs.new_folder_prompt')) })" data-i18n="docs_fs.new_folder">+ Папка</button></div>
+            <div id="docs-fs-body" style="margin-top: 8px; font-size: 0.9rem;"></div>
+            <div id="docs-fs-trash" style="margin-top: 12px; font-size: 0.85rem;"></div>
+        </details>
+        
+        <details class="service-card" id="usage-card" style="margin-bottom: 24px;" ontoggle="if (this.open) fetchUsage()">
+            <summary class="service-name" style="cursor: pointer;" data-i18n="panel.usage">📈 Статистика использования</summary>
+            <div id="usage-body" style="margin-top: 12px; color: #ccc; font-size: 0.9rem;"></div>
+            <div style="color: #666; font-size: 0.75rem; margin-top: 8px;" data-i18n="usage.local">Хранится только на этом компьютере</div>
+        </details>
+        
         <div class="footer">
-            <p>Работает автономно • <span id="uptime">0:00</span></p>
-            <button class="btn-text" onclick="shutdown()">Выход</button>
+            <p><span data-i18n="footer.offline">Работает автономно</span> • <span id="uptime">0:00</span> • <span data-i18n="footer.backup">Резервная копия</span>: <span id="last-backup">—</span></p>
+            <button class="btn-text" onclick="toggleHelp()" data-i18n="footer.help">Справка (?)</button>
+            <button class="btn-text" onclick="toggleLang()" id="lang-toggle" title="Русский / English">EN</button>
+            <button class="btn-text" onclick="shutdown()" data-i18n="footer.exit">Выход</button>
+        </div>
+    </div>
+    
+    <div class="overlay" id="help-overlay" onclick="if (event.target === this) toggleHelp()">
+        <div class="overlay-box">
+            <h3 data-i18n="help.shortcuts">⌨️ Горячие клавиши</h3>
+            <div id="help-shortcuts"></div>
+            <p style="margin-top: 12px; color: #888; font-size: 0.85rem;">
+                <kbd>Ctrl</kbd>+<kbd>K</kbd> — палитра команд • <a href="/help" style="color: #60a5fa;">полная справка и API</a>
+            </p>
+        </div>
+    </div>
+    
+    <div class="overlay" id="palette-overlay" onclick="if (event.target === this) closePalette()">
+        <div class="overlay-box">
+            <input type="text" id="palette-input" data-i18n-placeholder="palette.placeholder" placeholder="Команда..." autocomplete="off">
+            <div id="palette-list"></div>
         </div>
     </div>
     
@@ -640,6 +1760,68 @@ const LAUNCHER_HTML: &str = r#"<!DOCTYPE html>
         let supersetUrl = 'http://localhost:8088';
         let lightdocsUrl = 'http://localhost:3030';
         
+        // UI strings from /api/i18n; the HTML carries the Russian defaults
+        const LANG_KEY = 'launcher.lang';
+        const CRASH_KEY = 'launcher.crash_seen';
+        let uiStrings = {};
+        let uiLang = 'ru';
+        
+        function t(key) {
+            return uiStrings[key] || key;
+        }
+        
+        async function loadStrings() {
+            const chosen = localStorage.getItem(LANG_KEY);
+            try {
+                const data = await (await fetch('/api/i18n' + (chosen ? '?lang=' + chosen : ''))).json();
+                uiStrings = data.strings;
+                uiLang = data.lang;
+            } catch (e) {
+                return;
+            }
+            document.documentElement.lang = uiLang;
+            document.getElementById('lang-toggle').textContent = uiLang === 'ru' ? 'EN' : 'RU';
+            document.querySelectorAll('[data-i18n]').forEach(el => { el.textContent = t(el.dataset.i18n); });
+            document.querySelectorAll('[data-i18n-placeholder]').forEach(el => { el.placeholder = t(el.dataset.i18nPlaceholder); });
+        }
+        
+        async function toggleLang() {
+            localStorage.setItem(LANG_KEY, uiLang === 'ru' ? 'en' : 'ru');
+            await loadStrings();
+            fetchStatus();
+            fetchFreshness();
+            if (document.getElementById('schedules-card').open) fetchSchedules();
+        }
+        
+        // Service URLs are reported as localhost; on a phone use the launcher's host
+        function forThisHost(url) {
+            return url.replace('localhost', location.hostname);
+        }
+        
+        // Actions from another device ask for the Superset admin password once
+        async function apiFetch(url, opts) {
+            let res = await fetch(url, opts);
+            if (res.status !== 401) return res;
+            const password = prompt(t('login.prompt'));
+            if (!password) return res;
+            const login = await fetch('/api/login', {
+                method: 'POST',
+                headers: { 'Content-Type': 'application/json' },
+                body: JSON.stringify({ password }),
+            });
+            if (!login.ok) {
+                alert((await login.json()).error || t('login.failed'));
+                return res;
+            }
+            return fetch(url, opts);
+        }
+        
+        function dismissCrash() {
+            const banner = document.getElementById('crash-banner');
+            localStorage.setItem(CRASH_KEY, banner.dataset.file);
+            banner.style.display = 'none';
+        }
+        
         async function fetchStatus() {
             try {
                 const res = await fetch('/api/status');
@@ -655,34 +1837,102 @@ const LAUNCHER_HTML: &str = r#"<!DOCTYPE html>
             if (!q) return;
             
             const res = document.getElementById('search-results');
-            res.innerHTML = '<div class="loading">Поиск...</div>';
+            res.innerHTML = `<div class="loading">${t('search.loading')}</div>`;
             
             try {
-                const req = await fetch('/api/lightdocs/search?q=' + encodeURIComponent(q));
+                const req = await fetch('/api/search?limit=20&q=' + encodeURIComponent(q));
                 const results = await req.json();
                 
                 if (results.error) {
-                    res.innerHTML = '<div style="color: red;">Ошибка индекса</div>';
+                    res.innerHTML = `<div style="color: red;">${t('search.index_error')}</div>`;
                     return;
                 }
                 
                 if (results.length === 0) {
-                    res.innerHTML = '<div style="color: #888;">Ничего не найдено</div>';
+                    res.innerHTML = `<div style="color: #888;">${t('search.empty')}</div>`;
                     return;
                 }
                 
-                let html = '';
+                const icons = { doc: '📄', dashboard: '📊', chart: '📈', dataset: '🗃️', query: '🧾' };
+                res.innerHTML = '';
+                // Titles and excerpts come from articles and Superset objects: text only
                 results.forEach(item => {
-                    html += `
-                        <div style="margin-bottom: 10px; padding: 10px; background: rgba(255,255,255,0.05); border-radius: 8px;">
-                            <a href="${lightdocsUrl}/${item.slug}.html" target="_blank" style="color: #60a5fa; text-decoration: none; font-weight: bold;">${item.title}</a>
-                            <div style="color: #ccc; font-size: 0.85rem; margin-top: 5px;">${item.excerpt}</div>
-                        </div>
-                    `;
+                    const anchor = item.sections && item.sections.length ? '#' + encodeURIComponent(item.sections[0].anchor) : '';
+                    const row = document.createElement('div');
+                    row.style.cssText = 'margin-bottom: 10px; padding: 10px; background: rgba(255,255,255,0.05); border-radius: 8px;';
+                    row.append(`${icons[item.kind] || '🔹'} `);
+                    const link = document.createElement('a');
+                    link.href = item.url ? supersetUrl + item.url : `${lightdocsUrl}/${item.slug}.html${anchor}`;
+                    link.target = '_blank';
+                    link.style.cssText = 'color: #60a5fa; text-decoration: none; font-weight: bold;';
+                    link.textContent = item.title;
+                    row.appendChild(link);
+                    if (anchor) {
+                        const section = document.createElement('span');
+                        section.style.color = '#888';
+                        section.textContent = `→ ${item.sections[0].title}`;
+                        row.append(' ', section);
+                    }
+                    const excerpt = document.createElement('div');
+                    excerpt.style.cssText = 'color: #ccc; font-size: 0.85rem; margin-top: 5px;';
+                    excerpt.textContent = item.excerpt;
+                    row.appendChild(excerpt);
+                    res.appendChild(row);
                 });
-                res.innerHTML = html;
             } catch(e) {
-                res.innerHTML = 'Ошибка сети';
+                res.innerHTML = t('network_error');
+            }
+        }
+        
+        async function uploadFile(file) {
+            if (!file) return;
+            const out = document.getElementById('upload-result');
+            out.innerHTML = `<div class="loading">${t('upload.loading')} ${file.name}...</div>`;
+            const form = new FormData();
+            form.append('file', file);
+            try {
+                const data = await (await apiFetch('/api/upload', { method: 'POST', body: form })).json();
+                if (data.error) {
+                    out.style.color = '#ef4444';
+                    out.textContent = data.error;
+                    return;
+                }
+                out.style.color = '';
+                const note = data.renamed ? ` (${t('upload.renamed')} .${data.detected})` : '';
+                out.innerHTML = `<div style="color: #10b981;">✅ ${t('upload.table')} ${data.table}${note}</div>`;
+            } catch (e) {
+                out.innerHTML = t('network_error');
+            }
+        }
+        
+        const dropZone = document.getElementById('drop-zone');
+        dropZone.addEventListener('dragover', e => { e.preventDefault(); dropZone.classList.add('dragover'); });
+        dropZone.addEventListener('dragleave', () => dropZone.classList.remove('dragover'));
+        dropZone.addEventListener('drop', e => {
+            e.preventDefault();
+            dropZone.classList.remove('dragover');
+            uploadFile(e.dataTransfer.files[0]);
+        });
+        
+        async function fetchUsage() {
+            const body = document.getElementById('usage-body');
+            try {
+                const data = await (await fetch('/api/usage')).json();
+                if (data.error) {
+                    body.textContent = t('usage.unavailable');
+                    return;
+                }
+                const starts = data.service_starts.map(([name, n]) => `${name}: ${n}`).join(', ') || '—';
+                const dashboards = data.dashboards.slice(0, 5)
+                    .map(([id, n]) => `<div>📊 ${id} — ${n}</div>`).join('') || '—';
+                body.innerHTML = `
+                    <div>${t('usage.since')} ${data.since || '—'}</div>
+                    <div>${t('usage.starts')}: ${starts}</div>
+                    <div>${t('usage.searches')}: ${data.searches}</div>
+                    <div style="margin-top: 8px;">${t('usage.dashboards')}:</div>${dashboards}
+                `;
+            } catch (e) {
+                body.textContent = t('network_error');
             }
         }
         
@@ -692,25 +1942,28 @@ const LAUNCHER_HTML: &str = r#"<!DOCTYPE html>
             const supersetOpen = document.getElementById('superset-open');
             const supersetToggle = document.getElementById('superset-toggle');
             
-            supersetUrl = data.superset.url;
-            document.getElementById('superset-port').textContent = 'Порт: ' + data.superset.port;
+            supersetUrl = forThisHost(data.superset.url);
+            document.getElementById('superset-port').textContent = t('port') + ': ' + data.superset.port;
             
             if (data.superset.status === 'running') {
                 supersetBadge.className = 'status-badge status-running';
-                supersetBadge.textContent = 'Работает';
+                supersetBadge.textContent = t('status.running');
                 supersetOpen.disabled = false;
-                supersetToggle.textContent = 'Остановить';
+                supersetToggle.textContent = t('btn.stop');
                 supersetToggle.className = 'btn btn-danger';
             } else if (data.superset.status === 'starting') {
                 supersetBadge.className = 'status-badge status-starting loading';
-                supersetBadge.textContent = 'Запуск...';
+                supersetBadge.textContent = data.superset.warmup_seconds != null
+                    ? t('status.warmup') + ' ' + data.superset.warmup_seconds + t('seconds')
+                    : t('status.starting');
                 supersetOpen.disabled = true;
                 supersetToggle.disabled = true;
             } else {
-                supersetBadge.className = 'status-badge status-stopped';
-                supersetBadge.textContent = 'Остановлен';
+                const failed = data.superset.status === 'error';
+                supersetBadge.className = failed ? 'status-badge status-error' : 'status-badge status-stopped';
+                supersetBadge.textContent = failed ? t('status.failed') : t('status.stopped');
                 supersetOpen.disabled = true;
-                supersetToggle.textContent = 'Запустить';
+                supersetToggle.textContent = t('btn.start');
                 supersetToggle.className = 'btn btn-secondary';
                 supersetToggle.disabled = false;
             }
@@ -720,25 +1973,25 @@ const LAUNCHER_HTML: &str = r#"<!DOCTYPE html>
             const lightdocsOpen = document.getElementById('lightdocs-open');
             const lightdocsToggle = document.getElementById('lightdocs-toggle');
             
-            lightdocsUrl = data.lightdocs.url;
-            document.getElementById('lightdocs-port').textContent = 'Порт: ' + data.lightdocs.port;
+            lightdocsUrl = forThisHost(data.lightdocs.url);
+            document.getElementById('lightdocs-port').textContent = t('port') + ': ' + data.lightdocs.port;
             
             if (data.lightdocs.status === 'running') {
                 lightdocsBadge.className = 'status-badge status-running';
-                lightdocsBadge.textContent = 'Работает';
+                lightdocsBadge.textContent = t('status.running');
                 lightdocsOpen.disabled = false;
-                lightdocsToggle.textContent = 'Остановить';
+                lightdocsToggle.textContent = t('btn.stop');
                 lightdocsToggle.className = 'btn btn-danger';
             } else if (data.lightdocs.status === 'starting') {
                 lightdocsBadge.className = 'status-badge status-starting loading';
-                lightdocsBadge.textContent = 'Запуск...';
+                lightdocsBadge.textContent = t('status.starting');
                 lightdocsOpen.disabled = true;
                 lightdocsToggle.disabled = true;
             } else {
                 lightdocsBadge.className = 'status-badge status-stopped';
-                lightdocsBadge.textContent = 'Остановлен';
+                lightdocsBadge.textContent = t('status.stopped');
                 lightdocsOpen.disabled = true;
-                lightdocsToggle.textContent = 'Запустить';
+                lightdocsToggle.textContent = t('btn.start');
                 lightdocsToggle.className = 'btn btn-secondary';
                 lightdocsToggle.disabled = false;
             }
@@ -749,20 +2002,40 @@ const LAUNCHER_HTML: &str = r#"<!DOCTYPE html>
             
             if (data.watcher.status === 'running') {
                 watcherBadge.className = 'status-badge status-running';
-                watcherBadge.textContent = 'Активен';
-                watcherToggle.textContent = 'Отключить';
+                watcherBadge.textContent = t('status.active');
+                watcherToggle.textContent = t('btn.disable');
                 watcherToggle.className = 'btn btn-danger';
             } else {
                 watcherBadge.className = 'status-badge status-stopped';
-                watcherBadge.textContent = 'Остановлен';
-                watcherToggle.textContent = 'Включить';
+                watcherBadge.textContent = t('status.stopped');
+                watcherToggle.textContent = t('btn.enable');
                 watcherToggle.className = 'btn btn-secondary';
             }
             
+            // Flash drive watchdog
+            const banner = document.getElementById('drive-banner');
+            banner.style.display = data.drive_lost_seconds != null ? 'block' : 'none';
+            if (data.drive_lost_seconds != null) {
+                banner.textContent = `⚠️ ${t('drive.lost')} (${data.drive_lost_seconds}${t('seconds')})`;
+            }
+            
+            // Crash report of the last day, until dismissed
+            const crash = data.last_crash;
+            const crashShown = crash && localStorage.getItem(CRASH_KEY) !== crash.file;
+            document.getElementById('crash-banner').style.display = crashShown ? 'block' : 'none';
+            if (crashShown) {
+                document.getElementById('crash-text').textContent =
+                    `💥 ${t('crash.happened')} ${new Date(crash.at).toLocaleString(uiLang)}: ${crash.message}. ${t('crash.report')} logs/${crash.file}`;
+                document.getElementById('crash-banner').dataset.file = crash.file;
+            }
+            
             // Uptime
             const mins = Math.floor(data.uptime_seconds / 60);
             const secs = data.uptime_seconds % 60;
             document.getElementById('uptime').textContent = mins + ':' + String(secs).padStart(2, '0');
+            
+            // Last backup
+            document.getElementById('last-backup').textContent = data.last_backup || t('footer.no_backup');
         }
         
         async function toggleSuperset() {
@@ -770,9 +2043,9 @@ const LAUNCHER_HTML: &str = r#"<!DOCTYPE html>
             const isRunning = badge.classList.contains('status-running');
             
             if (isRunning) {
-                await fetch('/api/superset/stop', { method: 'POST' });
+                await apiFetch('/api/superset/stop', { method: 'POST' });
             } else {
-                await fetch('/api/superset/start', { method: 'POST' });
+                await apiFetch('/api/superset/start', { method: 'POST' });
             }
             setTimeout(fetchStatus, 500);
         }
@@ -782,9 +2055,9 @@ const LAUNCHER_HTML: &str = r#"<!DOCTYPE html>
             const isRunning = badge.classList.contains('status-running');
             
             if (isRunning) {
-                await fetch('/api/lightdocs/stop', { method: 'POST' });
+                await apiFetch('/api/lightdocs/stop', { method: 'POST' });
             } else {
-                await fetch('/api/lightdocs/start', { method: 'POST' });
+                await apiFetch('/api/lightdocs/start', { method: 'POST' });
             }
             setTimeout(fetchStatus, 500);
         }
@@ -794,9 +2067,9 @@ const LAUNCHER_HTML: &str = r#"<!DOCTYPE html>
             const isRunning = badge.classList.contains('status-running');
             
             if (isRunning) {
-                await fetch('/api/watcher/stop', { method: 'POST' });
+                await apiFetch('/api/watcher/stop', { method: 'POST' });
             } else {
-                await fetch('/api/watcher/start', { method: 'POST' });
+                await apiFetch('/api/watcher/start', { method: 'POST' });
             }
             setTimeout(fetchStatus, 500);
         }
@@ -810,20 +2083,617 @@ const LAUNCHER_HTML: &str = r#"<!DOCTYPE html>
         }
 
         async function shutdown() {
-            if (confirm('Выключить все сервисы и закрыть лаунчер?')) {
+            if (confirm(t('shutdown.confirm'))) {
                 try {
-                    await fetch('/api/shutdown', { method: 'POST' });
-                    document.body.innerHTML = '<div style="color:white;text-align:center"><h1>Лаунчер остановлен</h1><p>Можно закрыть вкладку</p></div>';
+                    await apiFetch('/api/shutdown', { method: 'POST' });
+                    document.body.innerHTML = `<div style="color:white;text-align:center"><h1>${t('shutdown.done')}</h1><p>${t('shutdown.close_tab')}</p></div>`;
                 } catch (e) {
-                    alert('Ошибка остановки');
+                    alert(t('shutdown.failed'));
+                }
+            }
+        }
+        
+        async function fetchFreshness() {
+            let tables = [];
+            try {
+                tables = await (await fetch('/api/data/freshness')).json();
+            } catch (e) {
+                return;
+            }
+            const body = document.getElementById('freshness-body');
+            const badge = document.getElementById('freshness-stale');
+            if (tables.error) {
+                body.textContent = t('freshness.no_data') + ': ' + tables.error;
+                return;
+            }
+            let validations = [];
+            try {
+                validations = await (await fetch('/api/data/validations')).json();
+            } catch (e) {}
+            const validationOf = Array.isArray(validations) ? Object.fromEntries(validations.map(v => [v.table, v])) : {};
+            const stale = tables.filter(table => table.stale).length;
+            badge.style.display = stale ? '' : 'none';
+            badge.textContent = `⚠️ ${t('freshness.stale_count')}: ${stale}`;
+            body.innerHTML = '';
+            tables.forEach(table => {
+                const row = document.createElement('div');
+                row.style.padding = '4px 0';
+                const when = table.updated_at ? new Date(table.updated_at).toLocaleString(uiLang) : t('freshness.unknown');
+                row.textContent = `${table.table} — ${table.rows.toLocaleString(uiLang)} ${t('freshness.rows')}, ${t('freshness.loaded')}: ${when}`
+                    + (table.source ? ` (${table.source})` : '');
+                if (table.stale) {
+                    const warn = document.createElement('span');
+                    warn.className = 'status-badge status-starting';
+                    warn.style.marginLeft = '8px';
+                    warn.textContent = '⚠️ ' + t('freshness.stale');
+                    row.appendChild(warn);
+                }
+                const validation = validationOf[table.table];
+                if (validation && validation.failures.length) {
+                    row.appendChild(validationDetails(validation));
                 }
+                body.appendChild(row);
+            });
+        }
+        
+        // Failed rules of validations.yaml with a few offending rows each
+        function validationDetails(validation) {
+            const details = document.createElement('details');
+            const summary = document.createElement('summary');
+            summary.className = 'status-badge ' + (validation.on_failure === 'block' ? 'status-error' : 'status-starting');
+            summary.style.cursor = 'pointer';
+            summary.textContent = `${validation.on_failure === 'block' ? '⛔' : '⚠️'} ${t('validation.failed')}: ${validation.failures.length}/${validation.rules}`;
+            details.appendChild(summary);
+            validation.failures.forEach(failure => {
+                const item = document.createElement('div');
+                item.style.margin = '6px 0 0 12px';
+                item.textContent = `${t('validation.rule.' + failure.rule)} «${failure.column}» (${failure.expected}): `
+                    + (failure.error ? failure.error : `${failure.rows.toLocaleString(uiLang)} ${t('freshness.rows')}`);
+                if (failure.samples.length) {
+                    const sample = document.createElement('pre');
+                    sample.style.cssText = 'font-size: 0.8rem; white-space: pre-wrap; opacity: 0.8; margin: 4px 0;';
+                    sample.textContent = failure.samples.map(row => JSON.stringify(row)).join('\n');
+                    item.appendChild(sample);
+                }
+                details.appendChild(item);
+            });
+            return details;
+        }
+        
+        // One SVG per service: uptime bars with the mean latency drawn over them
+        function healthSparkline(buckets) {
+            const w = 4, h = 40, ns = 'http://www.w3.org/2000/svg';
+            const svg = document.createElementNS(ns, 'svg');
+            svg.setAttribute('width', '100%');
+            svg.setAttribute('viewBox', `0 0 ${buckets.length * w} ${h}`);
+            svg.setAttribute('preserveAspectRatio', 'none');
+            svg.style.height = h + 'px';
+            const maxMs = Math.max(1, ...buckets.map(b => b.max_ms || 0));
+            const points = [];
+            buckets.forEach((bucket, i) => {
+                const bar = document.createElementNS(ns, 'rect');
+                const uptime = bucket.checks ? bucket.up / bucket.checks : null;
+                bar.setAttribute('x', i * w);
+                bar.setAttribute('y', 0);
+                bar.setAttribute('width', w - 1);
+                bar.setAttribute('height', h);
+                bar.setAttribute('fill', uptime === null ? '#333' : uptime === 1 ? '#166534' : uptime > 0 ? '#a16207' : '#991b1b');
+                const title = document.createElementNS(ns, 'title');
+                title.textContent = new Date(bucket.start).toLocaleTimeString(uiLang, { hour: '2-digit', minute: '2-digit' })
+                    + (uptime === null ? ` — ${t('health_history.no_checks')}`
+                        : ` — ${t('health_history.uptime')} ${Math.round(uptime * 100)}%`
+                            + (bucket.avg_ms !== null ? `, ${t('health_history.latency')} ${bucket.avg_ms} ${t('health_history.ms')}` : ''));
+                bar.appendChild(title);
+                svg.appendChild(bar);
+                if (bucket.avg_ms !== null) {
+                    points.push(`${i * w + w / 2},${h - 2 - (h - 4) * bucket.avg_ms / maxMs}`);
+                }
+            });
+            if (points.length > 1) {
+                const line = document.createElementNS(ns, 'polyline');
+                line.setAttribute('points', points.join(' '));
+                line.setAttribute('fill', 'none');
+                line.setAttribute('stroke', '#60a5fa');
+                line.setAttribute('stroke-width', '1.5');
+                line.setAttribute('pointer-events', 'none');
+                svg.appendChild(line);
             }
+            return svg;
         }
         
-        // Poll status every 2 seconds
-        setInterval(fetchStatus, 2000);
-        fetchStatus();
+        async function fetchHealthHistory() {
+            let history;
+            try {
+                history = await (await fetch('/api/health/history')).json();
+            } catch (e) {
+                return;
+            }
+            const body = document.getElementById('health-history-body');
+            if (history.error) {
+                body.textContent = t('health_history.unavailable') + ': ' + history.error;
+                return;
+            }
+            body.innerHTML = '';
+            Object.entries(history.services).forEach(([service, buckets]) => {
+                const checks = buckets.reduce((sum, b) => sum + b.checks, 0);
+                const up = buckets.reduce((sum, b) => sum + b.up, 0);
+                const label = document.createElement('div');
+                label.style.margin = '8px 0 4px';
+                label.textContent = `${t('panel.' + service)} — ` + (checks
+                    ? `${t('health_history.uptime')} ${(100 * up / checks).toFixed(1)}%`
+                    : t('health_history.no_checks'));
+                body.appendChild(label);
+                body.appendChild(healthSparkline(buckets));
+            });
+        }
+        
+        setInterval(() => {
+            if (document.getElementById('health-history-card').open) fetchHealthHistory();
+        }, 60000);
+        
+        function showFreshness() {
+            const card = document.getElementById('freshness-card');
+            card.open = true;
+            card.scrollIntoView({ behavior: 'smooth' });
+        }
+        
+        setInterval(fetchFreshness, 60000);
+        
+        async function fetchAlerts() {
+            let alerts = [];
+            try {
+                alerts = await (await fetch('/api/alerts')).json();
+            } catch (e) {
+                return;
+            }
+            const card = document.getElementById('alerts-card');
+            card.style.display = alerts.length ? '' : 'none';
+            const body = document.getElementById('alerts-body');
+            body.innerHTML = '';
+            alerts.forEach(a => {
+                const row = document.createElement('div');
+                row.style.padding = '4px 0';
+                const icon = a.error ? '⚠️' : (a.triggered ? '🔴' : '🟢');
+                const value = a.value === null ? '—' : a.value.toFixed(2);
+                const time = new Date(a.checked_at).toLocaleTimeString();
+                row.textContent = `${icon} ${a.name}: ${a.error || value + ' (' + a.condition + ')'} • ${time}`;
+                if (a.triggered) row.style.color = '#f87171';
+                body.appendChild(row);
+            });
+        }
+        
+        function showAlerts() {
+            fetchAlerts();
+            document.getElementById('alerts-card').scrollIntoView({ behavior: 'smooth' });
+        }
+        
+        setInterval(fetchAlerts, 30000);
+        fetchAlerts();
+        
+        // Backup and alert schedules (schedules.json)
+        function formatRun(at) {
+            return new Date(at).toLocaleString(uiLang, { weekday: 'short', day: 'numeric', month: 'short', hour: '2-digit', minute: '2-digit' });
+        }
+        
+        async function fetchSchedules() {
+            let jobs = [];
+            try {
+                jobs = await (await fetch('/api/schedules?lang=' + uiLang)).json();
+            } catch (e) {
+                return;
+            }
+            const body = document.getElementById('schedules-body');
+            body.innerHTML = '';
+            if (jobs.error) {
+                body.textContent = jobs.error;
+                return;
+            }
+            jobs.forEach(job => body.appendChild(scheduleRow(job)));
+        }
+        
+        function scheduleRow(job) {
+            const row = document.createElement('div');
+            row.style.cssText = 'padding: 8px 0; border-bottom: 1px solid #333;';
+            
+            const head = document.createElement('label');
+            const toggle = document.createElement('input');
+            toggle.type = 'checkbox';
+            toggle.checked = job.enabled;
+            // Alerts every N minutes have no cron form to keep when switched off
+            toggle.disabled = !job.cron;
+            toggle.title = job.cron ? '' : t('schedules.set_first');
+            toggle.onchange = () => saveSchedule(job.job, job.cron, toggle.checked);
+            const title = document.createElement('strong');
+            title.textContent = ' ' + job.title;
+            head.append(toggle, title);
+            
+            const edit = document.createElement('button');
+            edit.className = 'btn-text';
+            edit.textContent = t('schedules.edit');
+            
+            const words = document.createElement('div');
+            words.style.color = job.enabled ? '#ccc' : '#666';
+            words.textContent = job.description + (job.cron ? ` (${job.cron})` : '') + (job.custom ? '' : ` • ${t('schedules.builtin')}`);
+            const next = document.createElement('div');
+            next.style.cssText = 'color: #888; font-size: 0.8rem;';
+            if (job.enabled && job.next.length) next.textContent = `${t('schedules.next')}: ${job.next.map(formatRun).join(' • ')}`;
+            
+            const editor = document.createElement('div');
+            editor.style.cssText = 'display: none; margin-top: 6px;';
+            const input = document.createElement('input');
+            input.type = 'text';
+            input.value = job.cron || '';
+            input.placeholder = '0 3 * * *';
+            input.style.cssText = 'font-family: monospace; width: 12em; padding: 4px; border-radius: 4px; border: 1px solid #444; background: rgba(0,0,0,0.2); color: white;';
+            const preview = document.createElement('div');
+            preview.style.cssText = 'font-size: 0.8rem; margin-top: 4px;';
+            let typing;
+            input.oninput = () => {
+                clearTimeout(typing);
+                typing = setTimeout(() => previewCron(input.value, preview), 300);
+            };
+            const save = document.createElement('button');
+            save.className = 'btn-text';
+            save.textContent = t('schedules.save');
+            save.onclick = () => saveSchedule(job.job, input.value, true);
+            editor.append(input, ' ', save);
+            if (job.custom) {
+                const reset = document.createElement('button');
+                reset.className = 'btn-text';
+                reset.textContent = t('schedules.reset');
+                reset.onclick = () => saveSchedule(job.job, null, true);
+                editor.append(reset);
+            }
+            editor.append(preview);
+            edit.onclick = () => {
+                editor.style.display = editor.style.display === 'none' ? '' : 'none';
+                if (input.value) previewCron(input.value, preview);
+            };
+            
+            row.append(head, ' ', edit, words, next, editor);
+            return row;
+        }
+        
+        async function previewCron(cron, target) {
+            if (!cron.trim()) {
+                target.textContent = '';
+                return;
+            }
+            try {
+                const data = await (await fetch(`/api/schedules/preview?lang=${uiLang}&cron=${encodeURIComponent(cron)}`)).json();
+                target.style.color = data.error ? '#f87171' : '#8b949e';
+                target.textContent = data.error || `${data.description} • ${t('schedules.next')}: ${data.next.map(formatRun).join(' • ') || '—'}`;
+            } catch (e) {
+                target.textContent = t('network_error');
+            }
+        }
+        
+        async function saveSchedule(job, cron, enabled) {
+            try {
+                const res = await apiFetch('/api/schedules', {
+                    method: 'POST',
+                    headers: { 'Content-Type': 'application/json' },
+                    body: JSON.stringify({ job, cron, enabled }),
+                });
+                const data = await res.json();
+                if (data.error) alert(data.error);
+            } catch (e) {
+                alert(t('network_error'));
+            }
+            fetchSchedules();
+        }
+        
+        // Knowledge folder: files, folders and the trash
+        async function fetchDocsFs() {
+            let tree = {};
+            try {
+                tree = await (await apiFetch('/api/docs/fs/tree')).json();
+            } catch (e) {
+                return;
+            }
+            const body = document.getElementById('docs-fs-body');
+            const trash = document.getElementById('docs-fs-trash');
+            body.innerHTML = '';
+            trash.innerHTML = '';
+            if (tree.error) {
+                body.textContent = tree.error;
+                return;
+            }
+            if (!tree.entries.length) body.textContent = t('docs_fs.empty');
+            tree.entries.forEach(entry => body.appendChild(docsFsRow(entry)));
+            if (tree.trash.length) {
+                const title = document.createElement('div');
+                title.style.color = '#888';
+                title.textContent = t('docs_fs.trash');
+                trash.appendChild(title);
+            }
+            tree.trash.forEach(item => {
+                const row = document.createElement('div');
+                row.style.cssText = 'padding: 2px 0; color: #888;';
+                const restore = document.createElement('button');
+                restore.className = 'btn-text';
+                restore.textContent = t('docs_fs.restore');
+                restore.onclick = () => docsFsCall('restore', { path: item.id });
+                row.append(`${item.path} • ${new Date(item.deleted_at).toLocaleString(uiLang)} `, restore);
+                trash.appendChild(row);
+            });
+        }
+        
+        function docsFsRow(entry) {
+            const parts = entry.path.split('/');
+            const name = parts[parts.length - 1];
+            const row = document.createElement('div');
+            row.style.cssText = `padding: 3px 0 3px ${(parts.length - 1) * 18}px; border-bottom: 1px solid #333;`;
+            const label = document.createElement('span');
+            label.textContent = (entry.dir ? '📁 ' : '📄 ') + name;
+            if (!entry.dir) label.title = `${Math.ceil(entry.size / 1024)} KB`;
+            const rename = document.createElement('button');
+            rename.className = 'btn-text';
+            rename.textContent = t('docs_fs.rename');
+            rename.onclick = () => {
+                const to = prompt(t('docs_fs.rename_prompt'), name);
+                if (to && to !== name) docsFsCall('rename', { path: entry.path, name: to });
+            };
+            const move = document.createElement('button');
+            move.className = 'btn-text';
+            move.textContent = t('docs_fs.move');
+            move.onclick = () => {
+                const to = prompt(t('docs_fs.move_prompt'), parts.slice(0, -1).join('/'));
+                if (to !== null) docsFsCall('move', { path: entry.path, to });
+            };
+            const remove = document.createElement('button');
+            remove.className = 'btn-text';
+            remove.textContent = t('docs_fs.delete');
+            remove.onclick = () => {
+                if (confirm(`${t('docs_fs.delete_confirm')} ${entry.path}?`)) docsFsCall('delete', { path: entry.path });
+            };
+            row.append(label, ' ', rename, move, remove);
+            return row;
+        }
+        
+        async function docsFsCall(op, request) {
+            if (!request.path) return;
+            try {
+                const res = await apiFetch('/api/docs/fs/' + op, {
+                    method: 'POST',
+                    headers: { 'Content-Type': 'application/json' },
+                    body: JSON.stringify(request),
+                });
+                const data = await res.json();
+                if (data.error) alert(data.error);
+            } catch (e) {
+                alert(t('network_error'));
+            }
+            fetchDocsFs();
+        }
+        
+        // Knowledge base articles past their review_due date
+        async function fetchReviews() {
+            let stats = {};
+            try {
+                stats = await (await fetch('/api/lightdocs/stats')).json();
+            } catch (e) {
+                return;
+            }
+            const overdue = stats.overdue_reviews || [];
+            document.getElementById('reviews-card').style.display = overdue.length ? '' : 'none';
+            const body = document.getElementById('reviews-body');
+            body.innerHTML = '';
+            overdue.forEach(review => {
+                const row = document.createElement('div');
+                row.style.padding = '4px 0';
+                const link = document.createElement('a');
+                link.href = `${lightdocsUrl}/${review.slug}.html`;
+                link.target = '_blank';
+                link.style.color = '#58a6ff';
+                link.textContent = review.title;
+                row.appendChild(link);
+                row.append(` — ${t('reviews.due')} ${new Date(review.review_due).toLocaleDateString(uiLang)}`);
+                body.appendChild(row);
+            });
+        }
+        
+        fetchReviews();
+        
+        // Keyboard shortcuts, cheat sheet and command palette share /api/actions
+        let uiActions = [];
+        let paletteMatches = [];
+        let paletteIndex = 0;
+        
+        function focusSearch() {
+            document.getElementById('search-input').focus();
+        }
+        
+        function pickUpload() {
+            document.getElementById('upload-input').click();
+        }
+        
+        function showUsage() {
+            const card = document.getElementById('usage-card');
+            card.open = true;
+            card.scrollIntoView({ behavior: 'smooth' });
+        }
+        
+        function runAction(action) {
+            const fn = window[action.js];
+            if (typeof fn === 'function') fn();
+        }
+        
+        function toggleHelp() {
+            document.getElementById('help-overlay').classList.toggle('open');
+        }
+        
+        function openPalette() {
+            document.getElementById('palette-overlay').classList.add('open');
+            const input = document.getElementById('palette-input');
+            input.value = '';
+            renderPalette('');
+            input.focus();
+        }
+        
+        function closePalette() {
+            document.getElementById('palette-overlay').classList.remove('open');
+        }
+        
+        function renderPalette(query) {
+            const q = query.trim().toLowerCase();
+            paletteMatches = uiActions.filter(a =>
+                !q || a.title.toLowerCase().includes(q) || a.panel.toLowerCase().includes(q) || a.id.includes(q));
+            paletteIndex = 0;
+            drawPalette();
+        }
+        
+        function drawPalette() {
+            const list = document.getElementById('palette-list');
+            list.innerHTML = '';
+            paletteMatches.forEach((a, i) => {
+                const item = document.createElement('div');
+                item.className = 'palette-item' + (i === paletteIndex ? ' active' : '');
+                const title = document.createElement('span');
+                title.textContent = a.title;
+                item.appendChild(title);
+                if (a.shortcut) {
+                    const key = document.createElement('kbd');
+                    key.textContent = a.shortcut;
+                    item.appendChild(key);
+                }
+                item.onclick = () => { closePalette(); runAction(a); };
+                list.appendChild(item);
+            });
+        }
+        
+        async function loadActions() {
+            try {
+                uiActions = (await (await fetch('/api/actions')).json()).actions;
+            } catch (e) {
+                return;
+            }
+            const box = document.getElementById('help-shortcuts');
+            uiActions.filter(a => a.shortcut).forEach(a => {
+                const row = document.createElement('div');
+                row.className = 'shortcut-row';
+                const key = document.createElement('kbd');
+                key.textContent = a.shortcut;
+                const title = document.createElement('span');
+                title.textContent = a.title;
+                row.append(key, title);
+                box.appendChild(row);
+            });
+        }
+        
+        document.getElementById('palette-input').addEventListener('input', e => renderPalette(e.target.value));
+        
+        document.addEventListener('keydown', e => {
+            const paletteOpen = document.getElementById('palette-overlay').classList.contains('open');
+            if ((e.ctrlKey || e.metaKey) && e.key.toLowerCase() === 'k') {
+                e.preventDefault();
+                paletteOpen ? closePalette() : openPalette();
+                return;
+            }
+            if (paletteOpen) {
+                if (e.key === 'Escape') closePalette();
+                if (e.key === 'ArrowDown' || e.key === 'ArrowUp') {
+                    e.preventDefault();
+                    const step = e.key === 'ArrowDown' ? 1 : -1;
+                    paletteIndex = (paletteIndex + step + paletteMatches.length) % Math.max(paletteMatches.length, 1);
+                    drawPalette();
+                }
+                if (e.key === 'Enter' && paletteMatches[paletteIndex]) {
+                    closePalette();
+                    runAction(paletteMatches[paletteIndex]);
+                }
+                return;
+            }
+            if (e.key === 'Escape') {
+                document.getElementById('help-overlay').classList.remove('open');
+                return;
+            }
+            // Typing in a field never triggers shortcuts
+            if (e.ctrlKey || e.metaKey || e.altKey || ['INPUT', 'TEXTAREA', 'SELECT'].includes(e.target.tagName)) return;
+            const action = uiActions.find(a => a.shortcut === e.key);
+            if (action) {
+                e.preventDefault();
+                runAction(action);
+            }
+        });
+        
+        document.getElementById('search-input').addEventListener('keydown', e => {
+            if (e.key === 'Enter') searchDocs();
+        });
+        
+        loadActions();
+        
+        // Poll status every 2 seconds, once the strings are in
+        loadStrings().then(() => {
+            setInterval(fetchStatus, 2000);
+            fetchStatus();
+            fetchFreshness();
+        });
+        
+        // Offline shell (browsers allow service workers on localhost/HTTPS only)
+        if ('serviceWorker' in navigator) {
+            navigator.serviceWorker.register('/sw.js').catch(() => {});
+        }
     </script>
 </body>
 </html>
+"##;
+
+/// Web app manifest (installable on phones)
+const MANIFEST_JSON: &str = r##"{
+  "name": "Apache Superset Portable",
+  "short_name": "Superset",
+  "description": "Панель управления сервисами",
+  "start_url": "/",
+  "scope": "/",
+  "display": "standalone",
+  "background_color": "#1a1a2e",
+  "theme_color": "#1a1a2e",
+  "lang": "ru",
+  "icons": [
+    { "src": "/icon.svg", "sizes": "any", "type": "image/svg+xml", "purpose": "any maskable" }
+  ]
+}
+"##;
+
+/// Service worker: network-first for the page shell, API calls never cached
+const SERVICE_WORKER_JS: &str = r#"const CACHE = 'launcher-v1';
+const SHELL = ['/', '/manifest.webmanifest', '/icon.svg'];
+
+self.addEventListener('install', event => {
+    event.waitUntil(caches.open(CACHE).then(cache => cache.addAll(SHELL)));
+    self.skipWaiting();
+});
+
+self.addEventListener('activate', event => {
+    event.waitUntil(
+        caches.keys().then(keys => Promise.all(keys.filter(k => k !== CACHE).map(k => caches.delete(k))))
+    );
+    self.clients.claim();
+});
+
+self.addEventListener('fetch', event => {
+    const url = new URL(event.request.url);
+    if (event.request.method !== 'GET' || url.pathname.startsWith('/api/')) {
+        return;
+    }
+    event.respondWith(
+        fetch(event.request)
+            .then(response => {
+                const copy = response.clone();
+                caches.open(CACHE).then(cache => cache.put(event.request, copy));
+                return response;
+            })
+            .catch(() => caches.match(event.request))
+    );
+});
 "#;
+
+/// App icon
+const ICON_SVG: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 512 512">
+  <rect width="512" height="512" rx="96" fill="#1a1a2e"/>
+  <rect x="112" y="272" width="64" height="128" rx="12" fill="#3b82f6"/>
+  <rect x="224" y="192" width="64" height="208" rx="12" fill="#10b981"/>
+  <rect x="336" y="112" width="64" height="288" rx="12" fill="#e94560"/>
+</svg>
+"##;
@@ -0,0 +1,105 @@
+//! Launcher library: every module of `superset-launcher`, shared with the
+//! `create_dashboard` binary so both run the same code
+
+pub mod alerts;
+pub mod asset_cache;
+pub mod audit;
+pub mod auto_dashboard;
+pub mod autologin;
+pub mod av_warmup;
+pub mod backup;
+pub mod config;
+pub mod control;
+pub mod cache;
+pub mod cache_crypto;
+pub mod chart_lint;
+pub mod completions;
+pub mod crash_report;
+pub mod credentials;
+pub mod databases;
+pub mod dates;
+pub mod demo_data;
+pub mod docs_build;
+pub mod docs_fs;
+pub mod docs_server;
+pub mod doctor;
+pub mod drive_watchdog;
+pub mod encoding;
+pub mod feature_flags;
+pub mod freshness;
+pub mod gateway;
+pub mod gateway_spool;
+pub mod hardware;
+pub mod headless;
+pub mod health_check;
+pub mod health_history;
+pub mod hot_assets;
+pub mod html;
+pub mod kpi_charts;
+pub mod large_files;
+pub mod launcher_ui;
+pub mod lightdocs;
+pub mod log_shipping;
+pub mod metadata;
+pub mod metadata_diff;
+pub mod notifications;
+pub mod pack_analyze;
+pub mod pack_staging;
+pub mod packer;
+pub mod partitions;
+pub mod python;
+pub mod query_export;
+pub mod query_limits;
+pub mod replica;
+pub mod request_id;
+pub mod safe_path;
+pub mod secret_key;
+pub mod saved_queries;
+pub mod schedules;
+pub mod security_headers;
+pub mod self_docs;
+pub mod sled_journal;
+pub mod slo;
+pub mod smoke;
+pub mod snapshots;
+pub mod stack;
+pub mod superset;
+pub mod superset_search;
+pub mod timing;
+pub mod translations;
+pub mod tray;
+pub mod ui_actions;
+pub mod ui_strings;
+pub mod usage_stats;
+pub mod unpack;
+pub mod updater;
+pub mod upstream_boot;
+pub mod upstream_limit;
+pub mod upload;
+pub mod validations;
+pub mod validator;
+pub mod data_loader;
+pub mod watcher;
+
+use anyhow::Result;
+use std::path::PathBuf;
+use tracing::info;
+
+/// Get the portable root directory (where the exe is located)
+pub fn get_portable_root() -> Result<PathBuf> {
+    let exe_path = std::env::current_exe()?;
+    let root = exe_path.parent()
+        .ok_or_else(|| anyhow::anyhow!("Cannot determine executable directory"))?;
+
+    // Check if we are running in development (cargo run)
+    // The executable is in target/debug, but assets are in project root
+    if !root.join("python").exists() {
+        let cwd = std::env::current_dir()?;
+        if cwd.join("python").exists() || cwd.join("Cargo.toml").exists() {
+            info!("Development mode detected, using CWD as root: {}", cwd.display());
+            return Ok(cwd);
+        }
+    }
+
+    Ok(root.to_path_buf())
+}
@@ -1,6 +1,8 @@
 //! Document model with YAML frontmatter support
 
-use std::path::{Path, PathBuf};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
 use anyhow::{Result, Context};
 use serde::{Deserialize, Serialize};
 use chrono::NaiveDate;
@@ -31,18 +33,78 @@ pub struct Frontmatter {
     pub updated: Option<NaiveDate>,
     #[serde(default)]
     pub aliases: Vec<String>,
+    /// Stable slug that survives renames and moves (e.g. `guides/faq`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slug: Option<String>,
+    /// `toc: false` hides the page's table of contents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub toc: Option<bool>,
+    /// `pinned: true` lists the page at the top of the index
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    /// Position in the index when sorting is `manual` (smaller first)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<i64>,
+    /// Date by which the page must be reviewed again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review_due: Option<NaiveDate>,
+}
+
+/// Order of the documents in the index (`sort` in lightdocs.json).
+/// Pinned documents always come first, sorted the same way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentSort {
+    /// Alphabetically by title
+    #[default]
+    Title,
+    /// Recently updated (or created) first
+    Updated,
+    /// By frontmatter `order:`, pages without it last
+    Manual,
+}
+
+/// Extra read-only document root (`sources` in lightdocs.json), e.g. a
+/// departmental wiki on a mapped network drive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocSource {
+    /// Folder its pages appear under in the site (`hr` → `hr/policy.html`)
+    pub name: String,
+    /// Folder with the markdown files, e.g. `Z:\wiki`
+    pub path: PathBuf,
+    /// Badge text on its pages (default: `name`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl DocSource {
+    /// Badge text on the source's pages
+    pub fn label(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.name)
+    }
 }
 
 /// A document in the knowledge base
 #[derive(Debug, Clone)]
 pub struct Document {
     pub path: PathBuf,
+    /// Path relative to the docs root (used for folder-aware slugs)
+    pub rel_path: PathBuf,
+    /// Slug from frontmatter, overrides the path-based one
+    pub slug_override: Option<String>,
     pub title: String,
     pub status: DocumentStatus,
     pub tags: Vec<String>,
     pub created: Option<NaiveDate>,
     pub updated: Option<NaiveDate>,
     pub aliases: Vec<String>,
+    /// Show the in-page table of contents
+    pub toc: bool,
+    pub pinned: bool,
+    pub order: Option<i64>,
+    pub review_due: Option<NaiveDate>,
+    /// Extra root the page comes from (`None`: the local knowledge base)
+    pub source: Option<DocSource>,
     pub content: String,
     pub raw_content: String,
 }
@@ -57,17 +119,43 @@ impl Document {
         
         Ok(Self {
             path: path.to_path_buf(),
+            rel_path: PathBuf::from(path.file_name().unwrap_or_default()),
+            slug_override: frontmatter.slug,
             title: frontmatter.title,
             status: frontmatter.status,
             tags: frontmatter.tags,
             created: frontmatter.created,
             updated: frontmatter.updated,
             aliases: frontmatter.aliases,
+            toc: frontmatter.toc.unwrap_or(true),
+            pinned: frontmatter.pinned,
+            order: frontmatter.order,
+            review_due: frontmatter.review_due,
+            source: None,
             content,
             raw_content,
         })
     }
     
+    /// Load document located under `docs_root`, keeping its folder in the slug
+    pub fn load_in(docs_root: &Path, path: &Path) -> Result<Self> {
+        let mut doc = Self::load(path)?;
+        if let Ok(rel) = path.strip_prefix(docs_root) {
+            doc.rel_path = rel.to_path_buf();
+        }
+        Ok(doc)
+    }
+    
+    /// Load a page of an extra `source`: it lives under `<name>/` in the
+    /// site, so its paths and slugs cannot clash with local pages
+    pub fn load_from_source(source: &DocSource, path: &Path) -> Result<Self> {
+        let mut doc = Self::load_in(&source.path, path)?;
+        doc.rel_path = Path::new(&source.name).join(&doc.rel_path);
+        doc.slug_override = doc.slug_override.map(|slug| format!("{}/{}", source.name, slug));
+        doc.source = Some(source.clone());
+        Ok(doc)
+    }
+    
     /// Parse YAML frontmatter from document content
     fn parse_frontmatter(content: &str) -> Result<(Frontmatter, String)> {
         // Check for frontmatter delimiter
@@ -81,6 +169,11 @@ impl Document {
                     created: None,
                     updated: None,
                     aliases: Vec::new(),
+                    slug: None,
+                    toc: None,
+                    pinned: false,
+                    order: None,
+                    review_due: None,
                 },
                 content.to_string(),
             ));
@@ -110,6 +203,11 @@ impl Document {
             created: self.created,
             updated: self.updated,
             aliases: self.aliases.clone(),
+            slug: self.slug_override.clone(),
+            toc: (!self.toc).then_some(false),
+            pinned: self.pinned,
+            order: self.order,
+            review_due: self.review_due,
         };
         
         let yaml = serde_yaml::to_string(&frontmatter)?;
@@ -119,13 +217,22 @@ impl Document {
         Ok(())
     }
     
-    /// Get document slug (URL-safe name)
+    /// Get document slug (URL-safe name).
+    ///
+    /// Frontmatter `slug:` wins; otherwise the path relative to the docs root
+    /// without extension, so `guides/FAQ.md` becomes `guides/faq`.
     pub fn slug(&self) -> String {
-        self.path.file_stem()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_lowercase()
-            .replace(' ', "-")
+        let source = match &self.slug_override {
+            Some(slug) => PathBuf::from(slug),
+            None => self.rel_path.with_extension(""),
+        };
+        source.components()
+            .filter_map(|c| match c {
+                Component::Normal(part) => Some(part.to_string_lossy().to_lowercase().replace(' ', "-")),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/")
     }
     
     /// Check if document matches search query
@@ -135,6 +242,40 @@ impl Document {
             || self.content.to_lowercase().contains(&query)
             || self.tags.iter().any(|t| t.to_lowercase().contains(&query))
     }
+    
+    /// Whether the `review_due` date has passed by `today`
+    pub fn review_overdue(&self, today: NaiveDate) -> bool {
+        self.review_due.is_some_and(|due| due < today)
+    }
+}
+
+/// Sort documents for the index: pinned first, then by `sort`, ties by title
+pub fn sort_for_index(documents: &mut [&Document], sort: DocumentSort) {
+    let by_title = |a: &Document, b: &Document| a.title.to_lowercase().cmp(&b.title.to_lowercase());
+    documents.sort_by(|a, b| {
+        let by_sort = match sort {
+            DocumentSort::Title => Ordering::Equal,
+            DocumentSort::Updated => b.updated.or(b.created).cmp(&a.updated.or(a.created)),
+            DocumentSort::Manual => match (a.order, b.order) {
+                (Some(x), Some(y)) => x.cmp(&y),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            },
+        };
+        b.pinned.cmp(&a.pinned).then(by_sort).then_with(|| by_title(a, b))
+    });
+}
+
+/// Slugs claimed by more than one document, with the offending paths
+pub fn slug_collisions(documents: &[Document]) -> Vec<(String, Vec<PathBuf>)> {
+    let mut by_slug: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for doc in documents {
+        by_slug.entry(doc.slug()).or_default().push(doc.path.clone());
+    }
+    by_slug.into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .collect()
 }
 
 #[cfg(test)]
@@ -146,6 +287,8 @@ mod tests {
         let content = r#"---
 title: Test Document
 status: public
+pinned: true
+order: 3
 tags:
   - rust
   - test
@@ -159,6 +302,9 @@ This is content."#;
         assert_eq!(fm.title, "Test Document");
         assert_eq!(fm.status, DocumentStatus::Public);
         assert_eq!(fm.tags, vec!["rust", "test"]);
+        assert_eq!(fm.toc, None);
+        assert!(fm.pinned);
+        assert_eq!(fm.order, Some(3));
         assert!(body.contains("# Hello World"));
     }
     
@@ -170,4 +316,70 @@ This is content."#;
         assert_eq!(fm.status, DocumentStatus::Draft);
         assert!(body.contains("Just content"));
     }
+    
+    fn doc_at(rel: &str, slug: Option<&str>) -> Document {
+        Document {
+            path: PathBuf::from("knowledge").join(rel),
+            rel_path: PathBuf::from(rel),
+            slug_override: slug.map(str::to_string),
+            title: "FAQ".to_string(),
+            status: DocumentStatus::Public,
+            tags: Vec::new(),
+            created: None,
+            updated: None,
+            aliases: Vec::new(),
+            toc: true,
+            pinned: false,
+            order: None,
+            review_due: None,
+            source: None,
+            content: String::new(),
+            raw_content: String::new(),
+        }
+    }
+    
+    #[test]
+    fn test_slug_includes_folder() {
+        assert_eq!(doc_at("FAQ.md", None).slug(), "faq");
+        assert_eq!(doc_at("guides/Getting Started.md", None).slug(), "guides/getting-started");
+        assert_eq!(doc_at("guides/FAQ.md", Some("help/faq")).slug(), "help/faq");
+    }
+    
+    #[test]
+    fn test_sort_for_index() {
+        let doc = |title: &str, pinned: bool, order: Option<i64>, updated: Option<&str>| Document {
+            title: title.to_string(),
+            pinned,
+            order,
+            updated: updated.map(|d| d.parse().unwrap()),
+            ..doc_at(&format!("{}.md", title), None)
+        };
+        let docs = [
+            doc("Бюджет", false, Some(2), Some("2026-03-01")),
+            doc("Архив", false, None, Some("2026-01-01")),
+            doc("Регламент", true, Some(9), None),
+            doc("Вход", false, Some(1), Some("2026-02-01")),
+        ];
+        let titles = |sort| {
+            let mut sorted: Vec<&Document> = docs.iter().collect();
+            sort_for_index(&mut sorted, sort);
+            sorted.iter().map(|d| d.title.as_str()).collect::<Vec<_>>()
+        };
+        assert_eq!(titles(DocumentSort::Title), ["Регламент", "Архив", "Бюджет", "Вход"]);
+        assert_eq!(titles(DocumentSort::Updated), ["Регламент", "Бюджет", "Вход", "Архив"]);
+        assert_eq!(titles(DocumentSort::Manual), ["Регламент", "Вход", "Бюджет", "Архив"]);
+    }
+    
+    #[test]
+    fn test_slug_collisions() {
+        let docs = vec![
+            doc_at("FAQ.md", None),
+            doc_at("guides/FAQ.md", None),
+            doc_at("old/faq.md", Some("FAQ")),
+        ];
+        let collisions = slug_collisions(&docs);
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].0, "faq");
+        assert_eq!(collisions[0].1.len(), 2);
+    }
 }
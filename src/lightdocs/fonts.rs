@@ -0,0 +1,153 @@
+//! Bundled web font for the built site
+//!
+//! Locked-down workstations often lack decent Cyrillic system fonts, so the
+//! kit ships PT Sans (SIL OFL, see `fonts/pt-sans/OFL.txt`) in `fonts/pt-sans/`,
+//! split per script block the way Google Fonts serves it
+//! (`cyrillic-400-normal.woff2`, `latin-700-italic.woff2`, ...). A build
+//! copies only the blocks whose characters actually occur in the knowledge
+//! base and writes `fonts/fonts.css` with matching `unicode-range`s, so a
+//! plain Russian site doesn't carry the extended Latin and Cyrillic blocks.
+
+use anyhow::Result;
+use std::collections::BTreeSet;
+use std::path::Path;
+use tracing::{debug, info};
+
+/// Font folder in the kit root
+pub const FONTS_DIR: &str = "fonts/pt-sans";
+
+/// CSS family name used by the built-in templates
+pub const FAMILY: &str = "PT Sans";
+
+/// Stylesheet written to `<output>/fonts/`, linked by the templates
+pub const STYLESHEET: &str = "fonts/fonts.css";
+
+/// Script blocks of the split font and the code points each covers
+const BLOCKS: &[(&str, &[(u32, u32)])] = &[
+    (
+        "latin",
+        &[
+            (0x0000, 0x00FF), (0x0131, 0x0131), (0x0152, 0x0153), (0x02BB, 0x02BC), (0x02C6, 0x02C6),
+            (0x02DA, 0x02DA), (0x02DC, 0x02DC), (0x2000, 0x206F), (0x20AC, 0x20AC), (0x2122, 0x2122),
+            (0x2191, 0x2191), (0x2193, 0x2193), (0x2212, 0x2212), (0x2215, 0x2215), (0xFEFF, 0xFEFF),
+            (0xFFFD, 0xFFFD),
+        ],
+    ),
+    (
+        "latin-ext",
+        &[
+            (0x0100, 0x02AF), (0x1E00, 0x1EFF), (0x2020, 0x2020), (0x20A0, 0x20AB), (0x20AD, 0x20CF),
+            (0x2113, 0x2113), (0x2C60, 0x2C7F), (0xA720, 0xA7FF),
+        ],
+    ),
+    ("cyrillic", &[(0x0301, 0x0301), (0x0400, 0x045F), (0x0490, 0x0491), (0x04B0, 0x04B1), (0x2116, 0x2116)]),
+    ("cyrillic-ext", &[(0x0460, 0x052F), (0x1C80, 0x1C88), (0x20B4, 0x20B4), (0x2DE0, 0x2DFF), (0xA640, 0xA69F), (0xFE2E, 0xFE2F)]),
+];
+
+/// Weights and styles of the family
+const FACES: &[(u16, &str)] = &[(400, "normal"), (700, "normal"), (400, "italic"), (700, "italic")];
+
+/// What a build put into `<output>/fonts/`
+#[derive(Debug, Default)]
+pub struct FontBundle {
+    /// Script blocks included
+    pub blocks: Vec<&'static str>,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Script blocks needed to draw `texts` (latin is always kept for UI text)
+pub fn blocks_for<'a>(texts: impl IntoIterator<Item = &'a str>) -> Vec<&'static str> {
+    let used: BTreeSet<u32> = texts.into_iter().flat_map(str::chars).map(u32::from).collect();
+    BLOCKS
+        .iter()
+        .filter(|(name, ranges)| {
+            *name == "latin" || used.iter().any(|c| ranges.iter().any(|&(lo, hi)| (lo..=hi).contains(c)))
+        })
+        .map(|(name, _)| *name)
+        .collect()
+}
+
+fn unicode_range(block: &str) -> String {
+    let ranges = BLOCKS.iter().find(|(name, _)| *name == block).map(|(_, r)| *r).unwrap_or_default();
+    ranges
+        .iter()
+        .map(|(lo, hi)| if lo == hi { format!("U+{:04X}", lo) } else { format!("U+{:04X}-{:04X}", lo, hi) })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Copy the font blocks `texts` need from `<root>/fonts/pt-sans` into
+/// `<output_dir>/fonts` and write the stylesheet. Without bundled fonts the
+/// stylesheet is left empty and pages fall back to system fonts.
+pub fn bundle<'a>(root: &Path, output_dir: &Path, texts: impl IntoIterator<Item = &'a str>) -> Result<FontBundle> {
+    let source = root.join(FONTS_DIR);
+    let target = output_dir.join("fonts");
+    if target.exists() {
+        std::fs::remove_dir_all(&target)?;
+    }
+    std::fs::create_dir_all(&target)?;
+
+    let mut bundle = FontBundle::default();
+    let mut css = String::new();
+    if source.is_dir() {
+        bundle.blocks = blocks_for(texts);
+        for block in &bundle.blocks {
+            for (weight, style) in FACES {
+                let file = format!("{}-{}-{}.woff2", block, weight, style);
+                let src = source.join(&file);
+                if !src.is_file() {
+                    debug!("Font file missing: {}", src.display());
+                    continue;
+                }
+                bundle.bytes += std::fs::copy(&src, target.join(&file))?;
+                bundle.files += 1;
+                css.push_str(&format!(
+                    "@font-face {{\n  font-family: '{}';\n  font-style: {};\n  font-weight: {};\n  font-display: swap;\n  src: url('{}') format('woff2');\n  unicode-range: {};\n}}\n",
+                    FAMILY,
+                    style,
+                    weight,
+                    file,
+                    unicode_range(block)
+                ));
+            }
+        }
+        for license in ["OFL.txt", "LICENSE.txt"] {
+            if source.join(license).is_file() {
+                std::fs::copy(source.join(license), target.join(license))?;
+            }
+        }
+        info!("🔤 Fonts: {} ({} files, {} KB)", bundle.blocks.join(", "), bundle.files, bundle.bytes / 1024);
+    } else {
+        css.push_str(&format!("/* {} not found: system fonts are used */\n", FONTS_DIR));
+    }
+    std::fs::write(output_dir.join(STYLESHEET), css)?;
+    Ok(bundle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_only_used_blocks_are_bundled() {
+        assert_eq!(blocks_for(["Hello"]), vec!["latin"]);
+        assert_eq!(blocks_for(["Привет, Łódź"]), vec!["latin", "latin-ext", "cyrillic"]);
+
+        let root = tempdir().unwrap();
+        let fonts = root.path().join(FONTS_DIR);
+        std::fs::create_dir_all(&fonts).unwrap();
+        for block in ["latin", "cyrillic", "greek"] {
+            std::fs::write(fonts.join(format!("{}-400-normal.woff2", block)), b"wOF2").unwrap();
+        }
+        let out = tempdir().unwrap();
+
+        let bundle = bundle(root.path(), out.path(), ["Инструкция"]).unwrap();
+        assert_eq!(bundle.files, 2);
+        assert!(out.path().join("fonts/cyrillic-400-normal.woff2").exists());
+        assert!(!out.path().join("fonts/greek-400-normal.woff2").exists());
+        let css = std::fs::read_to_string(out.path().join(STYLESHEET)).unwrap();
+        assert!(css.contains("unicode-range: U+0301, U+0400-045F"));
+    }
+}
@@ -0,0 +1,196 @@
+//! Glossary of terms with hover definitions
+//!
+//! A public `glossary.md` at the root of the knowledge base lists terms as
+//! headings with the definition below (a heading without text under it, like
+//! the page title, is not a term):
+//!
+//! ```markdown
+//! ## Витрина
+//! Таблица, собранная из сырых выгрузок для одного дашборда.
+//! ```
+//!
+//! While the site is built, the first occurrence of every term on each other
+//! page becomes a link to its entry, with the start of the definition as the
+//! hover tooltip. Matching ignores case and whole words only; text in
+//! headings, links and code is left alone.
+
+use pulldown_cmark::{CowStr, Event, Tag, TagEnd};
+use std::collections::HashSet;
+use std::path::Path;
+
+use super::document::{Document, DocumentStatus};
+use super::parser;
+use crate::html::html_escape;
+
+/// File of the glossary, relative to the docs root
+pub const GLOSSARY_FILE: &str = "glossary.md";
+
+/// Longest tooltip, in characters
+const MAX_TOOLTIP: usize = 200;
+
+/// A term and where its definition is
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    pub term: String,
+    /// Heading id on the glossary page
+    pub anchor: String,
+    /// Start of the definition, plain text
+    pub definition: String,
+}
+
+/// Terms of the knowledge base's glossary
+#[derive(Debug, Clone, Default)]
+pub struct Glossary {
+    /// Slug of the glossary page
+    pub slug: String,
+    /// Longest first, so "набор данных" wins over "набор"
+    pub terms: Vec<Term>,
+}
+
+impl Glossary {
+    /// The glossary among `documents`: a public `glossary.md` of the local
+    /// knowledge base (not of a source)
+    pub fn find(documents: &[Document]) -> Option<Self> {
+        documents
+            .iter()
+            .find(|d| d.source.is_none() && d.status == DocumentStatus::Public && d.rel_path == Path::new(GLOSSARY_FILE))
+            .map(|d| Self::parse(&d.slug(), &d.content))
+    }
+
+    /// Terms of a glossary page's markdown
+    pub fn parse(slug: &str, markdown: &str) -> Self {
+        let mut terms: Vec<Term> = parser::sections(markdown)
+            .into_iter()
+            .filter(|s| !s.title.trim().is_empty() && !s.text.trim().is_empty())
+            .map(|s| Term { term: s.title.trim().to_string(), anchor: s.anchor, definition: tooltip(&s.text) })
+            .collect();
+        terms.sort_by(|a, b| b.term.chars().count().cmp(&a.term.chars().count()));
+        Self { slug: slug.to_string(), terms }
+    }
+
+    /// Link the first occurrence of every term in the events of one page;
+    /// `root` is the page's relative path to the site root
+    pub fn annotate<'a>(&self, events: Vec<Event<'a>>, root: &str) -> Vec<Event<'a>> {
+        let mut linked: HashSet<usize> = HashSet::new();
+        let mut out = Vec::with_capacity(events.len());
+        // Inside headings, links, images and code blocks nothing is linked;
+        // wikilinks to files and sites arrive as raw `<a>` tags
+        let mut skip = 0usize;
+        for event in events {
+            match &event {
+                Event::Start(Tag::Heading { .. } | Tag::Link { .. } | Tag::Image { .. } | Tag::CodeBlock(_)) => skip += 1,
+                Event::End(TagEnd::Heading(_) | TagEnd::Link | TagEnd::Image | TagEnd::CodeBlock) => skip = skip.saturating_sub(1),
+                Event::InlineHtml(html) if html.starts_with("<a ") || html.as_ref() == "<a>" => skip += 1,
+                Event::InlineHtml(html) if html.as_ref() == "</a>" => skip = skip.saturating_sub(1),
+                Event::Text(text) if skip == 0 && linked.len() < self.terms.len() => {
+                    self.link_text(text, root, &mut linked, &mut out);
+                    continue;
+                }
+                _ => {}
+            }
+            out.push(event);
+        }
+        out
+    }
+
+    /// `text` with terms not yet linked on the page wrapped in links
+    fn link_text<'a>(&self, text: &str, root: &str, linked: &mut HashSet<usize>, out: &mut Vec<Event<'a>>) {
+        let mut rest = text;
+        loop {
+            // Earliest occurrence of any term still to link
+            let next = self
+                .terms
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| !linked.contains(index))
+                .filter_map(|(index, term)| find_word(rest, &term.term).map(|(start, end)| (start, end, index)))
+                .min_by_key(|&(start, end, _)| (start, std::cmp::Reverse(end)));
+            let Some((start, end, index)) = next else {
+                break;
+            };
+            linked.insert(index);
+            let term = &self.terms[index];
+            if start > 0 {
+                out.push(Event::Text(CowStr::from(rest[..start].to_string())));
+            }
+            out.push(Event::InlineHtml(CowStr::from(format!(
+                "<a href=\"{}{}.html#{}\" class=\"glossary-term\" title=\"{}\">{}</a>",
+                root,
+                self.slug,
+                term.anchor,
+                html_escape(&term.definition),
+                html_escape(&rest[start..end])
+            ))));
+            rest = &rest[end..];
+        }
+        if !rest.is_empty() {
+            out.push(Event::Text(CowStr::from(rest.to_string())));
+        }
+    }
+}
+
+/// Byte range of the first whole-word, case-insensitive occurrence of `word`
+fn find_word(text: &str, word: &str) -> Option<(usize, usize)> {
+    let mut previous: Option<char> = None;
+    for (start, c) in text.char_indices() {
+        if !previous.is_some_and(char::is_alphanumeric) {
+            if let Some(end) = match_at(&text[start..], word).map(|len| start + len) {
+                if !text[end..].chars().next().is_some_and(char::is_alphanumeric) {
+                    return Some((start, end));
+                }
+            }
+        }
+        previous = Some(c);
+    }
+    None
+}
+
+/// Length in bytes of `word` at the start of `text`, ignoring case
+fn match_at(text: &str, word: &str) -> Option<usize> {
+    let mut chars = text.char_indices();
+    for expected in word.chars() {
+        let (_, c) = chars.next()?;
+        if !c.to_lowercase().eq(expected.to_lowercase()) {
+            return None;
+        }
+    }
+    Some(chars.next().map_or(text.len(), |(index, _)| index))
+}
+
+/// First sentences of a definition, one line, cut at a word
+fn tooltip(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= MAX_TOOLTIP {
+        return text;
+    }
+    let cut: String = text.chars().take(MAX_TOOLTIP).collect();
+    let cut = cut.rsplit_once(' ').map_or(cut.as_str(), |(head, _)| head);
+    format!("{}…", cut.trim_end_matches(|c: char| c.is_ascii_punctuation()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::{html, Parser};
+
+    #[test]
+    fn test_first_occurrences_are_linked() {
+        let glossary = Glossary::parse(
+            "glossary",
+            "# Глоссарий\n\n## Витрина\nТаблица для одного \"дашборда\".\n\n## Витрина данных\nТо же, что витрина.\n\n## SLA\nСрок ответа.\n",
+        );
+        assert_eq!(glossary.terms[0].term, "Витрина данных");
+
+        let md = "## Витрина\n\nВитрина данных и витрина. Ещё витрина, `SLA`, витринами, [SLA](./sla.html).\n\nSLA и sla.";
+        let mut html = String::new();
+        html::push_html(&mut html, glossary.annotate(Parser::new(md).collect(), "./").into_iter());
+        assert!(html.contains("<h2>Витрина</h2>"));
+        assert!(html.contains(
+            "<a href=\"./glossary.html#витрина-данных\" class=\"glossary-term\" title=\"То же, что витрина.\">Витрина данных</a> и \
+             <a href=\"./glossary.html#витрина\" class=\"glossary-term\" title=\"Таблица для одного &quot;дашборда&quot;.\">витрина</a>. Ещё витрина,"
+        ));
+        assert!(html.contains("витринами"));
+        assert!(html.contains("<a href=\"./sla.html\">SLA</a>"));
+        assert!(html.contains("<p><a href=\"./glossary.html#sla\" class=\"glossary-term\" title=\"Срок ответа.\">SLA</a> и sla.</p>"));
+    }
+}
@@ -11,16 +11,21 @@
 pub mod parser;
 pub mod wikilinks;
 pub mod document;
+pub mod fonts;
+pub mod glossary;
 pub mod server;
 pub mod search;
+pub mod vocabulary;
+pub mod stats;
+pub mod templates;
 
 use std::path::{Path, PathBuf};
 use anyhow::Result;
-use tracing::info;
+use tracing::{info, warn};
 
 pub use parser::MarkdownParser;
 pub use wikilinks::WikilinksTransformer;
-pub use document::{Document, DocumentStatus};
+pub use document::{DocSource, Document, DocumentSort, DocumentStatus};
 pub use server::LightDocsServer;
 
 use notify::{Watcher, RecursiveMode, Result as NotifyResult};
@@ -40,6 +45,12 @@ pub struct LightDocsConfig {
     pub title: String,
     /// Enable live reload
     pub live_reload: bool,
+    /// Index order: `title`, `updated` or `manual` (pinned pages first)
+    #[serde(default)]
+    pub sort: DocumentSort,
+    /// Read-only roots (network shares) whose public pages join the site
+    #[serde(default)]
+    pub sources: Vec<DocSource>,
 }
 
 impl Default for LightDocsConfig {
@@ -50,6 +61,8 @@ impl Default for LightDocsConfig {
             port: 8090,
             title: "LightDocs".to_string(),
             live_reload: true,
+            sort: DocumentSort::default(),
+            sources: Vec::new(),
         }
     }
 }
@@ -98,7 +111,6 @@ impl LightDocsConfig {
 pub struct LightDocs {
     root: PathBuf,
     config: LightDocsConfig,
-    parser: MarkdownParser,
 }
 
 impl LightDocs {
@@ -108,7 +120,6 @@ impl LightDocs {
         Ok(Self {
             root: root.to_path_buf(),
             config,
-            parser: MarkdownParser::new(),
         })
     }
     
@@ -168,156 +179,151 @@ created: 2026-01-28
     
     /// Build static site from markdown files
     pub fn build(&self) -> Result<Vec<Document>> {
-        let docs_root = self.config.docs_root_abs(&self.root);
         let output_dir = self.config.output_dir_abs(&self.root);
         
         // Ensure output dir exists
         std::fs::create_dir_all(&output_dir)?;
         
-        let mut documents = Vec::new();
+        let documents = self.list_documents()?;
         
-        // Walk through all markdown files
-        for entry in walkdir::WalkDir::new(&docs_root)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.path().extension().map_or(false, |ext| ext == "md"))
-        {
-            let path = entry.path();
-            let doc = Document::load(path)?;
+        // Two documents with the same slug would overwrite each other in
+        // the output and the search index
+        let collisions = document::slug_collisions(&documents);
+        if !collisions.is_empty() {
+            let details: Vec<String> = collisions.iter()
+                .map(|(slug, paths)| {
+                    let paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+                    format!("  {}: {}", slug, paths.join(", "))
+                })
+                .collect();
+            anyhow::bail!(
+                "Slug collision (add `slug:` to frontmatter to resolve):\n{}",
+                details.join("\n")
+            );
+        }
+        
+        let templates = templates::Templates::load(&self.config.docs_root_abs(&self.root))?;
+        let mut parser = MarkdownParser::new().with_templates(templates);
+        if let Some(glossary) = glossary::Glossary::find(&documents) {
+            info!("📖 Glossary: {} term(s)", glossary.terms.len());
+            parser = parser.with_glossary(glossary);
+        }
+        for doc in &documents {
+            parser.register_document(&doc.title, &doc.aliases, &doc.slug());
+        }
+        
+        // Only process public documents
+        for doc in documents.iter().filter(|d| d.status == DocumentStatus::Public) {
+            let html = parser.render(doc)?;
+            let html_path = output_dir.join(format!("{}.html", doc.slug()));
             
-            // Only process public documents
-            if doc.status == DocumentStatus::Public {
-                let html = self.parser.render(&doc)?;
-                
-                // Calculate output path
-                let rel_path = path.strip_prefix(&docs_root)?;
-                let html_path = output_dir.join(rel_path).with_extension("html");
-                
-                // Ensure parent directory exists
-                if let Some(parent) = html_path.parent() {
-                    std::fs::create_dir_all(parent)?;
-                }
-                
-                std::fs::write(&html_path, &html)?;
-                info!("Built: {} -> {}", path.display(), html_path.display());
+            // Ensure parent directory exists
+            if let Some(parent) = html_path.parent() {
+                std::fs::create_dir_all(parent)?;
             }
             
-            documents.push(doc);
+            std::fs::write(&html_path, &html)?;
+            info!("Built: {} -> {}", doc.path.display(), html_path.display());
+            self.copy_attachments(doc, &output_dir)?;
         }
         
         // Generate index page
-        self.generate_index(&output_dir, &documents)?;
+        self.generate_index(parser.templates(), &output_dir, &documents)?;
+        
+        // Font blocks for the characters the published pages use
+        let texts = documents.iter()
+            .filter(|d| d.status == DocumentStatus::Public)
+            .flat_map(|d| [d.title.as_str(), d.content.as_str()])
+            .chain([self.config.title.as_str()]);
+        fonts::bundle(&self.root, &output_dir, texts)?;
+        templates::Templates::write_print_css(&self.config.docs_root_abs(&self.root), &output_dir)?;
+        
+        // Generate stats page
+        let stats = stats::KnowledgeStats::collect(&documents, self.top_queries());
+        std::fs::write(output_dir.join("stats.html"), stats.render_html(&self.config.title))?;
         
         info!("Built {} documents", documents.len());
         Ok(documents)
     }
     
+    /// Copy files linked with `[[file:...]]` next to the built page
+    fn copy_attachments(&self, doc: &Document, output_dir: &Path) -> Result<()> {
+        let folder = MarkdownParser::folder(doc);
+        // Next to the markdown file, which for a source's page is on the share
+        let src_folder = doc.path.parent().unwrap_or(Path::new(""));
+        for attachment in WikilinksTransformer::extract_attachments(&doc.content) {
+            let rel = Path::new(&folder).join(&attachment);
+            let src = src_folder.join(&attachment);
+            if !src.is_file() {
+                warn!("Missing attachment in {}: {}", doc.path.display(), attachment);
+                continue;
+            }
+            let dst = output_dir.join(&rel);
+            if let Some(parent) = dst.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::copy(&src, &dst)?;
+        }
+        Ok(())
+    }
+    
     /// Generate index.html with list of all public documents
-    fn generate_index(&self, output_dir: &Path, documents: &[Document]) -> Result<()> {
-        let public_docs: Vec<_> = documents.iter()
+    fn generate_index(&self, templates: &templates::Templates, output_dir: &Path, documents: &[Document]) -> Result<()> {
+        let mut public: Vec<&Document> = documents.iter()
             .filter(|d| d.status == DocumentStatus::Public)
             .collect();
+        document::sort_for_index(&mut public, self.config.sort);
+        let entries = public.into_iter()
+            .map(|doc| templates::IndexEntry {
+                title: &doc.title,
+                slug: doc.slug(),
+                created: doc.created.map_or("".to_string(), |d| d.format("%d.%m.%Y").to_string()),
+                updated: doc.updated.map_or("".to_string(), |d| d.format("%d.%m.%Y").to_string()),
+                pinned: doc.pinned,
+                source: doc.source.as_ref().map(DocSource::label),
+                tags: &doc.tags,
+            })
+            .collect();
         
-        let mut html = format!(r#"<!DOCTYPE html>
-<html lang="ru">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>{}</title>
-    <style>
-        :root {{
-            --bg: #1a1a2e;
-            --surface: #16213e;
-            --primary: #0f3460;
-            --accent: #e94560;
-            --text: #eee;
-            --text-muted: #888;
-        }}
-        * {{ box-sizing: border-box; margin: 0; padding: 0; }}
-        body {{
-            font-family: 'Segoe UI', system-ui, sans-serif;
-            background: var(--bg);
-            color: var(--text);
-            line-height: 1.6;
-            padding: 2rem;
-        }}
-        .container {{ max-width: 800px; margin: 0 auto; }}
-        h1 {{ 
-            color: var(--accent); 
-            margin-bottom: 1rem;
-            font-size: 2rem;
-        }}
-        .search {{
-            width: 100%;
-            padding: 0.75rem 1rem;
-            border: 2px solid var(--primary);
-            background: var(--surface);
-            color: var(--text);
-            border-radius: 8px;
-            font-size: 1rem;
-            margin-bottom: 1.5rem;
-        }}
-        .search:focus {{ outline: none; border-color: var(--accent); }}
-        .doc-list {{ list-style: none; }}
-        .doc-item {{
-            background: var(--surface);
-            padding: 1rem;
-            margin-bottom: 0.5rem;
-            border-radius: 8px;
-            border-left: 3px solid var(--accent);
-        }}
-        .doc-item:hover {{ background: var(--primary); }}
-        .doc-title {{ 
-            color: var(--text); 
-            text-decoration: none;
-            font-weight: 600;
-        }}
-        .doc-title:hover {{ color: var(--accent); }}
-        .doc-meta {{ color: var(--text-muted); font-size: 0.875rem; }}
-    </style>
-</head>
-<body>
-    <div class="container">
-        <h1>📚 {}</h1>
-        <input type="text" class="search" placeholder="Поиск..." id="search">
-        <ul class="doc-list" id="docs">
-"#, self.config.title, self.config.title);
-        
-        for doc in public_docs {
-            let link = doc.path.file_stem()
-                .unwrap_or_default()
-                .to_string_lossy();
-            html.push_str(&format!(
-                r#"            <li class="doc-item" data-title="{}">
-                <a href="{}.html" class="doc-title">{}</a>
-                <div class="doc-meta">{}</div>
-            </li>
-"#,
-                doc.title.to_lowercase(),
-                link,
-                doc.title,
-                doc.created.map_or("".to_string(), |d| d.format("%d.%m.%Y").to_string())
-            ));
-        }
-        
-        html.push_str(r#"        </ul>
-    </div>
-    <script>
-        document.getElementById('search').addEventListener('input', function(e) {
-            const query = e.target.value.toLowerCase();
-            document.querySelectorAll('.doc-item').forEach(item => {
-                const title = item.dataset.title;
-                item.style.display = title.includes(query) ? '' : 'none';
-            });
-        });
-    </script>
-</body>
-</html>"#);
+        let html = templates.render_index(&templates::IndexContext {
+            site_title: &self.config.title,
+            documents: entries,
+        })?;
         
         std::fs::write(output_dir.join("index.html"), html)?;
         Ok(())
     }
     
+    /// Copy built-in layouts to `knowledge/_layouts/` for customization
+    pub fn eject_layouts(&self) -> Result<Vec<&'static str>> {
+        templates::Templates::eject(&self.config.docs_root_abs(&self.root))
+    }
+    
+    /// Collect knowledge base statistics
+    pub fn stats(&self) -> Result<stats::KnowledgeStats> {
+        let documents = self.list_documents()?;
+        Ok(stats::KnowledgeStats::collect(&documents, self.top_queries()))
+    }
+    
+    /// Rebuild the search index from the markdown files, whatever layout the
+    /// old one had. Returns the number of indexed documents.
+    pub fn reindex(&self) -> Result<usize> {
+        let index = search::SearchIndex::open_for_rebuild(&self.root)?;
+        let documents = self.list_documents()?;
+        for doc in &documents {
+            index.index_document(&doc.slug(), &doc.title, &doc.content)?;
+        }
+        index.flush()?;
+        Ok(documents.len())
+    }
+    
+    /// Most searched queries (empty if the search index is busy or missing)
+    fn top_queries(&self) -> Vec<(String, u64)> {
+        search::SearchIndex::open(&self.root)
+            .and_then(|index| index.top_queries(20))
+            .unwrap_or_default()
+    }
+    
     /// Get all documents
     pub fn list_documents(&self) -> Result<Vec<Document>> {
         let docs_root = self.config.docs_root_abs(&self.root);
@@ -328,13 +334,40 @@ created: 2026-01-28
             .filter_map(|e| e.ok())
             .filter(|e| e.path().extension().map_or(false, |ext| ext == "md"))
         {
-            let doc = Document::load(entry.path())?;
+            let doc = Document::load_in(&docs_root, entry.path())?;
             documents.push(doc);
         }
         
+        for source in &self.config.sources {
+            documents.extend(Self::source_documents(source));
+        }
+        
         Ok(documents)
     }
     
+    /// Public pages of an extra root. A share that is offline or a page that
+    /// fails to parse is skipped with a warning: a departmental wiki must not
+    /// break the local site.
+    fn source_documents(source: &DocSource) -> Vec<Document> {
+        if !source.path.is_dir() {
+            warn!("Document source '{}' unavailable: {}", source.name, source.path.display());
+            return Vec::new();
+        }
+        walkdir::WalkDir::new(&source.path)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().extension().map_or(false, |ext| ext == "md"))
+            .filter_map(|entry| match Document::load_from_source(source, entry.path()) {
+                Ok(doc) => Some(doc),
+                Err(e) => {
+                    warn!("Skipping {}: {}", entry.path().display(), e);
+                    None
+                }
+            })
+            .filter(|doc| doc.status == DocumentStatus::Public)
+            .collect()
+    }
+    
     /// Watch for changes and rebuild
     pub fn watch(&self) -> Result<()> {
         let (tx, rx) = channel();
@@ -374,6 +407,7 @@ created: 2026-01-28
                                 for doc in docs {
                                     let _ = index.index_document(&doc.slug(), &doc.title, &doc.content);
                                 }
+                                let _ = index.flush();
                             }
                         }
                     }
@@ -383,3 +417,46 @@ created: 2026-01-28
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_sources_join_the_site() {
+        let dir = tempdir().unwrap();
+        let local = dir.path().join("knowledge");
+        let share = dir.path().join("share");
+        std::fs::create_dir_all(&local).unwrap();
+        std::fs::create_dir_all(share.join("policies")).unwrap();
+        std::fs::write(local.join("faq.md"), "---\ntitle: FAQ\nstatus: public\n---\nSee [[Отпуск]]").unwrap();
+        std::fs::write(
+            share.join("policies").join("leave.md"),
+            "---\ntitle: Отпуск\nstatus: public\nslug: faq\n---\n[[file:form.docx]]",
+        ).unwrap();
+        std::fs::write(share.join("policies").join("form.docx"), "form").unwrap();
+        std::fs::write(share.join("draft.md"), "---\ntitle: Черновик\nstatus: draft\n---\n").unwrap();
+        let config = LightDocsConfig {
+            sources: vec![
+                DocSource { name: "hr".into(), path: share.clone(), label: Some("Отдел кадров".into()) },
+                DocSource { name: "it".into(), path: dir.path().join("offline"), label: None },
+            ],
+            ..LightDocsConfig::default()
+        };
+        config.save(dir.path()).unwrap();
+
+        let lightdocs = LightDocs::new(dir.path()).unwrap();
+        let mut slugs: Vec<String> = lightdocs.list_documents().unwrap().iter().map(|d| d.slug()).collect();
+        slugs.sort();
+        assert_eq!(slugs, vec!["faq", "hr/faq"]);
+
+        lightdocs.build().unwrap();
+        let site = dir.path().join("_site");
+        let page = std::fs::read_to_string(site.join("hr").join("faq.html")).unwrap();
+        assert!(page.contains("🌐 Отдел кадров"));
+        assert!(std::fs::read_to_string(site.join("faq.html")).unwrap().contains("hr/faq.html"));
+        assert!(site.join("hr").join("policies").join("form.docx").exists());
+        assert!(std::fs::read_to_string(site.join("index.html")).unwrap().contains("Отдел кадров"));
+    }
+}
@@ -1,14 +1,22 @@
 //! Markdown parser with HTML generation
 
 use anyhow::Result;
-use pulldown_cmark::{Parser, Options, html};
+use pulldown_cmark::{html, CowStr, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use std::collections::HashMap;
 
 use super::document::Document;
-use super::wikilinks::WikilinksTransformer;
+use super::glossary::Glossary;
+use super::templates::{PageContext, Templates, TocEntry};
+use super::wikilinks::{site_root, WikilinksTransformer};
+
+/// Fewer headings than this don't get a table of contents
+const MIN_TOC_ENTRIES: usize = 3;
 
 /// Markdown to HTML parser
 pub struct MarkdownParser {
     wikilinks: WikilinksTransformer,
+    templates: Templates,
+    glossary: Option<Glossary>,
 }
 
 impl MarkdownParser {
@@ -16,9 +24,28 @@ impl MarkdownParser {
     pub fn new() -> Self {
         Self {
             wikilinks: WikilinksTransformer::new(),
+            templates: Templates::builtin(),
+            glossary: None,
         }
     }
     
+    /// Use custom page templates
+    pub fn with_templates(mut self, templates: Templates) -> Self {
+        self.templates = templates;
+        self
+    }
+    
+    /// Link glossary terms on every page but the glossary's own
+    pub fn with_glossary(mut self, glossary: Glossary) -> Self {
+        self.glossary = Some(glossary);
+        self
+    }
+    
+    /// Templates used for pages (shared with the index page)
+    pub fn templates(&self) -> &Templates {
+        &self.templates
+    }
+    
     /// Register document for wikilink resolution
     pub fn register_document(&mut self, title: &str, aliases: &[String], slug: &str) {
         self.wikilinks.register_with_aliases(title, aliases, slug);
@@ -26,155 +53,174 @@ impl MarkdownParser {
     
     /// Render document to full HTML page
     pub fn render(&self, doc: &Document) -> Result<String> {
-        let content_html = self.render_content(&doc.content)?;
-        
-        Ok(format!(r#"<!DOCTYPE html>
-<html lang="ru">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>{title}</title>
-    <style>
-        :root {{
-            --bg: #1a1a2e;
-            --surface: #16213e;
-            --primary: #0f3460;
-            --accent: #e94560;
-            --text: #eee;
-            --text-muted: #888;
-            --code-bg: #0d1117;
-            --link: #58a6ff;
-        }}
-        * {{ box-sizing: border-box; margin: 0; padding: 0; }}
-        body {{
-            font-family: 'Segoe UI', system-ui, sans-serif;
-            background: var(--bg);
-            color: var(--text);
-            line-height: 1.7;
-            padding: 2rem;
-            max-width: 800px;
-            margin: 0 auto;
-        }}
-        a {{ color: var(--link); text-decoration: none; }}
-        a:hover {{ text-decoration: underline; }}
-        h1, h2, h3, h4 {{ margin: 1.5rem 0 0.75rem; color: var(--accent); }}
-        h1 {{ font-size: 2rem; border-bottom: 2px solid var(--primary); padding-bottom: 0.5rem; }}
-        h2 {{ font-size: 1.5rem; }}
-        h3 {{ font-size: 1.25rem; }}
-        p {{ margin: 0.75rem 0; }}
-        ul, ol {{ margin: 0.75rem 0; padding-left: 1.5rem; }}
-        li {{ margin: 0.25rem 0; }}
-        code {{
-            font-family: 'Cascadia Code', 'Consolas', monospace;
-            background: var(--code-bg);
-            padding: 0.125rem 0.375rem;
-            border-radius: 4px;
-            font-size: 0.875rem;
-        }}
-        pre {{
-            background: var(--code-bg);
-            padding: 1rem;
-            border-radius: 8px;
-            overflow-x: auto;
-            margin: 1rem 0;
-        }}
-        pre code {{ padding: 0; background: none; }}
-        blockquote {{
-            border-left: 3px solid var(--accent);
-            padding-left: 1rem;
-            margin: 1rem 0;
-            color: var(--text-muted);
-            font-style: italic;
-        }}
-        table {{
-            width: 100%;
-            border-collapse: collapse;
-            margin: 1rem 0;
-        }}
-        th, td {{
-            border: 1px solid var(--primary);
-            padding: 0.5rem;
-            text-align: left;
-        }}
-        th {{ background: var(--primary); }}
-        hr {{ border: none; border-top: 1px solid var(--primary); margin: 2rem 0; }}
-        img {{ max-width: 100%; border-radius: 8px; }}
-        .breadcrumb {{
-            margin-bottom: 1rem;
-            color: var(--text-muted);
-        }}
-        .breadcrumb a {{ color: var(--text-muted); }}
-        .meta {{
-            color: var(--text-muted);
-            font-size: 0.875rem;
-            margin-bottom: 1.5rem;
-        }}
-        .tags {{ display: flex; gap: 0.5rem; flex-wrap: wrap; margin-top: 0.5rem; }}
-        .tag {{
-            background: var(--primary);
-            padding: 0.125rem 0.5rem;
-            border-radius: 4px;
-            font-size: 0.75rem;
-        }}
-    </style>
-</head>
-<body>
-    <nav class="breadcrumb">
-        <a href="index.html">← Главная</a>
-    </nav>
-    <article>
-        <h1>{title}</h1>
-        <div class="meta">
-            {meta}
-        </div>
-        {content}
-    </article>
-</body>
-</html>"#,
-            title = doc.title,
-            meta = self.render_meta(doc),
-            content = content_html,
-        ))
+        let (content, mut toc) = self.render_markdown(&doc.content, Some(&doc.slug()), &Self::folder(doc))?;
+        if !doc.toc || toc.len() < MIN_TOC_ENTRIES {
+            toc.clear();
+        }
+        self.templates.render_page(&PageContext {
+            title: &doc.title,
+            slug: doc.slug(),
+            base: site_root(&doc.slug()),
+            content,
+            toc,
+            created: doc.created.map(|d| d.format("%d.%m.%Y").to_string()),
+            updated: doc.updated.map(|d| d.format("%d.%m.%Y").to_string()),
+            tags: &doc.tags,
+            review_due: doc.review_due.map(|d| d.to_string()),
+            review_overdue: doc.review_overdue(chrono::Local::now().date_naive()),
+            source: doc.source.as_ref().map(|s| s.label()),
+        })
+    }
+    
+    /// Folder of the note relative to the docs root (`[[file:...]]` links resolve against it)
+    pub fn folder(doc: &Document) -> String {
+        doc.rel_path
+            .parent()
+            .map(|p| p.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default()
     }
     
     /// Render just the content (markdown -> HTML)
     pub fn render_content(&self, markdown: &str) -> Result<String> {
+        Ok(self.render_markdown(markdown, None, "")?.0)
+    }
+    
+    /// Render markdown of the page `slug` in `folder` (used for same-page
+    /// `[[#Section]]` links and attachments), along with its h2/h3 outline
+    fn render_markdown(&self, markdown: &str, slug: Option<&str>, folder: &str) -> Result<(String, Vec<TocEntry>)> {
         // First transform wikilinks
-        let content = self.wikilinks.transform(markdown);
-        
-        // Parse markdown with extensions
-        let mut options = Options::empty();
-        options.insert(Options::ENABLE_TABLES);
-        options.insert(Options::ENABLE_FOOTNOTES);
-        options.insert(Options::ENABLE_STRIKETHROUGH);
-        options.insert(Options::ENABLE_TASKLISTS);
+        let content = self.wikilinks.transform_page(markdown, slug, folder);
         
-        let parser = Parser::new_ext(&content, options);
+        let mut events = with_heading_ids(Parser::new_ext(&content, markdown_options()).collect());
+        let toc = table_of_contents(&events);
+        if let (Some(glossary), Some(slug)) = (&self.glossary, slug) {
+            if slug != glossary.slug {
+                events = glossary.annotate(events, &site_root(slug));
+            }
+        }
         
         let mut html_output = String::new();
-        html::push_html(&mut html_output, parser);
+        html::push_html(&mut html_output, events.into_iter());
         
-        Ok(html_output)
+        Ok((html_output, toc))
     }
+}
+
+/// Markdown extensions enabled on pages
+fn markdown_options() -> Options {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_FOOTNOTES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    options
+}
+
+/// A heading and the plain text under it, up to the next heading
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    pub title: String,
+    /// Id the heading gets on the rendered page
+    pub anchor: String,
+    pub text: String,
+}
+
+/// Split markdown into sections by heading, with the same ids the rendered
+/// page uses. Text before the first heading belongs to no section.
+pub fn sections(markdown: &str) -> Vec<Section> {
+    let events = with_heading_ids(Parser::new_ext(markdown, markdown_options()).collect());
+    let mut sections: Vec<Section> = Vec::new();
+    let mut in_heading = false;
+    for event in events {
+        match event {
+            Event::Start(Tag::Heading { id, .. }) => {
+                in_heading = true;
+                sections.push(Section {
+                    title: String::new(),
+                    anchor: id.map(|id| id.to_string()).unwrap_or_default(),
+                    text: String::new(),
+                });
+            }
+            Event::End(TagEnd::Heading(_)) => in_heading = false,
+            Event::Text(t) | Event::Code(t) => {
+                if let Some(section) = sections.last_mut() {
+                    if in_heading {
+                        section.title.push_str(&t);
+                    } else {
+                        section.text.push_str(&t);
+                    }
+                }
+            }
+            Event::SoftBreak | Event::HardBreak | Event::End(_) => {
+                if let Some(section) = sections.last_mut().filter(|_| !in_heading) {
+                    section.text.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+    sections.retain(|s| !s.anchor.is_empty());
+    sections
+}
+
+/// Give every heading without an explicit id one derived from its text
+/// (duplicates get `-1`, `-2`, ...), so `[[Doc#Section]]` links have a target
+fn with_heading_ids(mut events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut used: HashMap<String, usize> = HashMap::new();
     
-    /// Render document metadata
-    fn render_meta(&self, doc: &Document) -> String {
-        let mut parts = Vec::new();
+    for start in 0..events.len() {
+        let Event::Start(Tag::Heading { id: None, .. }) = &events[start] else {
+            continue;
+        };
         
-        if let Some(created) = doc.created {
-            parts.push(format!("📅 {}", created.format("%d.%m.%Y")));
+        let mut text = String::new();
+        for event in &events[start + 1..] {
+            match event {
+                Event::End(TagEnd::Heading(_)) => break,
+                Event::Text(t) | Event::Code(t) => text.push_str(t),
+                _ => {}
+            }
         }
         
-        if !doc.tags.is_empty() {
-            let tags_html = doc.tags.iter()
-                .map(|t| format!("<span class=\"tag\">{}</span>", t))
-                .collect::<Vec<_>>()
-                .join("");
-            parts.push(format!("<div class=\"tags\">{}</div>", tags_html));
+        let base = WikilinksTransformer::heading_id(&text);
+        if base.is_empty() {
+            continue;
         }
+        let count = used.entry(base.clone()).or_insert(0);
+        let heading_id = if *count == 0 { base } else { format!("{}-{}", base, count) };
+        *count += 1;
         
-        parts.join(" ")
+        if let Event::Start(Tag::Heading { id, .. }) = &mut events[start] {
+            *id = Some(CowStr::from(heading_id));
+        }
     }
+    events
+}
+
+/// h2/h3 headings with ids, in page order
+fn table_of_contents(events: &[Event<'_>]) -> Vec<TocEntry> {
+    let mut toc = Vec::new();
+    for (start, event) in events.iter().enumerate() {
+        let Event::Start(Tag::Heading { level, id: Some(id), .. }) = event else {
+            continue;
+        };
+        let level = match level {
+            HeadingLevel::H2 => 2,
+            HeadingLevel::H3 => 3,
+            _ => continue,
+        };
+        
+        let mut text = String::new();
+        for event in &events[start + 1..] {
+            match event {
+                Event::End(TagEnd::Heading(_)) => break,
+                Event::Text(t) | Event::Code(t) => text.push_str(t),
+                _ => {}
+            }
+        }
+        toc.push(TocEntry { level, id: id.to_string(), text: text.trim().to_string() });
+    }
+    toc
 }
 
 impl Default for MarkdownParser {
@@ -191,7 +237,7 @@ mod tests {
     fn test_render_content() {
         let parser = MarkdownParser::new();
         let html = parser.render_content("# Hello\n\nWorld").unwrap();
-        assert!(html.contains("<h1>Hello</h1>"));
+        assert!(html.contains("<h1 id=\"hello\">Hello</h1>"));
         assert!(html.contains("<p>World</p>"));
     }
     
@@ -203,4 +249,39 @@ mod tests {
         let html = parser.render_content("See [[FAQ]] for help.").unwrap();
         assert!(html.contains("href=\"./faq.html\""));
     }
+    
+    #[test]
+    fn test_heading_ids() {
+        let parser = MarkdownParser::new();
+        let html = parser.render_content("## Установка\n\n## Установка").unwrap();
+        assert!(html.contains("<h2 id=\"установка\">"));
+        assert!(html.contains("<h2 id=\"установка-1\">"));
+    }
+    
+    #[test]
+    fn test_sections() {
+        let md = "Intro\n\n## Установка\n\nСкачайте **архив**.\n\n## Установка\n\n- `start.bat`\n";
+        let sections = sections(md);
+        let outline: Vec<_> = sections.iter().map(|s| (s.anchor.as_str(), s.text.trim())).collect();
+        assert_eq!(outline[0], ("установка", "Скачайте архив."));
+        assert_eq!(outline[1].0, "установка-1");
+        assert!(outline[1].1.contains("start.bat"));
+    }
+    
+    #[test]
+    fn test_table_of_contents() {
+        let parser = MarkdownParser::new();
+        let md = "## Установка\n\n### Windows\n\n#### Детали\n\n## `config.json`\n";
+        let (_, toc) = parser.render_markdown(md, None, "").unwrap();
+        let outline: Vec<_> = toc.iter().map(|e| (e.level, e.id.as_str(), e.text.as_str())).collect();
+        assert_eq!(outline, vec![(2, "установка", "Установка"), (3, "windows", "Windows"), (2, "config-json", "config.json")]);
+        
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manual.md");
+        std::fs::write(&path, format!("---\ntitle: Manual\n---\n{}", md)).unwrap();
+        let mut doc = Document::load_in(dir.path(), &path).unwrap();
+        assert!(parser.render(&doc).unwrap().contains("manual.html#windows\""));
+        doc.toc = false;
+        assert!(!parser.render(&doc).unwrap().contains("manual.html#windows\""));
+    }
 }
@@ -1,9 +1,49 @@
 //! Full-text search using sled embedded database
+//!
+//! The layout of the trees is versioned: `meta/format_version` is written
+//! when the index is created, and an index written by a launcher with a
+//! different layout is refused on open instead of returning wrong results.
+//! `lightdocs reindex` rebuilds it from the markdown files.
+//!
+//! A document and all its postings are written in one sled transaction, so
+//! the watcher and a CLI reindex running side by side can't drop each
+//! other's slugs from a word's list. Nothing is flushed per document: callers
+//! call [`SearchIndex::flush`] once a whole build is indexed, which spares the
+//! flash drive thousands of small syncs.
+//!
+//! Texts and queries go through the kit's synonyms and stopwords (see
+//! [`super::vocabulary`]), loaded when the index is opened.
+//!
+//! Searches are counted in memory and written to `search_log` every
+//! [`LOG_BATCH`] queries or when the popular queries are read, so a search
+//! is a pure read of the drive.
 
-use std::path::Path;
-use std::collections::HashMap;
-use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Mutex;
+use anyhow::{bail, Result};
 use serde::{Serialize, Deserialize};
+use sled::transaction::{ConflictableTransactionError, Transactional};
+
+use super::parser;
+use super::vocabulary::{Vocabulary, VOCABULARY_FILE};
+use crate::sled_journal::{CompactReport, Journal, CHECKPOINT_MAX_AGE, COMPACT_INTERVAL};
+
+/// Layout of the index trees; bump when keys or values change shape
+pub const INDEX_FORMAT_VERSION: u32 = 1;
+
+/// `meta` key holding the layout version
+const FORMAT_VERSION_KEY: &str = "format_version";
+
+/// `meta` key holding the fingerprint of the vocabulary entries were indexed with
+const VOCABULARY_KEY: &str = "vocabulary";
+
+/// Searches counted before the counts are written to `search_log`
+pub const LOG_BATCH: u64 = 32;
+
+/// Queries counted since the last write, per index folder; kept outside
+/// [`SearchIndex`] because the launcher opens the index per request
+static PENDING_QUERIES: Mutex<BTreeMap<PathBuf, HashMap<String, u64>>> = Mutex::new(BTreeMap::new());
 
 /// Search index entry
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,6 +52,58 @@ pub struct SearchEntry {
     pub title: String,
     pub excerpt: String,
     pub score: f32,
+    /// `doc` for knowledge base articles, otherwise the Superset object type
+    pub kind: String,
+    /// Deep link for non-document entries (relative to the Superset URL)
+    pub url: Option<String>,
+    /// Score breakdown (only with `SearchOptions::explain`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explain: Option<ScoreExplain>,
+    /// Sections of a document that contain query terms, best first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sections: Vec<SectionHit>,
+}
+
+/// A section of a document matched by the query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SectionHit {
+    pub title: String,
+    /// Heading id on the built page (`<slug>.html#<anchor>`)
+    pub anchor: String,
+    /// Query terms found in the section
+    pub matched: Vec<String>,
+}
+
+/// Why an entry got its score
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScoreExplain {
+    /// Query terms found in the entry with their share of the score
+    pub matched: Vec<(String, f32)>,
+    /// Query terms the entry doesn't contain
+    pub missing: Vec<String>,
+}
+
+/// Paging and filtering for `SearchIndex::search_with`
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// Page size (all results when `None`)
+    pub limit: Option<usize>,
+    pub offset: usize,
+    /// Drop entries scoring below this (0.0..=1.0)
+    pub min_score: f32,
+    /// Attach a `ScoreExplain` to every entry
+    pub explain: bool,
+    /// Only this document's entry (by slug)
+    pub doc: Option<String>,
+}
+
+/// One page of search results
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchPage {
+    /// Matches above `min_score` before paging
+    pub total: usize,
+    pub offset: usize,
+    pub results: Vec<SearchEntry>,
 }
 
 /// Full-text search index
@@ -21,58 +113,209 @@ pub struct SearchIndex {
     index_tree: sled::Tree,
     /// Document metadata
     docs_tree: sled::Tree,
+    /// Normalized query -> number of times searched
+    log_tree: sled::Tree,
+    /// Index bookkeeping (e.g. when external sources were last indexed)
+    meta_tree: sled::Tree,
+    /// Synonyms and stopwords applied to texts and queries
+    vocabulary: Vocabulary,
+    /// Index folder, key of the pending query counts
+    dir: PathBuf,
 }
 
 impl SearchIndex {
-    /// Open or create search index
+    /// Open or create search index; fails if it was written in another layout
     pub fn open(root: &Path) -> Result<Self> {
-        let db_path = root.join(".lightdocs_search");
-        let db = sled::open(&db_path)?;
+        let index = Self::open_any(root)?;
+        index.check_format()?;
+        index.check_vocabulary()?;
+        Ok(index)
+    }
+    
+    /// Open regardless of layout, drop all entries (the search log is kept)
+    /// and stamp the current version, ready to be filled again
+    pub fn open_for_rebuild(root: &Path) -> Result<Self> {
+        let index = Self::open_any(root)?;
+        index.clear()?;
+        Ok(index)
+    }
+    
+    fn open_any(root: &Path) -> Result<Self> {
+        let journal = Self::journal(root);
+        if let Err(e) = journal.compact_if_due(COMPACT_INTERVAL, |_| true) {
+            tracing::warn!("Search index compaction skipped: {}", e);
+        }
+        let db = journal.open()?;
+        if let Err(e) = journal.checkpoint_if_stale(&db, CHECKPOINT_MAX_AGE) {
+            tracing::warn!("Search index checkpoint failed: {}", e);
+        }
         
         let index_tree = db.open_tree("word_index")?;
         let docs_tree = db.open_tree("documents")?;
+        let log_tree = db.open_tree("search_log")?;
+        let meta_tree = db.open_tree("meta")?;
         
         Ok(Self {
             db,
             index_tree,
             docs_tree,
+            log_tree,
+            meta_tree,
+            vocabulary: Vocabulary::load(root),
+            dir: Self::index_dir(root),
         })
     }
     
-    /// Index a document
+    /// Layout version stored in the index (`None` before versioning)
+    pub fn format_version(&self) -> Result<Option<u32>> {
+        Ok(self.meta(FORMAT_VERSION_KEY)?.and_then(|v| v.parse().ok()))
+    }
+    
+    /// Stamp a new index; refuse one in a layout this build can't read.
+    /// Indexes from before versioning have the version 1 layout.
+    fn check_format(&self) -> Result<()> {
+        match self.format_version()? {
+            Some(INDEX_FORMAT_VERSION) => Ok(()),
+            Some(version) => bail!(
+                "Search index format v{} is not supported (this launcher uses v{}); run: superset-launcher lightdocs reindex",
+                version,
+                INDEX_FORMAT_VERSION
+            ),
+            None => self.set_meta(FORMAT_VERSION_KEY, &INDEX_FORMAT_VERSION.to_string()),
+        }
+    }
+    
+    /// Warn when the synonyms or stopwords changed since the entries were
+    /// indexed (they'd only apply to articles edited from now on)
+    fn check_vocabulary(&self) -> Result<()> {
+        let current = self.vocabulary.fingerprint();
+        match self.meta(VOCABULARY_KEY)? {
+            Some(stored) if stored != current => tracing::warn!(
+                "{} changed since the search index was built; run: superset-launcher lightdocs reindex",
+                VOCABULARY_FILE
+            ),
+            Some(_) => {}
+            None => self.set_meta(VOCABULARY_KEY, &current)?,
+        }
+        Ok(())
+    }
+    
+    fn index_dir(root: &Path) -> PathBuf {
+        root.join(".lightdocs_search")
+    }
+    
+    fn journal(root: &Path) -> Journal {
+        Journal::new(&Self::index_dir(root), &["word_index", "documents", "search_log", "meta"])
+    }
+    
+    /// Rewrite the (closed) index to reclaim space left by re-indexing
+    pub fn compact(root: &Path) -> Result<CompactReport> {
+        Self::journal(root).compact()
+    }
+    
+    /// Index a document along with the words of each of its sections, so
+    /// results can point at the heading where the query matched
     pub fn index_document(&self, slug: &str, title: &str, content: &str) -> Result<()> {
+        let sections: Vec<serde_json::Value> = parser::sections(content)
+            .into_iter()
+            .map(|section| {
+                let words: BTreeSet<String> = self.index_terms(&format!("{} {}", section.title, section.text)).into_iter().collect();
+                serde_json::json!({"title": section.title, "anchor": section.anchor, "words": words})
+            })
+            .collect();
         // Store document metadata
         let doc_data = serde_json::json!({
             "title": title,
             "excerpt": Self::create_excerpt(content),
+            "sections": sections,
         });
-        self.docs_tree.insert(slug.as_bytes(), doc_data.to_string().as_bytes())?;
-        
-        // Tokenize and index words
-        let words = Self::tokenize(content);
-        for word in words {
-            // Get existing doc list for this word
-            let key = word.to_lowercase();
-            let mut slugs: Vec<String> = self.index_tree
-                .get(key.as_bytes())?
-                .map(|v| serde_json::from_slice(&v).unwrap_or_default())
-                .unwrap_or_default();
-            
-            if !slugs.contains(&slug.to_string()) {
-                slugs.push(slug.to_string());
-                let value = serde_json::to_vec(&slugs)?;
-                self.index_tree.insert(key.as_bytes(), value)?;
+        self.store(slug, &doc_data, content)
+    }
+    
+    /// Index a non-document object (e.g. a Superset chart) with a deep link.
+    /// Unlike documents, the title is searchable too.
+    pub fn index_object(&self, key: &str, kind: &str, title: &str, content: &str, url: &str) -> Result<()> {
+        let doc_data = serde_json::json!({
+            "title": title,
+            "excerpt": Self::create_excerpt(content),
+            "kind": kind,
+            "url": url,
+        });
+        self.store(key, &doc_data, &format!("{} {}", title, content))
+    }
+    
+    /// Drop entries whose key starts with `prefix`, with their postings
+    pub fn remove_prefix(&self, prefix: &str) -> Result<()> {
+        for key in self.docs_tree.scan_prefix(prefix.as_bytes()).keys() {
+            self.docs_tree.remove(key?)?;
+        }
+        for item in self.index_tree.iter() {
+            let (word, value) = item?;
+            let slugs: Vec<String> = serde_json::from_slice(&value).unwrap_or_default();
+            if !slugs.iter().any(|slug| slug.starts_with(prefix)) {
+                continue;
             }
+            self.index_tree.update_and_fetch(word, |value| {
+                let mut slugs: Vec<String> = serde_json::from_slice(value?).unwrap_or_default();
+                slugs.retain(|slug| !slug.starts_with(prefix));
+                (!slugs.is_empty()).then(|| serde_json::to_vec(&slugs).unwrap_or_default())
+            })?;
         }
-        
+        Ok(())
+    }
+    
+    /// Read a bookkeeping value
+    pub fn meta(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.meta_tree.get(key.as_bytes())?.map(|v| String::from_utf8_lossy(&v).to_string()))
+    }
+    
+    /// Store a bookkeeping value
+    pub fn set_meta(&self, key: &str, value: &str) -> Result<()> {
+        self.meta_tree.insert(key.as_bytes(), value.as_bytes())?;
+        Ok(())
+    }
+    
+    /// Write an entry and its postings in one transaction (not flushed)
+    fn store(&self, slug: &str, doc_data: &serde_json::Value, content: &str) -> Result<()> {
+        let words: BTreeSet<String> = self.index_terms(content).into_iter().collect();
+        let doc_data = doc_data.to_string();
+        (&self.docs_tree, &self.index_tree).transaction(|(docs, index)| {
+            docs.insert(slug.as_bytes(), doc_data.as_bytes())?;
+            for word in &words {
+                let mut slugs: Vec<String> = index
+                    .get(word.as_bytes())?
+                    .map(|v| serde_json::from_slice(&v).unwrap_or_default())
+                    .unwrap_or_default();
+                if !slugs.iter().any(|s| s == slug) {
+                    slugs.push(slug.to_string());
+                    let value = serde_json::to_vec(&slugs).map_err(ConflictableTransactionError::Abort)?;
+                    index.insert(word.as_bytes(), value)?;
+                }
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+    
+    /// Write indexed entries to disk (once per build)
+    pub fn flush(&self) -> Result<()> {
+        self.write_log()?;
         self.db.flush()?;
         Ok(())
     }
     
-    /// Search for documents matching query
+    /// Search for documents matching query (all results, best first)
     pub fn search(&self, query: &str) -> Result<Vec<SearchEntry>> {
-        let query_words = Self::tokenize(query);
-        let mut doc_scores: HashMap<String, f32> = HashMap::new();
+        Ok(self.search_with(query, &SearchOptions::default())?.results)
+    }
+    
+    /// Search with paging, a score threshold and optional score breakdown
+    pub fn search_with(&self, query: &str, options: &SearchOptions) -> Result<SearchPage> {
+        self.log_query(query)?;
+        let query_words = self.vocabulary.query_terms(Self::tokenize(query));
+        // Each occurrence of a query word is worth the same share of the score
+        let weight = 1.0 / query_words.len().max(1) as f32;
+        let mut doc_matches: HashMap<String, Vec<String>> = HashMap::new();
         
         // Find documents containing query words
         for word in &query_words {
@@ -80,21 +323,23 @@ impl SearchIndex {
             if let Some(value) = self.index_tree.get(key.as_bytes())? {
                 let slugs: Vec<String> = serde_json::from_slice(&value)?;
                 for slug in slugs {
-                    *doc_scores.entry(slug).or_insert(0.0) += 1.0;
+                    doc_matches.entry(slug).or_default().push(key.clone());
                 }
             }
         }
-        
-        // Normalize scores
-        let max_score = query_words.len() as f32;
-        for score in doc_scores.values_mut() {
-            *score /= max_score;
+        if let Some(doc) = &options.doc {
+            doc_matches.retain(|slug, _| slug == doc);
         }
         
         // Build result list
-        let mut results: Vec<SearchEntry> = doc_scores
+        let mut results: Vec<SearchEntry> = doc_matches
             .into_iter()
-            .filter_map(|(slug, score)| {
+            .filter_map(|(slug, matched)| {
+                let score = matched.len() as f32 * weight;
+                if score < options.min_score {
+                    return None;
+                }
+                let explain = options.explain.then(|| Self::explain(&query_words, &matched, weight));
                 self.docs_tree.get(slug.as_bytes()).ok()?.map(|v| {
                     let doc: serde_json::Value = serde_json::from_slice(&v).ok()?;
                     Some(SearchEntry {
@@ -102,27 +347,142 @@ impl SearchIndex {
                         title: doc["title"].as_str()?.to_string(),
                         excerpt: doc["excerpt"].as_str()?.to_string(),
                         score,
+                        kind: doc["kind"].as_str().unwrap_or("doc").to_string(),
+                        url: doc["url"].as_str().map(str::to_string),
+                        sections: Self::section_hits(&doc["sections"], &matched),
+                        explain,
                     })
                 })?
             })
             .collect();
         
-        // Sort by score descending
-        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        // Sort by score descending; ties by title so pages are stable
+        results.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap()
+                .then_with(|| a.title.cmp(&b.title))
+                .then_with(|| a.slug.cmp(&b.slug))
+        });
         
-        Ok(results)
+        let total = results.len();
+        let results = results
+            .into_iter()
+            .skip(options.offset)
+            .take(options.limit.unwrap_or(usize::MAX))
+            .collect();
+        Ok(SearchPage { total, offset: options.offset, results })
+    }
+    
+    /// Sections containing any of the `matched` terms, most terms first,
+    /// then in page order (entries indexed before sections were stored have none)
+    fn section_hits(sections: &serde_json::Value, matched: &[String]) -> Vec<SectionHit> {
+        let mut hits: Vec<SectionHit> = sections
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|section| {
+                let words = section["words"].as_array()?;
+                let mut terms: Vec<String> = Vec::new();
+                for term in matched {
+                    if !terms.contains(term) && words.iter().any(|w| w.as_str() == Some(term.as_str())) {
+                        terms.push(term.clone());
+                    }
+                }
+                (!terms.is_empty()).then(|| SectionHit {
+                    title: section["title"].as_str().unwrap_or_default().to_string(),
+                    anchor: section["anchor"].as_str()?.to_string(),
+                    matched: terms,
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| b.matched.len().cmp(&a.matched.len()));
+        hits
     }
     
-    /// Clear the index
+    /// Per-term contributions for one entry
+    fn explain(query_words: &[String], matched: &[String], weight: f32) -> ScoreExplain {
+        let mut explain = ScoreExplain::default();
+        for word in query_words {
+            let key = word.to_lowercase();
+            if !matched.contains(&key) {
+                if !explain.missing.contains(&key) {
+                    explain.missing.push(key);
+                }
+            } else if let Some(entry) = explain.matched.iter_mut().find(|(term, _)| *term == key) {
+                entry.1 += weight;
+            } else {
+                explain.matched.push((key, weight));
+            }
+        }
+        explain
+    }
+    
+    /// Count a query; the counts reach the search log in batches
+    fn log_query(&self, query: &str) -> Result<()> {
+        let key = query.trim().to_lowercase();
+        if key.is_empty() {
+            return Ok(());
+        }
+        let due = {
+            let mut pending = PENDING_QUERIES.lock().unwrap_or_else(|e| e.into_inner());
+            let counts = pending.entry(self.dir.clone()).or_default();
+            *counts.entry(key).or_insert(0) += 1;
+            counts.values().sum::<u64>() >= LOG_BATCH
+        };
+        if due {
+            self.write_log()?;
+        }
+        Ok(())
+    }
+    
+    /// Add the pending query counts to the search log
+    fn write_log(&self) -> Result<()> {
+        let counts = PENDING_QUERIES.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.dir);
+        for (key, added) in counts.into_iter().flatten() {
+            self.log_tree.update_and_fetch(key.as_bytes(), |old| {
+                let count = old
+                    .and_then(|v| <[u8; 8]>::try_from(v).ok())
+                    .map(u64::from_be_bytes)
+                    .unwrap_or(0);
+                Some((count + added).to_be_bytes().to_vec())
+            })?;
+        }
+        Ok(())
+    }
+    
+    /// Most searched queries, most frequent first
+    pub fn top_queries(&self, limit: usize) -> Result<Vec<(String, u64)>> {
+        self.write_log()?;
+        let mut queries = Vec::new();
+        for item in self.log_tree.iter() {
+            let (key, value) = item?;
+            let count = <[u8; 8]>::try_from(&value[..]).map(u64::from_be_bytes).unwrap_or(0);
+            queries.push((String::from_utf8_lossy(&key).to_string(), count));
+        }
+        queries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        queries.truncate(limit);
+        Ok(queries)
+    }
+    
+    /// Clear the index (the search log is kept)
     pub fn clear(&self) -> Result<()> {
         self.index_tree.clear()?;
         self.docs_tree.clear()?;
+        self.meta_tree.clear()?;
+        self.set_meta(FORMAT_VERSION_KEY, &INDEX_FORMAT_VERSION.to_string())?;
+        self.set_meta(VOCABULARY_KEY, &self.vocabulary.fingerprint())?;
         self.db.flush()?;
         Ok(())
     }
     
+    /// Words of a text as stored in the index
+    fn index_terms(&self, text: &str) -> Vec<String> {
+        self.vocabulary.index_terms(Self::tokenize(text))
+    }
+    
     /// Tokenize text into words
-    fn tokenize(text: &str) -> Vec<String> {
+    pub(super) fn tokenize(text: &str) -> Vec<String> {
         text.split(|c: char| !c.is_alphanumeric())
             .filter(|w| w.len() > 2)
             .map(|w| w.to_lowercase())
@@ -161,5 +521,158 @@ mod tests {
         let results = index.search("world").unwrap();
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].slug, "test");
+        assert_eq!(results[0].kind, "doc");
+    }
+    
+    #[test]
+    fn test_concurrent_indexing_keeps_every_posting() {
+        let dir = tempdir().unwrap();
+        let index = SearchIndex::open(dir.path()).unwrap();
+        std::thread::scope(|scope| {
+            for t in 0..4 {
+                let index = &index;
+                scope.spawn(move || {
+                    for i in 0..10 {
+                        let slug = format!("doc-{}-{}", t, i);
+                        index.index_document(&slug, &slug, "shared words shared").unwrap();
+                    }
+                });
+            }
+        });
+        index.flush().unwrap();
+        assert_eq!(index.search("shared").unwrap().len(), 40);
+    }
+    
+    #[test]
+    fn test_index_object() {
+        let dir = tempdir().unwrap();
+        let index = SearchIndex::open(dir.path()).unwrap();
+        
+        index.index_object("superset:chart:1", "chart", "Выручка по месяцам", "bar", "/explore/?slice_id=1").unwrap();
+        let results = index.search("выручка").unwrap();
+        assert_eq!(results[0].url.as_deref(), Some("/explore/?slice_id=1"));
+        
+        index.index_document("guide", "Guide", "bar charts").unwrap();
+        index.remove_prefix("superset:").unwrap();
+        assert!(index.search("выручка").unwrap().is_empty());
+        assert!(index.index_tree.get("выручка").unwrap().is_none(), "no stale postings");
+        assert_eq!(index.search("bar").unwrap()[0].slug, "guide");
+    }
+    
+    #[test]
+    fn test_search_paging_and_explain() {
+        let dir = tempdir().unwrap();
+        let index = SearchIndex::open(dir.path()).unwrap();
+        
+        index.index_document("both", "Both", "superset charts").unwrap();
+        for i in 0..3 {
+            index.index_document(&format!("only-{}", i), &format!("Only {}", i), "superset").unwrap();
+        }
+        
+        let options = SearchOptions { limit: Some(2), offset: 1, explain: true, ..Default::default() };
+        let page = index.search_with("superset charts", &options).unwrap();
+        assert_eq!(page.total, 4);
+        let slugs: Vec<&str> = page.results.iter().map(|e| e.slug.as_str()).collect();
+        assert_eq!(slugs, ["only-0", "only-1"]);
+        let explain = page.results[0].explain.as_ref().unwrap();
+        assert_eq!(explain.matched, vec![("superset".to_string(), 0.5)]);
+        assert_eq!(explain.missing, vec!["charts".to_string()]);
+        
+        let options = SearchOptions { min_score: 0.75, ..Default::default() };
+        let page = index.search_with("superset charts", &options).unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.results[0].slug, "both");
+        assert!(page.results[0].explain.is_none());
+    }
+    
+    #[test]
+    fn test_doc_filter_and_section_anchors() {
+        let dir = tempdir().unwrap();
+        let index = SearchIndex::open(dir.path()).unwrap();
+        
+        let manual = "Intro\n\n## Установка\n\nСкачайте архив.\n\n## Порты\n\nПорт 8088 занят: смените порт в config.json.\n\n## Порты\n\nАрхив и порт.\n";
+        index.index_document("manual", "Manual", manual).unwrap();
+        index.index_document("faq", "FAQ", "Какой порт у Superset?").unwrap();
+        
+        let page = index.search_with("порт архив", &SearchOptions::default()).unwrap();
+        assert_eq!(page.total, 2);
+        
+        let options = SearchOptions { doc: Some("manual".to_string()), ..Default::default() };
+        let page = index.search_with("порт архив", &options).unwrap();
+        assert_eq!(page.total, 1);
+        let anchors: Vec<&str> = page.results[0].sections.iter().map(|s| s.anchor.as_str()).collect();
+        assert_eq!(anchors, ["порты-1", "установка", "порты"]);
+        assert_eq!(page.results[0].sections[0].matched, ["порт", "архив"]);
+        
+        let faq = index.search_with("порт", &SearchOptions { doc: Some("faq".to_string()), ..Default::default() }).unwrap();
+        assert!(faq.results[0].sections.is_empty());
+    }
+    
+    #[test]
+    fn test_synonyms_at_index_and_query_time() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join(VOCABULARY_FILE), "synonyms:\n  - БД = база данных = database\nstopwords: [также]\n").unwrap();
+        let index = SearchIndex::open(dir.path()).unwrap();
+        
+        index.index_document("backup", "Backup", "Copy the database file").unwrap();
+        index.index_document("restore", "Restore", "## Откат\n\nВосстановите БД из копии").unwrap();
+        let slugs = |query: &str| -> Vec<String> {
+            let mut slugs: Vec<String> = index.search(query).unwrap().into_iter().map(|e| e.slug).collect();
+            slugs.sort();
+            slugs
+        };
+        assert_eq!(slugs("база данных"), ["backup", "restore"]);
+        assert_eq!(slugs("database"), ["backup", "restore"]);
+        assert!(slugs("также").is_empty());
+        assert_eq!(index.search("база данных").unwrap().iter().find(|e| e.slug == "restore").unwrap().sections[0].anchor, "откат");
+        drop(index);
+        
+        // A changed vocabulary is only reported; the entries stay searchable
+        std::fs::write(dir.path().join(VOCABULARY_FILE), "stopwords: [copy]\n").unwrap();
+        let index = SearchIndex::open(dir.path()).unwrap();
+        assert_ne!(index.meta(VOCABULARY_KEY).unwrap().unwrap(), index.vocabulary.fingerprint());
+        assert_eq!(index.search("database").unwrap().len(), 1);
+    }
+    
+    #[test]
+    fn test_format_version() {
+        let dir = tempdir().unwrap();
+        let index = SearchIndex::open(dir.path()).unwrap();
+        assert_eq!(index.format_version().unwrap(), Some(INDEX_FORMAT_VERSION));
+        index.index_document("test", "Test", "superset").unwrap();
+        index.search("superset").unwrap();
+        index.set_meta(FORMAT_VERSION_KEY, "99").unwrap();
+        drop(index);
+        
+        let err = SearchIndex::open(dir.path()).err().unwrap();
+        assert!(err.to_string().contains("lightdocs reindex"));
+        
+        let index = SearchIndex::open_for_rebuild(dir.path()).unwrap();
+        assert!(index.search("superset").unwrap().is_empty());
+        assert_eq!(index.top_queries(10).unwrap()[0], ("superset".to_string(), 2));
+        drop(index);
+        assert!(SearchIndex::open(dir.path()).is_ok());
+    }
+    
+    #[test]
+    fn test_search_log() {
+        let dir = tempdir().unwrap();
+        let index = SearchIndex::open(dir.path()).unwrap();
+        
+        index.search("Superset").unwrap();
+        index.search("superset ").unwrap();
+        index.search("charts").unwrap();
+        
+        assert!(index.log_tree.is_empty(), "searches don't write until a batch is full");
+        
+        let top = index.top_queries(10).unwrap();
+        assert_eq!(top[0], ("superset".to_string(), 2));
+        assert_eq!(top.len(), 2);
+        
+        for _ in 0..LOG_BATCH {
+            index.search("charts").unwrap();
+        }
+        assert_eq!(index.log_tree.len(), 2);
+        assert_eq!(index.top_queries(1).unwrap()[0], ("charts".to_string(), LOG_BATCH + 1));
     }
 }
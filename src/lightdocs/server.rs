@@ -7,6 +7,8 @@ use axum::Router;
 use tower_http::services::ServeDir;
 use tracing::info;
 
+use crate::security_headers::SecurityConfig;
+
 /// LightDocs development server
 pub struct LightDocsServer {
     root: PathBuf,
@@ -37,6 +39,7 @@ impl LightDocsServer {
         
         let app = Router::new()
             .fallback_service(serve_dir);
+        let app = crate::security_headers::apply(app, &SecurityConfig::load(&self.root), true);
         
         let addr = SocketAddr::from(([127, 0, 0, 1], self.port));
         info!("📚 LightDocs server at http://localhost:{}", self.port);
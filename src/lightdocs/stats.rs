@@ -0,0 +1,323 @@
+//! Knowledge base statistics: counts, orphans, broken links, popular queries
+
+use std::collections::{BTreeMap, HashMap};
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use super::document::{Document, DocumentStatus};
+use crate::html::html_escape;
+use super::wikilinks::WikilinksTransformer;
+
+/// Link to a document that does not exist
+#[derive(Debug, Clone, Serialize)]
+pub struct BrokenLink {
+    /// Title of the document containing the link
+    pub from: String,
+    /// Link target as written in [[...]]
+    pub target: String,
+}
+
+/// Document without inbound links
+#[derive(Debug, Clone, Serialize)]
+pub struct OrphanDoc {
+    pub slug: String,
+    pub title: String,
+}
+
+/// Document whose `review_due` date has passed
+#[derive(Debug, Clone, Serialize)]
+pub struct OverdueReview {
+    pub slug: String,
+    pub title: String,
+    pub review_due: NaiveDate,
+}
+
+/// Aggregated knowledge base statistics
+#[derive(Debug, Clone, Serialize)]
+pub struct KnowledgeStats {
+    pub total_documents: usize,
+    pub by_status: BTreeMap<String, usize>,
+    pub by_tag: BTreeMap<String, usize>,
+    pub total_words: usize,
+    pub avg_words: usize,
+    pub orphans: Vec<OrphanDoc>,
+    pub broken_links: Vec<BrokenLink>,
+    /// Pages past their review date, longest overdue first
+    pub overdue_reviews: Vec<OverdueReview>,
+    /// (query, count), most searched first
+    pub top_queries: Vec<(String, u64)>,
+}
+
+impl KnowledgeStats {
+    /// Collect statistics for the given documents and search log
+    pub fn collect(documents: &[Document], top_queries: Vec<(String, u64)>) -> Self {
+        let mut by_status = BTreeMap::new();
+        let mut by_tag = BTreeMap::new();
+        let mut total_words = 0;
+
+        let mut resolver = WikilinksTransformer::new();
+        for doc in documents {
+            resolver.register_with_aliases(&doc.title, &doc.aliases, &doc.slug());
+        }
+
+        let mut inbound: HashMap<String, usize> = HashMap::new();
+        let mut broken_links = Vec::new();
+
+        for doc in documents {
+            let status = match doc.status {
+                DocumentStatus::Draft => "draft",
+                DocumentStatus::Public => "public",
+            };
+            *by_status.entry(status.to_string()).or_insert(0) += 1;
+            for tag in &doc.tags {
+                *by_tag.entry(tag.to_lowercase()).or_insert(0) += 1;
+            }
+            total_words += doc.content.split_whitespace().count();
+
+            for target in WikilinksTransformer::extract_links(&doc.content) {
+                match resolver.resolve(&target) {
+                    Some(slug) if slug != doc.slug() => *inbound.entry(slug).or_insert(0) += 1,
+                    Some(_) => {}
+                    None => broken_links.push(BrokenLink {
+                        from: doc.title.clone(),
+                        target,
+                    }),
+                }
+            }
+        }
+
+        let orphans = documents
+            .iter()
+            .filter(|d| d.slug() != "index" && !inbound.contains_key(&d.slug()))
+            .map(|d| OrphanDoc { slug: d.slug(), title: d.title.clone() })
+            .collect();
+
+        Self {
+            total_documents: documents.len(),
+            by_status,
+            by_tag,
+            total_words,
+            avg_words: if documents.is_empty() { 0 } else { total_words / documents.len() },
+            orphans,
+            broken_links,
+            overdue_reviews: overdue_reviews(documents, chrono::Local::now().date_naive()),
+            top_queries,
+        }
+    }
+
+    /// Render as a standalone HTML page for the built site. Titles, tags and
+    /// search queries come from writers and visitors, so all are escaped.
+    pub fn render_html(&self, site_title: &str) -> String {
+        let rows = |map: &BTreeMap<String, usize>| -> String {
+            map.iter()
+                .map(|(k, v)| format!("<tr><td>{}</td><td>{}</td></tr>", html_escape(k), v))
+                .collect()
+        };
+
+        let orphans: String = self.orphans.iter()
+            .map(|o| format!(r#"<li><a href="{}.html">{}</a></li>"#, html_escape(&o.slug), html_escape(&o.title)))
+            .collect();
+        let broken: String = self.broken_links.iter()
+            .map(|b| format!("<li>{} → <code>[[{}]]</code></li>", html_escape(&b.from), html_escape(&b.target)))
+            .collect();
+        let overdue: String = self.overdue_reviews.iter()
+            .map(|r| {
+                format!(
+                    r#"<li><a href="{}.html">{}</a> — {}</li>"#,
+                    html_escape(&r.slug),
+                    html_escape(&r.title),
+                    r.review_due.format("%d.%m.%Y")
+                )
+            })
+            .collect();
+        let queries: String = self.top_queries.iter()
+            .map(|(q, n)| format!("<tr><td>{}</td><td>{}</td></tr>", html_escape(q), n))
+            .collect();
+
+        format!(r#"<!DOCTYPE html>
+<html lang="ru">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Статистика — {title}</title>
+    <link rel="stylesheet" href="fonts/fonts.css">
+    <style>
+        body {{
+            font-family: 'PT Sans', 'Segoe UI', system-ui, sans-serif;
+            background: #1a1a2e;
+            color: #eee;
+            line-height: 1.6;
+            padding: 2rem;
+            max-width: 800px;
+            margin: 0 auto;
+        }}
+        a {{ color: #58a6ff; text-decoration: none; }}
+        h1, h2 {{ color: #e94560; margin: 1.5rem 0 0.75rem; }}
+        .cards {{ display: flex; gap: 1rem; flex-wrap: wrap; }}
+        .card {{ background: #16213e; padding: 1rem 1.5rem; border-radius: 8px; }}
+        .card b {{ display: block; font-size: 1.75rem; color: #e94560; }}
+        table {{ width: 100%; border-collapse: collapse; }}
+        td {{ border: 1px solid #0f3460; padding: 0.4rem 0.6rem; }}
+        code {{ background: #0d1117; padding: 0.1rem 0.3rem; border-radius: 4px; }}
+    </style>
+</head>
+<body>
+    <a href="index.html">← Главная</a>
+    <h1>📊 Статистика базы знаний</h1>
+    <div class="cards">
+        <div class="card"><b>{total}</b>документов</div>
+        <div class="card"><b>{words}</b>слов</div>
+        <div class="card"><b>{avg}</b>слов в среднем</div>
+    </div>
+    <h2>По статусу</h2>
+    <table>{status_rows}</table>
+    <h2>По тегам</h2>
+    <table>{tag_rows}</table>
+    <h2>Без входящих ссылок ({orphan_count})</h2>
+    <ul>{orphans}</ul>
+    <h2>Битые ссылки ({broken_count})</h2>
+    <ul>{broken}</ul>
+    <h2>Срок проверки истёк ({overdue_count})</h2>
+    <ul>{overdue}</ul>
+    <h2>Популярные запросы</h2>
+    <table>{queries}</table>
+</body>
+</html>"#,
+            title = html_escape(site_title),
+            total = self.total_documents,
+            words = self.total_words,
+            avg = self.avg_words,
+            status_rows = rows(&self.by_status),
+            tag_rows = rows(&self.by_tag),
+            orphan_count = self.orphans.len(),
+            orphans = orphans,
+            broken_count = self.broken_links.len(),
+            broken = broken,
+            overdue_count = self.overdue_reviews.len(),
+            overdue = overdue,
+            queries = queries,
+        )
+    }
+}
+
+/// Documents past their review date on `today`, longest overdue first
+pub fn overdue_reviews(documents: &[Document], today: NaiveDate) -> Vec<OverdueReview> {
+    let mut overdue: Vec<OverdueReview> = documents
+        .iter()
+        .filter_map(|d| match d.review_due {
+            Some(due) if due < today => Some(OverdueReview { slug: d.slug(), title: d.title.clone(), review_due: due }),
+            _ => None,
+        })
+        .collect();
+    overdue.sort_by(|a, b| a.review_due.cmp(&b.review_due).then_with(|| a.title.cmp(&b.title)));
+    overdue
+}
+
+impl std::fmt::Display for KnowledgeStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "📚 Документов: {} ({} слов, в среднем {})", self.total_documents, self.total_words, self.avg_words)?;
+        for (status, count) in &self.by_status {
+            writeln!(f, "   {}: {}", status, count)?;
+        }
+        if !self.by_tag.is_empty() {
+            writeln!(f, "🏷️ Теги:")?;
+            for (tag, count) in &self.by_tag {
+                writeln!(f, "   {}: {}", tag, count)?;
+            }
+        }
+        writeln!(f, "🏝️ Без входящих ссылок: {}", self.orphans.len())?;
+        for orphan in &self.orphans {
+            writeln!(f, "   {} ({})", orphan.title, orphan.slug)?;
+        }
+        writeln!(f, "💔 Битые ссылки: {}", self.broken_links.len())?;
+        for link in &self.broken_links {
+            writeln!(f, "   {} → [[{}]]", link.from, link.target)?;
+        }
+        if !self.overdue_reviews.is_empty() {
+            writeln!(f, "⏰ Срок проверки истёк: {}", self.overdue_reviews.len())?;
+            for review in &self.overdue_reviews {
+                writeln!(f, "   {} ({}) — {}", review.title, review.slug, review.review_due.format("%d.%m.%Y"))?;
+            }
+        }
+        if !self.top_queries.is_empty() {
+            writeln!(f, "🔍 Популярные запросы:")?;
+            for (query, count) in &self.top_queries {
+                writeln!(f, "   {} ({})", query, count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn doc(name: &str, title: &str, content: &str) -> Document {
+        Document {
+            path: PathBuf::from(format!("{}.md", name)),
+            rel_path: PathBuf::from(format!("{}.md", name)),
+            slug_override: None,
+            title: title.to_string(),
+            status: DocumentStatus::Public,
+            tags: vec!["guide".to_string()],
+            created: None,
+            updated: None,
+            aliases: Vec::new(),
+            toc: true,
+            pinned: false,
+            order: None,
+            review_due: None,
+            source: None,
+            content: content.to_string(),
+            raw_content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_collect_stats() {
+        let docs = vec![
+            doc("index", "Home", "See [[FAQ]] and [[Missing]]"),
+            doc("faq", "FAQ", "Questions here"),
+            doc("lonely", "Lonely", "Nobody links me"),
+        ];
+        let stats = KnowledgeStats::collect(&docs, vec![("superset".to_string(), 3)]);
+
+        assert_eq!(stats.total_documents, 3);
+        assert_eq!(stats.by_status["public"], 3);
+        assert_eq!(stats.by_tag["guide"], 3);
+        assert_eq!(stats.orphans.len(), 1);
+        assert_eq!(stats.orphans[0].slug, "lonely");
+        assert_eq!(stats.broken_links.len(), 1);
+        assert_eq!(stats.broken_links[0].target, "Missing");
+    }
+
+    #[test]
+    fn test_render_html_escapes_user_text() {
+        let docs = vec![doc("index", "Home", ""), doc("xss", "<b>Title</b>", "")];
+        let stats = KnowledgeStats::collect(&docs, vec![("<script>alert(1)</script>".to_string(), 1)]);
+        let html = stats.render_html("Docs");
+        assert!(!html.contains("<script>alert"));
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(html.contains("&lt;b&gt;Title&lt;/b&gt;"));
+    }
+
+    #[test]
+    fn test_overdue_reviews() {
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let mut policy = doc("policy", "Policy", "");
+        policy.review_due = Some(date("2024-01-31"));
+        let mut faq = doc("faq", "FAQ", "");
+        faq.review_due = Some(date("2024-06-01"));
+        let mut fresh = doc("fresh", "Fresh", "");
+        fresh.review_due = Some(date("2024-12-31"));
+        let docs = vec![faq, doc("plain", "Plain", ""), fresh, policy];
+
+        let overdue = overdue_reviews(&docs, date("2024-07-01"));
+        let slugs: Vec<&str> = overdue.iter().map(|r| r.slug.as_str()).collect();
+        assert_eq!(slugs, vec!["policy", "faq"]);
+        // Due today is not overdue yet
+        assert!(overdue_reviews(&docs, date("2024-01-31")).is_empty());
+    }
+}
@@ -0,0 +1,246 @@
+//! HTML templates for the built site (Tera)
+//!
+//! Built-in layouts live in `templates/`; any `*.html` file placed in
+//! `knowledge/_layouts/` overrides the built-in one with the same name
+//! (`page.html`, `index.html`) or adds a partial for `{% include %}`.
+//! `print.css` there replaces the built-in print stylesheet, which pages load
+//! for printing and, with `?print=1`, on screen.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+use tera::Tera;
+
+/// Folder (inside the docs root) with user layouts
+pub const LAYOUTS_DIR: &str = "_layouts";
+
+const PAGE_TEMPLATE: &str = "page.html";
+const INDEX_TEMPLATE: &str = "index.html";
+
+/// Print stylesheet written next to the pages
+pub const PRINT_STYLESHEET: &str = "print.css";
+
+const DEFAULT_PAGE: &str = include_str!("templates/page.html");
+const DEFAULT_INDEX: &str = include_str!("templates/index.html");
+const DEFAULT_PRINT_CSS: &str = include_str!("templates/print.css");
+
+/// Heading in a page's table of contents
+#[derive(Debug, Clone, Serialize)]
+pub struct TocEntry {
+    /// 2 or 3
+    pub level: u8,
+    /// Anchor of the heading
+    pub id: String,
+    pub text: String,
+}
+
+/// Variables available in `page.html`
+#[derive(Debug, Serialize)]
+pub struct PageContext<'a> {
+    pub title: &'a str,
+    pub slug: String,
+    /// Relative path back to the site root (`./`, `../`); prefix links to
+    /// site files with it, pages have no `<base href>`
+    pub base: String,
+    /// Rendered document HTML (use `{{ content | safe }}`)
+    pub content: String,
+    /// h2/h3 outline; empty for short pages and `toc: false`
+    pub toc: Vec<TocEntry>,
+    pub created: Option<String>,
+    pub updated: Option<String>,
+    pub tags: &'a [String],
+    /// `review_due` as YYYY-MM-DD (the page re-checks it in the browser)
+    pub review_due: Option<String>,
+    /// Review date had passed when the site was built
+    pub review_overdue: bool,
+    /// Badge of the extra root the page comes from
+    pub source: Option<&'a str>,
+}
+
+/// Document entry in `index.html`
+#[derive(Debug, Serialize)]
+pub struct IndexEntry<'a> {
+    pub title: &'a str,
+    pub slug: String,
+    pub created: String,
+    pub updated: String,
+    /// `pinned: true` in frontmatter; pinned entries come first
+    pub pinned: bool,
+    /// Badge of the extra root the page comes from
+    pub source: Option<&'a str>,
+    pub tags: &'a [String],
+}
+
+/// Variables available in `index.html`
+#[derive(Debug, Serialize)]
+pub struct IndexContext<'a> {
+    pub site_title: &'a str,
+    pub documents: Vec<IndexEntry<'a>>,
+}
+
+/// Compiled site templates
+pub struct Templates {
+    tera: Tera,
+}
+
+impl Templates {
+    /// Built-in templates only
+    pub fn builtin() -> Self {
+        let mut tera = Tera::default();
+        tera.add_raw_templates(vec![(PAGE_TEMPLATE, DEFAULT_PAGE), (INDEX_TEMPLATE, DEFAULT_INDEX)])
+            .expect("built-in templates are valid");
+        Self { tera }
+    }
+
+    /// Built-in templates overridden by `<docs_root>/_layouts/*.html`
+    pub fn load(docs_root: &Path) -> Result<Self> {
+        let mut templates = Self::builtin();
+        let layouts = docs_root.join(LAYOUTS_DIR);
+        if !layouts.is_dir() {
+            return Ok(templates);
+        }
+
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(&layouts)? {
+            let path = entry?.path();
+            if path.extension().map_or(false, |e| e == "html") {
+                let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                files.push((path, Some(name)));
+            }
+        }
+        templates.tera.add_template_files(files)
+            .with_context(|| format!("Invalid layout in {}", layouts.display()))?;
+        Ok(templates)
+    }
+
+    /// Write the built-in templates to `<docs_root>/_layouts/` for editing.
+    /// Existing files are kept; returns the names written.
+    pub fn eject(docs_root: &Path) -> Result<Vec<&'static str>> {
+        let layouts = docs_root.join(LAYOUTS_DIR);
+        std::fs::create_dir_all(&layouts)?;
+
+        let mut written = Vec::new();
+        for (name, content) in [
+            (PAGE_TEMPLATE, DEFAULT_PAGE),
+            (INDEX_TEMPLATE, DEFAULT_INDEX),
+            (PRINT_STYLESHEET, DEFAULT_PRINT_CSS),
+        ] {
+            let path = layouts.join(name);
+            if !path.exists() {
+                std::fs::write(&path, content)?;
+                written.push(name);
+            }
+        }
+        Ok(written)
+    }
+
+    /// Write the print stylesheet (`_layouts/print.css` or the built-in one)
+    /// to `output_dir`
+    pub fn write_print_css(docs_root: &Path, output_dir: &Path) -> Result<()> {
+        let custom = docs_root.join(LAYOUTS_DIR).join(PRINT_STYLESHEET);
+        let css = match std::fs::read_to_string(&custom) {
+            Ok(css) => css,
+            Err(_) => DEFAULT_PRINT_CSS.to_string(),
+        };
+        std::fs::write(output_dir.join(PRINT_STYLESHEET), css)?;
+        Ok(())
+    }
+
+    /// Render a document page
+    pub fn render_page(&self, ctx: &PageContext) -> Result<String> {
+        let context = tera::Context::from_serialize(ctx)?;
+        Ok(self.tera.render(PAGE_TEMPLATE, &context)?)
+    }
+
+    /// Render the site index
+    pub fn render_index(&self, ctx: &IndexContext) -> Result<String> {
+        let context = tera::Context::from_serialize(ctx)?;
+        Ok(self.tera.render(INDEX_TEMPLATE, &context)?)
+    }
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_user_layout_overrides_builtin() {
+        let dir = tempdir().unwrap();
+        let layouts = dir.path().join(LAYOUTS_DIR);
+        std::fs::create_dir_all(&layouts).unwrap();
+        std::fs::write(layouts.join("page.html"), "<h1>{{ title }}</h1>{{ content | safe }}").unwrap();
+
+        let templates = Templates::load(dir.path()).unwrap();
+        let html = templates.render_page(&PageContext {
+            title: "FAQ & Help",
+            slug: "faq".to_string(),
+            base: "./".to_string(),
+            content: "<p>Hi</p>".to_string(),
+            toc: Vec::new(),
+            created: None,
+            updated: None,
+            tags: &[],
+            review_due: None,
+            review_overdue: false,
+            source: None,
+        }).unwrap();
+        assert_eq!(html, "<h1>FAQ &amp; Help</h1><p>Hi</p>");
+
+        // Index still falls back to the built-in layout
+        let index = templates.render_index(&IndexContext { site_title: "KB", documents: Vec::new() }).unwrap();
+        assert!(index.contains("📚 KB"));
+    }
+
+    #[test]
+    fn test_print_stylesheet() {
+        let dir = tempdir().unwrap();
+        let html = Templates::builtin().render_page(&PageContext {
+            title: "Регламент",
+            slug: "sop/backup".to_string(),
+            base: "../".to_string(),
+            content: String::new(),
+            toc: Vec::new(),
+            created: None,
+            updated: None,
+            tags: &[],
+            review_due: None,
+            review_overdue: false,
+            source: None,
+        }).unwrap();
+        assert!(html.contains(r#"href="../print.css" media="print""#));
+        assert!(html.contains(r#"href="?print=1""#));
+        assert!(!html.contains("<base"));
+
+        Templates::write_print_css(dir.path(), dir.path()).unwrap();
+        assert!(std::fs::read_to_string(dir.path().join(PRINT_STYLESHEET)).unwrap().contains(".toc"));
+        std::fs::create_dir_all(dir.path().join(LAYOUTS_DIR)).unwrap();
+        std::fs::write(dir.path().join(LAYOUTS_DIR).join(PRINT_STYLESHEET), "body { color: red; }").unwrap();
+        Templates::write_print_css(dir.path(), dir.path()).unwrap();
+        assert_eq!(std::fs::read_to_string(dir.path().join(PRINT_STYLESHEET)).unwrap(), "body { color: red; }");
+    }
+
+    #[test]
+    fn test_review_banner() {
+        let html = Templates::builtin().render_page(&PageContext {
+            title: "Регламент",
+            slug: "sop/backup".to_string(),
+            base: "../".to_string(),
+            content: String::new(),
+            toc: Vec::new(),
+            created: None,
+            updated: None,
+            tags: &[],
+            review_due: Some("2024-01-31".to_string()),
+            review_overdue: true,
+            source: None,
+        }).unwrap();
+        assert!(html.contains(r#"<div class="outdated" data-review-due="2024-01-31">"#));
+    }
+}
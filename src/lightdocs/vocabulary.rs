@@ -0,0 +1,155 @@
+//! Synonyms and stopwords for search (`search.synonyms.yaml` in the kit root)
+//!
+//! ```yaml
+//! synonyms:
+//!   - БД = база данных = database
+//!   - [дашборд, dashboard, панель]
+//! stopwords: [также, который, this]
+//! ```
+//!
+//! Every phrase of a group is indexed and searched as the group's first
+//! phrase, so a query for «база данных» finds articles that only say
+//! «database» or «БД» (the original words stay searchable too). Stopwords
+//! are dropped from texts and queries alike. The index remembers the
+//! vocabulary it was built with; after editing the file run
+//! `lightdocs reindex` so articles already indexed pick it up.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use super::search::SearchIndex;
+
+pub const VOCABULARY_FILE: &str = "search.synonyms.yaml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct VocabularyFile {
+    synonyms: Vec<SynonymGroup>,
+    stopwords: Vec<String>,
+}
+
+/// `a = b = c` or a list of phrases
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SynonymGroup {
+    Line(String),
+    List(Vec<String>),
+}
+
+/// Parsed synonym groups and stopwords
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Vocabulary {
+    /// Term every phrase of the group is indexed as, and the phrases as tokens
+    groups: Vec<(String, Vec<Vec<String>>)>,
+    stopwords: BTreeSet<String>,
+}
+
+impl Vocabulary {
+    /// Vocabulary of the kit; empty (with a warning) when the file is broken
+    pub fn load(root: &Path) -> Self {
+        let path = root.join(VOCABULARY_FILE);
+        if !path.exists() {
+            return Self::default();
+        }
+        match std::fs::read_to_string(&path).map_err(anyhow::Error::from).and_then(|text| Self::parse(&text)) {
+            Ok(vocabulary) => vocabulary,
+            Err(e) => {
+                tracing::warn!("{} ignored: {:#}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let file: VocabularyFile = serde_yaml::from_str(text).context("Invalid search vocabulary")?;
+        let mut vocabulary = Self::default();
+        for group in file.synonyms {
+            let phrases = match group {
+                SynonymGroup::Line(line) => line.split('=').map(str::to_string).collect(),
+                SynonymGroup::List(phrases) => phrases,
+            };
+            let phrases: Vec<Vec<String>> =
+                phrases.iter().map(|p| SearchIndex::tokenize(p)).filter(|tokens| !tokens.is_empty()).collect();
+            if phrases.len() > 1 {
+                vocabulary.groups.push((phrases[0].join(" "), phrases));
+            }
+        }
+        vocabulary.stopwords = file.stopwords.iter().flat_map(|w| SearchIndex::tokenize(w)).collect();
+        Ok(vocabulary)
+    }
+
+    /// Longest phrase starting at `tokens[at]`: its group term and length
+    fn phrase_at(&self, tokens: &[String], at: usize) -> Option<(&str, usize)> {
+        self.groups
+            .iter()
+            .flat_map(|(term, phrases)| phrases.iter().map(move |phrase| (term.as_str(), phrase)))
+            .filter(|(_, phrase)| tokens[at..].starts_with(phrase))
+            .max_by_key(|(_, phrase)| phrase.len())
+            .map(|(term, phrase)| (term, phrase.len()))
+    }
+
+    /// Text tokens as indexed: stopwords out, group terms added after their phrases
+    pub fn index_terms(&self, tokens: Vec<String>) -> Vec<String> {
+        self.apply(tokens, true)
+    }
+
+    /// Query tokens as searched: stopwords out, phrases replaced by their group term
+    pub fn query_terms(&self, tokens: Vec<String>) -> Vec<String> {
+        self.apply(tokens, false)
+    }
+
+    fn apply(&self, tokens: Vec<String>, keep_phrases: bool) -> Vec<String> {
+        let mut terms = Vec::with_capacity(tokens.len());
+        let mut at = 0;
+        while at < tokens.len() {
+            if let Some((term, len)) = self.phrase_at(&tokens, at) {
+                if keep_phrases {
+                    terms.extend(tokens[at..at + len].iter().filter(|t| !self.stopwords.contains(*t)).cloned());
+                }
+                terms.push(term.to_string());
+                at += len;
+                continue;
+            }
+            if !self.stopwords.contains(&tokens[at]) {
+                terms.push(tokens[at].clone());
+            }
+            at += 1;
+        }
+        terms
+    }
+
+    /// Stable text form, stored in the index to notice a changed vocabulary
+    pub fn fingerprint(&self) -> String {
+        serde_json::json!({"synonyms": self.groups, "stopwords": self.stopwords}).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_synonyms_and_stopwords() {
+        let vocabulary = Vocabulary::parse(
+            "synonyms:\n  - БД = база данных = database\n  - [дашборд, dashboard]\nstopwords: [также, который]\n",
+        )
+        .unwrap();
+        let tokens = |text: &str| SearchIndex::tokenize(text);
+
+        assert_eq!(
+            vocabulary.index_terms(tokens("Также который database")),
+            ["database", "бд"]
+        );
+        assert_eq!(
+            vocabulary.index_terms(tokens("Подключение к базе данных, база данных SQLite")),
+            ["подключение", "базе", "данных", "база", "данных", "бд", "sqlite"]
+        );
+        assert_eq!(vocabulary.query_terms(tokens("база данных дашборд")), ["бд", "дашборд"]);
+        assert_eq!(vocabulary.query_terms(tokens("Dashboard")), ["дашборд"]);
+
+        assert_ne!(vocabulary.fingerprint(), Vocabulary::default().fingerprint());
+        assert!(Vocabulary::parse("synonyms: 5").is_err());
+    }
+}
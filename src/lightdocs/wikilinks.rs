@@ -1,8 +1,27 @@
 //! Wikilinks transformer [[Article Name]] -> [Article Name](./article-name.html)
+//!
+//! Section links are supported too: `[[Article Name#Section]]` points to a
+//! heading in another document, `[[#Section]]` to one in the current page.
+//! Heading IDs are generated with [`WikilinksTransformer::heading_id`].
+//!
+//! `[[file:report.xlsx]]` links a file stored next to the note (copied into
+//! the site on build and offered as a download), `[[https://...|Label]]` an
+//! external page. Both get an icon so readers see where a link leads.
 
 use regex::Regex;
 use std::collections::HashMap;
 
+use crate::html::html_escape;
+
+/// `[[Title#Section|Display]]` — title, section and display text are optional parts
+const WIKILINK_PATTERN: &str = r"\[\[([^\]|#]*)(?:#([^\]|]+))?(?:\|([^\]]+))?\]\]";
+
+/// Prefix of attachment links, `[[file:path/to/file.ext]]`
+const FILE_PREFIX: &str = "file:";
+
+/// Link targets that are external URLs rather than document titles
+const URL_SCHEMES: &[&str] = &["http://", "https://", "mailto:"];
+
 /// Transforms wikilinks to standard markdown links
 pub struct WikilinksTransformer {
     /// Map of document titles/aliases to their slugs
@@ -30,16 +49,67 @@ impl WikilinksTransformer {
         }
     }
     
+    /// Resolve a link target (title or alias) to a registered slug
+    pub fn resolve(&self, title: &str) -> Option<String> {
+        self.title_map.get(&title.to_lowercase()).cloned()
+    }
+    
     /// Transform all wikilinks in content to standard links
     pub fn transform(&self, content: &str) -> String {
-        // Match [[Title]] or [[Title|Display Text]]
-        let re = Regex::new(r"\[\[([^\]|]+)(?:\|([^\]]+))?\]\]").unwrap();
+        self.transform_in(content, None)
+    }
+    
+    /// Transform wikilinks in the document with slug `current`; links are
+    /// relative to that page, so they work from nested folders too
+    pub fn transform_in(&self, content: &str, current: Option<&str>) -> String {
+        self.transform_page(content, current, "")
+    }
+    
+    /// Like `transform_in`, resolving `[[file:...]]` links against the note's
+    /// `folder` (relative to the docs root, `""` for top-level notes)
+    pub fn transform_page(&self, content: &str, current: Option<&str>, folder: &str) -> String {
+        let re = Regex::new(WIKILINK_PATTERN).unwrap();
+        let root = current.map_or_else(|| "./".to_string(), site_root);
         
         re.replace_all(content, |caps: &regex::Captures| {
-            let title = &caps[1];
-            let display = caps.get(2)
-                .map(|m| m.as_str())
-                .unwrap_or(title);
+            let title = caps[1].trim();
+            let section = caps.get(2).map(|m| m.as_str().trim());
+            if title.is_empty() && section.is_none() {
+                return caps[0].to_string();
+            }
+            
+            if let Some(path) = title.strip_prefix(FILE_PREFIX) {
+                let display = caps.get(3).map(|m| m.as_str().trim());
+                return file_link(path.trim(), folder, &root, display).unwrap_or_else(|| caps[0].to_string());
+            }
+            if is_url(title) {
+                // The pattern splits `https://host/page#part` at the `#`
+                let url = match section {
+                    Some(fragment) => format!("{}#{}", title, fragment),
+                    None => title.to_string(),
+                };
+                let display = caps.get(3).map_or(url.as_str(), |m| m.as_str().trim());
+                return format!(
+                    "<a href=\"{}\" class=\"wikilink-external\" target=\"_blank\" rel=\"noopener\">🔗 {}</a>",
+                    html_escape(&url),
+                    html_escape(display)
+                );
+            }
+            
+            let display = match (caps.get(3), section) {
+                (Some(display), _) => display.as_str().to_string(),
+                (None, Some(section)) if title.is_empty() => section.to_string(),
+                (None, Some(section)) => format!("{} › {}", title, section),
+                (None, None) => title.to_string(),
+            };
+            
+            let anchor = section
+                .map(|s| format!("#{}", encode_anchor(&Self::heading_id(s))))
+                .unwrap_or_default();
+            
+            if title.is_empty() {
+                return format!("[{}]({})", display, anchor);
+            }
             
             // Look up slug in map, or create from title
             let slug = self.title_map
@@ -47,10 +117,15 @@ impl WikilinksTransformer {
                 .map(|s| s.clone())
                 .unwrap_or_else(|| Self::title_to_slug(title));
             
-            format!("[{}](./{}.html)", display, slug)
+            format!("[{}]({}{}.html{})", display, root, slug, anchor)
         }).to_string()
     }
     
+    /// HTML id of a heading, as referenced by `[[Doc#Heading]]`
+    pub fn heading_id(text: &str) -> String {
+        Self::title_to_slug(text)
+    }
+    
     /// Convert title to URL-safe slug
     pub fn title_to_slug(title: &str) -> String {
         let slug: String = title
@@ -76,11 +151,22 @@ impl WikilinksTransformer {
             .to_string()
     }
     
-    /// Extract all wikilinks from content
+    /// Extract all linked document titles from content (same-page section links,
+    /// attachments and URLs excluded)
     pub fn extract_links(content: &str) -> Vec<String> {
-        let re = Regex::new(r"\[\[([^\]|]+)(?:\|[^\]]+)?\]\]").unwrap();
+        let re = Regex::new(WIKILINK_PATTERN).unwrap();
+        re.captures_iter(content)
+            .map(|c| c[1].trim().to_string())
+            .filter(|title| !title.is_empty() && !title.starts_with(FILE_PREFIX) && !is_url(title))
+            .collect()
+    }
+    
+    /// Attachment paths from `[[file:...]]` links, relative to the note's folder
+    pub fn extract_attachments(content: &str) -> Vec<String> {
+        let re = Regex::new(WIKILINK_PATTERN).unwrap();
         re.captures_iter(content)
-            .map(|c| c[1].to_string())
+            .filter_map(|c| c[1].trim().strip_prefix(FILE_PREFIX).map(|p| p.trim().to_string()))
+            .filter(|path| safe_relative(path))
             .collect()
     }
     
@@ -93,6 +179,75 @@ impl WikilinksTransformer {
     }
 }
 
+/// Relative path from the page `slug` back to the site root (`./` for
+/// top-level pages, `../` for `guides/faq`)
+pub fn site_root(slug: &str) -> String {
+    let depth = slug.matches('/').count();
+    if depth == 0 {
+        "./".to_string()
+    } else {
+        "../".repeat(depth)
+    }
+}
+
+fn is_url(target: &str) -> bool {
+    let lower = target.to_lowercase();
+    URL_SCHEMES.iter().any(|scheme| lower.starts_with(scheme))
+}
+
+/// A relative path that stays inside the docs folder
+fn safe_relative(path: &str) -> bool {
+    !path.is_empty()
+        && !path.starts_with('/')
+        && !path.contains(':')
+        && path.split(['/', '\\']).all(|part| part != "..")
+}
+
+/// Download link for an attachment (`None` for paths leaving the docs folder)
+fn file_link(path: &str, folder: &str, root: &str, display: Option<&str>) -> Option<String> {
+    if !safe_relative(path) {
+        return None;
+    }
+    let path = path.replace('\\', "/");
+    let name = path.rsplit('/').next().unwrap_or(&path);
+    let extension = name.rsplit_once('.').map(|(_, ext)| ext.to_lowercase()).unwrap_or_default();
+    let full = if folder.is_empty() { path.clone() } else { format!("{}/{}", folder.trim_end_matches('/'), path) };
+    let href: Vec<String> = full.split('/').map(encode_anchor).collect();
+    Some(format!(
+        "<a href=\"{}{}\" class=\"wikilink-file\" download>{} {}</a>",
+        root,
+        href.join("/"),
+        file_icon(&extension),
+        html_escape(display.unwrap_or(name))
+    ))
+}
+
+fn file_icon(extension: &str) -> &'static str {
+    match extension {
+        "xlsx" | "xls" | "csv" | "ods" => "📊",
+        "pdf" => "📕",
+        "doc" | "docx" | "odt" | "txt" | "rtf" => "📝",
+        "ppt" | "pptx" | "odp" => "📽️",
+        "zip" | "7z" | "rar" => "🗜️",
+        "png" | "jpg" | "jpeg" | "gif" | "svg" => "🖼️",
+        "sql" | "py" | "json" | "yaml" | "yml" => "🧾",
+        _ => "📎",
+    }
+}
+
+/// Percent-encode an anchor for use in a URL fragment
+fn encode_anchor(anchor: &str) -> String {
+    let mut encoded = String::with_capacity(anchor.len());
+    for byte in anchor.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
 impl Default for WikilinksTransformer {
     fn default() -> Self {
         Self::new()
@@ -134,8 +289,54 @@ mod tests {
     
     #[test]
     fn test_extract_links() {
-        let content = "See [[Page1]] and [[Page2|Alias]] for more.";
+        let content = "See [[Page1]] and [[Page2|Alias]] for more, [[Page3#Setup]] and [[#Local]].";
         let links = WikilinksTransformer::extract_links(content);
-        assert_eq!(links, vec!["Page1", "Page2"]);
+        assert_eq!(links, vec!["Page1", "Page2", "Page3"]);
+    }
+    
+    #[test]
+    fn test_section_links() {
+        let mut transformer = WikilinksTransformer::new();
+        transformer.register("FAQ", "faq");
+        
+        assert_eq!(
+            transformer.transform("[[FAQ#Setup Guide]]"),
+            "[FAQ › Setup Guide](./faq.html#setup-guide)"
+        );
+        assert_eq!(
+            transformer.transform("[[FAQ#Установка|здесь]]"),
+            "[здесь](./faq.html#%D1%83%D1%81%D1%82%D0%B0%D0%BD%D0%BE%D0%B2%D0%BA%D0%B0)"
+        );
+        assert_eq!(transformer.transform_in("[[#Setup]]", Some("guides/start")), "[Setup](#setup)");
+        assert_eq!(transformer.transform("[[#Setup]]"), "[Setup](#setup)");
+        // Links from nested pages climb back to the site root
+        assert_eq!(transformer.transform_in("[[FAQ]]", Some("guides/sub/start")), "[FAQ](../../faq.html)");
+    }
+    
+    #[test]
+    fn test_file_and_url_links() {
+        let transformer = WikilinksTransformer::new();
+        
+        assert_eq!(
+            transformer.transform_page("[[file:Отчёт 2024.xlsx]]", None, "ops"),
+            "<a href=\"./ops/%D0%9E%D1%82%D1%87%D1%91%D1%82%202024.xlsx\" class=\"wikilink-file\" download>📊 Отчёт 2024.xlsx</a>"
+        );
+        assert_eq!(
+            transformer.transform_page("[[file:plan.pdf]]", Some("ops/report"), "ops"),
+            "<a href=\"../ops/plan.pdf\" class=\"wikilink-file\" download>📕 plan.pdf</a>"
+        );
+        assert_eq!(
+            transformer.transform("[[file:plan.pdf|План]]"),
+            "<a href=\"./plan.pdf\" class=\"wikilink-file\" download>📕 План</a>"
+        );
+        assert_eq!(transformer.transform("[[file:../secret.txt]]"), "[[file:../secret.txt]]");
+        assert_eq!(
+            transformer.transform("[[https://superset.apache.org/docs#intro|Docs]]"),
+            "<a href=\"https://superset.apache.org/docs#intro\" class=\"wikilink-external\" target=\"_blank\" rel=\"noopener\">🔗 Docs</a>"
+        );
+        
+        let content = "[[FAQ]] [[file:a/b.csv]] [[https://example.com]] [[file:../x]]";
+        assert_eq!(WikilinksTransformer::extract_links(content), vec!["FAQ"]);
+        assert_eq!(WikilinksTransformer::extract_attachments(content), vec!["a/b.csv"]);
     }
 }
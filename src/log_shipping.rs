@@ -0,0 +1,394 @@
+//! Rolling log shipping to a network drop folder
+//!
+//! Some sites collect logs only as files on a share. With
+//! `log_shipping.enabled`, the launcher also writes its own log (gateway
+//! included) to `logs/launcher.log`, and every few minutes copies what was
+//! appended to each `logs/*.log` since the last run into
+//! `<destination>/<computer>/<log>-YYYYMMDD-HHMMSS.log`. A piece is written
+//! as `.part` and renamed when complete, so the collector never picks up half
+//! a file. Shipped offsets are kept in `logs/.shipping.json`; a log that was
+//! recreated (Superset truncates its logs on start) is shipped from the top.
+//!
+//! An unreachable share is retried with doubling delays up to
+//! `max_backoff_secs`; nothing is lost meanwhile. A full share raises one
+//! notification per episode. `launcher.log` is emptied once shipped and
+//! bigger than `launcher_log_max_mb`, or regardless at twice that size so an
+//! unreachable share can't fill the flash drive.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{info, warn};
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::notifications::{self, Level};
+
+const LOG_DIR: &str = "logs";
+const LAUNCHER_LOG: &str = "launcher.log";
+const STATE_FILE: &str = ".shipping.json";
+
+/// Lines kept in memory for crash reports
+const RECENT_LINES: usize = 100;
+
+/// The launcher's own log file, once [`open_launcher_log`] has run
+static LAUNCHER_FILE: Mutex<Option<File>> = Mutex::new(None);
+
+/// Last `RECENT_LINES` lines logged, whether or not the file is open
+static RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Log shipping settings (`log_shipping` section of config.json)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogShippingConfig {
+    pub enabled: bool,
+    /// Drop folder, usually a UNC path (`\\logserver\drop\superset`)
+    pub destination: PathBuf,
+    /// Time between two shipping runs
+    pub interval_secs: u64,
+    /// Longest wait between retries while the share is unreachable
+    pub max_backoff_secs: u64,
+    /// `launcher.log` is emptied after shipping once it is this big
+    pub launcher_log_max_mb: u64,
+}
+
+impl Default for LogShippingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            destination: PathBuf::new(),
+            interval_secs: 300,
+            max_backoff_secs: 3600,
+            launcher_log_max_mb: 20,
+        }
+    }
+}
+
+/// Shipped part of one log
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Shipped {
+    offset: u64,
+    /// Creation time, to notice a recreated file that already grew past `offset`
+    created: Option<u64>,
+}
+
+/// Result of one shipping run
+#[derive(Debug, Default)]
+pub struct ShipReport {
+    pub files: Vec<PathBuf>,
+    pub bytes: u64,
+}
+
+/// `MakeWriter` for the tracing subscriber: stdout, plus `logs/launcher.log`
+/// when it is open (without the terminal colour codes)
+pub struct LauncherLog;
+
+impl<'a> MakeWriter<'a> for LauncherLog {
+    type Writer = LauncherLogWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        LauncherLogWriter
+    }
+}
+
+pub struct LauncherLogWriter;
+
+impl Write for LauncherLogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = io::stdout().write(buf)?;
+        let plain = strip_ansi(&buf[..written]);
+        if let Ok(mut guard) = LAUNCHER_FILE.lock() {
+            if let Some(file) = guard.as_mut() {
+                // A full drive must not stop the launcher; the line is lost
+                let _ = file.write_all(&plain);
+            }
+        }
+        if let Ok(mut recent) = RECENT.lock() {
+            for line in String::from_utf8_lossy(&plain).lines().filter(|l| !l.trim().is_empty()) {
+                if recent.len() == RECENT_LINES {
+                    recent.pop_front();
+                }
+                recent.push_back(line.to_string());
+            }
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+/// The last lines the launcher logged, oldest first
+pub fn recent_lines() -> Vec<String> {
+    // `try_lock`: called from the panic hook, maybe while this thread logs
+    RECENT.try_lock().map(|recent| recent.iter().cloned().collect()).unwrap_or_default()
+}
+
+/// Remove `ESC [ ... letter` sequences
+fn strip_ansi(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut iter = bytes.iter().copied().peekable();
+    while let Some(b) = iter.next() {
+        if b == 0x1b && iter.peek() == Some(&b'[') {
+            iter.next();
+            for c in iter.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(b);
+        }
+    }
+    out
+}
+
+/// Start copying the launcher's log into `logs/launcher.log`
+pub fn open_launcher_log(root: &Path) -> Result<()> {
+    let dir = root.join(LOG_DIR);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(LAUNCHER_LOG);
+    // Not `append`: on Windows an append-only handle can't be truncated
+    let mut file = OpenOptions::new().create(true).write(true).open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    file.seek(SeekFrom::End(0))?;
+    *LAUNCHER_FILE.lock().unwrap() = Some(file);
+    Ok(())
+}
+
+fn load_state(dir: &Path) -> HashMap<String, Shipped> {
+    fs::read(dir.join(STATE_FILE))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(dir: &Path, state: &HashMap<String, Shipped>) -> Result<()> {
+    fs::write(dir.join(STATE_FILE), serde_json::to_vec_pretty(state)?)?;
+    Ok(())
+}
+
+fn created_secs(meta: &fs::Metadata) -> Option<u64> {
+    meta.created().ok()?.duration_since(std::time::UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+/// ENOSPC, ERROR_DISK_FULL, ERROR_HANDLE_DISK_FULL
+pub fn is_disk_full(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<io::Error>())
+        .any(|e| match e.raw_os_error() {
+            Some(code) if cfg!(windows) => code == 112 || code == 39,
+            Some(code) => code == 28,
+            None => false,
+        })
+}
+
+/// Copy `len` bytes of `src` from `offset` to `dest` through a `.part` file
+fn copy_piece(src: &Path, offset: u64, len: u64, dest: &Path) -> Result<()> {
+    let part = dest.with_extension("log.part");
+    let result = (|| -> Result<()> {
+        let mut input = File::open(src)?;
+        input.seek(SeekFrom::Start(offset))?;
+        let mut output = File::create(&part).with_context(|| format!("Failed to create {}", part.display()))?;
+        let copied = io::copy(&mut input.take(len), &mut output)?;
+        output.sync_all()?;
+        if copied < len {
+            anyhow::bail!("{} shrank while being shipped", src.display());
+        }
+        fs::rename(&part, dest)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&part);
+    }
+    result
+}
+
+/// Ship what was appended to every log since the last run
+pub fn ship_once(root: &Path, config: &LogShippingConfig) -> Result<ShipReport> {
+    let dir = root.join(LOG_DIR);
+    let target = config.destination.join(notifications::hostname());
+    fs::create_dir_all(&target).with_context(|| format!("Drop folder unavailable: {}", target.display()))?;
+
+    let mut state = load_state(&dir);
+    let mut report = ShipReport::default();
+    let mut logs: Vec<PathBuf> = fs::read_dir(&dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().map_or(false, |ext| ext == "log"))
+        .collect();
+    logs.sort();
+
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    for log in logs {
+        let name = log.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let meta = fs::metadata(&log)?;
+        let created = created_secs(&meta);
+        let shipped = state.entry(name.clone()).or_default();
+        if meta.len() < shipped.offset || (shipped.created.is_some() && created != shipped.created) {
+            shipped.offset = 0;
+        }
+        shipped.created = created;
+        if meta.len() == shipped.offset {
+            continue;
+        }
+
+        let stem = log.file_stem().unwrap_or_default().to_string_lossy();
+        let mut dest = target.join(format!("{}-{}.log", stem, stamp));
+        for n in 1.. {
+            if !dest.exists() {
+                break;
+            }
+            dest = target.join(format!("{}-{}-{}.log", stem, stamp, n));
+        }
+        copy_piece(&log, shipped.offset, meta.len() - shipped.offset, &dest)?;
+        report.bytes += meta.len() - shipped.offset;
+        shipped.offset = meta.len();
+        report.files.push(dest);
+        save_state(&dir, &state)?;
+    }
+
+    truncate_launcher_log(&dir, config, &mut state, true)?;
+    Ok(report)
+}
+
+/// Empty `launcher.log` when it is over the limit: after it was shipped, or
+/// at twice the limit whether shipped or not
+fn truncate_launcher_log(dir: &Path, config: &LogShippingConfig, state: &mut HashMap<String, Shipped>, shipped_ok: bool) -> Result<()> {
+    let limit = config.launcher_log_max_mb * 1024 * 1024;
+    let Ok(meta) = fs::metadata(dir.join(LAUNCHER_LOG)) else {
+        return Ok(());
+    };
+    let entry = state.entry(LAUNCHER_LOG.to_string()).or_default();
+    let fully_shipped = shipped_ok && entry.offset == meta.len();
+    if meta.len() <= limit || !(fully_shipped || meta.len() > 2 * limit) {
+        return Ok(());
+    }
+    let dropped = meta.len() - entry.offset.min(meta.len());
+    {
+        let mut guard = LAUNCHER_FILE.lock().unwrap();
+        let Some(file) = guard.as_mut() else {
+            return Ok(());
+        };
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+    }
+    entry.offset = 0;
+    save_state(dir, state)?;
+    // Logged after the lock is released: the writer takes the same lock
+    if dropped > 0 {
+        warn!("launcher.log emptied with {} KB not shipped", dropped / 1024);
+    }
+    Ok(())
+}
+
+/// Ship logs on the configured interval for as long as the launcher runs
+pub fn spawn_shipper(root: PathBuf, config: LogShippingConfig) {
+    if !config.enabled {
+        return;
+    }
+    if config.destination.as_os_str().is_empty() {
+        warn!("log_shipping.enabled is set but log_shipping.destination is empty");
+        return;
+    }
+    info!("📤 Shipping logs to {} every {} s", config.destination.display(), config.interval_secs);
+
+    tokio::spawn(async move {
+        let interval = Duration::from_secs(config.interval_secs.max(10));
+        let max_backoff = Duration::from_secs(config.max_backoff_secs).max(interval);
+        let mut delay = interval;
+        let mut disk_full_reported = false;
+        loop {
+            tokio::time::sleep(delay).await;
+
+            let (ship_root, ship_config) = (root.clone(), config.clone());
+            let result = tokio::task::spawn_blocking(move || {
+                let result = ship_once(&ship_root, &ship_config);
+                if result.is_err() {
+                    // Keep the local log bounded while the share is away
+                    let dir = ship_root.join(LOG_DIR);
+                    let mut state = load_state(&dir);
+                    let _ = truncate_launcher_log(&dir, &ship_config, &mut state, false);
+                }
+                result
+            })
+            .await;
+
+            match result {
+                Ok(Ok(report)) => {
+                    if !report.files.is_empty() {
+                        info!("📤 Shipped {} log pieces ({} KB)", report.files.len(), report.bytes / 1024);
+                    }
+                    delay = interval;
+                    disk_full_reported = false;
+                }
+                Ok(Err(e)) => {
+                    delay = (delay * 2).min(max_backoff);
+                    if is_disk_full(&e) {
+                        if !disk_full_reported {
+                            notifications::notify(
+                                Level::Warning,
+                                "Папка для логов заполнена",
+                                &format!("{} — логи копятся локально и будут отправлены, когда место освободится", config.destination.display()),
+                            );
+                            disk_full_reported = true;
+                        }
+                    } else {
+                        warn!("Log shipping failed, retrying in {} s: {:#}", delay.as_secs(), e);
+                    }
+                }
+                Err(e) => warn!("Log shipping task failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_ship_appended_parts() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("kit");
+        let logs = root.join(LOG_DIR);
+        fs::create_dir_all(&logs).unwrap();
+        let config = LogShippingConfig {
+            enabled: true,
+            destination: dir.path().join("share"),
+            ..LogShippingConfig::default()
+        };
+
+        fs::write(logs.join("superset.stderr.log"), "line 1\n").unwrap();
+        fs::write(logs.join("notes.txt"), "not a log").unwrap();
+        let first = ship_once(&root, &config).unwrap();
+        assert_eq!(first.files.len(), 1);
+        assert_eq!(fs::read_to_string(&first.files[0]).unwrap(), "line 1\n");
+        assert!(ship_once(&root, &config).unwrap().files.is_empty(), "nothing new");
+
+        let mut log = OpenOptions::new().append(true).open(logs.join("superset.stderr.log")).unwrap();
+        log.write_all(b"line 2\n").unwrap();
+        drop(log);
+        let second = ship_once(&root, &config).unwrap();
+        assert_eq!(fs::read_to_string(&second.files[0]).unwrap(), "line 2\n");
+
+        // Recreated smaller: shipped from the top
+        fs::write(logs.join("superset.stderr.log"), "new\n").unwrap();
+        let third = ship_once(&root, &config).unwrap();
+        assert_eq!(fs::read_to_string(&third.files[0]).unwrap(), "new\n");
+
+        let shipped = fs::read_dir(config.destination.join(notifications::hostname())).unwrap().count();
+        assert_eq!(shipped, 3);
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(strip_ansi(b"\x1b[32m INFO\x1b[0m ok"), b" INFO ok");
+    }
+}
@@ -3,27 +3,22 @@
 //! A Rust-based launcher for running Apache Superset from a USB flash drive
 //! without requiring installation or admin privileges.
 
-mod config;
-mod cache;
-mod demo_data;
-mod docs_server;
-mod gateway;
-mod health_check;
-mod launcher_ui;
-mod lightdocs;
-mod packer;
-mod python;
-mod superset;
-mod tray;
-mod validator;
-mod data_loader;
-mod watcher;
-
-use anyhow::Result;
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use anyhow::{Context, Result};
+use clap::{CommandFactory, Parser, Subcommand};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use tracing::{info, error, Level};
 use tracing_subscriber::FmtSubscriber;
+use superset_launcher::{
+    alerts, audit, auto_dashboard, autologin, av_warmup, backup, config, control, cache, chart_lint,
+    completions, crash_report, credentials, databases, dates, demo_data, docs_build, docs_server,
+    doctor, encoding, feature_flags, gateway, hardware, headless, health_check, kpi_charts,
+    launcher_ui, lightdocs, log_shipping, metadata_diff, notifications, pack_analyze, packer,
+    partitions, python, replica, secret_key, saved_queries, self_docs, sled_journal, slo, smoke,
+    snapshots, stack, superset, superset_search, timing, translations, tray, usage_stats, unpack,
+    updater, validations, validator, data_loader, watcher,
+};
+use superset_launcher::get_portable_root;
 
 /// Portable Apache Superset Launcher
 #[derive(Parser)]
@@ -32,6 +27,9 @@ use tracing_subscriber::FmtSubscriber;
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+    /// Write man pages for every command into DIR (default: docs/man) and exit
+    #[arg(long, value_name = "DIR")]
+    generate_docs: Option<Option<PathBuf>>,
 }
 
 #[derive(Subcommand)]
@@ -49,9 +47,35 @@ enum Commands {
         /// Also start docs server
         #[arg(short, long, default_value = "true")]
         docs: bool,
+        
+        /// Seconds to wait for Superset to become ready (default: from config.json)
+        #[arg(long)]
+        timeout: Option<u64>,
     },
     /// Stop running Superset server
     Stop,
+    /// Open Superset in the browser, optionally already signed in
+    Open {
+        /// Sign in as this user with a one-time link (e.g. --as admin)
+        #[arg(long = "as")]
+        as_user: Option<String>,
+        /// Page to open, e.g. /superset/dashboard/sales/
+        #[arg(default_value = "/")]
+        path: String,
+    },
+    /// Start the full stack (Superset, docs, LightDocs, gateway, watcher)
+    Up {
+        /// Public gateway port (default: 8000)
+        #[arg(short, long, default_value = "8000")]
+        gateway_port: u16,
+    },
+    /// Stop everything started with `up`
+    Down,
+    /// Send a command to the running launcher or tray (ping, status, start, stop, shutdown)
+    Ctl {
+        /// Command name
+        command: String,
+    },
     /// Show server status and health check
     Status,
     /// Fast health check (no Python needed)
@@ -61,6 +85,8 @@ enum Commands {
         /// Port for docs server (default: 8089)
         #[arg(short, long, default_value = "8089")]
         port: u16,
+        #[command(subcommand)]
+        action: Option<DocsAction>,
     },
     /// Initialize Superset (first-time setup)
     Init {
@@ -68,20 +94,104 @@ enum Commands {
         #[arg(short, long, default_value = "admin")]
         username: String,
         
-        /// Admin password
-        #[arg(short, long, default_value = "admin")]
-        password: String,
+        /// Admin password (stays in shell history — prefer --password-stdin)
+        #[arg(short, long, conflicts_with = "password_stdin")]
+        password: Option<String>,
+        
+        /// Read the admin password from stdin
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// Superset user management
+    User {
+        #[command(subcommand)]
+        action: UserAction,
     },
     /// Pack release for distribution
     Pack {
+        #[command(subcommand)]
+        action: Option<PackAction>,
         /// Use zstd compression (faster) instead of ZIP
         #[arg(short, long)]
         zstd: bool,
+        /// Leave the knowledge base (sources and built site) out
+        #[arg(long)]
+        no_knowledge: bool,
+        /// Leave draft pages out of the shipped knowledge base
+        #[arg(long)]
+        strip_drafts: bool,
+    },
+    /// Unpack a release archive (.zip or .tar.zst) made by `pack`
+    Unpack {
+        /// Archive to unpack: a file, a UNC path or an http:// URL
+        archive: String,
+        /// Target folder (default: archive name next to it)
+        dest: Option<PathBuf>,
+        /// Unpack into a folder that already has files
+        #[arg(short, long)]
+        force: bool,
+        /// Pre-read the unpacked files so antivirus scans them now (see `warmup`)
+        #[arg(long)]
+        warm_up: bool,
+        /// Expected SHA-256 of the archive (default: from <archive>.sha256, if present)
+        #[arg(long)]
+        sha256: Option<String>,
+        /// Only show the archive's top-level folders and files with their sizes
+        #[arg(long, conflicts_with_all = ["force", "warm_up", "only"])]
+        list: bool,
+        /// Replace only these parts of an existing kit, e.g. --only python/,superset_home/
+        #[arg(long, conflicts_with = "force")]
+        only: Option<String>,
+    },
+    /// Read every Python file once so antivirus scans them before the first start,
+    /// and report the folders to exclude from scanning
+    Warmup {
+        /// Parallel readers (default: one per CPU)
+        #[arg(short, long, default_value = "0")]
+        threads: usize,
     },
     /// Run with system tray GUI
     Tray,
     /// Validate environment
     Validate,
+    /// Show the detected CPU, RAM and drive and the tuning chosen for them
+    Hardware,
+    /// Walk a running stack (gateway, login page, chart data, docs, search) and time each step
+    Smoke {
+        /// Public gateway port the stack was started with
+        #[arg(short, long, default_value = "8000")]
+        gateway_port: u16,
+        /// Launcher UI port, which answers the knowledge base search
+        #[arg(long, default_value = "3000")]
+        ui_port: u16,
+        /// Superset user for the chart-data query
+        #[arg(short, long, default_value = "admin")]
+        user: String,
+        /// Read the user's password from stdin (default: the password saved by `init`)
+        #[arg(long)]
+        password_stdin: bool,
+        /// Knowledge base search query
+        #[arg(long, default_value = "superset")]
+        query: String,
+    },
+    /// Run all diagnostics and write a support bundle for emailing
+    Doctor {
+        /// Only print the problems, don't write the support bundle
+        #[arg(long)]
+        no_bundle: bool,
+    },
+    /// SQL threshold alerts (alerts.yaml), checked by the launcher on a schedule
+    Alerts {
+        #[command(subcommand)]
+        action: AlertsAction,
+    },
+    /// Audit log of launcher actions: signed export for hand-off and its check
+    Audit {
+        #[command(subcommand)]
+        action: AuditAction,
+    },
+    /// Send a test notification to every sink in notification_sinks
+    NotifyTest,
     /// Import RZD demo data into examples.db
     ImportDemo,
     /// Manage cache (stats, clear)
@@ -105,6 +215,17 @@ enum Commands {
         /// LightDocs port (default: 3030)
         #[arg(long, default_value = "3030")]
         lightdocs_port: u16,
+        /// Listen on all interfaces (control from a phone on the same network)
+        #[arg(long)]
+        lan: bool,
+        /// Run in the background without a console or browser (control with `ctl`)
+        #[arg(long)]
+        headless: bool,
+    },
+    /// Boot-time task for the headless launcher, so it outlives user logoff (Windows)
+    Headless {
+        #[command(subcommand)]
+        action: HeadlessAction,
     },
     /// High-performance data loader (Excel/CSV)
     LoadData {
@@ -116,9 +237,330 @@ enum Commands {
         /// Database path (optional, defaults to examples.db)
         #[arg(short, long)]
         db: Option<PathBuf>,
+        /// Target database by logical name from config.json (e.g. finance)
+        #[arg(long, conflicts_with = "db")]
+        db_name: Option<String>,
+        /// Only show the inferred schema and sample rows, don't write
+        #[arg(long)]
+        dry_run: bool,
+        /// Number of sample rows shown with --dry-run and --preview
+        #[arg(long, default_value = "10")]
+        sample: usize,
+        /// A deeper --dry-run: load the first N thousand rows of a CSV into a scratch table and
+        /// estimate the full load: value distributions, time and database size (default N: 100)
+        #[arg(long, value_name = "THOUSANDS", num_args = 0..=1, default_missing_value = "100", conflicts_with = "dry_run")]
+        preview: Option<usize>,
+        /// Don't index filter columns of large tables
+        #[arg(long)]
+        no_index: bool,
+        /// Keep date-like columns as they are in the file
+        #[arg(long)]
+        no_dates: bool,
+        /// Store detected dates as unix epoch seconds instead of ISO-8601 text
+        #[arg(long, conflicts_with = "no_dates")]
+        dates_as_epoch: bool,
+        /// Split the table by this date column into shards behind a view (multi-year tables)
+        #[arg(long)]
+        partition_by: Option<String>,
+        /// Period of one shard: year or month
+        #[arg(long, default_value = "year", requires = "partition_by")]
+        partition_unit: partitions::PartitionUnit,
+        /// Load a partitioned table back into one plain table
+        #[arg(long, conflicts_with = "partition_by")]
+        no_partition: bool,
+        /// Memory budget of this load in MB (default: load_memory_mb from config.json)
+        #[arg(long)]
+        memory_mb: Option<u64>,
+    },
+    /// Print a shell completion script (bash, powershell, zsh, fish, elvish)
+    Completions {
+        shell: clap_complete::Shell,
+        /// Install it for the current user instead (bash and powershell)
+        #[arg(long)]
+        install: bool,
+    },
+    /// Snapshots taken before data loads, and rollback to one of them
+    Data {
+        #[command(subcommand)]
+        action: DataAction,
+    },
+    /// Back up Superset metadata and demo data
+    Backup {
+        #[command(subcommand)]
+        action: BackupAction,
+    },
+    /// Inspect Superset metadata (superset.db)
+    Metadata {
+        #[command(subcommand)]
+        action: MetadataAction,
+    },
+    /// Generate Superset dashboards
+    Dashboard {
+        #[command(subcommand)]
+        action: DashboardAction,
+    },
+    /// Convert Windows-1251 text files (md, csv, ...) to UTF-8 in place
+    ConvertEncoding {
+        /// File or folder to convert (default: docs)
+        path: Option<PathBuf>,
+        /// Only list files that would be converted
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Export/import SQL Lab saved queries
+    Queries {
+        #[command(subcommand)]
+        action: QueriesAction,
+    },
+    /// Local usage statistics (never leave this machine)
+    Stats {
+        #[command(subcommand)]
+        action: StatsAction,
+    },
+    /// Operations reports written into the knowledge base
+    Report {
+        #[command(subcommand)]
+        action: ReportAction,
+    },
+    /// Analytics databases declared in config.json
+    Databases {
+        #[command(subcommand)]
+        action: DatabasesAction,
+    },
+    /// Update the launcher from a signed local update package
+    Update {
+        /// Path to the update package (.zip)
+        #[arg(required_unless_present = "rollback")]
+        package: Option<PathBuf>,
+        /// Restore the version replaced by the last update
+        #[arg(long, conflicts_with = "package")]
+        rollback: bool,
+        /// Install even if the package is not newer than the current version
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Replace Superset's SECRET_KEY and re-encrypt saved database passwords (Superset must be stopped)
+    RotateSecret {
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+        /// Rotate even though the key in superset_config.py differs from the saved copy
+        /// (passwords saved with the lost key stay unreadable)
+        #[arg(long)]
+        force: bool,
+    },
+    /// Manage Superset feature flags in superset_config.py
+    Feature {
+        #[command(subcommand)]
+        action: FeatureAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum DataAction {
+    /// List snapshots, newest first
+    Snapshots,
+    /// Restore a database from a snapshot (the current state is snapshotted first)
+    Rollback {
+        /// Snapshot name as shown by `data snapshots`
+        snapshot: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum BackupAction {
+    /// Create a backup now and rotate old ones
+    Create {
+        /// Number of backups to keep (default: from config.json)
+        #[arg(short, long)]
+        keep: Option<usize>,
+    },
+    /// List existing backups
+    List,
+}
+
+#[derive(Subcommand)]
+enum MetadataAction {
+    /// Compare dashboards, charts and datasets of two superset.db files by UUID
+    Diff {
+        /// Previous superset.db
+        old: PathBuf,
+        /// New superset.db
+        new: PathBuf,
+        /// Print the diff as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum DashboardAction {
+    /// Starter dashboard from a table's columns (totals, time series, pies)
+    Auto {
+        /// Table to chart, e.g. rzd_daily_operations
+        #[arg(short, long)]
+        table: String,
+        /// Database by logical name from config.json (default: examples)
+        #[arg(long, default_value = "examples")]
+        db_name: String,
+    },
+    /// Check that charts only use columns and metrics their datasets still have
+    Lint {
+        /// Only the charts of this dashboard (slug or id)
+        #[arg(short, long)]
+        dashboard: Option<String>,
+        /// Print the findings as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Rewrite dashboard and chart titles to a language from translations.yaml (its source language restores them)
+    Translate {
+        /// Language, e.g. en or ru
+        #[arg(required_unless_present = "init")]
+        lang: Option<String>,
+        /// Write translations.yaml with every current title to fill in
+        #[arg(long, conflicts_with = "lang")]
+        init: bool,
     },
 }
 
+#[derive(Subcommand)]
+enum QueriesAction {
+    /// Export saved queries to .sql files with YAML sidecars
+    Export {
+        /// Target folder (default: knowledge/sql)
+        #[arg(short, long)]
+        dir: Option<PathBuf>,
+    },
+    /// Import saved queries from a folder (upsert by UUID)
+    Import {
+        /// Source folder (default: knowledge/sql)
+        #[arg(short, long)]
+        dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum UserAction {
+    /// Set a new password (generated unless --password-stdin) and store it
+    ResetPassword {
+        #[arg(short, long, default_value = "admin")]
+        username: String,
+        /// Read the new password from stdin
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// Print the stored admin password
+    ShowPassword,
+    /// Print the saved copy of Superset's SECRET_KEY
+    ShowSecretKey,
+}
+
+#[derive(Subcommand)]
+enum PackAction {
+    /// Report removable weight in the Python tree (tests, locales, unused drivers)
+    Analyze {
+        /// Number of packages to list
+        #[arg(long, default_value = "20")]
+        top: usize,
+        /// Add all candidates to pack-prune.txt (applied by `pack`)
+        #[arg(long)]
+        write_prune: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum StatsAction {
+    /// Show usage counters
+    Show,
+    /// Reset all counters
+    Reset,
+}
+
+#[derive(Subcommand)]
+enum ReportAction {
+    /// Uptime, restarts, chart latency and error rate (knowledge/operations/slo-<date>.md)
+    Slo {
+        /// Period: 7d, 30d, 2w...
+        #[arg(long, default_value = "7d")]
+        last: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum DatabasesAction {
+    /// List known databases and their files
+    List,
+    /// Register configured databases as Superset connections
+    Register,
+}
+
+#[derive(Subcommand)]
+enum FeatureAction {
+    /// Show flags currently set in superset_config.py
+    List,
+    /// Enable a feature flag
+    Enable {
+        /// Flag name (e.g. DRILL_TO_DETAIL)
+        flag: String,
+        /// Skip validation against the bundled Superset version
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Disable a feature flag
+    Disable {
+        /// Flag name (e.g. ALERT_REPORTS)
+        flag: String,
+        /// Skip validation against the bundled Superset version
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum AlertsAction {
+    /// Write an example alerts.yaml
+    Init,
+    /// Run every alert now and print the results
+    Check,
+}
+
+#[derive(Subcommand)]
+enum AuditAction {
+    /// Write the actions of a date range as hash-chained, signed JSONL
+    Export {
+        /// First day, YYYY-MM-DD (default: from the start of the log)
+        #[arg(long)]
+        from: Option<chrono::NaiveDate>,
+        /// Last day, YYYY-MM-DD (default: today)
+        #[arg(long)]
+        to: Option<chrono::NaiveDate>,
+        /// Output file (default: audit-<from>-<to>.jsonl)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Check that an export is complete, unmodified and signed
+    Verify {
+        /// Export file
+        file: PathBuf,
+        /// Key the export must be signed with (hex, printed by `audit export`)
+        #[arg(long)]
+        public_key: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum HeadlessAction {
+    /// Register a Task Scheduler task starting `launcher --headless` at boot (limited rights)
+    Install {
+        /// Account the task runs as, e.g. a dedicated low-privilege user (default: you)
+        #[arg(long)]
+        user: Option<String>,
+    },
+    /// Remove that task
+    Uninstall,
+}
+
 #[derive(Subcommand)]
 enum CacheAction {
     /// Show cache statistics
@@ -127,6 +569,20 @@ enum CacheAction {
     Clear,
     /// Test cache operations
     Test,
+    /// Rewrite the caches and search index to reclaim disk space (stop the launcher first)
+    Compact,
+}
+
+#[derive(Subcommand)]
+enum DocsAction {
+    /// Write reference pages (commands, config keys, HTTP API) to knowledge/launcher
+    SelfGenerate,
+    /// Re-save docs/ as UTF-8 and regenerate its HTML pages and toc.html
+    Build {
+        /// Keep rebuilding after every change until Ctrl+C
+        #[arg(long)]
+        watch: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -148,26 +604,120 @@ enum LightDocsAction {
     Search {
         /// Search query
         query: String,
+        /// Results per page
+        #[arg(long, default_value = "20")]
+        limit: usize,
+        /// Results to skip (next page: --offset 20)
+        #[arg(long, default_value = "0")]
+        offset: usize,
+        /// Hide results scoring below this (0.0-1.0)
+        #[arg(long, default_value = "0")]
+        min_score: f32,
+        /// Show which terms matched and what each contributed
+        #[arg(long)]
+        explain: bool,
+        /// Search only inside this document (slug) and list matching sections
+        #[arg(long)]
+        doc: Option<String>,
     },
+    /// Show knowledge base statistics
+    Stats,
+    /// List pages past their `review_due` date and broken links (exit 1 if any)
+    Lint,
+    /// Rebuild the search index from scratch (after an upgrade changed its format)
+    Reindex,
+    /// Copy built-in page/index templates to knowledge/_layouts for editing
+    Layouts,
 }
 
-/// Get the portable root directory (where the exe is located)
-fn get_portable_root() -> Result<PathBuf> {
-    let exe_path = std::env::current_exe()?;
-    let root = exe_path.parent()
-        .ok_or_else(|| anyhow::anyhow!("Cannot determine executable directory"))?;
-        
-    // Check if we are running in development (cargo run)
-    // The executable is in target/debug, but assets are in project root
-    if !root.join("python").exists() {
-        let cwd = std::env::current_dir()?;
-        if cwd.join("python").exists() || cwd.join("Cargo.toml").exists() {
-            info!("Development mode detected, using CWD as root: {}", cwd.display());
-            return Ok(cwd);
+/// Heavy commands whose duration is logged, with the folder they write to
+fn timed_operation(command: &Option<Commands>, root: &Path) -> Option<(timing::Operation, PathBuf)> {
+    match command {
+        Some(Commands::Pack { action: None, zstd, .. }) => Some((timing::Operation::Pack { zstd: *zstd }, root.to_path_buf())),
+        Some(Commands::Unpack { archive, dest, list: false, .. }) => {
+            let dest = dest.clone().unwrap_or_else(|| unpack::Source::parse(archive).default_dest());
+            Some((timing::Operation::Unpack, dest))
+        }
+        Some(Commands::LoadData { db, dry_run: false, preview: None, .. }) => {
+            let target = db.as_ref().and_then(|db| db.parent()).filter(|dir| !dir.as_os_str().is_empty());
+            Some((timing::Operation::LoadData, target.map_or_else(|| root.to_path_buf(), Path::to_path_buf)))
         }
+        Some(Commands::Lightdocs { action: LightDocsAction::Build })
+        | Some(Commands::Docs { action: Some(DocsAction::Build { watch: false }), .. }) => Some((timing::Operation::Build, root.to_path_buf())),
+        _ => None,
+    }
+}
+
+/// Admin password for `init`: stdin, argument, stored or generated (`true` when generated)
+fn resolve_admin_password(
+    store: &credentials::CredentialStore,
+    password: Option<String>,
+    password_stdin: bool,
+) -> Result<(String, bool)> {
+    if password_stdin {
+        return Ok((credentials::read_stdin_secret()?, false));
+    }
+    if let Some(password) = password {
+        tracing::warn!("⚠️ Password passed as an argument is kept in shell history; prefer --password-stdin");
+        return Ok((password, false));
+    }
+    if let Some(password) = store.get(credentials::ADMIN_PASSWORD)? {
+        return Ok((password, false));
+    }
+    Ok((credentials::generate_secret(), true))
+}
+
+/// Enable/disable a Superset feature flag and offer to restart Superset
+async fn toggle_feature_flag(
+    root: &Path,
+    python_env: &python::PythonEnv,
+    superset_port: u16,
+    flag: &str,
+    enabled: bool,
+    force: bool,
+) -> Result<()> {
+    let flag = flag.to_uppercase();
+    
+    if !force {
+        feature_flags::validate_flag(&flag, python_env.superset_version().as_deref())?;
+    }
+    
+    let mut config_file = feature_flags::SupersetConfigFile::open(root)?;
+    config_file.set_flag(&flag, enabled)?;
+    config_file.save()?;
+    info!("✅ {} = {}", flag, if enabled { "True" } else { "False" });
+    
+    // Changes only apply after Superset restarts
+    if !health_check::check_superset(superset_port).await.unwrap_or(false) {
+        info!("The change will apply on next Superset start.");
+        return Ok(());
+    }
+    
+    print!("Superset is running. Restart now to apply? [y/N] ");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    
+    if answer.trim().eq_ignore_ascii_case("y") {
+        superset::SupersetServer::stop_running()?;
+        let mut server = superset::SupersetServer::new(root, python_env, superset_port);
+        server.start().await?;
+        info!("Superset is running. Press Ctrl+C to stop.");
+        server.wait().await?;
+    } else {
+        info!("Restart Superset to apply the change.");
     }
     
-    Ok(root.to_path_buf())
+    Ok(())
+}
+
+/// Pre-read the kit at `root`, print the antivirus report and save it under `support/`
+fn run_warmup(root: &Path, threads: usize) -> Result<()> {
+    let report = av_warmup::warm_up(root, threads)?;
+    println!("{}", report);
+    let path = report.write(root)?;
+    info!("📝 Report saved to {}", path.display());
+    Ok(())
 }
 
 #[tokio::main]
@@ -177,22 +727,56 @@ async fn main() -> Result<()> {
         .with_max_level(Level::INFO)
         .with_target(false)
         .compact()
+        .with_writer(log_shipping::LauncherLog)
         .init();
     
     let cli = Cli::parse();
+    // Completion scripts go to stdout: nothing may be logged before them
+    if let Some(Commands::Completions { shell, install }) = &cli.command {
+        let shell = *shell;
+        if !install {
+            completions::print(Cli::command(), shell);
+            return Ok(());
+        }
+        for path in completions::install(Cli::command(), shell)? {
+            info!("⌨️ {}", path.display());
+        }
+        info!("Open a new {} window to use the completions", shell);
+        return Ok(());
+    }
     let root = get_portable_root()?;
+    if let Some(dir) = cli.generate_docs {
+        let dir = dir.unwrap_or_else(|| root.join("docs").join("man"));
+        let pages = completions::generate_man_pages(Cli::command(), &dir)?;
+        info!("📖 {} man page(s) written to {}", pages.len(), dir.display());
+        return Ok(());
+    }
     
     info!("Portable Superset Launcher");
     info!("Root directory: {}", root.display());
     
+    // Remove the previous exe left behind by a self-update
+    updater::cleanup_after_update(&root);
+    
     // Load or create config
     let mut config = config::Config::load_or_create(&root)?;
+    let tuning = hardware::init(&root, &config.hardware);
+    info!("⚙️ Hardware profile: {} ({} cores)", tuning.profile, tuning.hardware.cores);
+    notifications::set_enabled(config.notifications);
+    notifications::configure_sinks(&root, &config.notification_sinks);
+    crash_report::install(&root, &config);
+    if config.log_shipping.enabled {
+        if let Err(e) = log_shipping::open_launcher_log(&root) {
+            error!("Launcher log file not written: {:#}", e);
+        }
+    }
     
     // Validate Python environment
-    let python_env = python::PythonEnv::new(&root)?;
+    let python_env = python::PythonEnv::new(&root)?.with_env(&config.env.superset);
     
+    let timer = timed_operation(&cli.command, &root).map(|(operation, target)| timing::CommandTimer::start(operation, &target));
     match cli.command {
-        Some(Commands::Start { port, browser, docs }) => {
+        Some(Commands::Start { port, browser, docs, timeout }) => {
             if !python_env.is_valid() {
                 error!("Python environment not found at: {}", python_env.python_path().display());
                 std::process::exit(1);
@@ -208,8 +792,24 @@ async fn main() -> Result<()> {
                 docs_server.start().await?;
             }
             
+            if let Err(e) = databases::register_all(&root, &config.databases) {
+                error!("Failed to register databases: {}", e);
+            }
+            if config.replica.enabled {
+                if let Err(e) = replica::refresh_all(&root, &config.databases) {
+                    error!("Failed to refresh read replicas: {}", e);
+                }
+            } else if let Err(e) = replica::restore_all(&root, &config.databases) {
+                error!("Failed to switch back from read replicas: {}", e);
+            }
+            
             let mut server = superset::SupersetServer::new(&root, &python_env, port);
             server.start().await?;
+            usage_stats::record(&root, usage_stats::UsageEvent::ServiceStart("superset"));
+            
+            // Slow flash drives take a while to import Superset; open the browser only when ready
+            let timeout = timeout.unwrap_or(config.startup_timeout_secs);
+            server.wait_ready(std::time::Duration::from_secs(timeout)).await?;
             
             if browser {
                 let url = format!("http://localhost:{}", port);
@@ -217,6 +817,7 @@ async fn main() -> Result<()> {
                 let _ = open::that(&url);
             }
             
+            slo::spawn_monitor(&root, port, None);
             info!("Superset is running. Press Ctrl+C to stop.");
             server.wait().await?;
         }
@@ -225,9 +826,46 @@ async fn main() -> Result<()> {
             superset::SupersetServer::stop_running()?;
             info!("Superset stopped.");
         }
+        Some(Commands::Open { as_user, path }) => {
+            let path = if path.starts_with('/') { path } else { format!("/{}", path) };
+            let running = health_check::check_superset(config.port).await.unwrap_or(false);
+            if !running {
+                tracing::warn!("Superset is not responding on port {} — start it first: superset-launcher start", config.port);
+            }
+            let url = match as_user {
+                None => format!("http://localhost:{}{}", config.port, path),
+                Some(user) => {
+                    // Superset reads the helper only at startup
+                    if autologin::install(&root)? && running {
+                        error!("Auto-login was just enabled: restart Superset (superset-launcher stop, then start) and run this again");
+                        std::process::exit(1);
+                    }
+                    autologin::login_url(&root, config.port, &user, &path)?
+                }
+            };
+            info!("Opening browser: http://localhost:{}{}", config.port, path);
+            open::that(&url)?;
+        }
+        Some(Commands::Up { gateway_port }) => {
+            info!("🚀 Starting full stack...");
+            usage_stats::record(&root, usage_stats::UsageEvent::ServiceStart("stack"));
+            stack::up(&root, &python_env, &config, gateway_port).await?;
+        }
+        Some(Commands::Down) => {
+            info!("Stopping full stack...");
+            stack::down(&root)?;
+        }
+        Some(Commands::Ctl { command }) => {
+            let result = control::call(&root, &command).await?;
+            println!("{}", serde_json::to_string_pretty(&result)?);
+        }
         Some(Commands::Status) => {
             let status = superset::SupersetServer::get_status()?;
             println!("{}", status);
+            match backup::last_backup(&root) {
+                Some(last) => println!("Last backup: {}", last.format("%d.%m.%Y %H:%M")),
+                None => println!("Last backup: never"),
+            }
             // Also show health check
             health_check::print_health_status(config.port, docs_server::DOCS_DEFAULT_PORT).await;
         }
@@ -235,7 +873,27 @@ async fn main() -> Result<()> {
             // Fast health check - no Python needed
             health_check::print_health_status(config.port, docs_server::DOCS_DEFAULT_PORT).await;
         }
-        Some(Commands::Docs { port }) => {
+        Some(Commands::Docs { action: Some(DocsAction::SelfGenerate), .. }) => {
+            for path in self_docs::generate(&root, Cli::command())? {
+                info!("📝 {}", path.strip_prefix(&root).unwrap_or(&path).display());
+            }
+            info!("Rebuild the site to publish them: superset-launcher lightdocs build");
+        }
+        Some(Commands::Docs { action: Some(DocsAction::Build { watch }), .. }) => {
+            let docs = root.join("docs");
+            if watch {
+                docs_build::watch(&docs).await?;
+            } else {
+                let summary = docs_build::build(&docs)?;
+                info!(
+                    "✅ {} file(s) re-saved as UTF-8, {} page(s) written, {} removed",
+                    summary.converted.len(),
+                    summary.written.len(),
+                    summary.removed.len()
+                );
+            }
+        }
+        Some(Commands::Docs { port, action: None }) => {
             info!("Starting documentation server on port {}...", port);
             let mut docs_server = docs_server::DocsServer::new(&root, port);
             docs_server.start().await?;
@@ -250,19 +908,60 @@ async fn main() -> Result<()> {
                 tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
             }
         }
-        Some(Commands::Init { username, password }) => {
+        Some(Commands::Init { username, password, password_stdin }) => {
             if !python_env.is_valid() {
                 error!("Python environment not found at: {}", python_env.python_path().display());
                 std::process::exit(1);
             }
+            let store = credentials::CredentialStore::open(&root, &config.credentials);
+            let (password, generated) = resolve_admin_password(&store, password, password_stdin)?;
+            
             info!("Initializing Superset...");
-            superset::initialize(&root, &python_env, &username, &password).await?;
+            // Stored only once Superset has the password: an existing admin keeps its own
+            if superset::initialize(&root, &python_env, &store, &username, &password).await? {
+                store.set(credentials::ADMIN_PASSWORD, &password)?;
+                info!("🔐 Admin password stored in {}", store.describe());
+                if generated {
+                    println!("Пароль {}: {}", username, password);
+                    println!("Посмотреть позже: superset-launcher user show-password");
+                }
+            } else {
+                info!("To change it: superset-launcher user reset-password --username {}", username);
+            }
             info!("Superset initialized successfully!");
             info!("You can now run: superset-launcher start");
         }
-        Some(Commands::Pack { zstd }) => {
+        Some(Commands::Pack { action: Some(PackAction::Analyze { top, write_prune }), .. }) => {
+            info!("🔬 Analyzing Python tree...");
+            let report = pack_analyze::analyze(&root)?;
+            let mb = |bytes: u64| bytes as f64 / 1_048_576.0;
+            
+            println!("{:<32} {:>10}  {}", "Package", "Savings", "Reason");
+            for (package, size, categories) in report.by_package().into_iter().take(top) {
+                let reasons: Vec<String> = categories.iter().map(|c| c.to_string()).collect();
+                println!("{:<32} {:>7.1} MB  {}", package, mb(size), reasons.join(", "));
+            }
+            println!(
+                "\nPotential savings: {:.1} MB of {:.1} MB ({} paths)",
+                mb(report.total_savings()),
+                mb(report.python_size_bytes),
+                report.candidates.len()
+            );
+            
+            if write_prune {
+                let added = pack_analyze::write_prune_list(&root, &report)?;
+                info!("✅ Added {} paths to {} — review it before `pack`", added, pack_analyze::PRUNE_FILE);
+            }
+        }
+        Some(Commands::Pack { action: None, zstd, no_knowledge, strip_drafts }) => {
             info!("📦 Packing release for distribution...");
-            let packer = packer::ReleasePacker::new(&root);
+            let mut knowledge = config.pack.knowledge.clone();
+            if no_knowledge {
+                knowledge.sources = false;
+                knowledge.site = false;
+            }
+            knowledge.strip_drafts |= strip_drafts;
+            let packer = packer::ReleasePacker::new(&root).with_knowledge(knowledge);
             
             if zstd {
                 info!("Using Zstd compression (faster)");
@@ -272,6 +971,45 @@ async fn main() -> Result<()> {
                 packer.pack_zip()?;
             }
         }
+        Some(Commands::Unpack { archive, dest, force, warm_up, sha256, list, only }) => {
+            let source = unpack::Source::parse(&archive);
+            if list {
+                let (parts, stats) = tokio::task::spawn_blocking(move || unpack::list(&source, sha256.as_deref())).await??;
+                for part in &parts {
+                    let name = if part.is_dir { format!("{}/", part.name) } else { part.name.clone() };
+                    println!("{:<32} {:>8} files {:>10.1} MB", name, part.files, part.bytes as f64 / 1_048_576.0);
+                }
+                println!("{:<32} {:>8} files {:>10.1} MB", "Total", stats.files, stats.bytes as f64 / 1_048_576.0);
+                if stats.verified {
+                    info!("🔒 SHA-256 verified: {}", stats.sha256);
+                }
+                return Ok(());
+            }
+            let selection = only.as_deref().map(unpack::Selection::parse).transpose()?;
+            let dest = dest.unwrap_or_else(|| source.default_dest());
+            let target = dest.clone();
+            // Network reads block; keep them off the async workers
+            let stats = tokio::task::spawn_blocking(move || match selection {
+                Some(selection) => unpack::repair_from(&source, &target, &selection, sha256.as_deref()),
+                None => unpack::unpack_from(&source, &target, force, sha256.as_deref()),
+            })
+            .await??;
+            info!(
+                "✅ Unpacked {} files ({:.1} MB) into {}",
+                stats.files,
+                stats.bytes as f64 / 1_048_576.0,
+                dest.display()
+            );
+            if stats.verified {
+                info!("🔒 SHA-256 verified: {}", stats.sha256);
+            }
+            if warm_up {
+                run_warmup(&dest, 0)?;
+            }
+        }
+        Some(Commands::Warmup { threads }) => {
+            run_warmup(&root, threads)?;
+        }
         Some(Commands::Tray) => {
             info!("Starting with system tray...");
             tray::run_tray(&root, &python_env, &config).await?;
@@ -282,9 +1020,131 @@ async fn main() -> Result<()> {
             let results = validator.validate_all();
             validator::print_validation_report(&results);
         }
+        Some(Commands::Hardware) => {
+            println!("{}", tuning);
+            if config.hardware.profile != hardware::Profile::Auto {
+                println!("(профиль задан в config.json: hardware.profile)");
+            }
+        }
+        Some(Commands::Smoke { gateway_port, ui_port, user, password_stdin, query }) => {
+            let password = if password_stdin {
+                Some(credentials::read_stdin_secret()?)
+            } else {
+                credentials::CredentialStore::open(&root, &config.credentials).get(credentials::ADMIN_PASSWORD)?
+            };
+            let options = smoke::SmokeOptions { gateway_port, ui_port, user, password, query };
+            println!("🔥 Проверка стека через шлюз http://localhost:{}\n", gateway_port);
+            let results = smoke::run(&options).await?;
+            for result in &results {
+                println!("{}", result);
+            }
+            let failed = results.iter().filter(|r| r.outcome == smoke::Outcome::Fail).count();
+            let total: u128 = results.iter().map(|r| r.elapsed.as_millis()).sum();
+            if failed > 0 {
+                println!("\n❌ Не пройдено шагов: {} из {} ({} ms)", failed, results.len(), total);
+                std::process::exit(1);
+            }
+            println!("\n✅ Все проверки пройдены ({} ms)", total);
+        }
+        Some(Commands::Doctor { no_bundle }) => {
+            info!("🩺 Running diagnostics...");
+            let findings = doctor::diagnose(&root, &config).await;
+            if findings.is_empty() {
+                println!("✅ Проблем не найдено");
+            } else {
+                println!("\nНайдено проблем: {}\n", findings.len());
+                for finding in &findings {
+                    println!("{}", finding);
+                }
+                println!();
+            }
+            if !no_bundle {
+                let bundle = doctor::write_support_bundle(&root, &findings)?;
+                info!("📦 Support bundle: {} — attach it to your email", bundle.display());
+            }
+            if findings.iter().any(|f| f.severity == doctor::Severity::Critical) {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Alerts { action }) => {
+            match action {
+                AlertsAction::Init => {
+                    let path = alerts::init(&root)?;
+                    println!("✅ Created {} — edit the checks and restart the launcher", path.display());
+                }
+                AlertsAction::Check => {
+                    let results = alerts::check_all(&root, &config.databases)?;
+                    if results.is_empty() {
+                        println!("No alerts defined (run `alerts init`)");
+                    }
+                    for result in &results {
+                        let value = result.value.map(|v| format!("{:.2}", v)).unwrap_or_else(|| "—".to_string());
+                        match (&result.error, result.triggered) {
+                            (Some(e), _) => println!("⚠️ {}: {}", result.name, e),
+                            (None, true) => println!("🔔 {}: {} ({})", result.name, value, result.condition),
+                            (None, false) => println!("✅ {}: {}", result.name, value),
+                        }
+                    }
+                }
+            }
+        }
+        Some(Commands::Audit { action }) => {
+            match action {
+                AuditAction::Export { from, to, output } => {
+                    let output = output.unwrap_or_else(|| {
+                        let day = |d: Option<chrono::NaiveDate>, open: &str| d.map(|d| d.to_string()).unwrap_or_else(|| open.to_string());
+                        PathBuf::from(format!("audit-{}-{}.jsonl", day(from, "start"), day(to, "now")))
+                    });
+                    let store = credentials::CredentialStore::open(&root, &config.credentials);
+                    let (events, public_key) = audit::export(&root, &store, from, to, &output)?;
+                    println!("✅ {} action(s) exported to {}", events, output.display());
+                    println!("🔏 Public key: {}", public_key);
+                    println!("Give the key to the recipient separately; they check the file with `audit verify --public-key`");
+                }
+                AuditAction::Verify { file, public_key } => match audit::verify(&file, public_key.as_deref()) {
+                    Ok(verified) => {
+                        println!("✅ {}: {} action(s), chain and signature intact", file.display(), verified.events);
+                        println!("   {} ({}), exported {}", verified.header.computer, verified.header.kit, verified.header.exported_at);
+                        if public_key.is_none() {
+                            println!("⚠️ Signed with {} — compare it with the key you were given", verified.header.public_key);
+                        }
+                    }
+                    Err(e) => {
+                        println!("❌ {}: {:#}", file.display(), e);
+                        std::process::exit(1);
+                    }
+                },
+            }
+        }
+        Some(Commands::NotifyTest) => {
+            if config.notification_sinks.is_empty() {
+                info!("No notification_sinks in config.json: only desktop notifications are shown");
+            }
+            for sink in config.notification_sinks.clone() {
+                let describe = sink.describe();
+                let sink_root = root.clone();
+                let result = tokio::task::spawn_blocking(move || {
+                    notifications::deliver(
+                        &sink_root,
+                        &sink,
+                        notifications::Level::Info,
+                        "Проверка уведомлений",
+                        "Тестовое уведомление Superset Portable",
+                    )
+                })
+                .await?;
+                match result {
+                    Ok(()) => info!("✅ {}", describe),
+                    Err(e) => error!("❌ {}: {:#}", describe, e),
+                }
+            }
+        }
         Some(Commands::ImportDemo) => {
             info!("Importing RZD demo data...");
             demo_data::import_demo_data(&root)?;
+            if let Err(e) = kpi_charts::render(&root, &root.join(databases::EXAMPLES_PATH)) {
+                tracing::warn!("KPI charts not updated: {:#}", e);
+            }
         }
         Some(Commands::Cache { action }) => {
             match action {
@@ -329,6 +1189,32 @@ async fn main() -> Result<()> {
                     let stats = cache.stats();
                     println!("\n{}", stats);
                 }
+                CacheAction::Compact => {
+                    info!("🧹 Compacting caches...");
+                    let targets: [(&str, fn(&Path) -> Result<sled_journal::CompactReport>); 3] = [
+                        ("cache", cache::Cache::compact),
+                        ("gateway cache", gateway::compact_cache),
+                        ("search index", lightdocs::search::SearchIndex::compact),
+                    ];
+                    let mut reclaimed = 0;
+                    for (name, compact) in targets {
+                        match compact(&root) {
+                            Ok(report) => {
+                                println!(
+                                    "✅ {}: {} entries kept, {} expired dropped, {:.1} → {:.1} MB",
+                                    name,
+                                    report.kept,
+                                    report.dropped,
+                                    report.bytes_before as f64 / 1_048_576.0,
+                                    report.bytes_after as f64 / 1_048_576.0
+                                );
+                                reclaimed += report.reclaimed();
+                            }
+                            Err(e) => println!("⚠️ {}: {:#}", name, e),
+                        }
+                    }
+                    println!("\n🧹 Reclaimed {:.1} MB", reclaimed as f64 / 1_048_576.0);
+                }
             }
         }
         Some(Commands::Lightdocs { action }) => {
@@ -363,6 +1249,7 @@ async fn main() -> Result<()> {
                     for doc in lightdocs.list_documents()? {
                         search_index.index_document(&doc.slug(), &doc.title, &doc.content)?;
                     }
+                    search_index.flush()?;
                     
                     // Start watcher in background
                     if config.live_reload {
@@ -389,39 +1276,142 @@ async fn main() -> Result<()> {
                     info!("Press Ctrl+C to stop.");
                     server.start().await?;
                 }
-                LightDocsAction::Search { query } => {
+                LightDocsAction::Layouts => {
+                    let lightdocs = lightdocs::LightDocs::new(&root)?;
+                    let written = lightdocs.eject_layouts()?;
+                    if written.is_empty() {
+                        info!("Layouts already exist, nothing copied");
+                    }
+                    for name in written {
+                        info!("📝 knowledge/_layouts/{}", name);
+                    }
+                    info!("Edit the templates and run: superset-launcher lightdocs build");
+                }
+                LightDocsAction::Stats => {
+                    let lightdocs = lightdocs::LightDocs::new(&root)?;
+                    println!("{}", lightdocs.stats()?);
+                }
+                LightDocsAction::Lint => {
+                    let stats = lightdocs::LightDocs::new(&root)?.stats()?;
+                    for review in &stats.overdue_reviews {
+                        println!("⏰ {} ({}): срок проверки истёк {}", review.title, review.slug, review.review_due);
+                    }
+                    for link in &stats.broken_links {
+                        println!("💔 {}: битая ссылка [[{}]]", link.from, link.target);
+                    }
+                    if !stats.overdue_reviews.is_empty() || !stats.broken_links.is_empty() {
+                        std::process::exit(1);
+                    }
+                    println!("✅ Замечаний нет ({} документов)", stats.total_documents);
+                }
+                LightDocsAction::Reindex => {
+                    info!("🔄 Rebuilding search index (format v{})...", lightdocs::search::INDEX_FORMAT_VERSION);
+                    let lightdocs = lightdocs::LightDocs::new(&root)?;
+                    let documents = lightdocs.reindex()?;
+                    let search_index = lightdocs::search::SearchIndex::open(&root)?;
+                    let objects = superset_search::refresh(&root, &search_index)?;
+                    info!("✅ Indexed {} documents and {} Superset objects", documents, objects);
+                }
+                LightDocsAction::Search { query, limit, offset, min_score, explain, doc } => {
                     info!("🔍 Searching: {}", query);
                     let search_index = lightdocs::search::SearchIndex::open(&root)?;
-                    let results = search_index.search(&query)?;
+                    let options = lightdocs::search::SearchOptions { limit: Some(limit), offset, min_score, explain, doc };
+                    let page = search_index.search_with(&query, &options)?;
+                    usage_stats::record(&root, usage_stats::UsageEvent::Search);
                     
-                    if results.is_empty() {
+                    if page.results.is_empty() {
                         println!("Ничего не найдено.");
                     } else {
-                        println!("\n📚 Результаты ({}):\n", results.len());
-                        for (i, entry) in results.iter().enumerate() {
-                            println!("{}. {} ({})", i + 1, entry.title, entry.slug);
-                            println!("   {}\n", entry.excerpt);
+                        println!(
+                            "\n📚 Результаты {}-{} из {}:\n",
+                            offset + 1,
+                            offset + page.results.len(),
+                            page.total
+                        );
+                        for (i, entry) in page.results.iter().enumerate() {
+                            println!("{}. {} ({}) — {:.2}", offset + i + 1, entry.title, entry.slug, entry.score);
+                            println!("   {}", entry.excerpt);
+                            // Inside one document all matching sections, otherwise the best one
+                            let shown = if options.doc.is_some() { entry.sections.len() } else { 1 };
+                            for section in entry.sections.iter().take(shown) {
+                                println!("   § {} (#{})", section.title, section.anchor);
+                            }
+                            if let Some(explain) = &entry.explain {
+                                for (term, contribution) in &explain.matched {
+                                    println!("   + {:<20} {:.2}", term, contribution);
+                                }
+                                for term in &explain.missing {
+                                    println!("   - {:<20} не найдено", term);
+                                }
+                            }
+                            println!();
+                        }
+                        if offset + page.results.len() < page.total {
+                            println!("Дальше: --offset {}", offset + page.results.len());
                         }
                     }
                 }
             }
         }
-        Some(Commands::Launcher { port, superset_port, lightdocs_port }) => {
+        Some(Commands::Launcher { port, superset_port, lightdocs_port, lan, headless }) => {
+            if headless && !headless::is_detached() {
+                let pid = headless::detach(&root).await?;
+                println!("Headless launcher started (pid {}): http://localhost:{}", pid, port);
+                println!("Status: {}, log: logs/headless.log", headless::STATUS_FILE);
+                println!("Control: superset-launcher ctl status | ctl shutdown");
+                return Ok(());
+            }
             info!("🚀 Starting unified launcher UI...");
             
             // Start Data Watcher
             let watcher = std::sync::Arc::new(watcher::DataWatcher::new(&root));
             watcher.start().await;
             
-            let launcher = launcher_ui::LauncherUI::new(&root, port, superset_port, lightdocs_port, watcher);
-            
-            let url = format!("http://localhost:{}", port);
-            info!("🌐 Opening: {}", url);
-            let _ = open::that(&url);
+            let launcher = launcher_ui::LauncherUI::new(&root, port, superset_port, lightdocs_port, watcher)
+                .lan(lan);
             
-            launcher.start().await?;
+            if headless {
+                headless::survive_session_end(root.clone());
+                headless::spawn_status_writer(root.clone(), port);
+                let result = launcher.start().await;
+                headless::write_stopped(&root);
+                result?;
+            } else {
+                let url = format!("http://localhost:{}", port);
+                info!("🌐 Opening: {}", url);
+                let _ = open::that(&url);
+                
+                launcher.start().await?;
+            }
         }
-        Some(Commands::LoadData { file, table, db }) => {
+        Some(Commands::Headless { action }) => {
+            match action {
+                HeadlessAction::Install { user } => {
+                    let name = headless::install_task(&root, user.as_deref())?;
+                    info!("🗓️ Task '{}' registered: the launcher starts headless at boot", name);
+                }
+                HeadlessAction::Uninstall => {
+                    let name = headless::uninstall_task(&root)?;
+                    info!("🗓️ Task '{}' removed", name);
+                }
+            }
+        }
+        Some(Commands::LoadData {
+            file,
+            table,
+            db,
+            db_name,
+            dry_run,
+            sample,
+            preview,
+            no_index,
+            no_dates,
+            dates_as_epoch,
+            partition_by,
+            partition_unit,
+            no_partition,
+            memory_mb,
+        }) => {
             let table_name = table.unwrap_or_else(|| {
                 file.file_stem()
                     .unwrap_or_default()
@@ -429,11 +1419,375 @@ async fn main() -> Result<()> {
                     .to_string()
             });
             
-            let db_path = db.unwrap_or_else(|| root.join("examples.db"));
-            
-            match data_loader::load_file(&file, &table_name, &db_path) {
-                Ok(msg) => info!("{}", msg),
-                Err(e) => error!("Failed to load data: {}", e),
+            if dry_run {
+                let preview = data_loader::preview_file(&file, &table_name, sample, None)?;
+                println!("{}", preview);
+            } else {
+                // Logical name, for the read replica (none for an ad-hoc --db file)
+                let (db_path, logical_name) = match (db, db_name) {
+                    (Some(db), _) => (db, None),
+                    (None, Some(name)) => (databases::resolve(&root, &config.databases, &name)?, Some(name)),
+                    (None, None) => (root.join(databases::EXAMPLES_PATH), Some(databases::EXAMPLES_NAME.to_string())),
+                };
+                if let Some(parent) = db_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                
+                let options = data_loader::LoadOptions {
+                    auto_index: !no_index,
+                    detect_dates: !no_dates,
+                    date_storage: if dates_as_epoch { dates::DateStorage::Epoch } else { dates::DateStorage::Iso },
+                    partition: partition_by.map(|column| partitions::Partitioning { column, unit: partition_unit }),
+                    keep_partitioning: !no_partition,
+                    memory_budget_mb: memory_mb.unwrap_or(tuning.load_memory_mb(config.load_memory_mb)),
+                };
+                if let Some(thousands) = preview {
+                    let scratch_dir = db_path.parent().unwrap_or(root.as_path());
+                    let scan = data_loader::PreviewScan { rows: thousands.max(1) * 1000, scratch_dir, options: &options };
+                    let preview = data_loader::preview_file(&file, &table_name, sample, Some(&scan))?;
+                    println!("{}", preview);
+                    return Ok(());
+                }
+                snapshots::take(&root, &config.snapshots, &db_path, &format!("load-data {}", table_name))
+                    .context("Snapshot before the load failed, nothing was changed")?;
+                match data_loader::load_file_with(&file, &table_name, &db_path, &options) {
+                    Ok(msg) => {
+                        info!("{}", msg);
+                        let validation = validations::after_load(&root, &db_path, &table_name)?;
+                        if validation.as_ref().is_some_and(|v| v.blocks()) {
+                            anyhow::bail!(
+                                "'{}' failed the rules of {}: Superset dataset and replica not updated",
+                                table_name,
+                                validations::RULES_FILE
+                            );
+                        }
+                        if let Some(name) = &logical_name {
+                            let columns = data_loader::date_columns(&db_path, &table_name)?;
+                            let marked =
+                                databases::mark_temporal_columns(&root, name, &table_name, &columns, options.date_storage)?;
+                            if marked > 0 {
+                                info!("📅 Marked {} dataset column(s) as temporal in Superset", marked);
+                            }
+                            let profiles = data_loader::profile_columns(&db_path, &table_name)?;
+                            let described = databases::push_column_stats(&root, name, &table_name, &profiles)?;
+                            if described > 0 {
+                                info!("📊 Column statistics written to {} dataset column(s)", described);
+                            }
+                        }
+                        if let (true, Some(name)) = (config.replica.enabled, logical_name) {
+                            replica::refresh(&root, &name, databases::connection_uuid(&config.databases, &name), &db_path)?;
+                        }
+                    }
+                    Err(e) => error!("Failed to load data: {}", e),
+                }
+            }
+        }
+        // Handled before the root is resolved
+        Some(Commands::Completions { .. }) => {}
+        Some(Commands::Data { action }) => {
+            match action {
+                DataAction::Snapshots => {
+                    let snapshots = snapshots::list(&root)?;
+                    if snapshots.is_empty() {
+                        println!("Снимков нет.");
+                    }
+                    for s in snapshots {
+                        println!(
+                            "{}  {}  {:.1} MB  {} ({})",
+                            s.name,
+                            s.created.format("%d.%m.%Y %H:%M:%S"),
+                            s.size_bytes as f64 / 1_048_576.0,
+                            s.source,
+                            s.reason
+                        );
+                    }
+                }
+                DataAction::Rollback { snapshot } => {
+                    let restored = snapshots::rollback(&root, &config.snapshots, &snapshot)?;
+                    info!("⏪ {} restored from {}", restored.display(), snapshot);
+                    // Superset may read a replica of the file rather than the file itself
+                    let logical_name = if restored == root.join(databases::EXAMPLES_PATH) {
+                        Some(databases::EXAMPLES_NAME.to_string())
+                    } else {
+                        config.databases.iter().find(|d| d.path_abs(&root) == restored).map(|d| d.name.clone())
+                    };
+                    if let (true, Some(name)) = (config.replica.enabled, logical_name) {
+                        replica::refresh(&root, &name, databases::connection_uuid(&config.databases, &name), &restored)?;
+                    }
+                }
+            }
+        }
+        Some(Commands::Backup { action }) => {
+            match action {
+                BackupAction::Create { keep } => {
+                    info!("💾 Creating backup...");
+                    backup::create_backup(&root)?;
+                    let removed = backup::rotate_backups(&root, keep.unwrap_or(config.backup.keep))?;
+                    if removed > 0 {
+                        info!("Removed {} old backup(s)", removed);
+                    }
+                }
+                BackupAction::List => {
+                    let backups = backup::list_backups(&root)?;
+                    if backups.is_empty() {
+                        println!("Резервных копий нет.");
+                    }
+                    for b in backups {
+                        println!(
+                            "{}  {:.1} MB  {}",
+                            b.created.format("%d.%m.%Y %H:%M:%S"),
+                            b.size_bytes as f64 / 1_048_576.0,
+                            b.path.display()
+                        );
+                    }
+                }
+            }
+        }
+        Some(Commands::Metadata { action }) => {
+            match action {
+                MetadataAction::Diff { old, new, json } => {
+                    let diff = metadata_diff::diff(&old, &new)?;
+                    if json {
+                        println!("{}", serde_json::to_string_pretty(&diff)?);
+                    } else if diff.iter().all(metadata_diff::KindDiff::is_empty) {
+                        println!("✅ Дашборды, графики и датасеты не изменились");
+                    } else {
+                        for kind in &diff {
+                            println!(
+                                "{}: +{} −{} ~{}",
+                                kind.kind,
+                                kind.added.len(),
+                                kind.removed.len(),
+                                kind.changed.len()
+                            );
+                            for o in &kind.added {
+                                println!("  + {} ({})", o.title, o.uuid);
+                            }
+                            for o in &kind.removed {
+                                println!("  - {} ({})", o.title, o.uuid);
+                            }
+                            for o in &kind.changed {
+                                println!("  ~ {} ({}): {}", o.title, o.uuid, o.fields.join(", "));
+                            }
+                        }
+                    }
+                    for kind in diff.iter().filter(|k| k.skipped > 0) {
+                        tracing::warn!("{} {}(s) without UUID were not compared", kind.skipped, kind.kind);
+                    }
+                }
+            }
+        }
+        Some(Commands::Dashboard { action }) => {
+            match action {
+                DashboardAction::Auto { table, db_name } => {
+                    let db_path = databases::resolve(&root, &config.databases, &db_name)?;
+                    let dashboard = auto_dashboard::create(&root, &db_name, &db_path, &table)?;
+                    info!("✅ Dashboard '{}' (id={}) with {} chart(s):", dashboard.title, dashboard.id, dashboard.charts.len());
+                    for chart in &dashboard.charts {
+                        info!("   - {}", chart);
+                    }
+                    info!("   Open: http://localhost:{}/superset/dashboard/{}/", config.port, dashboard.slug);
+                }
+                DashboardAction::Lint { dashboard, json } => {
+                    let meta_path = root.join("superset_home").join("superset.db");
+                    let issues = chart_lint::lint(&meta_path, dashboard.as_deref())?;
+                    if json {
+                        println!("{}", serde_json::to_string_pretty(&issues)?);
+                    } else if issues.is_empty() {
+                        println!("✅ Все графики ссылаются на существующие колонки и метрики");
+                    } else {
+                        for issue in &issues {
+                            println!("  ⚠️ {}", issue);
+                        }
+                        println!("\nГрафиков с ошибками: {}", issues.iter().map(|i| i.chart_id).collect::<std::collections::HashSet<_>>().len());
+                    }
+                    if !issues.is_empty() {
+                        std::process::exit(1);
+                    }
+                }
+                DashboardAction::Translate { init: true, .. } => {
+                    let path = translations::init(&root)?;
+                    println!("✅ Created {} — fill in the translations and run `dashboard translate en`", path.display());
+                }
+                DashboardAction::Translate { lang, .. } => {
+                    let lang = lang.context("Language required")?;
+                    let translations = translations::Translations::load(&root)?;
+                    let summary = translations::apply(&root, &translations, &lang)?;
+                    info!("✅ {}: {} dashboard(s) and {} chart(s) rewritten", lang, summary.dashboards, summary.charts);
+                    for title in &summary.missing {
+                        info!("   no '{}' translation: {}", lang, title);
+                    }
+                    info!("Reload the dashboards in the browser to see the new titles");
+                }
+            }
+        }
+        Some(Commands::ConvertEncoding { path, dry_run }) => {
+            let path = path.unwrap_or_else(|| root.join("docs"));
+            info!("🔤 Looking for Windows-1251 files in {}...", path.display());
+            let summary = encoding::convert_path(&path, dry_run)?;
+            if dry_run {
+                info!("{} of {} files would be converted", summary.converted.len(), summary.scanned);
+            } else {
+                info!("✅ Converted {} of {} files to UTF-8", summary.converted.len(), summary.scanned);
+            }
+        }
+        Some(Commands::Queries { action }) => {
+            let default_dir = root.join(saved_queries::DEFAULT_QUERIES_DIR);
+            match action {
+                QueriesAction::Export { dir } => {
+                    let dir = dir.unwrap_or(default_dir);
+                    info!("📤 Exporting saved queries to {}...", dir.display());
+                    let count = saved_queries::export(&root, &dir)?;
+                    info!("✅ Exported {} queries", count);
+                }
+                QueriesAction::Import { dir } => {
+                    let dir = dir.unwrap_or(default_dir);
+                    info!("📥 Importing saved queries from {}...", dir.display());
+                    let summary = saved_queries::import(&root, &dir)?;
+                    info!(
+                        "✅ Imported: {} new, {} updated, {} skipped",
+                        summary.inserted, summary.updated, summary.skipped
+                    );
+                }
+            }
+        }
+        Some(Commands::User { action }) => {
+            let store = credentials::CredentialStore::open(&root, &config.credentials);
+            match action {
+                UserAction::ResetPassword { username, password_stdin } => {
+                    let password = if password_stdin {
+                        credentials::read_stdin_secret()?
+                    } else {
+                        credentials::generate_secret()
+                    };
+                    superset::reset_password(&python_env, &username, &password)?;
+                    store.set(credentials::ADMIN_PASSWORD, &password)?;
+                    info!("🔐 Password for {} reset and stored in {}", username, store.describe());
+                    if !password_stdin {
+                        println!("Новый пароль {}: {}", username, password);
+                    }
+                }
+                UserAction::ShowPassword => match store.get(credentials::ADMIN_PASSWORD)? {
+                    Some(password) => println!("{}", password),
+                    None => {
+                        error!("No stored password in {} — run: superset-launcher user reset-password", store.describe());
+                        std::process::exit(1);
+                    }
+                },
+                UserAction::ShowSecretKey => match store.get(credentials::SUPERSET_SECRET_KEY)? {
+                    Some(key) => println!("{}", key),
+                    None => {
+                        error!("No saved SECRET_KEY in {} — it is saved on the next Superset start", store.describe());
+                        std::process::exit(1);
+                    }
+                },
+            }
+        }
+        Some(Commands::RotateSecret { yes, force }) => {
+            if health_check::check_superset(config.port).await.unwrap_or(false) {
+                error!("Superset is running — stop it first: superset-launcher stop");
+                std::process::exit(1);
+            }
+            let store = credentials::CredentialStore::open(&root, &config.credentials);
+            if secret_key::ensure_backed_up(&root, &store)? == secret_key::KeyStatus::Mismatch && !force {
+                error!("Resolve the SECRET_KEY mismatch above before rotating, or rotate anyway with --force");
+                std::process::exit(1);
+            }
+            let encrypted = secret_key::encrypted_connections(&root)?;
+            println!("⚠️  SECRET_KEY будет заменён. Сохранённые пароли подключений ({}) будут перешифрованы.", encrypted);
+            println!("   Сеансы пользователей сбросятся — всем придётся войти заново.");
+            if !yes {
+                print!("Продолжить? [y/N] ");
+                std::io::stdout().flush()?;
+                let mut answer = String::new();
+                std::io::stdin().read_line(&mut answer)?;
+                if !answer.trim().eq_ignore_ascii_case("y") {
+                    info!("Cancelled.");
+                    return Ok(());
+                }
+            }
+            let count = secret_key::rotate(&root, &python_env, &store, &superset::generate_secret_key())?;
+            info!("✅ SECRET_KEY rotated, {} connection(s) re-encrypted; new key saved to {}", count, store.describe());
+        }
+        Some(Commands::Stats { action }) => {
+            let stats = usage_stats::UsageStats::open(&root)?;
+            match action {
+                StatsAction::Show => println!("{}", stats.report()?),
+                StatsAction::Reset => {
+                    stats.reset()?;
+                    info!("🧹 Usage counters reset");
+                }
+            }
+        }
+        Some(Commands::Report { action: ReportAction::Slo { last } }) => {
+            let report = slo::report(&root, slo::parse_days(&last)?)?;
+            let path = slo::write_report(&root, &report)?;
+            let total = report.total();
+            match total.uptime_percent() {
+                Some(uptime) => info!("📊 Uptime {:.2}%, {} Superset start(s) over {}", uptime, total.get(slo::Metric::Starts), last),
+                None => info!("📊 No uptime data for {} yet: it is collected while `up` or `start` runs", last),
+            }
+            info!("📝 Report saved to {}", path.display());
+        }
+        Some(Commands::Databases { action }) => {
+            match action {
+                DatabasesAction::List => {
+                    println!("{:<16} {}", databases::EXAMPLES_NAME, root.join(databases::EXAMPLES_PATH).display());
+                    for db in &config.databases {
+                        let status = if db.path_abs(&root).exists() { "" } else { "  (файл не найден)" };
+                        println!("{:<16} {}  {}{}", db.name, db.path_abs(&root).display(), db.uuid(), status);
+                    }
+                }
+                DatabasesAction::Register => {
+                    let count = databases::register_all(&root, &config.databases)?;
+                    info!("✅ Registered {} database(s) in Superset", count);
+                    if config.replica.enabled {
+                        let count = replica::refresh_all(&root, &config.databases)?;
+                        info!("📖 {} connection(s) switched to read replicas", count);
+                    } else {
+                        let count = replica::restore_all(&root, &config.databases)?;
+                        if count > 0 {
+                            info!("📖 {} connection(s) switched back from read replicas", count);
+                        }
+                    }
+                }
+            }
+        }
+        Some(Commands::Update { package, rollback, force }) => {
+            if rollback {
+                info!("↩️ Rolling back last update...");
+                updater::rollback(&root)?;
+            } else if let Some(package) = package {
+                info!("📦 Applying update: {}", package.display());
+                updater::apply_update(&root, &package, force)?;
+            }
+        }
+        Some(Commands::Feature { action }) => {
+            match action {
+                FeatureAction::List => {
+                    let config_file = feature_flags::SupersetConfigFile::open(&root)?;
+                    let flags = config_file.flags()?;
+                    if let Some(version) = python_env.superset_version() {
+                        println!("Superset {}", version);
+                    }
+                    if flags.is_empty() {
+                        println!("FEATURE_FLAGS не заданы.");
+                    }
+                    for flag in flags {
+                        let icon = match flag.enabled() {
+                            Some(true) => "✅",
+                            Some(false) => "❌",
+                            None => "❔",
+                        };
+                        println!("{} {} = {}", icon, flag.name, flag.value);
+                    }
+                }
+                FeatureAction::Enable { flag, force } => {
+                    toggle_feature_flag(&root, &python_env, config.port, &flag, true, force).await?;
+                }
+                FeatureAction::Disable { flag, force } => {
+                    toggle_feature_flag(&root, &python_env, config.port, &flag, false, force).await?;
+                }
             }
         }
         None => {
@@ -453,6 +1807,9 @@ async fn main() -> Result<()> {
             launcher.start().await?;
         }
     }
+    if let Some(timer) = timer {
+        timer.finish(tuning);
+    }
     
     Ok(())
 }
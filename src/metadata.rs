@@ -0,0 +1,915 @@
+//! Superset metadata upserts, shared by `create_dashboard` and the data watcher
+//!
+//! `upsert_database`, `upsert_dataset`, `upsert_chart` and `upsert_dashboard`
+//! write one object into superset.db, matched by its fixed UUID, so running
+//! them again updates the object in place. Only when no row has the UUID do
+//! they fall back to the name (database, chart), table name (dataset) or slug
+//! (dashboard), and then take over the row only if the UUID registry
+//! (`superset_home/demo_uuids.json`) records it as ours or it has no UUID.
+//! Any other row of that name is a user's object: it is never overwritten,
+//! and the clash is logged and listed in the registry. `rebuild_demo` uses them to reload the demo tables
+//! from `docs/demo_data` into examples.db and refresh the RZD and World
+//! Railways dashboards. `src/bin/create_dashboard.rs` calls them through the
+//! launcher library, and the watcher calls them in-process instead of
+//! looking for the exe.
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, ToSql};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::data_loader::{profile_table, quote_ident, PROFILE_DESCRIPTION_PREFIX};
+
+pub const DEMO_DATA_DIR: &str = "docs/demo_data";
+const EXAMPLES_DB_PATH: &str = "examples.db";
+const SUPERSET_HOME_DIR: &str = "superset_home";
+const SUPERSET_DB_NAME: &str = "superset.db";
+const UUID_REGISTRY_NAME: &str = "demo_uuids.json";
+// Same bookkeeping table the launcher's loader writes (src/freshness.rs)
+const FRESHNESS_TABLE: &str = "_table_freshness";
+
+// Fixed UUIDs for stability (same as the Python setup script)
+const UUID_DB_EXAMPLES: &str = "a2dc77af-e654-49bb-b321-40f6b559a1ee";
+const UUID_DASHBOARD: &str = "d3000001-0001-0001-0001-000000000001";
+const UUID_WORLD_DASHBOARD: &str = "e4000001-0001-0001-0001-000000000001";
+
+const UUID_CH_TOTAL_PASS: &str = "c2000001-0001-0001-0001-000000000001";
+const UUID_CH_MONTHLY_BAR: &str = "c2000002-0002-0002-0002-000000000002";
+const UUID_CH_CARGO_PIE: &str = "c2000003-0003-0003-0003-000000000003";
+const UUID_CH_STATIONS_TBL: &str = "c2000004-0004-0004-0004-000000000004";
+const UUID_CH_DAILY_LINE: &str = "c2000005-0005-0005-0005-000000000005";
+const UUID_CH_INCIDENTS_BAR: &str = "c2000006-0006-0006-0006-000000000006";
+const UUID_CH_WORLD_STATS: &str = "e4000003-0003-0003-0003-000000000003";
+const UUID_CH_WORLD_MAP: &str = "e4000004-0004-0004-0004-000000000004";
+
+/// One dataset column as Superset sees it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnSpec {
+    pub name: String,
+    /// `INTEGER`, `FLOAT` or `STRING`
+    pub superset_type: &'static str,
+    pub is_dttm: bool,
+    pub groupby: bool,
+}
+
+/// A physical dataset on a table of the database
+pub struct DatasetSpec<'a> {
+    pub table_name: &'a str,
+    pub description: &'a str,
+    pub uuid: &'a str,
+    pub main_dttm_col: Option<&'a str>,
+    /// Replace the dataset's columns (the old ones are dropped)
+    pub columns: &'a [ColumnSpec],
+}
+
+/// A chart on one dataset; `datasource` is filled in from the dataset
+pub struct ChartSpec<'a> {
+    pub name: &'a str,
+    pub viz_type: &'a str,
+    pub uuid: &'a str,
+    pub params: &'a Value,
+}
+
+/// A published dashboard with its layout
+pub struct DashboardSpec<'a> {
+    pub title: &'a str,
+    pub slug: &'a str,
+    pub uuid: &'a str,
+    pub position: &'a Value,
+    pub metadata: &'a Value,
+}
+
+// --- Demo definitions ---
+struct DatasetDef {
+    key: &'static str,
+    table_name: &'static str,
+    description: &'static str,
+    csv: &'static str,
+    sql_create: Option<&'static str>,
+    main_dttm_col: Option<&'static str>,
+    uuid_str: &'static str,
+}
+
+const DATASETS: &[DatasetDef] = &[
+    DatasetDef { key: "ds_stations", table_name: "rzd_stations", description: "Станции РЖД", csv: "rzd_stations.csv", sql_create: None, main_dttm_col: None, uuid_str: "d1000001-0001-0001-0001-000000000001" },
+    DatasetDef { key: "ds_monthly", table_name: "rzd_monthly_stats", description: "Месячная статистика", csv: "rzd_monthly_stats.csv", sql_create: None, main_dttm_col: None, uuid_str: "d1000002-0002-0002-0002-000000000002" },
+    DatasetDef { key: "ds_cargo", table_name: "rzd_cargo_types", description: "Типы грузов", csv: "rzd_cargo_types.csv", sql_create: None, main_dttm_col: None, uuid_str: "d1000003-0003-0003-0003-000000000003" },
+    DatasetDef { key: "ds_daily", table_name: "rzd_daily_operations", description: "Ежедневные операции", csv: "rzd_daily_operations.csv", sql_create: None, main_dttm_col: Some("date"), uuid_str: "d1000004-0004-0004-0004-000000000004" },
+    DatasetDef { key: "ds_incidents", table_name: "rzd_incidents", description: "Инциденты", csv: "rzd_incidents.csv", sql_create: None, main_dttm_col: Some("date"), uuid_str: "d1000005-0005-0005-0005-000000000005" },
+    DatasetDef { key: "ds_kpi", table_name: "rzd_kpi_metrics", description: "KPI", csv: "rzd_kpi_metrics.csv", sql_create: None, main_dttm_col: None, uuid_str: "d1000006-0006-0006-0006-000000000006" },
+    DatasetDef { key: "ds_world", table_name: "world_rail_stats", description: "World Rail Stats", csv: "world_rail_stats.csv", sql_create: None, main_dttm_col: None, uuid_str: "e4000002-0002-0002-0002-000000000002" },
+    // Pre-aggregated table for performance
+    DatasetDef {
+        key: "ds_region_agg",
+        table_name: "rzd_region_agg",
+        description: "Агрегат по регионам (Pre-calc)",
+        csv: "",
+        sql_create: Some("CREATE TABLE rzd_region_agg AS SELECT region, CAST(SUM(passengers_thousands) AS INTEGER) as total_passengers, CAST(SUM(revenue_mln_rub) AS INTEGER) as total_revenue FROM rzd_daily_operations GROUP BY region"),
+        main_dttm_col: None,
+        uuid_str: "d1000007-0007-0007-0007-000000000007"
+    },
+];
+
+struct ChartDef {
+    key: &'static str,
+    name: &'static str,
+    viz_type: &'static str,
+    dataset_key: &'static str,
+    uuid_str: &'static str,
+    params_json: &'static str,
+}
+
+const CHARTS: &[ChartDef] = &[
+    ChartDef { key: "ch_world_stats", name: "Railway Statistics", viz_type: "table", dataset_key: "ds_world", uuid_str: "e4000003-0003-0003-0003-000000000003",
+        params_json: r#"{
+            "viz_type": "table", "query_mode": "raw", "all_columns": ["line_name", "country", "length_km", "passengers_mln_year", "max_speed_kmh"],
+            "order_by_cols": [["length_km", false]], "include_search": true, "page_length": 10
+        }"# },
+    ChartDef { key: "ch_world_map", name: "Global Networks", viz_type: "deck_geojson", dataset_key: "ds_world", uuid_str: "e4000004-0004-0004-0004-000000000004",
+        params_json: r#"{
+            "viz_type": "deck_geojson", "geojson_url": "http://localhost:8089/world_rail.geojson",
+            "viewport": {"latitude": 20, "longitude": 0, "zoom": 1.5, "bearing": 0, "pitch": 0},
+            "filled": false, "stroked": true, "extruded": false, "lineWidth": 1500, "lineColor": [255, 0, 0, 200],
+            "autozoom": true
+        }"# },
+    ChartDef { key: "ch_total_pass", name: "Пассажиропоток (млн)", viz_type: "big_number_total", dataset_key: "ds_monthly", uuid_str: "c2000001-0001-0001-0001-000000000001",
+        params_json: r#"{
+            "viz_type": "big_number_total", "granularity_sqla": null, "time_range": "No filter",
+            "metric": {"aggregate": "SUM", "column": {"column_name": "passengers_mln", "type": "FLOAT", "label": "passengers_mln"}, "expressionType": "SIMPLE", "label": "SUM(passengers_mln)"},
+            "subheader": "млн пасс. за 2024 год", "y_axis_format": ",.1f"
+        }"# },
+    ChartDef { key: "ch_monthly_bar", name: "Выручка по месяцам (млрд ₽)", viz_type: "echarts_timeseries_bar", dataset_key: "ds_monthly", uuid_str: "c2000002-0002-0002-0002-000000000002",
+        params_json: r#"{
+            "viz_type": "echarts_timeseries_bar", "granularity_sqla": null, "time_range": "No filter", "x_axis": "month", "x_axis_sort_asc": true,
+            "metrics": [{"aggregate": "SUM", "column": {"column_name": "revenue_bln_rub", "type": "FLOAT", "label": "revenue_bln_rub"}, "expressionType": "SIMPLE", "label": "Выручка (млрд ₽)"}],
+            "groupby": [], "order_desc": true, "show_legend": true, "y_axis_format": ",.1f"
+        }"# },
+    ChartDef { key: "ch_cargo_pie", name: "Распределение грузов", viz_type: "pie", dataset_key: "ds_cargo", uuid_str: "c2000003-0003-0003-0003-000000000003",
+        params_json: r#"{
+            "viz_type": "pie", "granularity_sqla": null, "time_range": "No filter", "groupby": ["cargo_type"],
+            "metric": {"aggregate": "SUM", "column": {"column_name": "volume_mln_tons", "type": "FLOAT", "label": "volume_mln_tons"}, "expressionType": "SIMPLE", "label": "Объём (млн тонн)"},
+            "show_labels": true, "show_legend": true, "label_type": "key_percent", "number_format": ",.1f"
+        }"# },
+    ChartDef { key: "ch_stations_tbl", name: "Крупнейшие станции РЖД", viz_type: "table", dataset_key: "ds_stations", uuid_str: "c2000004-0004-0004-0004-000000000004",
+        params_json: r#"{
+            "viz_type": "table", "granularity_sqla": null, "time_range": "No filter", "query_mode": "raw",
+            "all_columns": ["name", "city", "region", "railway_branch", "passengers_day", "cargo_tons_year", "station_class"],
+            "order_by_cols": [["passengers_day", false]], "include_search": true, "page_length": 15
+        }"# },
+    ChartDef { key: "ch_daily_line", name: "Пассажиры по регионам (тыс.)", viz_type: "echarts_timeseries_line", dataset_key: "ds_daily", uuid_str: "c2000005-0005-0005-0005-000000000005",
+        params_json: r#"{
+            "viz_type": "echarts_timeseries_line", "granularity_sqla": "date", "time_range": "No filter",
+            "metrics": [{"aggregate": "SUM", "column": {"column_name": "passengers_thousands", "type": "FLOAT", "label": "passengers_thousands"}, "expressionType": "SIMPLE", "label": "Пассажиров (тыс.)"}],
+            "groupby": ["region"], "show_legend": true, "y_axis_format": ",.0f"
+        }"# },
+    ChartDef { key: "ch_incidents_bar", name: "Инциденты по типам", viz_type: "echarts_timeseries_bar", dataset_key: "ds_incidents", uuid_str: "c2000006-0006-0006-0006-000000000006",
+        params_json: r#"{
+            "viz_type": "echarts_timeseries_bar", "granularity_sqla": null, "time_range": "No filter", "x_axis": "incident_type",
+            "metrics": [{"aggregate": "COUNT", "column": {"column_name": "incident_id", "type": "STRING", "label": "incident_id"}, "expressionType": "SIMPLE", "label": "Количество"}],
+            "groupby": ["severity"], "stack": true, "show_legend": true, "y_axis_format": ",.0f"
+        }"# },
+];
+
+
+// --- UUID registry ---
+
+/// Kind of Superset object an upsert writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObjectKind {
+    Database,
+    Dataset,
+    Chart,
+    Dashboard,
+}
+
+impl ObjectKind {
+    fn table(self) -> &'static str {
+        match self {
+            ObjectKind::Database => "dbs",
+            ObjectKind::Dataset => "tables",
+            ObjectKind::Chart => "slices",
+            ObjectKind::Dashboard => "dashboards",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ObjectKind::Database => "database",
+            ObjectKind::Dataset => "dataset",
+            ObjectKind::Chart => "chart",
+            ObjectKind::Dashboard => "dashboard",
+        }
+    }
+
+    fn key(self, uuid: &str) -> String {
+        format!("{}:{}", self.name(), uuid)
+    }
+}
+
+/// A foreign object holding the name (or slug) one of ours wants
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Collision {
+    pub kind: ObjectKind,
+    pub name: String,
+    /// UUID the launcher uses for its object
+    pub uuid: String,
+    /// The other object, left untouched
+    pub their_id: i64,
+    pub their_uuid: Option<String>,
+    /// `shared` (used as is), `duplicate` (ours created beside it) or
+    /// `renamed:<slug>` (ours created under another slug)
+    pub resolution: String,
+}
+
+/// Rows of superset.db the upserts own, kept in `superset_home/demo_uuids.json`.
+/// Besides the UUID match it lets an upsert recognise its own row after the
+/// UUID was changed (a re-import, a restored backup) instead of mistaking it
+/// for a user's object of the same name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UuidRegistry {
+    /// `<kind>:<uuid>` -> row id
+    pub objects: BTreeMap<String, i64>,
+    /// Clashes found by the last update
+    pub collisions: Vec<Collision>,
+}
+
+impl UuidRegistry {
+    /// Registry of the kit; empty when missing or unreadable
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path).ok().and_then(|text| serde_json::from_str(&text).ok()).unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Row id recorded for our object
+    pub fn id_of(&self, kind: ObjectKind, uuid: &str) -> Option<i64> {
+        self.objects.get(&kind.key(uuid)).copied()
+    }
+
+    /// Whether row `id` is ours (not a foreign object we only share)
+    pub fn owns(&self, kind: ObjectKind, uuid: &str, id: i64) -> bool {
+        self.id_of(kind, uuid) == Some(id)
+    }
+
+    fn own(&mut self, kind: ObjectKind, uuid: &str, id: i64) {
+        self.objects.insert(kind.key(uuid), id);
+    }
+
+    fn collide(&mut self, kind: ObjectKind, name: &str, uuid: &str, their_id: i64, their_uuid: Option<String>, resolution: String) {
+        warn!(
+            "⚠️ {} '{}' (id={}, uuid={}) is not the launcher's ({}): {}",
+            kind.name(),
+            name,
+            their_id,
+            their_uuid.as_deref().unwrap_or("-"),
+            uuid,
+            resolution
+        );
+        self.collisions.push(Collision { kind, name: name.to_string(), uuid: uuid.to_string(), their_id, their_uuid, resolution });
+    }
+}
+
+/// Where `find` found the row for one of our objects
+#[derive(Debug, PartialEq, Eq)]
+enum Found {
+    /// Our row (by UUID, by the registry, or a legacy row without a UUID)
+    Ours(i64),
+    /// Someone else's object with the same name
+    Foreign(i64, Option<String>),
+    Missing,
+}
+
+/// Match by UUID first; fall back to the natural key (`key_sql`), where a row
+/// counts as ours only if the registry says so or it has no UUID at all
+fn find(conn: &Connection, registry: &UuidRegistry, kind: ObjectKind, uuid: &str, key_sql: &str, key: &[&dyn ToSql]) -> Result<Found> {
+    let table = kind.table();
+    let by_uuid: Option<i64> = conn
+        .query_row(&format!("SELECT id FROM {} WHERE uuid = ?", table), params![uuid_bytes(uuid)?], |r| r.get(0))
+        .optional()?;
+    if let Some(id) = by_uuid {
+        return Ok(Found::Ours(id));
+    }
+
+    let mut stmt = conn.prepare(&format!("SELECT id, uuid FROM {} WHERE {} ORDER BY id", table, key_sql))?;
+    let candidates: Vec<(i64, Option<Vec<u8>>)> = stmt.query_map(key, |r| Ok((r.get(0)?, r.get(1)?)))?.collect::<rusqlite::Result<_>>()?;
+    let recorded = registry.id_of(kind, uuid);
+    if let Some((id, _)) = candidates.iter().find(|(id, their)| Some(*id) == recorded || their.as_deref().unwrap_or_default().is_empty()) {
+        return Ok(Found::Ours(*id));
+    }
+    Ok(match candidates.into_iter().next() {
+        Some((id, their)) => Found::Foreign(id, their.and_then(|b| Uuid::from_slice(&b).ok()).map(|u| u.to_string())),
+        None => Found::Missing,
+    })
+}
+
+// --- Upserts ---
+
+fn now_iso() -> String {
+    Utc::now().format("%Y-%m-%d %H:%M:%S%.6f").to_string()
+}
+
+fn uuid_bytes(s: &str) -> Result<Vec<u8>> {
+    Ok(Uuid::parse_str(s).with_context(|| format!("Invalid UUID {}", s))?.as_bytes().to_vec())
+}
+
+/// Create or update the database connection `name`, returns its id. A
+/// foreign connection of that name is used as is if it points at the same
+/// URI, otherwise the update stops: Superset won't have two of one name.
+pub fn upsert_database(conn: &Connection, registry: &mut UuidRegistry, name: &str, uri: &str, uuid: &str) -> Result<i64> {
+    let now = now_iso();
+    let uuid_str = uuid;
+    let uuid = uuid_bytes(uuid)?;
+    match find(conn, registry, ObjectKind::Database, uuid_str, "database_name = ?", &[&name])? {
+        Found::Ours(id) => {
+            conn.execute(
+                "UPDATE dbs SET sqlalchemy_uri = ?, uuid = ?, changed_on = ? WHERE id = ?",
+                params![uri, uuid, now, id],
+            )?;
+            registry.own(ObjectKind::Database, uuid_str, id);
+            return Ok(id);
+        }
+        Found::Foreign(id, their_uuid) => {
+            let their_uri: String = conn.query_row("SELECT sqlalchemy_uri FROM dbs WHERE id = ?", params![id], |r| r.get(0))?;
+            if their_uri != uri {
+                bail!(
+                    "Database connection '{}' (id={}) is not the launcher's and points to {}; rename it in Superset",
+                    name,
+                    id,
+                    their_uri
+                );
+            }
+            registry.collide(ObjectKind::Database, name, uuid_str, id, their_uuid, "shared".to_string());
+            return Ok(id);
+        }
+        Found::Missing => {}
+    }
+    let extra = json!({
+        "metadata_params": {}, "engine_params": {}, "metadata_cache_timeout": {},
+        "schemas_allowed_for_file_upload": []
+    })
+    .to_string();
+    conn.execute(
+        "INSERT INTO dbs (database_name, sqlalchemy_uri, uuid, extra, expose_in_sqllab, allow_dml, allow_file_upload, created_on, changed_on, created_by_fk, changed_by_fk) VALUES (?, ?, ?, ?, 1, 1, 1, ?, ?, 1, 1)",
+        params![name, uri, uuid, extra, now, now],
+    )?;
+    let id = conn.last_insert_rowid();
+    registry.own(ObjectKind::Database, uuid_str, id);
+    Ok(id)
+}
+
+/// Create or update a dataset of database `database_id` and replace its
+/// columns, returns the dataset id. A foreign dataset on the same table is
+/// used as is (check `UuidRegistry::owns` before changing it further).
+pub fn upsert_dataset(conn: &Connection, registry: &mut UuidRegistry, database_id: i64, spec: &DatasetSpec) -> Result<i64> {
+    let now = now_iso();
+    let uuid = uuid_bytes(spec.uuid)?;
+    let database_name: String = conn
+        .query_row("SELECT database_name FROM dbs WHERE id = ?", params![database_id], |r| r.get(0))
+        .with_context(|| format!("Database id={} not found", database_id))?;
+    let perm = format!("[{}].[{}](id:{})", database_name, spec.table_name, database_id);
+
+    let found = find(conn, registry, ObjectKind::Dataset, spec.uuid, "table_name = ? AND database_id = ?", &[&spec.table_name, &database_id])?;
+    let table_id = match found {
+        Found::Ours(id) => {
+            conn.execute(
+                "UPDATE tables SET uuid = ?, description = ?, schema = '', perm = ?, main_dttm_col = ?, changed_on = ? WHERE id = ?",
+                params![uuid, spec.description, perm, spec.main_dttm_col, now, id],
+            )?;
+            id
+        }
+        Found::Foreign(id, their_uuid) => {
+            registry.collide(ObjectKind::Dataset, spec.table_name, spec.uuid, id, their_uuid, "shared".to_string());
+            return Ok(id);
+        }
+        Found::Missing => {
+            conn.execute(
+                "INSERT INTO tables (table_name, database_id, schema, description, uuid, perm, main_dttm_col, created_on, changed_on, created_by_fk, changed_by_fk, is_sqllab_view, filter_select_enabled) VALUES (?, ?, '', ?, ?, ?, ?, ?, ?, 1, 1, 0, 1)",
+                params![spec.table_name, database_id, spec.description, uuid, perm, spec.main_dttm_col, now, now],
+            )?;
+            conn.last_insert_rowid()
+        }
+    };
+    registry.own(ObjectKind::Dataset, spec.uuid, table_id);
+
+    conn.execute("DELETE FROM table_columns WHERE table_id = ?", params![table_id])?;
+    for column in spec.columns {
+        conn.execute(
+            "INSERT INTO table_columns (table_id, column_name, type, is_dttm, is_active, groupby, filterable, uuid, created_on, changed_on, created_by_fk, changed_by_fk) VALUES (?, ?, ?, ?, 1, ?, 1, ?, ?, ?, 1, 1)",
+            params![table_id, column.name, column.superset_type, column.is_dttm, column.groupby, Uuid::new_v4().as_bytes().to_vec(), now, now],
+        )?;
+    }
+    Ok(table_id)
+}
+
+/// Create or update a chart on dataset `dataset_id`, returns the chart id.
+/// A user's chart of the same name is left alone and ours is created beside it.
+pub fn upsert_chart(conn: &Connection, registry: &mut UuidRegistry, dataset_id: i64, spec: &ChartSpec) -> Result<i64> {
+    let now = now_iso();
+    let uuid = uuid_bytes(spec.uuid)?;
+    let table_name: String = conn
+        .query_row("SELECT table_name FROM tables WHERE id = ?", params![dataset_id], |r| r.get(0))
+        .with_context(|| format!("Dataset id={} not found", dataset_id))?;
+    let mut chart_params = spec.params.clone();
+    chart_params["datasource"] = json!(format!("{}__table", dataset_id));
+    let chart_params = chart_params.to_string();
+
+    let id = match find(conn, registry, ObjectKind::Chart, spec.uuid, "slice_name = ?", &[&spec.name])? {
+        Found::Ours(id) => {
+            conn.execute(
+                "UPDATE slices SET viz_type = ?, datasource_type = 'table', datasource_id = ?, datasource_name = ?, params = ?, uuid = ?, changed_on = ? WHERE id = ?",
+                params![spec.viz_type, dataset_id, table_name, chart_params, uuid, now, id],
+            )?;
+            id
+        }
+        found => {
+            if let Found::Foreign(their_id, their_uuid) = found {
+                registry.collide(ObjectKind::Chart, spec.name, spec.uuid, their_id, their_uuid, "duplicate".to_string());
+            }
+            conn.execute(
+                "INSERT INTO slices (slice_name, viz_type, datasource_type, datasource_id, datasource_name, params, uuid, created_on, changed_on, created_by_fk, changed_by_fk) VALUES (?, ?, 'table', ?, ?, ?, ?, ?, ?, 1, 1)",
+                params![spec.name, spec.viz_type, dataset_id, table_name, chart_params, uuid, now, now],
+            )?;
+            conn.last_insert_rowid()
+        }
+    };
+    registry.own(ObjectKind::Chart, spec.uuid, id);
+    Ok(id)
+}
+
+/// Create or update a published dashboard holding exactly `chart_ids`,
+/// returns the dashboard id. Slugs are unique, so when a user's dashboard
+/// has ours, ours is created under `<slug>-<first UUID group>`.
+pub fn upsert_dashboard(conn: &Connection, registry: &mut UuidRegistry, spec: &DashboardSpec, chart_ids: &[i64]) -> Result<i64> {
+    let now = now_iso();
+    let uuid = uuid_bytes(spec.uuid)?;
+    let position = spec.position.to_string();
+    let metadata = spec.metadata.to_string();
+    let dashboard_id = match find(conn, registry, ObjectKind::Dashboard, spec.uuid, "slug = ?", &[&spec.slug])? {
+        Found::Ours(id) => {
+            conn.execute(
+                "UPDATE dashboards SET dashboard_title = ?, position_json = ?, json_metadata = ?, uuid = ?, published = 1, changed_on = ? WHERE id = ?",
+                params![spec.title, position, metadata, uuid, now, id],
+            )?;
+            id
+        }
+        found => {
+            let slug = match found {
+                Found::Foreign(their_id, their_uuid) => {
+                    let slug = format!("{}-{}", spec.slug, spec.uuid.split('-').next().unwrap_or_default());
+                    registry.collide(ObjectKind::Dashboard, spec.slug, spec.uuid, their_id, their_uuid, format!("renamed:{}", slug));
+                    slug
+                }
+                _ => spec.slug.to_string(),
+            };
+            conn.execute(
+                "INSERT INTO dashboards (dashboard_title, slug, position_json, json_metadata, uuid, published, created_on, changed_on, created_by_fk, changed_by_fk) VALUES (?, ?, ?, ?, ?, 1, ?, ?, 1, 1)",
+                params![spec.title, slug, position, metadata, uuid, now, now],
+            )?;
+            conn.last_insert_rowid()
+        }
+    };
+    registry.own(ObjectKind::Dashboard, spec.uuid, dashboard_id);
+
+    conn.execute("DELETE FROM dashboard_slices WHERE dashboard_id = ?", params![dashboard_id])?;
+    for chart_id in chart_ids {
+        conn.execute(
+            "INSERT INTO dashboard_slices (dashboard_id, slice_id) VALUES (?, ?)",
+            params![dashboard_id, chart_id],
+        )?;
+    }
+    Ok(dashboard_id)
+}
+
+/// Columns of `table` in examples.db, typed from the SQLite declaration.
+/// Measures (`FLOAT`) can't be grouped by unless `groupby_all` is set.
+pub fn columns_of(data: &Connection, table: &str, groupby_all: bool) -> Result<Vec<ColumnSpec>> {
+    let mut stmt = data.prepare(&format!("PRAGMA table_info({})", quote_ident(table)))?;
+    let declared: Vec<(String, String)> = stmt
+        .query_map([], |r| Ok((r.get(1)?, r.get(2)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(declared
+        .into_iter()
+        .map(|(name, declared_type)| {
+            let superset_type = if declared_type.contains("INT") {
+                "INTEGER"
+            } else if declared_type.contains("REAL") || declared_type.contains("FLOAT") {
+                "FLOAT"
+            } else {
+                "STRING"
+            };
+            ColumnSpec {
+                is_dttm: name == "date",
+                groupby: groupby_all || superset_type != "FLOAT",
+                name,
+                superset_type,
+            }
+        })
+        .collect())
+}
+
+/// Value ranges and distinct counts as dataset column descriptions and
+/// `column_stats` in the dataset extra (the profile `load-data` writes).
+/// A description written by hand is kept.
+pub fn describe_columns(meta: &Connection, data: &Connection, table_id: i64, table: &str) -> Result<()> {
+    let mut stats = serde_json::Map::new();
+    for profile in profile_table(data, table)? {
+        meta.execute(
+            "UPDATE table_columns SET description = ?1 WHERE table_id = ?2 AND column_name = ?3
+               AND (description IS NULL OR description = '' OR substr(description, 1, length(?4)) = ?4)",
+            params![profile.description(), table_id, profile.column, PROFILE_DESCRIPTION_PREFIX],
+        )?;
+        stats.insert(profile.column.clone(), profile.stats());
+    }
+
+    let extra: Option<String> = meta.query_row("SELECT extra FROM tables WHERE id = ?", params![table_id], |r| r.get(0))?;
+    let mut extra: Value = extra.and_then(|e| serde_json::from_str(&e).ok()).filter(|e: &Value| e.is_object()).unwrap_or_else(|| json!({}));
+    extra["column_stats"] = Value::Object(stats);
+    extra["column_stats_at"] = json!(now_iso());
+    meta.execute("UPDATE tables SET extra = ? WHERE id = ?", params![extra.to_string(), table_id])?;
+    Ok(())
+}
+
+// --- Demo rebuild ---
+
+/// Record row count and load time for the launcher's freshness panel
+fn record_freshness(conn: &Connection, table: &str, source: &str) -> Result<()> {
+    conn.execute(&format!("CREATE TABLE IF NOT EXISTS \"{}\" (table_name TEXT PRIMARY KEY, row_count INTEGER NOT NULL, updated_at TEXT NOT NULL, source TEXT)", FRESHNESS_TABLE), [])?;
+    let rows: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {}", quote_ident(table)), [], |r| r.get(0))?;
+    conn.execute(
+        &format!("INSERT OR REPLACE INTO \"{}\" (table_name, row_count, updated_at, source) VALUES (?, ?, ?, ?)", FRESHNESS_TABLE),
+        params![table, rows, chrono::Local::now().to_rfc3339(), source],
+    )?;
+    Ok(())
+}
+
+fn infer_col_type(val: &str) -> &'static str {
+    if val.is_empty() {
+        return "TEXT";
+    }
+    if val.parse::<i64>().is_ok() {
+        return "INTEGER";
+    }
+    if val.parse::<f64>().is_ok() {
+        return "REAL";
+    }
+    "TEXT"
+}
+
+/// Recreate the demo tables in examples.db from their CSV files (types from
+/// the first row) and the derived tables from SQL
+pub fn update_examples_db(root: &Path) -> Result<()> {
+    let db_path = root.join(EXAMPLES_DB_PATH);
+    let conn = Connection::open(&db_path).with_context(|| format!("Cannot open {}", db_path.display()))?;
+
+    for ds in DATASETS {
+        // Re-creation strategy: other tables in examples.db are left alone
+        conn.execute(&format!("DROP TABLE IF EXISTS \"{}\"", ds.table_name), [])?;
+
+        if let Some(sql) = ds.sql_create {
+            conn.execute(sql, [])?;
+            record_freshness(&conn, ds.table_name, "create_dashboard")?;
+            info!("  Derived table '{}' created", ds.table_name);
+            continue;
+        }
+
+        let csv_path = root.join(DEMO_DATA_DIR).join(ds.csv);
+        if !csv_path.exists() {
+            info!("  Skipped '{}': {} not found", ds.table_name, csv_path.display());
+            continue;
+        }
+
+        let mut rdr = csv::Reader::from_path(&csv_path)?;
+        let headers = rdr.headers()?.clone();
+        let types: Vec<&str> = match rdr.records().next() {
+            Some(record) => record?.iter().map(infer_col_type).collect(),
+            None => headers.iter().map(|_| "TEXT").collect(),
+        };
+        let cols_def: Vec<String> = headers
+            .iter()
+            .zip(types.iter())
+            .map(|(name, typ)| format!("\"{}\" {}", name, typ))
+            .collect();
+        conn.execute(&format!("CREATE TABLE \"{}\" ({})", ds.table_name, cols_def.join(", ")), [])?;
+
+        let placeholders = vec!["?"; headers.len()].join(", ");
+        let mut stmt = conn.prepare(&format!("INSERT INTO \"{}\" VALUES ({})", ds.table_name, placeholders))?;
+        let mut row_count = 0;
+        // The first record was consumed for the types, read the file again
+        for record in csv::Reader::from_path(&csv_path)?.records() {
+            stmt.execute(rusqlite::params_from_iter(record?.iter()))?;
+            row_count += 1;
+        }
+
+        record_freshness(&conn, ds.table_name, ds.csv)?;
+        info!("  Table '{}': {} rows", ds.table_name, row_count);
+    }
+    Ok(())
+}
+
+/// Point the `examples` database at examples.db and upsert the demo
+/// datasets, charts and dashboards in superset.db
+pub fn update_metadata(root: &Path) -> Result<()> {
+    let db_path = root.join(SUPERSET_HOME_DIR).join(SUPERSET_DB_NAME);
+    if !db_path.exists() {
+        bail!("superset.db not found at {}", db_path.display());
+    }
+    let registry_path = root.join(SUPERSET_HOME_DIR).join(UUID_REGISTRY_NAME);
+    let mut registry = UuidRegistry::load(&registry_path);
+    registry.collisions.clear();
+    let mut conn = Connection::open(&db_path)?;
+    let data = Connection::open(root.join(EXAMPLES_DB_PATH))?;
+    let tx = conn.transaction()?;
+
+    let examples_abs = root.join(EXAMPLES_DB_PATH);
+    let uri = format!("sqlite:///{}", examples_abs.to_string_lossy().replace('\\', "/"));
+    let db_id = upsert_database(&tx, &mut registry, "examples", &uri, UUID_DB_EXAMPLES)?;
+    info!("  Database 'examples' (id={})", db_id);
+
+    let mut dataset_ids: HashMap<&str, i64> = HashMap::new();
+    for ds in DATASETS {
+        let columns = columns_of(&data, ds.table_name, ds.sql_create.is_some())?;
+        if columns.is_empty() {
+            bail!("Table '{}' not found in examples.db", ds.table_name);
+        }
+        let spec = DatasetSpec {
+            table_name: ds.table_name,
+            description: ds.description,
+            uuid: ds.uuid_str,
+            main_dttm_col: ds.main_dttm_col,
+            columns: &columns,
+        };
+        let table_id = upsert_dataset(&tx, &mut registry, db_id, &spec)?;
+        if registry.owns(ObjectKind::Dataset, ds.uuid_str, table_id) {
+            describe_columns(&tx, &data, table_id, ds.table_name)?;
+        }
+        dataset_ids.insert(ds.key, table_id);
+        info!("  Dataset '{}' (id={})", ds.table_name, table_id);
+    }
+
+    let mut chart_ids: HashMap<&str, i64> = HashMap::new();
+    for chart in CHARTS {
+        let dataset_id = *dataset_ids
+            .get(chart.dataset_key)
+            .with_context(|| format!("Dataset '{}' of chart '{}' not registered", chart.dataset_key, chart.name))?;
+        let chart_params: Value = serde_json::from_str(chart.params_json)?;
+        let spec = ChartSpec { name: chart.name, viz_type: chart.viz_type, uuid: chart.uuid_str, params: &chart_params };
+        let chart_id = upsert_chart(&tx, &mut registry, dataset_id, &spec)?;
+        chart_ids.insert(chart.key, chart_id);
+        info!("  Chart '{}' (id={})", chart.name, chart_id);
+    }
+
+    let metadata = json!({
+        "color_scheme": "supersetColors",
+        "refresh_frequency": 0,
+        "expanded_slices": {},
+        "timed_refresh_immune_slices": [],
+        "label_colors": {},
+        "shared_label_colors": {},
+        "color_scheme_domain": [],
+        "map_label_colors": {}
+    });
+
+    let ch_total = chart_ids["ch_total_pass"];
+    let ch_bar = chart_ids["ch_monthly_bar"];
+    let ch_pie = chart_ids["ch_cargo_pie"];
+    let ch_line = chart_ids["ch_daily_line"];
+    let ch_table = chart_ids["ch_stations_tbl"];
+    let ch_inc = chart_ids["ch_incidents_bar"];
+    let position = json!({
+        "DASHBOARD_VERSION_KEY": "v2",
+        "ROOT_ID": { "id": "ROOT_ID", "type": "ROOT", "children": ["GRID_ID"] },
+        "GRID_ID": { "id": "GRID_ID", "type": "GRID", "children": ["ROW-1", "ROW-2", "ROW-3"], "parents": ["ROOT_ID"] },
+        "HEADER_ID": { "id": "HEADER_ID", "type": "HEADER", "meta": { "text": "РЖД Аналитика" } },
+
+        // Row 1
+        "ROW-1": { "id": "ROW-1", "type": "ROW", "children": ["CHART-total", "CHART-bar"], "meta": { "background": "BACKGROUND_TRANSPARENT" } },
+        "CHART-total": { "id": "CHART-total", "type": "CHART", "children": [], "meta": { "chartId": ch_total, "width": 4, "height": 50, "sliceName": "Пассажиропоток (млн)", "uuid": UUID_CH_TOTAL_PASS } },
+        "CHART-bar": { "id": "CHART-bar", "type": "CHART", "children": [], "meta": { "chartId": ch_bar, "width": 8, "height": 50, "sliceName": "Выручка по месяцам (млрд руб)", "uuid": UUID_CH_MONTHLY_BAR } },
+
+        // Row 2
+        "ROW-2": { "id": "ROW-2", "type": "ROW", "children": ["CHART-pie", "CHART-line"], "meta": { "background": "BACKGROUND_TRANSPARENT" } },
+        "CHART-pie": { "id": "CHART-pie", "type": "CHART", "children": [], "meta": { "chartId": ch_pie, "width": 4, "height": 50, "sliceName": "Распределение грузов", "uuid": UUID_CH_CARGO_PIE } },
+        "CHART-line": { "id": "CHART-line", "type": "CHART", "children": [], "meta": { "chartId": ch_line, "width": 8, "height": 50, "sliceName": "Пассажиры по регионам (тыс.)", "uuid": UUID_CH_DAILY_LINE } },
+
+        // Row 3
+        "ROW-3": { "id": "ROW-3", "type": "ROW", "children": ["CHART-table", "CHART-inc"], "meta": { "background": "BACKGROUND_TRANSPARENT" } },
+        "CHART-table": { "id": "CHART-table", "type": "CHART", "children": [], "meta": { "chartId": ch_table, "width": 8, "height": 50, "sliceName": "Крупнейшие станции РЖД", "uuid": UUID_CH_STATIONS_TBL } },
+        "CHART-inc": { "id": "CHART-inc", "type": "CHART", "children": [], "meta": { "chartId": ch_inc, "width": 4, "height": 50, "sliceName": "Инциденты по типам", "uuid": UUID_CH_INCIDENTS_BAR } }
+    });
+    let spec = DashboardSpec {
+        title: "РЖД Аналитика",
+        slug: "rzd_analytics",
+        uuid: UUID_DASHBOARD,
+        position: &position,
+        metadata: &metadata,
+    };
+    let dash_id = upsert_dashboard(&tx, &mut registry, &spec, &[ch_total, ch_bar, ch_pie, ch_line, ch_table, ch_inc])?;
+    info!("  Dashboard '{}' (id={})", spec.title, dash_id);
+
+    let ch_world_table = chart_ids["ch_world_stats"];
+    let ch_world_map = chart_ids["ch_world_map"];
+    let world_position = json!({
+        "DASHBOARD_VERSION_KEY": "v2",
+        "ROOT_ID": { "id": "ROOT_ID", "type": "ROOT", "children": ["GRID_ID"] },
+        "GRID_ID": { "id": "GRID_ID", "type": "GRID", "children": ["ROW-MAP", "ROW-TABLE"], "parents": ["ROOT_ID"] },
+        "HEADER_ID": { "id": "HEADER_ID", "type": "HEADER", "meta": { "text": "World Railways (Offline Map)" } },
+
+        "ROW-MAP": { "id": "ROW-MAP", "type": "ROW", "children": ["CHART-MAP"], "meta": { "background": "BACKGROUND_TRANSPARENT" } },
+        "CHART-MAP": { "id": "CHART-MAP", "type": "CHART", "children": [], "meta": { "chartId": ch_world_map, "width": 12, "height": 60, "sliceName": "Global Networks", "uuid": UUID_CH_WORLD_MAP } },
+
+        "ROW-TABLE": { "id": "ROW-TABLE", "type": "ROW", "children": ["CHART-TABLE"], "meta": { "background": "BACKGROUND_TRANSPARENT" } },
+        "CHART-TABLE": { "id": "CHART-TABLE", "type": "CHART", "children": [], "meta": { "chartId": ch_world_table, "width": 12, "height": 40, "sliceName": "Railway Statistics", "uuid": UUID_CH_WORLD_STATS } }
+    });
+    let spec = DashboardSpec {
+        title: "World Railways",
+        slug: "world_railways",
+        uuid: UUID_WORLD_DASHBOARD,
+        position: &world_position,
+        metadata: &metadata,
+    };
+    let world_dash_id = upsert_dashboard(&tx, &mut registry, &spec, &[ch_world_map, ch_world_table])?;
+    info!("  Dashboard '{}' (id={})", spec.title, world_dash_id);
+
+    tx.commit()?;
+    registry.save(&registry_path)?;
+    if !registry.collisions.is_empty() {
+        warn!("⚠️ {} name clashes with user objects, see {}", registry.collisions.len(), registry_path.display());
+    }
+    Ok(())
+}
+
+/// Indexes for the demo filters, then VACUUM
+fn optimize_examples_db(root: &Path) -> Result<()> {
+    let conn = Connection::open(root.join(EXAMPLES_DB_PATH))?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_daily_date ON rzd_daily_operations(date)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_daily_region ON rzd_daily_operations(region)", [])?;
+    conn.execute("VACUUM", [])?;
+    Ok(())
+}
+
+/// Reload the demo data and refresh its dashboards (what `create_dashboard` does)
+pub fn rebuild_demo(root: &Path) -> Result<()> {
+    info!("📥 Loading demo tables from {}", root.join(DEMO_DATA_DIR).display());
+    update_examples_db(root)?;
+    info!("🧩 Updating Superset metadata");
+    update_metadata(root)?;
+    optimize_examples_db(root)?;
+    info!("✅ Demo dashboards updated");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn superset_schema() -> Connection {
+        let meta = Connection::open_in_memory().unwrap();
+        meta.execute_batch(
+            "CREATE TABLE dbs (id INTEGER PRIMARY KEY, database_name TEXT, sqlalchemy_uri TEXT, uuid BLOB, extra TEXT,
+                expose_in_sqllab BOOLEAN, allow_dml BOOLEAN, allow_file_upload BOOLEAN, created_on DATETIME,
+                changed_on DATETIME, created_by_fk INTEGER, changed_by_fk INTEGER);
+             CREATE TABLE tables (id INTEGER PRIMARY KEY, table_name TEXT, database_id INTEGER, schema TEXT, description TEXT,
+                uuid BLOB, main_dttm_col TEXT, perm TEXT, extra TEXT, created_on DATETIME, changed_on DATETIME,
+                created_by_fk INTEGER, changed_by_fk INTEGER, is_sqllab_view BOOLEAN, filter_select_enabled BOOLEAN);
+             CREATE TABLE table_columns (id INTEGER PRIMARY KEY, table_id INTEGER, column_name TEXT, type TEXT, description TEXT,
+                is_dttm BOOLEAN, is_active BOOLEAN, groupby BOOLEAN, filterable BOOLEAN, uuid BLOB,
+                created_on DATETIME, changed_on DATETIME, created_by_fk INTEGER, changed_by_fk INTEGER);
+             CREATE TABLE slices (id INTEGER PRIMARY KEY, slice_name TEXT, viz_type TEXT, datasource_type TEXT,
+                datasource_id INTEGER, datasource_name TEXT, params TEXT, uuid BLOB, created_on DATETIME,
+                changed_on DATETIME, created_by_fk INTEGER, changed_by_fk INTEGER);
+             CREATE TABLE dashboards (id INTEGER PRIMARY KEY, dashboard_title TEXT, slug TEXT, position_json TEXT,
+                json_metadata TEXT, uuid BLOB, published BOOLEAN, created_on DATETIME, changed_on DATETIME,
+                created_by_fk INTEGER, changed_by_fk INTEGER);
+             CREATE TABLE dashboard_slices (id INTEGER PRIMARY KEY, dashboard_id INTEGER, slice_id INTEGER);",
+        )
+        .unwrap();
+        meta
+    }
+
+    #[test]
+    fn test_upserts_update_in_place() {
+        let meta = superset_schema();
+        let data = Connection::open_in_memory().unwrap();
+        data.execute_batch(
+            "CREATE TABLE ops (date TEXT, region TEXT, trains INTEGER, revenue REAL);
+             INSERT INTO ops VALUES ('2024-01-01', 'Север', 3, 1.5), ('2024-01-02', 'Юг', 5, 2.5);",
+        )
+        .unwrap();
+
+        let columns = columns_of(&data, "ops", false).unwrap();
+        assert_eq!(columns.len(), 4);
+        assert!(columns[0].is_dttm);
+        assert_eq!(columns[3].superset_type, "FLOAT");
+        assert!(!columns[3].groupby);
+        assert!(columns_of(&data, "missing", false).unwrap().is_empty());
+
+        let params = json!({"viz_type": "table"});
+        let position = json!({"DASHBOARD_VERSION_KEY": "v2"});
+        let metadata = json!({});
+        let mut registry = UuidRegistry::default();
+        let mut ids = Vec::new();
+        for uri in ["sqlite:///a.db", "sqlite:///b.db"] {
+            let db_id = upsert_database(&meta, &mut registry, "examples", uri, UUID_DB_EXAMPLES).unwrap();
+            let dataset = DatasetSpec {
+                table_name: "ops",
+                description: "Операции",
+                uuid: "d1000004-0004-0004-0004-000000000004",
+                main_dttm_col: Some("date"),
+                columns: &columns,
+            };
+            let table_id = upsert_dataset(&meta, &mut registry, db_id, &dataset).unwrap();
+            describe_columns(&meta, &data, table_id, "ops").unwrap();
+            let chart = ChartSpec { name: "Операции", viz_type: "table", uuid: UUID_CH_TOTAL_PASS, params: &params };
+            let chart_id = upsert_chart(&meta, &mut registry, table_id, &chart).unwrap();
+            let dashboard = DashboardSpec {
+                title: "Операции",
+                slug: "ops",
+                uuid: UUID_DASHBOARD,
+                position: &position,
+                metadata: &metadata,
+            };
+            let dash_id = upsert_dashboard(&meta, &mut registry, &dashboard, &[chart_id]).unwrap();
+            ids.push((db_id, table_id, chart_id, dash_id));
+        }
+        assert_eq!(ids[0], ids[1]);
+
+        let count = |sql: &str| -> i64 { meta.query_row(sql, [], |r| r.get(0)).unwrap() };
+        assert_eq!(count("SELECT COUNT(*) FROM dbs"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM table_columns"), 4);
+        assert_eq!(count("SELECT COUNT(*) FROM slices"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM dashboard_slices"), 1);
+
+        let uri: String = meta.query_row("SELECT sqlalchemy_uri FROM dbs", [], |r| r.get(0)).unwrap();
+        assert_eq!(uri, "sqlite:///b.db");
+        let perm: String = meta.query_row("SELECT perm FROM tables", [], |r| r.get(0)).unwrap();
+        assert_eq!(perm, format!("[examples].[ops](id:{})", ids[0].0));
+        let chart_params: String = meta.query_row("SELECT params FROM slices", [], |r| r.get(0)).unwrap();
+        assert!(chart_params.contains(&format!("\"{}__table\"", ids[0].1)));
+        let description: String = meta
+            .query_row("SELECT description FROM table_columns WHERE column_name = 'trains'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(description, "📊 от 3 до 5 · различных значений: 2");
+        meta.execute("UPDATE table_columns SET description = 'Поездов за день' WHERE column_name = 'trains'", []).unwrap();
+        describe_columns(&meta, &data, ids[0].1, "ops").unwrap();
+        let description: String = meta
+            .query_row("SELECT description FROM table_columns WHERE column_name = 'trains'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(description, "Поездов за день");
+
+        assert!(registry.collisions.is_empty());
+        assert!(upsert_chart(&meta, &mut registry, 999, &ChartSpec { name: "x", viz_type: "table", uuid: UUID_CH_TOTAL_PASS, params: &params }).is_err());
+    }
+
+    #[test]
+    fn test_uuid_collisions() {
+        let meta = superset_schema();
+        let mut registry = UuidRegistry::default();
+        let foreign = Uuid::new_v4();
+        meta.execute_batch(&format!(
+            "INSERT INTO dbs (id, database_name, sqlalchemy_uri) VALUES (1, 'examples', 'sqlite:///a.db');
+             INSERT INTO tables (id, table_name, database_id) VALUES (1, 'ops', 1);
+             INSERT INTO slices (id, slice_name, uuid) VALUES (1, 'Операции', X'{0}');
+             INSERT INTO dashboards (id, slug, uuid) VALUES (1, 'ops', NULL), (2, 'kpi', X'{0}');",
+            foreign.simple()
+        ))
+        .unwrap();
+
+        // No UUID on the legacy rows: taken over and stamped
+        assert_eq!(upsert_database(&meta, &mut registry, "examples", "sqlite:///a.db", UUID_DB_EXAMPLES).unwrap(), 1);
+        let params = json!({});
+        let chart = ChartSpec { name: "Операции", viz_type: "table", uuid: UUID_CH_TOTAL_PASS, params: &params };
+        let chart_id = upsert_chart(&meta, &mut registry, 1, &chart).unwrap();
+        assert_ne!(chart_id, 1, "the user's chart of the same name is left alone");
+        assert_eq!(upsert_chart(&meta, &mut registry, 1, &chart).unwrap(), chart_id);
+
+        let layout = json!({});
+        let ops = DashboardSpec { title: "Ops", slug: "ops", uuid: UUID_DASHBOARD, position: &layout, metadata: &layout };
+        assert_eq!(upsert_dashboard(&meta, &mut registry, &ops, &[chart_id]).unwrap(), 1);
+        let kpi = DashboardSpec { title: "KPI", slug: "kpi", uuid: UUID_WORLD_DASHBOARD, position: &layout, metadata: &layout };
+        let kpi_id = upsert_dashboard(&meta, &mut registry, &kpi, &[]).unwrap();
+        let slug: String = meta.query_row("SELECT slug FROM dashboards WHERE id = ?", params![kpi_id], |r| r.get(0)).unwrap();
+        assert_eq!(slug, "kpi-e4000001");
+
+        let resolutions: Vec<(ObjectKind, &str)> = registry.collisions.iter().map(|c| (c.kind, c.resolution.as_str())).collect();
+        assert_eq!(resolutions, [(ObjectKind::Chart, "duplicate"), (ObjectKind::Dashboard, "renamed:kpi-e4000001")]);
+        assert_eq!(registry.collisions[0].their_uuid, Some(foreign.to_string()));
+
+        // Our dashboard got a new UUID (e.g. re-imported): the registry still knows its row
+        meta.execute("UPDATE dashboards SET uuid = ? WHERE id = 1", params![Uuid::new_v4().as_bytes().to_vec()]).unwrap();
+        assert_eq!(upsert_dashboard(&meta, &mut registry, &ops, &[chart_id]).unwrap(), 1);
+
+        // A foreign connection of the same name elsewhere stops the update
+        meta.execute("UPDATE dbs SET uuid = ?, sqlalchemy_uri = 'postgresql://x' WHERE id = 1", params![foreign.as_bytes().to_vec()]).unwrap();
+        let mut fresh = UuidRegistry::default();
+        assert!(upsert_database(&meta, &mut fresh, "examples", "sqlite:///a.db", UUID_DB_EXAMPLES).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+//! Diff of two superset.db snapshots
+//!
+//! `metadata diff old.db new.db` matches dashboards, charts and datasets by
+//! UUID (ids change whenever the demo bundle is rebuilt) and lists what was
+//! added, removed or changed, down to the top-level keys of JSON fields such
+//! as chart `params`, so a rebuilt bundle can be reviewed before shipping.
+
+use anyhow::{Context, Result};
+use rusqlite::{types::Value, Connection, OpenFlags};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::data_loader::quote_ident;
+
+/// Compared object kinds: (kind, table, title column, compared columns)
+const KINDS: &[(&str, &str, &str, &[&str])] = &[
+    (
+        "dashboard",
+        "dashboards",
+        "dashboard_title",
+        &["dashboard_title", "slug", "description", "css", "published", "position_json", "json_metadata"],
+    ),
+    (
+        "chart",
+        "slices",
+        "slice_name",
+        &["slice_name", "viz_type", "description", "datasource_type", "cache_timeout", "params", "query_context"],
+    ),
+    (
+        "dataset",
+        "tables",
+        "table_name",
+        &["table_name", "schema", "sql", "main_dttm_col", "description", "is_sqllab_view", "cache_timeout", "extra"],
+    ),
+];
+
+/// Columns holding JSON, compared key by key
+const JSON_COLUMNS: &[&str] = &["position_json", "json_metadata", "params", "query_context", "extra"];
+
+/// One object present in only one snapshot
+#[derive(Debug, Clone, Serialize)]
+pub struct ObjectRef {
+    pub uuid: String,
+    pub title: String,
+}
+
+/// Object present in both snapshots with different content
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangedObject {
+    pub uuid: String,
+    pub title: String,
+    /// Changed fields, `params.color_scheme` for keys inside JSON columns
+    pub fields: Vec<String>,
+}
+
+/// Differences for one object kind
+#[derive(Debug, Clone, Serialize)]
+pub struct KindDiff {
+    pub kind: &'static str,
+    pub added: Vec<ObjectRef>,
+    pub removed: Vec<ObjectRef>,
+    pub changed: Vec<ChangedObject>,
+    /// Objects without a UUID (not comparable)
+    pub skipped: usize,
+}
+
+impl KindDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Object snapshot: title plus compared field values
+struct Snapshot {
+    title: String,
+    fields: BTreeMap<String, Value>,
+}
+
+/// Compare two superset.db files
+pub fn diff(old: &Path, new: &Path) -> Result<Vec<KindDiff>> {
+    let old = open(old)?;
+    let new = open(new)?;
+    KINDS
+        .iter()
+        .map(|(kind, table, title, columns)| {
+            let (before, skipped_old) = load(&old, kind, table, title, columns)?;
+            let (after, skipped_new) = load(&new, kind, table, title, columns)?;
+            let mut diff = compare(kind, &before, &after);
+            diff.skipped = skipped_old + skipped_new;
+            Ok(diff)
+        })
+        .collect()
+}
+
+fn open(path: &Path) -> Result<Connection> {
+    if !path.exists() {
+        anyhow::bail!("{} not found", path.display());
+    }
+    Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open {}", path.display()))
+}
+
+/// Columns of `table` (empty if the table doesn't exist)
+fn table_columns(conn: &Connection, table: &str) -> Result<HashSet<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", quote_ident(table)))?;
+    let columns = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    Ok(columns.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Objects of one kind keyed by UUID, plus the number without one.
+/// Columns missing from this Superset version are left out.
+fn load(
+    conn: &Connection,
+    kind: &str,
+    table: &str,
+    title: &str,
+    columns: &[&str],
+) -> Result<(BTreeMap<String, Snapshot>, usize)> {
+    let existing = table_columns(conn, table)?;
+    if !existing.contains("uuid") {
+        return Ok((BTreeMap::new(), 0));
+    }
+    let columns: Vec<&str> = columns.iter().copied().filter(|c| existing.contains(*c)).collect();
+    let select: Vec<String> = columns.iter().map(|c| quote_ident(c)).collect();
+    let sql = format!("SELECT uuid, {}, {} FROM {}", quote_ident(title), select.join(", "), quote_ident(table));
+
+    let mut stmt = conn.prepare(&sql).with_context(|| format!("Failed to read {}s", kind))?;
+    let mut rows = stmt.query([])?;
+    let mut objects = BTreeMap::new();
+    let mut skipped = 0;
+    while let Some(row) = rows.next()? {
+        let Some(uuid) = uuid_text(row.get(0)?) else {
+            skipped += 1;
+            continue;
+        };
+        let title = row.get::<_, Option<String>>(1)?.unwrap_or_default();
+        let mut fields = BTreeMap::new();
+        for (i, column) in columns.iter().enumerate() {
+            fields.insert(column.to_string(), row.get::<_, Value>(i + 2)?);
+        }
+        objects.insert(uuid, Snapshot { title, fields });
+    }
+    Ok((objects, skipped))
+}
+
+/// Superset stores UUIDs as 16-byte blobs; older exports as text
+fn uuid_text(value: Value) -> Option<String> {
+    match value {
+        Value::Blob(bytes) => Uuid::from_slice(&bytes).ok().map(|u| u.to_string()),
+        Value::Text(text) => Uuid::parse_str(&text).ok().map(|u| u.to_string()),
+        _ => None,
+    }
+}
+
+fn compare(kind: &'static str, before: &BTreeMap<String, Snapshot>, after: &BTreeMap<String, Snapshot>) -> KindDiff {
+    let mut diff = KindDiff { kind, added: Vec::new(), removed: Vec::new(), changed: Vec::new(), skipped: 0 };
+
+    for (uuid, new) in after {
+        let Some(old) = before.get(uuid) else {
+            diff.added.push(ObjectRef { uuid: uuid.clone(), title: new.title.clone() });
+            continue;
+        };
+        let mut fields = Vec::new();
+        for (column, value) in &new.fields {
+            // A column added by a newer Superset isn't a change to the object
+            let Some(old_value) = old.fields.get(column) else {
+                continue;
+            };
+            if old_value != value {
+                fields.extend(changed_fields(column, old_value, value));
+            }
+        }
+        if !fields.is_empty() {
+            diff.changed.push(ChangedObject { uuid: uuid.clone(), title: new.title.clone(), fields });
+        }
+    }
+    for (uuid, old) in before {
+        if !after.contains_key(uuid) {
+            diff.removed.push(ObjectRef { uuid: uuid.clone(), title: old.title.clone() });
+        }
+    }
+    diff
+}
+
+/// Changed field names; JSON columns are compared by top-level key so
+/// reformatting doesn't count and `params.color_scheme` shows what moved
+fn changed_fields(column: &str, old: &Value, new: &Value) -> Vec<String> {
+    if JSON_COLUMNS.contains(&column) {
+        if let (Some(old), Some(new)) = (json(old), json(new)) {
+            return match (old, new) {
+                (serde_json::Value::Object(old), serde_json::Value::Object(new)) => {
+                    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+                    keys.into_iter()
+                        .filter(|k| old.get(*k) != new.get(*k))
+                        .map(|k| format!("{}.{}", column, k))
+                        .collect()
+                }
+                (old, new) if old == new => Vec::new(),
+                _ => vec![column.to_string()],
+            };
+        }
+    }
+    vec![column.to_string()]
+}
+
+fn json(value: &Value) -> Option<serde_json::Value> {
+    match value {
+        Value::Null => Some(serde_json::Value::Null),
+        Value::Text(text) if text.trim().is_empty() => Some(serde_json::Value::Null),
+        Value::Text(text) => serde_json::from_str(text).ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn snapshot(path: &Path, charts: &[(Uuid, &str, &str)]) {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE dashboards (id INTEGER PRIMARY KEY, dashboard_title TEXT, slug TEXT, uuid BLOB);
+             CREATE TABLE slices (id INTEGER PRIMARY KEY, slice_name TEXT, viz_type TEXT, params TEXT, uuid BLOB);
+             CREATE TABLE tables (id INTEGER PRIMARY KEY, table_name TEXT, uuid BLOB);",
+        )
+        .unwrap();
+        for (uuid, name, params) in charts {
+            conn.execute(
+                "INSERT INTO slices (slice_name, viz_type, params, uuid) VALUES (?1, 'line', ?2, ?3)",
+                rusqlite::params![name, params, uuid],
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_diff_by_uuid() {
+        let dir = tempdir().unwrap();
+        let (kept, gone, fresh) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let old = dir.path().join("old.db");
+        let new = dir.path().join("new.db");
+        snapshot(&old, &[(kept, "Выручка", r#"{"color_scheme": "a", "row_limit": 100}"#), (gone, "Старый", "{}")]);
+        snapshot(&new, &[(kept, "Выручка", r#"{"row_limit":100,"color_scheme":"b"}"#), (fresh, "Новый", "{}")]);
+
+        let diff = diff(&old, &new).unwrap();
+        let charts = diff.iter().find(|d| d.kind == "chart").unwrap();
+        assert_eq!(charts.added[0].title, "Новый");
+        assert_eq!(charts.removed[0].uuid, gone.to_string());
+        assert_eq!(charts.changed.len(), 1);
+        assert_eq!(charts.changed[0].fields, vec!["params.color_scheme"]);
+        assert!(diff.iter().filter(|d| d.kind != "chart").all(KindDiff::is_empty));
+    }
+}
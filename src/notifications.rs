@@ -0,0 +1,401 @@
+//! Notifications for service state changes, failures and data refreshes
+//!
+//! On Windows a balloon tip is shown through PowerShell's `NotifyIcon`
+//! (part of every Windows 10/11 install, so nothing extra ships with the kit).
+//! Elsewhere `notify-send` is used when present. Every notification is also
+//! logged, so a headless run loses nothing.
+//!
+//! Sinks from `notification_sinks` in config.json forward the same
+//! notifications off the machine: mail through a closed-network SMTP relay,
+//! a POST to an internal webhook, or a `.txt` file in a drop folder. They
+//! are delivered on a background thread, so a dead relay never stalls the
+//! watchdog, the schedulers or the alert engine that raised them.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Sinks set by [`configure_sinks`], with the kit root for relative folders
+static SINKS: Mutex<Option<Arc<(PathBuf, Vec<SinkConfig>)>>> = Mutex::new(None);
+
+/// Keeps drop-folder file names unique within a second
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Network timeout of SMTP and webhook delivery
+const SINK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How a notification is presented
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    #[default]
+    Info,
+    Warning,
+    Error,
+}
+
+/// Where a sink delivers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SinkKind {
+    /// Mail through a relay that accepts unauthenticated mail from the network
+    Smtp {
+        host: String,
+        #[serde(default = "default_smtp_port")]
+        port: u16,
+        from: String,
+        to: Vec<String>,
+    },
+    /// JSON POST: `{"level", "title", "message", "host", "time"}`
+    Webhook { url: String },
+    /// One `.txt` file per notification (relative to the kit root)
+    Folder { path: PathBuf },
+}
+
+fn default_smtp_port() -> u16 {
+    25
+}
+
+/// One entry of `notification_sinks` in config.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SinkConfig {
+    #[serde(flatten)]
+    pub kind: SinkKind,
+    /// Quieter notifications are not sent here
+    #[serde(default)]
+    pub min_level: Level,
+}
+
+impl SinkConfig {
+    /// Short description for logs
+    pub fn describe(&self) -> String {
+        match &self.kind {
+            SinkKind::Smtp { host, port, to, .. } => format!("smtp {}:{} -> {}", host, port, to.join(", ")),
+            SinkKind::Webhook { url } => format!("webhook {}", url),
+            SinkKind::Folder { path } => format!("folder {}", path.display()),
+        }
+    }
+}
+
+impl Level {
+    #[cfg(windows)]
+    fn balloon_icon(self) -> &'static str {
+        match self {
+            Level::Info => "Info",
+            Level::Warning => "Warning",
+            Level::Error => "Error",
+        }
+    }
+}
+
+/// Turn desktop notifications on or off (`notifications` in config.json)
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+/// Forward notifications to `sinks` (`notification_sinks` in config.json)
+pub fn configure_sinks(root: &Path, sinks: &[SinkConfig]) {
+    let configured = (!sinks.is_empty()).then(|| Arc::new((root.to_path_buf(), sinks.to_vec())));
+    *SINKS.lock().unwrap_or_else(|e| e.into_inner()) = configured;
+}
+
+/// Log and, when enabled, show a desktop notification. Never blocks.
+pub fn notify(level: Level, title: &str, message: &str) {
+    match level {
+        Level::Info => info!("🔔 {}: {}", title, message),
+        Level::Warning => warn!("🔔 {}: {}", title, message),
+        Level::Error => error!("🔔 {}: {}", title, message),
+    }
+    forward(level, title, message);
+    if !ENABLED.load(Ordering::SeqCst) {
+        return;
+    }
+    if let Err(e) = show(level, title, message) {
+        debug!("Desktop notification not shown: {}", e);
+    }
+}
+
+/// Hand the notification to the configured sinks on a background thread
+fn forward(level: Level, title: &str, message: &str) {
+    let Some(sinks) = SINKS.lock().unwrap_or_else(|e| e.into_inner()).clone() else {
+        return;
+    };
+    if !sinks.1.iter().any(|sink| level >= sink.min_level) {
+        return;
+    }
+    let (title, message) = (title.to_string(), message.to_string());
+    std::thread::spawn(move || {
+        let (root, sinks) = &*sinks;
+        for sink in sinks.iter().filter(|sink| level >= sink.min_level) {
+            if let Err(e) = deliver(root, sink, level, &title, &message) {
+                warn!("Notification not sent to {}: {:#}", sink.describe(), e);
+            }
+        }
+    });
+}
+
+/// Send one notification to one sink, waiting for the result
+pub fn deliver(root: &Path, sink: &SinkConfig, level: Level, title: &str, message: &str) -> Result<()> {
+    let time = chrono::Local::now();
+    let host = hostname();
+    match &sink.kind {
+        SinkKind::Smtp { host: relay, port, from, to } => {
+            let subject = mail_subject(title);
+            let body = format!("{}\n\nКомпьютер: {}\nВремя: {}\n", message, host, time.format("%Y-%m-%d %H:%M:%S"));
+            send_mail(relay, *port, from, to, &subject, &body)
+        }
+        SinkKind::Webhook { url } => {
+            let payload = serde_json::json!({
+                "level": level,
+                "title": title,
+                "message": message,
+                "host": host,
+                "time": time.to_rfc3339(),
+            });
+            let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+            runtime.block_on(async {
+                reqwest::Client::new()
+                    .post(url)
+                    .timeout(SINK_TIMEOUT)
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(payload.to_string())
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                Ok::<(), anyhow::Error>(())
+            })
+        }
+        SinkKind::Folder { path } => {
+            let dir = root.join(path);
+            std::fs::create_dir_all(&dir).with_context(|| format!("Cannot create {}", dir.display()))?;
+            let name = format!(
+                "{}-{:?}-{}.txt",
+                time.format("%Y%m%d-%H%M%S"),
+                level,
+                SEQUENCE.fetch_add(1, Ordering::Relaxed)
+            )
+            .to_lowercase();
+            let text = format!("{}\r\n\r\n{}\r\n\r\n{} {}\r\n", title, message, host, time.format("%Y-%m-%d %H:%M:%S"));
+            std::fs::write(dir.join(name), text)?;
+            Ok(())
+        }
+    }
+}
+
+/// Subject of a notification mail; line breaks would start new headers
+fn mail_subject(title: &str) -> String {
+    let title: String = title.chars().map(|c| if matches!(c, '\r' | '\n') { ' ' } else { c }).collect();
+    format!("[Superset Portable] {}", title)
+}
+
+pub fn hostname() -> String {
+    std::env::var("COMPUTERNAME").or_else(|_| std::env::var("HOSTNAME")).unwrap_or_else(|_| "localhost".to_string())
+}
+
+/// Plain SMTP: no auth, no TLS (closed-network relays), UTF-8 body
+fn send_mail(relay: &str, port: u16, from: &str, to: &[String], subject: &str, body: &str) -> Result<()> {
+    let addr = (relay, port).to_socket_addrs()?.next().with_context(|| format!("Cannot resolve {}", relay))?;
+    let stream = TcpStream::connect_timeout(&addr, SINK_TIMEOUT)?;
+    stream.set_read_timeout(Some(SINK_TIMEOUT))?;
+    stream.set_write_timeout(Some(SINK_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    expect_reply(&mut reader, 220)?;
+    smtp_command(&mut writer, &mut reader, "HELO superset-portable", 250)?;
+    smtp_command(&mut writer, &mut reader, &format!("MAIL FROM:<{}>", from), 250)?;
+    for recipient in to {
+        smtp_command(&mut writer, &mut reader, &format!("RCPT TO:<{}>", recipient), 250)?;
+    }
+    smtp_command(&mut writer, &mut reader, "DATA", 354)?;
+    let mut data = format!(
+        "From: <{}>\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\n\
+         Content-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n",
+        from,
+        to.iter().map(|r| format!("<{}>", r)).collect::<Vec<_>>().join(", "),
+        encode_header(subject),
+        chrono::Local::now().to_rfc2822()
+    );
+    for line in body.lines() {
+        // Dot-stuffing: a lone "." would end the message
+        if line.starts_with('.') {
+            data.push('.');
+        }
+        data.push_str(line);
+        data.push_str("\r\n");
+    }
+    data.push('.');
+    smtp_command(&mut writer, &mut reader, &data, 250)?;
+    let _ = smtp_command(&mut writer, &mut reader, "QUIT", 221);
+    Ok(())
+}
+
+fn smtp_command(writer: &mut TcpStream, reader: &mut impl BufRead, command: &str, code: u16) -> Result<()> {
+    writer.write_all(command.as_bytes())?;
+    writer.write_all(b"\r\n")?;
+    expect_reply(reader, code)
+}
+
+/// Read a (possibly multi-line) reply and check its code
+fn expect_reply(reader: &mut impl BufRead, code: u16) -> Result<()> {
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            bail!("SMTP server closed the connection");
+        }
+        // "250-..." continues, "250 ..." ends the reply
+        if line.as_bytes().get(3) == Some(&b'-') {
+            continue;
+        }
+        if !line.starts_with(&code.to_string()) {
+            bail!("SMTP server answered: {}", line.trim());
+        }
+        return Ok(());
+    }
+}
+
+/// RFC 2047 Q-encoding for a header holding non-ASCII text
+fn encode_header(text: &str) -> String {
+    if text.is_ascii() {
+        return text.to_string();
+    }
+    let mut encoded = String::from("=?UTF-8?Q?");
+    for byte in text.bytes() {
+        match byte {
+            b' ' => encoded.push('_'),
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'-' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("={:02X}", byte)),
+        }
+    }
+    encoded.push_str("?=");
+    encoded
+}
+
+#[cfg(windows)]
+fn show(level: Level, title: &str, message: &str) -> std::io::Result<()> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let script = format!(
+        "Add-Type -AssemblyName System.Windows.Forms; \
+         $n = New-Object System.Windows.Forms.NotifyIcon; \
+         $n.Icon = [System.Drawing.SystemIcons]::Information; \
+         $n.Visible = $true; \
+         $n.ShowBalloonTip(8000, '{}', '{}', '{}'); \
+         Start-Sleep -Seconds 9; $n.Dispose()",
+        ps_quote(title),
+        ps_quote(message),
+        level.balloon_icon()
+    );
+    std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .creation_flags(CREATE_NO_WINDOW)
+        .spawn()?;
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn show(level: Level, title: &str, message: &str) -> std::io::Result<()> {
+    let urgency = match level {
+        Level::Error => "critical",
+        _ => "normal",
+    };
+    std::process::Command::new("notify-send")
+        .args(["-u", urgency, title, message])
+        .spawn()?;
+    Ok(())
+}
+
+/// Escape for a single-quoted PowerShell string
+#[cfg_attr(not(windows), allow(dead_code))]
+fn ps_quote(text: &str) -> String {
+    text.replace('\'', "''").replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ps_quote() {
+        assert_eq!(ps_quote("it's\r\ndone"), "it''s  done");
+    }
+
+    #[test]
+    fn test_sink_config() {
+        let sinks: Vec<SinkConfig> = serde_json::from_str(
+            r#"[{"type": "smtp", "host": "relay.local", "from": "bi@corp", "to": ["ops@corp"], "min_level": "error"},
+                {"type": "folder", "path": "notifications"}]"#,
+        )
+        .unwrap();
+        assert!(matches!(&sinks[0].kind, SinkKind::Smtp { port: 25, .. }));
+        assert_eq!((sinks[0].min_level, sinks[1].min_level), (Level::Error, Level::Info));
+        assert!(Level::Warning < sinks[0].min_level);
+        assert_eq!(encode_header("Сбой A"), "=?UTF-8?Q?=D0=A1=D0=B1=D0=BE=D0=B9_A?=");
+    }
+
+    #[test]
+    fn test_folder_and_smtp_delivery() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = SinkConfig { kind: SinkKind::Folder { path: "drop".into() }, min_level: Level::Info };
+        deliver(dir.path(), &folder, Level::Error, "Ошибка загрузки", "нет файла").unwrap();
+        let files: Vec<_> = std::fs::read_dir(dir.path().join("drop")).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        let text = std::fs::read_to_string(files[0].as_ref().unwrap().path()).unwrap();
+        assert!(text.starts_with("Ошибка загрузки\r\n\r\nнет файла"));
+
+        // Minimal relay: accepts everything and keeps the session
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let mut session = String::new();
+            writer.write_all(b"220 relay\r\n").unwrap();
+            let mut in_data = false;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 {
+                    break;
+                }
+                session.push_str(&line);
+                let reply: &[u8] = if in_data {
+                    if line != ".\r\n" {
+                        continue;
+                    }
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else if line.starts_with("DATA") {
+                    in_data = true;
+                    b"354 go\r\n"
+                } else if line.starts_with("QUIT") {
+                    writer.write_all(b"221 bye\r\n").unwrap();
+                    break;
+                } else {
+                    b"250-ok\r\n250 ok\r\n"
+                };
+                writer.write_all(reply).unwrap();
+            }
+            session
+        });
+        let smtp = SinkConfig {
+            kind: SinkKind::Smtp { host: "127.0.0.1".into(), port, from: "bi@corp".into(), to: vec!["ops@corp".into()] },
+            min_level: Level::Warning,
+        };
+        deliver(dir.path(), &smtp, Level::Error, "Сбой", ".hidden line").unwrap();
+        let session = server.join().unwrap();
+        assert!(session.contains("RCPT TO:<ops@corp>\r\n"));
+        assert!(session.contains("Subject: =?UTF-8?Q?"));
+        assert!(session.contains("\r\n..hidden line\r\n"), "dot-stuffed");
+        assert_eq!(mail_subject("Disk\r\nBcc: all@corp"), "[Superset Portable] Disk  Bcc: all@corp");
+    }
+}
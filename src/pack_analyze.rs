@@ -0,0 +1,375 @@
+//! Release size analyzer (`pack analyze`)
+//!
+//! Scans the bundled Python tree for weight that a closed-network kit never
+//! uses — package test suites, foreign locales, duplicate `.dist-info`
+//! folders, DB drivers for engines no connection points at — and reports
+//! the savings per package. Accepted candidates go into `pack-prune.txt`,
+//! which `pack` applies while staging the release.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OpenFlags};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::python::PythonEnv;
+
+/// Prune list (relative to root), one path prefix per line, `#` comments
+pub const PRUNE_FILE: &str = "pack-prune.txt";
+
+/// Locales worth shipping (UI is Russian, fallback English)
+const KEEP_LOCALES: &[&str] = &["ru", "en", "root"];
+
+/// SQLAlchemy URI scheme -> driver packages it needs
+const DB_DRIVERS: &[(&str, &[&str])] = &[
+    ("postgresql", &["psycopg2", "psycopg2_binary.libs", "psycopg"]),
+    ("mysql", &["MySQLdb", "pymysql"]),
+    ("mssql", &["pymssql", "pyodbc"]),
+    ("oracle", &["cx_Oracle", "oracledb"]),
+    ("clickhouse", &["clickhouse_connect", "clickhouse_driver"]),
+    ("trino", &["trino"]),
+    ("presto", &["pyhive"]),
+    ("hive", &["pyhive"]),
+    ("snowflake", &["snowflake"]),
+    ("bigquery", &["google_cloud_bigquery", "pybigquery"]),
+    ("databricks", &["databricks"]),
+    ("duckdb", &["duckdb", "duckdb_engine"]),
+];
+
+/// Why a path can be dropped from the release
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TrimCategory {
+    Tests,
+    Locales,
+    DuplicateDistInfo,
+    UnusedDriver,
+}
+
+impl std::fmt::Display for TrimCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TrimCategory::Tests => "tests",
+            TrimCategory::Locales => "locales",
+            TrimCategory::DuplicateDistInfo => "dist-info duplicates",
+            TrimCategory::UnusedDriver => "unused DB driver",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A file or folder that can be pruned
+#[derive(Debug, Clone)]
+pub struct TrimCandidate {
+    /// Path relative to root, `/`-separated
+    pub path: String,
+    /// Top-level package it belongs to
+    pub package: String,
+    pub category: TrimCategory,
+    pub size_bytes: u64,
+}
+
+/// Analyzer result
+#[derive(Debug, Default)]
+pub struct TrimReport {
+    pub candidates: Vec<TrimCandidate>,
+    pub python_size_bytes: u64,
+}
+
+impl TrimReport {
+    pub fn total_savings(&self) -> u64 {
+        self.candidates.iter().map(|c| c.size_bytes).sum()
+    }
+
+    /// (package, bytes, categories) sorted by savings, largest first
+    pub fn by_package(&self) -> Vec<(String, u64, Vec<TrimCategory>)> {
+        let mut packages: BTreeMap<&str, (u64, Vec<TrimCategory>)> = BTreeMap::new();
+        for c in &self.candidates {
+            let entry = packages.entry(&c.package).or_default();
+            entry.0 += c.size_bytes;
+            if !entry.1.contains(&c.category) {
+                entry.1.push(c.category);
+            }
+        }
+        let mut rows: Vec<_> = packages
+            .into_iter()
+            .map(|(name, (size, cats))| (name.to_string(), size, cats))
+            .collect();
+        rows.sort_by(|a, b| b.1.cmp(&a.1));
+        rows
+    }
+}
+
+/// Scan the Python tree under `root`
+pub fn analyze(root: &Path) -> Result<TrimReport> {
+    let site = PythonEnv::new(root)?.site_packages().to_path_buf();
+    let mut report = TrimReport::default();
+    if !site.exists() {
+        return Ok(report);
+    }
+    report.python_size_bytes = dir_size(&root.join("python"));
+
+    let used_schemes = used_db_schemes(root)?;
+    let mut dist_infos: HashMap<String, Vec<(String, PathBuf)>> = HashMap::new();
+
+    for entry in fs::read_dir(&site)?.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if let Some(stem) = name.strip_suffix(".dist-info") {
+            if let Some((pkg, version)) = stem.split_once('-') {
+                dist_infos
+                    .entry(pkg.to_lowercase().replace('-', "_"))
+                    .or_default()
+                    .push((version.to_string(), path.clone()));
+            }
+            continue;
+        }
+
+        if is_unused_driver(&name, &used_schemes) {
+            report.candidates.push(candidate(root, &path, &name, TrimCategory::UnusedDriver));
+            continue;
+        }
+
+        scan_package(root, &path, &name, &mut report.candidates);
+    }
+
+    // Keep the newest dist-info of each package, flag the rest
+    for (pkg, mut versions) in dist_infos {
+        if versions.len() < 2 {
+            continue;
+        }
+        versions.sort_by(|a, b| compare_versions(&a.0, &b.0));
+        versions.pop();
+        for (_, path) in versions {
+            report.candidates.push(candidate(root, &path, &pkg, TrimCategory::DuplicateDistInfo));
+        }
+    }
+
+    report.candidates.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(report)
+}
+
+/// Tests and foreign locales inside one package
+fn scan_package(root: &Path, package_dir: &Path, package: &str, out: &mut Vec<TrimCandidate>) {
+    let mut walker = WalkDir::new(package_dir).min_depth(1).into_iter();
+    while let Some(entry) = walker.next() {
+        let Ok(entry) = entry else { continue };
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_lowercase();
+        let parent = entry
+            .path()
+            .parent()
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        let category = if name == "tests" || name == "test" {
+            Some(TrimCategory::Tests)
+        } else if (parent == "translations" || parent == "locale" || parent == "locales") && !keep_locale(&name) {
+            Some(TrimCategory::Locales)
+        } else {
+            None
+        };
+
+        if let Some(category) = category {
+            out.push(candidate(root, entry.path(), package, category));
+            walker.skip_current_dir();
+        } else if name == "locale-data" {
+            // babel: one .dat file per locale
+            for file in fs::read_dir(entry.path()).into_iter().flatten().filter_map(|e| e.ok()) {
+                let file_name = file.file_name().to_string_lossy().to_lowercase();
+                let locale = file_name.trim_end_matches(".dat");
+                if file_name.ends_with(".dat") && !keep_locale(locale) {
+                    out.push(candidate(root, &file.path(), package, TrimCategory::Locales));
+                }
+            }
+            walker.skip_current_dir();
+        }
+    }
+}
+
+fn keep_locale(name: &str) -> bool {
+    let lang = name.split(['_', '-', '.']).next().unwrap_or(name);
+    KEEP_LOCALES.contains(&lang)
+}
+
+/// A known DB driver package that no registered connection needs
+fn is_unused_driver(package: &str, used: &HashSet<String>) -> bool {
+    let mut schemes = DB_DRIVERS
+        .iter()
+        .filter(|(_, packages)| packages.iter().any(|p| p.eq_ignore_ascii_case(package)))
+        .map(|(scheme, _)| *scheme)
+        .peekable();
+    schemes.peek().is_some() && schemes.all(|scheme| !used.contains(scheme))
+}
+
+/// URI schemes of connections registered in superset.db (`postgresql+psycopg2` -> `postgresql`).
+/// An unreadable superset.db is an error: an empty set would mark every driver unused
+fn used_db_schemes(root: &Path) -> Result<HashSet<String>> {
+    let db_path = root.join("superset_home").join("superset.db");
+    let conn = Connection::open_with_flags(&db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Cannot open {} to see which DB drivers are used", db_path.display()))?;
+    let mut stmt = conn
+        .prepare("SELECT sqlalchemy_uri FROM dbs")
+        .with_context(|| format!("Cannot read connections from {}", db_path.display()))?;
+    let uris = stmt
+        .query_map([], |r| r.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(uris
+        .iter()
+        .filter_map(|uri| uri.split("://").next())
+        .map(|scheme| scheme.split('+').next().unwrap_or(scheme).to_lowercase())
+        .collect())
+}
+
+/// Numeric-aware version comparison (`1.10.0` > `1.9.2`)
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let parts = |v: &str| -> Vec<u64> { v.split('.').map(|p| p.parse().unwrap_or(0)).collect() };
+    parts(a).cmp(&parts(b))
+}
+
+fn candidate(root: &Path, path: &Path, package: &str, category: TrimCategory) -> TrimCandidate {
+    TrimCandidate {
+        path: relative(root, path),
+        package: package.to_string(),
+        category,
+        size_bytes: if path.is_dir() { dir_size(path) } else { path.metadata().map_or(0, |m| m.len()) },
+    }
+}
+
+fn relative(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root).unwrap_or(path).to_string_lossy().replace('\\', "/")
+}
+
+fn dir_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Write the candidates as a prune list (existing entries are kept)
+pub fn write_prune_list(root: &Path, report: &TrimReport) -> Result<usize> {
+    let mut list = PruneList::load(root)?;
+    let before = list.prefixes.len();
+    for c in &report.candidates {
+        if !list.prefixes.contains(&c.path) {
+            list.prefixes.push(c.path.clone());
+        }
+    }
+
+    let mut content = String::from("# Paths excluded from the release by `pack` (one prefix per line)\n");
+    for prefix in &list.prefixes {
+        content.push_str(prefix);
+        content.push('\n');
+    }
+    fs::write(root.join(PRUNE_FILE), content)?;
+    Ok(list.prefixes.len() - before)
+}
+
+/// Paths excluded while staging a release
+#[derive(Debug, Default)]
+pub struct PruneList {
+    prefixes: Vec<String>,
+}
+
+impl PruneList {
+    /// Load `pack-prune.txt` (empty list if missing)
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = root.join(PRUNE_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let prefixes = fs::read_to_string(path)?
+            .lines()
+            .map(|l| l.trim().trim_end_matches('/').replace('\\', "/"))
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .collect();
+        Ok(Self { prefixes })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prefixes.is_empty()
+    }
+
+    /// Whether `rel_path` (relative to root) is excluded
+    pub fn is_pruned(&self, rel_path: &str) -> bool {
+        let rel_path = rel_path.replace('\\', "/");
+        self.prefixes.iter().any(|p| {
+            rel_path == *p || (rel_path.starts_with(p.as_str()) && rel_path[p.len()..].starts_with('/'))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn touch(path: &Path, size: usize) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![0u8; size]).unwrap();
+    }
+
+    #[test]
+    fn test_analyze_finds_candidates() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let site = PythonEnv::new(root).unwrap().site_packages().to_path_buf();
+        touch(&site.join("pandas/core/frame.py"), 10);
+        touch(&site.join("pandas/tests/test_frame.py"), 100);
+        touch(&site.join("superset/translations/de/LC_MESSAGES/messages.mo"), 50);
+        touch(&site.join("superset/translations/ru/LC_MESSAGES/messages.mo"), 50);
+        touch(&site.join("babel/locale-data/de_DE.dat"), 30);
+        touch(&site.join("babel/locale-data/ru_RU.dat"), 30);
+        touch(&site.join("psycopg2/_psycopg.pyd"), 500);
+        touch(&site.join("numpy-1.9.0.dist-info/METADATA"), 5);
+        touch(&site.join("numpy-1.10.0.dist-info/METADATA"), 5);
+
+        // Without superset.db nothing tells which drivers are in use
+        assert!(analyze(root).is_err());
+        fs::create_dir_all(root.join("superset_home")).unwrap();
+        let conn = Connection::open(root.join("superset_home/superset.db")).unwrap();
+        conn.execute_batch("CREATE TABLE dbs (sqlalchemy_uri TEXT); INSERT INTO dbs VALUES ('sqlite:///examples.db');")
+            .unwrap();
+        drop(conn);
+
+        let report = analyze(root).unwrap();
+        let paths: Vec<&str> = report.candidates.iter().map(|c| c.path.as_str()).collect();
+        // `python/Lib/site-packages` on Windows
+        let prefix = site.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/");
+        let expected: Vec<String> = [
+            "babel/locale-data/de_DE.dat",
+            "numpy-1.9.0.dist-info",
+            "pandas/tests",
+            "psycopg2",
+            "superset/translations/de",
+        ]
+        .iter()
+        .map(|path| format!("{}/{}", prefix, path))
+        .collect();
+        assert_eq!(paths, expected);
+        assert_eq!(report.candidates.len(), 5);
+        assert_eq!(report.total_savings(), 30 + 5 + 100 + 500 + 50);
+        assert_eq!(report.by_package()[0].0, "psycopg2");
+    }
+
+    #[test]
+    fn test_prune_list() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join(PRUNE_FILE), "# comment\npython/Lib/site-packages/pandas/tests/\n").unwrap();
+        let list = PruneList::load(dir.path()).unwrap();
+        assert!(list.is_pruned("python/Lib/site-packages/pandas/tests"));
+        assert!(list.is_pruned("python\\Lib\\site-packages\\pandas\\tests\\test_frame.py"));
+        assert!(!list.is_pruned("python/Lib/site-packages/pandas/tests_util.py"));
+    }
+}
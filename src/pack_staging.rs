@@ -0,0 +1,241 @@
+//! Staging folder of `pack` without a second copy of the kit
+//!
+//! `pack` assembles the release in `release/<name>/` before archiving it.
+//! Files are hard-linked there from the kit rather than copied. When a file
+//! can't be linked (a knowledge folder on another drive), identical files —
+//! Python ships many — are copied once and linked to each other by content
+//! hash; only a file system without hard links (FAT32/exFAT sticks) gets a
+//! plain copy of everything. What was staged is recorded next to the folder
+//! in `<name>.staging.json`, so the next `pack` restages only files whose
+//! source changed and removes the ones no longer shipped.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tracing::{info, warn};
+
+/// Suffix of the manifest, next to the staging folder
+pub const MANIFEST_SUFFIX: &str = ".staging.json";
+
+/// What goes into the staging folder
+#[derive(Debug, Default)]
+pub struct StagingPlan {
+    /// Relative, so empty folders are shipped too
+    dirs: Vec<PathBuf>,
+    /// Staged path (`/`-separated, relative) to its source
+    files: BTreeMap<String, PathBuf>,
+}
+
+impl StagingPlan {
+    pub fn dir(&mut self, dst: &str) {
+        self.dirs.push(PathBuf::from(dst));
+    }
+
+    pub fn file(&mut self, src: &Path, dst: &str) {
+        self.files.insert(dst.to_string(), src.to_path_buf());
+    }
+
+    /// Staged paths, sorted
+    #[cfg(test)]
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.files.keys().map(String::as_str)
+    }
+}
+
+/// Source of a staged file when it was staged
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Source {
+    size: u64,
+    /// Nanoseconds since the epoch
+    modified: Option<u64>,
+}
+
+impl Source {
+    fn of(path: &Path) -> Result<Self> {
+        let metadata = fs::metadata(path).with_context(|| format!("Cannot read {}", path.display()))?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos() as u64);
+        Ok(Self { size: metadata.len(), modified })
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    files: BTreeMap<String, Source>,
+}
+
+/// What [`sync`] did
+#[derive(Debug, Default, PartialEq)]
+pub struct StagingStats {
+    /// Already staged from the same source
+    pub unchanged: usize,
+    /// Hard-linked to the source
+    pub linked: usize,
+    /// Hard-linked to an identical file staged before
+    pub deduplicated: usize,
+    pub copied: usize,
+    /// Staged before, no longer shipped
+    pub removed: usize,
+}
+
+impl StagingStats {
+    pub fn summary(&self) -> String {
+        format!(
+            "{} unchanged, {} linked, {} deduplicated, {} copied, {} removed",
+            self.unchanged, self.linked, self.deduplicated, self.copied, self.removed
+        )
+    }
+}
+
+/// Bring `staging` in line with `plan`, recording it in `manifest`
+pub fn sync(plan: &StagingPlan, staging: &Path, manifest_path: &Path) -> Result<StagingStats> {
+    let previous: Option<Manifest> =
+        fs::read(manifest_path).ok().and_then(|bytes| serde_json::from_slice(&bytes).ok());
+    // Left by an older launcher or an interrupted run: nothing in it can be trusted
+    if previous.is_none() && staging.exists() {
+        info!("  Staging folder has no manifest, staging from scratch");
+        fs::remove_dir_all(staging).with_context(|| format!("Cannot clear {}", staging.display()))?;
+    }
+    let previous = previous.unwrap_or_default();
+    // Written again at the end; a run that fails halfway starts over
+    let _ = fs::remove_file(manifest_path);
+
+    let mut stats = StagingStats::default();
+    for path in previous.files.keys().filter(|path| !plan.files.contains_key(*path)) {
+        let target = staging.join(path);
+        if fs::remove_file(&target).is_ok() {
+            stats.removed += 1;
+        }
+        // Folders it leaves empty would be packed as empty folders
+        // (planned ones are created again below)
+        for dir in target.ancestors().skip(1).take_while(|dir| *dir != staging) {
+            if fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+    }
+    fs::create_dir_all(staging)?;
+    for dir in &plan.dirs {
+        fs::create_dir_all(staging.join(dir))?;
+    }
+
+    let mut manifest = Manifest::default();
+    let mut by_hash: HashMap<Vec<u8>, PathBuf> = HashMap::new();
+    // Cleared once linking inside the staging folder fails: no hard links here
+    let mut links = true;
+    for (dst, src) in &plan.files {
+        let source = Source::of(src)?;
+        let target = staging.join(dst);
+        let staged = fs::metadata(&target).ok();
+        if previous.files.get(dst) == Some(&source) && staged.is_some_and(|m| m.len() == source.size) {
+            stats.unchanged += 1;
+            manifest.files.insert(dst.clone(), source);
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Copying onto a staged hard link would write into the kit's own file
+        if staged.is_some() {
+            fs::remove_file(&target).with_context(|| format!("Cannot replace {}", target.display()))?;
+        }
+        if links && fs::hard_link(src, &target).is_ok() {
+            stats.linked += 1;
+        } else if links {
+            let hash = hash_file(src)?;
+            match by_hash.get(&hash) {
+                Some(first) if fs::hard_link(first, &target).is_ok() => stats.deduplicated += 1,
+                Some(_) => {
+                    warn!("  Hard links are not supported here; copying every file");
+                    links = false;
+                    copy(src, &target)?;
+                    stats.copied += 1;
+                }
+                None => {
+                    copy(src, &target)?;
+                    stats.copied += 1;
+                    by_hash.insert(hash, target.clone());
+                }
+            }
+        } else {
+            copy(src, &target)?;
+            stats.copied += 1;
+        }
+        manifest.files.insert(dst.clone(), source);
+    }
+
+    fs::write(manifest_path, serde_json::to_vec_pretty(&manifest)?)
+        .with_context(|| format!("Cannot write {}", manifest_path.display()))?;
+    Ok(stats)
+}
+
+fn copy(src: &Path, dst: &Path) -> Result<()> {
+    fs::copy(src, dst).with_context(|| format!("Cannot copy {}", src.display()))?;
+    Ok(())
+}
+
+fn hash_file(path: &Path) -> Result<Vec<u8>> {
+    let mut file = File::open(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    let mut hash = ring::digest::Context::new(&ring::digest::SHA256);
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hash.update(&buf[..n]);
+    }
+    Ok(hash.finish().as_ref().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_sync_links_and_skips_unchanged() {
+        let dir = tempdir().unwrap();
+        let kit = dir.path().join("kit");
+        fs::create_dir_all(kit.join("python/lib")).unwrap();
+        fs::write(kit.join("python/python.exe"), b"exe").unwrap();
+        fs::write(kit.join("python/lib/__init__.py"), b"").unwrap();
+        fs::write(kit.join("README.txt"), b"readme").unwrap();
+        let staging = dir.path().join("release/kit");
+        let manifest = dir.path().join("release/kit.staging.json");
+
+        let mut plan = StagingPlan::default();
+        plan.dir("python/empty");
+        plan.file(&kit.join("python/python.exe"), "python/python.exe");
+        plan.file(&kit.join("python/lib/__init__.py"), "python/lib/__init__.py");
+        plan.file(&kit.join("README.txt"), "README.txt");
+        let first = sync(&plan, &staging, &manifest).unwrap();
+        assert_eq!(first.linked + first.deduplicated + first.copied, 3);
+        assert_eq!(fs::read(staging.join("python/python.exe")).unwrap(), b"exe");
+        assert!(staging.join("python/empty").is_dir());
+
+        let again = sync(&plan, &staging, &manifest).unwrap();
+        assert_eq!(again, StagingStats { unchanged: 3, ..Default::default() });
+
+        // A replaced source is restaged without touching the old file
+        fs::remove_file(kit.join("README.txt")).unwrap();
+        fs::write(kit.join("README.txt"), b"new readme").unwrap();
+        let mut plan = StagingPlan::default();
+        plan.file(&kit.join("python/python.exe"), "python/python.exe");
+        plan.file(&kit.join("README.txt"), "README.txt");
+        let changed = sync(&plan, &staging, &manifest).unwrap();
+        assert_eq!((changed.unchanged, changed.removed), (1, 1));
+        assert_eq!(fs::read(staging.join("README.txt")).unwrap(), b"new readme");
+        assert!(!staging.join("python/lib/__init__.py").exists());
+        assert!(!staging.join("python/lib").exists(), "emptied folder pruned");
+        assert!(staging.join("python").is_dir());
+        assert_eq!(fs::read(kit.join("python/python.exe")).unwrap(), b"exe");
+    }
+}
@@ -2,8 +2,16 @@
 //! 
 //! Replaces PowerShell Compress-Archive with native Rust implementation
 //! for 5-10x faster release packaging.
+//!
+//! The knowledge base ships as configured in `pack.knowledge`: the markdown
+//! sources (`knowledge/`), the built site (`_site/`, rebuilt first so it
+//! matches the sources), or both, optionally without draft pages.
+//!
+//! Release files are staged by hard links where possible (see `pack_staging`),
+//! and a second `pack` restages only what changed.
 
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 use std::fs::{self, File};
 use std::io::{self, BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
@@ -12,11 +20,43 @@ use walkdir::WalkDir;
 use zip::{write::FileOptions, CompressionMethod, ZipWriter};
 use tracing::{info, warn};
 
+use crate::lightdocs::{Document, DocumentStatus, LightDocs, LightDocsConfig};
+use crate::pack_analyze::PruneList;
+use crate::pack_staging::{self, StagingPlan};
+
+/// What goes into a release (`pack` section of config.json)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PackConfig {
+    pub knowledge: KnowledgePackConfig,
+}
+
+/// How the knowledge base is shipped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KnowledgePackConfig {
+    /// Markdown sources, so the knowledge base can be edited on the target
+    pub sources: bool,
+    /// Pre-built HTML site, so pages open before the first build
+    pub site: bool,
+    /// Rebuild `_site` before packing
+    pub build: bool,
+    /// Leave out pages with `status: draft` (and pages without frontmatter)
+    pub strip_drafts: bool,
+}
+
+impl Default for KnowledgePackConfig {
+    fn default() -> Self {
+        Self { sources: true, site: true, build: true, strip_drafts: false }
+    }
+}
+
 /// Release packer configuration
 pub struct ReleasePacker {
     root: PathBuf,
     output_dir: PathBuf,
     release_name: String,
+    knowledge: KnowledgePackConfig,
 }
 
 /// Packing statistics
@@ -50,9 +90,16 @@ impl ReleasePacker {
             root: root.to_path_buf(),
             output_dir: root.join("release"),
             release_name,
+            knowledge: KnowledgePackConfig::default(),
         }
     }
     
+    /// Set how the knowledge base is shipped
+    pub fn with_knowledge(mut self, knowledge: KnowledgePackConfig) -> Self {
+        self.knowledge = knowledge;
+        self
+    }
+    
     /// Pack the release using standard ZIP with deflate
     pub fn pack_zip(&self) -> Result<PackStats> {
         let start = Instant::now();
@@ -83,6 +130,7 @@ impl ReleasePacker {
         
         info!("{}", stats.summary());
         info!("📍 Output: {}", zip_path.display());
+        crate::unpack::write_checksum(&zip_path)?;
         
         Ok(stats)
     }
@@ -117,12 +165,27 @@ impl ReleasePacker {
         
         info!("{}", stats.summary());
         info!("📍 Output: {}", archive_path.display());
+        crate::unpack::write_checksum(&archive_path)?;
         
         Ok(stats)
     }
     
     /// Prepare staging directory with release files
     fn prepare_staging(&self, staging: &Path) -> Result<()> {
+        let mut plan = StagingPlan::default();
+        // Paths accepted from `pack analyze`
+        let prune = PruneList::load(&self.root)?;
+        if !prune.is_empty() {
+            info!("  Applying prune list ({})", crate::pack_analyze::PRUNE_FILE);
+        }
+        
+        // docs/index.html links to the generated pages, which are not in git
+        let docs = self.root.join("docs");
+        if docs.is_dir() {
+            let built = crate::docs_build::build(&docs)?;
+            info!("  Docs pages: {} written, {} removed", built.written.len(), built.removed.len());
+        }
+
         // Components to include
         let components = [
             ("python", "python"),
@@ -135,30 +198,64 @@ impl ReleasePacker {
             ("LICENSE", "LICENSE"),
             ("NOTICE", "NOTICE"),
             ("QUICKSTART.md", "README.txt"),
-            ("knowledge", "knowledge"),
+            ("fonts", "fonts"),
         ];
         
         for (src, dst) in &components {
             let src_path = self.root.join(src);
-            let dst_path = staging.join(dst);
             
-            if src_path.exists() {
-                if src_path.is_dir() {
-                    if !dst_path.exists() {
-                        info!("  Copying directory: {}", src);
-                        copy_dir_all(&src_path, &dst_path)?;
-                    }
-                } else {
-                    fs::create_dir_all(dst_path.parent().unwrap())?;
-                    if !dst_path.exists() {
-                        fs::copy(&src_path, &dst_path)?;
-                    }
-                }
+            if src_path.is_dir() {
+                plan_dir(&src_path, src, &prune, false, &mut plan)?;
+            } else if src_path.exists() {
+                plan.file(&src_path, dst);
             } else {
                 warn!("  Skipping missing: {}", src);
             }
         }
         
+        self.plan_knowledge(&prune, &mut plan)?;
+        
+        let manifest = self.output_dir.join(format!("{}{}", self.release_name, pack_staging::MANIFEST_SUFFIX));
+        let stats = pack_staging::sync(&plan, staging, &manifest)?;
+        info!("  Staged: {}", stats.summary());
+        Ok(())
+    }
+    
+    /// Add the knowledge base sources and/or built site. They land under
+    /// the default names whatever lightdocs.json says, because the release
+    /// doesn't carry lightdocs.json.
+    fn plan_knowledge(&self, prune: &PruneList, plan: &mut StagingPlan) -> Result<()> {
+        let knowledge = &self.knowledge;
+        let config = LightDocsConfig::load(&self.root)?;
+        let defaults = LightDocsConfig::default();
+        
+        if knowledge.sources {
+            let src = config.docs_root_abs(&self.root);
+            if !src.is_dir() {
+                warn!("  Skipping missing: {}", src.display());
+            } else {
+                info!("  Staging knowledge base{}", if knowledge.strip_drafts { " (without drafts)" } else { "" });
+                let stripped = plan_dir(&src, &defaults.docs_root.to_string_lossy(), prune, knowledge.strip_drafts, plan)?;
+                if stripped > 0 {
+                    info!("  Left out {} draft pages", stripped);
+                }
+            }
+        }
+        
+        if knowledge.site {
+            if knowledge.build {
+                info!("  Building knowledge base site...");
+                LightDocs::new(&self.root)?.build().context("Failed to build the knowledge base site")?;
+            }
+            let src = config.output_dir_abs(&self.root);
+            if !src.is_dir() {
+                warn!("  Skipping missing: {} (run `lightdocs build`)", src.display());
+            } else {
+                info!("  Staging built site: {}", src.display());
+                plan_dir(&src, &defaults.output_dir.to_string_lossy(), prune, false, plan)?;
+            }
+        }
+        
         Ok(())
     }
     
@@ -207,7 +304,7 @@ impl ReleasePacker {
     /// Create tar.zst archive (faster than ZIP)
     fn create_tar_zstd(&self, staging: &Path, output: &Path) -> Result<(usize, u64)> {
         let file = File::create(output)?;
-        let encoder = zstd::Encoder::new(file, 3)?;  // Level 3 = fast
+        let encoder = zstd::Encoder::new(file, crate::hardware::current().zstd_level)?;
         let mut tar = tar::Builder::new(encoder);
         
         let mut files_count = 0;
@@ -240,23 +337,38 @@ impl ReleasePacker {
     }
 }
 
-/// Recursively copy a directory
-fn copy_dir_all(src: &Path, dst: &Path) -> Result<()> {
-    fs::create_dir_all(dst)?;
+/// Add a directory to the staging plan, skipping pruned paths and, with
+/// `strip_drafts`, draft pages (`rel` is its path relative to root and in the
+/// release); returns the number of pages left out
+fn plan_dir(src: &Path, rel: &str, prune: &PruneList, strip_drafts: bool, plan: &mut StagingPlan) -> Result<usize> {
+    plan.dir(rel);
     
+    let mut stripped = 0;
     for entry in fs::read_dir(src)? {
         let entry = entry?;
         let src_path = entry.path();
-        let dst_path = dst.join(entry.file_name());
+        let entry_rel = format!("{}/{}", rel, entry.file_name().to_string_lossy());
+        if prune.is_pruned(&entry_rel) {
+            continue;
+        }
         
         if src_path.is_dir() {
-            copy_dir_all(&src_path, &dst_path)?;
+            stripped += plan_dir(&src_path, &entry_rel, prune, strip_drafts, plan)?;
+        } else if strip_drafts && is_draft(&src_path) {
+            stripped += 1;
         } else {
-            fs::copy(&src_path, &dst_path)?;
+            plan.file(&src_path, &entry_rel);
         }
     }
     
-    Ok(())
+    Ok(stripped)
+}
+
+/// A markdown page that the site wouldn't publish. Pages that fail to parse
+/// are shipped, so the problem stays visible on the target.
+fn is_draft(path: &Path) -> bool {
+    path.extension().map_or(false, |ext| ext == "md")
+        && Document::load(path).map_or(false, |doc| doc.status == DocumentStatus::Draft)
 }
 
 /// Default release format
@@ -264,3 +376,32 @@ pub enum ReleaseFormat {
     Zip,
     TarZstd,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_plan_dir_strips_drafts() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("knowledge");
+        fs::create_dir_all(src.join("guides")).unwrap();
+        fs::write(src.join("index.md"), "---\ntitle: Главная\nstatus: public\n---\n").unwrap();
+        fs::write(src.join("guides/wip.md"), "---\ntitle: Черновик\nstatus: draft\n---\n").unwrap();
+        fs::write(src.join("guides/notes.md"), "Без frontmatter").unwrap();
+        fs::write(src.join("guides/scheme.png"), b"png").unwrap();
+        let prune = PruneList::load(dir.path()).unwrap();
+
+        let mut full = StagingPlan::default();
+        assert_eq!(plan_dir(&src, "knowledge", &prune, false, &mut full).unwrap(), 0);
+        assert!(full.paths().any(|p| p == "knowledge/guides/wip.md"));
+
+        let mut stripped = StagingPlan::default();
+        assert_eq!(plan_dir(&src, "knowledge", &prune, true, &mut stripped).unwrap(), 2);
+        assert_eq!(
+            stripped.paths().collect::<Vec<_>>(),
+            vec!["knowledge/guides/scheme.png", "knowledge/index.md"]
+        );
+    }
+}
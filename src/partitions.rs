@@ -0,0 +1,304 @@
+//! Year/month shards of big fact tables
+//!
+//! `load-data --partition-by <date column>` splits a loaded table into one
+//! SQLite table per year (or month), `<table>__p2024` / `<table>__p2024_03`,
+//! each indexed on the date column, and puts a `UNION ALL` view under the
+//! original name. Superset keeps using the same dataset name; SQLite pushes
+//! a date filter down into every branch of the view, and a branch outside
+//! the range costs one index lookup, so a dashboard of the last months reads
+//! a small part of a multi-year file on a slow drive. The split is recorded
+//! in `_partitions`, so a reload (from the `data` watcher or the panel too)
+//! replaces the shards and cuts the new data the same way.
+
+use anyhow::{bail, Context, Result};
+use rusqlite::{Connection, OptionalExtension};
+use std::str::FromStr;
+use tracing::info;
+
+use crate::data_loader::quote_ident;
+
+/// Shard names are `<table>` + this + the period
+const SHARD_SEPARATOR: &str = "__p";
+
+/// A reload of a partitioned table is parsed into `<table>` + this first
+const LOADING_SUFFIX: &str = "__loading";
+
+/// SQLite's default limit on the terms of a compound SELECT
+const MAX_SHARDS: usize = 500;
+
+/// Table remembering how each partitioned table was split
+const PARTITIONS_TABLE: &str = "_partitions";
+
+/// Period covered by one shard
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PartitionUnit {
+    #[default]
+    Year,
+    Month,
+}
+
+impl FromStr for PartitionUnit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "year" => Ok(PartitionUnit::Year),
+            "month" => Ok(PartitionUnit::Month),
+            other => bail!("Unknown partition unit '{}' (year or month)", other),
+        }
+    }
+}
+
+impl PartitionUnit {
+    fn as_str(self) -> &'static str {
+        match self {
+            PartitionUnit::Year => "year",
+            PartitionUnit::Month => "month",
+        }
+    }
+
+    fn strftime(self) -> &'static str {
+        match self {
+            PartitionUnit::Year => "%Y",
+            PartitionUnit::Month => "%Y_%m",
+        }
+    }
+}
+
+/// How to shard a table (`LoadOptions::partition`)
+#[derive(Debug, Clone)]
+pub struct Partitioning {
+    /// Date column the shards are cut by
+    pub column: String,
+    pub unit: PartitionUnit,
+}
+
+/// Whether `name` is a view over shards made by [`partition`]
+pub fn is_partitioned(conn: &Connection, name: &str) -> Result<bool> {
+    let kind: Option<String> = conn
+        .query_row("SELECT type FROM sqlite_master WHERE name = ?1", [name], |r| r.get(0))
+        .optional()?;
+    Ok(kind.as_deref() == Some("view") && !shards(conn, name)?.is_empty())
+}
+
+/// Shard tables of `table`
+pub fn shards(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let pattern = format!("{}%", like_escape(&format!("{}{}", table, SHARD_SEPARATOR)));
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name LIKE ?1 ESCAPE '\\' ORDER BY name",
+    )?;
+    let names = stmt.query_map([pattern], |r| r.get(0))?;
+    Ok(names.collect::<rusqlite::Result<_>>()?)
+}
+
+/// `value` as a literal `LIKE` pattern with `ESCAPE '\'`
+fn like_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// How `table` was split, if it is partitioned
+pub fn partitioning(conn: &Connection, table: &str) -> Result<Option<Partitioning>> {
+    if !is_partitioned(conn, table)? {
+        return Ok(None);
+    }
+    let recorded: Option<(String, String)> = conn
+        .query_row(
+            &format!("SELECT column_name, unit FROM {} WHERE table_name = ?1", PARTITIONS_TABLE),
+            [table],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .optional()?;
+    Ok(recorded.map(|(column, unit)| Partitioning { column, unit: unit.parse().unwrap_or_default() }))
+}
+
+/// Table a reload of the partitioned `table` is parsed into
+pub fn loading_table(table: &str) -> String {
+    format!("{}{}", table, LOADING_SUFFIX)
+}
+
+/// Put the freshly parsed `loaded` table in place of the view and shards of
+/// `table`, in one transaction
+pub fn replace_partitioned(conn: &Connection, table: &str, loaded: &str) -> Result<()> {
+    conn.execute_batch("BEGIN")?;
+    let result = (|| -> Result<()> {
+        conn.execute(&format!("DROP VIEW {}", quote_ident(table)), [])?;
+        for shard in shards(conn, table)? {
+            conn.execute(&format!("DROP TABLE {}", quote_ident(&shard)), [])?;
+        }
+        conn.execute(&format!("DELETE FROM {} WHERE table_name = ?1", PARTITIONS_TABLE), [table])?;
+        conn.execute(&format!("ALTER TABLE {} RENAME TO {}", quote_ident(loaded), quote_ident(table)), [])?;
+        Ok(())
+    })();
+    match result {
+        Ok(()) => Ok(conn.execute_batch("COMMIT")?),
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            Err(e)
+        }
+    }
+}
+
+/// Split the freshly loaded `table` into shards and replace it with a view.
+/// Returns the shard names.
+pub fn partition(conn: &Connection, table: &str, partitioning: &Partitioning) -> Result<Vec<String>> {
+    let column = &partitioning.column;
+    let ddl: String = conn
+        .query_row("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?1", [table], |r| r.get(0))
+        .optional()?
+        .with_context(|| format!("Table '{}' not found", table))?;
+    let columns: Vec<String> = conn
+        .prepare(&format!("PRAGMA table_info({})", quote_ident(table)))?
+        .query_map([], |r| r.get(1))?
+        .collect::<rusqlite::Result<_>>()?;
+    if !columns.contains(column) {
+        bail!("Table '{}' has no column '{}' to partition by", table, column);
+    }
+
+    // ISO text and unix epoch (`--dates-as-epoch`) both map to a period
+    let period = format!(
+        "CASE WHEN typeof({0}) IN ('integer', 'real') THEN strftime('{1}', {0}, 'unixepoch') \
+         ELSE strftime('{1}', {0}) END",
+        quote_ident(column),
+        partitioning.unit.strftime()
+    );
+    let periods: Vec<Option<String>> = conn
+        .prepare(&format!("SELECT DISTINCT {} AS period FROM {} ORDER BY period", period, quote_ident(table)))?
+        .query_map([], |r| r.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    if periods.len() > MAX_SHARDS {
+        bail!(
+            "'{}' spans {} periods, more than {} shards; partition by year instead",
+            column,
+            periods.len(),
+            MAX_SHARDS
+        );
+    }
+    let body = &ddl[ddl.find('(').context("Unexpected table definition")?..];
+    let indexes = index_columns(conn, table)?;
+
+    conn.execute_batch("BEGIN")?;
+    let result = (|| -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for value in &periods {
+            let name = format!("{}{}{}", table, SHARD_SEPARATOR, value.as_deref().unwrap_or("none"));
+            conn.execute(&format!("CREATE TABLE {} {}", quote_ident(&name), body), [])?;
+            let filter = if value.is_some() { format!("{} = ?1", period) } else { format!("{} IS NULL", period) };
+            let insert = format!("INSERT INTO {} SELECT * FROM {} WHERE {}", quote_ident(&name), quote_ident(table), filter);
+            match value {
+                Some(value) => conn.execute(&insert, [value])?,
+                None => conn.execute(&insert, [])?,
+            };
+            let mut indexed = indexes.clone();
+            if !indexed.contains(column) {
+                indexed.push(column.clone());
+            }
+            for indexed_column in indexed {
+                conn.execute(
+                    &format!(
+                        "CREATE INDEX {} ON {} ({})",
+                        quote_ident(&format!("idx_{}_{}", name, indexed_column)),
+                        quote_ident(&name),
+                        quote_ident(&indexed_column)
+                    ),
+                    [],
+                )?;
+            }
+            names.push(name);
+        }
+        conn.execute(&format!("DROP TABLE {}", quote_ident(table)), [])?;
+        let branches: Vec<String> = names.iter().map(|n| format!("SELECT * FROM {}", quote_ident(n))).collect();
+        conn.execute(&format!("CREATE VIEW {} AS {}", quote_ident(table), branches.join(" UNION ALL ")), [])?;
+        conn.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (table_name TEXT PRIMARY KEY, column_name TEXT, unit TEXT)",
+                PARTITIONS_TABLE
+            ),
+            [],
+        )?;
+        conn.execute(
+            &format!("INSERT OR REPLACE INTO {} VALUES (?1, ?2, ?3)", PARTITIONS_TABLE),
+            [table, column.as_str(), partitioning.unit.as_str()],
+        )?;
+        Ok(names)
+    })();
+    match result {
+        Ok(names) => {
+            conn.execute_batch("COMMIT")?;
+            info!("🗂️ '{}' split into {} shard(s) by {}", table, names.len(), column);
+            Ok(names)
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            Err(e)
+        }
+    }
+}
+
+/// Single-column indexes of `table` (the filter indexes made on load)
+fn index_columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT ii.name FROM pragma_index_list(?1) il, pragma_index_info(il.name) ii \
+         WHERE il.origin = 'c' GROUP BY il.name HAVING COUNT(*) = 1",
+    )?;
+    let columns = stmt.query_map([table], |r| r.get(0))?;
+    Ok(columns.collect::<rusqlite::Result<_>>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_loader::{self, LoadOptions};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_partition_and_reload() {
+        let dir = tempdir().unwrap();
+        let csv = dir.path().join("trips.csv");
+        let mut content = String::from("trip_date,region,passengers\n");
+        for year in 2022..2025 {
+            for month in 1..=12 {
+                content.push_str(&format!("{}-{:02}-15,Север,{}\n", year, month, month));
+            }
+        }
+        std::fs::write(&csv, &content).unwrap();
+        let db = dir.path().join("test.db");
+        let options = LoadOptions {
+            partition: Some(Partitioning { column: "trip_date".into(), unit: PartitionUnit::Year }),
+            ..LoadOptions::default()
+        };
+
+        let message = data_loader::load_file_with(&csv, "trips", &db, &options).unwrap();
+        assert!(message.contains("3 shard"), "{}", message);
+        let conn = Connection::open(&db).unwrap();
+        assert!(is_partitioned(&conn, "trips").unwrap());
+        assert_eq!(shards(&conn, "trips").unwrap(), vec!["trips__p2022", "trips__p2023", "trips__p2024"]);
+        let count = |sql: &str| -> i64 { conn.query_row(sql, [], |r| r.get(0)).unwrap() };
+        assert_eq!(count("SELECT COUNT(*) FROM trips"), 36);
+        assert_eq!(count("SELECT COUNT(*) FROM trips WHERE trip_date >= '2024-07-01'"), 6);
+        assert_eq!(count("SELECT COUNT(*) FROM trips__p2023"), 12);
+        assert_eq!(data_loader::date_columns(&db, "trips").unwrap(), vec!["trip_date"]);
+
+        // A file that fails to load leaves the old shards in place
+        let bad = dir.path().join("trips.txt");
+        std::fs::write(&bad, "trip_date\n").unwrap();
+        assert!(data_loader::load_file(&bad, "trips", &db).is_err());
+        assert_eq!(count("SELECT COUNT(*) FROM trips"), 36);
+        assert!(shards(&conn, "trip%").unwrap().is_empty(), "% is not a wildcard");
+
+        // A plain reload replaces the shards and keeps the split
+        std::fs::write(&csv, "trip_date,region,passengers\n2025-01-01,Юг,1\n").unwrap();
+        data_loader::load_file(&csv, "trips", &db).unwrap();
+        assert_eq!(shards(&conn, "trips").unwrap(), vec!["trips__p2025"]);
+        assert_eq!(count("SELECT COUNT(*) FROM trips"), 1);
+
+        let plain = LoadOptions { keep_partitioning: false, ..LoadOptions::default() };
+        data_loader::load_file_with(&csv, "trips", &db, &plain).unwrap();
+        assert!(!is_partitioned(&conn, "trips").unwrap());
+        assert!(shards(&conn, "trips").unwrap().is_empty());
+
+        let months = Partitioning { column: "trip_date".into(), unit: "month".parse().unwrap() };
+        assert_eq!(partition(&conn, "trips", &months).unwrap(), vec!["trips__p2025_01"]);
+        let missing = Partitioning { column: "nope".into(), unit: PartitionUnit::Year };
+        assert!(partition(&conn, "trips__p2025_01", &missing).is_err());
+    }
+}
@@ -1,59 +1,182 @@
 //! Python environment management
+//!
+//! The kit carries Windows' embeddable layout (`python.exe`, `Scripts\`,
+//! `Lib\site-packages`). A stick prepared for Linux or macOS carries a
+//! relocatable Unix build instead (`bin/python3`, `lib/python3.X/site-packages`);
+//! the layout follows the OS the launcher runs on.
 
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use tracing::info;
 
+/// Extra environment variables per service (`env` in config.json).
+///
+/// Merged over the launcher's defaults, so site tweaks (SQLAlchemy pool
+/// settings, proxies, `SUPERSET_*` flags read by superset_config.py) need no
+/// rebuild. `PATH` is always built by the launcher.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnvConfig {
+    /// Superset web server and its CLI commands (`init`, `reset-password`, ...)
+    pub superset: BTreeMap<String, String>,
+    /// Trial Superset behind `/_canary`, applied on top of `superset`
+    pub canary: BTreeMap<String, String>,
+}
+
+/// Where a Python tree keeps its interpreter, scripts and packages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PythonLayout {
+    /// `python.exe`, `Scripts\superset.exe`, `;` in PATH
+    Windows,
+    /// `bin/python3`, `bin/superset`, `:` in PATH
+    Unix,
+}
+
+impl PythonLayout {
+    /// Layout of the OS the launcher runs on
+    pub fn current() -> Self {
+        if cfg!(windows) {
+            PythonLayout::Windows
+        } else {
+            PythonLayout::Unix
+        }
+    }
+
+    fn python_exe(&self, python_dir: &Path) -> PathBuf {
+        match self {
+            PythonLayout::Windows => python_dir.join("python.exe"),
+            PythonLayout::Unix => python_dir.join("bin").join("python3"),
+        }
+    }
+
+    fn scripts_dir(&self, python_dir: &Path) -> PathBuf {
+        match self {
+            PythonLayout::Windows => python_dir.join("Scripts"),
+            PythonLayout::Unix => python_dir.join("bin"),
+        }
+    }
+
+    /// `Lib/site-packages`, or `lib/python3.X/site-packages` of the first
+    /// version found (`lib/python3/site-packages` when there is none yet)
+    fn site_packages(&self, python_dir: &Path) -> PathBuf {
+        match self {
+            PythonLayout::Windows => python_dir.join("Lib").join("site-packages"),
+            PythonLayout::Unix => {
+                let lib = python_dir.join("lib");
+                let version = std::fs::read_dir(&lib)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.file_name().to_string_lossy().to_string())
+                    .filter(|name| name.starts_with("python3") && lib.join(name).join("site-packages").is_dir())
+                    .min()
+                    .unwrap_or_else(|| "python3".to_string());
+                lib.join(version).join("site-packages")
+            }
+        }
+    }
+
+    fn executable(&self, name: &str) -> String {
+        match self {
+            PythonLayout::Windows => format!("{}.exe", name),
+            PythonLayout::Unix => name.to_string(),
+        }
+    }
+
+    fn path_separator(&self) -> &'static str {
+        match self {
+            PythonLayout::Windows => ";",
+            PythonLayout::Unix => ":",
+        }
+    }
+}
+
 /// Represents the portable Python environment
+#[derive(Debug, Clone)]
 pub struct PythonEnv {
-    root: PathBuf,
+    layout: PythonLayout,
+    python_dir: PathBuf,
+    superset_home: PathBuf,
     python_exe: PathBuf,
     scripts_dir: PathBuf,
     site_packages: PathBuf,
+    /// Overrides from config.json, applied after the defaults
+    extra_env: BTreeMap<String, String>,
 }
 
 impl PythonEnv {
     /// Create a new Python environment reference
     pub fn new(root: &Path) -> Result<Self> {
-        let python_dir = root.join("python");
-        let python_exe = python_dir.join("python.exe");
-        let scripts_dir = python_dir.join("Scripts");
-        let site_packages = python_dir.join("Lib").join("site-packages");
+        Self::with_layout(&root.join("python"), &root.join("superset_home"))
+    }
+    
+    /// Environment with a custom Python tree and Superset home
+    /// (e.g. a second Superset build trialled next to production)
+    pub fn with_layout(python_dir: &Path, superset_home: &Path) -> Result<Self> {
+        Self::for_platform(python_dir, superset_home, PythonLayout::current())
+    }
+    
+    /// Environment of a Python tree in the given layout
+    pub fn for_platform(python_dir: &Path, superset_home: &Path, layout: PythonLayout) -> Result<Self> {
+        let python_exe = layout.python_exe(python_dir);
+        let scripts_dir = layout.scripts_dir(python_dir);
+        let site_packages = layout.site_packages(python_dir);
         
         Ok(Self {
-            root: root.to_path_buf(),
+            layout,
+            python_dir: python_dir.to_path_buf(),
+            superset_home: superset_home.to_path_buf(),
             python_exe,
             scripts_dir,
             site_packages,
+            extra_env: BTreeMap::new(),
         })
     }
     
-    /// Check if Python environment is valid (python.exe exists)
+    /// Add environment overrides; later calls win over earlier ones
+    pub fn with_env(mut self, vars: &BTreeMap<String, String>) -> Self {
+        self.extra_env.extend(vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+        self
+    }
+    
+    /// Check if Python environment is valid (the interpreter exists)
     pub fn is_valid(&self) -> bool {
         self.python_exe.exists()
     }
     
-    /// Get path to python.exe
+    /// Get path to the interpreter (`python.exe`, `bin/python3`)
     pub fn python_path(&self) -> &Path {
         &self.python_exe
     }
     
-    /// Get path to Scripts directory (where superset CLI is)
+    /// Superset home (metadata DB and superset_config.py)
+    pub fn superset_home(&self) -> &Path {
+        &self.superset_home
+    }
+    
+    /// Installed packages: `Lib/site-packages`, `lib/python3.X/site-packages`
+    pub fn site_packages(&self) -> &Path {
+        &self.site_packages
+    }
+    
+    /// Get path to the scripts directory (where superset CLI is): `Scripts`, `bin`
     pub fn scripts_path(&self) -> &Path {
         &self.scripts_dir
     }
     
     /// Get path to superset CLI executable
     pub fn superset_cli(&self) -> PathBuf {
-        self.scripts_dir.join("superset.exe")
+        self.scripts_dir.join(self.layout.executable("superset"))
     }
     
     /// Get environment variables for running Python/Superset
     pub fn get_env_vars(&self) -> Vec<(String, String)> {
-        let python_dir = self.root.join("python");
-        let superset_home = self.root.join("superset_home");
+        let python_dir = &self.python_dir;
+        let superset_home = &self.superset_home;
         
-        vec![
+        let mut vars = vec![
             // Python paths
             ("PYTHONHOME".to_string(), python_dir.to_string_lossy().to_string()),
             // Superset specific
@@ -65,20 +188,41 @@ impl PythonEnv {
             // Flask
             ("FLASK_APP".to_string(), "superset".to_string()),
             ("FLASK_ENV".to_string(), "production".to_string()),
-        ]
+        ];
+        // numpy/pandas thread pools sized for this machine
+        vars.extend(crate::hardware::current().superset_env());
+        
+        // Overrides replace a default in place or are appended
+        for (key, value) in &self.extra_env {
+            if key.eq_ignore_ascii_case("PATH") {
+                continue;
+            }
+            match vars.iter_mut().find(|(k, _)| k == key) {
+                Some(var) => var.1 = value.clone(),
+                None => vars.push((key.clone(), value.clone())),
+            }
+        }
+        vars
     }
     
     /// Build PATH environment variable including Python directories
     pub fn get_path_env(&self) -> String {
-        let python_dir = self.root.join("python");
         let current_path = std::env::var("PATH").unwrap_or_default();
-        
-        format!(
-            "{};{};{}",
-            python_dir.to_string_lossy(),
-            self.scripts_dir.to_string_lossy(),
-            current_path
-        )
+        self.path_env_with(&current_path)
+    }
+    
+    /// Python directories in front of `current_path`
+    fn path_env_with(&self, current_path: &str) -> String {
+        let mut dirs = Vec::new();
+        // On Unix the interpreter sits in `bin` with the scripts
+        if self.layout == PythonLayout::Windows {
+            dirs.push(self.python_dir.to_string_lossy().to_string());
+        }
+        dirs.push(self.scripts_dir.to_string_lossy().to_string());
+        if !current_path.is_empty() {
+            dirs.push(current_path.to_string());
+        }
+        dirs.join(self.layout.path_separator())
     }
     
     /// Run a Python command and return output
@@ -96,6 +240,18 @@ impl PythonEnv {
         Ok(output)
     }
     
+    /// Get installed Superset version from its dist-info folder (no Python needed)
+    pub fn superset_version(&self) -> Option<String> {
+        std::fs::read_dir(&self.site_packages).ok()?
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.file_name().into_string().ok())
+            .find_map(|name| {
+                name.strip_prefix("apache_superset-")?
+                    .strip_suffix(".dist-info")
+                    .map(|v| v.to_string())
+            })
+    }
+
     /// Check if Superset is installed
     pub fn is_superset_installed(&self) -> bool {
         self.superset_cli().exists() || {
@@ -117,9 +273,46 @@ mod tests {
     #[test]
     fn test_python_env_paths() {
         let root = PathBuf::from("C:\\test");
-        let env = PythonEnv::new(&root).unwrap();
+        let env = PythonEnv::for_platform(&root.join("python"), &root.join("superset_home"), PythonLayout::Windows).unwrap();
+        
+        assert_eq!(env.python_path(), root.join("python").join("python.exe"));
+        assert_eq!(env.scripts_path(), root.join("python").join("Scripts"));
+        assert_eq!(env.superset_cli(), root.join("python").join("Scripts").join("superset.exe"));
+        assert_eq!(
+            env.path_env_with("C:\\Windows"),
+            format!("{};{};C:\\Windows", root.join("python").display(), root.join("python").join("Scripts").display())
+        );
+    }
+    
+    #[test]
+    fn test_unix_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let python = dir.path().join("python");
+        std::fs::create_dir_all(python.join("lib/python3.11/site-packages/apache_superset-4.1.1.dist-info")).unwrap();
+        let env = PythonEnv::for_platform(&python, &dir.path().join("superset_home"), PythonLayout::Unix).unwrap();
+        
+        assert_eq!(env.python_path(), python.join("bin").join("python3"));
+        assert_eq!(env.superset_cli(), python.join("bin").join("superset"));
+        assert_eq!(env.superset_version().as_deref(), Some("4.1.1"));
+        assert_eq!(env.path_env_with("/usr/bin:/bin"), format!("{}:/usr/bin:/bin", python.join("bin").display()));
+    }
+    
+    #[test]
+    fn test_env_overrides() {
+        let superset = BTreeMap::from([
+            ("FLASK_ENV".to_string(), "development".to_string()),
+            ("HTTPS_PROXY".to_string(), "http://proxy:3128".to_string()),
+            ("PATH".to_string(), "C:\\evil".to_string()),
+        ]);
+        let canary = BTreeMap::from([("HTTPS_PROXY".to_string(), String::new())]);
+        let env = PythonEnv::new(&PathBuf::from("C:\\test")).unwrap().with_env(&superset).with_env(&canary);
+        let vars = env.get_env_vars();
+        let get = |key: &str| vars.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
         
-        assert_eq!(env.python_path(), PathBuf::from("C:\\test\\python\\python.exe"));
-        assert_eq!(env.scripts_path(), PathBuf::from("C:\\test\\python\\Scripts"));
+        assert_eq!(get("FLASK_ENV"), Some("development"));
+        assert_eq!(vars.iter().filter(|(k, _)| k == "FLASK_ENV").count(), 1);
+        assert_eq!(get("HTTPS_PROXY"), Some(""));
+        assert_eq!(get("PATH"), None);
+        assert_eq!(get("SUPERSET_TELEMETRY"), Some("false"));
     }
 }
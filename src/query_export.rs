@@ -0,0 +1,401 @@
+//! CSV/XLSX export of query results
+//!
+//! `POST /api/db/query/export` on the launcher UI runs one read-only query
+//! against examples.db (or another logical database from config.json) and
+//! sends the rows back as a file, so cleaned data can be taken out of the kit
+//! without Superset's chart → export path and its row limits:
+//!
+//! ```json
+//! {"sql": "SELECT * FROM rzd_monthly_stats WHERE year = :year",
+//!  "params": {"year": 2023}, "format": "csv"}
+//! ```
+//!
+//! The database is opened read-only with `query_only` on, and only a single
+//! statement that returns rows and writes nothing is accepted. Parameters are
+//! bound, never pasted into the SQL: an object binds `:name`/`@name`/`$name`,
+//! an array binds `?1`, `?2`, …. CSV is streamed while the query runs (UTF-8
+//! with BOM and `;` by default, which Russian Excel opens as is); XLSX is
+//! built in memory, so it is limited to one sheet and `xlsx_max_rows` rows.
+//! While the drive watchdog has paused writes the endpoint answers 503.
+
+use anyhow::{bail, Context, Result};
+use axum::body::{Bytes, HttpBody};
+use hyper::body::Frame;
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{Batch, Connection, OpenFlags, Statement};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
+use tokio::sync::mpsc;
+
+/// Bytes gathered before a CSV chunk is sent
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Rows an XLSX sheet holds, header included
+const XLSX_MAX_ROWS: u64 = 1_048_576;
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+/// Export settings (`query_export` section of config.json)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QueryExportConfig {
+    pub enabled: bool,
+    /// Larger results are refused; add a filter or LIMIT
+    pub max_rows: u64,
+    /// Same for XLSX, whose workbook is held in memory until it is sent
+    pub xlsx_max_rows: u64,
+    /// CSV field separator
+    pub csv_delimiter: char,
+    /// Start CSV files with a UTF-8 BOM so Excel detects the encoding
+    pub csv_bom: bool,
+}
+
+impl Default for QueryExportConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_rows: 1_000_000,
+            xlsx_max_rows: 100_000,
+            csv_delimiter: ';',
+            csv_bom: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Xlsx => "xlsx",
+        }
+    }
+}
+
+/// Body of `POST /api/db/query/export`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExportRequest {
+    pub sql: String,
+    /// Object of named parameters or array of positional ones
+    #[serde(default)]
+    pub params: serde_json::Value,
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// Logical database name from config.json (default: examples)
+    pub db: Option<String>,
+    /// Download name without extension (default: export)
+    pub file_name: Option<String>,
+    /// Overrides `query_export.csv_delimiter`
+    pub delimiter: Option<char>,
+}
+
+impl ExportRequest {
+    /// `Content-Disposition` file name, reduced to characters safe in a header
+    pub fn download_name(&self) -> String {
+        let stem: String = self
+            .file_name
+            .as_deref()
+            .unwrap_or("export")
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            .collect();
+        let stem = if stem.is_empty() { "export".to_string() } else { stem };
+        format!("{}.{}", stem, self.format.extension())
+    }
+}
+
+fn open(db_path: &Path) -> Result<Connection> {
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open {}", db_path.display()))?;
+    conn.execute_batch("PRAGMA query_only = ON")?;
+    Ok(conn)
+}
+
+fn json_to_sql(name: &str, value: &serde_json::Value) -> Result<Value> {
+    Ok(match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Integer(*b as i64),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Real(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => Value::Text(s.clone()),
+        _ => bail!("Parameter '{}' must be a string, number, boolean or null", name),
+    })
+}
+
+/// Prepare `sql` and bind `params`, refusing anything that isn't a single
+/// read-only query
+fn prepare<'c>(conn: &'c Connection, sql: &str, params: &serde_json::Value) -> Result<Statement<'c>> {
+    // `prepare` compiles the first statement and ignores the rest: walk them all
+    let mut batch = Batch::new(conn, sql);
+    let mut stmt = batch.next().context("Invalid query")?.context("The query is empty")?;
+    if batch.next().context("Invalid query (only one statement is allowed)")?.is_some() {
+        bail!("Only one statement is allowed");
+    }
+    if !stmt.readonly() || stmt.column_count() == 0 {
+        bail!("Only queries that return rows can be exported (SELECT, WITH ... SELECT)");
+    }
+    let mut bound = std::collections::HashSet::new();
+    match params {
+        serde_json::Value::Null => {}
+        serde_json::Value::Array(values) => {
+            for (i, value) in values.iter().enumerate() {
+                if i >= stmt.parameter_count() {
+                    bail!("The query takes {} parameters, {} given", stmt.parameter_count(), values.len());
+                }
+                stmt.raw_bind_parameter(i + 1, json_to_sql(&format!("?{}", i + 1), value)?)?;
+                bound.insert(i + 1);
+            }
+        }
+        serde_json::Value::Object(values) => {
+            for (name, value) in values {
+                let index = [":", "@", "$", ""]
+                    .iter()
+                    .map(|prefix| format!("{}{}", prefix, name))
+                    .find_map(|name| stmt.parameter_index(&name).ok().flatten())
+                    .with_context(|| format!("The query has no parameter '{}'", name))?;
+                stmt.raw_bind_parameter(index, json_to_sql(name, value)?)?;
+                bound.insert(index);
+            }
+        }
+        _ => bail!("params must be an object or an array"),
+    }
+    if let Some(missing) = (1..=stmt.parameter_count()).find(|i| !bound.contains(i)) {
+        let name = stmt.parameter_name(missing).map(str::to_string).unwrap_or_else(|| format!("?{}", missing));
+        bail!("Parameter '{}' is not set", name);
+    }
+    Ok(stmt)
+}
+
+fn text(value: ValueRef<'_>) -> String {
+    match value {
+        ValueRef::Null => String::new(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Text(t) => String::from_utf8_lossy(t).into_owned(),
+        ValueRef::Blob(b) => hex::encode(b),
+    }
+}
+
+/// Run the export into `out`, returns the number of data rows written
+pub fn export(db_path: &Path, config: &QueryExportConfig, request: &ExportRequest, out: &mut dyn Write) -> Result<u64> {
+    let conn = open(db_path)?;
+    let mut stmt = prepare(&conn, &request.sql, &request.params)?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(str::to_string).collect();
+    let max_rows = match request.format {
+        ExportFormat::Csv => config.max_rows,
+        ExportFormat::Xlsx => config.max_rows.min(config.xlsx_max_rows).min(XLSX_MAX_ROWS - 1),
+    };
+    let too_many = || match request.format {
+        ExportFormat::Csv => anyhow::anyhow!("The result has more than {} rows — add a filter or LIMIT", max_rows),
+        ExportFormat::Xlsx => {
+            anyhow::anyhow!("The result has more than {} rows — add a filter or LIMIT, or export as CSV", max_rows)
+        }
+    };
+
+    let mut rows = stmt.raw_query();
+    let mut count = 0u64;
+    match request.format {
+        ExportFormat::Csv => {
+            if config.csv_bom {
+                out.write_all(UTF8_BOM)?;
+            }
+            let delimiter = request.delimiter.unwrap_or(config.csv_delimiter);
+            if !delimiter.is_ascii() {
+                bail!("CSV delimiter must be an ASCII character");
+            }
+            let mut writer = csv::WriterBuilder::new().delimiter(delimiter as u8).from_writer(out);
+            writer.write_record(&columns)?;
+            while let Some(row) = rows.next()? {
+                count += 1;
+                if count > max_rows {
+                    return Err(too_many());
+                }
+                let record = (0..columns.len()).map(|i| row.get_ref(i).map(text)).collect::<rusqlite::Result<Vec<_>>>()?;
+                writer.write_record(&record)?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::Xlsx => {
+            let mut workbook = rust_xlsxwriter::Workbook::new();
+            let sheet = workbook.add_worksheet();
+            let bold = rust_xlsxwriter::Format::new().set_bold();
+            for (col, name) in columns.iter().enumerate() {
+                sheet.write_string_with_format(0, col as u16, name, &bold)?;
+            }
+            while let Some(row) = rows.next()? {
+                count += 1;
+                if count > max_rows {
+                    return Err(too_many());
+                }
+                for col in 0..columns.len() {
+                    let (r, c) = (count as u32, col as u16);
+                    match row.get_ref(col)? {
+                        ValueRef::Null => {}
+                        ValueRef::Integer(i) => {
+                            sheet.write_number(r, c, i as f64)?;
+                        }
+                        ValueRef::Real(f) => {
+                            sheet.write_number(r, c, f)?;
+                        }
+                        other => {
+                            sheet.write_string(r, c, text(other))?;
+                        }
+                    }
+                }
+            }
+            out.write_all(&workbook.save_to_buffer()?)?;
+        }
+    }
+    Ok(count)
+}
+
+/// `Write` end of a streamed response: sends a chunk every [`CHUNK_SIZE`]
+/// bytes and fails once the client has gone, which stops the query
+pub struct ChunkWriter {
+    tx: mpsc::Sender<Result<Bytes, String>>,
+    buffer: Vec<u8>,
+}
+
+impl ChunkWriter {
+    pub fn new(tx: mpsc::Sender<Result<Bytes, String>>) -> Self {
+        Self { tx, buffer: Vec::with_capacity(CHUNK_SIZE) }
+    }
+
+    fn send(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = Bytes::from(std::mem::replace(&mut self.buffer, Vec::with_capacity(CHUNK_SIZE)));
+        self.tx
+            .blocking_send(Ok(chunk))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "client disconnected"))
+    }
+
+    /// Send what is left, or report `error` to the handler
+    pub fn finish(mut self, result: Result<u64>) {
+        let _ = match result {
+            Ok(_) => self.send().map_err(drop),
+            Err(e) => self.tx.blocking_send(Err(format!("{:#}", e))).map_err(drop),
+        };
+    }
+}
+
+impl Write for ChunkWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(data);
+        if self.buffer.len() >= CHUNK_SIZE {
+            self.send()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Response body fed by a [`ChunkWriter`]; an error after the first chunk
+/// cuts the download short so a partial file is never taken as complete
+pub struct ChunkBody {
+    rx: mpsc::Receiver<Result<Bytes, String>>,
+    first: Option<Bytes>,
+}
+
+impl ChunkBody {
+    pub fn new(first: Option<Bytes>, rx: mpsc::Receiver<Result<Bytes, String>>) -> Self {
+        Self { rx, first }
+    }
+}
+
+impl HttpBody for ChunkBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Result<Frame<Bytes>, axum::Error>>> {
+        let this = self.get_mut();
+        if let Some(first) = this.first.take() {
+            return Poll::Ready(Some(Ok(Frame::data(first))));
+        }
+        match this.rx.poll_recv(cx) {
+            Poll::Ready(Some(Ok(chunk))) => Poll::Ready(Some(Ok(Frame::data(chunk)))),
+            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(axum::Error::new(io::Error::other(e))))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn request(sql: &str, params: serde_json::Value) -> ExportRequest {
+        serde_json::from_value(serde_json::json!({"sql": sql, "params": params})).unwrap()
+    }
+
+    fn csv_of(db: &Path, request: &ExportRequest) -> Result<String> {
+        let config = QueryExportConfig { csv_bom: false, ..QueryExportConfig::default() };
+        let mut out = Vec::new();
+        export(db, &config, request, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_export_csv_with_params() {
+        let dir = tempdir().unwrap();
+        let db = dir.path().join("examples.db");
+        Connection::open(&db)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE ops (year INTEGER, region TEXT, pct REAL);
+                 INSERT INTO ops VALUES (2023, 'Москва; центр', 96.5), (2023, 'Урал', NULL), (2022, 'Урал', 91);",
+            )
+            .unwrap();
+
+        let named = request("SELECT region, pct FROM ops WHERE year = :year ORDER BY region", serde_json::json!({"year": 2023}));
+        assert_eq!(csv_of(&db, &named).unwrap(), "region;pct\n\"Москва; центр\";96.5\nУрал;\n");
+
+        let positional = request("SELECT COUNT(*) AS n FROM ops WHERE region = ?1", serde_json::json!(["Урал"]));
+        assert_eq!(csv_of(&db, &positional).unwrap(), "n\n2\n");
+
+        assert!(csv_of(&db, &request("SELECT * FROM ops WHERE year = :year", serde_json::Value::Null)).is_err());
+        assert!(csv_of(&db, &request("SELECT 1", serde_json::json!({"nope": 1}))).is_err());
+        assert!(csv_of(&db, &request("DELETE FROM ops", serde_json::Value::Null)).is_err());
+        assert!(csv_of(&db, &request("SELECT 1; DELETE FROM ops", serde_json::Value::Null)).is_err());
+        assert!(csv_of(&db, &request("SELECT 1; SELECT 2", serde_json::Value::Null)).is_err());
+        assert_eq!(csv_of(&db, &request("SELECT 1 AS n; -- done", serde_json::Value::Null)).unwrap(), "n\n1\n");
+        assert!(csv_of(&db, &request("ATTACH 'other.db' AS other", serde_json::Value::Null)).is_err());
+
+        let config = QueryExportConfig { max_rows: 2, ..QueryExportConfig::default() };
+        assert!(export(&db, &config, &request("SELECT * FROM ops", serde_json::Value::Null), &mut Vec::new()).is_err());
+
+        let mut xlsx = request("SELECT * FROM ops", serde_json::Value::Null);
+        xlsx.format = ExportFormat::Xlsx;
+        let mut out = Vec::new();
+        assert_eq!(export(&db, &QueryExportConfig::default(), &xlsx, &mut out).unwrap(), 3);
+        assert!(out.starts_with(b"PK"));
+        assert_eq!(xlsx.download_name(), "export.xlsx");
+        let config = QueryExportConfig { xlsx_max_rows: 2, ..QueryExportConfig::default() };
+        assert!(export(&db, &config, &xlsx, &mut Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+//! Row limits and query timeouts for Superset (`query_limits` in config.json)
+//!
+//! ```json
+//! "query_limits": {
+//!   "row_limit": 20000,
+//!   "samples_row_limit": 500,
+//!   "sql_max_row": 50000,
+//!   "sqllab_timeout_secs": 30,
+//!   "databases": { "finance": { "row_limit": 5000, "timeout_secs": 10 } }
+//! }
+//! ```
+//!
+//! The top-level keys become Superset's own `ROW_LIMIT`, `SAMPLES_ROW_LIMIT`,
+//! `SQL_MAX_ROW` and `SQLLAB_TIMEOUT`. Limits of a single database (by its name
+//! in `databases`, or `examples`) are stricter guards Superset has no setting
+//! for: every SELECT sent to it is wrapped in `LIMIT n` by `SQL_QUERY_MUTATOR`,
+//! and a statement running longer than the timeout is interrupted through
+//! SQLite's progress handler — on the live file and on its replicas alike.
+//!
+//! All of it is written as a marked block at the end of `superset_config.py`
+//! before every Superset start, so the Python file is never edited by hand
+//! for this. Without `query_limits` the block is removed.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::info;
+
+use crate::config::Config;
+use crate::databases;
+
+pub const BLOCK_START: &str = "# >>> superset-launcher: query limits";
+pub const BLOCK_END: &str = "# <<< superset-launcher: query limits";
+
+/// `query_limits` section of config.json; unset keys keep Superset's defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct QueryLimitsConfig {
+    /// Rows a chart query may return (`ROW_LIMIT`, Superset default 50000)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub row_limit: Option<u64>,
+    /// Rows shown in a dataset's samples (`SAMPLES_ROW_LIMIT`, default 1000)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub samples_row_limit: Option<u64>,
+    /// Rows a SQL Lab query may return (`SQL_MAX_ROW`, default 100000)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sql_max_row: Option<u64>,
+    /// Seconds before a SQL Lab query is stopped (`SQLLAB_TIMEOUT`, default 30)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sqllab_timeout_secs: Option<u64>,
+    /// Guards for single databases, by name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub databases: BTreeMap<String, DatabaseLimits>,
+}
+
+/// Guards of one database
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DatabaseLimits {
+    /// Rows any SELECT may return
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub row_limit: Option<u64>,
+    /// Seconds a statement may run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+/// Per-database guards as the Python block reads them
+#[derive(Serialize)]
+struct GuardEntry {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    row_limit: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeout_secs: Option<u64>,
+}
+
+/// Looks up the guards of a connection by its name (row limit) or its file (timeout)
+const GUARDS_PY: &str = r#"
+import os as _launcher_os
+import re as _launcher_re
+import sqlite3 as _launcher_sqlite3
+import time as _launcher_time
+from sqlalchemy import event as _launcher_event
+from sqlalchemy.pool import Pool as _launcher_Pool
+
+
+def _launcher_norm(path):
+    return _launcher_os.path.normcase(_launcher_os.path.abspath(path))
+
+
+def _launcher_guards_for_file(path):
+    path = _launcher_norm(path)
+    for name, guards in _LAUNCHER_GUARDS.items():
+        if path == _launcher_norm(guards["path"]):
+            return guards
+        # Replicas are replicas/<name>-<time>.db
+        replica = _launcher_re.fullmatch(_launcher_re.escape(name) + r"-\d{8}-\d{9}\.db", _launcher_os.path.basename(path), _launcher_re.IGNORECASE)
+        if replica and _launcher_os.path.dirname(path) == _launcher_norm(_LAUNCHER_REPLICAS):
+            return guards
+    return {}
+
+
+def SQL_QUERY_MUTATOR(sql, **kwargs):
+    name = getattr(kwargs.get("database"), "database_name", None)
+    row_limit = _LAUNCHER_GUARDS.get(name, {}).get("row_limit")
+    if not row_limit or not _launcher_re.match(r"\s*(select|with)\b", sql, _launcher_re.IGNORECASE):
+        return sql
+    # On lines of its own, so a trailing "-- comment" can't swallow the LIMIT
+    return "SELECT * FROM (\n%s\n) LIMIT %d" % (sql.strip().rstrip(";"), row_limit)
+
+
+@_launcher_event.listens_for(_launcher_Pool, "connect")
+def _launcher_statement_timeout(dbapi_connection, connection_record):
+    if not isinstance(dbapi_connection, _launcher_sqlite3.Connection):
+        return
+    files = [row[2] for row in dbapi_connection.execute("PRAGMA database_list") if row[1] == "main" and row[2]]
+    timeout = _launcher_guards_for_file(files[0]).get("timeout_secs") if files else None
+    if not timeout:
+        return
+    deadline = [None]
+
+    def started(statement):
+        deadline[0] = _launcher_time.monotonic() + timeout
+
+    # A non-zero answer interrupts the running statement
+    dbapi_connection.set_trace_callback(started)
+    dbapi_connection.set_progress_handler(lambda: deadline[0] is not None and _launcher_time.monotonic() > deadline[0], 100000)
+"#;
+
+fn positive(value: Option<u64>, key: &str) -> Result<Option<u64>> {
+    if value == Some(0) {
+        bail!("query_limits: {} must be positive", key);
+    }
+    Ok(value)
+}
+
+/// The marked block for `config`, or an empty string when nothing is limited
+pub fn render(root: &Path, config: &Config) -> Result<String> {
+    let limits = &config.query_limits;
+    if limits == &QueryLimitsConfig::default() {
+        return Ok(String::new());
+    }
+
+    let mut block = format!("{}\n# Written from \"query_limits\" in config.json before every start; change the limits there\n", BLOCK_START);
+    for (name, value) in [
+        ("ROW_LIMIT", positive(limits.row_limit, "row_limit")?),
+        ("SAMPLES_ROW_LIMIT", positive(limits.samples_row_limit, "samples_row_limit")?),
+        ("SQL_MAX_ROW", positive(limits.sql_max_row, "sql_max_row")?),
+        ("SQLLAB_TIMEOUT", positive(limits.sqllab_timeout_secs, "sqllab_timeout_secs")?),
+    ] {
+        if let Some(value) = value {
+            block.push_str(&format!("{} = {}\n", name, value));
+        }
+    }
+
+    if !limits.databases.is_empty() {
+        let mut guards = BTreeMap::new();
+        for (name, database) in &limits.databases {
+            let path = databases::resolve(root, &config.databases, name).context("query_limits.databases")?;
+            guards.insert(
+                name,
+                GuardEntry {
+                    path: path.to_string_lossy().to_string(),
+                    row_limit: positive(database.row_limit, "row_limit")?,
+                    timeout_secs: positive(database.timeout_secs, "timeout_secs")?,
+                },
+            );
+        }
+        // JSON without nulls is a valid Python literal
+        block.push_str(&format!("\n_LAUNCHER_GUARDS = {}\n", serde_json::to_string(&guards)?));
+        let replicas = root.join(crate::replica::REPLICAS_DIR);
+        block.push_str(&format!("_LAUNCHER_REPLICAS = {}\n", serde_json::to_string(&replicas.to_string_lossy())?));
+        block.push_str(GUARDS_PY);
+    }
+    block.push_str(BLOCK_END);
+    block.push('\n');
+    Ok(block)
+}
+
+/// `source` with its block replaced by `block` (appended last, so it wins
+/// over settings earlier in the file; removed when `block` is empty)
+pub fn replace_block(source: &str, block: &str) -> String {
+    let mut out = match (source.find(BLOCK_START), source.find(BLOCK_END)) {
+        (Some(start), Some(end)) if start < end => {
+            let rest = &source[end + BLOCK_END.len()..];
+            let after = rest.strip_prefix('\n').unwrap_or(rest);
+            match source[..start].trim_end() {
+                // The blank line it was appended after goes too
+                before if after.is_empty() && !before.is_empty() => format!("{}\n", before),
+                _ if after.is_empty() => String::new(),
+                _ => format!("{}{}", &source[..start], after),
+            }
+        }
+        _ => source.to_string(),
+    };
+    if !block.is_empty() {
+        if !out.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+        }
+        if !out.ends_with("\n\n") {
+            out.push('\n');
+        }
+        out.push_str(block);
+    }
+    out
+}
+
+/// Bring the block of the kit's `superset_config.py` in line with config.json;
+/// true when the file changed
+pub fn apply(root: &Path, config: &Config) -> Result<bool> {
+    let path = root.join("superset_home").join("superset_config.py");
+    if !path.exists() {
+        return Ok(false);
+    }
+    let source = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let updated = replace_block(&source, &render(root, config)?);
+    if updated == source {
+        return Ok(false);
+    }
+    std::fs::write(&path, updated).with_context(|| format!("Failed to write {}", path.display()))?;
+    info!("🛡️ Query limits from config.json written to superset_config.py");
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_block_is_replaced_and_removed() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("superset_home")).unwrap();
+        let config_path = root.join("superset_home").join("superset_config.py");
+        std::fs::write(&config_path, "SECRET_KEY = \"x\"\nROW_LIMIT = 50000").unwrap();
+
+        let mut config = Config::default();
+        assert!(!apply(root, &config).unwrap());
+
+        config.query_limits.row_limit = Some(20000);
+        config.query_limits.databases.insert(
+            "examples".to_string(),
+            DatabaseLimits { row_limit: Some(5000), timeout_secs: Some(10) },
+        );
+        assert!(apply(root, &config).unwrap());
+        assert!(!apply(root, &config).unwrap());
+        let written = std::fs::read_to_string(&config_path).unwrap();
+        assert!(written.starts_with("SECRET_KEY = \"x\"\nROW_LIMIT = 50000\n\n# >>>"));
+        assert!(written.contains("\nROW_LIMIT = 20000\n"));
+        assert!(written.contains(r#""examples":{"path":"#));
+        assert!(written.contains(r#""row_limit":5000,"timeout_secs":10}"#));
+        assert!(!written.contains("SAMPLES_ROW_LIMIT"));
+
+        config.query_limits.databases.insert("nope".to_string(), DatabaseLimits::default());
+        assert!(apply(root, &config).is_err());
+        config.query_limits = QueryLimitsConfig { sqllab_timeout_secs: Some(0), ..Default::default() };
+        assert!(render(root, &config).is_err());
+
+        config.query_limits = QueryLimitsConfig::default();
+        assert!(apply(root, &config).unwrap());
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), "SECRET_KEY = \"x\"\nROW_LIMIT = 50000\n");
+    }
+}
@@ -0,0 +1,203 @@
+//! Read-only replicas of analytics databases
+//!
+//! With `"replica": { "enabled": true }` Superset never opens the files the
+//! loaders write to. After every load the database is snapshotted with
+//! `VACUUM INTO` into `replicas/<name>-<time>.db`, and the Superset connection
+//! is switched to that file in read-only mode. Long dashboard queries then
+//! can't hold locks against `load-data`, which is where "database is locked"
+//! errors came from.
+//!
+//! Every refresh writes a new file instead of replacing the old one: Windows
+//! can't rename over a file Superset still has open. Old replicas are removed
+//! once nothing holds them. The replica URI is relative to the kit root
+//! (Superset runs there), so it survives the kit moving to another drive.
+//! With the option turned off, [`restore_all`] points the connections back
+//! at the live files.
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::databases::{self, DatabaseConfig};
+
+/// Replica folder (relative to root)
+pub const REPLICAS_DIR: &str = "replicas";
+
+/// Read replica settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReplicaConfig {
+    /// Point Superset at read-only snapshots instead of the live files
+    pub enabled: bool,
+}
+
+/// Snapshot `source` and switch the Superset connection `name` (matched by
+/// its `uuid`) to it
+pub fn refresh(root: &Path, name: &str, uuid: Uuid, source: &Path) -> Result<PathBuf> {
+    let dir = root.join(REPLICAS_DIR);
+    std::fs::create_dir_all(&dir)?;
+
+    let stamp = Local::now().format("%Y%m%d-%H%M%S%3f");
+    let path = dir.join(format!("{}-{}.db", name, stamp));
+    let tmp = path.with_extension("db.tmp");
+    let _ = std::fs::remove_file(&tmp);
+
+    let conn = Connection::open(source).with_context(|| format!("Failed to open {}", source.display()))?;
+    conn.execute("VACUUM INTO ?1", [tmp.to_string_lossy()])
+        .with_context(|| format!("Failed to snapshot {}", source.display()))?;
+    drop(conn);
+    std::fs::rename(&tmp, &path)?;
+
+    if point_superset(root, uuid, &replica_uri(root, &path), None)? {
+        info!("📖 {} now reads from {}", name, path.display());
+    }
+    prune(&dir, name, Some(&path));
+    Ok(path)
+}
+
+/// Switch the Superset connection `name` from a replica back to `source`
+/// and delete its replicas (`false` if it wasn't on a replica)
+pub fn restore(root: &Path, name: &str, uuid: Uuid, source: &Path) -> Result<bool> {
+    let uri = format!("sqlite:///{}", source.to_string_lossy().replace('\\', "/"));
+    let restored = point_superset(root, uuid, &uri, Some(REPLICA_URI_PATTERN))?;
+    if restored {
+        info!("📖 {} reads from {} again", name, source.display());
+    }
+    prune(&root.join(REPLICAS_DIR), name, None);
+    Ok(restored)
+}
+
+/// Refresh the examples database and every configured one that exists
+pub fn refresh_all(root: &Path, databases: &[DatabaseConfig]) -> Result<usize> {
+    let mut refreshed = 0;
+    let examples = root.join(databases::EXAMPLES_PATH);
+    if examples.exists() {
+        refresh(root, databases::EXAMPLES_NAME, databases::EXAMPLES_UUID, &examples)?;
+        refreshed += 1;
+    }
+    for db in databases {
+        let path = db.path_abs(root);
+        if path.exists() {
+            refresh(root, &db.name, db.uuid(), &path)?;
+            refreshed += 1;
+        }
+    }
+    Ok(refreshed)
+}
+
+/// Point every connection back at its live file
+pub fn restore_all(root: &Path, databases: &[DatabaseConfig]) -> Result<usize> {
+    let mut restored = 0;
+    if restore(root, databases::EXAMPLES_NAME, databases::EXAMPLES_UUID, &root.join(databases::EXAMPLES_PATH))? {
+        restored += 1;
+    }
+    for db in databases {
+        if restore(root, &db.name, db.uuid(), &db.path_abs(root))? {
+            restored += 1;
+        }
+    }
+    Ok(restored)
+}
+
+/// URIs [`replica_uri`] writes (including absolute ones of older launchers)
+const REPLICA_URI_PATTERN: &str = "sqlite:///file:%?mode=ro&uri=true";
+
+/// Read-only SQLAlchemy URI for a replica file, relative to `root` when inside it
+pub fn replica_uri(root: &Path, path: &Path) -> String {
+    let path = path.strip_prefix(root).unwrap_or(path);
+    format!("sqlite:///file:{}?mode=ro&uri=true", path.to_string_lossy().replace('\\', "/"))
+}
+
+/// Update the connection's URI in superset.db, only if the current one is
+/// `LIKE current` when given (`false` if nothing was updated)
+fn point_superset(root: &Path, uuid: Uuid, uri: &str, current: Option<&str>) -> Result<bool> {
+    let db_path = root.join("superset_home").join("superset.db");
+    if !db_path.exists() {
+        return Ok(false);
+    }
+    let conn = Connection::open(&db_path)?;
+    let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S%.6f").to_string();
+    let updated = conn.execute(
+        "UPDATE dbs SET sqlalchemy_uri = ?1, changed_on = ?2 WHERE uuid = ?3
+           AND (?4 IS NULL OR sqlalchemy_uri LIKE ?4)",
+        params![uri, now, uuid, current],
+    )?;
+    Ok(updated > 0)
+}
+
+/// Delete replicas of `name` but `keep`; files still open are left for next time
+fn prune(dir: &Path, name: &str, keep: Option<&Path>) {
+    let prefix = format!("{}-", name);
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if Some(path.as_path()) == keep || !file_name.starts_with(&prefix) || !file_name.ends_with(".db") {
+            continue;
+        }
+        // `<name>-<stamp>.db` only: "fin" must not prune "fin-ops"
+        let stamp = &file_name[prefix.len()..file_name.len() - 3];
+        if !stamp.chars().all(|c| c.is_ascii_digit() || c == '-') {
+            continue;
+        }
+        if let Err(e) = std::fs::remove_file(&path) {
+            debug!("Replica {} still in use: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_refresh_switches_connection() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let source = root.join("examples.db");
+        Connection::open(&source)
+            .unwrap()
+            .execute_batch("CREATE TABLE t (x INTEGER); INSERT INTO t VALUES (1);")
+            .unwrap();
+        std::fs::create_dir_all(root.join("superset_home")).unwrap();
+        let meta = Connection::open(root.join("superset_home").join("superset.db")).unwrap();
+        meta.execute_batch(
+            "CREATE TABLE dbs (id INTEGER PRIMARY KEY, database_name TEXT, sqlalchemy_uri TEXT, uuid BLOB, changed_on DATETIME);
+             INSERT INTO dbs (database_name, sqlalchemy_uri) VALUES ('examples', 'sqlite:///examples.db');",
+        )
+        .unwrap();
+        meta.execute("UPDATE dbs SET uuid = ?1", [databases::EXAMPLES_UUID]).unwrap();
+        // Another connection named like ours is not touched
+        meta.execute("INSERT INTO dbs (database_name, sqlalchemy_uri) VALUES ('examples', 'sqlite:///mine.db')", []).unwrap();
+
+        let first = refresh(root, "examples", databases::EXAMPLES_UUID, &source).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let second = refresh(root, "examples", databases::EXAMPLES_UUID, &source).unwrap();
+        assert!(!first.exists());
+
+        let count: i64 = Connection::open(&second)
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM t", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+        let uri: String = meta.query_row("SELECT sqlalchemy_uri FROM dbs WHERE id = 1", [], |r| r.get(0)).unwrap();
+        assert_eq!(uri, replica_uri(root, &second));
+        assert!(uri.starts_with("sqlite:///file:replicas/examples-"));
+
+        assert!(restore(root, "examples", databases::EXAMPLES_UUID, &source).unwrap());
+        let uri: String = meta.query_row("SELECT sqlalchemy_uri FROM dbs WHERE id = 1", [], |r| r.get(0)).unwrap();
+        assert_eq!(uri, format!("sqlite:///{}", source.to_string_lossy().replace('\\', "/")));
+        assert!(!second.exists());
+        // A connection already on the live file is left alone
+        assert!(!restore(root, "examples", databases::EXAMPLES_UUID, &source).unwrap());
+        let mine: String = meta.query_row("SELECT sqlalchemy_uri FROM dbs WHERE id = 2", [], |r| r.get(0)).unwrap();
+        assert_eq!(mine, "sqlite:///mine.db");
+    }
+}
@@ -0,0 +1,116 @@
+//! Request IDs for the gateway
+//!
+//! Every request through the gateway gets an `X-Request-Id` (a valid one from
+//! the client is kept). The ID goes to Superset with the proxied request,
+//! rides on the tracing span of the request so every gateway log line shows
+//! it, comes back as a response header, and is printed on the gateway's own
+//! error pages. Superset's `superset_config.py` puts the same header into its
+//! log lines, so one ID quoted by a user finds the request in both logs.
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use hyper::StatusCode;
+use tracing::{warn, Instrument};
+
+/// Header carrying the ID (the de-facto standard name Superset and proxies know)
+pub const HEADER: &str = "x-request-id";
+
+/// Longest client-supplied ID that is kept as is
+const MAX_LEN: usize = 64;
+
+/// New short ID: 12 hex digits, easy to read out over the phone
+pub fn generate() -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..12].to_string()
+}
+
+/// Client-supplied ID, if it is short and safe to put in logs
+pub fn from_headers(headers: &HeaderMap) -> Option<String> {
+    let id = headers.get(HEADER)?.to_str().ok()?.trim();
+    let valid = !id.is_empty()
+        && id.len() <= MAX_LEN
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+    valid.then(|| id.to_string())
+}
+
+/// Tag the request with an ID, run it inside a span carrying the ID, and
+/// turn bare gateway errors into a page that shows the ID
+pub async fn middleware(mut req: Request, next: Next) -> Response {
+    let id = from_headers(req.headers()).unwrap_or_else(generate);
+    let value = HeaderValue::from_str(&id).expect("request id is ASCII");
+    req.headers_mut().insert(HEADER, value.clone());
+
+    let wants_json = req.uri().path().starts_with("/api/")
+        || req
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.contains("application/json"));
+    let span = tracing::info_span!("request", id = %id, method = %req.method(), path = %req.uri().path());
+
+    let mut response = next.run(req).instrument(span.clone()).await;
+    let status = response.status();
+    // Errors from the gateway itself have no body; Superset's own pages are left alone
+    if status.is_server_error() && !response.headers().contains_key(header::CONTENT_TYPE) {
+        span.in_scope(|| warn!("Gateway error {}", status.as_u16()));
+        response = error_response(status, &id, wants_json);
+    }
+    response.headers_mut().insert(HEADER, value);
+    response
+}
+
+/// Error page (or JSON for API calls) quoting the request ID
+fn error_response(status: StatusCode, id: &str, json: bool) -> Response {
+    let message = match status {
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT => {
+            "Superset не отвечает. Подождите минуту и обновите страницу."
+        }
+        _ => "Внутренняя ошибка шлюза.",
+    };
+    if json {
+        return (status, Json(serde_json::json!({ "error": message, "request_id": id }))).into_response();
+    }
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="ru"><head><meta charset="utf-8"><title>Ошибка {code}</title></head>
+<body style="font-family: sans-serif; max-width: 40em; margin: 4em auto;">
+<h1>Ошибка {code}</h1>
+<p>{message}</p>
+<p>Если ошибка повторяется, сообщите в поддержку код запроса: <code>{id}</code></p>
+</body></html>"#,
+        code = status.as_u16(),
+        message = message,
+        id = id,
+    );
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+        .body(Body::from(html))
+        .unwrap_or_else(|_| status.into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(from_headers(&headers), None);
+        headers.insert(HEADER, HeaderValue::from_static("abc-123_x.y"));
+        assert_eq!(from_headers(&headers).as_deref(), Some("abc-123_x.y"));
+        headers.insert(HEADER, HeaderValue::from_static("bad id; drop table"));
+        assert_eq!(from_headers(&headers), None);
+        headers.insert(HEADER, HeaderValue::from_str(&"a".repeat(MAX_LEN + 1)).unwrap());
+        assert_eq!(from_headers(&headers), None);
+
+        let id = generate();
+        assert_eq!(id.len(), 12);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(id, generate());
+    }
+}
@@ -0,0 +1,98 @@
+//! Path checks for names that come from untrusted files
+//!
+//! Kits are fed archives and spreadsheets from USB sticks of unknown origin.
+//! An archive entry named `../../start_superset.bat` or `C:\Windows\...`
+//! (zip-slip), or an upload called `..\config.json`, must never land
+//! outside the folder it was meant for. Names are checked with both `/` and
+//! `\` as separators, whatever the host OS, since archives made on Windows
+//! are unpacked everywhere.
+
+use anyhow::{bail, Result};
+use std::path::{Path, PathBuf};
+
+/// Device names Windows refuses (or misroutes) as file names
+const WINDOWS_RESERVED: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8", "com9", "lpt1",
+    "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// Longest file name kept by [`file_name`]
+const MAX_FILE_NAME: usize = 120;
+
+/// Relative path for an archive entry or similar untrusted name.
+/// Fails on absolute paths, drive letters, `..` and empty names.
+pub fn relative(name: &str) -> Result<PathBuf> {
+    if name.contains('\0') {
+        bail!("Unsafe path (NUL byte): {:?}", name);
+    }
+    if name.starts_with('/') || name.starts_with('\\') {
+        bail!("Unsafe path (absolute): {}", name);
+    }
+    let mut out = PathBuf::new();
+    for part in name.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => bail!("Unsafe path (parent directory): {}", name),
+            // `C:` drive prefixes and NTFS alternate streams (`file:stream`)
+            _ if part.contains(':') => bail!("Unsafe path (drive or stream): {}", name),
+            _ => out.push(part),
+        }
+    }
+    if out.as_os_str().is_empty() {
+        bail!("Empty path: {:?}", name);
+    }
+    Ok(out)
+}
+
+/// `base` joined with the untrusted relative `name`
+pub fn join(base: &Path, name: &str) -> Result<PathBuf> {
+    Ok(base.join(relative(name)?))
+}
+
+/// Plain file name for an upload: directory parts dropped, characters
+/// Windows rejects replaced, reserved device names and dot-names defused.
+pub fn file_name(name: &str) -> String {
+    let leaf = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let mut clean: String = leaf
+        .chars()
+        .map(|c| if c.is_control() || "<>:\"|?*".contains(c) { '_' } else { c })
+        .collect();
+    clean = clean.trim().trim_start_matches('.').trim_end_matches(['.', ' ']).to_string();
+    if clean.chars().count() > MAX_FILE_NAME {
+        clean = clean.chars().take(MAX_FILE_NAME).collect();
+    }
+
+    let stem = clean.split('.').next().unwrap_or_default().to_lowercase();
+    if WINDOWS_RESERVED.contains(&stem.as_str()) {
+        clean = format!("_{}", clean);
+    }
+    if clean.is_empty() {
+        clean = "upload".to_string();
+    }
+    clean
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_rejects_escapes() {
+        assert_eq!(relative("docs/a.md").unwrap(), Path::new("docs").join("a.md"));
+        assert_eq!(relative("./docs\\b.md").unwrap(), Path::new("docs").join("b.md"));
+        for bad in ["../evil.bat", "docs/../../evil", "..\\..\\evil.exe", "/etc/passwd", "\\\\server\\share", "C:\\Windows\\x.dll", "c:evil", "a.txt:stream", "", "./", "a\0b"] {
+            assert!(relative(bad).is_err(), "{:?} accepted", bad);
+        }
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("../../config.json"), "config.json");
+        assert_eq!(file_name("..\\..\\start_superset.bat"), "start_superset.bat");
+        assert_eq!(file_name("C:\\Users\\me\\отчёт.xlsx"), "отчёт.xlsx");
+        assert_eq!(file_name("data.csv:hidden"), "data.csv_hidden");
+        assert_eq!(file_name("CON.csv"), "_CON.csv");
+        assert_eq!(file_name(".."), "upload");
+        assert_eq!(file_name(".htaccess"), "htaccess");
+    }
+}
@@ -0,0 +1,268 @@
+//! SQL Lab saved queries import/export
+//!
+//! Exports `saved_query` rows from superset.db as `<name>.sql` files with a
+//! `<name>.yaml` sidecar (label, database, schema, uuid, ...), so analyst SQL
+//! can be versioned in the knowledge folder and moved between kits.
+//! Import upserts by UUID, so re-importing the same folder is idempotent:
+//! export gives every query that has none a UUID first. Imported queries
+//! belong to the first Superset admin.
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::lightdocs::WikilinksTransformer;
+
+/// Default export folder (relative to root)
+pub const DEFAULT_QUERIES_DIR: &str = "knowledge/sql";
+
+/// Sidecar metadata stored next to each .sql file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedQueryMeta {
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Database connection name (`dbs.database_name`), e.g. `examples`
+    pub database: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    /// Stable identity used to match queries on import
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template_parameters: Option<String>,
+}
+
+/// Result of an import run
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub inserted: usize,
+    pub updated: usize,
+    pub skipped: usize,
+}
+
+fn open_superset_db(root: &Path) -> Result<Connection> {
+    let db_path = root.join("superset_home").join("superset.db");
+    if !db_path.exists() {
+        bail!("superset.db not found at {}", db_path.display());
+    }
+    Connection::open(&db_path).with_context(|| format!("Failed to open {}", db_path.display()))
+}
+
+/// Export all saved queries into `dir`, returns number of queries written
+pub fn export(root: &Path, dir: &Path) -> Result<usize> {
+    let conn = open_superset_db(root)?;
+    fs::create_dir_all(dir)?;
+
+    let rows: Vec<(i64, SavedQueryMeta, String)> = conn
+        .prepare(
+            "SELECT q.id, q.label, q.description, d.database_name, q.schema, q.uuid, q.template_parameters, q.sql
+             FROM saved_query q JOIN dbs d ON d.id = q.db_id
+             ORDER BY q.id",
+        )?
+        .query_map([], |row| {
+            Ok((
+                row.get(0)?,
+                SavedQueryMeta {
+                    label: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    description: row.get::<_, Option<String>>(2)?.filter(|s| !s.is_empty()),
+                    database: row.get(3)?,
+                    schema: row.get::<_, Option<String>>(4)?.filter(|s| !s.is_empty()),
+                    uuid: row.get(5)?,
+                    template_parameters: row.get::<_, Option<String>>(6)?.filter(|s| !s.is_empty()),
+                },
+                row.get::<_, Option<String>>(7)?.unwrap_or_default(),
+            ))
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut used_names = HashSet::new();
+    let mut count = 0;
+    for (id, mut meta, sql) in rows {
+        // Without a UUID the next import couldn't find the query again
+        if meta.uuid.is_none() {
+            let uuid = Uuid::new_v4();
+            conn.execute("UPDATE saved_query SET uuid = ?1 WHERE id = ?2", params![uuid, id])?;
+            meta.uuid = Some(uuid);
+        }
+        let name = file_stem_for(&meta, &mut used_names);
+        fs::write(dir.join(format!("{}.sql", name)), &sql)?;
+        fs::write(dir.join(format!("{}.yaml", name)), serde_yaml::to_string(&meta)?)?;
+        info!("  📝 {} -> {}.sql", meta.label, name);
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+/// Unique file name for a query, derived from its label
+fn file_stem_for(meta: &SavedQueryMeta, used: &mut HashSet<String>) -> String {
+    let mut base = WikilinksTransformer::title_to_slug(&meta.label);
+    if base.is_empty() {
+        base = "query".to_string();
+    }
+
+    let mut name = base.clone();
+    if used.contains(&name) {
+        name = match meta.uuid {
+            Some(uuid) => format!("{}-{}", base, &uuid.simple().to_string()[..8]),
+            None => format!("{}-{}", base, used.len()),
+        };
+    }
+    used.insert(name.clone());
+    name
+}
+
+/// Import every `*.yaml` + `*.sql` pair from `dir`
+pub fn import(root: &Path, dir: &Path) -> Result<ImportSummary> {
+    let mut conn = open_superset_db(root)?;
+    let tx = conn.transaction()?;
+    let mut summary = ImportSummary::default();
+    let now = Utc::now().format("%Y-%m-%d %H:%M:%S%.6f").to_string();
+    let owner: i64 = tx
+        .query_row(
+            "SELECT u.id FROM ab_user u JOIN ab_user_role ur ON ur.user_id = u.id JOIN ab_role r ON r.id = ur.role_id
+             WHERE r.name = 'Admin' ORDER BY u.id LIMIT 1",
+            [],
+            |r| r.get(0),
+        )
+        .optional()?
+        .context("Superset has no admin user to own the queries (run: superset-launcher init)")?;
+
+    for (meta_path, sql_path) in query_files(dir)? {
+        let meta: SavedQueryMeta = serde_yaml::from_str(&fs::read_to_string(&meta_path)?)
+            .with_context(|| format!("Invalid sidecar: {}", meta_path.display()))?;
+        let sql = fs::read_to_string(&sql_path)
+            .with_context(|| format!("Missing SQL file: {}", sql_path.display()))?;
+
+        let db_id: Option<i64> = tx
+            .query_row("SELECT id FROM dbs WHERE database_name = ?1", [&meta.database], |r| r.get(0))
+            .optional()?;
+        let Some(db_id) = db_id else {
+            warn!("  ⚠️ {}: database '{}' not found, skipped", meta.label, meta.database);
+            summary.skipped += 1;
+            continue;
+        };
+
+        // Hand-written sidecars may lack one: the same file must map to the same query
+        let uuid = meta.uuid.unwrap_or_else(|| {
+            let key = format!("{}/{}", meta.database, meta_path.file_stem().unwrap_or_default().to_string_lossy());
+            Uuid::new_v5(&Uuid::NAMESPACE_URL, key.as_bytes())
+        });
+        let existing: Option<i64> = tx
+            .query_row("SELECT id FROM saved_query WHERE uuid = ?1", [uuid], |r| r.get(0))
+            .optional()?;
+
+        match existing {
+            Some(id) => {
+                tx.execute(
+                    "UPDATE saved_query SET label = ?1, description = ?2, db_id = ?3, schema = ?4,
+                     sql = ?5, template_parameters = ?6, changed_on = ?7 WHERE id = ?8",
+                    params![meta.label, meta.description, db_id, meta.schema, sql, meta.template_parameters, now, id],
+                )?;
+                summary.updated += 1;
+            }
+            None => {
+                tx.execute(
+                    "INSERT INTO saved_query (label, description, db_id, schema, sql, template_parameters, uuid,
+                     user_id, created_by_fk, changed_by_fk, created_on, changed_on)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8, ?8, ?9, ?9)",
+                    params![meta.label, meta.description, db_id, meta.schema, sql, meta.template_parameters, uuid, owner, now],
+                )?;
+                summary.inserted += 1;
+            }
+        }
+        info!("  📥 {}", meta.label);
+    }
+
+    tx.commit()?;
+    Ok(summary)
+}
+
+/// (sidecar, sql) pairs in `dir`, sorted by name
+fn query_files(dir: &Path) -> Result<Vec<(PathBuf, PathBuf)>> {
+    if !dir.exists() {
+        bail!("Folder not found: {}", dir.display());
+    }
+
+    let mut pairs: Vec<(PathBuf, PathBuf)> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().map_or(false, |ext| ext == "yaml" || ext == "yml"))
+        .map(|p| {
+            let sql = p.with_extension("sql");
+            (p, sql)
+        })
+        .collect();
+    pairs.sort();
+    Ok(pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn setup_db(root: &Path) -> Connection {
+        fs::create_dir_all(root.join("superset_home")).unwrap();
+        let conn = Connection::open(root.join("superset_home").join("superset.db")).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE dbs (id INTEGER PRIMARY KEY, database_name TEXT);
+             CREATE TABLE saved_query (id INTEGER PRIMARY KEY, label TEXT, description TEXT, db_id INTEGER,
+                schema TEXT, sql TEXT, template_parameters TEXT, uuid BLOB, user_id INTEGER,
+                created_by_fk INTEGER, changed_by_fk INTEGER, created_on DATETIME, changed_on DATETIME);
+             CREATE TABLE ab_user (id INTEGER PRIMARY KEY);
+             CREATE TABLE ab_role (id INTEGER PRIMARY KEY, name TEXT);
+             CREATE TABLE ab_user_role (id INTEGER PRIMARY KEY, user_id INTEGER, role_id INTEGER);
+             INSERT INTO ab_user VALUES (3), (7);
+             INSERT INTO ab_role VALUES (1, 'Admin'), (2, 'Gamma');
+             INSERT INTO ab_user_role (user_id, role_id) VALUES (3, 2), (7, 1);
+             INSERT INTO dbs VALUES (1, 'examples');",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_export_import_roundtrip() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let conn = setup_db(root);
+        let uuid = Uuid::new_v4();
+        conn.execute(
+            "INSERT INTO saved_query (label, db_id, sql, uuid) VALUES ('Выручка', 1, 'SELECT 1', ?1)",
+            [uuid],
+        )
+        .unwrap();
+        conn.execute("INSERT INTO saved_query (label, db_id, sql) VALUES ('Выручка', 1, 'SELECT 2')", [])
+            .unwrap();
+
+        let out = root.join("sql");
+        assert_eq!(export(root, &out).unwrap(), 2);
+        assert_eq!(fs::read_to_string(out.join("выручка.sql")).unwrap(), "SELECT 1");
+
+        fs::write(out.join("выручка.sql"), "SELECT 42").unwrap();
+        let summary = import(root, &out).unwrap();
+        assert_eq!(summary.updated, 2, "export gave the second query a uuid");
+        assert_eq!(summary.inserted, 0);
+
+        // A hand-written sidecar without a uuid is inserted once, owned by the admin
+        fs::write(out.join("new.yaml"), "label: Новый\ndatabase: examples\n").unwrap();
+        fs::write(out.join("new.sql"), "SELECT 3").unwrap();
+        assert_eq!(import(root, &out).unwrap().inserted, 1);
+        assert_eq!(import(root, &out).unwrap().inserted, 0);
+        let owner: i64 = conn.query_row("SELECT user_id FROM saved_query WHERE label = 'Новый'", [], |r| r.get(0)).unwrap();
+        assert_eq!(owner, 7);
+
+        let sql: String = conn
+            .query_row("SELECT sql FROM saved_query WHERE uuid = ?1", [uuid], |r| r.get(0))
+            .unwrap();
+        assert_eq!(sql, "SELECT 42");
+    }
+}
@@ -0,0 +1,493 @@
+//! Cron schedules of the launcher's background jobs (`schedules.json`)
+//!
+//! ```json
+//! {
+//!   "backup": {"cron": "30 1 * * 1-5", "enabled": true},
+//!   "alert:Нет продаж": {"cron": "*/15 8-20 * * *", "enabled": false}
+//! }
+//! ```
+//!
+//! Jobs are the nightly backup (`backup`) and every rule of alerts.yaml
+//! (`alert:<name>`). A job without an entry keeps its built-in timing
+//! (`backup.hour` in config.json, `every_minutes` of the rule); an entry
+//! replaces it with a standard five-field cron expression in local time and
+//! can switch the job off. The launcher panel («🗓️ Расписания») edits this
+//! file, shows each schedule in words with its next five runs, and the
+//! schedulers re-read it every minute, so changes apply without a restart.
+
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::backup::BackupConfig;
+use crate::ui_strings::Lang;
+
+pub const SCHEDULES_FILE: &str = "schedules.json";
+
+pub const BACKUP_JOB: &str = "backup";
+
+/// Runs listed by the panel for each job
+pub const PREVIEW_RUNS: usize = 5;
+
+/// How far ahead a run is looked for (an expression like `0 0 30 2 *` never runs)
+const SEARCH_DAYS: u32 = 366 * 5;
+
+const MONTHS: &[&str] = &["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+const WEEKDAYS: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// Job id of an alerts.yaml rule
+pub fn alert_job(name: &str) -> String {
+    format!("alert:{}", name)
+}
+
+/// One field of a cron expression
+#[derive(Debug, Clone, PartialEq)]
+struct Field {
+    /// Matching values, ascending
+    values: Vec<u32>,
+    /// Written as `*`: every value
+    any: bool,
+    /// Written as `*/n`
+    step: Option<u32>,
+}
+
+impl Field {
+    fn parse(text: &str, min: u32, max: u32, names: &[&str]) -> Result<Self> {
+        let value = |token: &str| -> Result<u32> {
+            let lower = token.to_lowercase();
+            let value = match names.iter().position(|name| *name == lower) {
+                Some(index) => index as u32 + min,
+                None => token.parse().with_context(|| format!("'{}' is not a number", token))?,
+            };
+            if !(min..=max).contains(&value) {
+                bail!("{} is out of range {}-{}", value, min, max);
+            }
+            Ok(value)
+        };
+
+        let mut values = BTreeSet::new();
+        for item in text.split(',') {
+            let (range, step) = match item.split_once('/') {
+                Some((range, step)) => {
+                    let step = step.parse::<u32>().ok().filter(|s| *s > 0).with_context(|| format!("Bad step in '{}'", item))?;
+                    (range, Some(step))
+                }
+                None => (item, None),
+            };
+            let (from, to) = if range == "*" {
+                (min, max)
+            } else if let Some((from, to)) = range.split_once('-') {
+                (value(from)?, value(to)?)
+            } else {
+                // `5/15` runs from 5 to the end of the range
+                let from = value(range)?;
+                (from, if step.is_some() { max } else { from })
+            };
+            if from > to {
+                bail!("Range '{}' goes backwards", range);
+            }
+            values.extend((from..=to).step_by(step.unwrap_or(1) as usize));
+        }
+
+        let step = text.strip_prefix("*/").and_then(|s| s.parse::<u32>().ok());
+        Ok(Self {
+            values: values.into_iter().collect(),
+            any: text == "*" || step == Some(1),
+            step: step.filter(|s| *s > 1),
+        })
+    }
+
+    fn single(&self) -> Option<u32> {
+        match self.values[..] {
+            [value] => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Parsed five-field cron expression: minute, hour, day of month, month, day of week
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cron {
+    minute: Field,
+    hour: Field,
+    day: Field,
+    month: Field,
+    /// 0 = Sunday (7 is accepted too)
+    weekday: Field,
+}
+
+impl FromStr for Cron {
+    type Err = anyhow::Error;
+
+    fn from_str(expr: &str) -> Result<Self> {
+        let expr = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            bail!("Expected 5 fields (minute hour day month weekday), got {}", fields.len());
+        };
+        let mut weekday = Field::parse(weekday, 0, 7, WEEKDAYS).with_context(|| format!("Invalid weekday '{}'", weekday))?;
+        if weekday.values.last() == Some(&7) {
+            weekday.values.pop();
+            if weekday.values.first() != Some(&0) {
+                weekday.values.insert(0, 0);
+            }
+        }
+        Ok(Self {
+            minute: Field::parse(minute, 0, 59, &[]).with_context(|| format!("Invalid minute '{}'", minute))?,
+            hour: Field::parse(hour, 0, 23, &[]).with_context(|| format!("Invalid hour '{}'", hour))?,
+            day: Field::parse(day, 1, 31, &[]).with_context(|| format!("Invalid day of month '{}'", day))?,
+            month: Field::parse(month, 1, 12, MONTHS).with_context(|| format!("Invalid month '{}'", month))?,
+            weekday,
+        })
+    }
+}
+
+impl Cron {
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        if !self.month.values.contains(&date.month()) {
+            return false;
+        }
+        let day = self.day.values.contains(&date.day());
+        let weekday = self.weekday.values.contains(&date.weekday().num_days_from_sunday());
+        // As in cron: with both day fields restricted, either one is enough
+        if !self.day.any && !self.weekday.any {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+
+    /// First run strictly after `after`
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut date = after.date();
+        for _ in 0..SEARCH_DAYS {
+            if self.day_matches(date) {
+                for &hour in &self.hour.values {
+                    for &minute in &self.minute.values {
+                        let at = date.and_hms_opt(hour, minute, 0)?;
+                        if at > after {
+                            return Some(at);
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+
+    /// The next `count` runs after `after`
+    pub fn upcoming(&self, after: NaiveDateTime, count: usize) -> Vec<NaiveDateTime> {
+        std::iter::successors(self.next_after(after), |at| self.next_after(*at)).take(count).collect()
+    }
+
+    /// The schedule in words, e.g. «ежедневно в 03:00» or «every 15 minutes, on weekdays»
+    pub fn describe(&self, lang: Lang) -> String {
+        let ru = lang == Lang::Ru;
+        let time = self.describe_time(ru);
+        match self.describe_days(ru) {
+            Some(days) => format!("{}, {}", time, days),
+            None if self.hour.any || self.hour.step.is_some() => time,
+            None if ru => format!("ежедневно {}", time),
+            None => format!("daily {}", time),
+        }
+    }
+
+    fn describe_time(&self, ru: bool) -> String {
+        let (minute, hour) = (&self.minute, &self.hour);
+        if minute.any && hour.any {
+            return if ru { "каждую минуту" } else { "every minute" }.to_string();
+        }
+        if hour.any {
+            return match (minute.step, minute.single()) {
+                (Some(n), _) if ru => format!("каждые {} мин", n),
+                (Some(n), _) => format!("every {} minutes", n),
+                (None, Some(m)) if ru => format!("каждый час в :{:02}", m),
+                (None, Some(m)) => format!("every hour at :{:02}", m),
+                _ if ru => format!("в {} мин каждого часа", compact(&minute.values)),
+                _ => format!("at minutes {} of every hour", compact(&minute.values)),
+            };
+        }
+        if let (Some(n), Some(m)) = (hour.step, minute.single()) {
+            return if ru { format!("каждые {} ч в :{:02}", n, m) } else { format!("every {} hours at :{:02}", n, m) };
+        }
+        if !minute.any && minute.step.is_none() && minute.values.len() * hour.values.len() <= 6 {
+            let times: Vec<String> =
+                hour.values.iter().flat_map(|h| minute.values.iter().map(move |m| format!("{:02}:{:02}", h, m))).collect();
+            return format!("{} {}", if ru { "в" } else { "at" }, times.join(", "));
+        }
+        let hours = compact(&hour.values);
+        match minute.step {
+            _ if minute.any && ru => format!("каждую минуту в часы {}", hours),
+            _ if minute.any => format!("every minute during hours {}", hours),
+            Some(n) if ru => format!("каждые {} мин в часы {}", n, hours),
+            Some(n) => format!("every {} minutes during hours {}", n, hours),
+            None if ru => format!("в {} мин в часы {}", compact(&minute.values), hours),
+            None => format!("at minutes {} of hours {}", compact(&minute.values), hours),
+        }
+    }
+
+    fn describe_days(&self, ru: bool) -> Option<String> {
+        let weekdays = match self.weekday.values[..] {
+            _ if self.weekday.any => None,
+            [1, 2, 3, 4, 5] => Some(if ru { "по будням" } else { "on weekdays" }.to_string()),
+            [0, 6] => Some(if ru { "по выходным" } else { "on weekends" }.to_string()),
+            _ => {
+                let names: &[&str] =
+                    if ru { &["вс", "пн", "вт", "ср", "чт", "пт", "сб"] } else { &["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"] };
+                let list: Vec<&str> = self.weekday.values.iter().map(|d| names[*d as usize]).collect();
+                Some(format!("{} {}", if ru { "по" } else { "on" }, list.join(", ")))
+            }
+        };
+        let days = (!self.day.any).then(|| {
+            if ru {
+                format!("{} числа", compact(&self.day.values))
+            } else {
+                format!("on day {}", compact(&self.day.values))
+            }
+        });
+        let months = (!self.month.any).then(|| {
+            let names: &[&str] = if ru {
+                &["январе", "феврале", "марте", "апреле", "мае", "июне", "июле", "августе", "сентябре", "октябре", "ноябре", "декабре"]
+            } else {
+                &["January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December"]
+            };
+            let list: Vec<&str> = self.month.values.iter().map(|m| names[*m as usize - 1]).collect();
+            format!("{} {}", if ru { "в" } else { "in" }, list.join(", "))
+        });
+
+        let days = match (days, weekdays) {
+            (Some(days), Some(weekdays)) => Some(format!("{} {} {}", days, if ru { "или" } else { "or" }, weekdays)),
+            (days, weekdays) => days.or(weekdays),
+        };
+        match (days, months) {
+            (Some(days), Some(months)) => Some(format!("{} {}", days, months)),
+            (days, months) => days.or(months),
+        }
+    }
+}
+
+/// `1,2,3,4,10` as `1-4,10`
+fn compact(values: &[u32]) -> String {
+    let mut parts = Vec::new();
+    let mut at = 0;
+    while at < values.len() {
+        let mut end = at;
+        while end + 1 < values.len() && values[end + 1] == values[end] + 1 {
+            end += 1;
+        }
+        parts.push(match end - at {
+            0 => values[at].to_string(),
+            1 => format!("{},{}", values[at], values[end]),
+            _ => format!("{}-{}", values[at], values[end]),
+        });
+        at = end + 1;
+    }
+    parts.join(",")
+}
+
+/// Entry of `schedules.json`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Schedule {
+    pub cron: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Schedule {
+    /// True when the job is on and a run falls in `(since, now]`
+    pub fn is_due(&self, since: NaiveDateTime, now: NaiveDateTime) -> bool {
+        self.enabled
+            && self.cron.parse::<Cron>().ok().and_then(|cron| cron.next_after(since)).is_some_and(|at| at <= now)
+    }
+}
+
+/// Entries of the kit; empty (with a warning) when the file is broken
+pub fn load(root: &Path) -> BTreeMap<String, Schedule> {
+    let path = root.join(SCHEDULES_FILE);
+    if !path.exists() {
+        return BTreeMap::new();
+    }
+    match std::fs::read_to_string(&path).map_err(anyhow::Error::from).and_then(|text| Ok(serde_json::from_str(&text)?)) {
+        Ok(schedules) => schedules,
+        Err(e) => {
+            tracing::warn!("{} ignored: {:#}", path.display(), e);
+            BTreeMap::new()
+        }
+    }
+}
+
+/// Set the entry of `job`; `None` brings back its built-in timing
+pub fn set(root: &Path, job: &str, schedule: Option<Schedule>) -> Result<()> {
+    let mut schedules = load(root);
+    match schedule {
+        Some(schedule) => {
+            schedule.cron.parse::<Cron>()?;
+            schedules.insert(job.to_string(), schedule);
+        }
+        None => {
+            schedules.remove(job);
+        }
+    }
+    // Written whole and renamed: the schedulers read it every minute
+    let tmp = root.join(format!("{}.tmp", SCHEDULES_FILE));
+    std::fs::write(&tmp, serde_json::to_string_pretty(&schedules)?)?;
+    std::fs::rename(&tmp, root.join(SCHEDULES_FILE))?;
+    Ok(())
+}
+
+/// A cron expression in words with its next runs
+#[derive(Debug, Clone, Serialize)]
+pub struct Preview {
+    pub description: String,
+    /// Local time, `2026-10-16T03:00:00`
+    pub next: Vec<String>,
+}
+
+pub fn preview(expr: &str, lang: Lang, now: NaiveDateTime) -> Result<Preview> {
+    let cron: Cron = expr.parse()?;
+    Ok(Preview {
+        description: cron.describe(lang),
+        next: cron.upcoming(now, PREVIEW_RUNS).iter().map(|at| at.format("%Y-%m-%dT%H:%M:%S").to_string()).collect(),
+    })
+}
+
+/// A job as the panel shows it
+#[derive(Debug, Clone, Serialize)]
+pub struct JobView {
+    pub job: String,
+    pub title: String,
+    /// Expression in `schedules.json`, or the one matching the built-in timing
+    pub cron: Option<String>,
+    /// The timing comes from `schedules.json`
+    pub custom: bool,
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub preview: Preview,
+}
+
+/// `every_minutes` of an alert rule as cron, when it divides an hour or a day
+fn every_minutes_cron(minutes: u64) -> Option<String> {
+    match minutes.max(1) {
+        1 => Some("* * * * *".to_string()),
+        m if 60 % m == 0 => Some(format!("*/{} * * * *", m)),
+        m if m % 60 == 0 && 24 % (m / 60) == 0 => Some(format!("0 */{} * * *", m / 60)),
+        _ => None,
+    }
+}
+
+/// Every job of the kit with its current timing
+pub fn jobs(root: &Path, backup: &BackupConfig, lang: Lang, now: NaiveDateTime) -> Result<Vec<JobView>> {
+    let ru = lang == Lang::Ru;
+    let mut builtin = vec![(
+        BACKUP_JOB.to_string(),
+        if ru { "Резервная копия" } else { "Backup" }.to_string(),
+        Some(format!("0 {} * * *", backup.hour)),
+        backup.enabled,
+        0,
+    )];
+    for rule in crate::alerts::load_rules(root)? {
+        let title = if ru { format!("Оповещение «{}»", rule.name) } else { format!("Alert “{}”", rule.name) };
+        builtin.push((alert_job(&rule.name), title, every_minutes_cron(rule.every_minutes), true, rule.every_minutes));
+    }
+
+    let schedules = load(root);
+    Ok(builtin
+        .into_iter()
+        .map(|(job, title, default_cron, default_enabled, every_minutes)| {
+            let custom = schedules.get(&job);
+            let cron = custom.map(|s| s.cron.clone()).or(default_cron);
+            let preview = match &cron {
+                Some(cron) => preview(cron, lang, now).unwrap_or_else(|e| Preview { description: format!("{:#}", e), next: Vec::new() }),
+                // Every N minutes since the previous check: no fixed times to show
+                None => Preview {
+                    description: if ru { format!("каждые {} мин", every_minutes) } else { format!("every {} minutes", every_minutes) },
+                    next: Vec::new(),
+                },
+            };
+            JobView {
+                title,
+                cron,
+                custom: custom.is_some(),
+                enabled: custom.map_or(default_enabled, |s| s.enabled),
+                preview,
+                job,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn at(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_cron_next_runs_and_words() {
+        // 2026-10-16 is a Friday
+        let now = at("2026-10-16 10:07");
+        let weekdays: Cron = "30 1 * * mon-fri".parse().unwrap();
+        assert_eq!(weekdays.upcoming(now, 2), [at("2026-10-19 01:30"), at("2026-10-20 01:30")]);
+        assert_eq!(weekdays.describe(Lang::Ru), "в 01:30, по будням");
+        assert_eq!(weekdays.describe(Lang::En), "at 01:30, on weekdays");
+
+        let quarter: Cron = "*/15 * * * *".parse().unwrap();
+        assert_eq!(quarter.next_after(now), Some(at("2026-10-16 10:15")));
+        assert_eq!(quarter.describe(Lang::Ru), "каждые 15 мин");
+        assert_eq!("0 3 * * *".parse::<Cron>().unwrap().describe(Lang::En), "daily at 03:00");
+        assert_eq!("0 9 1,15 * 0".parse::<Cron>().unwrap().describe(Lang::Ru), "в 09:00, 1,15 числа или по вс");
+        // Sunday as 7, and either day field matching
+        assert_eq!("0 0 13 * 7".parse::<Cron>().unwrap().next_after(now), Some(at("2026-10-18 00:00")));
+        assert_eq!("0 0 30 2 *".parse::<Cron>().unwrap().next_after(now), None);
+
+        assert!("* * *".parse::<Cron>().is_err());
+        assert!("61 * * * *".parse::<Cron>().is_err());
+        assert!("0 5-1 * * *".parse::<Cron>().is_err());
+        assert_eq!(compact(&[1, 2, 3, 4, 10, 11]), "1-4,10,11");
+    }
+
+    #[test]
+    fn test_overlay_replaces_builtin_timing() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let now = at("2026-10-16 10:07");
+        let backup = BackupConfig::default();
+
+        let jobs_now = |lang| jobs(root, &backup, lang, now).unwrap();
+        let builtin = &jobs_now(Lang::Ru)[0];
+        assert_eq!((builtin.cron.as_deref(), builtin.custom, builtin.enabled), (Some("0 3 * * *"), false, true));
+        assert_eq!(builtin.preview.next[0], "2026-10-17T03:00:00");
+
+        let schedule = Schedule { cron: "0 22 * * 5".to_string(), enabled: false };
+        set(root, BACKUP_JOB, Some(schedule.clone())).unwrap();
+        let custom = &jobs_now(Lang::En)[0];
+        assert_eq!((custom.custom, custom.enabled), (true, false));
+        assert_eq!(custom.preview.description, "at 22:00, on Fri");
+        assert!(!schedule.is_due(now, at("2026-10-16 22:00")));
+        assert!(Schedule { enabled: true, ..schedule }.is_due(now, at("2026-10-16 22:00")));
+
+        assert!(set(root, BACKUP_JOB, Some(Schedule { cron: "nope".to_string(), enabled: true })).is_err());
+        set(root, BACKUP_JOB, None).unwrap();
+        assert!(load(root).is_empty());
+        assert_eq!(every_minutes_cron(120).as_deref(), Some("0 */2 * * *"));
+        assert_eq!(every_minutes_cron(45), None);
+    }
+}
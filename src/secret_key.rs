@@ -0,0 +1,234 @@
+//! Superset `SECRET_KEY` safekeeping and rotation
+//!
+//! Superset encrypts the passwords and secure extras of database connections
+//! in superset.db with `SECRET_KEY` from `superset_config.py`. A config that
+//! is deleted and regenerated gets a new key, and every stored connection
+//! password silently stops decrypting. The launcher therefore keeps a copy of
+//! the key in the credential store, writes that copy (not a new key) into a
+//! recreated config, warns loudly when the config's key no longer matches it,
+//! and changes the key only through `rotate-secret`, which re-encrypts the
+//! stored secrets with Superset's own `re-encrypt-secrets`.
+
+use anyhow::{bail, Context, Result};
+use rusqlite::{Connection, OpenFlags};
+use std::path::{Path, PathBuf};
+use tracing::{error, info, warn};
+
+use crate::credentials::{self, CredentialStore};
+use crate::python::PythonEnv;
+
+/// Assignment holding the current key
+const KEY_NAME: &str = "SECRET_KEY";
+
+/// Assignment Superset reads the old key from during re-encryption
+const PREVIOUS_KEY_NAME: &str = "PREVIOUS_SECRET_KEY";
+
+/// State of the config's key against the stored copy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyStatus {
+    /// No superset_config.py, or no `SECRET_KEY` in it
+    NoConfig,
+    /// Key in the config, no copy stored yet
+    NotBackedUp,
+    /// Config and copy agree
+    BackedUp,
+    /// Config key differs from the copy: encrypted passwords may be unreadable
+    Mismatch,
+}
+
+pub fn config_path(root: &Path) -> PathBuf {
+    root.join("superset_home").join("superset_config.py")
+}
+
+/// Value of the top-level `name = "..."` assignment in a config
+pub(crate) fn read_assignment(config: &str, name: &str) -> Option<String> {
+    config.lines().find_map(|line| {
+        let (left, right) = line.split_once('=')?;
+        if left.trim() != name || line.starts_with([' ', '\t']) {
+            return None;
+        }
+        let value = right.trim();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let value = &value[1..];
+        Some(value[..value.find(quote)?].to_string())
+    })
+}
+
+/// Config with `name = "value"` set (replaced in place or appended), or
+/// removed when `value` is `None`
+pub(crate) fn set_assignment(config: &str, name: &str, value: Option<&str>) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut found = false;
+    for line in config.lines() {
+        let is_target = line.split_once('=').is_some_and(|(left, _)| left.trim() == name) && !line.starts_with([' ', '\t']);
+        if !is_target {
+            lines.push(line.to_string());
+        } else if let (Some(value), false) = (value, found) {
+            lines.push(format!("{} = \"{}\"", name, value));
+            found = true;
+        }
+    }
+    if let (Some(value), false) = (value, found) {
+        lines.push(format!("{} = \"{}\"", name, value));
+    }
+    lines.join("\n") + "\n"
+}
+
+/// `SECRET_KEY` of the kit's superset_config.py
+pub fn current(root: &Path) -> Option<String> {
+    let config = std::fs::read_to_string(config_path(root)).ok()?;
+    read_assignment(&config, KEY_NAME)
+}
+
+/// Connections in superset.db with an encrypted password or secure extra
+pub fn encrypted_connections(root: &Path) -> Result<usize> {
+    let db_path = root.join("superset_home").join("superset.db");
+    if !db_path.exists() {
+        return Ok(0);
+    }
+    let conn = Connection::open_with_flags(&db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let count: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM dbs WHERE (password IS NOT NULL AND password != '')
+                OR (encrypted_extra IS NOT NULL AND encrypted_extra != '')",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap_or_default();
+    Ok(count as usize)
+}
+
+/// Compare the config's key with the stored copy
+pub fn status(root: &Path, store: &CredentialStore) -> Result<KeyStatus> {
+    let Some(key) = current(root) else {
+        return Ok(KeyStatus::NoConfig);
+    };
+    Ok(match store.get(credentials::SUPERSET_SECRET_KEY)? {
+        None => KeyStatus::NotBackedUp,
+        Some(stored) if credentials::secrets_match(&stored, &key) => KeyStatus::BackedUp,
+        Some(_) => KeyStatus::Mismatch,
+    })
+}
+
+/// Store a copy of the key if there is none yet, and shout about a mismatch.
+/// Called before every Superset start; never changes the config.
+pub fn ensure_backed_up(root: &Path, store: &CredentialStore) -> Result<KeyStatus> {
+    let status = status(root, store)?;
+    match status {
+        KeyStatus::NotBackedUp => {
+            if let Some(key) = current(root) {
+                store.set(credentials::SUPERSET_SECRET_KEY, &key)?;
+                info!("🔐 SECRET_KEY backed up to {}", store.describe());
+            }
+        }
+        KeyStatus::Mismatch => {
+            error!("🚨 SECRET_KEY in superset_config.py differs from the saved copy!");
+            error!("   Passwords of database connections saved with the old key can't be decrypted.");
+            error!("   If superset_config.py was recreated by mistake, put the saved key back");
+            error!("   (superset-launcher user show-secret-key). Change keys only with: superset-launcher rotate-secret");
+        }
+        KeyStatus::NoConfig | KeyStatus::BackedUp => {}
+    }
+    Ok(status)
+}
+
+/// Key for a superset_config.py being created: the saved copy if there is
+/// one, otherwise `generate()`. Warns when superset.db already holds
+/// secrets a new key can't read.
+pub fn key_for_new_config(root: &Path, store: &CredentialStore, generate: impl FnOnce() -> String) -> Result<String> {
+    if let Some(key) = store.get(credentials::SUPERSET_SECRET_KEY)? {
+        info!("🔐 Reusing the saved SECRET_KEY for the new superset_config.py");
+        return Ok(key);
+    }
+    let encrypted = encrypted_connections(root).unwrap_or_default();
+    if encrypted > 0 {
+        warn!(
+            "🚨 New SECRET_KEY generated, but superset.db has {} connection(s) with saved passwords: \
+             they will have to be re-entered",
+            encrypted
+        );
+    }
+    let key = generate();
+    store.set(credentials::SUPERSET_SECRET_KEY, &key)?;
+    Ok(key)
+}
+
+/// Replace the key with `new_key` and re-encrypt every stored secret.
+/// Superset must be stopped. The config is restored if re-encryption fails.
+/// Returns the number of connections with encrypted secrets.
+pub fn rotate(root: &Path, python_env: &PythonEnv, store: &CredentialStore, new_key: &str) -> Result<usize> {
+    let path = config_path(root);
+    let original = std::fs::read_to_string(&path).with_context(|| format!("Cannot read {}", path.display()))?;
+    let Some(old_key) = read_assignment(&original, KEY_NAME) else {
+        bail!("No SECRET_KEY in {}", path.display());
+    };
+
+    let backup = path.with_file_name(format!(
+        "superset_config.py.bak-{}",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    std::fs::copy(&path, &backup)?;
+    store.set(credentials::SUPERSET_SECRET_KEY_PREVIOUS, &old_key)?;
+    info!("💾 Old config saved to {}", backup.display());
+
+    let staged = set_assignment(&set_assignment(&original, KEY_NAME, Some(new_key)), PREVIOUS_KEY_NAME, Some(&old_key));
+    std::fs::write(&path, staged)?;
+
+    info!("🔁 Re-encrypting stored secrets...");
+    let output = python_env.run_python(&["-m", "superset", "re-encrypt-secrets"]);
+    let failure = match &output {
+        Ok(out) if out.status.success() => None,
+        Ok(out) => Some(String::from_utf8_lossy(&out.stderr).to_string()),
+        Err(e) => Some(e.to_string()),
+    };
+    if let Some(failure) = failure {
+        std::fs::write(&path, &original)?;
+        bail!("re-encrypt-secrets failed, superset_config.py restored: {}", failure.trim());
+    }
+
+    let rotated = std::fs::read_to_string(&path)?;
+    std::fs::write(&path, set_assignment(&rotated, PREVIOUS_KEY_NAME, None))?;
+    store.set(credentials::SUPERSET_SECRET_KEY, new_key)?;
+    encrypted_connections(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_assignments() {
+        let config = "import os\nSECRET_KEY = \"abc\"\nPREVIOUS_SECRET_KEY='old'\ndef f():\n    SECRET_KEY = 'inner'\n";
+        assert_eq!(read_assignment(config, KEY_NAME).as_deref(), Some("abc"));
+        assert_eq!(read_assignment(config, PREVIOUS_KEY_NAME).as_deref(), Some("old"));
+        assert_eq!(read_assignment("SECRET_KEY = os.environ['X']", KEY_NAME), None);
+
+        let updated = set_assignment(config, KEY_NAME, Some("new"));
+        assert_eq!(read_assignment(&updated, KEY_NAME).as_deref(), Some("new"));
+        assert!(updated.contains("    SECRET_KEY = 'inner'"), "nested assignment untouched");
+        let removed = set_assignment(&updated, PREVIOUS_KEY_NAME, None);
+        assert_eq!(read_assignment(&removed, PREVIOUS_KEY_NAME), None);
+        let appended = set_assignment("import os\n", PREVIOUS_KEY_NAME, Some("old"));
+        assert_eq!(appended, "import os\nPREVIOUS_SECRET_KEY = \"old\"\n");
+    }
+
+    #[test]
+    fn test_status_and_backup() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let store = CredentialStore::file(root);
+        assert_eq!(status(root, &store).unwrap(), KeyStatus::NoConfig);
+
+        std::fs::create_dir_all(root.join("superset_home")).unwrap();
+        std::fs::write(config_path(root), "SECRET_KEY = \"one\"\n").unwrap();
+        assert_eq!(ensure_backed_up(root, &store).unwrap(), KeyStatus::NotBackedUp);
+        assert_eq!(status(root, &store).unwrap(), KeyStatus::BackedUp);
+
+        std::fs::write(config_path(root), "SECRET_KEY = \"two\"\n").unwrap();
+        assert_eq!(ensure_backed_up(root, &store).unwrap(), KeyStatus::Mismatch);
+        assert_eq!(store.get(credentials::SUPERSET_SECRET_KEY).unwrap().as_deref(), Some("one"), "copy kept");
+
+        assert_eq!(key_for_new_config(root, &store, || "fresh".into()).unwrap(), "one");
+    }
+}
@@ -0,0 +1,222 @@
+//! CORS and security headers shared by every HTTP server
+//!
+//! The gateway, launcher UI, docs server and LightDocs server all wrap their
+//! routers with [`apply`], so one `"security"` section in `config.json`
+//! controls them. Intranet portals that embed dashboards in an iframe list
+//! themselves in `frame_ancestors`; that switches X-Frame-Options off in
+//! favour of CSP `frame-ancestors`, which (unlike X-Frame-Options) accepts
+//! an allow-list.
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue},
+    middleware::{self, Next},
+    response::Response,
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tracing::warn;
+
+/// Header settings (`security` in config.json)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecurityConfig {
+    /// Origins allowed to call the servers from other sites, `"*"` for any.
+    /// Unset: docs servers allow any origin, the gateway and launcher UI none.
+    pub cors_origins: Option<Vec<String>>,
+    /// `DENY`, `SAMEORIGIN`, or empty to leave the header out
+    pub frame_options: String,
+    /// Pages allowed to embed us in an iframe, e.g. `https://portal.corp.local`
+    pub frame_ancestors: Vec<String>,
+    /// Full Content-Security-Policy; empty keeps whatever the page sends
+    pub content_security_policy: String,
+    /// Referrer-Policy; empty to leave the header out
+    pub referrer_policy: String,
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            cors_origins: None,
+            frame_options: "SAMEORIGIN".to_string(),
+            frame_ancestors: Vec::new(),
+            content_security_policy: String::new(),
+            referrer_policy: "strict-origin-when-cross-origin".to_string(),
+        }
+    }
+}
+
+impl SecurityConfig {
+    /// Security section of the portable root's config.json (defaults if unreadable)
+    pub fn load(root: &Path) -> Self {
+        crate::config::Config::load_or_create(root)
+            .map(|c| c.security)
+            .unwrap_or_default()
+    }
+}
+
+/// Headers precomputed from [`SecurityConfig`]
+#[derive(Debug, Default)]
+struct SecurityHeaders {
+    set: Vec<(HeaderName, HeaderValue)>,
+    remove: Vec<HeaderName>,
+    /// `frame-ancestors` directive merged into an upstream CSP
+    frame_ancestors: Option<String>,
+}
+
+impl SecurityHeaders {
+    fn from_config(config: &SecurityConfig) -> Self {
+        let mut headers = Self::default();
+        let mut set = |name: HeaderName, value: &str| match HeaderValue::from_str(value) {
+            Ok(value) => headers.set.push((name, value)),
+            Err(_) => warn!("Ignoring invalid {} value: {:?}", name, value),
+        };
+
+        let frame_ancestors = (!config.frame_ancestors.is_empty())
+            .then(|| format!("frame-ancestors 'self' {}", config.frame_ancestors.join(" ")));
+
+        if !config.content_security_policy.is_empty() {
+            let csp = match &frame_ancestors {
+                Some(fa) => format!("{}; {}", config.content_security_policy.trim_end_matches(';'), fa),
+                None => config.content_security_policy.clone(),
+            };
+            set(header::CONTENT_SECURITY_POLICY, &csp);
+        }
+        if !config.referrer_policy.is_empty() {
+            set(header::REFERRER_POLICY, &config.referrer_policy);
+        }
+        // X-Frame-Options can't name other sites: embedding needs it gone
+        let frame_options = frame_ancestors.is_none() && !config.frame_options.is_empty();
+        if frame_options {
+            set(header::X_FRAME_OPTIONS, &config.frame_options);
+        }
+        set(header::X_CONTENT_TYPE_OPTIONS, "nosniff");
+
+        if !frame_options {
+            headers.remove.push(header::X_FRAME_OPTIONS);
+        }
+        if config.content_security_policy.is_empty() {
+            headers.frame_ancestors = frame_ancestors;
+        }
+        headers
+    }
+
+    fn apply(&self, response: &mut Response) {
+        let h = response.headers_mut();
+        for name in &self.remove {
+            h.remove(name);
+        }
+        for (name, value) in &self.set {
+            h.insert(name.clone(), value.clone());
+        }
+        if let Some(fa) = &self.frame_ancestors {
+            let csp = match h.get(header::CONTENT_SECURITY_POLICY).and_then(|v| v.to_str().ok()) {
+                Some(existing) => format!("{}; {}", without_frame_ancestors(existing), fa),
+                None => fa.clone(),
+            };
+            if let Ok(value) = HeaderValue::from_str(&csp) {
+                h.insert(header::CONTENT_SECURITY_POLICY, value);
+            }
+        }
+    }
+}
+
+/// Upstream CSP minus its own `frame-ancestors` directive
+fn without_frame_ancestors(csp: &str) -> String {
+    csp.split(';')
+        .map(str::trim)
+        .filter(|d| !d.is_empty() && !d.starts_with("frame-ancestors"))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+async fn security_headers_middleware(
+    State(headers): State<Arc<SecurityHeaders>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(req).await;
+    headers.apply(&mut response);
+    response
+}
+
+fn cors_layer(origins: &[String]) -> Option<CorsLayer> {
+    if origins.is_empty() {
+        return None;
+    }
+    let allow_origin = if origins.iter().any(|o| o == "*") {
+        AllowOrigin::from(Any)
+    } else {
+        AllowOrigin::list(origins.iter().filter_map(|o| HeaderValue::from_str(o.trim_end_matches('/')).ok()))
+    };
+    Some(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods(Any)
+            .allow_headers(Any),
+    )
+}
+
+/// Wrap `router` with the configured headers and CORS rules.
+///
+/// `open_by_default` is the CORS policy when `cors_origins` is unset: static
+/// docs are fetched by dashboards on other ports, the UI and gateway are not.
+pub fn apply<S>(router: Router<S>, config: &SecurityConfig, open_by_default: bool) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let headers = Arc::new(SecurityHeaders::from_config(config));
+    let router = router.layer(middleware::from_fn_with_state(headers, security_headers_middleware));
+
+    let default_origins = if open_by_default { vec!["*".to_string()] } else { Vec::new() };
+    match cors_layer(config.cors_origins.as_deref().unwrap_or(&default_origins)) {
+        Some(cors) => router.layer(cors),
+        None => router,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response_with(csp: Option<&str>) -> Response {
+        let mut response = Response::new(axum::body::Body::empty());
+        response.headers_mut().insert(header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+        if let Some(csp) = csp {
+            response.headers_mut().insert(header::CONTENT_SECURITY_POLICY, HeaderValue::from_str(csp).unwrap());
+        }
+        response
+    }
+
+    #[test]
+    fn test_default_headers() {
+        let headers = SecurityHeaders::from_config(&SecurityConfig::default());
+        let mut response = response_with(None);
+        headers.apply(&mut response);
+        let h = response.headers();
+        assert_eq!(h[header::X_FRAME_OPTIONS], "SAMEORIGIN");
+        assert_eq!(h[header::REFERRER_POLICY], "strict-origin-when-cross-origin");
+        assert!(h.get(header::CONTENT_SECURITY_POLICY).is_none());
+    }
+
+    #[test]
+    fn test_frame_ancestors_allow_embedding() {
+        let config = SecurityConfig {
+            frame_ancestors: vec!["https://portal.corp".to_string()],
+            ..Default::default()
+        };
+        let headers = SecurityHeaders::from_config(&config);
+
+        let mut response = response_with(Some("default-src 'self'; frame-ancestors 'none'"));
+        headers.apply(&mut response);
+        let h = response.headers();
+        assert!(h.get(header::X_FRAME_OPTIONS).is_none());
+        assert_eq!(
+            h[header::CONTENT_SECURITY_POLICY],
+            "default-src 'self'; frame-ancestors 'self' https://portal.corp"
+        );
+    }
+}
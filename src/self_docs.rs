@@ -0,0 +1,248 @@
+//! Launcher reference pages generated into the knowledge base
+//!
+//! `docs self-generate` writes `knowledge/launcher/*.md` from what the binary
+//! itself knows: the clap command tree, the defaults of `config.json` and
+//! the HTTP route registries of the launcher UI and the gateway. The pages
+//! are rebuilt on every run, so the offline help never drifts from the
+//! installed version; hand edits are overwritten.
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+
+/// Folder of the generated pages (inside `knowledge/`)
+pub const SELF_DOCS_DIR: &str = "launcher";
+
+/// One HTTP endpoint in a route registry
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ApiRoute {
+    /// `GET`, `POST`, or `*` for any method
+    pub method: &'static str,
+    pub path: &'static str,
+    /// Needs the launcher token when called from another machine
+    pub token: bool,
+    pub summary: &'static str,
+}
+
+/// Write the reference pages; returns the files written
+pub fn generate(root: &Path, cli: clap::Command) -> Result<Vec<PathBuf>> {
+    let dir = root.join("knowledge").join(SELF_DOCS_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let pages = [
+        ("index.md", index_page()),
+        ("cli.md", cli_page(cli)),
+        ("config.md", config_page()),
+        ("api.md", api_page()),
+    ];
+    let mut written = Vec::new();
+    for (name, content) in pages {
+        let path = dir.join(name);
+        std::fs::write(&path, content)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+fn header(title: &str, tags: &str) -> String {
+    format!(
+        "---\ntitle: {}\nstatus: public\ntags: [лаунчер, справка, {}]\n---\n\n# {}\n\n\
+         > Страница создана командой `superset-launcher docs self-generate` (версия {}). \
+         Не редактируйте её вручную: изменения пропадут при следующей генерации.\n\n",
+        title,
+        tags,
+        title,
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+/// Table cell text: one line, no stray pipes
+fn cell(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ").replace('|', "\\|")
+}
+
+fn index_page() -> String {
+    let mut page = header("Справочник лаунчера", "index");
+    page.push_str("- [[Команды лаунчера]] — все команды и параметры `superset-launcher`\n");
+    page.push_str("- [[Настройки config.json]] — ключи `config.json` и их значения по умолчанию\n");
+    page.push_str("- [[HTTP API лаунчера]] — адреса панели лаунчера и шлюза\n");
+    page
+}
+
+fn cli_page(mut cli: clap::Command) -> String {
+    cli.build();
+    let mut page = header("Команды лаунчера", "cli");
+    let mut commands = Vec::new();
+    collect_commands(&cli, &mut commands);
+    for command in commands {
+        write_command(&mut page, command);
+    }
+    page
+}
+
+/// Every visible command that does something (leaves and commands with own arguments)
+fn collect_commands<'a>(command: &'a clap::Command, out: &mut Vec<&'a clap::Command>) {
+    for sub in command.get_subcommands().filter(|s| !s.is_hide_set() && s.get_name() != "help") {
+        let has_args = sub.get_arguments().any(|a| !is_builtin(a));
+        if sub.get_subcommands().next().is_none() || has_args {
+            out.push(sub);
+        }
+        collect_commands(sub, out);
+    }
+}
+
+fn is_builtin(arg: &clap::Arg) -> bool {
+    arg.is_hide_set() || matches!(arg.get_id().as_str(), "help" | "version")
+}
+
+fn write_command(page: &mut String, command: &clap::Command) {
+    let name = command.get_bin_name().unwrap_or(command.get_name());
+    let _ = writeln!(page, "## `{}`\n", name);
+    if let Some(about) = command.get_long_about().or(command.get_about()) {
+        let _ = writeln!(page, "{}\n", about);
+    }
+    let usage = command.clone().render_usage().to_string();
+    let _ = writeln!(page, "```cmd\n{}\n```\n", usage.trim_start_matches("Usage: "));
+
+    let args: Vec<&clap::Arg> = command.get_arguments().filter(|a| !is_builtin(a)).collect();
+    if args.is_empty() {
+        return;
+    }
+    page.push_str("| Параметр | Описание | По умолчанию |\n|---|---|---|\n");
+    for arg in args {
+        let flag = match (arg.get_long(), arg.get_short()) {
+            (Some(long), Some(short)) => format!("`-{}`, `--{}`", short, long),
+            (Some(long), None) => format!("`--{}`", long),
+            (None, Some(short)) => format!("`-{}`", short),
+            (None, None) => format!("`<{}>`", arg.get_id().as_str().to_uppercase()),
+        };
+        let help = arg.get_long_help().or(arg.get_help()).map(|h| cell(&h.to_string())).unwrap_or_default();
+        let default: Vec<String> = arg.get_default_values().iter().map(|v| v.to_string_lossy().to_string()).collect();
+        let default = if default.is_empty() { String::new() } else { format!("`{}`", default.join(", ")) };
+        let _ = writeln!(page, "| {} | {} | {} |", flag, help, default);
+    }
+    page.push('\n');
+}
+
+fn config_page() -> String {
+    let mut page = header("Настройки config.json", "config");
+    page.push_str(
+        "Файл `config.json` лежит в корне комплекта. Ключ, которого нет в файле, берёт значение \
+         по умолчанию. Вложенные ключи записаны через точку: `gateway.landing_dashboard` — это \
+         поле `landing_dashboard` внутри `\"gateway\": {...}`.\n\n",
+    );
+    page.push_str("| Ключ | Тип | По умолчанию |\n|---|---|---|\n");
+    let defaults = serde_json::to_value(Config::default()).unwrap_or(Value::Null);
+    let mut keys = Vec::new();
+    flatten("", &defaults, &mut keys);
+    for (key, value) in keys {
+        let kind = match &value {
+            Value::Null => "—",
+            Value::Bool(_) => "да/нет",
+            Value::Number(_) => "число",
+            Value::String(_) => "строка",
+            Value::Array(_) => "список",
+            Value::Object(_) => "объект",
+        };
+        let _ = writeln!(page, "| `{}` | {} | `{}` |", key, kind, cell(&value.to_string()));
+    }
+    page
+}
+
+/// Dotted paths of the leaves of `value` (lists and empty objects count as leaves)
+fn flatten(prefix: &str, value: &Value, out: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, child) in map {
+                let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten(&path, child, out);
+            }
+        }
+        _ => out.push((prefix.to_string(), value.clone())),
+    }
+}
+
+fn api_page() -> String {
+    let mut page = header("HTTP API лаунчера", "api");
+    let sections: [(&str, &str, &[ApiRoute]); 2] = [
+        (
+            "Панель лаунчера",
+            "Адрес по умолчанию `http://localhost:3000`. Запросы с другого компьютера (`--lan`) \
+             к отмеченным адресам требуют входа в панель (`/api/login`) или токен в заголовке \
+             `X-Launcher-Token`.",
+            crate::launcher_ui::ROUTES,
+        ),
+        (
+            "Шлюз",
+            "Единый адрес для пользователей (`superset-launcher up`, по умолчанию `http://localhost:8000`): запросы, \
+             не перечисленные ниже, передаются в Superset.",
+            crate::gateway::ROUTES,
+        ),
+    ];
+    for (title, intro, routes) in sections {
+        let _ = writeln!(page, "## {}\n\n{}\n", title, intro);
+        page.push_str("| Метод | Адрес | Токен | Назначение |\n|---|---|---|---|\n");
+        for route in routes {
+            let token = if route.token { "да" } else { "" };
+            let _ = writeln!(page, "| {} | `{}` | {} | {} |", route.method, route.path, token, cell(route.summary));
+        }
+        page.push('\n');
+    }
+    page
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Paths passed to `.route("...")` in a module's source
+    fn routed_paths(source: &str) -> Vec<&str> {
+        source
+            .split(".route(")
+            .skip(1)
+            .filter_map(|rest| rest.trim_start().strip_prefix('"')?.split('"').next())
+            .collect()
+    }
+
+    #[test]
+    fn test_registries_cover_every_route() {
+        for (source, routes) in [
+            (include_str!("launcher_ui.rs"), crate::launcher_ui::ROUTES),
+            (include_str!("gateway.rs"), crate::gateway::ROUTES),
+        ] {
+            for path in routed_paths(source) {
+                assert!(routes.iter().any(|r| r.path == path), "{} is missing from the route registry", path);
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_pages() {
+        let dir = tempdir().unwrap();
+        let cli = clap::Command::new("superset-launcher").subcommand(
+            clap::Command::new("lightdocs").subcommand(
+                clap::Command::new("search")
+                    .about("Search documents")
+                    .arg(clap::Arg::new("query").required(true).help("Search query"))
+                    .arg(clap::Arg::new("limit").long("limit").default_value("20").help("Results | per page")),
+            ),
+        );
+        let written = generate(dir.path(), cli).unwrap();
+        assert_eq!(written.len(), 4);
+
+        let cli = std::fs::read_to_string(dir.path().join("knowledge/launcher/cli.md")).unwrap();
+        assert!(cli.starts_with("---\ntitle: Команды лаунчера\nstatus: public"));
+        assert!(cli.contains("## `superset-launcher lightdocs search`"));
+        assert!(cli.contains("| `--limit` | Results \\| per page | `20` |"));
+        assert!(cli.contains("| `<QUERY>` | Search query |  |"));
+        assert!(!cli.contains("## `superset-launcher lightdocs`"), "groups without arguments are skipped");
+
+        let config = std::fs::read_to_string(dir.path().join("knowledge/launcher/config.md")).unwrap();
+        assert!(config.contains("| `port` | число | `8088` |"));
+        assert!(config.contains("| `gateway."), "nested keys are flattened");
+    }
+}
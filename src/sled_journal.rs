@@ -0,0 +1,445 @@
+//! Anti-corruption journal for sled databases
+//!
+//! Pulling the USB stick while sled is writing regularly leaves a database
+//! that fails to open. Critical trees are periodically exported to a flat
+//! JSON checkpoint next to the database (`<db>.checkpoint.json`); when the
+//! database cannot be opened, it is moved aside and rebuilt from the last
+//! checkpoint instead of crashing the launcher.
+//!
+//! sled never gives space back on its own: a cache that churns for a month
+//! takes several times its live size on the stick. [`Journal::compact`]
+//! rewrites a closed database with only its live entries and swaps it in.
+//!
+//! sled locks a database for a single handle, so small stores written from
+//! several places (usage counters, SLO totals, health history) go through
+//! [`shared`]: one handle per process, opened on first use.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{error, info, warn};
+
+/// Name used for the default (unnamed) sled tree
+pub const DEFAULT_TREE: &str = "";
+
+/// How often long-running services checkpoint
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Checkpoints older than this are refreshed when a database is opened
+pub const CHECKPOINT_MAX_AGE: Duration = Duration::from_secs(600);
+
+/// Cached payloads above this size are not worth journaling
+pub const CHECKPOINT_MAX_VALUE_BYTES: usize = 64 * 1024;
+
+/// Databases not compacted for this long are compacted when opened
+pub const COMPACT_INTERVAL: Duration = Duration::from_secs(7 * 24 * 3600);
+
+/// sled's own files inside a database folder; anything else there is carried over on compaction
+const SLED_FILES: &[&str] = &["conf", "db", "blobs"];
+
+/// Handles given out by [`shared`], by database path
+static SHARED: Mutex<BTreeMap<PathBuf, sled::Db>> = Mutex::new(BTreeMap::new());
+
+/// The process-wide handle of the database at `path`, opened on first use
+/// and kept until the process exits
+pub fn shared(path: &Path) -> Result<sled::Db> {
+    let mut open = SHARED.lock().map_err(|_| anyhow::anyhow!("sled handles poisoned"))?;
+    if let Some(db) = open.get(path) {
+        return Ok(db.clone());
+    }
+    let db = sled::open(path).with_context(|| format!("Database is in use or unreadable: {}", path.display()))?;
+    open.insert(path.to_path_buf(), db.clone());
+    Ok(db)
+}
+
+/// Forget the shared handle of `path` after a failed write (the stick may
+/// have been pulled), so the next [`shared`] call opens the database again
+pub fn release(path: &Path) {
+    if let Ok(mut open) = SHARED.lock() {
+        open.remove(path);
+    }
+}
+
+/// Outcome of compacting one database
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompactReport {
+    /// Entries copied into the new database
+    pub kept: usize,
+    /// Entries the filter dropped
+    pub dropped: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+impl CompactReport {
+    /// Disk space given back
+    pub fn reclaimed(&self) -> u64 {
+        self.bytes_before.saturating_sub(self.bytes_after)
+    }
+}
+
+/// On-disk checkpoint: tree name -> hex encoded (key, value) pairs
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Checkpoint {
+    created_at: u64,
+    trees: BTreeMap<String, Vec<(String, String)>>,
+}
+
+/// Checkpoint/restore policy for one sled database
+#[derive(Debug, Clone)]
+pub struct Journal {
+    db_path: PathBuf,
+    checkpoint_path: PathBuf,
+    trees: Vec<String>,
+    max_value_bytes: usize,
+}
+
+impl Journal {
+    /// Journal for the database at `db_path` covering `trees`
+    pub fn new(db_path: &Path, trees: &[&str]) -> Self {
+        let mut name = db_path.file_name().unwrap_or_default().to_os_string();
+        name.push(".checkpoint.json");
+        Self {
+            db_path: db_path.to_path_buf(),
+            checkpoint_path: db_path.with_file_name(name),
+            trees: trees.iter().map(|t| t.to_string()).collect(),
+            max_value_bytes: usize::MAX,
+        }
+    }
+
+    /// Skip values larger than `bytes` (bulky cached payloads are cheap to refetch)
+    pub fn max_value_bytes(mut self, bytes: usize) -> Self {
+        self.max_value_bytes = bytes;
+        self
+    }
+
+    /// Open the database, rebuilding it from the checkpoint if it is corrupted
+    pub fn open(&self) -> Result<sled::Db> {
+        match sled::open(&self.db_path) {
+            Ok(db) => Ok(db),
+            // Another process holds the database: not corruption, don't touch it
+            Err(sled::Error::Io(e)) if e.to_string().contains("lock") => Err(e)
+                .with_context(|| format!("Database is in use: {}", self.db_path.display())),
+            Err(e) => {
+                error!("💥 Failed to open {}: {}", self.db_path.display(), e);
+                self.rebuild()
+            }
+        }
+    }
+
+    /// Move the broken database aside and restore the last checkpoint
+    pub fn rebuild(&self) -> Result<sled::Db> {
+        if self.db_path.exists() {
+            let mut name = self.db_path.file_name().unwrap_or_default().to_os_string();
+            name.push(format!(".corrupt-{}", now_secs()));
+            let aside = self.db_path.with_file_name(name);
+            std::fs::rename(&self.db_path, &aside)
+                .with_context(|| format!("Failed to move aside {}", self.db_path.display()))?;
+            warn!("Moved corrupted database to {}", aside.display());
+        }
+
+        let db = sled::open(&self.db_path)?;
+        match self.restore(&db) {
+            Ok(0) => info!("No checkpoint for {}, starting empty", self.db_path.display()),
+            Ok(n) => info!("♻️ Restored {} entries into {}", n, self.db_path.display()),
+            Err(e) => warn!("Checkpoint unusable, starting empty: {}", e),
+        }
+        Ok(db)
+    }
+
+    fn restore(&self, db: &sled::Db) -> Result<usize> {
+        if !self.checkpoint_path.exists() {
+            return Ok(0);
+        }
+        let checkpoint: Checkpoint = serde_json::from_slice(&std::fs::read(&self.checkpoint_path)?)?;
+
+        let mut count = 0;
+        for (name, entries) in &checkpoint.trees {
+            let tree = open_tree(db, name)?;
+            for (key, value) in entries {
+                tree.insert(hex::decode(key)?, hex::decode(value)?)?;
+                count += 1;
+            }
+        }
+        db.flush()?;
+        Ok(count)
+    }
+
+    /// Export the journaled trees to the checkpoint file, returns entries written
+    pub fn checkpoint(&self, db: &sled::Db) -> Result<usize> {
+        let mut checkpoint = Checkpoint {
+            created_at: now_secs(),
+            trees: BTreeMap::new(),
+        };
+
+        let mut count = 0;
+        for name in &self.trees {
+            let tree = open_tree(db, name)?;
+            let mut entries = Vec::new();
+            for item in tree.iter() {
+                let (key, value) = item?;
+                if value.len() <= self.max_value_bytes {
+                    entries.push((hex::encode(key), hex::encode(value)));
+                }
+            }
+            count += entries.len();
+            checkpoint.trees.insert(name.clone(), entries);
+        }
+
+        // Write-then-rename so a yank mid-write never destroys the previous checkpoint
+        let tmp = self.checkpoint_path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&checkpoint)?)?;
+        std::fs::rename(&tmp, &self.checkpoint_path)?;
+        Ok(count)
+    }
+
+    /// Checkpoint unless the existing one is younger than `max_age`
+    pub fn checkpoint_if_stale(&self, db: &sled::Db, max_age: Duration) -> Result<()> {
+        let fresh = std::fs::metadata(&self.checkpoint_path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.elapsed().ok())
+            .map_or(false, |age| age < max_age);
+        if !fresh {
+            self.checkpoint(db)?;
+        }
+        Ok(())
+    }
+
+    /// Rewrite the database with only its live entries, keeping all of them
+    pub fn compact(&self) -> Result<CompactReport> {
+        self.compact_with(|_| true)
+    }
+
+    /// Rewrite the database, copying only values for which `keep` is true
+    ///
+    /// The database must not be open anywhere. Entries are copied tree by tree
+    /// into `<db>.compacting`, which then replaces the original folder.
+    pub fn compact_with(&self, keep: impl Fn(&[u8]) -> bool) -> Result<CompactReport> {
+        if !self.db_path.join("db").exists() {
+            return Ok(CompactReport::default());
+        }
+        if SHARED.lock().map_or(true, |open| open.contains_key(&self.db_path)) {
+            anyhow::bail!("Database is in use: {}", self.db_path.display());
+        }
+        let mut report = CompactReport {
+            bytes_before: sled_size(&self.db_path),
+            ..Default::default()
+        };
+        let fresh_path = self.sibling(".compacting");
+        let _ = std::fs::remove_dir_all(&fresh_path);
+
+        let copied = (|| -> Result<sled::Db> {
+            let old = self.open()?;
+            let fresh = sled::open(&fresh_path)?;
+            for name in old.tree_names() {
+                let src = old.open_tree(&name)?;
+                let dst = fresh.open_tree(&name)?;
+                for item in src.iter() {
+                    let (key, value) = item?;
+                    if keep(&value) {
+                        dst.insert(key, value)?;
+                        report.kept += 1;
+                    } else {
+                        report.dropped += 1;
+                    }
+                }
+            }
+            fresh.flush()?;
+            Ok(old)
+        })();
+        let old = match copied {
+            Ok(old) => old,
+            Err(e) => {
+                let _ = std::fs::remove_dir_all(&fresh_path);
+                return Err(e);
+            }
+        };
+
+        // Keep sled's lock through the swap so no other process opens the old
+        // folder in between and writes into what is about to be deleted.
+        // Windows can't rename a folder with open files: there the rename
+        // itself fails while anyone has the database open.
+        #[cfg(windows)]
+        drop(old);
+
+        // Swap folders; on failure the original goes back in place untouched
+        let aside = self.sibling(&format!(".precompact-{}", now_secs()));
+        if let Err(e) = std::fs::rename(&self.db_path, &aside) {
+            let _ = std::fs::remove_dir_all(&fresh_path);
+            return Err(e).with_context(|| format!("Database is in use: {}", self.db_path.display()));
+        }
+        if let Err(e) = std::fs::rename(&fresh_path, &self.db_path) {
+            let _ = std::fs::rename(&aside, &self.db_path);
+            return Err(e).context("Failed to swap in the compacted database");
+        }
+        #[cfg(not(windows))]
+        drop(old);
+
+        // Nested databases and checkpoints living in the old folder move over
+        for entry in std::fs::read_dir(&aside)?.flatten() {
+            let name = entry.file_name();
+            if !is_sled_file(&name.to_string_lossy()) {
+                std::fs::rename(entry.path(), self.db_path.join(&name))?;
+            }
+        }
+        if let Err(e) = std::fs::remove_dir_all(&aside) {
+            warn!("Could not remove {}: {}", aside.display(), e);
+        }
+
+        std::fs::write(self.sibling(".compacted"), now_secs().to_string())?;
+        report.bytes_after = sled_size(&self.db_path);
+        Ok(report)
+    }
+
+    /// Compact if the last compaction is older than `interval`
+    ///
+    /// Returns `None` when nothing was due. Call before [`Journal::open`].
+    pub fn compact_if_due(&self, interval: Duration, keep: impl Fn(&[u8]) -> bool) -> Result<Option<CompactReport>> {
+        if !self.db_path.join("db").exists() {
+            return Ok(None);
+        }
+        let due = std::fs::metadata(self.sibling(".compacted"))
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.elapsed().ok())
+            .map_or(true, |age| age >= interval);
+        if !due {
+            return Ok(None);
+        }
+        let report = self.compact_with(keep)?;
+        info!(
+            "🧹 Compacted {}: {:.1} MB reclaimed",
+            self.db_path.display(),
+            report.reclaimed() as f64 / 1_048_576.0
+        );
+        Ok(Some(report))
+    }
+
+    /// `<db><suffix>` next to the database folder
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let mut name = self.db_path.file_name().unwrap_or_default().to_os_string();
+        name.push(suffix);
+        self.db_path.with_file_name(name)
+    }
+
+    /// Checkpoint every `interval` in the background
+    pub fn spawn_periodic(self, db: sled::Db, interval: Duration) {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                if let Err(e) = self.checkpoint(&db) {
+                    warn!("Checkpoint of {} failed: {}", self.db_path.display(), e);
+                }
+            }
+        });
+    }
+}
+
+fn open_tree(db: &sled::Db, name: &str) -> Result<sled::Tree> {
+    if name == DEFAULT_TREE {
+        Ok((**db).clone())
+    } else {
+        Ok(db.open_tree(name)?)
+    }
+}
+
+fn is_sled_file(name: &str) -> bool {
+    SLED_FILES.contains(&name) || name.starts_with("snap.")
+}
+
+/// Bytes taken by sled's own files in `db_path` (nested databases are not counted)
+pub fn sled_size(db_path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(db_path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter(|e| is_sled_file(&e.file_name().to_string_lossy()))
+        .flat_map(|e| walkdir::WalkDir::new(e.path()))
+        .flatten()
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_checkpoint_and_rebuild() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("search");
+        let journal = Journal::new(&db_path, &[DEFAULT_TREE, "documents"]).max_value_bytes(16);
+
+        {
+            let db = journal.open().unwrap();
+            db.insert("small", "value").unwrap();
+            db.insert("big", vec![0u8; 64]).unwrap();
+            db.open_tree("documents").unwrap().insert("faq", "{}").unwrap();
+            db.open_tree("scratch").unwrap().insert("tmp", "x").unwrap();
+            assert_eq!(journal.checkpoint(&db).unwrap(), 2);
+        }
+        assert!(dir.path().join("search.checkpoint.json").exists());
+
+        let db = journal.rebuild().unwrap();
+        assert_eq!(db.get("small").unwrap().unwrap(), b"value");
+        assert!(db.get("big").unwrap().is_none());
+        assert!(db.open_tree("documents").unwrap().get("faq").unwrap().is_some());
+        assert!(db.open_tree("scratch").unwrap().is_empty());
+
+        // The broken copy is kept for inspection
+        let aside = std::fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .any(|e| e.file_name().to_string_lossy().starts_with("search.corrupt-"));
+        assert!(aside);
+    }
+
+    #[test]
+    fn test_compact_keeps_live_entries() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("cache");
+        let journal = Journal::new(&db_path, &[DEFAULT_TREE]);
+
+        {
+            let db = journal.open().unwrap();
+            for i in 0..200u32 {
+                db.insert(i.to_be_bytes(), vec![b'x'; 4096]).unwrap();
+            }
+            for i in 0..190u32 {
+                db.remove(i.to_be_bytes()).unwrap();
+            }
+            db.insert("dead", "expired").unwrap();
+            db.open_tree("other").unwrap().insert("k", "v").unwrap();
+            db.flush().unwrap();
+        }
+        // A nested database must survive the folder swap
+        std::fs::create_dir_all(db_path.join("gateway_sled")).unwrap();
+        std::fs::write(db_path.join("gateway_sled").join("marker"), "x").unwrap();
+
+        let report = journal.compact_with(|value| value != b"expired").unwrap();
+        assert_eq!(report.kept, 11);
+        assert_eq!(report.dropped, 1);
+        assert!(db_path.join("gateway_sled").join("marker").exists());
+        assert!(journal.compact_if_due(COMPACT_INTERVAL, |_| true).unwrap().is_none());
+
+        let db = journal.open().unwrap();
+        assert_eq!(db.len(), 10);
+        assert!(db.get("dead").unwrap().is_none());
+        assert_eq!(db.open_tree("other").unwrap().get("k").unwrap().unwrap(), b"v");
+    }
+}
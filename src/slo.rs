@@ -0,0 +1,353 @@
+//! Service-level numbers for kiosk deployments
+//!
+//! Daily counters kept in a sled database inside the kit: minutes Superset
+//! answered (or did not) while the launcher was watching, Superset starts,
+//! requests and 5xx answers through the gateway, and chart data timings as
+//! the dashboard render latency. `report slo --last 7d` sums them into a
+//! markdown page in `knowledge/operations/` for the operations review.
+//!
+//! Hot paths only bump in-memory totals ([`add`]); [`flush`] merges them into
+//! the database through the process's one handle
+//! ([`crate::sled_journal::shared`]). A database another process holds keeps
+//! the totals for the next flush.
+
+use anyhow::Result;
+use chrono::{Days, Local, NaiveDate};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::{health_history, sled_journal};
+
+const SLO_DB: &str = ".slo_stats";
+
+/// How often the monitor checks Superset and flushes the totals
+const MONITOR_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A service slower than this to answer counts as down
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Folder of the reports (inside `knowledge/`)
+const REPORTS_DIR: &str = "operations";
+
+/// Daily counter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Metric {
+    /// Seconds Superset answered its health check
+    UpSecs,
+    /// Seconds it did not
+    DownSecs,
+    /// Superset process starts
+    Starts,
+    /// Requests proxied to Superset
+    Requests,
+    /// Of those, answered with 5xx (or not at all)
+    Errors,
+    /// Chart data requests
+    Renders,
+    /// Their total time to first byte
+    RenderMillis,
+}
+
+impl Metric {
+    const ALL: [Metric; 7] = [
+        Metric::UpSecs,
+        Metric::DownSecs,
+        Metric::Starts,
+        Metric::Requests,
+        Metric::Errors,
+        Metric::Renders,
+        Metric::RenderMillis,
+    ];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Metric::UpSecs => "up_secs",
+            Metric::DownSecs => "down_secs",
+            Metric::Starts => "starts",
+            Metric::Requests => "requests",
+            Metric::Errors => "errors",
+            Metric::Renders => "renders",
+            Metric::RenderMillis => "render_ms",
+        }
+    }
+}
+
+/// Totals not yet in the database, by day
+static PENDING: Mutex<BTreeMap<(NaiveDate, Metric), u64>> = Mutex::new(BTreeMap::new());
+
+/// Add `value` to today's `metric` (in memory until the next [`flush`])
+pub fn add(metric: Metric, value: u64) {
+    add_on(Local::now().date_naive(), metric, value);
+}
+
+fn add_on(day: NaiveDate, metric: Metric, value: u64) {
+    if let Ok(mut pending) = PENDING.lock() {
+        *pending.entry((day, metric)).or_default() += value;
+    }
+}
+
+fn key(day: NaiveDate, metric: Metric) -> String {
+    format!("{}:{}", day.format("%Y-%m-%d"), metric.as_str())
+}
+
+fn decode(bytes: &[u8]) -> u64 {
+    bytes.try_into().map(u64::from_be_bytes).unwrap_or(0)
+}
+
+/// Merge the in-memory totals into the database
+pub fn flush(root: &Path) -> Result<()> {
+    let pending = std::mem::take(&mut *PENDING.lock().map_err(|_| anyhow::anyhow!("SLO totals poisoned"))?);
+    if pending.is_empty() {
+        return Ok(());
+    }
+    let result = (|| -> Result<()> {
+        let db = sled_journal::shared(&root.join(SLO_DB))?;
+        for ((day, metric), value) in &pending {
+            db.update_and_fetch(key(*day, *metric), |old| {
+                Some((old.map_or(0, decode) + value).to_be_bytes().to_vec())
+            })?;
+        }
+        db.flush()?;
+        Ok(())
+    })();
+    if let Err(e) = &result {
+        debug!("SLO totals kept for the next flush: {}", e);
+        sled_journal::release(&root.join(SLO_DB));
+        for ((day, metric), value) in pending {
+            add_on(day, metric, value);
+        }
+    }
+    result
+}
+
+/// Check Superset (and LightDocs, when this process serves it) every minute,
+/// keep the answers in the health history and flush the totals, for as long
+/// as the process runs (the `up` stack, `start` and the launcher UI)
+pub fn spawn_monitor(root: &Path, superset_port: u16, lightdocs_port: Option<u16>) {
+    let root = root.to_path_buf();
+    tokio::spawn(async move {
+        let Ok(client) = reqwest::Client::builder().timeout(CHECK_TIMEOUT).build() else {
+            return;
+        };
+        let mut interval = tokio::time::interval(MONITOR_INTERVAL);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let at = Local::now().timestamp();
+            let superset = health_history::timed_get(&client, &format!("http://127.0.0.1:{}/health", superset_port)).await;
+            add(if superset.is_some() { Metric::UpSecs } else { Metric::DownSecs }, MONITOR_INTERVAL.as_secs());
+            let mut samples = vec![("superset", superset)];
+            if let Some(port) = lightdocs_port {
+                samples.push(("lightdocs", health_history::timed_get(&client, &format!("http://127.0.0.1:{}/", port)).await));
+            }
+            let root = root.clone();
+            let _ = tokio::task::spawn_blocking(move || {
+                for (service, latency) in samples {
+                    if let Err(e) = health_history::record(&root, service, at, latency) {
+                        debug!("Health sample of {} dropped: {}", service, e);
+                    }
+                }
+                flush(&root)
+            })
+            .await;
+        }
+    });
+}
+
+/// Counters of one day
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DayTotals {
+    values: BTreeMap<Metric, u64>,
+}
+
+impl DayTotals {
+    pub fn get(&self, metric: Metric) -> u64 {
+        self.values.get(&metric).copied().unwrap_or(0)
+    }
+
+    fn merge(&mut self, other: &DayTotals) {
+        for (metric, value) in &other.values {
+            *self.values.entry(*metric).or_default() += value;
+        }
+    }
+
+    /// Share of watched time Superset was up, in percent
+    pub fn uptime_percent(&self) -> Option<f64> {
+        let watched = self.get(Metric::UpSecs) + self.get(Metric::DownSecs);
+        (watched > 0).then(|| self.get(Metric::UpSecs) as f64 * 100.0 / watched as f64)
+    }
+
+    /// Share of gateway requests that failed, in percent
+    pub fn error_percent(&self) -> Option<f64> {
+        let requests = self.get(Metric::Requests);
+        (requests > 0).then(|| self.get(Metric::Errors) as f64 * 100.0 / requests as f64)
+    }
+
+    pub fn average_render_ms(&self) -> Option<u64> {
+        let renders = self.get(Metric::Renders);
+        (renders > 0).then(|| self.get(Metric::RenderMillis) / renders)
+    }
+}
+
+/// Totals over a period
+#[derive(Debug, Clone)]
+pub struct SloReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Days with any data, oldest first
+    pub days: Vec<(NaiveDate, DayTotals)>,
+}
+
+impl SloReport {
+    pub fn total(&self) -> DayTotals {
+        let mut total = DayTotals::default();
+        for (_, day) in &self.days {
+            total.merge(day);
+        }
+        total
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let percent = |v: Option<f64>| v.map(|v| format!("{:.2}%", v)).unwrap_or_else(|| "—".into());
+        let millis = |v: Option<u64>| v.map(|v| format!("{} мс", v)).unwrap_or_else(|| "—".into());
+        let title = format!("SLO киоска {} — {}", self.from.format("%d.%m.%Y"), self.to.format("%d.%m.%Y"));
+        let mut page = format!(
+            "---\ntitle: {}\nstatus: public\ncreated: {}\ntags: [эксплуатация, slo, отчёт]\n---\n\n# {}\n\n\
+             > Отчёт создан командой `superset-launcher report slo`. Доступность считается только за время, \
+             когда лаунчер был запущен (`up`, `start` или панель управления) и проверял Superset раз в минуту.\n\n",
+            title,
+            self.to.format("%Y-%m-%d"),
+            title
+        );
+        let total = self.total();
+        page.push_str("## Итого\n\n| Показатель | Значение |\n|---|---|\n");
+        let _ = writeln!(page, "| Доступность | {} |", percent(total.uptime_percent()));
+        let _ = writeln!(page, "| Время наблюдения | {:.1} ч |", watched_hours(&total));
+        let _ = writeln!(page, "| Запусков Superset | {} |", total.get(Metric::Starts));
+        let _ = writeln!(page, "| Средняя загрузка графика | {} |", millis(total.average_render_ms()));
+        let _ = writeln!(page, "| Запросов через шлюз | {} |", total.get(Metric::Requests));
+        let _ = writeln!(page, "| Ошибок (5xx) | {} ({}) |\n", total.get(Metric::Errors), percent(total.error_percent()));
+
+        page.push_str("## По дням\n\n");
+        if self.days.is_empty() {
+            page.push_str("За этот период данных нет.\n");
+            return page;
+        }
+        page.push_str("| День | Доступность | Запуски | Загрузка графика | Запросы | Ошибки |\n|---|---|---|---|---|---|\n");
+        for (date, day) in &self.days {
+            let _ = writeln!(
+                page,
+                "| {} | {} | {} | {} | {} | {} ({}) |",
+                date.format("%d.%m.%Y"),
+                percent(day.uptime_percent()),
+                day.get(Metric::Starts),
+                millis(day.average_render_ms()),
+                day.get(Metric::Requests),
+                day.get(Metric::Errors),
+                percent(day.error_percent())
+            );
+        }
+        page
+    }
+}
+
+fn watched_hours(totals: &DayTotals) -> f64 {
+    (totals.get(Metric::UpSecs) + totals.get(Metric::DownSecs)) as f64 / 3600.0
+}
+
+/// Parse a period like `7d`, `2w` or `30` (days)
+pub fn parse_days(period: &str) -> Result<u64> {
+    let period = period.trim().to_lowercase();
+    let (number, scale) = match period.strip_suffix('w') {
+        Some(weeks) => (weeks, 7),
+        None => (period.strip_suffix('d').unwrap_or(&period), 1),
+    };
+    match number.parse::<u64>() {
+        Ok(n) if n > 0 => Ok(n * scale),
+        _ => anyhow::bail!("Bad period '{}': use e.g. 7d or 2w", period),
+    }
+}
+
+/// Totals of the last `days` days, today included
+pub fn report(root: &Path, days: u64) -> Result<SloReport> {
+    flush(root)?;
+    let to = Local::now().date_naive();
+    let from = to - Days::new(days.saturating_sub(1));
+    let db = sled_journal::shared(&root.join(SLO_DB))?;
+    let mut by_day: BTreeMap<NaiveDate, DayTotals> = BTreeMap::new();
+    let start = from.format("%Y-%m-%d").to_string();
+    for item in db.range(start.as_bytes()..) {
+        let (key, value) = item?;
+        let key = String::from_utf8_lossy(&key);
+        let Some((date, name)) = key.split_once(':') else { continue };
+        let (Ok(date), Some(metric)) =
+            (NaiveDate::parse_from_str(date, "%Y-%m-%d"), Metric::ALL.into_iter().find(|m| m.as_str() == name))
+        else {
+            continue;
+        };
+        if date <= to {
+            by_day.entry(date).or_default().values.insert(metric, decode(&value));
+        }
+    }
+    Ok(SloReport { from, to, days: by_day.into_iter().collect() })
+}
+
+/// Write the report into the knowledge base; returns the page path
+pub fn write_report(root: &Path, report: &SloReport) -> Result<PathBuf> {
+    let dir = root.join("knowledge").join(REPORTS_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("slo-{}.md", report.to.format("%Y-%m-%d")));
+    std::fs::write(&path, report.to_markdown())?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_totals_and_report() {
+        let dir = tempdir().unwrap();
+        let old = Local::now().date_naive() - Days::new(10);
+        add(Metric::UpSecs, 3540);
+        add(Metric::DownSecs, 60);
+        add(Metric::Starts, 2);
+        add(Metric::Requests, 200);
+        add(Metric::Errors, 1);
+        add(Metric::Renders, 4);
+        add(Metric::RenderMillis, 1000);
+        add_on(old, Metric::Starts, 5);
+        flush(dir.path()).unwrap();
+        add(Metric::UpSecs, 60);
+        flush(dir.path()).unwrap();
+
+        let report = report(dir.path(), 7).unwrap();
+        assert_eq!(report.days.len(), 1, "days outside the period are skipped");
+        let total = report.total();
+        assert_eq!(total.get(Metric::Starts), 2);
+        assert_eq!(total.uptime_percent().map(|p| (p * 100.0).round()), Some(9836.0));
+        assert_eq!(total.average_render_ms(), Some(250));
+        assert_eq!(total.error_percent(), Some(0.5));
+
+        let path = write_report(dir.path(), &report).unwrap();
+        let page = std::fs::read_to_string(path).unwrap();
+        assert!(page.starts_with("---\ntitle: SLO киоска"));
+        assert!(page.contains("| Доступность | 98.36% |"));
+        assert!(page.contains("| Средняя загрузка графика | 250 мс |"));
+        assert!(page.contains("| Ошибок (5xx) | 1 (0.50%) |"));
+    }
+
+    #[test]
+    fn test_parse_days() {
+        assert_eq!(parse_days("7d").unwrap(), 7);
+        assert_eq!(parse_days("2w").unwrap(), 14);
+        assert_eq!(parse_days("30").unwrap(), 30);
+        assert!(parse_days("0d").is_err());
+        assert!(parse_days("week").is_err());
+    }
+}
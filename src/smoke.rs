@@ -0,0 +1,224 @@
+//! Smoke test of a running stack (`superset-launcher smoke`)
+//!
+//! Meant for right after the kit was unpacked on a new machine and started
+//! with `up`: walks the path a user takes — the gateway answers, the login
+//! page opens, one chart-data query comes back, a docs page renders and the
+//! knowledge base search finds something — and prints each step with its
+//! time. Everything goes through the gateway except the search, which asks
+//! the launcher UI's `/api/lightdocs/search` like the panel does.
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+use std::fmt;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// Longest a single request may take (the first chart query warms Superset up)
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    Fail,
+    /// Couldn't be tried here (e.g. no saved password)
+    Skip,
+}
+
+/// One step of the walk
+#[derive(Debug, Clone)]
+pub struct StepResult {
+    pub name: &'static str,
+    pub outcome: Outcome,
+    pub elapsed: Duration,
+    pub detail: String,
+}
+
+impl fmt::Display for StepResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mark = match self.outcome {
+            Outcome::Pass => "✅",
+            Outcome::Fail => "❌",
+            Outcome::Skip => "⏭️",
+        };
+        write!(f, "{} {:<22} {:>7} ms  {}", mark, self.name, self.elapsed.as_millis(), self.detail)
+    }
+}
+
+/// What to test against
+pub struct SmokeOptions {
+    pub gateway_port: u16,
+    /// Launcher UI port, for the search
+    pub ui_port: u16,
+    /// Superset user for the chart query
+    pub user: String,
+    /// Its password (`None`: skip the chart query)
+    pub password: Option<String>,
+    /// Knowledge base search query
+    pub query: String,
+}
+
+/// Time `step`; an error fails it with the message as the detail
+async fn timed<F>(name: &'static str, step: F) -> StepResult
+where
+    F: Future<Output = Result<(Outcome, String)>>,
+{
+    let started = Instant::now();
+    let (outcome, detail) = step.await.unwrap_or_else(|e| (Outcome::Fail, format!("{:#}", e)));
+    StepResult { name, outcome, elapsed: started.elapsed(), detail }
+}
+
+/// GET `url`, failing on anything but 2xx
+async fn get_ok(client: &reqwest::Client, url: &str) -> Result<reqwest::Response> {
+    let response = client.get(url).send().await.with_context(|| format!("{} не отвечает", url))?;
+    if !response.status().is_success() {
+        bail!("{} ответил {}", url, response.status());
+    }
+    Ok(response)
+}
+
+async fn json_of(response: reqwest::Response) -> Result<Value> {
+    Ok(serde_json::from_slice(&response.bytes().await?)?)
+}
+
+async fn gateway(client: &reqwest::Client, base: &str) -> Result<(Outcome, String)> {
+    let routes = json_of(get_ok(client, &format!("{}/gateway/routes", base)).await?).await?;
+    let superset = &routes["upstreams"][0];
+    if superset["up"].as_bool() != Some(true) {
+        bail!("шлюз работает, но Superset (порт {}) не отвечает", superset["port"]);
+    }
+    Ok((Outcome::Pass, format!("Superset отвечает за {} ms", superset["latency_ms"])))
+}
+
+async fn login_page(client: &reqwest::Client, base: &str) -> Result<(Outcome, String)> {
+    let page = get_ok(client, &format!("{}/login/", base)).await?.text().await?;
+    Ok((Outcome::Pass, format!("{} КБ", page.len() / 1024)))
+}
+
+/// Body of `/api/v1/chart/data` counting the rows of a dataset
+fn chart_data_request(dataset_id: i64) -> Value {
+    json!({
+        "datasource": {"id": dataset_id, "type": "table"},
+        "queries": [{
+            "columns": [],
+            "metrics": [{"expressionType": "SQL", "sqlExpression": "COUNT(*)", "label": "rows"}],
+            "row_limit": 1
+        }],
+        "result_format": "json",
+        "result_type": "full"
+    })
+}
+
+async fn chart_data(client: &reqwest::Client, base: &str, user: &str, password: Option<&str>) -> Result<(Outcome, String)> {
+    let Some(password) = password else {
+        return Ok((Outcome::Skip, "пароль не сохранён на этом компьютере — укажите --password-stdin".to_string()));
+    };
+    let login = client
+        .post(format!("{}/api/v1/security/login", base))
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(json!({"username": user, "password": password, "provider": "db", "refresh": false}).to_string())
+        .send()
+        .await?;
+    if !login.status().is_success() {
+        bail!("вход под {} не удался ({})", user, login.status());
+    }
+    let token = json_of(login).await?["access_token"].as_str().context("no access_token in the login answer")?.to_string();
+
+    let datasets = client
+        .get(format!("{}/api/v1/dataset/?q=(page_size:1,order_column:id,order_direction:asc)", base))
+        .bearer_auth(&token)
+        .send()
+        .await?;
+    let datasets = json_of(datasets).await?;
+    let Some(dataset) = datasets["result"].get(0) else {
+        return Ok((Outcome::Skip, "в Superset нет ни одного набора данных — import-demo".to_string()));
+    };
+    let dataset_id = dataset["id"].as_i64().context("dataset without id")?;
+
+    let response = client
+        .post(format!("{}/api/v1/chart/data", base))
+        .bearer_auth(&token)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(chart_data_request(dataset_id).to_string())
+        .send()
+        .await?;
+    let status = response.status();
+    let cache = response.headers().get("x-superset-cache").and_then(|v| v.to_str().ok()).unwrap_or("-").to_string();
+    let answer = json_of(response).await.unwrap_or(Value::Null);
+    if !status.is_success() {
+        bail!("запрос к набору {} вернул {}: {}", dataset["table_name"], status, answer["message"]);
+    }
+    let rows = &answer["result"][0]["data"][0]["rows"];
+    Ok((Outcome::Pass, format!("{}: {} строк, кэш шлюза {}", dataset["table_name"].as_str().unwrap_or("?"), rows, cache)))
+}
+
+async fn docs_page(client: &reqwest::Client, base: &str) -> Result<(Outcome, String)> {
+    let page = get_ok(client, &format!("{}/docs/", base)).await?.text().await?;
+    if !page.to_lowercase().contains("<html") {
+        bail!("/docs/ отдал не HTML-страницу");
+    }
+    Ok((Outcome::Pass, format!("{} КБ", page.len() / 1024)))
+}
+
+async fn search(client: &reqwest::Client, ui_base: &str, query: &str) -> Result<(Outcome, String)> {
+    let url = format!("{}/api/lightdocs/search", ui_base);
+    let response = match client.get(&url).query(&[("q", query), ("limit", "1")]).send().await {
+        Ok(response) => response,
+        // `up` doesn't start the panel; `superset-launcher ui` does
+        Err(e) if e.is_connect() => {
+            return Ok((Outcome::Skip, format!("панель лаунчера ({}) не запущена: superset-launcher ui", ui_base)))
+        }
+        Err(e) => return Err(e).with_context(|| format!("{} не отвечает", url)),
+    };
+    if !response.status().is_success() {
+        bail!("{} ответил {}", url, response.status());
+    }
+    let total = response.headers().get("x-total-count").and_then(|v| v.to_str().ok()).unwrap_or("?").to_string();
+    let answer = json_of(response).await?;
+    if let Some(error) = answer["error"].as_str() {
+        bail!("поиск не работает: {}", error);
+    }
+    match answer[0]["title"].as_str() {
+        Some(title) => Ok((Outcome::Pass, format!("«{}»: {} результатов, первый — {}", query, total, title))),
+        None => bail!("«{}» ничего не нашёл — пересоберите индекс: lightdocs reindex", query),
+    }
+}
+
+/// Walk the stack step by step; later steps run even when earlier ones fail
+pub async fn run(options: &SmokeOptions) -> Result<Vec<StepResult>> {
+    let client = reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build()?;
+    let base = format!("http://127.0.0.1:{}", options.gateway_port);
+    let ui_base = format!("http://127.0.0.1:{}", options.ui_port);
+    Ok(vec![
+        timed("Шлюз", gateway(&client, &base)).await,
+        timed("Страница входа", login_page(&client, &base)).await,
+        timed("Данные графика", chart_data(&client, &base, &options.user, options.password.as_deref())).await,
+        timed("Документация", docs_page(&client, &base)).await,
+        timed("Поиск", search(&client, &ui_base, &options.query)).await,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unreachable_stack_fails_every_step() {
+        // A port nothing listens on
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let options = SmokeOptions {
+            gateway_port: port,
+            ui_port: port,
+            user: "admin".to_string(),
+            password: None,
+            query: "superset".to_string(),
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let results = runtime.block_on(run(&options)).unwrap();
+
+        let outcomes: Vec<Outcome> = results.iter().map(|r| r.outcome).collect();
+        assert_eq!(outcomes, [Outcome::Fail, Outcome::Fail, Outcome::Skip, Outcome::Fail, Outcome::Skip]);
+        assert!(results[0].detail.contains("не отвечает"));
+        assert!(results[4].detail.contains("не запущена"));
+        assert_eq!(chart_data_request(7)["datasource"]["id"], 7);
+    }
+}
@@ -0,0 +1,217 @@
+//! Snapshots of data files before destructive loads
+//!
+//! `load-data`, uploads from the launcher UI, `import-demo` and the data
+//! watcher drop and refill tables. Before they touch a database, a copy goes
+//! to `snapshots/<db>-YYYYMMDD-HHMMSS-mmm.db` (`VACUUM INTO`, consistent even
+//! while Superset reads the file) with a `.json` note naming the source file
+//! and the load that followed. Only the newest `snapshots.keep` copies of each
+//! database are kept. `data rollback <snapshot>` writes a copy back through
+//! SQLite's backup API, so open connections see the restored tables at once;
+//! the state being replaced is snapshotted first, so a rollback can be undone.
+
+use anyhow::{bail, Context, Result};
+use chrono::{Local, NaiveDateTime};
+use rusqlite::{Connection, DatabaseName};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+pub const SNAPSHOT_DIR: &str = "snapshots";
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S-%3f";
+
+/// Snapshot settings (`snapshots` section of config.json)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnapshotConfig {
+    /// Copy a database before every load that replaces tables in it
+    pub enabled: bool,
+    /// Snapshots kept per database
+    pub keep: usize,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self { enabled: true, keep: 5 }
+    }
+}
+
+/// Note stored next to each snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SnapshotNote {
+    /// Database file, relative to the root when it lives inside it
+    source: String,
+    /// What was about to change it
+    reason: String,
+}
+
+/// Snapshot found on disk
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotInfo {
+    /// Name given to `data rollback`
+    pub name: String,
+    pub path: PathBuf,
+    pub source: String,
+    pub reason: String,
+    pub created: NaiveDateTime,
+    pub size_bytes: u64,
+}
+
+impl SnapshotInfo {
+    /// Absolute path of the database this snapshot was taken from
+    pub fn source_path(&self, root: &Path) -> PathBuf {
+        root.join(&self.source)
+    }
+}
+
+fn source_key(root: &Path, db_path: &Path) -> String {
+    db_path
+        .strip_prefix(root)
+        .unwrap_or(db_path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Copy `db_path` into `snapshots/` before `reason` changes it.
+/// Returns `None` when snapshots are off or the database doesn't exist yet.
+pub fn take(root: &Path, config: &SnapshotConfig, db_path: &Path, reason: &str) -> Result<Option<PathBuf>> {
+    if !config.enabled || !db_path.exists() {
+        return Ok(None);
+    }
+    let (path, source) = copy(root, db_path, reason)?;
+    rotate(root, &source, config.keep)?;
+    Ok(Some(path))
+}
+
+/// Write the snapshot and its note, returns the snapshot path and source key
+fn copy(root: &Path, db_path: &Path, reason: &str) -> Result<(PathBuf, String)> {
+    let dir = root.join(SNAPSHOT_DIR);
+    fs::create_dir_all(&dir)?;
+
+    let stem = db_path.file_stem().unwrap_or_default().to_string_lossy();
+    let (name, path) = loop {
+        let name = format!("{}-{}", stem, Local::now().format(TIMESTAMP_FORMAT));
+        let path = dir.join(format!("{}.db", name));
+        if !path.exists() {
+            break (name, path);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    };
+    let conn = Connection::open(db_path).with_context(|| format!("Failed to open {}", db_path.display()))?;
+    conn.execute("VACUUM INTO ?1", [path.to_string_lossy().as_ref()])
+        .with_context(|| format!("Failed to snapshot {}", db_path.display()))?;
+
+    let note = SnapshotNote { source: source_key(root, db_path), reason: reason.to_string() };
+    fs::write(dir.join(format!("{}.json", name)), serde_json::to_string_pretty(&note)?)?;
+    info!("📸 Snapshot of {} saved as {}", note.source, name);
+    Ok((path, note.source))
+}
+
+/// All snapshots, newest first
+pub fn list(root: &Path) -> Result<Vec<SnapshotInfo>> {
+    let dir = root.join(SNAPSHOT_DIR);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut snapshots: Vec<SnapshotInfo> = fs::read_dir(&dir)?
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let path = e.path();
+            let name = path.file_name()?.to_str()?.strip_suffix(".db")?.to_string();
+            let note: SnapshotNote = serde_json::from_str(&fs::read_to_string(dir.join(format!("{}.json", name))).ok()?).ok()?;
+            let created = parse_created(&name)?;
+            let size_bytes = e.metadata().map(|m| m.len()).unwrap_or(0);
+            Some(SnapshotInfo { name, path, source: note.source, reason: note.reason, created, size_bytes })
+        })
+        .collect();
+    snapshots.sort_by(|a, b| b.created.cmp(&a.created));
+    Ok(snapshots)
+}
+
+/// Timestamp at the end of `<db>-YYYYMMDD-HHMMSS-mmm`
+fn parse_created(name: &str) -> Option<NaiveDateTime> {
+    let stamp_len = "YYYYMMDD-HHMMSS-mmm".len();
+    let stamp = name.get(name.len().checked_sub(stamp_len)?..)?;
+    NaiveDateTime::parse_from_str(stamp, TIMESTAMP_FORMAT).ok()
+}
+
+/// Delete all but the newest `keep` snapshots of `source`, returns number removed
+fn rotate(root: &Path, source: &str, keep: usize) -> Result<usize> {
+    let mut removed = 0;
+    for old in list(root)?.iter().filter(|s| s.source == source).skip(keep.max(1)) {
+        fs::remove_file(&old.path)?;
+        let _ = fs::remove_file(old.path.with_extension("json"));
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// Write snapshot `name` back over its database, returns the restored path
+pub fn rollback(root: &Path, config: &SnapshotConfig, name: &str) -> Result<PathBuf> {
+    let snapshots = list(root)?;
+    let Some(snapshot) = snapshots.iter().find(|s| s.name == name) else {
+        bail!("Snapshot '{}' not found — see: superset-launcher data snapshots", name);
+    };
+    let target = snapshot.source_path(root);
+
+    // The state being replaced is kept whether or not snapshots are enabled;
+    // rotation waits until the restore is done so it can't remove `name` first
+    if target.exists() {
+        copy(root, &target, &format!("rollback to {}", name))?;
+    }
+    let mut conn = Connection::open(&target).with_context(|| format!("Failed to open {}", target.display()))?;
+    conn.restore(DatabaseName::Main, &snapshot.path, None::<fn(rusqlite::backup::Progress)>)
+        .with_context(|| format!("Failed to restore {} from {}", target.display(), name))?;
+    info!("⏪ {} rolled back to {}", snapshot.source, name);
+    rotate(root, &snapshot.source, config.keep)?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn rows(db: &Path) -> i64 {
+        Connection::open(db).unwrap().query_row("SELECT COUNT(*) FROM ops", [], |r| r.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_take_rotate_and_rollback() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let db = root.join("examples.db");
+        let config = SnapshotConfig { enabled: true, keep: 2 };
+
+        assert!(take(root, &config, &db, "load-data ops").unwrap().is_none());
+        Connection::open(&db)
+            .unwrap()
+            .execute_batch("CREATE TABLE ops (n INTEGER); INSERT INTO ops VALUES (1), (2), (3);")
+            .unwrap();
+        take(root, &config, &db, "load-data ops").unwrap().unwrap();
+        let good = list(root).unwrap()[0].name.clone();
+        assert!(good.starts_with("examples-"));
+
+        // A bad load empties the table
+        Connection::open(&db).unwrap().execute("DELETE FROM ops", []).unwrap();
+        assert_eq!(rows(&db), 0);
+
+        let restored = rollback(root, &config, &good).unwrap();
+        assert_eq!(restored, db);
+        assert_eq!(rows(&db), 3);
+
+        let snapshots = list(root).unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].reason, format!("rollback to {}", good));
+        assert!(snapshots.iter().all(|s| s.source == "examples.db"));
+
+        for _ in 0..3 {
+            take(root, &config, &db, "import-demo").unwrap();
+        }
+        assert_eq!(list(root).unwrap().len(), 2);
+        assert!(rollback(root, &config, "missing").is_err());
+
+        let off = SnapshotConfig { enabled: false, ..config };
+        assert!(take(root, &off, &db, "load-data ops").unwrap().is_none());
+    }
+}
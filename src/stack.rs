@@ -0,0 +1,303 @@
+//! One-command full stack start/stop
+//!
+//! `up` starts services in dependency order, waiting for each one to become
+//! healthy before starting the next:
+//! Superset → docs server → LightDocs → gateway (proxies the first three) → watcher.
+//! If Superset does not come up, nothing after it is started.
+//! `down` stops a stack started by `up` from another console.
+
+use anyhow::{bail, Result};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+use crate::config::Config;
+use crate::docs_server::{DocsServer, DOCS_DEFAULT_PORT};
+use crate::health_check;
+use crate::lightdocs::{LightDocs, LightDocsConfig, LightDocsServer};
+use crate::python::PythonEnv;
+use crate::superset::SupersetServer;
+use crate::watcher::DataWatcher;
+
+const STACK_PID_FILE: &str = "stack.pid";
+
+/// How long to wait for the lightweight Rust services
+const SERVICE_READY_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Row of the summary table
+struct ServiceRow {
+    name: &'static str,
+    url: String,
+    ok: bool,
+}
+
+/// Services started so far; dropping it stops them, so an error halfway
+/// through `up` doesn't leave Superset running without the rest
+struct Running {
+    root: PathBuf,
+    superset: SupersetServer,
+    canary: Option<SupersetServer>,
+    docs: Option<DocsServer>,
+    watcher: Option<Arc<DataWatcher>>,
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        if let Some(watcher) = &self.watcher {
+            watcher.stop();
+        }
+        if let Some(docs) = &mut self.docs {
+            docs.stop();
+        }
+        if let Err(e) = self.superset.stop() {
+            warn!("Superset not stopped: {}", e);
+        }
+        if let Some(canary) = &mut self.canary {
+            if let Err(e) = canary.stop() {
+                warn!("Trial Superset not stopped: {}", e);
+            }
+        }
+        let _ = std::fs::remove_file(self.root.join(STACK_PID_FILE));
+    }
+}
+
+/// Start the full stack and block until Ctrl+C
+pub async fn up(root: &Path, python_env: &PythonEnv, config: &Config, gateway_port: u16) -> Result<()> {
+    if !python_env.is_valid() {
+        bail!("Python environment not found at: {}", python_env.python_path().display());
+    }
+
+    std::fs::write(root.join(STACK_PID_FILE), std::process::id().to_string())?;
+    let mut rows = Vec::new();
+
+    // 1. Superset
+    info!("[1/5] Starting Superset...");
+    if let Err(e) = crate::databases::register_all(root, &config.databases) {
+        warn!("Failed to register databases: {}", e);
+    }
+    if config.replica.enabled {
+        if let Err(e) = crate::replica::refresh_all(root, &config.databases) {
+            warn!("Failed to refresh read replicas: {}", e);
+        }
+    } else if let Err(e) = crate::replica::restore_all(root, &config.databases) {
+        warn!("Failed to switch back from read replicas: {}", e);
+    }
+    let mut running = Running {
+        root: root.to_path_buf(),
+        superset: SupersetServer::new(root, python_env, config.port),
+        canary: None,
+        docs: None,
+        watcher: None,
+    };
+    running.superset.start().await?;
+    let port = config.port;
+    let superset_timeout = Duration::from_secs(config.startup_timeout_secs);
+    let ok = wait_until("Superset", superset_timeout, || async move {
+        health_check::check_superset(port).await.unwrap_or(false)
+    })
+    .await;
+    if !ok {
+        bail!(
+            "Superset did not become healthy in {}s, the rest of the stack was not started (see logs/superset.stderr.log)",
+            config.startup_timeout_secs
+        );
+    }
+    rows.push(ServiceRow { name: "Superset", url: format!("http://localhost:{}", config.port), ok });
+
+    // Optional trial Superset behind the gateway's /_canary switch
+    if let Some(canary_config) = &config.gateway.canary {
+        info!("      Starting trial Superset (canary)...");
+        let env = PythonEnv::with_layout(
+            &root.join(&canary_config.python_dir),
+            &root.join(&canary_config.superset_home),
+        )?
+        .with_env(&config.env.superset)
+        .with_env(&config.env.canary);
+        let canary_port = canary_config.port;
+        let server = running.canary.insert(SupersetServer::secondary(root, env, canary_port, "superset_canary"));
+        let ok = match server.start().await {
+            Ok(()) => {
+                wait_until("Canary", superset_timeout, || async move {
+                    health_check::check_superset(canary_port).await.unwrap_or(false)
+                })
+                .await
+            }
+            Err(e) => {
+                warn!("Trial Superset not started: {}", e);
+                false
+            }
+        };
+        rows.push(ServiceRow { name: "Canary", url: format!("http://localhost:{}/_canary/on", gateway_port), ok });
+    }
+
+    // 2. Docs server
+    info!("[2/5] Starting docs server...");
+    let docs = running.docs.insert(DocsServer::new(root, DOCS_DEFAULT_PORT));
+    let ok = match docs.start().await {
+        Ok(()) => {
+            wait_until("Docs", SERVICE_READY_TIMEOUT, || async {
+                health_check::check_docs(DOCS_DEFAULT_PORT).await.unwrap_or(false)
+            })
+            .await
+        }
+        Err(e) => {
+            warn!("Docs server not started: {}", e);
+            false
+        }
+    };
+    rows.push(ServiceRow { name: "Docs", url: format!("http://localhost:{}", DOCS_DEFAULT_PORT), ok });
+
+    // 3. LightDocs
+    info!("[3/5] Building and starting LightDocs...");
+    let lightdocs_config = LightDocsConfig::load(root)?;
+    let lightdocs_port = lightdocs_config.port;
+    LightDocs::new(root)?.build()?;
+    LightDocsServer::new(root, &lightdocs_config.output_dir_abs(root), lightdocs_port).start_background();
+    let ok = wait_until("LightDocs", SERVICE_READY_TIMEOUT, || port_open(lightdocs_port)).await;
+    rows.push(ServiceRow { name: "LightDocs", url: format!("http://localhost:{}", lightdocs_port), ok });
+
+    // 4. Gateway (depends on Superset and docs)
+    info!("[4/5] Starting gateway...");
+    let gateway_root = root.to_path_buf();
+    let superset_port = config.port;
+    let gateway_config = config.gateway.clone();
+    tokio::spawn(async move {
+        if let Err(e) = crate::gateway::start_gateway(gateway_port, superset_port, &gateway_root, &gateway_config).await {
+            error!("Gateway error: {}", e);
+        }
+    });
+    let ok = wait_until("Gateway", SERVICE_READY_TIMEOUT, || port_open(gateway_port)).await;
+    rows.push(ServiceRow { name: "Gateway", url: format!("http://localhost:{}", gateway_port), ok });
+
+    // 5. Data watcher
+    info!("[5/5] Starting data watcher...");
+    let watcher = running.watcher.insert(Arc::new(DataWatcher::new(&root.to_path_buf()))).clone();
+    watcher.start().await;
+    let watcher_check = watcher.clone();
+    let ok = wait_until("Watcher", SERVICE_READY_TIMEOUT, || {
+        let w = watcher_check.clone();
+        async move { w.is_running() }
+    })
+    .await;
+    rows.push(ServiceRow { name: "Watcher", url: "docs/demo_data".to_string(), ok });
+
+    print_summary(&rows);
+    crate::slo::spawn_monitor(root, config.port, Some(lightdocs_port));
+
+    if config.open_browser {
+        let _ = open::that(format!("http://localhost:{}", gateway_port));
+    }
+
+    info!("Stack is up. Press Ctrl+C (or run `superset-launcher down`) to stop.");
+    tokio::signal::ctrl_c().await?;
+
+    info!("Stopping stack...");
+    drop(running);
+    let _ = crate::slo::flush(root);
+    info!("Stack stopped.");
+    Ok(())
+}
+
+/// Stop a stack started with `up` (possibly from another console)
+pub fn down(root: &Path) -> Result<()> {
+    let pid_path = root.join(STACK_PID_FILE);
+
+    // Superset runs as a child process with its own PID file
+    SupersetServer::stop_running()?;
+
+    if pid_path.exists() {
+        let pid = std::fs::read_to_string(&pid_path)?.trim().to_string();
+        if is_launcher(&pid) {
+            info!("Stopping launcher stack (PID: {})", pid);
+            kill_pid(&pid);
+        } else {
+            // The stack died without cleaning up and Windows reused its PID
+            warn!("PID {} from {} is not a launcher process, left alone", pid, STACK_PID_FILE);
+        }
+        std::fs::remove_file(&pid_path)?;
+    } else {
+        info!("No running stack found");
+    }
+
+    Ok(())
+}
+
+/// Executable name of this launcher, compared against the process behind a PID file
+fn launcher_exe_name() -> Option<String> {
+    let exe = std::env::current_exe().ok()?;
+    Some(exe.file_name()?.to_string_lossy().to_lowercase())
+}
+
+/// Whether `pid` is still a launcher process (not another program that got the PID)
+#[cfg(windows)]
+fn is_launcher(pid: &str) -> bool {
+    let Some(name) = launcher_exe_name() else {
+        return false;
+    };
+    std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+        .output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).to_lowercase().starts_with(&format!("\"{}\"", name)))
+        .unwrap_or(false)
+}
+
+#[cfg(not(windows))]
+fn is_launcher(pid: &str) -> bool {
+    let Some(name) = launcher_exe_name() else {
+        return false;
+    };
+    std::fs::read_link(format!("/proc/{}/exe", pid))
+        .ok()
+        .and_then(|exe| exe.file_name().map(|n| n.to_string_lossy().to_lowercase()))
+        .is_some_and(|exe| exe == name)
+}
+
+fn kill_pid(pid: &str) {
+    #[cfg(windows)]
+    {
+        let _ = std::process::Command::new("taskkill")
+            .args(["/F", "/T", "/PID", pid])
+            .output();
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = std::process::Command::new("kill").arg(pid).output();
+    }
+}
+
+/// Poll `check` every 500 ms until it succeeds or `timeout` expires
+async fn wait_until<F, Fut>(name: &str, timeout: Duration, check: F) -> bool
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if check().await {
+            info!("  ✅ {} ready in {:.1}s", name, start.elapsed().as_secs_f64());
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    warn!("  ❌ {} not ready after {}s", name, timeout.as_secs());
+    false
+}
+
+async fn port_open(port: u16) -> bool {
+    tokio::net::TcpStream::connect(("127.0.0.1", port)).await.is_ok()
+}
+
+fn print_summary(rows: &[ServiceRow]) {
+    println!();
+    println!("╔════════════════════════════════════════════╗");
+    println!("║          Superset Portable Stack           ║");
+    println!("╠════════════════════════════════════════════╣");
+    for row in rows {
+        let icon = if row.ok { "✅" } else { "❌" };
+        println!("║ {} {:<10} {:<29} ║", icon, row.name, row.url);
+    }
+    println!("╚════════════════════════════════════════════╝");
+    println!();
+}
@@ -5,13 +5,29 @@ use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{info, error, warn};
 
+use crate::credentials::CredentialStore;
 use crate::python::PythonEnv;
 use std::net::TcpListener;
 
 const PID_FILE: &str = "superset.pid";
 
+/// Instance name of the production Superset (PID and log file prefix)
+const MAIN_INSTANCE: &str = "superset";
+
+/// Environment variable carrying the password to [`FAB_WITH_PASSWORD`]
+const PASSWORD_ENV: &str = "LAUNCHER_ADMIN_PASSWORD";
+
+/// Runs the `superset` CLI with `--password` taken from the environment, so the
+/// password never shows up in the process list
+const FAB_WITH_PASSWORD: &str = "import os, sys
+from superset.cli.main import superset
+sys.argv = ['superset'] + sys.argv[1:] + ['--password', os.environ.pop('LAUNCHER_ADMIN_PASSWORD')]
+superset()
+";
+
 /// Get a free random port
 pub fn get_free_port() -> Result<u16> {
     let listener = TcpListener::bind("127.0.0.1:0")?;
@@ -25,17 +41,74 @@ pub struct SupersetServer {
     port: u16,
     process: Option<Child>,
     running: Arc<AtomicBool>,
+    /// PID/log file prefix, so a secondary instance doesn't clobber the main one
+    instance: &'static str,
 }
 
 impl SupersetServer {
     /// Create a new Superset server manager
-    pub fn new(root: &Path, _python_env: &PythonEnv, port: u16) -> Self {
+    pub fn new(root: &Path, python_env: &PythonEnv, port: u16) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            python_env: python_env.clone(),
+            port,
+            process: None,
+            running: Arc::new(AtomicBool::new(false)),
+            instance: MAIN_INSTANCE,
+        }
+    }
+    
+    /// Manager for an additional Superset instance with its own Python tree and home
+    pub fn secondary(root: &Path, python_env: PythonEnv, port: u16, instance: &'static str) -> Self {
         Self {
             root: root.to_path_buf(),
-            python_env: PythonEnv::new(root).unwrap(),
+            python_env,
             port,
             process: None,
             running: Arc::new(AtomicBool::new(false)),
+            instance,
+        }
+    }
+    
+    fn pid_path(&self) -> PathBuf {
+        self.root.join(format!("{}.pid", self.instance))
+    }
+    
+    /// Poll `/health` until Superset answers, reporting warm-up progress.
+    ///
+    /// Fails early if the process exits, or after `timeout`.
+    pub async fn wait_ready(&mut self, timeout: Duration) -> Result<()> {
+        let start = Instant::now();
+        let mut last_report = 0;
+        info!("⏳ Waiting for Superset to warm up (up to {}s)...", timeout.as_secs());
+        
+        loop {
+            if crate::health_check::check_superset(self.port).await.unwrap_or(false) {
+                info!("✅ Superset ready in {}s", start.elapsed().as_secs());
+                return Ok(());
+            }
+            
+            if let Some(ref mut process) = self.process {
+                if let Ok(Some(status)) = process.try_wait() {
+                    self.running.store(false, Ordering::SeqCst);
+                    anyhow::bail!("Superset exited during startup with status: {} (see logs/{}.stderr.log)", status, self.instance);
+                }
+            }
+            
+            let elapsed = start.elapsed().as_secs();
+            if elapsed >= timeout.as_secs() {
+                anyhow::bail!(
+                    "Superset did not become ready within {}s (see logs/{}.stderr.log, or raise startup_timeout_secs in config.json)",
+                    timeout.as_secs(),
+                    self.instance
+                );
+            }
+            if elapsed >= last_report + 10 {
+                info!("   ...warming up ({}s)", elapsed);
+                last_report = elapsed;
+            }
+            
+            tokio::time::sleep(Duration::from_secs(1)).await;
         }
     }
     
@@ -46,13 +119,26 @@ impl SupersetServer {
             return Ok(());
         }
         
-        let superset_home = self.root.join("superset_home");
+        let superset_home = self.python_env.superset_home().to_path_buf();
         let logs_dir = self.root.join("logs");
         
         // Ensure directories exist
         std::fs::create_dir_all(&superset_home)?;
         std::fs::create_dir_all(&logs_dir)?;
         
+        // Keep a copy of SECRET_KEY, and say so loudly if the config's key was replaced;
+        // bring the query limits of config.json into superset_config.py
+        if superset_home == self.root.join("superset_home") {
+            let config = crate::config::Config::load_or_create(&self.root).unwrap_or_default();
+            let store = CredentialStore::open(&self.root, &config.credentials);
+            if let Err(e) = crate::secret_key::ensure_backed_up(&self.root, &store) {
+                warn!("SECRET_KEY check skipped: {}", e);
+            }
+            if let Err(e) = crate::query_limits::apply(&self.root, &config) {
+                warn!("Query limits not written to superset_config.py: {:#}", e);
+            }
+        }
+        
         // Build command
         let mut cmd = Command::new(self.python_env.python_path());
         
@@ -69,11 +155,14 @@ impl SupersetServer {
             "-h", "127.0.0.1",
             "-p", &self.port.to_string(),
         ]);
+        if !crate::hardware::current().flask_threaded {
+            cmd.arg("--without-threads");
+        }
         
         cmd.current_dir(&self.root);
         
-        let stdout_file = std::fs::File::create(logs_dir.join("superset.stdout.log"))?;
-        let stderr_file = std::fs::File::create(logs_dir.join("superset.stderr.log"))?;
+        let stdout_file = std::fs::File::create(logs_dir.join(format!("{}.stdout.log", self.instance)))?;
+        let stderr_file = std::fs::File::create(logs_dir.join(format!("{}.stderr.log", self.instance)))?;
         
         cmd.stdout(Stdio::from(stdout_file));
         cmd.stderr(Stdio::from(stderr_file));
@@ -87,7 +176,7 @@ impl SupersetServer {
         info!("Superset started with PID: {}", pid);
         
         // Save PID file
-        let pid_path = self.root.join(PID_FILE);
+        let pid_path = self.pid_path();
         std::fs::write(&pid_path, pid.to_string())?;
         
         self.process = Some(child);
@@ -105,6 +194,10 @@ impl SupersetServer {
                 }
                 Ok(None) => {
                     info!("Superset is running on http://127.0.0.1:{}", self.port);
+                    if self.instance == MAIN_INSTANCE {
+                        crate::slo::add(crate::slo::Metric::Starts, 1);
+                        let _ = crate::slo::flush(&self.root);
+                    }
                     
                     // Pre-warm server to load Python modules
                     let port = self.port;
@@ -150,7 +243,7 @@ impl SupersetServer {
             self.running.store(false, Ordering::SeqCst);
             
             // Clean up PID file
-            let pid_path = self.root.join(PID_FILE);
+            let pid_path = self.pid_path();
             let _ = std::fs::remove_file(&pid_path);
         }
         Ok(())
@@ -164,12 +257,19 @@ impl SupersetServer {
             self.running.store(false, Ordering::SeqCst);
             
             // Clean up PID file
-            let pid_path = self.root.join(PID_FILE);
+            let pid_path = self.pid_path();
             let _ = std::fs::remove_file(&pid_path);
         }
         Ok(())
     }
     
+    /// Exit status if the process has ended on its own (doesn't block)
+    pub fn exit_status(&mut self) -> Option<std::process::ExitStatus> {
+        let status = self.process.as_mut()?.try_wait().ok()??;
+        self.running.store(false, Ordering::SeqCst);
+        Some(status)
+    }
+    
     /// Check if running
     pub fn is_running(&self) -> bool {
         self.running.load(Ordering::SeqCst)
@@ -217,15 +317,22 @@ impl SupersetServer {
     }
 }
 
-/// Initialize Superset (first-time setup)
-pub async fn initialize(root: &Path, python_env: &PythonEnv, username: &str, password: &str) -> Result<()> {
+/// Initialize Superset (first-time setup). Returns `false` when the admin user
+/// already existed: its password is left as it was.
+pub async fn initialize(
+    root: &Path,
+    python_env: &PythonEnv,
+    store: &CredentialStore,
+    username: &str,
+    password: &str,
+) -> Result<bool> {
     let superset_home = root.join("superset_home");
     std::fs::create_dir_all(&superset_home)?;
     
     // Create superset_config.py if not exists
     let config_path = superset_home.join("superset_config.py");
     if !config_path.exists() {
-        let secret_key = generate_secret_key();
+        let secret_key = crate::secret_key::key_for_new_config(root, store, generate_secret_key)?;
         let config_content = format!(r#"
 # Superset Portable Configuration
 import os
@@ -252,6 +359,20 @@ CACHE_CONFIG = {{
     'CACHE_TYPE': 'SimpleCache',
     'CACHE_DEFAULT_TIMEOUT': 300,
 }}
+
+# Gateway request ID (X-Request-Id) in every log line
+import logging
+from flask import has_request_context, request
+
+_record_factory = logging.getLogRecordFactory()
+
+def _request_id_record(*args, **kwargs):
+    record = _record_factory(*args, **kwargs)
+    record.request_id = request.headers.get("X-Request-Id", "-") if has_request_context() else "-"
+    return record
+
+logging.setLogRecordFactory(_request_id_record)
+LOG_FORMAT = "%(asctime)s:%(levelname)s:%(name)s:[%(request_id)s] %(message)s"
 "#, secret_key);
         
         std::fs::write(&config_path, config_content)?;
@@ -266,17 +387,21 @@ CACHE_CONFIG = {{
     }
     
     info!("Creating admin user...");
-    let output = python_env.run_python(&[
-        "-m", "superset", "fab", "create-admin",
+    let output = run_with_password(python_env, &[
+        "fab", "create-admin",
         "--username", username,
-        "--password", password,
         "--firstname", "Admin",
         "--lastname", "User", 
         "--email", "admin@localhost",
-    ])?;
+    ], password)?;
     if !output.status.success() {
-        // User might already exist, not a fatal error
-        warn!("Admin creation output: {}", String::from_utf8_lossy(&output.stderr));
+        error!("Admin creation failed: {}", String::from_utf8_lossy(&output.stderr));
+        return Err(anyhow::anyhow!("Admin creation failed"));
+    }
+    // FAB reports an existing user on stdout and still exits with 0
+    let created = !String::from_utf8_lossy(&output.stdout).contains("already exists");
+    if !created {
+        warn!("Admin user '{}' already exists, its password was not changed", username);
     }
     
     info!("Initializing Superset...");
@@ -287,11 +412,29 @@ CACHE_CONFIG = {{
     }
     
     info!("Superset initialization complete!");
+    Ok(created)
+}
+
+/// Set a new password for an existing Superset user
+pub fn reset_password(python_env: &PythonEnv, username: &str, password: &str) -> Result<()> {
+    let output = run_with_password(python_env, &["fab", "reset-password", "--username", username], password)?;
+    if !output.status.success() {
+        error!("Password reset failed: {}", String::from_utf8_lossy(&output.stderr));
+        return Err(anyhow::anyhow!("Password reset failed"));
+    }
     Ok(())
 }
 
+/// Run a `superset` CLI command that takes `--password`
+fn run_with_password(python_env: &PythonEnv, args: &[&str], password: &str) -> Result<std::process::Output> {
+    let env = std::collections::BTreeMap::from([(PASSWORD_ENV.to_string(), password.to_string())]);
+    let mut command = vec!["-c", FAB_WITH_PASSWORD];
+    command.extend_from_slice(args);
+    python_env.clone().with_env(&env).run_python(&command)
+}
+
 /// Generate a cryptographically secure random secret key
-fn generate_secret_key() -> String {
+pub fn generate_secret_key() -> String {
     use rand::Rng;
     let key: [u8; 32] = rand::thread_rng().gen();
     format!("portable-superset-{}", hex::encode(key))
@@ -0,0 +1,144 @@
+//! Superset metadata in the knowledge base search
+//!
+//! Charts, dashboards, datasets and SQL Lab saved queries from superset.db are
+//! indexed into the LightDocs sled index under `superset:` keys, so one search
+//! box finds both articles and Superset objects (with deep links).
+
+use anyhow::Result;
+use rusqlite::{Connection, OpenFlags};
+use std::path::Path;
+use tracing::info;
+
+use crate::lightdocs::search::SearchIndex;
+
+/// Key prefix for Superset objects in the search index
+const KEY_PREFIX: &str = "superset:";
+
+/// Meta key holding the [`fingerprint`] of superset.db at last indexing
+const MTIME_KEY: &str = "superset_db_mtime";
+
+/// (kind, query, deep link pattern) — queries return id, title, text
+const SOURCES: &[(&str, &str, &str)] = &[
+    (
+        "chart",
+        "SELECT id, slice_name, COALESCE(description, '') || ' ' || COALESCE(viz_type, '') FROM slices",
+        "/explore/?slice_id={id}",
+    ),
+    (
+        "dashboard",
+        "SELECT COALESCE(slug, id), dashboard_title, COALESCE(description, '') FROM dashboards",
+        "/superset/dashboard/{id}/",
+    ),
+    (
+        "dataset",
+        "SELECT id, table_name, COALESCE(schema, '') || ' ' || COALESCE(description, '') FROM tables",
+        "/explore/?datasource_type=table&datasource_id={id}",
+    ),
+    (
+        "query",
+        "SELECT id, label, COALESCE(description, '') || ' ' || COALESCE(sql, '') FROM saved_query",
+        "/sqllab/?savedQueryId={id}",
+    ),
+];
+
+/// Re-index Superset objects if superset.db changed since the last run.
+/// Returns the number of indexed objects (0 when nothing changed).
+pub fn refresh(root: &Path, index: &SearchIndex) -> Result<usize> {
+    let db_path = root.join("superset_home").join("superset.db");
+    let Some(mtime) = fingerprint(&db_path) else {
+        return Ok(0);
+    };
+    if index.meta(MTIME_KEY)?.as_deref() == Some(mtime.as_str()) {
+        return Ok(0);
+    }
+
+    let conn = Connection::open_with_flags(&db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    index.remove_prefix(KEY_PREFIX)?;
+
+    let mut count = 0;
+    for (kind, sql, url) in SOURCES {
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, rusqlite::types::Value>(0)?,
+                row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            ))
+        })?;
+
+        for row in rows {
+            let (id, title, text) = row?;
+            let id = match id {
+                rusqlite::types::Value::Integer(i) => i.to_string(),
+                rusqlite::types::Value::Text(t) => t,
+                _ => continue,
+            };
+            let key = format!("{}{}:{}", KEY_PREFIX, kind, id);
+            index.index_object(&key, kind, &title, &text, &url.replace("{id}", &id))?;
+            count += 1;
+        }
+    }
+
+    index.set_meta(MTIME_KEY, &mtime)?;
+    index.flush()?;
+    info!("🔎 Indexed {} Superset objects", count);
+    Ok(count)
+}
+
+/// Modification time and size of the database and of its `-wal` file:
+/// in WAL mode Superset's writes reach superset.db itself only at a checkpoint
+fn fingerprint(db_path: &Path) -> Option<String> {
+    let stamp = |path: &Path| {
+        std::fs::metadata(path).ok().map(|meta| {
+            let modified = meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .unwrap_or_default()
+                .as_nanos();
+            format!("{}:{}", modified, meta.len())
+        })
+    };
+    let mut wal = db_path.as_os_str().to_owned();
+    wal.push("-wal");
+    let wal = stamp(Path::new(&wal)).unwrap_or_default();
+    Some(format!("{}/{}", stamp(db_path)?, wal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_refresh_indexes_superset_objects() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("superset_home")).unwrap();
+        let conn = Connection::open(root.join("superset_home").join("superset.db")).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE slices (id INTEGER, slice_name TEXT, description TEXT, viz_type TEXT);
+             CREATE TABLE dashboards (id INTEGER, dashboard_title TEXT, slug TEXT, description TEXT);
+             CREATE TABLE tables (id INTEGER, table_name TEXT, schema TEXT, description TEXT);
+             CREATE TABLE saved_query (id INTEGER, label TEXT, description TEXT, sql TEXT);
+             INSERT INTO slices VALUES (7, 'Инциденты по типам', NULL, 'pie');
+             INSERT INTO dashboards VALUES (1, 'РЖД Аналитика', 'rzd_analytics', NULL);
+             INSERT INTO tables VALUES (3, 'rzd_incidents', NULL, 'Инциденты');",
+        )
+        .unwrap();
+        drop(conn);
+
+        let index = SearchIndex::open(root).unwrap();
+        assert_eq!(refresh(root, &index).unwrap(), 3);
+        // Unchanged database is not re-indexed
+        assert_eq!(refresh(root, &index).unwrap(), 0);
+        // A write still sitting in the WAL is noticed
+        std::fs::write(root.join("superset_home").join("superset.db-wal"), b"wal").unwrap();
+        assert_eq!(refresh(root, &index).unwrap(), 3);
+
+        let results = index.search("инциденты").unwrap();
+        assert_eq!(results.len(), 2);
+        let dashboards = index.search("аналитика").unwrap();
+        assert_eq!(dashboards[0].url.as_deref(), Some("/superset/dashboard/rzd_analytics/"));
+    }
+}
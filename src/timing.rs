@@ -0,0 +1,172 @@
+//! How long the heavy commands took, and what to try when they were slow
+//!
+//! `pack`, `unpack`, `load-data` and the site builds log their duration when
+//! they finish. One that ran past its threshold measures how fast the drive
+//! it worked on writes and prints hints that fit this machine — a kit packed
+//! on a stick in a USB 2.0 port needs different advice than a load on a
+//! two-core laptop.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::hardware::{self, DiskKind, Profile, Tuning};
+
+/// Below this a drive writes like USB 2.0 (or a worn stick)
+const SLOW_DRIVE_MB_S: f64 = 35.0;
+
+/// Sequential writes of a typical internal disk, for "N times slower"
+const LOCAL_DISK_MB_S: f64 = 200.0;
+
+/// A timed command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// `lightdocs build`, `docs build`
+    Build,
+    Pack { zstd: bool },
+    Unpack,
+    LoadData,
+}
+
+impl Operation {
+    fn name(&self) -> &'static str {
+        match self {
+            Operation::Build => "build",
+            Operation::Pack { .. } => "pack",
+            Operation::Unpack => "unpack",
+            Operation::LoadData => "load-data",
+        }
+    }
+
+    /// Duration after which hints are printed
+    fn threshold(&self) -> Duration {
+        Duration::from_secs(match self {
+            Operation::Build => 30,
+            Operation::Pack { .. } => 180,
+            Operation::Unpack => 120,
+            Operation::LoadData => 60,
+        })
+    }
+}
+
+/// Times one command; call [`CommandTimer::finish`] when it succeeded
+pub struct CommandTimer {
+    operation: Operation,
+    /// Folder on the drive the command writes to
+    target: PathBuf,
+    started: Instant,
+}
+
+impl CommandTimer {
+    pub fn start(operation: Operation, target: &Path) -> Self {
+        Self { operation, target: target.to_path_buf(), started: Instant::now() }
+    }
+
+    /// Log the duration, with hints when it was over the threshold
+    pub fn finish(self, tuning: &Tuning) {
+        let elapsed = self.started.elapsed();
+        info!("⏱️ {} took {}", self.operation.name(), format_duration(elapsed));
+        if elapsed < self.operation.threshold() {
+            return;
+        }
+        let write_speed = hardware::write_speed_mb_s(&self.target);
+        for hint in hints(self.operation, tuning, write_speed) {
+            warn!("💡 {}", hint);
+        }
+    }
+}
+
+/// Advice for a slow `operation` on this machine; `write_speed` of the
+/// target drive in MB/s, if it could be measured
+pub fn hints(operation: Operation, tuning: &Tuning, write_speed: Option<f64>) -> Vec<String> {
+    let mut hints = Vec::new();
+    let hardware = &tuning.hardware;
+    match write_speed {
+        Some(speed) if speed < SLOW_DRIVE_MB_S => hints.push(format!(
+            "The drive writes ~{:.0} MB/s (USB 2.0 speeds): expect {} about {:.0}x slower than on an internal disk; \
+             use a USB 3 port or work on a copy on a local disk",
+            speed,
+            operation.name(),
+            (LOCAL_DISK_MB_S / speed.max(1.0)).max(2.0)
+        )),
+        None if hardware.disk == DiskKind::Removable => hints.push(format!(
+            "The kit is on a removable drive; {} runs faster on a copy on a local disk",
+            operation.name()
+        )),
+        // Measured fast enough: the drive is not the problem
+        _ => {}
+    }
+    match operation {
+        Operation::Pack { zstd } => {
+            if !zstd {
+                hints.push("ZIP compresses slowly in one thread; `pack --zstd` is usually several times faster".to_string());
+            } else if tuning.profile == Profile::High {
+                hints.push(format!(
+                    "Profile high packs at zstd level {}; `\"hardware\": {{\"profile\": \"standard\"}}` packs faster at level 3",
+                    tuning.zstd_level
+                ));
+            }
+            hints.push("`pack analyze` shows what takes the most space; pruning it speeds up every pack".to_string());
+        }
+        Operation::Unpack => {
+            hints.push("To fix a damaged folder, `unpack --only <folder>` restores just that part".to_string());
+        }
+        Operation::LoadData => {
+            if hardware.cores <= 2 {
+                hints.push(format!("Only {} CPU core(s): files are parsed on {} thread(s)", hardware.cores, tuning.polars_threads));
+            }
+            let budget = tuning.load_memory_mb;
+            if tuning.profile != Profile::Low && hardware.ram_mb.is_some_and(|ram| ram >= budget * 4) {
+                hints.push(format!(
+                    "Loads read at most {} MB at a time (profile {}); this machine has {} MB, raise `load_memory_mb` in config.json",
+                    budget,
+                    tuning.profile,
+                    hardware.ram_mb.unwrap_or_default()
+                ));
+            }
+            hints.push("`load-data --preview` estimates a load from the start of the file before running it".to_string());
+        }
+        Operation::Build => {
+            hints.push("Attachments are copied on every build; link large files on a share instead of keeping them in the knowledge base".to_string());
+        }
+    }
+    hints
+}
+
+/// `4.3s`, `3m 05s`, `1h 02m`
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=59 => format!("{:.1}s", duration.as_secs_f64()),
+        60..=3599 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::Hardware;
+
+    #[test]
+    fn test_hints_fit_the_machine() {
+        let stick = Tuning::new(Hardware { cores: 2, ram_mb: Some(4096), disk: DiskKind::Removable }, Profile::Auto);
+        let pack = hints(Operation::Pack { zstd: false }, &stick, Some(20.0));
+        assert!(pack[0].contains("~20 MB/s (USB 2.0 speeds): expect pack about 10x slower"));
+        assert!(pack.iter().any(|h| h.contains("--zstd")));
+        assert!(hints(Operation::Unpack, &stick, None)[0].contains("removable drive"));
+
+        let load = hints(Operation::LoadData, &stick, Some(150.0));
+        assert!(load[0].starts_with("Only 2 CPU core(s)"));
+        assert!(!load.iter().any(|h| h.contains("load_memory_mb")));
+
+        let workstation = Tuning::new(Hardware { cores: 16, ram_mb: Some(65536), disk: DiskKind::Fixed }, Profile::Auto);
+        let load = hints(Operation::LoadData, &workstation, Some(400.0));
+        assert!(load[0].contains("at most 4096 MB"));
+        assert!(hints(Operation::Pack { zstd: true }, &workstation, None)[0].contains("zstd level 9"));
+
+        assert_eq!(format_duration(Duration::from_millis(4300)), "4.3s");
+        assert_eq!(format_duration(Duration::from_secs(185)), "3m 05s");
+        assert_eq!(format_duration(Duration::from_secs(3720)), "1h 02m");
+    }
+}
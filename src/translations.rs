@@ -0,0 +1,388 @@
+//! Dashboard and chart titles in other languages
+//!
+//! `translations.yaml` in the kit root maps the titles dashboards and charts
+//! were created with to other languages:
+//!
+//! ```yaml
+//! source: ru
+//! dashboards:
+//!   Перевозки РЖД: { en: RZD freight }
+//! charts:
+//!   Погрузка по дням: { en: Daily loading }
+//! labels:
+//!   Объём, т: { en: Volume, t }
+//! ```
+//!
+//! `dashboard translate en` rewrites `dashboard_title` and `slice_name` in
+//! superset.db, along with `labels`: headers and tab names of dashboard
+//! layouts and axis titles of charts. The values before the first rewrite
+//! are kept in a launcher table of superset.db, so `dashboard translate ru`
+//! (the `source` language) brings them back and one bundle ships both demo
+//! variants. A title changed in Superset after a rewrite becomes the new
+//! original. Titles without a translation stay as they are.
+
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// Translations file (relative to root)
+pub const TRANSLATIONS_FILE: &str = "translations.yaml";
+
+/// Values as they were before the first rewrite, and what was written last
+const ORIGINALS_TABLE: &str = "launcher_translation_originals";
+
+/// Chart params holding axis titles and captions
+const LABEL_PARAMS: &[&str] = &["x_axis_title", "y_axis_title", "x_axis_label", "y_axis_label", "subheader"];
+
+/// Layout components whose `meta.text` is shown as a caption
+const LABEL_COMPONENTS: &[&str] = &["HEADER", "TAB"];
+
+/// Original text -> language -> translation
+pub type TranslationMap = BTreeMap<String, BTreeMap<String, String>>;
+
+/// Contents of `translations.yaml`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Translations {
+    /// Language the titles were created in; applying it restores them
+    #[serde(default = "default_source")]
+    pub source: String,
+    #[serde(default)]
+    pub dashboards: TranslationMap,
+    #[serde(default)]
+    pub charts: TranslationMap,
+    /// Layout headers, tab names and axis titles
+    #[serde(default)]
+    pub labels: TranslationMap,
+}
+
+fn default_source() -> String {
+    "ru".to_string()
+}
+
+impl Translations {
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = root.join(TRANSLATIONS_FILE);
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("{} not found (run `dashboard translate --init`)", path.display()))?;
+        serde_yaml::from_str(&text).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Languages the file has translations for, plus the source
+    pub fn languages(&self) -> BTreeSet<String> {
+        [&self.dashboards, &self.charts, &self.labels]
+            .into_iter()
+            .flat_map(|map| map.values().flat_map(|t| t.keys().cloned()))
+            .chain([self.source.clone()])
+            .collect()
+    }
+}
+
+/// What [`apply`] changed
+#[derive(Debug, Default)]
+pub struct ApplySummary {
+    pub dashboards: usize,
+    pub charts: usize,
+    /// Dashboard and chart titles without a translation
+    pub missing: Vec<String>,
+}
+
+fn open_superset_db(root: &Path) -> Result<Connection> {
+    let db_path = root.join("superset_home").join("superset.db");
+    if !db_path.exists() {
+        bail!("superset.db not found at {}", db_path.display());
+    }
+    let conn = Connection::open(&db_path).with_context(|| format!("Failed to open {}", db_path.display()))?;
+    // Superset may be running and writing too
+    conn.busy_timeout(std::time::Duration::from_secs(10))?;
+    Ok(conn)
+}
+
+/// Write `translations.yaml` listing the original titles of every dashboard
+/// and chart, with empty `en` translations to fill in
+pub fn init(root: &Path) -> Result<PathBuf> {
+    let path = root.join(TRANSLATIONS_FILE);
+    if path.exists() {
+        bail!("{} already exists", path.display());
+    }
+    let conn = open_superset_db(root)?;
+    let has_originals = conn
+        .query_row("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1", [ORIGINALS_TABLE], |_| Ok(()))
+        .optional()?
+        .is_some();
+    let titles = |table: &str, column: &str, kind: &str| -> Result<TranslationMap> {
+        // Originals of already translated objects, not what is shown now
+        let sql = if has_originals {
+            format!(
+                "SELECT COALESCE(o.original, t.{column}) FROM {table} t LEFT JOIN {ORIGINALS_TABLE} o \
+                 ON o.kind = '{kind}' AND o.object_id = t.id AND o.field = '{column}' WHERE t.{column} IS NOT NULL"
+            )
+        } else {
+            format!("SELECT {column} FROM {table} WHERE {column} IS NOT NULL")
+        };
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map([], |r| r.get::<_, String>(0))?;
+        let mut map = TranslationMap::new();
+        for title in rows {
+            map.insert(title?, BTreeMap::from([("en".to_string(), String::new())]));
+        }
+        Ok(map)
+    };
+    let translations = Translations {
+        source: default_source(),
+        dashboards: titles("dashboards", "dashboard_title", "dashboard")?,
+        charts: titles("slices", "slice_name", "chart")?,
+        labels: TranslationMap::new(),
+    };
+    let header = "# Переводы названий дашбордов и графиков: `dashboard translate en` применяет,\n\
+                  # `dashboard translate ru` возвращает исходные. Пустой перевод — название не меняется.\n\
+                  # labels: заголовки и вкладки дашбордов, подписи осей графиков.\n";
+    std::fs::write(&path, format!("{}{}", header, serde_yaml::to_string(&translations)?))?;
+    Ok(path)
+}
+
+/// Rewrite titles in superset.db to `lang` (`translations.source` restores the originals)
+pub fn apply(root: &Path, translations: &Translations, lang: &str) -> Result<ApplySummary> {
+    if !translations.languages().contains(lang) {
+        bail!(
+            "No '{}' translations in {} (languages: {})",
+            lang,
+            TRANSLATIONS_FILE,
+            translations.languages().into_iter().collect::<Vec<_>>().join(", ")
+        );
+    }
+    let mut conn = open_superset_db(root)?;
+    conn.execute_batch(&format!(
+        "CREATE TABLE IF NOT EXISTS {ORIGINALS_TABLE} (
+            kind TEXT NOT NULL,
+            object_id INTEGER NOT NULL,
+            field TEXT NOT NULL,
+            original TEXT NOT NULL,
+            applied TEXT NOT NULL,
+            PRIMARY KEY (kind, object_id, field)
+        )"
+    ))?;
+    let tx = conn.transaction()?;
+    let translator = Translator { translations, lang, restore: lang == translations.source };
+    let mut summary = ApplySummary::default();
+
+    let dashboards: Vec<(i64, Option<String>, Option<String>)> = tx
+        .prepare("SELECT id, dashboard_title, position_json FROM dashboards ORDER BY id")?
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    for (id, title, position) in dashboards {
+        let title = rewrite(&tx, "dashboard", id, "dashboard_title", title, |original| {
+            translator.title(&translator.translations.dashboards, original, &mut summary.missing)
+        })?;
+        let position = rewrite(&tx, "dashboard", id, "position_json", position, |original| {
+            translator.json(original, |layout| translator.layout(layout))
+        })?;
+        if title.is_some() || position.is_some() {
+            tx.execute(
+                "UPDATE dashboards SET dashboard_title = COALESCE(?1, dashboard_title), position_json = COALESCE(?2, position_json) WHERE id = ?3",
+                params![title, position, id],
+            )?;
+            summary.dashboards += 1;
+        }
+    }
+
+    let charts: Vec<(i64, Option<String>, Option<String>)> = tx
+        .prepare("SELECT id, slice_name, params FROM slices ORDER BY id")?
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    for (id, name, chart_params) in charts {
+        let name = rewrite(&tx, "chart", id, "slice_name", name, |original| {
+            translator.title(&translator.translations.charts, original, &mut summary.missing)
+        })?;
+        let chart_params = rewrite(&tx, "chart", id, "params", chart_params, |original| {
+            translator.json(original, |params| translator.chart_params(params))
+        })?;
+        if name.is_some() || chart_params.is_some() {
+            tx.execute(
+                "UPDATE slices SET slice_name = COALESCE(?1, slice_name), params = COALESCE(?2, params) WHERE id = ?3",
+                params![name, chart_params, id],
+            )?;
+            summary.charts += 1;
+        }
+    }
+
+    if translator.restore {
+        tx.execute(&format!("DELETE FROM {ORIGINALS_TABLE}"), [])?;
+    }
+    tx.commit()?;
+    Ok(summary)
+}
+
+/// The new value of one field, computed from its original by `translate`;
+/// `None` when it stays as it is. Records the original on the first rewrite.
+fn rewrite(
+    tx: &Transaction,
+    kind: &str,
+    id: i64,
+    field: &str,
+    current: Option<String>,
+    translate: impl FnOnce(&str) -> String,
+) -> Result<Option<String>> {
+    let Some(current) = current else {
+        return Ok(None);
+    };
+    let stored: Option<(String, String)> = tx
+        .query_row(
+            &format!("SELECT original, applied FROM {ORIGINALS_TABLE} WHERE kind = ?1 AND object_id = ?2 AND field = ?3"),
+            params![kind, id, field],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .optional()?;
+    // Edited in Superset since the last rewrite: that is the original now
+    let original = match stored {
+        Some((original, applied)) if applied == current => original,
+        _ => current.clone(),
+    };
+    let value = translate(&original);
+    tx.execute(
+        &format!(
+            "INSERT INTO {ORIGINALS_TABLE} (kind, object_id, field, original, applied) VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT (kind, object_id, field) DO UPDATE SET original = excluded.original, applied = excluded.applied"
+        ),
+        params![kind, id, field, original, value],
+    )?;
+    Ok((value != current).then_some(value))
+}
+
+struct Translator<'a> {
+    translations: &'a Translations,
+    lang: &'a str,
+    /// Applying the source language
+    restore: bool,
+}
+
+impl Translator<'_> {
+    fn lookup(&self, map: &TranslationMap, original: &str) -> Option<String> {
+        if self.restore {
+            return None;
+        }
+        map.get(original.trim())
+            .and_then(|t| t.get(self.lang))
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty())
+            .map(str::to_string)
+    }
+
+    fn title(&self, map: &TranslationMap, original: &str, missing: &mut Vec<String>) -> String {
+        self.lookup(map, original).unwrap_or_else(|| {
+            if !self.restore {
+                missing.push(original.to_string());
+            }
+            original.to_string()
+        })
+    }
+
+    fn label(&self, original: &str) -> Option<String> {
+        self.lookup(&self.translations.labels, original)
+    }
+
+    /// JSON text with `edit` applied; the original text when nothing changed
+    fn json(&self, original: &str, edit: impl FnOnce(&mut Value)) -> String {
+        let Ok(mut value) = serde_json::from_str::<Value>(original) else {
+            return original.to_string();
+        };
+        let before = value.clone();
+        edit(&mut value);
+        if value == before {
+            original.to_string()
+        } else {
+            value.to_string()
+        }
+    }
+
+    /// Headers and tab names, chart names shown on the dashboard
+    fn layout(&self, layout: &mut Value) {
+        let Some(components) = layout.as_object_mut() else {
+            return;
+        };
+        for component in components.values_mut() {
+            let kind = component.get("type").and_then(Value::as_str).unwrap_or_default().to_string();
+            let Some(meta) = component.get_mut("meta").and_then(Value::as_object_mut) else {
+                continue;
+            };
+            let keys: &[&str] = match kind.as_str() {
+                k if LABEL_COMPONENTS.contains(&k) => &["text"],
+                "CHART" => &["sliceName", "sliceNameOverride"],
+                _ => continue,
+            };
+            for key in keys {
+                let Some(text) = meta.get(*key).and_then(Value::as_str) else {
+                    continue;
+                };
+                let translated = if kind == "CHART" {
+                    self.lookup(&self.translations.charts, text).or_else(|| self.label(text))
+                } else {
+                    self.label(text)
+                };
+                if let Some(translated) = translated {
+                    meta.insert(key.to_string(), Value::String(translated));
+                }
+            }
+        }
+    }
+
+    fn chart_params(&self, params: &mut Value) {
+        for key in LABEL_PARAMS {
+            let translated = params.get(*key).and_then(Value::as_str).and_then(|text| self.label(text));
+            if let Some(translated) = translated {
+                params[*key] = Value::String(translated);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_apply_and_restore() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("superset_home")).unwrap();
+        let conn = Connection::open(root.join("superset_home").join("superset.db")).unwrap();
+        conn.execute_batch(
+            r#"CREATE TABLE dashboards (id INTEGER PRIMARY KEY, dashboard_title TEXT, position_json TEXT);
+               CREATE TABLE slices (id INTEGER PRIMARY KEY, slice_name TEXT, params TEXT);
+               INSERT INTO dashboards VALUES (1, 'Перевозки', '{"HEADER-1":{"type":"HEADER","meta":{"text":"Итоги"}},"CHART-1":{"type":"CHART","meta":{"sliceName":"Погрузка"}}}');
+               INSERT INTO dashboards VALUES (2, 'Черновик', NULL);
+               INSERT INTO slices VALUES (1, 'Погрузка', '{"viz_type":"line","y_axis_title":"Объём, т"}');"#,
+        )
+        .unwrap();
+        let translations: Translations = serde_yaml::from_str(
+            "dashboards:\n  Перевозки: { en: Freight }\n  Черновик: { en: '' }\ncharts:\n  Погрузка: { en: Loading }\nlabels:\n  Итоги: { en: Totals }\n  Объём, т: { en: 'Volume, t' }\n",
+        )
+        .unwrap();
+        assert!(apply(root, &translations, "de").is_err());
+
+        let summary = apply(root, &translations, "en").unwrap();
+        assert_eq!((summary.dashboards, summary.charts), (1, 1));
+        assert_eq!(summary.missing, vec!["Черновик"]);
+        let row = |sql: &str| conn.query_row(sql, [], |r| r.get::<_, String>(0)).unwrap();
+        assert_eq!(row("SELECT dashboard_title FROM dashboards WHERE id = 1"), "Freight");
+        let position = row("SELECT position_json FROM dashboards WHERE id = 1");
+        assert!(position.contains(r#""text":"Totals""#) && position.contains(r#""sliceName":"Loading""#));
+        assert_eq!(row("SELECT slice_name FROM slices"), "Loading");
+        assert!(row("SELECT params FROM slices").contains(r#""y_axis_title":"Volume, t""#));
+
+        // Applying twice changes nothing; a title edited in Superset is the new original
+        assert_eq!(apply(root, &translations, "en").unwrap().dashboards, 0);
+        conn.execute("UPDATE slices SET slice_name = 'Погрузка, вагоны'", []).unwrap();
+        apply(root, &translations, "en").unwrap();
+        assert_eq!(row("SELECT slice_name FROM slices"), "Погрузка, вагоны");
+
+        apply(root, &translations, "ru").unwrap();
+        assert_eq!(row("SELECT dashboard_title FROM dashboards WHERE id = 1"), "Перевозки");
+        assert!(row("SELECT position_json FROM dashboards WHERE id = 1").contains(r#""text":"Итоги""#));
+        assert!(row("SELECT params FROM slices").contains("Объём, т"));
+        assert_eq!(row(&format!("SELECT COUNT(*) || '' FROM {ORIGINALS_TABLE}")), "0");
+    }
+}
@@ -1,21 +1,28 @@
 //! System tray integration for Portable Superset Launcher
+//!
+//! Menu items don't touch the server themselves: they post requests on the
+//! control channel (see `control`), which the tray's async loop answers —
+//! the same path scripts use through the pipe/socket.
 
 use anyhow::Result;
 use std::path::Path;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::mpsc;
 use tray_item::{IconSource, TrayItem};
-use tracing::{info, error};
+use tracing::{info, error, warn};
 
 use crate::config::Config;
+use crate::control::{self, ControlCommand, ControlRequest};
 use crate::python::PythonEnv;
 use crate::superset::SupersetServer;
 use crate::gateway;
+use crate::notifications::{self, Level};
 
 /// Run the application with system tray
 pub async fn run_tray(root: &Path, python_env: &PythonEnv, config: &Config) -> Result<()> {
-    let running = Arc::new(AtomicBool::new(true));
-    let r = running.clone();
+    let (control_tx, mut control_rx) = mpsc::channel::<ControlRequest>(16);
+    if let Err(e) = control::spawn(root, control_tx.clone()) {
+        warn!("{:#}", e);
+    }
     
     // Create tray item
     let mut tray = TrayItem::new("Superset Launcher", IconSource::Resource("icon"))?;
@@ -28,22 +35,17 @@ pub async fn run_tray(root: &Path, python_env: &PythonEnv, config: &Config) -> R
         let _ = open::that(&url);
     })?;
     
-    tray.add_menu_item("Start Server", || {
-        info!("Start requested from tray");
-        // TODO: Implement proper async communication
-    })?;
-    
-    tray.add_menu_item("Stop Server", || {
-        info!("Stop requested from tray");
-        let _ = SupersetServer::stop_running();
-    })?;
-    
-    let r2 = running.clone();
-    tray.add_menu_item("Exit", move || {
-        info!("Exit requested from tray");
-        let _ = SupersetServer::stop_running();
-        r2.store(false, Ordering::SeqCst);
-    })?;
+    for (label, command) in [
+        ("Start Server", ControlCommand::Start),
+        ("Stop Server", ControlCommand::Stop),
+        ("Exit", ControlCommand::Shutdown),
+    ] {
+        let tx = control_tx.clone();
+        tray.add_menu_item(label, move || {
+            info!("{} requested from tray", command.method());
+            let _ = tx.try_send(ControlRequest::detached(command));
+        })?;
+    }
     
     // Start Superset automatically
     let mut server = SupersetServer::new(root, python_env, config.port);
@@ -57,9 +59,13 @@ pub async fn run_tray(root: &Path, python_env: &PythonEnv, config: &Config) -> R
     
     server.start().await?;
     
-    // Open browser if configured
+    // Open browser once Superset actually answers
+    if let Err(e) = server.wait_ready(std::time::Duration::from_secs(config.startup_timeout_secs)).await {
+        notifications::notify(Level::Error, "Superset не запустился", &e.to_string());
+        return Err(e);
+    }
+    notifications::notify(Level::Info, "Superset запущен", &format!("http://localhost:{}", config.port));
     if config.open_browser {
-        tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
         let url = format!("http://localhost:{}", config.port);
         let _ = open::that(&url);
     }
@@ -67,9 +73,46 @@ pub async fn run_tray(root: &Path, python_env: &PythonEnv, config: &Config) -> R
     info!("Superset Launcher running in system tray");
     info!("Right-click the tray icon for options");
     
-    // Keep running until exit
-    while running.load(Ordering::SeqCst) {
+    // Keep running until exit, answering control requests and watching for crashes
+    let mut crashed = false;
+    let mut ticks = 0u32;
+    let mut running = true;
+    while running {
         tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        while let Ok(request) = control_rx.try_recv() {
+            let answer = match request.command {
+                ControlCommand::Ping | ControlCommand::Status => Ok(serde_json::json!({
+                    "superset": if server.is_running() { "running" } else { "stopped" },
+                    "port": config.port,
+                })),
+                ControlCommand::Start if server.is_running() => Ok(serde_json::json!({"started": false})),
+                ControlCommand::Start => {
+                    crashed = false;
+                    server.start().await.map(|_| serde_json::json!({"started": true})).map_err(|e| e.to_string())
+                }
+                ControlCommand::Stop => {
+                    // A stop on request is not a crash
+                    crashed = true;
+                    server.stop().map(|_| serde_json::json!({"stopped": true})).map_err(|e| e.to_string())
+                }
+                ControlCommand::Shutdown => {
+                    running = false;
+                    Ok(serde_json::json!({"shutdown": true}))
+                }
+            };
+            let _ = request.reply.send(answer);
+        }
+        ticks += 1;
+        if !crashed && ticks % 20 == 0 {
+            if let Some(status) = server.exit_status() {
+                crashed = true;
+                notifications::notify(
+                    Level::Error,
+                    "Superset остановился",
+                    &format!("Процесс завершился ({}). Подробности: logs\\superset.stderr.log", status),
+                );
+            }
+        }
     }
     
     // Cleanup
@@ -0,0 +1,274 @@
+//! Launcher UI action registry
+//!
+//! One table of what the launcher page can do: panels, the keyboard shortcut
+//! and JS function behind each action and a few common workflows; the HTTP
+//! endpoints come from the server's own route list
+//! ([`crate::launcher_ui::ROUTES`]). The page loads it from `/api/actions`
+//! for its shortcuts, the `?` cheat sheet and the Ctrl+K command palette,
+//! and `/help` renders it as a standalone page, so none of them can drift
+//! from the others.
+
+use serde::Serialize;
+
+use crate::html::html_escape;
+
+/// Something the user can do from the launcher page
+#[derive(Debug, Serialize)]
+pub struct UiAction {
+    pub id: &'static str,
+    pub title: &'static str,
+    /// Panel the action lives on
+    pub panel: &'static str,
+    /// Single key (no modifiers), as in `KeyboardEvent.key`
+    pub shortcut: Option<&'static str>,
+    /// Page JS function the shortcut and palette call
+    pub js: &'static str,
+    pub description: &'static str,
+}
+
+/// Common multi-step task
+#[derive(Debug, Serialize)]
+pub struct Workflow {
+    pub title: &'static str,
+    pub steps: &'static [&'static str],
+}
+
+/// Panels of the launcher page, top to bottom
+pub const PANELS: &[(&str, &str)] = &[
+    ("📊 Superset", "Запуск, остановка и открытие Superset"),
+    ("📚 База знаний", "Сборка и просмотр LightDocs"),
+    ("🔄 Авто-обновление", "Перезагрузка таблиц при изменении файлов в папке data"),
+    ("🧠 Поиск", "Статьи базы знаний, дашборды, графики и сохранённые запросы"),
+    ("📥 Загрузка данных", "CSV или Excel в таблицу базы examples"),
+    ("🗄️ Свежесть данных", "Строки и время последней загрузки каждой таблицы, устаревшие помечены ⚠️"),
+    ("🔔 Оповещения", "Результаты проверок из alerts.yaml (видна, когда проверки настроены)"),
+    ("📈 Статистика использования", "Локальные счётчики открытий и запросов"),
+];
+
+pub const ACTIONS: &[UiAction] = &[
+    UiAction {
+        id: "superset.toggle",
+        title: "Запустить / остановить Superset",
+        panel: "📊 Superset",
+        shortcut: Some("s"),
+        js: "toggleSuperset",
+        description: "Первый запуск с флешки занимает 1–3 минуты",
+    },
+    UiAction {
+        id: "superset.open",
+        title: "Открыть Superset",
+        panel: "📊 Superset",
+        shortcut: Some("o"),
+        js: "openSuperset",
+        description: "В новой вкладке, когда Superset запущен",
+    },
+    UiAction {
+        id: "lightdocs.toggle",
+        title: "Запустить / остановить базу знаний",
+        panel: "📚 База знаний",
+        shortcut: Some("d"),
+        js: "toggleLightdocs",
+        description: "Пересобирает сайт из папки knowledge",
+    },
+    UiAction {
+        id: "lightdocs.open",
+        title: "Открыть базу знаний",
+        panel: "📚 База знаний",
+        shortcut: Some("k"),
+        js: "openLightdocs",
+        description: "В новой вкладке",
+    },
+    UiAction {
+        id: "watcher.toggle",
+        title: "Включить / выключить авто-обновление",
+        panel: "🔄 Авто-обновление",
+        shortcut: Some("w"),
+        js: "toggleWatcher",
+        description: "Следит за CSV и Excel в папке data",
+    },
+    UiAction {
+        id: "search.focus",
+        title: "Поиск",
+        panel: "🧠 Поиск",
+        shortcut: Some("/"),
+        js: "focusSearch",
+        description: "Enter — искать",
+    },
+    UiAction {
+        id: "upload.pick",
+        title: "Загрузить файл",
+        panel: "📥 Загрузка данных",
+        shortcut: Some("u"),
+        js: "pickUpload",
+        description: "Или перетащите файл на панель",
+    },
+    UiAction {
+        id: "freshness.show",
+        title: "Свежесть данных",
+        panel: "🗄️ Свежесть данных",
+        shortcut: Some("f"),
+        js: "showFreshness",
+        description: "Какие таблицы давно не обновлялись",
+    },
+    UiAction {
+        id: "alerts.show",
+        title: "Оповещения",
+        panel: "🔔 Оповещения",
+        shortcut: Some("a"),
+        js: "showAlerts",
+        description: "Последние результаты проверок порогов",
+    },
+    UiAction {
+        id: "usage.show",
+        title: "Статистика использования",
+        panel: "📈 Статистика использования",
+        shortcut: None,
+        js: "showUsage",
+        description: "Самые открываемые дашборды и частые запросы",
+    },
+    UiAction {
+        id: "help.overlay",
+        title: "Горячие клавиши",
+        panel: "Справка",
+        shortcut: Some("?"),
+        js: "toggleHelp",
+        description: "Эта шпаргалка; полная справка — /help",
+    },
+    UiAction {
+        id: "ui.lang",
+        title: "Язык: русский / English",
+        panel: "Справка",
+        shortcut: Some("l"),
+        js: "toggleLang",
+        description: "По умолчанию — язык браузера; выбор запоминается в этом браузере",
+    },
+    UiAction {
+        id: "launcher.shutdown",
+        title: "Выключить всё и выйти",
+        panel: "Справка",
+        shortcut: None,
+        js: "shutdown",
+        description: "Останавливает все сервисы и лаунчер",
+    },
+];
+
+pub const WORKFLOWS: &[Workflow] = &[
+    Workflow {
+        title: "Показать дашборд",
+        steps: &["S — запустить Superset", "дождаться статуса «Работает»", "O — открыть Superset"],
+    },
+    Workflow {
+        title: "Обновить данные из Excel",
+        steps: &["U — выбрать файл (или перетащить на панель)", "таблица перезаписывается в базе examples", "обновить дашборд в Superset"],
+    },
+    Workflow {
+        title: "Найти инструкцию",
+        steps: &["/ — поиск", "ввести вопрос, Enter", "открыть статью из результатов"],
+    },
+    Workflow {
+        title: "Управлять с телефона",
+        steps: &["запустить лаунчер с --lan", "открыть адрес компьютера в браузере телефона", "при первом действии ввести пароль администратора"],
+    },
+];
+
+/// Everything above as one JSON document
+pub fn registry_json() -> serde_json::Value {
+    let panels: Vec<_> = PANELS
+        .iter()
+        .map(|(name, description)| serde_json::json!({ "name": name, "description": description }))
+        .collect();
+    serde_json::json!({
+        "panels": panels,
+        "actions": ACTIONS,
+        "endpoints": crate::launcher_ui::ROUTES,
+        "workflows": WORKFLOWS,
+    })
+}
+
+/// Standalone help page
+pub fn help_html() -> String {
+    let mut body = String::new();
+
+    body.push_str("<h2>Горячие клавиши</h2><table>");
+    for action in ACTIONS.iter().filter(|a| a.shortcut.is_some()) {
+        body.push_str(&format!(
+            "<tr><td><kbd>{}</kbd></td><td>{}</td><td class=\"muted\">{}</td></tr>",
+            html_escape(action.shortcut.unwrap_or_default()),
+            html_escape(action.title),
+            html_escape(action.description)
+        ));
+    }
+    body.push_str("<tr><td><kbd>Ctrl</kbd>+<kbd>K</kbd></td><td>Палитра команд</td><td class=\"muted\">Все действия по названию</td></tr></table>");
+
+    body.push_str("<h2>Панели</h2><dl>");
+    for (name, description) in PANELS {
+        body.push_str(&format!("<dt>{}</dt><dd>{}</dd>", html_escape(name), html_escape(description)));
+    }
+    body.push_str("</dl>");
+
+    body.push_str("<h2>Типовые задачи</h2>");
+    for workflow in WORKFLOWS {
+        body.push_str(&format!("<h3>{}</h3><ol>", html_escape(workflow.title)));
+        for step in workflow.steps {
+            body.push_str(&format!("<li>{}</li>", html_escape(step)));
+        }
+        body.push_str("</ol>");
+    }
+
+    body.push_str("<h2>API</h2><table>");
+    for route in crate::launcher_ui::ROUTES.iter().filter(|r| r.path.starts_with("/api/")) {
+        let lock = if route.token { " 🔒" } else { "" };
+        body.push_str(&format!(
+            "<tr><td><code>{} {}</code>{}</td><td>{}</td></tr>",
+            route.method,
+            html_escape(route.path),
+            lock,
+            html_escape(route.summary)
+        ));
+    }
+    body.push_str("</table><p class=\"muted\">🔒 — с другого устройства нужен вход (пароль администратора Superset). Реестр в JSON: <a href=\"/api/actions\">/api/actions</a></p>");
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="ru">
+<head>
+<meta charset="UTF-8">
+<meta name="viewport" content="width=device-width, initial-scale=1.0">
+<title>Справка — Superset Portable</title>
+<style>
+body {{ font-family: -apple-system, 'Segoe UI', Roboto, sans-serif; background: #1a1a2e; color: #e0e0e0; max-width: 860px; margin: 0 auto; padding: 24px; }}
+a {{ color: #60a5fa; }}
+h2 {{ margin-top: 28px; border-bottom: 1px solid #333; padding-bottom: 6px; }}
+td {{ padding: 6px 12px 6px 0; vertical-align: top; }}
+kbd {{ background: #2a2a40; border: 1px solid #444; border-radius: 4px; padding: 1px 6px; }}
+dt {{ margin-top: 8px; font-weight: 600; }}
+.muted {{ color: #888; }}
+</style>
+</head>
+<body>
+<p><a href="/">← Панель управления</a></p>
+<h1>Справка</h1>
+{}
+</body>
+</html>
+"#,
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_is_consistent() {
+        let mut shortcuts: Vec<_> = ACTIONS.iter().filter_map(|a| a.shortcut).collect();
+        let count = shortcuts.len();
+        shortcuts.sort();
+        shortcuts.dedup();
+        assert_eq!(shortcuts.len(), count, "duplicate shortcut");
+        assert!(ACTIONS.iter().all(|a| a.panel == "Справка" || PANELS.iter().any(|(p, _)| *p == a.panel)));
+        assert!(help_html().contains("/api/upload"));
+        assert!(help_html().contains("/api/docs/fs/tree"), "endpoints come from the server's route list");
+    }
+}
@@ -0,0 +1,191 @@
+//! Launcher UI strings in Russian and English
+//!
+//! The page fetches its bundle from `/api/i18n`: the language comes from
+//! `?lang=` (the page's RU/EN toggle, kept in localStorage) or else from the
+//! browser's `Accept-Language`. Russian stays the default and is also what
+//! the HTML shows before the bundle arrives.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// UI language
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    Ru,
+    En,
+}
+
+impl Lang {
+    /// Language from a code such as `en`, `en-GB` or `ru_RU`
+    pub fn from_code(code: &str) -> Option<Self> {
+        let primary = code.trim().split(['-', '_']).next().unwrap_or_default().to_lowercase();
+        match primary.as_str() {
+            "ru" => Some(Lang::Ru),
+            "en" => Some(Lang::En),
+            _ => None,
+        }
+    }
+
+    /// Best supported language of an `Accept-Language` header, by q-value
+    pub fn from_accept_language(header: &str) -> Self {
+        let mut best: Option<(Lang, f32)> = None;
+        for item in header.split(',') {
+            let mut parts = item.split(';');
+            let Some(lang) = Lang::from_code(parts.next().unwrap_or_default()) else {
+                continue;
+            };
+            let q = parts
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if q > 0.0 && !best.is_some_and(|(_, best_q)| q <= best_q) {
+                best = Some((lang, q));
+            }
+        }
+        best.map(|(lang, _)| lang).unwrap_or(Lang::Ru)
+    }
+}
+
+/// Key, Russian, English
+const STRINGS: &[(&str, &str, &str)] = &[
+    ("subtitle", "Панель управления сервисами", "Service control panel"),
+    // Service cards
+    ("status.running", "Работает", "Running"),
+    ("status.stopped", "Остановлен", "Stopped"),
+    ("status.starting", "Запуск...", "Starting..."),
+    ("status.warmup", "Прогрев...", "Warming up..."),
+    ("status.failed", "Ошибка запуска", "Failed to start"),
+    ("status.active", "Активен", "Active"),
+    ("btn.open", "Открыть", "Open"),
+    ("btn.start", "Запустить", "Start"),
+    ("btn.stop", "Остановить", "Stop"),
+    ("btn.enable", "Включить", "Enable"),
+    ("btn.disable", "Отключить", "Disable"),
+    ("btn.background", "Фон", "Background"),
+    ("port", "Порт", "Port"),
+    ("seconds", "с", "s"),
+    ("panel.superset", "📊 Superset", "📊 Superset"),
+    ("panel.lightdocs", "📚 База знаний", "📚 Knowledge base"),
+    ("panel.watcher", "🔄 Авто-обновление", "🔄 Auto-refresh"),
+    ("watcher.hint", "Мониторинг CSV", "Watching CSV"),
+    // Search
+    ("panel.search", "🧠 Поиск (статьи, дашборды, графики, запросы)", "🧠 Search (articles, dashboards, charts, queries)"),
+    ("search.placeholder", "Выручка, инциденты, как исправить ошибку...", "Revenue, incidents, how to fix an error..."),
+    ("search.button", "Найти", "Search"),
+    ("search.loading", "Поиск...", "Searching..."),
+    ("search.index_error", "Ошибка индекса", "Search index error"),
+    ("search.empty", "Ничего не найдено", "Nothing found"),
+    // Upload
+    ("panel.upload", "📥 Загрузка данных", "📥 Data upload"),
+    ("upload.hint", "Перетащите сюда CSV или Excel — таблица появится в базе examples", "Drop a CSV or Excel file here to load it into the examples database"),
+    ("upload.loading", "Загрузка", "Uploading"),
+    ("upload.table", "Таблица", "Table"),
+    ("upload.renamed", "на самом деле это", "actually a"),
+    // Health history
+    ("panel.health_history", "📉 Доступность за 24 часа", "📉 Availability, last 24 hours"),
+    ("health_history.legend", "Столбик — 15 минут: зелёный — всё отвечало, жёлтый — были сбои, красный — не отвечал, серый — лаунчер был закрыт. Линия — время ответа.", "Each bar is 15 minutes: green — always answered, yellow — some failures, red — down, grey — launcher was closed. The line is the response time."),
+    ("health_history.uptime", "доступность", "uptime"),
+    ("health_history.latency", "ответ", "response"),
+    ("health_history.ms", "мс", "ms"),
+    ("health_history.no_checks", "нет проверок", "no checks"),
+    ("health_history.unavailable", "История недоступна", "History unavailable"),
+    // Freshness
+    ("panel.freshness", "🗄️ Свежесть данных", "🗄️ Data freshness"),
+    ("freshness.no_data", "Нет данных", "No data"),
+    ("freshness.stale_count", "устарело", "stale"),
+    ("freshness.stale", "устарела", "stale"),
+    ("freshness.rows", "строк", "rows"),
+    ("freshness.loaded", "загружена", "loaded"),
+    ("freshness.unknown", "неизвестно", "unknown"),
+    ("validation.failed", "нарушены правила", "rules failed"),
+    ("validation.rule.not_null", "Пустые значения", "Empty values"),
+    ("validation.rule.range", "Вне диапазона", "Out of range"),
+    ("validation.rule.pattern", "Не соответствует шаблону", "Pattern mismatch"),
+    ("validation.rule.reference", "Нет в справочнике", "Missing reference"),
+    // Knowledge base reviews
+    ("panel.reviews", "⏰ Статьи на проверку", "⏰ Articles due for review"),
+    ("reviews.hint", "Срок проверки (review_due) истёк — обновите статью и дату", "Review date (review_due) has passed — update the article and the date"),
+    ("reviews.due", "срок проверки", "review due"),
+    // Alerts and usage
+    ("panel.alerts", "🔔 Оповещения", "🔔 Alerts"),
+    ("panel.usage", "📈 Статистика использования", "📈 Usage statistics"),
+    ("usage.local", "Хранится только на этом компьютере", "Stored on this computer only"),
+    ("usage.unavailable", "Статистика недоступна", "Statistics unavailable"),
+    ("usage.since", "С", "Since"),
+    ("usage.starts", "Запуски", "Starts"),
+    ("usage.searches", "Поисковых запросов", "Searches"),
+    ("usage.dashboards", "Популярные дашборды", "Top dashboards"),
+    // Schedules
+    ("panel.schedules", "🗓️ Расписания", "🗓️ Schedules"),
+    ("schedules.hint", "Cron: минута час день месяц день_недели, например 30 1 * * 1-5 — в 01:30 по будням", "Cron: minute hour day month weekday, e.g. 30 1 * * 1-5 — at 01:30 on weekdays"),
+    ("schedules.edit", "Изменить", "Edit"),
+    ("schedules.save", "Сохранить", "Save"),
+    ("schedules.reset", "Как было", "Restore default"),
+    ("schedules.next", "Ближайшие", "Next"),
+    ("schedules.builtin", "по умолчанию", "default"),
+    ("schedules.set_first", "Сначала задайте расписание", "Set a schedule first"),
+    // Knowledge files
+    ("panel.docs_fs", "🗂️ Файлы базы знаний", "🗂️ Knowledge files"),
+    ("docs_fs.new_folder", "+ Папка", "+ Folder"),
+    ("docs_fs.new_folder_prompt", "Новая папка (можно с подпапками, например guides/hr):", "New folder (subfolders allowed, e.g. guides/hr):"),
+    ("docs_fs.empty", "Папка базы знаний пуста", "The knowledge folder is empty"),
+    ("docs_fs.rename", "Переименовать", "Rename"),
+    ("docs_fs.rename_prompt", "Новое имя:", "New name:"),
+    ("docs_fs.move", "Переместить", "Move"),
+    ("docs_fs.move_prompt", "В папку (пусто — в корень базы знаний):", "To folder (empty for the top of the knowledge folder):"),
+    ("docs_fs.delete", "Удалить", "Delete"),
+    ("docs_fs.delete_confirm", "Переместить в корзину", "Move to the trash"),
+    ("docs_fs.trash", "🗑️ Корзина (хранится 30 дней)", "🗑️ Trash (kept for 30 days)"),
+    ("docs_fs.restore", "Восстановить", "Restore"),
+    // Footer and dialogs
+    ("footer.offline", "Работает автономно", "Runs offline"),
+    ("footer.backup", "Резервная копия", "Backup"),
+    ("footer.no_backup", "нет", "none"),
+    ("footer.help", "Справка (?)", "Help (?)"),
+    ("footer.exit", "Выход", "Exit"),
+    ("help.shortcuts", "⌨️ Горячие клавиши", "⌨️ Keyboard shortcuts"),
+    ("palette.placeholder", "Команда...", "Command..."),
+    ("login.prompt", "Пароль администратора Superset:", "Superset admin password:"),
+    ("login.failed", "Вход не выполнен", "Login failed"),
+    ("shutdown.confirm", "Выключить все сервисы и закрыть лаунчер?", "Stop all services and close the launcher?"),
+    ("shutdown.done", "Лаунчер остановлен", "Launcher stopped"),
+    ("shutdown.close_tab", "Можно закрыть вкладку", "You can close this tab"),
+    ("shutdown.failed", "Ошибка остановки", "Failed to stop"),
+    ("network_error", "Ошибка сети", "Network error"),
+    ("crash.happened", "Сбой лаунчера", "Launcher failure"),
+    ("crash.report", "Отчёт для поддержки:", "Report for support:"),
+    ("crash.dismiss", "Скрыть", "Dismiss"),
+    ("drive.lost", "Флешка недоступна — сервисы приостановлены и запустятся снова, когда она вернётся", "Flash drive unavailable — services are paused and will restart when it is back"),
+];
+
+/// Strings of the page in `lang`, by key
+pub fn bundle(lang: Lang) -> BTreeMap<&'static str, &'static str> {
+    STRINGS
+        .iter()
+        .map(|(key, ru, en)| (*key, if lang == Lang::En { *en } else { *ru }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_language() {
+        assert_eq!(Lang::from_accept_language("en-US,en;q=0.9,ru;q=0.8"), Lang::En);
+        assert_eq!(Lang::from_accept_language("de-DE,ru;q=0.7,en;q=0.5"), Lang::Ru);
+        assert_eq!(Lang::from_accept_language("ru-RU;q=0.4, en-GB;q=0.6"), Lang::En);
+        assert_eq!(Lang::from_accept_language("de, fr;q=0.8"), Lang::Ru);
+        assert_eq!(Lang::from_accept_language("en;q=0"), Lang::Ru);
+        assert_eq!(Lang::from_accept_language(""), Lang::Ru);
+    }
+
+    #[test]
+    fn test_bundle_keys_are_unique() {
+        let keys: std::collections::BTreeSet<_> = STRINGS.iter().map(|(key, _, _)| key).collect();
+        assert_eq!(keys.len(), STRINGS.len(), "duplicate key");
+        assert_eq!(bundle(Lang::En)["btn.start"], "Start");
+        assert_eq!(bundle(Lang::Ru)["btn.start"], "Запустить");
+    }
+}
@@ -0,0 +1,730 @@
+//! Unpack a release archive made by `pack`
+//!
+//! Handles `.zip` and `.tar.zst`, told apart by their first bytes (the name
+//! is only a fallback). The archive may be a local file, a UNC path on a
+//! share or an `http://` URL of another machine in the closed network: it is
+//! read as a stream and extracted entry by entry, so a 3 GB kit needs no
+//! temporary copy. A dropped connection is resumed from the byte it stopped
+//! at (an HTTP `Range` request, or a re-open and seek on the share). The
+//! stream's SHA-256 is checked against `--sha256` or the `<archive>.sha256`
+//! file `pack` writes next to the archive; on a mismatch the freshly
+//! unpacked folder is removed. `--force` over an existing kit unpacks into a
+//! staging folder and moves the files in only after the checksum passed.
+//!
+//! `--list` streams the archive without writing and sums its top-level
+//! parts; `--only python/` replaces just those parts of an existing kit,
+//! swapping them in after the checksum passed.
+//!
+//! Every entry name goes through [`safe_path::relative`] before anything is
+//! written, so an archive from an untrusted stick can't drop files outside
+//! the target folder (zip-slip). Tar links are refused for the same reason;
+//! zip entries are always written as plain files.
+
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::safe_path;
+
+/// First bytes of a zip local file header and of a zstd frame
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xB5, 0x2F, 0xFD];
+
+/// Extension of the checksum file next to an archive
+pub const CHECKSUM_SUFFIX: &str = ".sha256";
+
+/// Times a dropped stream is resumed before giving up
+const MAX_RESUMES: u32 = 5;
+
+/// Read buffer between the stream and the decoders
+const STREAM_BUFFER: usize = 1 << 20;
+
+/// Folders inside the kit used while `--only` parts are swapped
+const STAGING_DIR: &str = ".unpack-staging";
+const REPLACED_DIR: &str = ".unpack-replaced";
+
+/// Result of an unpack run
+#[derive(Debug, Default)]
+pub struct UnpackStats {
+    pub files: usize,
+    pub bytes: u64,
+    /// SHA-256 of the archive stream (hex)
+    pub sha256: String,
+    /// The hash was compared with a known checksum
+    pub verified: bool,
+}
+
+/// Archive formats `pack` produces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarZstd,
+}
+
+impl ArchiveFormat {
+    /// Format from the file name
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+        if name.ends_with(".zip") {
+            Ok(ArchiveFormat::Zip)
+        } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+            Ok(ArchiveFormat::TarZstd)
+        } else {
+            bail!("Unknown archive type: {} (expected .zip or .tar.zst)", path.display())
+        }
+    }
+
+    /// Format from the first bytes of the archive
+    pub fn sniff(head: &[u8]) -> Option<Self> {
+        if head.starts_with(ZIP_MAGIC) {
+            Some(ArchiveFormat::Zip)
+        } else if head.starts_with(ZSTD_MAGIC) {
+            Some(ArchiveFormat::TarZstd)
+        } else {
+            None
+        }
+    }
+}
+
+/// Where the archive comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// Local file or UNC path
+    Path(PathBuf),
+    /// `http://` or `https://` URL
+    Url(String),
+}
+
+impl Source {
+    pub fn parse(archive: &str) -> Self {
+        let lower = archive.to_lowercase();
+        if lower.starts_with("http://") || lower.starts_with("https://") {
+            Source::Url(archive.to_string())
+        } else {
+            Source::Path(PathBuf::from(archive))
+        }
+    }
+
+    /// File name of the archive (last path or URL segment, query dropped)
+    pub fn file_name(&self) -> String {
+        let text = match self {
+            Source::Path(path) => path.to_string_lossy().to_string(),
+            Source::Url(url) => url.split(['?', '#']).next().unwrap_or_default().to_string(),
+        };
+        text.rsplit(['/', '\\']).next().unwrap_or_default().to_string()
+    }
+
+    /// Default target: the archive name without extension, next to a local
+    /// archive, in the current folder for a URL or a share
+    pub fn default_dest(&self) -> PathBuf {
+        let name = self.file_name();
+        let stem = name.trim_end_matches(".zip").trim_end_matches(".tar.zst").trim_end_matches(".tzst");
+        match self {
+            Source::Path(path) if !is_unc(path) => path.with_file_name(stem),
+            _ => PathBuf::from(stem),
+        }
+    }
+
+    /// Stream of the archive from byte `offset` on
+    fn open(&self, offset: u64) -> io::Result<Box<dyn Read + Send>> {
+        match self {
+            Source::Path(path) => {
+                let mut file = File::open(path)?;
+                file.seek(SeekFrom::Start(offset))?;
+                Ok(Box::new(file))
+            }
+            Source::Url(url) => Ok(Box::new(HttpBody::get(url, offset)?)),
+        }
+    }
+
+    /// Expected SHA-256 from the `<archive>.sha256` file, if there is one
+    fn published_checksum(&self) -> Option<String> {
+        let text = match self {
+            Source::Path(path) => {
+                let mut name = path.as_os_str().to_owned();
+                name.push(CHECKSUM_SUFFIX);
+                fs::read_to_string(PathBuf::from(name)).ok()?
+            }
+            Source::Url(url) => {
+                let mut body = String::new();
+                HttpBody::get(&format!("{}{}", url, CHECKSUM_SUFFIX), 0).ok()?.read_to_string(&mut body).ok()?;
+                body
+            }
+        };
+        let hash = text.split_whitespace().next()?;
+        (hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit())).then(|| hash.to_lowercase())
+    }
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Path(path) => write!(f, "{}", path.display()),
+            Source::Url(url) => f.write_str(url),
+        }
+    }
+}
+
+/// `\\server\share\...`
+fn is_unc(path: &Path) -> bool {
+    let text = path.to_string_lossy();
+    text.starts_with(r"\\") || text.starts_with("//")
+}
+
+/// Body of an HTTP GET, read synchronously on a blocking thread of the
+/// runtime. A server that ignores `Range` gets the skipped part discarded.
+struct HttpBody {
+    runtime: tokio::runtime::Handle,
+    response: reqwest::Response,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl HttpBody {
+    fn get(url: &str, offset: u64) -> io::Result<Self> {
+        let runtime = tokio::runtime::Handle::try_current().map_err(io::Error::other)?;
+        let client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .build()
+            .map_err(io::Error::other)?;
+        let mut request = client.get(url);
+        if offset > 0 {
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+        }
+        let response = runtime.block_on(request.send()).map_err(io::Error::other)?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(io::Error::other(format!("{}: HTTP {}", url, status)));
+        }
+        let mut body = Self { runtime, response, chunk: Vec::new(), pos: 0 };
+        if offset > 0 && status != reqwest::StatusCode::PARTIAL_CONTENT {
+            io::copy(&mut (&mut body).take(offset), &mut io::sink())?;
+        }
+        Ok(body)
+    }
+}
+
+impl Read for HttpBody {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.chunk.len() {
+            match self.runtime.block_on(self.response.chunk()).map_err(io::Error::other)? {
+                Some(chunk) => {
+                    self.chunk = chunk.to_vec();
+                    self.pos = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Archive stream that re-opens the source at the current offset when a
+/// read fails, and hashes everything it hands out
+struct ResumingReader<'a> {
+    source: &'a Source,
+    inner: Box<dyn Read + Send>,
+    offset: u64,
+    resumes: u32,
+    hash: ring::digest::Context,
+}
+
+impl<'a> ResumingReader<'a> {
+    fn open(source: &'a Source) -> Result<Self> {
+        let inner = source.open(0).with_context(|| format!("Cannot open {}", source))?;
+        Ok(Self { source, inner, offset: 0, resumes: 0, hash: ring::digest::Context::new(&ring::digest::SHA256) })
+    }
+
+    /// Read to the end (bytes after the last entry) and return the hash
+    fn finish(mut self) -> io::Result<String> {
+        io::copy(&mut self, &mut io::sink())?;
+        Ok(hex::encode(self.hash.finish()))
+    }
+}
+
+impl Read for ResumingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.inner.read(buf) {
+                Ok(n) => {
+                    self.hash.update(&buf[..n]);
+                    self.offset += n as u64;
+                    return Ok(n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) if self.resumes < MAX_RESUMES => {
+                    self.resumes += 1;
+                    warn!(
+                        "🔁 Read failed at {:.1} MB ({}), resuming ({}/{})",
+                        self.offset as f64 / 1_048_576.0,
+                        e,
+                        self.resumes,
+                        MAX_RESUMES
+                    );
+                    std::thread::sleep(Duration::from_secs(2 * self.resumes as u64));
+                    // A failed re-open counts as another attempt
+                    if let Ok(inner) = self.source.open(self.offset) {
+                        self.inner = inner;
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Stream `source` into `dest`, checking the archive's SHA-256 against
+/// `sha256` or the published checksum file. A non-empty `dest` is refused
+/// unless `force`; then the archive goes to a staging folder first and its
+/// files replace the kit's only once verified. A URL source must be unpacked
+/// on a blocking thread of the tokio runtime (`spawn_blocking`).
+pub fn unpack_from(source: &Source, dest: &Path, force: bool, sha256: Option<&str>) -> Result<UnpackStats> {
+    let fresh = !dest.is_dir() || fs::read_dir(dest)?.next().is_none();
+    if !fresh && !force {
+        bail!("{} is not empty (use --force to unpack over it)", dest.display());
+    }
+    fs::create_dir_all(dest)?;
+    info!("📂 Unpacking {} -> {}", source, dest.display());
+
+    if fresh {
+        let result = extract(source, dest, &Selection::default()).and_then(|stats| verify(source, stats, sha256));
+        if result.is_err() {
+            let _ = fs::remove_dir_all(dest);
+        }
+        return result;
+    }
+    let staging = dest.join(STAGING_DIR);
+    let _ = fs::remove_dir_all(&staging);
+    fs::create_dir_all(&staging)?;
+    let result = extract(source, &staging, &Selection::default())
+        .and_then(|stats| verify(source, stats, sha256))
+        .and_then(|stats| {
+            merge_in(&staging, dest)?;
+            Ok(stats)
+        });
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+/// Move every file from `staging` into `dest`, replacing files of the same
+/// name and keeping the rest of the kit (data, local settings)
+fn merge_in(staging: &Path, dest: &Path) -> Result<()> {
+    for entry in walkdir::WalkDir::new(staging).min_depth(1) {
+        let entry = entry?;
+        let target = dest.join(entry.path().strip_prefix(staging)?);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+            continue;
+        }
+        if target.is_dir() {
+            bail!("{} is a folder in the kit but a file in the archive", target.display());
+        }
+        fs::rename(entry.path(), &target)
+            .with_context(|| format!("Cannot replace {} (is Superset still running?)", target.display()))?;
+    }
+    Ok(())
+}
+
+/// Replace only the `selection` parts of the kit in `dest` with the
+/// archive's copies (e.g. a broken `python/`). They are unpacked into a
+/// staging folder first and swapped in only after the checksum passed, so
+/// a failed run leaves the kit as it was.
+pub fn repair_from(source: &Source, dest: &Path, selection: &Selection, sha256: Option<&str>) -> Result<UnpackStats> {
+    let staging = dest.join(STAGING_DIR);
+    let _ = fs::remove_dir_all(&staging);
+    fs::create_dir_all(&staging)?;
+    info!("📂 Unpacking {} from {} -> {}", selection, source, dest.display());
+
+    let result = extract(source, &staging, selection)
+        .and_then(|stats| verify(source, stats, sha256))
+        .and_then(|stats| {
+            if stats.files == 0 {
+                bail!("The archive has nothing under {}", selection);
+            }
+            swap_in(&staging, dest, selection)?;
+            Ok(stats)
+        });
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+/// Move the freshly unpacked parts from `staging` over the old ones
+fn swap_in(staging: &Path, dest: &Path, selection: &Selection) -> Result<()> {
+    let old_root = dest.join(REPLACED_DIR);
+    let _ = fs::remove_dir_all(&old_root);
+    for part in &selection.prefixes {
+        let new = staging.join(part);
+        if !new.exists() {
+            warn!("{} is not in the archive, left as it is", part.display());
+            continue;
+        }
+        let current = dest.join(part);
+        let old = old_root.join(part);
+        if current.exists() {
+            fs::create_dir_all(old.parent().unwrap_or(&old_root))?;
+            fs::rename(&current, &old)
+                .with_context(|| format!("Cannot replace {} (is Superset still running?)", current.display()))?;
+        }
+        if let Some(parent) = current.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if let Err(e) = fs::rename(&new, &current) {
+            let _ = fs::rename(&old, &current);
+            return Err(e).with_context(|| format!("Cannot move the new {} into place", part.display()));
+        }
+        info!("🔧 Replaced {}", part.display());
+    }
+    let _ = fs::remove_dir_all(&old_root);
+    Ok(())
+}
+
+/// Compare the stream hash with `sha256` or the published checksum
+fn verify(source: &Source, stats: UnpackStats, sha256: Option<&str>) -> Result<UnpackStats> {
+    let expected = match sha256 {
+        Some(hash) => Some(hash.trim().to_lowercase()),
+        None => source.published_checksum(),
+    };
+    match &expected {
+        Some(expected) if *expected != stats.sha256 => {
+            bail!("SHA-256 mismatch: expected {}, got {} (damaged or incomplete archive)", expected, stats.sha256)
+        }
+        Some(_) => Ok(UnpackStats { verified: true, ..stats }),
+        None => {
+            warn!("No checksum for {} (no --sha256, no {} file): integrity not verified", source, CHECKSUM_SUFFIX);
+            Ok(stats)
+        }
+    }
+}
+
+/// Top-level part of an archive, as shown by `unpack --list`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchivePart {
+    /// First path component, `python` for everything under `python/`
+    pub name: String,
+    pub is_dir: bool,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Parts of the archive with their sizes. The archive is streamed to the end
+/// (its checksum is checked on the way), nothing is written.
+pub fn list(source: &Source, sha256: Option<&str>) -> Result<(Vec<ArchivePart>, UnpackStats)> {
+    let mut parts: BTreeMap<String, ArchivePart> = BTreeMap::new();
+    let mut stats = UnpackStats::default();
+    let hash = walk(source, &mut |rel, kind, reader| {
+        let mut components = rel.components();
+        let name = components.next().map(|c| c.as_os_str().to_string_lossy().to_string()).unwrap_or_default();
+        let is_dir = kind == EntryKind::Dir || components.next().is_some();
+        let part = parts.entry(name.clone()).or_insert(ArchivePart { name, is_dir, files: 0, bytes: 0 });
+        part.is_dir |= is_dir;
+        if kind == EntryKind::File {
+            let bytes = io::copy(reader, &mut io::sink())?;
+            part.files += 1;
+            part.bytes += bytes;
+            stats.files += 1;
+            stats.bytes += bytes;
+        }
+        Ok(())
+    })?;
+    stats.sha256 = hash;
+    let stats = verify(source, stats, sha256)?;
+    Ok((parts.into_values().collect(), stats))
+}
+
+/// Parts of the kit picked with `--only python/,superset_home/`; empty picks everything
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    prefixes: Vec<PathBuf>,
+}
+
+impl Selection {
+    pub fn parse(list: &str) -> Result<Self> {
+        let prefixes = list
+            .split(',')
+            .map(|part| part.trim().trim_end_matches(['/', '\\']))
+            .filter(|part| !part.is_empty())
+            .map(safe_path::relative)
+            .collect::<Result<Vec<_>>>()?;
+        if prefixes.is_empty() {
+            bail!("Nothing selected: list folders or files, e.g. --only python/,superset_home/");
+        }
+        Ok(Self { prefixes })
+    }
+
+    fn matches(&self, rel: &Path) -> bool {
+        self.prefixes.is_empty() || self.prefixes.iter().any(|prefix| rel.starts_with(prefix))
+    }
+}
+
+impl std::fmt::Display for Selection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<String> = self.prefixes.iter().map(|p| p.display().to_string()).collect();
+        f.write_str(&names.join(", "))
+    }
+}
+
+/// Extract the entries picked by `selection`
+fn extract(source: &Source, dest: &Path, selection: &Selection) -> Result<UnpackStats> {
+    let mut stats = UnpackStats::default();
+    let hash = walk(source, &mut |rel, kind, reader| {
+        if !selection.matches(rel) {
+            return Ok(());
+        }
+        match kind {
+            EntryKind::Dir => fs::create_dir_all(dest.join(rel))?,
+            EntryKind::File => {
+                stats.bytes += write_entry(dest, rel, reader)?;
+                stats.files += 1;
+            }
+        }
+        Ok(())
+    })?;
+    stats.sha256 = hash;
+    Ok(stats)
+}
+
+/// Archive entry handed to a [`walk`] visitor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntryKind {
+    Dir,
+    File,
+}
+
+/// Entry name (already checked by `safe_path`), kind and content
+type Visitor<'v> = dyn FnMut(&Path, EntryKind, &mut dyn Read) -> Result<()> + 'v;
+
+/// Detect the format and hand every entry of the stream to `visit`;
+/// returns the SHA-256 of the whole stream
+fn walk(source: &Source, visit: &mut Visitor) -> Result<String> {
+    let mut reader = BufReader::with_capacity(STREAM_BUFFER, ResumingReader::open(source)?);
+    let head = reader.fill_buf()?;
+    let format = match ArchiveFormat::sniff(head) {
+        Some(format) => format,
+        None => ArchiveFormat::from_path(Path::new(&source.file_name()))?,
+    };
+    let reader = match format {
+        ArchiveFormat::Zip => walk_zip(reader, visit)?,
+        ArchiveFormat::TarZstd => walk_tar_zstd(reader, visit)?,
+    };
+    // Whatever the decoder left buffered was hashed already
+    Ok(reader.into_inner().finish()?)
+}
+
+/// Create the parent folders of an entry and write it
+fn write_entry(dest: &Path, rel: &Path, reader: &mut dyn Read) -> Result<u64> {
+    let target: PathBuf = dest.join(rel);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut out = File::create(&target).with_context(|| format!("Cannot write {}", target.display()))?;
+    Ok(io::copy(reader, &mut out)?)
+}
+
+type Stream<'a> = BufReader<ResumingReader<'a>>;
+
+/// Zip entries read from their local headers, front to back; the central
+/// directory at the end is never needed
+fn walk_zip<'a>(mut reader: Stream<'a>, visit: &mut Visitor) -> Result<Stream<'a>> {
+    while let Some(mut entry) = zip::read::read_zipfile_from_stream(&mut reader).context("Not a valid ZIP archive")? {
+        let rel = safe_path::relative(entry.name())?;
+        let kind = if entry.is_dir() { EntryKind::Dir } else { EntryKind::File };
+        visit(&rel, kind, &mut entry)?;
+    }
+    Ok(reader)
+}
+
+fn walk_tar_zstd<'a>(reader: Stream<'a>, visit: &mut Visitor) -> Result<Stream<'a>> {
+    let mut archive = tar::Archive::new(zstd::Decoder::with_buffer(reader)?);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = String::from_utf8_lossy(&entry.path_bytes()).to_string();
+        let rel = safe_path::relative(&name)?;
+        let kind = entry.header().entry_type();
+        if kind.is_dir() {
+            visit(&rel, EntryKind::Dir, &mut io::empty())?;
+        } else if kind.is_file() {
+            visit(&rel, EntryKind::File, &mut entry)?;
+        } else if kind.is_symlink() || kind.is_hard_link() {
+            bail!("Links are not allowed in archives: {}", name);
+        }
+        // PAX headers and other metadata entries carry no file
+    }
+    Ok(archive.into_inner().finish())
+}
+
+/// Write `<archive>.sha256` (`sha256sum` format) next to `archive`
+pub fn write_checksum(archive: &Path) -> Result<String> {
+    let mut file = File::open(archive)?;
+    let mut hash = ring::digest::Context::new(&ring::digest::SHA256);
+    let mut buf = vec![0u8; STREAM_BUFFER];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hash.update(&buf[..n]);
+    }
+    let hex = hex::encode(hash.finish());
+    let mut name = archive.as_os_str().to_owned();
+    name.push(CHECKSUM_SUFFIX);
+    let name_only = archive.file_name().unwrap_or_default().to_string_lossy();
+    fs::write(PathBuf::from(name), format!("{}  {}\n", hex, name_only))?;
+    Ok(hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    fn unpack(archive: &Path, dest: &Path, force: bool) -> Result<UnpackStats> {
+        unpack_from(&Source::Path(archive.to_path_buf()), dest, force, None)
+    }
+
+    fn zip_with(path: &Path, entries: &[(&str, &str)]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, content) in entries {
+            zip.start_file(*name, zip::write::FileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_zip_slip_is_refused() {
+        let dir = tempdir().unwrap();
+        let good = dir.path().join("good.zip");
+        zip_with(&good, &[("docs/readme.md", "hi"), ("start.bat", "@echo off")]);
+        let dest = dir.path().join("kit");
+        let stats = unpack(&good, &dest, false).unwrap();
+        assert_eq!(stats.files, 2);
+        assert_eq!(fs::read_to_string(dest.join("docs/readme.md")).unwrap(), "hi");
+        assert!(unpack(&good, &dest, false).is_err(), "non-empty target without --force");
+
+        for evil in ["../evil.bat", "..\\..\\evil.bat", "/tmp/evil.bat", "C:\\evil.bat"] {
+            let archive = dir.path().join("evil.zip");
+            zip_with(&archive, &[("ok.txt", "ok"), (evil, "pwned")]);
+            assert!(unpack(&archive, &dir.path().join("out"), true).is_err(), "{} accepted", evil);
+        }
+        assert!(!dir.path().join("evil.bat").exists());
+    }
+
+    #[test]
+    fn test_tar_zstd_round_trip() {
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("kit.tar.zst");
+        let mut tar = tar::Builder::new(zstd::Encoder::new(File::create(&archive).unwrap(), 3).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(2);
+        header.set_mode(0o644);
+        header.set_cksum();
+        tar.append_data(&mut header, "docs/a.md", &b"hi"[..]).unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+
+        let stats = unpack(&archive, &dir.path().join("kit"), false).unwrap();
+        assert_eq!((stats.files, stats.bytes), (1, 2));
+    }
+
+    #[test]
+    fn test_format_sniffed_and_checksum_verified() {
+        let dir = tempdir().unwrap();
+        // Renamed by a download: only the content tells the format
+        let archive = dir.path().join("kit.bin");
+        zip_with(&archive, &[("python/python.exe", "MZ")]);
+
+        let stats = unpack(&archive, &dir.path().join("unchecked"), false).unwrap();
+        assert!(!stats.verified);
+        assert_eq!(stats.files, 1);
+
+        let hash = write_checksum(&archive).unwrap();
+        assert_eq!(hash, stats.sha256, "the stream hash covers the whole file");
+        let stats = unpack(&archive, &dir.path().join("checked"), false).unwrap();
+        assert!(stats.verified);
+
+        let source = Source::Path(archive.clone());
+        let dest = dir.path().join("bad");
+        assert!(unpack_from(&source, &dest, false, Some(&"0".repeat(64))).is_err());
+        assert!(!dest.exists(), "a failed unpack leaves no half-written folder");
+    }
+
+    #[test]
+    fn test_force_replaces_files_only_after_verification() {
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("kit.zip");
+        zip_with(&archive, &[("python/python.exe", "new"), ("start.bat", "@echo on")]);
+        let kit = dir.path().join("kit");
+        fs::create_dir_all(kit.join("python")).unwrap();
+        fs::write(kit.join("python/python.exe"), "old").unwrap();
+        fs::write(kit.join("config.json"), "{}").unwrap();
+
+        let source = Source::Path(archive.clone());
+        assert!(unpack_from(&source, &kit, true, Some(&"0".repeat(64))).is_err());
+        assert_eq!(fs::read_to_string(kit.join("python/python.exe")).unwrap(), "old", "the kit is untouched");
+        assert!(!kit.join("start.bat").exists());
+
+        let stats = unpack(&archive, &kit, true).unwrap();
+        assert_eq!(stats.files, 2);
+        assert_eq!(fs::read_to_string(kit.join("python/python.exe")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(kit.join("config.json")).unwrap(), "{}");
+        assert!(!kit.join(STAGING_DIR).exists());
+    }
+
+    #[test]
+    fn test_list_and_repair_selected_parts() {
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("kit.zip");
+        zip_with(
+            &archive,
+            &[
+                ("python/python.exe", "MZ"),
+                ("python/Lib/os.py", "import sys"),
+                ("superset_home/superset_config.py", "SECRET_KEY = 'new'"),
+                ("start.bat", "@echo off"),
+            ],
+        );
+        let source = Source::Path(archive.clone());
+        let (parts, stats) = list(&source, None).unwrap();
+        let summary: Vec<(&str, bool, usize)> = parts.iter().map(|p| (p.name.as_str(), p.is_dir, p.files)).collect();
+        assert_eq!(summary, vec![("python", true, 2), ("start.bat", false, 1), ("superset_home", true, 1)]);
+        assert_eq!(stats.bytes, 2 + 10 + 18 + 9);
+
+        // A kit with a broken Python tree and local data that must survive
+        let kit = dir.path().join("kit");
+        fs::create_dir_all(kit.join("python/Lib")).unwrap();
+        fs::write(kit.join("python/Lib/broken.pyc"), "garbage").unwrap();
+        fs::create_dir_all(kit.join("superset_home")).unwrap();
+        fs::write(kit.join("superset_home/superset_config.py"), "SECRET_KEY = 'mine'").unwrap();
+        let stats = repair_from(&source, &kit, &Selection::parse("python/").unwrap(), None).unwrap();
+        assert_eq!(stats.files, 2);
+        assert_eq!(fs::read_to_string(kit.join("python/Lib/os.py")).unwrap(), "import sys");
+        assert!(!kit.join("python/Lib/broken.pyc").exists(), "the old tree is replaced, not merged");
+        assert_eq!(fs::read_to_string(kit.join("superset_home/superset_config.py")).unwrap(), "SECRET_KEY = 'mine'");
+        assert!(!kit.join("start.bat").exists());
+        assert!(!kit.join(STAGING_DIR).exists() && !kit.join(REPLACED_DIR).exists());
+
+        assert!(repair_from(&source, &kit, &Selection::parse("docs").unwrap(), None).is_err());
+        assert!(repair_from(&source, &kit, &Selection::parse("python").unwrap(), Some(&"0".repeat(64))).is_err());
+        assert!(kit.join("python/Lib/os.py").exists(), "a failed repair keeps the kit");
+        assert!(Selection::parse("../python").is_err());
+        assert!(Selection::parse(" , ").is_err());
+    }
+
+    #[test]
+    fn test_source() {
+        let url = Source::parse("http://10.0.0.5:8000/kits/superset-portable.tar.zst?token=1");
+        assert_eq!(url.file_name(), "superset-portable.tar.zst");
+        assert_eq!(url.default_dest(), PathBuf::from("superset-portable"));
+        let share = Source::parse(r"\\fileserver\kits\superset-portable.zip");
+        assert_eq!(share.default_dest(), PathBuf::from("superset-portable"), "not onto the share");
+        let local = Source::parse("/media/stick/superset-portable.zip");
+        assert_eq!(local.default_dest(), PathBuf::from("/media/stick/superset-portable"));
+    }
+}
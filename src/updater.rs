@@ -0,0 +1,469 @@
+//! Launcher self-update from a local update package
+//!
+//! An update package is a ZIP archive containing:
+//! - `manifest.json` — target version, file list with SHA-256 hashes, migrations
+//! - `manifest.sig`  — hex-encoded Ed25519 signature of `manifest.json`
+//! - the files listed in the manifest (e.g. `superset-launcher.exe`)
+//!
+//! The running exe cannot be overwritten on Windows, but it can be renamed:
+//! it is moved to `*.old` and the new exe takes its place; leftovers are
+//! removed on the next start. The replaced files are kept under
+//! `updates/rollback-<version>/` so `update --rollback` can restore them;
+//! files the update added are removed again. Migrations save the files they
+//! edit or delete there too, just before they run, and renames are undone.
+//!
+//! The release signing key is built in: set `SUPERSET_LAUNCHER_UPDATE_KEY`
+//! (hex Ed25519 public key) when building the release. A build without it
+//! refuses every package.
+
+use anyhow::{anyhow, bail, Context, Result};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Release signing key (Ed25519, hex), given at build time
+const UPDATE_PUBLIC_KEY_HEX: Option<&str> = option_env!("SUPERSET_LAUNCHER_UPDATE_KEY");
+
+const UPDATES_DIR: &str = "updates";
+const ROLLBACK_FILE: &str = "previous_version.json";
+
+/// Update manifest (`manifest.json`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateManifest {
+    pub version: String,
+    pub files: Vec<ManifestFile>,
+    #[serde(default)]
+    pub migrations: Vec<Migration>,
+}
+
+/// File shipped in the package, path relative to the portable root
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub path: String,
+    pub sha256: String,
+}
+
+/// Migration step applied after files are installed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Migration {
+    /// Set a top-level key in a JSON config only if it is missing
+    ConfigDefault { file: String, key: String, value: serde_json::Value },
+    /// Set a top-level key in a JSON config unconditionally
+    ConfigSet { file: String, key: String, value: serde_json::Value },
+    /// Rename a file or folder (e.g. moved layouts)
+    Rename { from: String, to: String },
+    /// Remove an obsolete file
+    Delete { path: String },
+}
+
+/// Rollback record (`updates/previous_version.json`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollbackInfo {
+    pub version: String,
+    pub updated_to: String,
+    /// Relative to root (absolute in records of older launchers)
+    pub backup_dir: PathBuf,
+    pub files: Vec<String>,
+    /// Installed by the update with nothing to restore
+    #[serde(default)]
+    pub added: Vec<String>,
+    /// Renames done by migrations, in order
+    #[serde(default)]
+    pub renamed: Vec<RenamedPath>,
+}
+
+/// A `Rename` migration that moved something
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenamedPath {
+    pub from: String,
+    pub to: String,
+}
+
+/// Verify, install and migrate an update package
+pub fn apply_update(root: &Path, package: &Path, force: bool) -> Result<String> {
+    let file = fs::File::open(package)
+        .with_context(|| format!("Cannot open update package: {}", package.display()))?;
+    let mut archive = zip::ZipArchive::new(file).context("Update package is not a valid ZIP")?;
+
+    let manifest_bytes = read_entry(&mut archive, "manifest.json")?;
+    let signature_hex = String::from_utf8(read_entry(&mut archive, "manifest.sig")?)?;
+    let public_key = UPDATE_PUBLIC_KEY_HEX
+        .context("This launcher was built without an update signing key (SUPERSET_LAUNCHER_UPDATE_KEY)")?;
+    let public_key = hex::decode(public_key.trim()).context("SUPERSET_LAUNCHER_UPDATE_KEY is not valid hex")?;
+    verify_signature(&manifest_bytes, signature_hex.trim(), &public_key)?;
+    info!("🔏 Signature verified");
+
+    let manifest: UpdateManifest = serde_json::from_slice(&manifest_bytes)?;
+    let current = env!("CARGO_PKG_VERSION");
+    if !force && !is_newer(&manifest.version, current) {
+        bail!("Package version {} is not newer than {} (use --force to reinstall)", manifest.version, current);
+    }
+
+    // Extract and check every file before touching the installation
+    let staging = root.join(UPDATES_DIR).join(format!("staging-{}", manifest.version));
+    let _ = fs::remove_dir_all(&staging);
+    for entry in &manifest.files {
+        let rel = safe_relative(&entry.path)?;
+        let data = read_entry(&mut archive, &entry.path)?;
+        let actual = hex::encode(ring::digest::digest(&ring::digest::SHA256, &data));
+        if !actual.eq_ignore_ascii_case(&entry.sha256) {
+            bail!("Checksum mismatch for {}", entry.path);
+        }
+        let dst = staging.join(&rel);
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&dst, data)?;
+    }
+
+    // Keep the current files for rollback
+    let backup_rel = Path::new(UPDATES_DIR).join(format!("rollback-{}", current));
+    let backup_dir = root.join(&backup_rel);
+    let _ = fs::remove_dir_all(&backup_dir);
+    fs::create_dir_all(&backup_dir)?;
+    let (mut backed_up, mut added) = (Vec::new(), Vec::new());
+    for entry in &manifest.files {
+        let installed = root.join(safe_relative(&entry.path)?);
+        if installed.exists() {
+            let dst = backup_dir.join(safe_relative(&entry.path)?);
+            if let Some(parent) = dst.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(&installed, &dst)?;
+            backed_up.push(entry.path.clone());
+        } else {
+            added.push(entry.path.clone());
+        }
+    }
+    for config in ["config.json", "lightdocs.json"] {
+        if root.join(config).exists() && !backed_up.iter().any(|p| p == config) {
+            fs::copy(root.join(config), backup_dir.join(config))?;
+            backed_up.push(config.to_string());
+        }
+    }
+
+    let mut rollback = RollbackInfo {
+        version: current.to_string(),
+        updated_to: manifest.version.clone(),
+        backup_dir: backup_rel,
+        files: backed_up,
+        added,
+        renamed: Vec::new(),
+    };
+    write_rollback(root, &rollback)?;
+
+    // Install
+    for entry in &manifest.files {
+        let rel = safe_relative(&entry.path)?;
+        install_file(&staging.join(&rel), &root.join(&rel))?;
+        info!("  Installed: {}", entry.path);
+    }
+    let _ = fs::remove_dir_all(&staging);
+
+    migrate(root, &manifest.migrations, &mut rollback)?;
+
+    info!("✅ Updated {} → {}. Restart the launcher to use the new version.", current, manifest.version);
+    Ok(manifest.version)
+}
+
+/// Restore files saved by the last update
+pub fn rollback(root: &Path) -> Result<String> {
+    let record_path = root.join(UPDATES_DIR).join(ROLLBACK_FILE);
+    let content = fs::read_to_string(&record_path)
+        .map_err(|_| anyhow!("No previous version recorded, nothing to roll back"))?;
+    let record: RollbackInfo = serde_json::from_str(&content)?;
+
+    let backup_dir = root.join(&record.backup_dir);
+    for renamed in record.renamed.iter().rev() {
+        let from = root.join(safe_relative(&renamed.from)?);
+        let to = root.join(safe_relative(&renamed.to)?);
+        if to.exists() && !from.exists() {
+            fs::rename(&to, &from).with_context(|| format!("Cannot move {} back", to.display()))?;
+            info!("  Renamed back: {} → {}", renamed.to, renamed.from);
+        }
+    }
+    for rel in &record.files {
+        let rel_path = safe_relative(rel)?;
+        install_file(&backup_dir.join(&rel_path), &root.join(&rel_path))?;
+        info!("  Restored: {}", rel);
+    }
+    for rel in &record.added {
+        let path = root.join(safe_relative(rel)?);
+        if path.is_file() {
+            fs::remove_file(&path).with_context(|| format!("Cannot remove {}", path.display()))?;
+            info!("  Removed: {}", rel);
+        }
+    }
+
+    fs::remove_file(&record_path)?;
+    info!("↩️ Rolled back {} → {}. Restart the launcher.", record.updated_to, record.version);
+    Ok(record.version)
+}
+
+/// Remove `*.exe.old` / `*.dll.old` files left by a previous exe swap
+pub fn cleanup_after_update(root: &Path) {
+    let Ok(entries) = fs::read_dir(root) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_lowercase();
+        let leftover = name.ends_with(".exe.old") || name.ends_with(".dll.old");
+        if leftover && fs::remove_file(&path).is_ok() {
+            info!("Removed leftover from update: {}", path.display());
+        }
+    }
+}
+
+/// Verify a hex Ed25519 signature over `message`
+fn verify_signature(message: &[u8], signature_hex: &str, public_key: &[u8]) -> Result<()> {
+    let signature = hex::decode(signature_hex).context("manifest.sig is not valid hex")?;
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(message, &signature)
+        .map_err(|_| anyhow!("Update package signature is invalid"))
+}
+
+fn read_entry(archive: &mut zip::ZipArchive<fs::File>, name: &str) -> Result<Vec<u8>> {
+    let mut file = archive.by_name(name)
+        .map_err(|_| anyhow!("{} is missing from the update package", name))?;
+    let mut data = Vec::with_capacity(file.size() as usize);
+    file.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Reject absolute paths and `..` in package paths
+fn safe_relative(path: &str) -> Result<PathBuf> {
+    crate::safe_path::relative(path).context("Bad path in update package")
+}
+
+/// Move `src` over `dst`, renaming a locked (running) `dst` out of the way
+fn install_file(src: &Path, dst: &Path) -> Result<()> {
+    if let Some(parent) = dst.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if dst.exists() && fs::remove_file(dst).is_err() {
+        // Running executables can be renamed but not deleted on Windows
+        let old = dst.with_extension(format!(
+            "{}old",
+            dst.extension().map(|e| format!("{}.", e.to_string_lossy())).unwrap_or_default()
+        ));
+        let _ = fs::remove_file(&old);
+        fs::rename(dst, &old)
+            .with_context(|| format!("Cannot replace {}", dst.display()))?;
+    }
+    if fs::rename(src, dst).is_err() {
+        fs::copy(src, dst)?;
+    }
+    Ok(())
+}
+
+fn write_rollback(root: &Path, record: &RollbackInfo) -> Result<()> {
+    fs::write(root.join(UPDATES_DIR).join(ROLLBACK_FILE), serde_json::to_string_pretty(record)?)?;
+    Ok(())
+}
+
+/// Run `migrations`, first saving in `record` what each one is about to
+/// change so `rollback` can undo it
+fn migrate(root: &Path, migrations: &[Migration], record: &mut RollbackInfo) -> Result<()> {
+    let backup_dir = root.join(&record.backup_dir);
+    for migration in migrations {
+        match migration {
+            Migration::ConfigDefault { file: path, .. } | Migration::ConfigSet { file: path, .. } | Migration::Delete { path } => {
+                let target = root.join(safe_relative(path)?);
+                // Saved already, or put back by undoing the rename that made it
+                let known = record.files.contains(path)
+                    || record.added.contains(path)
+                    || record.renamed.iter().any(|renamed| &renamed.to == path);
+                if !known && target.is_file() {
+                    let dst = backup_dir.join(safe_relative(path)?);
+                    if let Some(parent) = dst.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::copy(&target, &dst)?;
+                    record.files.push(path.clone());
+                } else if !known && !target.exists() && !matches!(migration, Migration::Delete { .. }) {
+                    record.added.push(path.clone());
+                }
+            }
+            Migration::Rename { from, to } => {
+                if root.join(safe_relative(from)?).exists() && !root.join(safe_relative(to)?).exists() {
+                    record.renamed.push(RenamedPath { from: from.clone(), to: to.clone() });
+                }
+            }
+        }
+        write_rollback(root, record)?;
+        apply_migration(root, migration)?;
+    }
+    Ok(())
+}
+
+fn apply_migration(root: &Path, migration: &Migration) -> Result<()> {
+    match migration {
+        Migration::ConfigDefault { file, key, value } | Migration::ConfigSet { file, key, value } => {
+            let path = root.join(safe_relative(file)?);
+            let mut json: serde_json::Value = if path.exists() {
+                serde_json::from_str(&fs::read_to_string(&path)?)?
+            } else {
+                serde_json::json!({})
+            };
+            let obj = json.as_object_mut()
+                .ok_or_else(|| anyhow!("{} is not a JSON object", file))?;
+            let overwrite = matches!(migration, Migration::ConfigSet { .. });
+            if overwrite || !obj.contains_key(key) {
+                obj.insert(key.clone(), value.clone());
+                fs::write(&path, serde_json::to_string_pretty(&json)?)?;
+                info!("  Migration: {}.{} = {}", file, key, value);
+            }
+        }
+        Migration::Rename { from, to } => {
+            let src = root.join(safe_relative(from)?);
+            let dst = root.join(safe_relative(to)?);
+            if src.exists() && !dst.exists() {
+                if let Some(parent) = dst.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::rename(&src, &dst)?;
+                info!("  Migration: {} → {}", from, to);
+            }
+        }
+        Migration::Delete { path } => {
+            let target = root.join(safe_relative(path)?);
+            if target.is_file() {
+                fs::remove_file(&target)?;
+                info!("  Migration: removed {}", path);
+            } else if target.exists() {
+                warn!("  Migration: {} is not a file, skipped", path);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Compare dotted numeric versions ("0.7.0" > "0.6.2")
+fn is_newer(candidate: &str, current: &str) -> bool {
+    let parse = |v: &str| -> Vec<u64> {
+        v.trim_start_matches('v')
+            .split('.')
+            .map(|p| p.chars().take_while(|c| c.is_ascii_digit()).collect::<String>().parse().unwrap_or(0))
+            .collect()
+    };
+    parse(candidate) > parse(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    #[test]
+    fn test_signature_verification() {
+        let rng = ring::rand::SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+        let key = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+
+        let manifest = br#"{"version":"9.9.9","files":[]}"#;
+        let sig = hex::encode(key.sign(manifest).as_ref());
+
+        assert!(verify_signature(manifest, &sig, key.public_key().as_ref()).is_ok());
+        assert!(verify_signature(b"tampered", &sig, key.public_key().as_ref()).is_err());
+    }
+
+    #[test]
+    fn test_safe_relative() {
+        assert!(safe_relative("superset-launcher.exe").is_ok());
+        assert!(safe_relative("layouts/main.json").is_ok());
+        assert!(safe_relative("../evil.exe").is_err());
+        assert!(safe_relative("/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("0.7.0", "0.6.2"));
+        assert!(is_newer("v1.0.0", "0.6.2"));
+        assert!(!is_newer("0.6.2", "0.6.2"));
+        assert!(!is_newer("0.6.1", "0.6.2"));
+    }
+
+    #[test]
+    fn test_rollback_removes_added_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let backup = Path::new(UPDATES_DIR).join("rollback-0.6.2");
+        fs::create_dir_all(root.join(&backup)).unwrap();
+        fs::write(root.join(&backup).join("config.json"), "old").unwrap();
+        fs::write(root.join("config.json"), "new").unwrap();
+        fs::create_dir_all(root.join("layouts")).unwrap();
+        fs::write(root.join("layouts/new.json"), "{}").unwrap();
+        let record = RollbackInfo {
+            version: "0.6.2".into(),
+            updated_to: "0.7.0".into(),
+            backup_dir: backup,
+            files: vec!["config.json".into()],
+            added: vec!["layouts/new.json".into()],
+            renamed: Vec::new(),
+        };
+        fs::write(root.join(UPDATES_DIR).join(ROLLBACK_FILE), serde_json::to_string(&record).unwrap()).unwrap();
+
+        assert_eq!(rollback(root).unwrap(), "0.6.2");
+        assert_eq!(fs::read_to_string(root.join("config.json")).unwrap(), "old");
+        assert!(!root.join("layouts/new.json").exists());
+    }
+
+    #[test]
+    fn test_rollback_undoes_migrations() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join(UPDATES_DIR)).unwrap();
+        fs::create_dir_all(root.join("layouts")).unwrap();
+        fs::write(root.join("layouts/main.json"), "layout").unwrap();
+        fs::write(root.join("lightdocs.json"), r#"{"port": 8089}"#).unwrap();
+        fs::write(root.join("obsolete.txt"), "old").unwrap();
+        let mut record = RollbackInfo {
+            version: "0.6.2".into(),
+            updated_to: "0.7.0".into(),
+            backup_dir: Path::new(UPDATES_DIR).join("rollback-0.6.2"),
+            files: Vec::new(),
+            added: Vec::new(),
+            renamed: Vec::new(),
+        };
+        let migrations = [
+            Migration::Rename { from: "layouts/main.json".into(), to: "layouts/v2/main.json".into() },
+            Migration::ConfigSet { file: "lightdocs.json".into(), key: "port".into(), value: serde_json::json!(9000) },
+            Migration::Delete { path: "obsolete.txt".into() },
+            Migration::ConfigDefault { file: "extra.json".into(), key: "on".into(), value: serde_json::json!(true) },
+        ];
+        migrate(root, &migrations, &mut record).unwrap();
+        assert!(!root.join("layouts/main.json").exists());
+        assert!(!root.join("obsolete.txt").exists());
+        assert!(root.join("extra.json").exists());
+
+        assert_eq!(rollback(root).unwrap(), "0.6.2");
+        assert_eq!(fs::read_to_string(root.join("layouts/main.json")).unwrap(), "layout");
+        assert!(!root.join("layouts/v2/main.json").exists());
+        assert_eq!(fs::read_to_string(root.join("lightdocs.json")).unwrap(), r#"{"port": 8089}"#);
+        assert_eq!(fs::read_to_string(root.join("obsolete.txt")).unwrap(), "old");
+        assert!(!root.join("extra.json").exists());
+    }
+
+    #[test]
+    fn test_config_migrations() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("config.json"), r#"{"port": 8088}"#).unwrap();
+
+        apply_migration(dir.path(), &Migration::ConfigDefault {
+            file: "config.json".into(), key: "port".into(), value: serde_json::json!(9000),
+        }).unwrap();
+        apply_migration(dir.path(), &Migration::ConfigDefault {
+            file: "config.json".into(), key: "host".into(), value: serde_json::json!("127.0.0.1"),
+        }).unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.path().join("config.json")).unwrap()).unwrap();
+        assert_eq!(json["port"], 8088);
+        assert_eq!(json["host"], "127.0.0.1");
+    }
+}
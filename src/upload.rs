@@ -0,0 +1,188 @@
+//! Drag-and-drop upload handling
+//!
+//! Users rename `.xls` to `.csv` (and worse) all the time, so the extension
+//! of an uploaded file is never trusted: the real type is sniffed from magic
+//! bytes, text is normalized to UTF-8 (BOM stripped, Windows-1251 transcoded)
+//! and the file is stored with the right extension before `data_loader`
+//! sees it.
+
+use anyhow::{bail, Result};
+use std::path::{Path, PathBuf};
+
+use crate::encoding::{self, TextEncoding};
+use crate::safe_path;
+
+/// Folder (relative to root) where uploads are staged before loading
+pub const UPLOADS_DIR: &str = "uploads";
+
+/// Largest accepted upload
+pub const MAX_UPLOAD_BYTES: usize = 200 * 1024 * 1024;
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const OLE_MAGIC: &[u8] = &[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+/// Detected upload type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Csv,
+    Xlsx,
+    /// Legacy Excel 97-2003 (OLE container)
+    Xls,
+    /// Known but unsupported format (human-readable name)
+    Unsupported(&'static str),
+}
+
+impl FileKind {
+    /// Extension `data_loader` understands for this kind
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            FileKind::Csv => Some("csv"),
+            FileKind::Xlsx => Some("xlsx"),
+            FileKind::Xls => Some("xls"),
+            FileKind::Unsupported(_) => None,
+        }
+    }
+}
+
+/// Detect the real file type from its first bytes
+pub fn sniff(bytes: &[u8]) -> FileKind {
+    if bytes.starts_with(ZIP_MAGIC) {
+        // Office Open XML is a zip whose entries live under xl/ (Excel) or word/, ppt/
+        let head = &bytes[..bytes.len().min(4096)];
+        return if contains(head, b"xl/") {
+            FileKind::Xlsx
+        } else if contains(head, b"word/") {
+            FileKind::Unsupported("документ Word")
+        } else if contains(head, b"[Content_Types].xml") {
+            // Entry order varies; an OOXML zip without a visible part name is most likely a workbook
+            FileKind::Xlsx
+        } else {
+            FileKind::Unsupported("ZIP-архив")
+        };
+    }
+    if bytes.starts_with(OLE_MAGIC) {
+        return FileKind::Xls;
+    }
+    if bytes.starts_with(b"%PDF") {
+        return FileKind::Unsupported("PDF");
+    }
+    if bytes.starts_with(b"\x89PNG") || bytes.starts_with(&[0xFF, 0xD8, 0xFF]) || bytes.starts_with(b"GIF8") {
+        return FileKind::Unsupported("изображение");
+    }
+    if bytes.starts_with(b"SQLite format 3") {
+        return FileKind::Unsupported("база SQLite");
+    }
+
+    match encoding::detect(bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes)) {
+        TextEncoding::Binary => FileKind::Unsupported("двоичный файл"),
+        _ => FileKind::Csv,
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+/// UTF-8 text without BOM (Windows-1251 transcoded)
+pub fn normalize_text(bytes: &[u8]) -> Vec<u8> {
+    let bytes = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+    match encoding::detect(bytes) {
+        TextEncoding::Windows1251 => {
+            let (text, _, _) = encoding_rs::WINDOWS_1251.decode(bytes);
+            text.into_owned().into_bytes()
+        }
+        _ => bytes.to_vec(),
+    }
+}
+
+/// A stored upload, ready for `data_loader`
+#[derive(Debug)]
+pub struct PreparedUpload {
+    pub path: PathBuf,
+    pub kind: FileKind,
+    /// The file's extension didn't match its content
+    pub renamed: bool,
+}
+
+/// Sniff, normalize and store an uploaded file under `uploads/`
+pub fn prepare(root: &Path, file_name: &str, bytes: &[u8]) -> Result<PreparedUpload> {
+    let kind = sniff(bytes);
+    if let FileKind::Unsupported(what) = kind {
+        bail!(
+            "Файл «{}» — это {}. Поддерживаются CSV и Excel (.xlsx, .xls): \
+             откройте файл в Excel и сохраните как «Книга Excel» или «CSV UTF-8».",
+            file_name,
+            what
+        );
+    }
+    let extension = kind.extension().unwrap_or_default();
+
+    // Browsers may send a full path, and a crafted request anything at all
+    let clean_name = safe_path::file_name(file_name);
+    let original = Path::new(&clean_name);
+    let stem = original
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "upload".to_string());
+    let renamed = original
+        .extension()
+        .map_or(true, |ext| !ext.to_string_lossy().eq_ignore_ascii_case(extension));
+
+    let dir = root.join(UPLOADS_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.{}", stem, extension));
+
+    match kind {
+        FileKind::Csv => std::fs::write(&path, normalize_text(bytes))?,
+        _ => std::fs::write(&path, bytes)?,
+    }
+
+    Ok(PreparedUpload { path, kind, renamed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(sniff(b"id,name\n1,a\n"), FileKind::Csv);
+        assert_eq!(sniff(&[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1, 0, 0]), FileKind::Xls);
+        assert_eq!(sniff(b"PK\x03\x04....xl/workbook.xml"), FileKind::Xlsx);
+        assert_eq!(sniff(b"PK\x03\x04....data.csv"), FileKind::Unsupported("ZIP-архив"));
+        assert_eq!(sniff(b"%PDF-1.7"), FileKind::Unsupported("PDF"));
+    }
+
+    #[test]
+    fn test_prepare_fixes_extension_and_encoding() {
+        let dir = tempdir().unwrap();
+
+        // Legacy Excel renamed to .csv
+        let xls = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1, 0, 0];
+        let upload = prepare(dir.path(), "отчёт.csv", &xls).unwrap();
+        assert_eq!(upload.path, dir.path().join(UPLOADS_DIR).join("отчёт.xls"));
+        assert!(upload.renamed);
+
+        // Windows-1251 CSV with "Москва"
+        let csv = [b'c', b'i', b't', b'y', b'\n', 0xCC, 0xEE, 0xF1, 0xEA, 0xE2, 0xE0];
+        let upload = prepare(dir.path(), "cities.csv", &csv).unwrap();
+        assert!(!upload.renamed);
+        assert_eq!(std::fs::read_to_string(&upload.path).unwrap(), "city\nМосква");
+
+        assert!(prepare(dir.path(), "scan.xlsx", b"%PDF-1.4").is_err());
+    }
+
+    #[test]
+    fn test_prepare_stays_in_uploads() {
+        let dir = tempdir().unwrap();
+        for name in ["../../config.csv", "..\\..\\config.csv", "/etc/config.csv", "C:\\kit\\config.csv"] {
+            let upload = prepare(dir.path(), name, b"a,b\n1,2\n").unwrap();
+            assert_eq!(upload.path, dir.path().join(UPLOADS_DIR).join("config.csv"), "{}", name);
+        }
+        let upload = prepare(dir.path(), "..", b"a\n1\n").unwrap();
+        assert_eq!(upload.path, dir.path().join(UPLOADS_DIR).join("upload.csv"));
+    }
+}
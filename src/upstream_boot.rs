@@ -0,0 +1,123 @@
+//! Superset boot IDs, so the gateway can drop its cache after a restart
+//!
+//! A block in `superset_config.py` draws a random ID when Superset starts
+//! and sends it on every response (`X-Superset-Boot-Id`, through Superset's
+//! `DEFAULT_HTTP_HEADERS`). With `gateway.flush_cache_on_restart` the gateway
+//! probes `/health` every few seconds and, when the ID differs from the one
+//! recorded in `cache/gateway_boot_id`, empties its chart-data cache: the
+//! first dashboard after a restart then shows fresh data. By default the
+//! cache survives restarts and dashboards open warm.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::gateway_spool::ResponseCache;
+use crate::secret_key;
+
+/// Response header carrying the boot ID
+pub const HEADER: &str = "x-superset-boot-id";
+
+/// How often the gateway asks Superset for its boot ID
+const PROBE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Boot ID seen last, next to the gateway cache
+const RECORD_FILE: &str = "gateway_boot_id";
+
+/// Line that marks the block in superset_config.py
+const HOOK_MARKER: &str = "# Boot ID for the gateway cache";
+
+const HOOK_BLOCK: &str = r#"import uuid as _uuid
+DEFAULT_HTTP_HEADERS = {**globals().get("DEFAULT_HTTP_HEADERS", {}), "X-Superset-Boot-Id": _uuid.uuid4().hex}
+"#;
+
+/// Add the boot ID block to superset_config.py.
+/// Returns `true` when the config changed (effective from the next start).
+pub fn install(root: &Path) -> Result<bool> {
+    let config_path = secret_key::config_path(root);
+    let mut config = std::fs::read_to_string(&config_path)
+        .with_context(|| format!("{} not found — run: superset-launcher init", config_path.display()))?;
+    if config.contains(HOOK_MARKER) {
+        return Ok(false);
+    }
+    if !config.ends_with('\n') {
+        config.push('\n');
+    }
+    config.push_str(&format!("\n{}\n{}", HOOK_MARKER, HOOK_BLOCK));
+    std::fs::write(&config_path, config)?;
+    Ok(true)
+}
+
+fn record_path(root: &Path) -> PathBuf {
+    root.join("cache").join(RECORD_FILE)
+}
+
+/// Boot ID Superset on `port` answers with (`None`: down or no block yet)
+pub async fn probe(port: u16) -> Option<String> {
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(5)).build().ok()?;
+    let response = client.get(format!("http://127.0.0.1:{}/health", port)).send().await.ok()?;
+    let id = response.headers().get(HEADER)?.to_str().ok()?.trim();
+    (!id.is_empty()).then(|| id.to_string())
+}
+
+/// Record `id` and say whether it differs from the one recorded before
+fn boot_changed(root: &Path, id: &str) -> bool {
+    let path = record_path(root);
+    let previous = std::fs::read_to_string(&path).ok();
+    if previous.as_deref().map(str::trim) == Some(id) {
+        return false;
+    }
+    if let Err(e) = std::fs::write(&path, id) {
+        warn!("Could not record Superset boot ID: {}", e);
+    }
+    true
+}
+
+/// Empty `cache` whenever Superset on `port` comes up with a new boot ID
+pub fn spawn_watch(root: &Path, port: u16, cache: ResponseCache) {
+    match install(root) {
+        Ok(true) => info!("🔁 Boot ID added to superset_config.py; the cache is flushed from the next Superset restart"),
+        Ok(false) => {}
+        Err(e) => warn!("Superset boot ID unavailable, cache kept across restarts: {}", e),
+    }
+    let root = root.to_path_buf();
+    tokio::spawn(async move {
+        loop {
+            if let Some(id) = probe(port).await {
+                if boot_changed(&root, &id) {
+                    match cache.clear() {
+                        Ok(removed) => info!("🧹 Superset restarted: {} cached chart response(s) dropped", removed),
+                        Err(e) => warn!("Gateway cache flush failed: {}", e),
+                    }
+                }
+            }
+            tokio::time::sleep(PROBE_INTERVAL).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_install_and_record() {
+        let dir = tempdir().unwrap();
+        let config_path = secret_key::config_path(dir.path());
+        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+        std::fs::write(&config_path, "SECRET_KEY = \"abc\"").unwrap();
+
+        assert!(install(dir.path()).unwrap());
+        assert!(!install(dir.path()).unwrap());
+        let config = std::fs::read_to_string(&config_path).unwrap();
+        assert_eq!(config.matches("X-Superset-Boot-Id").count(), 1);
+        assert!(config.starts_with("SECRET_KEY = \"abc\"\n"));
+
+        std::fs::create_dir_all(dir.path().join("cache")).unwrap();
+        assert!(boot_changed(dir.path(), "a1"));
+        assert!(!boot_changed(dir.path(), "a1"));
+        assert!(boot_changed(dir.path(), "b2"));
+    }
+}
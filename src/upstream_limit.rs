@@ -0,0 +1,234 @@
+//! Concurrency limit and queue in front of Superset
+//!
+//! On a weak CPU a burst of dashboard loads (a room full of kiosks switching
+//! on at 9:00) gives threaded Flask dozens of requests at once; they all slow
+//! down together and time out together. The gateway lets only a few requests
+//! per upstream through at a time (`gateway.concurrency.max_requests`, from
+//! the hardware profile when 0), parks the next ones in a queue, and answers
+//! 429 with `Retry-After` when the queue is full or a request has waited too
+//! long: a "please wait" page that reloads itself for browsers, a Superset
+//! style JSON error for API calls. Cache hits, static files and docs never
+//! queue. A slot is held until the response body has been sent.
+
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{Html, IntoResponse, Json, Response},
+};
+use hyper::body::{Frame, SizeHint};
+use serde::{Deserialize, Serialize};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Seconds a rejected client is told to wait before retrying
+const RETRY_AFTER_SECS: u64 = 5;
+
+/// Request limit toward Superset (`gateway.concurrency` in config.json)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConcurrencyConfig {
+    pub enabled: bool,
+    /// Requests in flight per Superset instance (0: from the hardware profile)
+    pub max_requests: usize,
+    /// Requests allowed to wait for a slot; more get 429 at once
+    pub queue: usize,
+    /// Longest wait for a slot before answering 429
+    pub wait_secs: u64,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_requests: 0,
+            queue: 64,
+            wait_secs: 30,
+        }
+    }
+}
+
+/// Why a request was turned away
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    QueueFull,
+    TimedOut,
+}
+
+/// Current load of one upstream, for `/metrics`
+#[derive(Debug, Clone, Serialize)]
+pub struct LimiterStats {
+    pub limit: usize,
+    pub in_flight: usize,
+    pub waiting: usize,
+    pub rejected: usize,
+}
+
+/// Slots and queue of one Superset instance
+#[derive(Clone)]
+pub struct Limiter {
+    permits: Arc<Semaphore>,
+    waiting: Arc<AtomicUsize>,
+    rejected: Arc<AtomicUsize>,
+    limit: usize,
+    queue: usize,
+    wait: Duration,
+}
+
+impl Limiter {
+    pub fn new(config: &ConcurrencyConfig, limit: usize) -> Self {
+        let limit = limit.max(1);
+        Self {
+            permits: Arc::new(Semaphore::new(limit)),
+            waiting: Arc::new(AtomicUsize::new(0)),
+            rejected: Arc::new(AtomicUsize::new(0)),
+            limit,
+            queue: config.queue,
+            wait: Duration::from_secs(config.wait_secs),
+        }
+    }
+
+    /// Wait for a slot; the request may go upstream while the permit lives
+    pub async fn acquire(&self) -> Result<OwnedSemaphorePermit, Rejection> {
+        if let Ok(permit) = self.permits.clone().try_acquire_owned() {
+            return Ok(permit);
+        }
+        if self.waiting.fetch_add(1, Ordering::SeqCst) >= self.queue {
+            self.waiting.fetch_sub(1, Ordering::SeqCst);
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            return Err(Rejection::QueueFull);
+        }
+        let result = tokio::time::timeout(self.wait, self.permits.clone().acquire_owned()).await;
+        self.waiting.fetch_sub(1, Ordering::SeqCst);
+        match result {
+            Ok(Ok(permit)) => Ok(permit),
+            _ => {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                Err(Rejection::TimedOut)
+            }
+        }
+    }
+
+    pub fn stats(&self) -> LimiterStats {
+        LimiterStats {
+            limit: self.limit,
+            in_flight: self.limit - self.permits.available_permits(),
+            waiting: self.waiting.load(Ordering::SeqCst),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// 429 for a request that found no slot: a self-reloading page for
+/// browsers, JSON for API calls (shown in the chart's error box)
+pub fn busy_response(headers: &HeaderMap, rejection: Rejection) -> Response {
+    let message = match rejection {
+        Rejection::QueueFull => "Superset сейчас перегружен запросами. Повторите через несколько секунд.",
+        Rejection::TimedOut => "Superset не успел обработать запрос из очереди. Повторите через несколько секунд.",
+    };
+    let wants_html = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"));
+    let mut response = if wants_html {
+        Html(format!(
+            r#"<!DOCTYPE html><html lang="ru"><head><meta charset="utf-8"><meta http-equiv="refresh" content="{secs}"><title>Подождите…</title></head><body style="font-family: sans-serif; text-align: center; margin-top: 20vh"><h2>⏳ Подождите немного</h2><p>{message}</p><p>Страница обновится сама через {secs} с.</p></body></html>"#,
+            secs = RETRY_AFTER_SECS,
+            message = message
+        ))
+        .into_response()
+    } else {
+        Json(serde_json::json!({
+            "message": message,
+            "errors": [{"message": message, "error_type": "GENERIC_BACKEND_ERROR", "level": "warning"}],
+        }))
+        .into_response()
+    };
+    *response.status_mut() = StatusCode::TOO_MANY_REQUESTS;
+    response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(RETRY_AFTER_SECS));
+    response
+}
+
+/// Keep `permit` until the response body has been sent
+pub fn hold(response: Response, permit: Option<OwnedSemaphorePermit>) -> Response {
+    match permit {
+        Some(permit) => response.map(|inner| Body::new(PermitBody { inner, _permit: permit })),
+        None => response,
+    }
+}
+
+/// Response body that releases its upstream slot when dropped
+struct PermitBody {
+    inner: Body,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl HttpBody for PermitBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, axum::Error>>> {
+        Pin::new(&mut self.get_mut().inner).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_and_timeout() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(async {
+            let config = ConcurrencyConfig { queue: 1, wait_secs: 1, ..ConcurrencyConfig::default() };
+            let limiter = Limiter::new(&config, 1);
+
+            let first = limiter.acquire().await.unwrap();
+            assert_eq!(limiter.stats().in_flight, 1);
+
+            // One waiter fits in the queue, the next one is turned away at once
+            let waiter = tokio::spawn({
+                let limiter = limiter.clone();
+                async move { limiter.acquire().await.map(drop) }
+            });
+            tokio::task::yield_now().await;
+            assert_eq!(limiter.stats().waiting, 1);
+            assert_eq!(limiter.acquire().await.unwrap_err(), Rejection::QueueFull);
+
+            // Releasing the slot lets the waiter through
+            let body = hold(Response::new(Body::from("ok")), Some(first));
+            drop(body);
+            assert!(waiter.await.unwrap().is_ok());
+
+            let held = limiter.acquire().await.unwrap();
+            assert_eq!(limiter.acquire().await.unwrap_err(), Rejection::TimedOut);
+            drop(held);
+            assert_eq!(limiter.stats().rejected, 2);
+            assert_eq!(limiter.stats().in_flight, 0);
+        });
+    }
+
+    #[test]
+    fn test_busy_response() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, "text/html,application/xhtml+xml".parse().unwrap());
+        let page = busy_response(&headers, Rejection::QueueFull);
+        assert_eq!(page.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(page.headers()[header::RETRY_AFTER], "5");
+        assert!(page.headers()[header::CONTENT_TYPE].to_str().unwrap().starts_with("text/html"));
+
+        let api = busy_response(&HeaderMap::new(), Rejection::TimedOut);
+        assert!(api.headers()[header::CONTENT_TYPE].to_str().unwrap().starts_with("application/json"));
+    }
+}
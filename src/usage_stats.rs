@@ -0,0 +1,195 @@
+//! Local usage statistics
+//!
+//! Anonymous counters (service starts, dashboards opened via the gateway,
+//! searches run) kept in a sled database inside the kit. Nothing ever leaves
+//! the machine — the numbers are only shown by `stats show` and the launcher
+//! UI, to help owners justify and tune the deployment.
+//!
+//! The gateway, launcher UI and CLI commands of one process record through
+//! the same handle ([`crate::sled_journal::shared`]). sled locks the database
+//! for that process, so `stats show` from another console while the launcher
+//! runs fails with "in use"; the launcher UI shows the same numbers.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::path::Path;
+use tracing::debug;
+
+use crate::{drive_watchdog, sled_journal};
+
+const USAGE_DB: &str = ".usage_stats";
+
+/// Counter key prefixes
+const SERVICE_PREFIX: &str = "start:";
+const DASHBOARD_PREFIX: &str = "dashboard:";
+const SEARCH_KEY: &str = "search";
+const SINCE_KEY: &[u8] = b"since";
+
+/// Something worth counting
+#[derive(Debug, Clone)]
+pub enum UsageEvent<'a> {
+    /// A service was started (`superset`, `lightdocs`, `watcher`, `stack`)
+    ServiceStart(&'a str),
+    /// A dashboard page was opened (slug or id)
+    DashboardOpen(&'a str),
+    Search,
+}
+
+impl UsageEvent<'_> {
+    fn key(&self) -> String {
+        match self {
+            UsageEvent::ServiceStart(name) => format!("{}{}", SERVICE_PREFIX, name),
+            UsageEvent::DashboardOpen(id) => format!("{}{}", DASHBOARD_PREFIX, id),
+            UsageEvent::Search => SEARCH_KEY.to_string(),
+        }
+    }
+}
+
+/// Collected counters
+#[derive(Debug, Default, Serialize)]
+pub struct UsageReport {
+    /// Date the first event was recorded
+    pub since: Option<String>,
+    pub service_starts: Vec<(String, u64)>,
+    /// Most opened first
+    pub dashboards: Vec<(String, u64)>,
+    pub searches: u64,
+}
+
+impl std::fmt::Display for UsageReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "📈 Статистика использования (только локально)")?;
+        writeln!(f, "   С: {}", self.since.as_deref().unwrap_or("—"))?;
+        writeln!(f, "   Запуски сервисов:")?;
+        for (name, count) in &self.service_starts {
+            writeln!(f, "     {:<12} {}", name, count)?;
+        }
+        writeln!(f, "   Открытия дашбордов:")?;
+        for (name, count) in &self.dashboards {
+            writeln!(f, "     {:<24} {}", name, count)?;
+        }
+        write!(f, "   Поисковых запросов: {}", self.searches)
+    }
+}
+
+/// Usage counters database
+pub struct UsageStats {
+    db: sled::Db,
+}
+
+impl UsageStats {
+    pub fn open(root: &Path) -> Result<Self> {
+        Ok(Self { db: sled_journal::shared(&root.join(USAGE_DB))? })
+    }
+
+    /// Increment the counter for `event`
+    pub fn increment(&self, event: &UsageEvent) -> Result<u64> {
+        self.increment_key(&event.key())
+    }
+
+    fn increment_key(&self, key: &str) -> Result<u64> {
+        if !self.db.contains_key(SINCE_KEY)? {
+            let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+            self.db.insert(SINCE_KEY, today.as_bytes())?;
+        }
+        let value = self.db.update_and_fetch(key, |old| {
+            let count = old.map_or(0, decode) + 1;
+            Some(count.to_be_bytes().to_vec())
+        })?;
+        self.db.flush()?;
+        Ok(value.map_or(0, |v| decode(&v)))
+    }
+
+    /// All counters
+    pub fn report(&self) -> Result<UsageReport> {
+        let mut report = UsageReport {
+            since: self
+                .db
+                .get(SINCE_KEY)?
+                .map(|v| String::from_utf8_lossy(&v).to_string()),
+            ..Default::default()
+        };
+
+        for item in self.db.iter() {
+            let (key, value) = item?;
+            if key.as_ref() == SINCE_KEY {
+                continue;
+            }
+            let key = String::from_utf8_lossy(&key).to_string();
+            let count = decode(&value);
+            if let Some(name) = key.strip_prefix(SERVICE_PREFIX) {
+                report.service_starts.push((name.to_string(), count));
+            } else if let Some(id) = key.strip_prefix(DASHBOARD_PREFIX) {
+                report.dashboards.push((id.to_string(), count));
+            } else if key == SEARCH_KEY {
+                report.searches = count;
+            }
+        }
+        report.dashboards.sort_by(|a, b| b.1.cmp(&a.1));
+        Ok(report)
+    }
+
+    /// Forget all counters
+    pub fn reset(&self) -> Result<()> {
+        self.db.clear()?;
+        self.db.flush()?;
+        Ok(())
+    }
+}
+
+fn decode(bytes: &[u8]) -> u64 {
+    bytes.try_into().map(u64::from_be_bytes).unwrap_or(0)
+}
+
+/// Best-effort: count `event`, never failing the caller; held while the
+/// drive watchdog has paused writes
+pub fn record(root: &Path, event: UsageEvent) {
+    let root = root.to_path_buf();
+    let key = event.key();
+    drive_watchdog::defer_write(move || {
+        if let Err(e) = UsageStats::open(&root).and_then(|stats| stats.increment_key(&key)) {
+            debug!("Usage event {} not recorded: {}", key, e);
+            sled_journal::release(&root.join(USAGE_DB));
+        }
+    });
+}
+
+/// Dashboard slug/id from a `/superset/dashboard/<id>/` page path
+pub fn dashboard_from_path(path: &str) -> Option<&str> {
+    let id = path.strip_prefix("/superset/dashboard/")?.trim_end_matches('/');
+    (!id.is_empty() && !id.contains('/')).then_some(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_counters() {
+        let dir = tempdir().unwrap();
+        let stats = UsageStats::open(dir.path()).unwrap();
+        stats.increment(&UsageEvent::ServiceStart("superset")).unwrap();
+        stats.increment(&UsageEvent::DashboardOpen("rzd_analytics")).unwrap();
+        stats.increment(&UsageEvent::DashboardOpen("rzd_analytics")).unwrap();
+        stats.increment(&UsageEvent::DashboardOpen("7")).unwrap();
+        assert_eq!(stats.increment(&UsageEvent::Search).unwrap(), 1);
+
+        let report = stats.report().unwrap();
+        assert!(report.since.is_some());
+        assert_eq!(report.service_starts, vec![("superset".to_string(), 1)]);
+        assert_eq!(report.dashboards[0], ("rzd_analytics".to_string(), 2));
+        assert_eq!(report.searches, 1);
+
+        stats.reset().unwrap();
+        assert_eq!(stats.report().unwrap().searches, 0);
+    }
+
+    #[test]
+    fn test_dashboard_from_path() {
+        assert_eq!(dashboard_from_path("/superset/dashboard/rzd_analytics/"), Some("rzd_analytics"));
+        assert_eq!(dashboard_from_path("/superset/dashboard/12"), Some("12"));
+        assert_eq!(dashboard_from_path("/superset/dashboard/"), None);
+        assert_eq!(dashboard_from_path("/api/v1/dashboard/12"), None);
+    }
+}
@@ -0,0 +1,517 @@
+//! Data validation rules for loaded tables
+//!
+//! `validations.yaml` in the kit root lists rules per table: columns that
+//! must not be empty, numeric ranges, regex patterns and references to a
+//! column of another table. The rules run after every load (`load-data` and
+//! launcher uploads). A table that fails a rule with `on_failure: block`
+//! (the default) stays in the SQLite file, but its Superset dataset, column
+//! statistics and read replica are not updated and `dashboard auto` refuses
+//! it; `on_failure: warn` only reports. Each result, with a few offending
+//! rows, is kept in `_table_validations` inside the same database for the
+//! launcher UI's data panel.
+//!
+//! ```yaml
+//! trips:
+//!   not_null: [trip_date, region]
+//!   ranges:
+//!     passengers: { min: 0, max: 5000 }
+//!   patterns:
+//!     ticket: '^T\d+$'
+//!   references:
+//!     region: regions.name
+//!   on_failure: warn
+//! ```
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use regex::Regex;
+use rusqlite::types::Value;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::{error, info, warn};
+
+use crate::data_loader::quote_ident;
+use crate::partitions;
+
+/// Rules file in the kit root
+pub const RULES_FILE: &str = "validations.yaml";
+
+/// Bookkeeping table inside each data database
+pub const RESULTS_TABLE: &str = "_table_validations";
+
+/// Offending rows kept per failed rule
+const SAMPLE_ROWS: usize = 5;
+
+/// What a failed rule does to the load
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnFailure {
+    /// Skip the Superset side of the load
+    #[default]
+    Block,
+    /// Report only
+    Warn,
+}
+
+/// Allowed range of a numeric column (either bound may be left out)
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Range {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// Rules of one table
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TableRules {
+    pub on_failure: OnFailure,
+    /// Columns without NULL or blank values
+    pub not_null: Vec<String>,
+    /// Column -> numeric range
+    pub ranges: BTreeMap<String, Range>,
+    /// Column -> regex every non-empty value must match
+    pub patterns: BTreeMap<String, String>,
+    /// Column -> `table.column` its non-empty values must occur in
+    pub references: BTreeMap<String, String>,
+}
+
+/// Rules of `validations.yaml` by table; empty when the file is missing
+pub fn load_rules(root: &Path) -> Result<BTreeMap<String, TableRules>> {
+    let path = root.join(RULES_FILE);
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let text = std::fs::read_to_string(&path)?;
+    if text.trim().is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    serde_yaml::from_str(&text).with_context(|| format!("Invalid {}", path.display()))
+}
+
+/// One rule that didn't hold
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleFailure {
+    /// `not_null`, `range`, `pattern` or `reference`
+    pub rule: String,
+    pub column: String,
+    /// Range, regex or referenced column, as written in the rules
+    pub expected: String,
+    /// Offending rows
+    pub rows: u64,
+    /// Set when the rule couldn't be checked (missing column, bad regex)
+    pub error: Option<String>,
+    /// First offending rows, column -> value
+    pub samples: Vec<serde_json::Map<String, serde_json::Value>>,
+}
+
+/// Outcome of the rules of one table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableValidation {
+    pub table: String,
+    pub checked_at: DateTime<Local>,
+    pub on_failure: OnFailure,
+    /// Rules checked
+    pub rules: usize,
+    pub failures: Vec<RuleFailure>,
+}
+
+impl TableValidation {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Failed with `on_failure: block`
+    pub fn blocks(&self) -> bool {
+        !self.passed() && self.on_failure == OnFailure::Block
+    }
+
+    /// One line per failed rule
+    pub fn summary(&self) -> String {
+        let lines: Vec<String> = self
+            .failures
+            .iter()
+            .map(|f| match &f.error {
+                Some(e) => format!("{} {}: {}", f.rule, f.column, e),
+                None => format!("{} {} ({}): {} row(s)", f.rule, f.column, f.expected, f.rows),
+            })
+            .collect();
+        format!("Table '{}' failed validation: {}", self.table, lines.join("; "))
+    }
+}
+
+/// Check `table` against `rules`
+pub fn validate(conn: &Connection, table: &str, rules: &TableRules) -> Result<TableValidation> {
+    let columns: Vec<String> = conn
+        .prepare(&format!("PRAGMA table_info({})", quote_ident(table)))?
+        .query_map([], |r| r.get::<_, String>(1))?
+        .collect::<rusqlite::Result<_>>()?;
+    let mut failures = Vec::new();
+    let mut checked = 0;
+    let mut check = |rule: &str, column: &str, expected: String, outcome: Result<Option<Offending>, String>| {
+        checked += 1;
+        let failure = |rows: u64, error: Option<String>, samples| RuleFailure {
+            rule: rule.to_string(),
+            column: column.to_string(),
+            expected: expected.clone(),
+            rows,
+            error,
+            samples,
+        };
+        let outcome = if columns.iter().any(|c| c == column) {
+            outcome
+        } else {
+            Err("no such column".to_string())
+        };
+        match outcome {
+            Ok(None) => {}
+            Ok(Some(offending)) => failures.push(failure(offending.rows, None, offending.samples)),
+            Err(e) => failures.push(failure(0, Some(e), Vec::new())),
+        }
+    };
+
+    for column in &rules.not_null {
+        let condition = format!("{0} IS NULL OR TRIM({0}) = ''", quote_ident(column));
+        check("not_null", column, "NOT NULL".to_string(), matching_rows(conn, table, &condition));
+    }
+    for (column, range) in &rules.ranges {
+        let expected = format!(
+            "{}..{}",
+            range.min.map(|v| v.to_string()).unwrap_or_default(),
+            range.max.map(|v| v.to_string()).unwrap_or_default()
+        );
+        // Anything that isn't a number is out of range too
+        let outcome = scan(conn, table, column, |value| match number(value) {
+            Some(v) => range.min.is_some_and(|min| v < min) || range.max.is_some_and(|max| v > max),
+            None => true,
+        });
+        check("range", column, expected, outcome);
+    }
+    for (column, pattern) in &rules.patterns {
+        let outcome = match Regex::new(pattern) {
+            Ok(regex) => scan(conn, table, column, |value| !regex.is_match(&text(value))),
+            Err(e) => Err(e.to_string()),
+        };
+        check("pattern", column, pattern.clone(), outcome);
+    }
+    for (column, target) in &rules.references {
+        let outcome = match target.split_once('.') {
+            Some((ref_table, ref_column)) => {
+                let condition = format!(
+                    "{0} IS NOT NULL AND TRIM({0}) != '' AND {0} NOT IN (SELECT {2} FROM {1} WHERE {2} IS NOT NULL)",
+                    quote_ident(column),
+                    quote_ident(ref_table),
+                    quote_ident(ref_column)
+                );
+                matching_rows(conn, table, &condition)
+            }
+            None => Err("expected table.column".to_string()),
+        };
+        check("reference", column, target.clone(), outcome);
+    }
+
+    Ok(TableValidation {
+        table: table.to_string(),
+        checked_at: Local::now(),
+        on_failure: rules.on_failure,
+        rules: checked,
+        failures,
+    })
+}
+
+/// Rows that broke a rule
+#[derive(Debug, Default)]
+struct Offending {
+    rows: u64,
+    /// The first [`SAMPLE_ROWS`], column -> value
+    samples: Vec<serde_json::Map<String, serde_json::Value>>,
+}
+
+impl Offending {
+    /// Count `rows` more and sample them with the query `sample_sql(limit)`
+    /// until there are enough samples
+    fn add(&mut self, conn: &Connection, rows: u64, sample_sql: impl FnOnce(usize) -> String) {
+        self.rows += rows;
+        let wanted = SAMPLE_ROWS.saturating_sub(self.samples.len()).min(rows as usize);
+        if wanted > 0 {
+            self.samples.extend(sample_rows(conn, &sample_sql(wanted)).unwrap_or_default());
+        }
+    }
+
+    fn found(self) -> Option<Self> {
+        (self.rows > 0).then_some(self)
+    }
+}
+
+/// Tables holding the rows of `table`: the shards of a partitioned one, whose
+/// `UNION ALL` view has no rowid
+fn sources(conn: &Connection, table: &str) -> Result<Vec<String>, String> {
+    if partitions::is_partitioned(conn, table).map_err(|e| e.to_string())? {
+        partitions::shards(conn, table).map_err(|e| e.to_string())
+    } else {
+        Ok(vec![table.to_string()])
+    }
+}
+
+/// Rows matching `condition`; `None` when there are none
+fn matching_rows(conn: &Connection, table: &str, condition: &str) -> Result<Option<Offending>, String> {
+    let mut offending = Offending::default();
+    for source in sources(conn, table)? {
+        let source = quote_ident(&source);
+        let rows: u64 = conn
+            .query_row(&format!("SELECT COUNT(*) FROM {} WHERE {}", source, condition), [], |r| r.get(0))
+            .map_err(|e| e.to_string())?;
+        offending.add(conn, rows, |limit| format!("SELECT * FROM {} WHERE {} LIMIT {}", source, condition, limit));
+    }
+    Ok(offending.found())
+}
+
+/// Rows whose non-empty `column` value fails `fails`
+fn scan(conn: &Connection, table: &str, column: &str, fails: impl Fn(&Value) -> bool) -> Result<Option<Offending>, String> {
+    let mut offending = Offending::default();
+    for source in sources(conn, table)? {
+        let sql = format!(
+            "SELECT rowid, {0} FROM {1} WHERE {0} IS NOT NULL AND TRIM({0}) != ''",
+            quote_ident(column),
+            quote_ident(&source)
+        );
+        let (mut failed, mut rowids) = (0, Vec::new());
+        let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+        let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+        while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+            let value: Value = row.get(1).map_err(|e| e.to_string())?;
+            if fails(&value) {
+                failed += 1;
+                if rowids.len() < SAMPLE_ROWS {
+                    rowids.push(row.get::<_, i64>(0).map_err(|e| e.to_string())?.to_string());
+                }
+            }
+        }
+        offending.add(conn, failed, |limit| {
+            format!("SELECT * FROM {} WHERE rowid IN ({})", quote_ident(&source), rowids[..limit].join(", "))
+        });
+    }
+    Ok(offending.found())
+}
+
+/// Numeric value of a cell; text loaded from Excel may use a decimal comma
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(v) => Some(*v as f64),
+        Value::Real(v) => Some(*v),
+        Value::Text(v) => v.trim().replace(',', ".").parse().ok(),
+        _ => None,
+    }
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::Integer(v) => v.to_string(),
+        Value::Real(v) => v.to_string(),
+        Value::Text(v) => v.clone(),
+        _ => String::new(),
+    }
+}
+
+/// Rows of the query `sql` as column -> value maps
+fn sample_rows(conn: &Connection, sql: &str) -> Result<Vec<serde_json::Map<String, serde_json::Value>>> {
+    let mut stmt = conn.prepare(sql)?;
+    let names: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    let samples = stmt
+        .query_map([], |r| {
+            let mut row = serde_json::Map::new();
+            for (i, name) in names.iter().enumerate() {
+                let value = match r.get::<_, Value>(i)? {
+                    Value::Integer(v) => serde_json::json!(v),
+                    Value::Real(v) => serde_json::json!(v),
+                    Value::Text(v) => serde_json::json!(v),
+                    Value::Blob(v) => serde_json::json!(format!("<{} bytes>", v.len())),
+                    Value::Null => serde_json::Value::Null,
+                };
+                row.insert(name.clone(), value);
+            }
+            Ok(row)
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(samples)
+}
+
+fn has_results(conn: &Connection) -> Result<bool> {
+    Ok(conn
+        .query_row("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1", [RESULTS_TABLE], |_| Ok(()))
+        .optional()?
+        .is_some())
+}
+
+fn ensure_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(&format!(
+        "CREATE TABLE IF NOT EXISTS \"{}\" (
+            table_name TEXT PRIMARY KEY,
+            checked_at TEXT NOT NULL,
+            blocked INTEGER NOT NULL,
+            result TEXT NOT NULL
+        )",
+        RESULTS_TABLE
+    ))?;
+    Ok(())
+}
+
+/// Keep `validation` as the latest result of its table
+pub fn record(conn: &Connection, validation: &TableValidation) -> Result<()> {
+    ensure_table(conn)?;
+    conn.execute(
+        &format!(
+            "INSERT INTO \"{}\" (table_name, checked_at, blocked, result) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(table_name) DO UPDATE SET checked_at = ?2, blocked = ?3, result = ?4",
+            RESULTS_TABLE
+        ),
+        params![
+            validation.table,
+            validation.checked_at.to_rfc3339(),
+            validation.blocks(),
+            serde_json::to_string(validation)?
+        ],
+    )?;
+    Ok(())
+}
+
+/// Run the rules for `table` just loaded into `db_path`, store and log the
+/// result. `None` when `validations.yaml` has no rules for the table; a
+/// result stored while it had some is dropped then, so it no longer blocks.
+pub fn after_load(root: &Path, db_path: &Path, table: &str) -> Result<Option<TableValidation>> {
+    let rules = load_rules(root)?;
+    let conn = Connection::open(db_path).with_context(|| format!("Failed to open {}", db_path.display()))?;
+    let Some(table_rules) = rules.get(table) else {
+        if has_results(&conn)? {
+            conn.execute(&format!("DELETE FROM \"{}\" WHERE table_name = ?1", RESULTS_TABLE), [table])?;
+        }
+        return Ok(None);
+    };
+    let validation = validate(&conn, table, table_rules)?;
+    record(&conn, &validation)?;
+
+    if validation.passed() {
+        info!("✅ {}: {} validation rule(s) passed", table, validation.rules);
+    }
+    for failure in &validation.failures {
+        let line = match &failure.error {
+            Some(e) => format!("{} {}: {}", failure.rule, failure.column, e),
+            None => format!(
+                "{} {} ({}): {} row(s), e.g. {}",
+                failure.rule,
+                failure.column,
+                failure.expected,
+                failure.rows,
+                serde_json::to_string(&failure.samples.first()).unwrap_or_default()
+            ),
+        };
+        if validation.blocks() {
+            error!("⛔ {}: {}", table, line);
+        } else {
+            warn!("⚠️ {}: {}", table, line);
+        }
+    }
+    Ok(Some(validation))
+}
+
+/// Latest result of every validated table in `db_path`, failures first
+pub fn results(db_path: &Path) -> Result<Vec<TableValidation>> {
+    if !db_path.exists() {
+        return Ok(Vec::new());
+    }
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open {}", db_path.display()))?;
+    if !has_results(&conn)? {
+        return Ok(Vec::new());
+    }
+    let mut results: Vec<TableValidation> = conn
+        .prepare(&format!("SELECT result FROM \"{}\"", RESULTS_TABLE))?
+        .query_map([], |r| r.get::<_, String>(0))?
+        .filter_map(|r| r.ok())
+        .filter_map(|json| serde_json::from_str(&json).ok())
+        .collect();
+    results.sort_by(|a, b| a.passed().cmp(&b.passed()).then_with(|| a.table.cmp(&b.table)));
+    Ok(results)
+}
+
+/// The latest validation of `table` failed with `on_failure: block`
+pub fn is_blocked(db_path: &Path, table: &str) -> Result<bool> {
+    Ok(results(db_path)?.iter().any(|v| v.table == table && v.blocks()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_partitioned_table() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let csv = root.join("trips.csv");
+        std::fs::write(&csv, "trip_date,region\n2023-05-01,Север\n2024-05-01,\n2024-06-01,Юг\n").unwrap();
+        let db_path = root.join("examples.db");
+        let options = crate::data_loader::LoadOptions {
+            partition: Some(partitions::Partitioning { column: "trip_date".into(), unit: partitions::PartitionUnit::Year }),
+            ..Default::default()
+        };
+        crate::data_loader::load_file_with(&csv, "trips", &db_path, &options).unwrap();
+        std::fs::write(root.join(RULES_FILE), "trips:\n  not_null: [region]\n").unwrap();
+
+        let validation = after_load(root, &db_path, "trips").unwrap().unwrap();
+        let failure = &validation.failures[0];
+        assert_eq!(failure.rows, 1);
+        assert_eq!(failure.samples[0]["trip_date"], "2024-05-01");
+    }
+
+    #[test]
+    fn test_rules_and_results() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let db_path = root.join("examples.db");
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE regions (name TEXT); INSERT INTO regions VALUES ('Север'), ('Юг');
+             CREATE TABLE trips (id INTEGER PRIMARY KEY, region TEXT, ticket TEXT, passengers TEXT);
+             INSERT INTO trips (region, ticket, passengers) VALUES
+                ('Север', 'T1', '10'), ('Юг', 'T2', '12,5'), ('Запад', 'X3', '-4'), ('', 'T4', 'много');",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join(RULES_FILE),
+            "trips:\n  not_null: [region, missing]\n  ranges:\n    passengers: { min: 0 }\n  patterns:\n    ticket: '^T\\d+$'\n  references:\n    region: regions.name\n",
+        )
+        .unwrap();
+
+        let validation = after_load(root, &db_path, "trips").unwrap().unwrap();
+        assert_eq!(validation.rules, 5);
+        assert!(validation.blocks());
+        let rows = |rule: &str, column: &str| {
+            let failure = validation.failures.iter().find(|f| f.rule == rule && f.column == column).unwrap();
+            (failure.rows, failure.error.is_some())
+        };
+        assert_eq!(rows("not_null", "region"), (1, false));
+        assert_eq!(rows("not_null", "missing"), (0, true));
+        assert_eq!(rows("range", "passengers"), (2, false), "-4 and a non-number");
+        assert_eq!(rows("pattern", "ticket"), (1, false));
+        assert_eq!(rows("reference", "region"), (1, false), "blank values are not_null's business");
+        let sample = &validation.failures.iter().find(|f| f.rule == "pattern").unwrap().samples[0];
+        assert_eq!(sample["ticket"], "X3");
+
+        assert!(after_load(root, &db_path, "regions").unwrap().is_none());
+        assert!(is_blocked(&db_path, "trips").unwrap());
+
+        // Rules taken out of the file no longer block the table
+        std::fs::write(root.join(RULES_FILE), "regions:\n  not_null: [name]\n").unwrap();
+        assert!(after_load(root, &db_path, "trips").unwrap().is_none());
+        assert!(!is_blocked(&db_path, "trips").unwrap());
+
+        std::fs::write(root.join(RULES_FILE), "trips:\n  on_failure: warn\n  not_null: [ticket]\n").unwrap();
+        let validation = after_load(root, &db_path, "trips").unwrap().unwrap();
+        assert!(validation.passed());
+        assert!(!is_blocked(&db_path, "trips").unwrap());
+        assert_eq!(results(&db_path).unwrap().len(), 1);
+    }
+}
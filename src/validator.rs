@@ -1,19 +1,31 @@
 //! Environment validation module
 //! 
 //! Validates the portable Superset environment before startup.
+//!
+//! Besides Python and the ports, the media itself is checked: a stick with
+//! its write-protect switch on, a read-only mount, a folder without write
+//! permission or a FAT32 volume (files are capped at 4 GB) otherwise show up
+//! only later, as "attempt to write a readonly database" from SQLite or an
+//! I/O error from sled.
 
 use anyhow::Result;
+use std::io;
 use std::net::TcpListener;
 use std::path::Path;
 use tracing::info;
 
 use crate::python::PythonEnv;
 
+/// Folders the launcher and Superset write to at runtime
+const WRITABLE_DIRS: &[&str] = &["superset_home", "cache", "logs"];
+
 /// Validation result for a single check
 #[derive(Debug)]
 pub struct CheckResult {
     pub name: String,
     pub passed: bool,
+    /// A failure that stops Superset (not just a warning)
+    pub critical: bool,
     pub message: String,
 }
 
@@ -22,6 +34,7 @@ impl CheckResult {
         Self {
             name: name.to_string(),
             passed: true,
+            critical: false,
             message: message.to_string(),
         }
     }
@@ -30,9 +43,35 @@ impl CheckResult {
         Self {
             name: name.to_string(),
             passed: false,
+            critical: true,
             message: message.to_string(),
         }
     }
+    
+    fn warn(name: &str, message: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            passed: false,
+            critical: false,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// File system of the volume holding a path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeInfo {
+    /// `NTFS`, `exFAT`, `FAT32`, `vfat`, `ext4`, ...
+    pub fs_type: String,
+    pub read_only: bool,
+}
+
+impl VolumeInfo {
+    /// FAT12/16/32: no file over 4 GB
+    pub fn is_fat(&self) -> bool {
+        let fs = self.fs_type.to_ascii_lowercase();
+        fs.starts_with("fat") || fs == "vfat" || fs == "msdos"
+    }
 }
 
 /// Environment validator
@@ -55,6 +94,11 @@ impl Validator {
         results.push(self.check_superset_installed());
         results.push(self.check_database());
         results.push(self.check_config());
+        results.push(self.check_volume());
+        for dir in WRITABLE_DIRS {
+            results.push(self.check_writable(dir));
+        }
+        results.push(self.check_database_writable());
         results.push(self.check_port(8088, "Superset"));
         results.push(self.check_port(8089, "Docs"));
         
@@ -114,6 +158,50 @@ impl Validator {
         }
     }
     
+    /// Check that the volume is writable and not FAT
+    fn check_volume(&self) -> CheckResult {
+        let Some(volume) = volume_info(&self.root) else {
+            return CheckResult::pass("Носитель", "Тип файловой системы не определён");
+        };
+        if volume.read_only {
+            CheckResult::fail("Носитель", "Только для чтения: снимите защиту от записи или скопируйте комплект на диск")
+        } else if volume.is_fat() {
+            CheckResult::warn(
+                "Носитель",
+                &format!("{}: файлы больше 4 ГБ не поместятся (база, кэш) — отформатируйте в exFAT или NTFS", volume.fs_type),
+            )
+        } else {
+            CheckResult::pass("Носитель", &volume.fs_type)
+        }
+    }
+    
+    /// Check that `rel` (or the nearest existing folder above it) accepts new files
+    fn check_writable(&self, rel: &str) -> CheckResult {
+        let name = format!("Запись в {}", rel);
+        let mut dir = self.root.join(rel);
+        while !dir.is_dir() {
+            match dir.parent() {
+                Some(parent) => dir = parent.to_path_buf(),
+                None => return CheckResult::fail(&name, "Папка не найдена"),
+            }
+        }
+        match probe_write(&dir) {
+            Ok(()) => CheckResult::pass(&name, "Доступна"),
+            Err(e) => CheckResult::fail(&name, &describe_write_error(&e)),
+        }
+    }
+    
+    /// A read-only attribute on superset.db (copied from a CD, or set by hand)
+    fn check_database_writable(&self) -> CheckResult {
+        let db_path = self.root.join("superset_home").join("superset.db");
+        match std::fs::metadata(&db_path) {
+            Ok(meta) if meta.permissions().readonly() => {
+                CheckResult::fail("Запись в superset.db", "У файла атрибут «только чтение» — снимите его в свойствах файла")
+            }
+            _ => CheckResult::pass("Запись в superset.db", "Доступна"),
+        }
+    }
+    
     /// Check if port is available
     fn check_port(&self, port: u16, service: &str) -> CheckResult {
         match TcpListener::bind(format!("127.0.0.1:{}", port)) {
@@ -129,7 +217,7 @@ impl Validator {
                         "Занят (сервис работает)"
                     )
                 } else {
-                    CheckResult::fail(
+                    CheckResult::warn(
                         &format!("Порт {} ({})", port, service),
                         "Занят другим процессом"
                     )
@@ -139,6 +227,85 @@ impl Validator {
     }
 }
 
+/// Create and delete a file in `dir`
+fn probe_write(dir: &Path) -> io::Result<()> {
+    let probe = dir.join(format!(".write-test-{}.tmp", std::process::id()));
+    std::fs::OpenOptions::new().write(true).create_new(true).open(&probe)?;
+    std::fs::remove_file(&probe)
+}
+
+/// EROFS, ERROR_WRITE_PROTECT
+fn describe_write_error(error: &io::Error) -> String {
+    match error.raw_os_error() {
+        Some(30) if cfg!(unix) => "Носитель смонтирован только для чтения".to_string(),
+        Some(19) if cfg!(windows) => "Носитель защищён от записи".to_string(),
+        _ if error.kind() == io::ErrorKind::PermissionDenied => "Нет прав на запись".to_string(),
+        _ => format!("Запись не удалась: {}", error),
+    }
+}
+
+/// File system and read-only flag of the volume holding `path`
+#[cfg(windows)]
+pub fn volume_info(path: &Path) -> Option<VolumeInfo> {
+    use std::os::windows::ffi::OsStrExt;
+    use winapi::um::fileapi::{GetVolumeInformationW, GetVolumePathNameW};
+    use winapi::um::winnt::FILE_READ_ONLY_VOLUME;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    let mut volume = [0u16; 261];
+    let mut fs_name = [0u16; 64];
+    let mut flags = 0u32;
+    // SAFETY: `wide` is NUL-terminated and the buffers' lengths are passed alongside them
+    let ok = unsafe {
+        GetVolumePathNameW(wide.as_ptr(), volume.as_mut_ptr(), volume.len() as u32) != 0
+            && GetVolumeInformationW(
+                volume.as_ptr(),
+                std::ptr::null_mut(),
+                0,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                &mut flags,
+                fs_name.as_mut_ptr(),
+                fs_name.len() as u32,
+            ) != 0
+    };
+    if !ok {
+        return None;
+    }
+    let len = fs_name.iter().position(|&c| c == 0).unwrap_or(fs_name.len());
+    Some(VolumeInfo {
+        fs_type: String::from_utf16_lossy(&fs_name[..len]),
+        read_only: flags & FILE_READ_ONLY_VOLUME != 0,
+    })
+}
+
+/// File system and read-only flag of the volume holding `path`
+#[cfg(not(windows))]
+pub fn volume_info(path: &Path) -> Option<VolumeInfo> {
+    let path = path.canonicalize().ok()?;
+    let mounts = std::fs::read_to_string("/proc/mounts").ok()?;
+    parse_mounts(&mounts, &path)
+}
+
+/// The `/proc/mounts` entry with the longest mount point containing `path`
+#[cfg(not(windows))]
+fn parse_mounts(mounts: &str, path: &Path) -> Option<VolumeInfo> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (_device, mount_point, fs_type, options) = (fields.next()?, fields.next()?, fields.next()?, fields.next()?);
+            // Spaces in mount points are written as \040
+            let mount_point = mount_point.replace("\\040", " ");
+            path.starts_with(&mount_point).then(|| {
+                let read_only = options.split(',').any(|option| option == "ro");
+                (mount_point.len(), VolumeInfo { fs_type: fs_type.to_string(), read_only })
+            })
+        })
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, volume)| volume)
+}
+
 /// Check if a port is responding to HTTP requests
 fn is_port_responding(port: u16) -> bool {
     use std::net::TcpStream;
@@ -166,7 +333,7 @@ pub fn print_validation_report(results: &[CheckResult]) {
             "✅"
         } else {
             failed += 1;
-            "❌"
+            if result.critical { "❌" } else { "⚠️" }
         };
         
         println!("║ {} {:<20} │ {:<30} ║", 
@@ -195,3 +362,42 @@ fn truncate(s: &str, max_len: usize) -> String {
         format!("{}...", s.chars().take(max_len - 3).collect::<String>())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_parse_mounts() {
+        let mounts = "/dev/sda1 / ext4 rw,relatime 0 0\n\
+                      /dev/sdb1 /media/user/KIT\\040USB vfat ro,nosuid,nodev 0 0\n";
+        let stick = parse_mounts(mounts, Path::new("/media/user/KIT USB/superset")).unwrap();
+        assert!(stick.read_only && stick.is_fat());
+        let system = parse_mounts(mounts, Path::new("/home/user")).unwrap();
+        assert_eq!(system, VolumeInfo { fs_type: "ext4".to_string(), read_only: false });
+    }
+
+    #[test]
+    fn test_writable_checks() {
+        let dir = tempdir().unwrap();
+        let home = dir.path().join("superset_home");
+        std::fs::create_dir_all(&home).unwrap();
+        let validator = Validator::new(dir.path());
+
+        assert!(validator.check_writable("superset_home").passed);
+        // Missing folders are judged by the folder they'd be created in
+        assert!(validator.check_writable("cache").passed);
+        assert!(!dir.path().join("cache").exists());
+
+        let db = home.join("superset.db");
+        std::fs::write(&db, b"").unwrap();
+        assert!(validator.check_database_writable().passed);
+        let mut permissions = std::fs::metadata(&db).unwrap().permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(&db, permissions).unwrap();
+        let result = validator.check_database_writable();
+        assert!(!result.passed && result.critical);
+    }
+}
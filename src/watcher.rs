@@ -1,10 +1,16 @@
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
+
+use crate::kpi_charts;
+use crate::metadata;
+use crate::notifications::{self, Level};
+use crate::replica;
+use crate::snapshots;
 
 pub struct DataWatcher {
     root: PathBuf,
@@ -52,7 +58,7 @@ impl DataWatcher {
             // Let's watch both key locations to be safe, or just the one we know works.
             // implementation_plan says "docs/demo_data/".
             
-            let watch_path = root.join("docs").join("demo_data");
+            let watch_path = root.join(metadata::DEMO_DATA_DIR);
             
             if !watch_path.exists() {
                  error!("Watch path does not exist: {:?}", watch_path);
@@ -82,37 +88,35 @@ impl DataWatcher {
                             // Run update logic
                             info!("Triggering dashboard update...");
                             
-                            // We run the binary we just built
-                            // Assuming create_dashboard.exe is in the same dir as superset-launcher.exe (root)
-                            // OR in target/release if dev.
-                            // In portable release, it's in root.
-                            // In dev, we might need to look in target/release.
-                            
-                            let exe_name = if cfg!(windows) { "create_dashboard.exe" } else { "create_dashboard" };
-                            let mut exe_path = root.join(exe_name);
-                            
-                            if !exe_path.exists() {
-                                // Try target/release for dev mode
-                                exe_path = root.join("target").join("release").join(exe_name);
-                            }
-
-                            if exe_path.exists() {
-                                match tokio::process::Command::new(&exe_path)
-                                    .current_dir(&root)
-                                    .output()
-                                    .await 
-                                {
-                                    Ok(output) => {
-                                        if output.status.success() {
-                                            info!("Data updated successfully!");
-                                        } else {
-                                            error!("Data update failed: {}", String::from_utf8_lossy(&output.stderr));
-                                        }
-                                    },
-                                    Err(e) => error!("Failed to execute updater: {}", e),
+                            let rebuild_root = root.clone();
+                            let rebuild = move || -> anyhow::Result<()> {
+                                let config = crate::config::Config::load_or_create(&rebuild_root)?;
+                                let examples_db = rebuild_root.join(crate::databases::EXAMPLES_PATH);
+                                snapshots::take(&rebuild_root, &config.snapshots, &examples_db, "data watcher")?;
+                                metadata::rebuild_demo(&rebuild_root)?;
+                                // The rebuild points the connection at the live file again
+                                if config.replica.enabled {
+                                    let (name, uuid) = (crate::databases::EXAMPLES_NAME, crate::databases::EXAMPLES_UUID);
+                                    replica::refresh(&rebuild_root, name, uuid, &examples_db)?;
+                                }
+                                if let Err(e) = kpi_charts::render(&rebuild_root, &examples_db) {
+                                    warn!("KPI charts not updated: {:#}", e);
+                                }
+                                Ok(())
+                            };
+                            match tokio::task::spawn_blocking(rebuild).await {
+                                Ok(Ok(())) => {
+                                    info!("Data updated successfully!");
+                                    notifications::notify(Level::Info, "Данные обновлены", "Дашборды пересобраны из папки data");
+                                }
+                                Ok(Err(e)) => {
+                                    error!("Data update failed: {:#}", e);
+                                    notifications::notify(Level::Error, "Ошибка обновления данных", &e.to_string());
+                                }
+                                Err(e) => {
+                                    error!("Data update task failed: {}", e);
+                                    notifications::notify(Level::Error, "Ошибка обновления данных", "см. консоль");
                                 }
-                            } else {
-                                error!("Updater binary not found at {:?}", exe_path);
                             }
                         },
                         Err(e) => error!("Watch error: {}", e),